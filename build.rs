@@ -0,0 +1,14 @@
+use std::{env, process::Command};
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(nightly)");
+    println!("cargo:rerun-if-env-changed=RUSTC");
+
+    // The read_buf feature needs unstable std APIs, so only turn it on for compilers that accept them.
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc).arg("--version").output().map(|output| output.stdout).unwrap_or_default();
+    let version = String::from_utf8_lossy(&version);
+    if version.contains("-nightly") || version.contains("-dev") {
+        println!("cargo:rustc-cfg=nightly");
+    }
+}
@@ -0,0 +1,132 @@
+//! `#[derive(SwappedLayout)]` for structs whose fields live at fixed offsets from a base address.
+//!
+//! ```ignore
+//! #[derive(SwappedLayout)]
+//! #[swapped(endian = "big")]
+//! struct Player {
+//!     #[swapped(offset = 0x24)]
+//!     health: u32,
+//!     #[swapped(offset = 0x28, endian = "little")]
+//!     score: u16,
+//! }
+//! ```
+//!
+//! generates `Player::health(&mut words, base)` / `Player::set_health(&mut words, base, value)`
+//! for every field, and implements `SwappedLayout::load` / `SwappedLayout::store` for the whole struct.
+//! The struct level endianness defaults to the view's, see `ReversedWords::with_endian`. A field whose
+//! address would overflow `u64` fails with `ReversedWordsError::AddressOutOfRange`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, LitInt, LitStr};
+
+#[proc_macro_derive(SwappedLayout, attributes(swapped))]
+pub fn derive_swapped_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct SwappedAttr {
+    offset: Option<u64>,
+    endian: Option<TokenStream2>,
+}
+
+fn parse_endian(lit: &LitStr) -> syn::Result<TokenStream2> {
+    match lit.value().as_str() {
+        "big" => Ok(quote!(::reversed_word_byte_rw::Endian::Big)),
+        "little" => Ok(quote!(::reversed_word_byte_rw::Endian::Little)),
+        "native" => Ok(quote!(::reversed_word_byte_rw::Endian::Native)),
+        _ => Err(syn::Error::new(lit.span(), "expected \"big\", \"little\" or \"native\"")),
+    }
+}
+
+fn parse_attrs(attrs: &[Attribute]) -> syn::Result<SwappedAttr> {
+    let mut parsed = SwappedAttr::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("swapped")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("offset") {
+                let lit: LitInt = meta.value()?.parse()?;
+                parsed.offset = Some(lit.base10_parse()?);
+                Ok(())
+            } else if meta.path.is_ident("endian") {
+                let lit: LitStr = meta.value()?.parse()?;
+                parsed.endian = Some(parse_endian(&lit)?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `offset` or `endian`"))
+            }
+        })?;
+    }
+    Ok(parsed)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new(Span::call_site(), "SwappedLayout requires named fields")),
+        },
+        _ => return Err(syn::Error::new(Span::call_site(), "SwappedLayout can only be derived for structs")),
+    };
+    let default_endian = parse_attrs(&input.attrs)?.endian.unwrap_or_else(|| quote!(words.endian()));
+
+    let mut accessors = Vec::new();
+    let mut loads = Vec::new();
+    let mut stores = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+        let attr = parse_attrs(&field.attrs)?;
+        let offset = attr
+            .offset
+            .ok_or_else(|| syn::Error::new_spanned(ident, "missing #[swapped(offset = ...)]"))?;
+        let endian = attr.endian.unwrap_or_else(|| default_endian.clone());
+        let setter = format_ident!("set_{}", ident);
+        let addr = quote! {
+            base.checked_add(#offset).ok_or_else(|| ::reversed_word_byte_rw::ReversedWordsError::AddressOutOfRange {
+                addr: u64::MAX,
+                len: words.len(),
+            })?
+        };
+
+        accessors.push(quote! {
+            pub fn #ident(words: &mut ::reversed_word_byte_rw::ReversedWords<'_>, base: u64) -> ::std::io::Result<#ty> {
+                let addr = #addr;
+                words.read_value_at::<#ty>(addr, #endian)
+            }
+
+            pub fn #setter(words: &mut ::reversed_word_byte_rw::ReversedWords<'_>, base: u64, value: #ty) -> ::std::io::Result<()> {
+                let addr = #addr;
+                words.write_value_at::<#ty>(addr, value, #endian)
+            }
+        });
+        loads.push(quote!(#ident: Self::#ident(words, base)?));
+        stores.push(quote!(Self::#setter(words, base, self.#ident)?;));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#accessors)*
+        }
+
+        impl #impl_generics ::reversed_word_byte_rw::SwappedLayout for #name #ty_generics #where_clause {
+            fn load(words: &mut ::reversed_word_byte_rw::ReversedWords<'_>, base: u64) -> ::std::io::Result<Self> {
+                Ok(#name {
+                    #(#loads,)*
+                })
+            }
+
+            fn store(&self, words: &mut ::reversed_word_byte_rw::ReversedWords<'_>, base: u64) -> ::std::io::Result<()> {
+                #(#stores)*
+                Ok(())
+            }
+        }
+    })
+}
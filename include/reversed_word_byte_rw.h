@@ -0,0 +1,56 @@
+/* C interface to reversed-word-byte-rw, built with `cargo build --features ffi`. */
+#ifndef REVERSED_WORD_BYTE_RW_H
+#define REVERSED_WORD_BYTE_RW_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A swapped view over a caller-provided buffer. The buffer must outlive the view. */
+typedef struct RwView RwView;
+
+/* Returns NULL if buf is NULL or word_size is 0. */
+RwView *rw_view_new(uint8_t *buf, size_t len, uint8_t word_size);
+void rw_view_free(RwView *view);
+uint64_t rw_view_len(const RwView *view);
+
+/* Return the number of bytes copied, which is less than len at the end of the buffer.
+ * Copy nothing and return 0 if out/src is NULL or len is 0. */
+size_t rw_view_read_at(const RwView *view, uint64_t addr, uint8_t *out, size_t len);
+size_t rw_view_write_at(RwView *view, uint64_t addr, const uint8_t *src, size_t len);
+
+/* The following return 0 on success and -1 on failure. Ranges are [start, end). */
+int rw_view_fill(RwView *view, uint64_t start, uint64_t end, uint8_t byte);
+int rw_view_copy_within(RwView *view, uint64_t start, uint64_t end, uint64_t dst);
+
+int rw_view_read_u16(const RwView *view, uint64_t addr, int big_endian, uint16_t *out);
+int rw_view_write_u16(RwView *view, uint64_t addr, int big_endian, uint16_t value);
+int rw_view_read_u32(const RwView *view, uint64_t addr, int big_endian, uint32_t *out);
+int rw_view_write_u32(RwView *view, uint64_t addr, int big_endian, uint32_t value);
+int rw_view_read_u64(const RwView *view, uint64_t addr, int big_endian, uint64_t *out);
+int rw_view_write_u64(RwView *view, uint64_t addr, int big_endian, uint64_t value);
+int rw_view_read_f32(const RwView *view, uint64_t addr, int big_endian, float *out);
+int rw_view_write_f32(RwView *view, uint64_t addr, int big_endian, float value);
+
+/* Writes up to max matching addresses to out and returns the total number of matches, so a call
+   with max 0 sizes out. Overlapping matches are included. */
+size_t rw_view_find_all(const RwView *view, const uint8_t *needle, size_t needle_len, uint64_t *out, size_t max);
+
+/* A ring of up to capacity snapshots of a view's buffer. */
+typedef struct RwSnapshots RwSnapshots;
+
+RwSnapshots *rw_snapshots_new(size_t capacity);
+void rw_snapshots_free(RwSnapshots *snapshots);
+size_t rw_snapshots_len(const RwSnapshots *snapshots);
+void rw_snapshots_capture(RwSnapshots *snapshots, const RwView *view);
+/* Restores the snapshot back captures before the newest (0 is the newest). Returns 0 or -1. */
+int rw_snapshots_rewind(RwSnapshots *snapshots, size_t back, RwView *view);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
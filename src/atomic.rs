@@ -0,0 +1,441 @@
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    sync::atomic::{fence, AtomicU64, AtomicU8, Ordering},
+};
+use binread::Endian;
+
+use crate::{storage_index, transform_byte, AddressMode, Primitive, ReversedVec, ReversedWords, ReversedWordsError};
+
+/// Positioned access to reversed words stored as `AtomicU8`, for sharing one buffer between threads.
+///
+/// Every method takes `&self`, so any number of threads can read while others write.
+/// Each byte is loaded and stored individually with `Ordering::Relaxed`: a single byte is never
+/// torn, but a multi-byte read racing a write may observe a mix of old and new bytes, and there
+/// is no ordering between accesses to different addresses. Synchronize externally if a
+/// consistent view of more than one byte is needed.
+#[derive(Clone, Copy)]
+pub struct AtomicReversedWords<'a> {
+    ram: &'a [AtomicU8],
+    word_size: u8,
+    nibble_swap: bool,
+    bit_reversal: bool,
+    address_mode: AddressMode,
+}
+
+impl<'a> AtomicReversedWords<'a> {
+    pub fn new(ram: &'a [AtomicU8]) -> AtomicReversedWords<'a> {
+        AtomicReversedWords::new_with_word_size(ram, 4)
+    }
+
+    /// Panics if `word_size` is 0, see [`AtomicReversedWords::try_new_with_word_size`].
+    pub fn new_with_word_size(ram: &'a [AtomicU8], word_size: u8) -> AtomicReversedWords<'a> {
+        match AtomicReversedWords::try_new_with_word_size(ram, word_size) {
+            Ok(words) => words,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_new_with_word_size(ram: &'a [AtomicU8], word_size: u8) -> Result<AtomicReversedWords<'a>, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        Ok(AtomicReversedWords { ram, word_size, nibble_swap: false, bit_reversal: false, address_mode: AddressMode::default() })
+    }
+
+    /// Also swap the nibbles of every byte, see [`ReversedWords::with_nibble_swap`].
+    pub fn with_nibble_swap(mut self, nibble_swap: bool) -> AtomicReversedWords<'a> {
+        self.nibble_swap = nibble_swap;
+        self
+    }
+
+    /// Also reverse the bits of every byte, see [`ReversedWords::with_bit_reversal`].
+    pub fn with_bit_reversal(mut self, bit_reversal: bool) -> AtomicReversedWords<'a> {
+        self.bit_reversal = bit_reversal;
+        self
+    }
+
+    /// How `read_at` and the other addressed accessors treat addresses past the end. The halves
+    /// from [`AtomicReversedWords::split`] stream like `Read`/`Write` and stop at the end either way.
+    pub fn with_address_mode(mut self, mode: AddressMode) -> AtomicReversedWords<'a> {
+        self.address_mode = mode;
+        self
+    }
+
+    /// View an exclusively borrowed byte slice as atomics for the duration of the borrow.
+    ///
+    /// Panics if `word_size` is 0, see [`AtomicReversedWords::try_from_mut_slice`].
+    pub fn from_mut_slice(ram: &'a mut [u8], word_size: u8) -> AtomicReversedWords<'a> {
+        // SAFETY: AtomicU8 has the same size and alignment as u8, and the exclusive borrow
+        // guarantees nothing else accesses the bytes non-atomically while the view exists.
+        let atomics = unsafe { &*(ram as *mut [u8] as *const [AtomicU8]) };
+        AtomicReversedWords::new_with_word_size(atomics, word_size)
+    }
+
+    pub fn try_from_mut_slice(ram: &'a mut [u8], word_size: u8) -> Result<AtomicReversedWords<'a>, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        Ok(AtomicReversedWords::from_mut_slice(ram, word_size))
+    }
+
+    pub fn len(&self) -> u64 {
+        self.ram.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ram.is_empty()
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    pub fn nibble_swap(&self) -> bool {
+        self.nibble_swap
+    }
+
+    pub fn bit_reversal(&self) -> bool {
+        self.bit_reversal
+    }
+
+    pub fn address_mode(&self) -> AddressMode {
+        self.address_mode
+    }
+
+    // like ReversedWords::offset_address, None if a bounded address overflows
+    fn offset_address(&self, addr: u64, offset: u64) -> Option<u64> {
+        match self.address_mode {
+            AddressMode::Wrapping if !self.is_empty() => Some((addr % self.len() + offset) % self.len()),
+            _ => addr.checked_add(offset),
+        }
+    }
+
+    /// Read the logical bytes starting at `addr`, returning how many were in range.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> usize {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            match self.offset_address(addr, i as u64).and_then(|position| storage_index(position, self.word_size, self.len())) {
+                Some(index) => *byte = transform_byte(self.ram[index].load(Ordering::Relaxed), (self.nibble_swap, self.bit_reversal)),
+                None => return i,
+            }
+        }
+        buf.len()
+    }
+
+    /// Write logical bytes starting at `addr`, returning how many were in range.
+    pub fn write_at(&self, addr: u64, buf: &[u8]) -> usize {
+        for (i, byte) in buf.iter().enumerate() {
+            match self.offset_address(addr, i as u64).and_then(|position| storage_index(position, self.word_size, self.len())) {
+                Some(index) => self.ram[index].store(transform_byte(*byte, (self.nibble_swap, self.bit_reversal)), Ordering::Relaxed),
+                None => return i,
+            }
+        }
+        buf.len()
+    }
+
+    pub fn read_value_at<T: Primitive>(&self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        if self.read_at(addr, bytes.as_mut()) < bytes.as_ref().len() {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    pub fn write_value_at<T: Primitive>(&self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        let bytes = value.to_bytes(endian);
+        if self.write_at(addr, bytes.as_ref()) < bytes.as_ref().len() {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        Ok(())
+    }
+
+    /// A reader and a writer over the whole view, each with its own position starting at 0, so
+    /// one thread can stream data in while another reads elsewhere.
+    pub fn split(&self) -> (ReadHalf<'a>, WriteHalf<'a>) {
+        (ReadHalf { ram: *self, position: 0 }, WriteHalf { ram: *self, position: 0 })
+    }
+}
+
+impl<'a> ReversedWords<'a> {
+    /// [`AtomicReversedWords::split`] over this view's data, for as long as the view is borrowed.
+    /// The word size, nibble swap, bit reversal and address mode carry over.
+    pub fn split(&mut self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        let (word_size, (nibble_swap, bit_reversal), address_mode) = (self.word_size, self.byte_transform(), self.address_mode);
+        AtomicReversedWords::from_mut_slice(self.cursor.get_mut(), word_size)
+            .with_nibble_swap(nibble_swap)
+            .with_bit_reversal(bit_reversal)
+            .with_address_mode(address_mode)
+            .split()
+    }
+}
+
+/// [`AtomicReversedWords`] whose writers coordinate through a sequence lock, so readers can take
+/// internally consistent copies while writes keep going.
+///
+/// Writes go one at a time, each bumping the sequence number to odd before it starts and back to
+/// even when it is done. A consistent read copies the bytes and retries if the sequence changed
+/// meanwhile, so it never blocks writers and never sees half of a write. Writes made directly
+/// through the inner [`AtomicReversedWords`] bypass this and can still tear.
+pub struct SeqLockWords<'a> {
+    ram: AtomicReversedWords<'a>,
+    sequence: AtomicU64,
+}
+
+impl<'a> SeqLockWords<'a> {
+    pub fn new(ram: AtomicReversedWords<'a>) -> SeqLockWords<'a> {
+        SeqLockWords { ram, sequence: AtomicU64::new(0) }
+    }
+
+    pub fn words(&self) -> AtomicReversedWords<'a> {
+        self.ram
+    }
+
+    /// Number of writes completed so far.
+    pub fn writes(&self) -> u64 {
+        self.sequence.load(Ordering::Acquire) / 2
+    }
+
+    fn write_locked<T>(&self, write: impl FnOnce(&AtomicReversedWords<'a>) -> T) -> T {
+        let mut current = self.sequence.load(Ordering::Relaxed);
+        loop {
+            if current % 2 == 1 {
+                std::hint::spin_loop();
+                current = self.sequence.load(Ordering::Relaxed);
+                continue;
+            }
+            match self.sequence.compare_exchange_weak(current, current + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+        // keep the byte stores after the odd sequence number becomes visible
+        fence(Ordering::Release);
+        let result = write(&self.ram);
+        self.sequence.store(current + 2, Ordering::Release);
+        result
+    }
+
+    /// [`AtomicReversedWords::write_at`] as one consistent write.
+    pub fn write_at(&self, addr: u64, buf: &[u8]) -> usize {
+        self.write_locked(|ram| ram.write_at(addr, buf))
+    }
+
+    pub fn write_value_at<T: Primitive>(&self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        self.write_locked(|ram| ram.write_value_at(addr, value, endian))
+    }
+
+    /// Run `read` until it completes without a write overlapping it, giving up after `attempts`.
+    fn read_consistent<T>(&self, attempts: usize, mut read: impl FnMut(&AtomicReversedWords<'a>) -> T) -> Option<T> {
+        for _ in 0..attempts {
+            let before = self.sequence.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let result = read(&self.ram);
+            fence(Ordering::Acquire);
+            if self.sequence.load(Ordering::Relaxed) == before {
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    /// [`AtomicReversedWords::read_at`], seeing every write either completely or not at all.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> usize {
+        self.read_consistent(usize::MAX, |ram| ram.read_at(addr, buf)).expect("retries until consistent")
+    }
+
+    pub fn read_value_at<T: Primitive>(&self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        self.read_consistent(usize::MAX, |ram| ram.read_value_at(addr, endian)).expect("retries until consistent")
+    }
+
+    /// A consistent copy of the whole buffer, retrying for as long as writes interrupt it.
+    pub fn snapshot(&self) -> ReversedVec {
+        self.try_snapshot(usize::MAX).expect("retries until consistent")
+    }
+
+    /// Like [`SeqLockWords::snapshot`], but give up after `attempts` tries, for writers that
+    /// never pause.
+    pub fn try_snapshot(&self, attempts: usize) -> Option<ReversedVec> {
+        let data = self.read_consistent(attempts, |ram| ram.ram.iter().map(|byte| byte.load(Ordering::Relaxed)).collect())?;
+        Some(ReversedVec::try_new_with_word_size(data, self.ram.word_size).expect("word size is never 0"))
+    }
+}
+
+pub(crate) fn seek_target(pos: SeekFrom, position: u64, len: u64) -> std::io::Result<u64> {
+    let target = match pos {
+        SeekFrom::Start(offset) => offset as i128,
+        SeekFrom::End(offset) => len as i128 + offset as i128,
+        SeekFrom::Current(offset) => position as i128 + offset as i128,
+    };
+    if target < 0 || target > u64::MAX as i128 {
+        return Err(ReversedWordsError::SeekOutOfRange { target, len }.into());
+    }
+    Ok(target as u64)
+}
+
+/// The reading half of [`AtomicReversedWords::split`].
+pub struct ReadHalf<'a> {
+    ram: AtomicReversedWords<'a>,
+    position: u64,
+}
+
+/// The writing half of [`AtomicReversedWords::split`].
+pub struct WriteHalf<'a> {
+    ram: AtomicReversedWords<'a>,
+    position: u64,
+}
+
+impl<'a> ReadHalf<'a> {
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn words(&self) -> AtomicReversedWords<'a> {
+        self.ram
+    }
+}
+
+impl<'a> WriteHalf<'a> {
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn words(&self) -> AtomicReversedWords<'a> {
+        self.ram
+    }
+}
+
+impl Read for ReadHalf<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.ram.with_address_mode(AddressMode::Bounded).read_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for ReadHalf<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = seek_target(pos, self.position, self.ram.len())?;
+        Ok(self.position)
+    }
+}
+
+impl Write for WriteHalf<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.ram.with_address_mode(AddressMode::Bounded).write_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for WriteHalf<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = seek_target(pos, self.position, self.ram.len())?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn read_and_write_through_atomics() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let ram = AtomicReversedWords::from_mut_slice(&mut data, 4);
+        let mut out = [0u8; 3];
+        assert_eq!(3, ram.read_at(2, &mut out));
+        assert_eq!([1, 0, 7], out);
+        assert_eq!(1, ram.write_at(7, &[9, 9]));
+        assert_eq!(0x07060509, ram.read_value_at::<u32>(4, Endian::Big).unwrap());
+        assert_eq!(0, ram.read_at(u64::MAX, &mut out));
+        assert_eq!(0, ram.write_at(u64::MAX, &[1, 2]));
+    }
+
+    #[test]
+    fn zero_word_size_is_rejected() {
+        let mut data = vec![0u8; 8];
+        assert_eq!(Some(ReversedWordsError::InvalidWordSize), AtomicReversedWords::try_from_mut_slice(&mut data, 0).err());
+        let atomics: Vec<std::sync::atomic::AtomicU8> = data.iter().map(|byte| (*byte).into()).collect();
+        assert!(AtomicReversedWords::try_new_with_word_size(&atomics, 0).is_err());
+        assert!(std::panic::catch_unwind(|| AtomicReversedWords::new_with_word_size(&atomics, 0)).is_err());
+    }
+
+    #[test]
+    fn concurrent_readers_and_writer() {
+        let mut data = vec![0u8; 64];
+        let ram = AtomicReversedWords::from_mut_slice(&mut data, 4);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for value in 0..=255u8 {
+                    ram.write_at(0, &[value; 64]);
+                }
+            });
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut out = [0u8; 64];
+                    for _ in 0..100 {
+                        assert_eq!(64, ram.read_at(0, &mut out));
+                    }
+                });
+            }
+        });
+        assert_eq!(vec![255u8; 64], data);
+    }
+
+    #[test]
+    fn seqlock_snapshots_are_consistent() {
+        let mut data = vec![0u8; 64];
+        let ram = SeqLockWords::new(AtomicReversedWords::from_mut_slice(&mut data, 4));
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for value in 0..=255u8 {
+                    ram.write_at(0, &[value; 64]);
+                }
+            });
+            for _ in 0..100 {
+                let snapshot = ram.snapshot().into_inner();
+                assert!(snapshot.iter().all(|byte| *byte == snapshot[0]), "torn snapshot {:?}", snapshot);
+                let value = ram.read_value_at::<u64>(8, Endian::Big).unwrap();
+                assert_eq!(value.to_be_bytes(), [value as u8; 8]);
+            }
+        });
+        assert_eq!(256, ram.writes());
+        assert_eq!(vec![255u8; 64], ram.snapshot().into_inner());
+    }
+
+    #[test]
+    fn split_halves_stream_independently() {
+        let mut data = vec![0u8; 16];
+        let mut words = ReversedWords::new(&mut data).with_nibble_swap(true).with_address_mode(AddressMode::Wrapping);
+        let (mut reader, mut writer) = words.split();
+        assert!(reader.words().nibble_swap() && writer.words().address_mode() == AddressMode::Wrapping);
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                writer.seek(SeekFrom::Start(8)).unwrap();
+                writer.write_all(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+                assert!(writer.write_all(&[9]).is_err());
+            });
+            // reading the bytes being written sees each one either before or after its write
+            let mut out = [0u8; 8];
+            while out != [1, 2, 3, 4, 5, 6, 7, 8] {
+                reader.seek(SeekFrom::Start(8)).unwrap();
+                reader.read_exact(&mut out).unwrap();
+                assert!(out.iter().zip(1..).all(|(byte, written)| *byte == 0 || *byte == written));
+            }
+        });
+        assert_eq!(16, reader.position());
+        assert_eq!(0, reader.read(&mut [0u8; 4]).unwrap());
+        // the addressed accessors still wrap
+        let mut out = [0u8; 4];
+        assert_eq!(4, reader.words().read_at(28, &mut out));
+        assert_eq!([5, 6, 7, 8], out);
+        assert_eq!(0x05060708, words.read_u32_at(12, Endian::Big).unwrap());
+        assert_eq!([0x40, 0x30, 0x20, 0x10], data[8..12]);
+    }
+}
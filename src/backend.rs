@@ -0,0 +1,1240 @@
+//! Swapped access over any storage that can read and write byte ranges.
+//!
+//! [`ReversedWords`](crate::ReversedWords) is specialized for a borrowed slice, which lets it hand
+//! out zero-copy spans. Everything else (files, another process, a socket, emulator APIs) only
+//! needs to implement [`MemoryBackend`] in storage order; [`ReversedBackend`] puts the cursor and
+//! typed accessors on top. Both map logical bytes to storage through the same layout, so the word
+//! swap, nibble swap, bit reversal, [`AddressMode`] and [`MisalignmentPolicy`] behave the same
+//! whatever holds the bytes.
+//!
+//! [`NullBackend`] and [`PatternBackend`] hold no data at all, for testing code built on a backend
+//! without constructing a dump.
+//!
+//! Backends that talk to something else fail transiently; wrapping one in a [`RetryBackend`]
+//! retries those failures according to a [`RetryPolicy`] and reports giving up as
+//! [`ReversedWordsError::BackendTimeout`] or [`ReversedWordsError::RetriesExhausted`], with the
+//! last failure kept as the `source()`. Ones that may silently drop writes can be wrapped in a
+//! [`ShadowBackend`], which remembers what was written and reads it back to check, and ones
+//! with slow round trips in a [`PrefetchBackend`], which reads ahead of sequential reads.
+//!
+//! [`ReversedBackend`] stages raw words in one scratch buffer it keeps between accesses, so a
+//! stream of reads or writes allocates at most once. The buffer can be handed in with
+//! [`ReversedBackend::with_scratch`] and is capped by [`ReversedBackend::with_chunk_size`], which
+//! splits larger accesses into several raw ones.
+
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fs::File,
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use binread::Endian;
+
+use crate::{AddressMode, ErrorContext, Layout, MisalignmentPolicy, Primitive, ReversedWordsError};
+
+/// Storage that can be read and written in storage order by offset.
+pub trait MemoryBackend {
+    /// Length of the storage in bytes.
+    fn len(&self) -> u64;
+
+    /// Read the storage bytes starting at `offset`, returning how many were in range.
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Write storage bytes starting at `offset`, returning how many were in range.
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// What to call the backend in the [`ErrorContext`] of its failures. The type name by
+    /// default.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Make sure earlier writes have reached the storage. Does nothing by default.
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read_slice(data: &[u8], offset: u64, buf: &mut [u8]) -> usize {
+    let data_len = <[u8]>::len(data);
+    let start = offset.min(data_len as u64) as usize;
+    let n = buf.len().min(data_len - start);
+    buf[..n].copy_from_slice(&data[start..start + n]);
+    n
+}
+
+fn write_slice(data: &mut [u8], offset: u64, buf: &[u8]) -> usize {
+    let data_len = <[u8]>::len(data);
+    let start = offset.min(data_len as u64) as usize;
+    let n = buf.len().min(data_len - start);
+    data[start..start + n].copy_from_slice(&buf[..n]);
+    n
+}
+
+impl MemoryBackend for [u8] {
+    fn len(&self) -> u64 {
+        <[u8]>::len(self) as u64
+    }
+
+    fn name(&self) -> &str {
+        "slice"
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(read_slice(self, offset, buf))
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(write_slice(self, offset, buf))
+    }
+}
+
+/// The length is fixed, writes past the end stop short like they do for a slice.
+impl MemoryBackend for Vec<u8> {
+    fn len(&self) -> u64 {
+        Vec::len(self) as u64
+    }
+
+    fn name(&self) -> &str {
+        "vec"
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(read_slice(self, offset, buf))
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(write_slice(self, offset, buf))
+    }
+}
+
+// how many of `wanted` bytes from `offset` are within `len`
+fn available(wanted: usize, offset: u64, len: u64) -> usize {
+    (wanted as u64).min(len.saturating_sub(offset)) as usize
+}
+
+/// A file used in place, without loading it into memory. The length is taken when it is opened,
+/// and may be larger than the address space.
+pub struct FileBackend {
+    file: File,
+    len: u64,
+}
+
+impl FileBackend {
+    pub fn new(file: File) -> std::io::Result<FileBackend> {
+        let len = file.metadata()?.len();
+        Ok(FileBackend { file, len })
+    }
+
+    pub fn into_inner(self) -> File {
+        self.file
+    }
+}
+
+impl MemoryBackend for FileBackend {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = available(buf.len(), offset, self.len);
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut buf[..n])?;
+        Ok(n)
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let n = available(buf.len(), offset, self.len);
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+/// Storage with nothing in it: every read and write is out of range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NullBackend;
+
+impl MemoryBackend for NullBackend {
+    fn len(&self) -> u64 {
+        0
+    }
+
+    fn name(&self) -> &str {
+        "null"
+    }
+
+    fn read_raw(&mut self, _offset: u64, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
+
+    fn write_raw(&mut self, _offset: u64, _buf: &[u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
+}
+
+/// `len` bytes of storage that always read as `pattern` repeated from offset 0, and ignore
+/// writes. For testing code built on [`MemoryBackend`] without a real dump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternBackend {
+    pattern: Vec<u8>,
+    len: u64,
+}
+
+impl PatternBackend {
+    /// An empty `pattern` reads as zeros.
+    pub fn new(pattern: &[u8], len: u64) -> PatternBackend {
+        let pattern = if pattern.is_empty() { vec![0] } else { pattern.to_vec() };
+        PatternBackend { pattern, len }
+    }
+
+    /// Storage that reads as all zeros.
+    pub fn zeros(len: u64) -> PatternBackend {
+        PatternBackend::new(&[0], len)
+    }
+
+    pub fn pattern(&self) -> &[u8] {
+        &self.pattern
+    }
+}
+
+impl MemoryBackend for PatternBackend {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn name(&self) -> &str {
+        "pattern"
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = available(buf.len(), offset, self.len);
+        let period = self.pattern.len() as u64;
+        for (i, byte) in buf[..n].iter_mut().enumerate() {
+            *byte = self.pattern[((offset + i as u64) % period) as usize];
+        }
+        Ok(n)
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(available(buf.len(), offset, self.len))
+    }
+}
+
+/// When and how often a [`RetryBackend`] retries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per operation, including the first.
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for each one after it.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// Give up once an operation has been retried for this long. Attempts already running are not
+    /// interrupted, so blocking backends should also set their own I/O timeouts.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            timeout: Some(Duration::from_secs(5)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Fail on the first error.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy { attempts: 1, backoff: Duration::ZERO, max_backoff: Duration::ZERO, timeout: None }
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> RetryPolicy {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Duration, max_backoff: Duration) -> RetryPolicy {
+        self.backoff = backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> RetryPolicy {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Errors worth another attempt: interruptions, timeouts and dropped connections. Anything
+    /// else (an address out of range, say) would fail the same way again.
+    pub fn is_transient(error: &std::io::Error) -> bool {
+        matches!(
+            error.kind(),
+            ErrorKind::Interrupted
+                | ErrorKind::WouldBlock
+                | ErrorKind::TimedOut
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+        )
+    }
+
+    fn run<T>(&self, operation: &'static str, offset: u64, mut attempt: impl FnMut() -> std::io::Result<T>) -> std::io::Result<(T, u32)> {
+        let start = Instant::now();
+        let mut backoff = self.backoff;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let error = match attempt() {
+                Ok(value) => return Ok((value, attempts - 1)),
+                Err(error) if !RetryPolicy::is_transient(&error) => return Err(error),
+                Err(error) => error,
+            };
+            if attempts >= self.attempts {
+                let last = error.to_string();
+                return Err(ReversedWordsError::RetriesExhausted { operation, offset, attempts, kind: error.kind(), last }.with_source(error));
+            }
+            if self.timeout.is_some_and(|timeout| start.elapsed() + backoff >= timeout) {
+                return Err(ReversedWordsError::BackendTimeout { operation, offset, attempts }.with_source(error));
+            }
+            std::thread::sleep(backoff);
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+    }
+}
+
+/// A backend whose transiently failing reads and writes are retried.
+pub struct RetryBackend<B> {
+    backend: B,
+    policy: RetryPolicy,
+    retries: u64,
+}
+
+impl<B: MemoryBackend> RetryBackend<B> {
+    pub fn new(backend: B, policy: RetryPolicy) -> RetryBackend<B> {
+        RetryBackend { backend, policy, retries: 0 }
+    }
+
+    pub fn policy(&self) -> RetryPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: RetryPolicy) {
+        self.policy = policy;
+    }
+
+    /// Retries made so far, over all operations.
+    pub fn retries(&self) -> u64 {
+        self.retries
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+}
+
+impl<B: MemoryBackend> MemoryBackend for RetryBackend<B> {
+    fn len(&self) -> u64 {
+        self.backend.len()
+    }
+
+    fn name(&self) -> &str {
+        self.backend.name()
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let backend = &mut self.backend;
+        let (n, retries) = self.policy.run("read", offset, || backend.read_raw(offset, buf))?;
+        self.retries += retries as u64;
+        Ok(n)
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let backend = &mut self.backend;
+        let (n, retries) = self.policy.run("write", offset, || backend.write_raw(offset, buf))?;
+        self.retries += retries as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.backend.flush()
+    }
+}
+
+/// A backend that keeps a copy of everything written through it, so dropped writes can be
+/// caught by reading the storage back.
+///
+/// [`ShadowBackend::verify`] rereads every byte written so far and returns the storage ranges
+/// that no longer hold what was written; `flush` does the same and fails with
+/// [`ReversedWordsError::ShadowMismatch`] if there are any. Only writes set expectations, so
+/// bytes the target changes on its own are reported too; [`ShadowBackend::clear`] forgets them.
+pub struct ShadowBackend<B> {
+    backend: B,
+    // written storage bytes, as disjoint extents keyed by their start offset
+    shadow: BTreeMap<u64, Vec<u8>>,
+}
+
+impl<B: MemoryBackend> ShadowBackend<B> {
+    pub fn new(backend: B) -> ShadowBackend<B> {
+        ShadowBackend { backend, shadow: BTreeMap::new() }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+
+    /// How many bytes of storage have expected contents.
+    pub fn shadowed_len(&self) -> u64 {
+        self.shadow.values().map(|extent| extent.len() as u64).sum()
+    }
+
+    /// Forget everything written so far.
+    pub fn clear(&mut self) {
+        self.shadow.clear();
+    }
+
+    // remember `data` as written at `offset`, merging it with the extents it overlaps or touches
+    fn record(&mut self, offset: u64, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let (mut start, mut end) = (offset, offset + data.len() as u64);
+        let touching: Vec<u64> =
+            self.shadow.range(..=end).rev().take_while(|(extent, bytes)| **extent + bytes.len() as u64 >= offset).map(|(extent, _)| *extent).collect();
+        let old: Vec<(u64, Vec<u8>)> = touching.into_iter().map(|extent| (extent, self.shadow.remove(&extent).expect("listed above"))).collect();
+        for (extent, bytes) in &old {
+            start = start.min(*extent);
+            end = end.max(extent + bytes.len() as u64);
+        }
+        let mut merged = vec![0u8; (end - start) as usize];
+        for (extent, bytes) in &old {
+            let at = (extent - start) as usize;
+            merged[at..at + bytes.len()].copy_from_slice(bytes);
+        }
+        let at = (offset - start) as usize;
+        merged[at..at + data.len()].copy_from_slice(data);
+        self.shadow.insert(start, merged);
+    }
+
+    /// Read back everything written so far and return the storage ranges that differ from it,
+    /// in order. Bytes the backend no longer has count as differing.
+    pub fn verify(&mut self) -> std::io::Result<Vec<Range<u64>>> {
+        let mut mismatches: Vec<Range<u64>> = Vec::new();
+        for (start, expected) in &self.shadow {
+            let mut actual = vec![0u8; expected.len()];
+            let n = self.backend.read_raw(*start, &mut actual)?;
+            for (i, byte) in expected.iter().enumerate() {
+                if i < n && actual[i] == *byte {
+                    continue;
+                }
+                let addr = start + i as u64;
+                match mismatches.last_mut() {
+                    Some(last) if last.end == addr => last.end += 1,
+                    _ => mismatches.push(addr..addr + 1),
+                }
+            }
+        }
+        Ok(mismatches)
+    }
+}
+
+impl<B: MemoryBackend> MemoryBackend for ShadowBackend<B> {
+    fn len(&self) -> u64 {
+        self.backend.len()
+    }
+
+    fn name(&self) -> &str {
+        self.backend.name()
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.backend.read_raw(offset, buf)
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.backend.write_raw(offset, buf)?;
+        self.record(offset, &buf[..n]);
+        Ok(n)
+    }
+
+    /// Flushes the backend, then verifies it.
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.backend.flush()?;
+        let ranges = self.verify()?;
+        if !ranges.is_empty() {
+            return Err(ReversedWordsError::ShadowMismatch { ranges }.into());
+        }
+        Ok(())
+    }
+}
+
+/// A backend whose sequential reads fetch ahead, for backends where every call is a round trip
+/// (a GDB stub, a socket).
+///
+/// A read that starts where the previous one ended and misses the cache fetches `window`
+/// bytes beyond what was asked for, so a parser walking memory one field at a time makes one
+/// call per window instead of one per field. Other reads fetch only what they ask for. Writes go
+/// through to the backend and update the cache, but changes made to the storage by something
+/// else aren't seen until the cache is [invalidated](PrefetchBackend::invalidate).
+pub struct PrefetchBackend<B> {
+    backend: B,
+    window: usize,
+    cache_start: u64,
+    cache: Vec<u8>,
+    // where the previous read ended
+    next: Option<u64>,
+    fetches: u64,
+}
+
+impl<B: MemoryBackend> PrefetchBackend<B> {
+    /// Read `window` bytes ahead of sequential reads.
+    pub fn new(backend: B, window: usize) -> PrefetchBackend<B> {
+        PrefetchBackend { backend, window, cache_start: 0, cache: Vec::new(), next: None, fetches: 0 }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window;
+    }
+
+    /// Forget the cached bytes, for when the storage may have changed.
+    pub fn invalidate(&mut self) {
+        self.cache.clear();
+        self.next = None;
+    }
+
+    /// Reads made of the backend so far.
+    pub fn fetches(&self) -> u64 {
+        self.fetches
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+
+    // the cached bytes from `offset`, if it is cached
+    fn cached(&self, offset: u64) -> Option<&[u8]> {
+        let start = offset.checked_sub(self.cache_start)?;
+        if start >= self.cache.len() as u64 {
+            return None;
+        }
+        Some(&self.cache[start as usize..])
+    }
+}
+
+impl<B: MemoryBackend> MemoryBackend for PrefetchBackend<B> {
+    fn len(&self) -> u64 {
+        self.backend.len()
+    }
+
+    fn name(&self) -> &str {
+        self.backend.name()
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let sequential = self.next == Some(offset);
+        let mut done = 0;
+        while done < buf.len() {
+            let position = offset + done as u64;
+            if let Some(cached) = self.cached(position) {
+                let n = cached.len().min(buf.len() - done);
+                buf[done..done + n].copy_from_slice(&cached[..n]);
+                done += n;
+                continue;
+            }
+            // a read running off the end of the cache is sequential too
+            let ahead = if sequential || done > 0 { self.window } else { 0 };
+            let wanted = available((buf.len() - done).saturating_add(ahead), position, self.len());
+            if wanted == 0 {
+                break;
+            }
+            self.cache.clear();
+            self.cache.resize(wanted, 0);
+            self.fetches += 1;
+            let fetched = self.backend.read_raw(position, &mut self.cache);
+            let n = *fetched.as_ref().unwrap_or(&0);
+            self.cache.truncate(n);
+            self.cache_start = position;
+            fetched?;
+            if n == 0 {
+                break;
+            }
+        }
+        self.next = Some(offset + done as u64);
+        Ok(done)
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.backend.write_raw(offset, buf)?;
+        let (start, end) = (offset.max(self.cache_start), (offset + n as u64).min(self.cache_start + self.cache.len() as u64));
+        if start < end {
+            let (from, to) = ((start - offset) as usize, (start - self.cache_start) as usize);
+            let count = (end - start) as usize;
+            self.cache[to..to + count].copy_from_slice(&buf[from..from + count]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.backend.flush()
+    }
+}
+
+/// `Read + Write + Seek` and typed accessors in logical order over a [`MemoryBackend`].
+///
+/// Each access reads (and for writes, writes back) the whole words it touches with one raw
+/// access per [chunk](ReversedBackend::with_chunk_size), so backends with expensive round trips
+/// see as few calls as possible.
+pub struct ReversedBackend<B> {
+    backend: B,
+    layout: Layout,
+    endian: Endian,
+    position: u64,
+    // raw words of the chunk being accessed, kept to be reused
+    scratch: Vec<u8>,
+    chunk_size: usize,
+}
+
+impl<B: MemoryBackend> ReversedBackend<B> {
+    /// The largest raw access made by default.
+    pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+    pub fn new(backend: B, word_size: u8) -> Result<ReversedBackend<B>, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        Ok(ReversedBackend {
+            backend,
+            layout: Layout::new(word_size),
+            endian: Endian::Big,
+            position: 0,
+            scratch: Vec::new(),
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+        })
+    }
+
+    /// Also swap the high and low nibble of every byte, see [`ReversedWords::with_nibble_swap`](crate::ReversedWords::with_nibble_swap).
+    pub fn with_nibble_swap(mut self, nibble_swap: bool) -> ReversedBackend<B> {
+        self.layout.nibble_swap = nibble_swap;
+        self
+    }
+
+    /// Also reverse the bits of every byte, see [`ReversedWords::with_bit_reversal`](crate::ReversedWords::with_bit_reversal).
+    pub fn with_bit_reversal(mut self, bit_reversal: bool) -> ReversedBackend<B> {
+        self.layout.bit_reversal = bit_reversal;
+        self
+    }
+
+    /// Wrap addresses past the end for the addressed accessors. `Read` and `Write` are unaffected.
+    pub fn with_address_mode(mut self, mode: AddressMode) -> ReversedBackend<B> {
+        self.layout.address_mode = mode;
+        self
+    }
+
+    pub fn with_misalignment_policy(mut self, policy: MisalignmentPolicy) -> ReversedBackend<B> {
+        self.layout.misalignment_policy = policy;
+        self
+    }
+
+    /// The byte order [`ReversedBackend::read_value`] and [`ReversedBackend::write_value`] use.
+    /// Defaults to big endian.
+    pub fn with_endian(mut self, endian: Endian) -> ReversedBackend<B> {
+        self.endian = endian;
+        self
+    }
+
+    /// Stage raw words in `scratch` instead of a buffer allocated on the first access. Only its
+    /// capacity matters: give it at least the chunk size to never allocate.
+    pub fn with_scratch(mut self, scratch: Vec<u8>) -> ReversedBackend<B> {
+        self.scratch = scratch;
+        self
+    }
+
+    /// Make raw accesses of at most `chunk_size` bytes, rounded down to whole words but at least
+    /// one word. Accesses spanning more are split, and one failing leaves the chunks before it
+    /// done.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> ReversedBackend<B> {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Take back the scratch buffer, leaving an empty one in its place.
+    pub fn take_scratch(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.scratch)
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.layout.word_size
+    }
+
+    pub fn nibble_swap(&self) -> bool {
+        self.layout.nibble_swap
+    }
+
+    pub fn bit_reversal(&self) -> bool {
+        self.layout.bit_reversal
+    }
+
+    pub fn address_mode(&self) -> AddressMode {
+        self.layout.address_mode
+    }
+
+    pub fn misalignment_policy(&self) -> MisalignmentPolicy {
+        self.layout.misalignment_policy
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub fn len(&self) -> u64 {
+        self.backend.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.backend.is_empty()
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+
+    // how many of `count` logical bytes from `addr` are in range, and the storage span of the
+    // words they are in
+    fn span(&self, addr: u64, count: usize) -> (usize, u64, u64) {
+        let len = self.len();
+        let in_range = (0..count).take_while(|i| addr.checked_add(*i as u64).and_then(|position| self.layout.storage_offset(position, len)).is_some()).count();
+        let word_size = self.word_size() as u64;
+        let start = addr - addr % word_size;
+        let end = ((addr + in_range as u64).div_ceil(word_size) * word_size).min(len);
+        (in_range, start, end)
+    }
+
+    // how many logical bytes from `addr` fit in one chunk
+    fn chunk_len(&self, addr: u64) -> usize {
+        let word_size = self.word_size() as usize;
+        (self.chunk_size / word_size).max(1) * word_size - (addr % word_size as u64) as usize
+    }
+
+    // the scratch buffer, zeroed to `len` bytes
+    fn scratch(&mut self, len: u64) -> Vec<u8> {
+        let mut raw = std::mem::take(&mut self.scratch);
+        raw.clear();
+        raw.resize(len as usize, 0);
+        raw
+    }
+
+    // `error` from the backend with the context of the access it failed
+    fn context(&self, operation: &'static str, addr: u64, len: usize, error: std::io::Error) -> std::io::Error {
+        ErrorContext::new(operation, addr, len, self.backend.name(), error).into()
+    }
+
+    // how many logical bytes from `addr` the addressed accessors can take before wrapping
+    fn until_wrap(&self, addr: u64) -> usize {
+        match self.layout.address_mode {
+            AddressMode::Wrapping => usize::try_from(self.len().saturating_sub(addr)).unwrap_or(usize::MAX),
+            AddressMode::Bounded => usize::MAX,
+        }
+    }
+
+    /// Read the logical bytes starting at `addr` without moving the cursor, returning how many
+    /// were in range. Backend failures are returned with an [`ErrorContext`].
+    pub fn read_at(&mut self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.layout.check_alignment(addr)?;
+        let mut done = 0;
+        while done < buf.len() {
+            let at = self.layout.offset_address(addr, done as u64, self.len());
+            let piece = buf.len().min(done.saturating_add(self.until_wrap(at)));
+            let n = self.read_unwrapped(at, &mut buf[done..piece])?;
+            done += n;
+            if n == 0 || done < piece {
+                break;
+            }
+        }
+        Ok(done)
+    }
+
+    // `read_at` for bytes that don't wrap, a chunk at a time
+    fn read_unwrapped(&mut self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut done = 0;
+        while done < buf.len() {
+            let at = addr + done as u64;
+            let chunk = buf.len().min(done + self.chunk_len(at));
+            let n = self.read_chunk(at, &mut buf[done..chunk]).map_err(|error| self.context("read", addr, buf.len(), error))?;
+            done += n;
+            if done < chunk {
+                break;
+            }
+        }
+        Ok(done)
+    }
+
+    fn read_chunk(&mut self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let (n, start, end) = self.span(addr, buf.len());
+        if n == 0 {
+            return Ok(0);
+        }
+        let mut raw = self.scratch(end - start);
+        let result = self.backend.read_raw(start, &mut raw);
+        if result.is_ok() {
+            self.layout.load(&raw, start, self.len(), addr, &mut buf[..n]);
+        }
+        self.scratch = raw;
+        result.map(|_| n)
+    }
+
+    /// Write logical bytes starting at `addr` without moving the cursor, returning how many
+    /// were in range. Words only partly covered are read first so their other bytes survive.
+    /// Backend failures are returned with an [`ErrorContext`].
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        self.layout.check_alignment(addr)?;
+        let mut done = 0;
+        while done < buf.len() {
+            let at = self.layout.offset_address(addr, done as u64, self.len());
+            let piece = buf.len().min(done.saturating_add(self.until_wrap(at)));
+            let n = self.write_unwrapped(at, &buf[done..piece])?;
+            done += n;
+            if n == 0 || done < piece {
+                break;
+            }
+        }
+        Ok(done)
+    }
+
+    // `write_at` for bytes that don't wrap, a chunk at a time
+    fn write_unwrapped(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let mut done = 0;
+        while done < buf.len() {
+            let at = addr + done as u64;
+            let chunk = buf.len().min(done + self.chunk_len(at));
+            let n = self.write_chunk(at, &buf[done..chunk]).map_err(|error| self.context("write", addr, buf.len(), error))?;
+            done += n;
+            if done < chunk {
+                break;
+            }
+        }
+        Ok(done)
+    }
+
+    fn write_chunk(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let (n, start, end) = self.span(addr, buf.len());
+        if n == 0 {
+            return Ok(0);
+        }
+        let mut raw = self.scratch(end - start);
+        let mut result = Ok(0);
+        if addr != start || addr + n as u64 != end {
+            result = self.backend.read_raw(start, &mut raw);
+        }
+        if result.is_ok() {
+            let len = self.len();
+            self.layout.store(&mut raw, start, len, addr, &buf[..n]);
+            result = self.backend.write_raw(start, &raw);
+        }
+        self.scratch = raw;
+        result.map(|_| n)
+    }
+
+    /// Copy the logical bytes in `src` to start at `dst`, a chunk of `scratch` at a time.
+    /// Overlapping ranges are handled as if the source were copied out first.
+    pub fn copy_within(&mut self, src: Range<u64>, dst: u64, scratch: &mut [u8]) -> std::io::Result<()> {
+        let len = self.len();
+        if src.start > src.end || src.end > len {
+            return Err(ReversedWordsError::RangeOutOfRange { start: src.start, end: src.end, len }.into());
+        }
+        let count = src.end - src.start;
+        let dst_end = dst.saturating_add(count);
+        if dst_end - dst < count || dst_end > len {
+            return Err(ReversedWordsError::RangeOutOfRange { start: dst, end: dst_end, len }.into());
+        }
+        if count > 0 && scratch.is_empty() {
+            return Err(ReversedWordsError::ScratchTooSmall { len: 0, needed: 1 }.into());
+        }
+        let step = scratch.len() as u64;
+        let chunks = count.div_ceil(step.max(1));
+        for chunk in 0..chunks {
+            // copy from the end so bytes aren't overwritten before they are read
+            let offset = if dst > src.start { chunks - 1 - chunk } else { chunk } * step;
+            let n = (count - offset).min(step) as usize;
+            self.read_at(src.start + offset, &mut scratch[..n])?;
+            self.write_at(dst + offset, &scratch[..n])?;
+        }
+        Ok(())
+    }
+
+    pub fn read_value_at<T: Primitive>(&mut self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        if self.read_at(addr, bytes.as_mut())? < bytes.as_ref().len() {
+            return Err(ReversedWordsError::AddressOutOfRange { addr, len: self.len() }.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        let bytes = value.to_bytes(endian);
+        let end = addr.saturating_add(bytes.as_ref().len() as u64);
+        if self.layout.address_mode == AddressMode::Bounded && end > self.len() {
+            return Err(ReversedWordsError::RangeOutOfRange { start: addr, end, len: self.len() }.into());
+        }
+        if self.write_at(addr, bytes.as_ref())? < bytes.as_ref().len() {
+            return Err(ReversedWordsError::AddressOutOfRange { addr, len: self.len() }.into());
+        }
+        Ok(())
+    }
+
+    /// [`ReversedBackend::read_value_at`] in the backend's [`endian`](ReversedBackend::with_endian).
+    pub fn read_value<T: Primitive>(&mut self, addr: u64) -> std::io::Result<T> {
+        self.read_value_at(addr, self.endian)
+    }
+
+    pub fn write_value<T: Primitive>(&mut self, addr: u64, value: T) -> std::io::Result<()> {
+        self.write_value_at(addr, value, self.endian)
+    }
+}
+
+impl<B: MemoryBackend> Read for ReversedBackend<B> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.layout.check_alignment(self.position)?;
+        let n = self.read_unwrapped(self.position, buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<B: MemoryBackend> Write for ReversedBackend<B> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.layout.check_alignment(self.position)?;
+        let n = self.write_unwrapped(self.position, buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.backend.flush()
+    }
+}
+
+impl<B: MemoryBackend> Seek for ReversedBackend<B> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.len() as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.position as i128 + offset as i128,
+        };
+        if target < 0 || target > u64::MAX as i128 {
+            return Err(ReversedWordsError::SeekOutOfRange { target, len: self.len() }.into());
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{backend::*, ReversedWords};
+
+    #[test]
+    fn matches_slice_view() {
+        let mut expected: Vec<u8> = (0..12).collect();
+        let mut words = ReversedBackend::new((0..12).collect::<Vec<u8>>(), 4).unwrap();
+        let mut view = ReversedWords::new(&mut expected);
+        for (addr, bytes) in [(1, &[0xA0, 0xA1][..]), (3, &[0xB0, 0xB1, 0xB2, 0xB3, 0xB4, 0xB5][..]), (7, &[0xC0][..]), (10, &[0xD0, 0xD1, 0xD2][..])] {
+            assert_eq!(view.write_at(addr, bytes).unwrap(), words.write_at(addr, bytes).unwrap());
+        }
+        let mut out = [0u8; 12];
+        words.read_exact(&mut out).unwrap();
+        let mut view_out = [0u8; 12];
+        view.seek(SeekFrom::Start(0)).unwrap();
+        view.read_exact(&mut view_out).unwrap();
+        assert_eq!(view_out, out);
+        assert_eq!(expected, words.into_inner());
+    }
+
+    #[test]
+    fn settings_match_slice_view() {
+        for (nibble_swap, bit_reversal, address_mode) in
+            [(true, false, AddressMode::Bounded), (false, true, AddressMode::Wrapping), (true, true, AddressMode::Wrapping)]
+        {
+            let mut expected: Vec<u8> = (0..14).collect();
+            let mut words = ReversedBackend::new((0..14).collect::<Vec<u8>>(), 4)
+                .unwrap()
+                .with_nibble_swap(nibble_swap)
+                .with_bit_reversal(bit_reversal)
+                .with_address_mode(address_mode)
+                .with_chunk_size(4);
+            let mut view = ReversedWords::new(&mut expected)
+                .with_nibble_swap(nibble_swap)
+                .with_bit_reversal(bit_reversal)
+                .with_address_mode(address_mode);
+            for (addr, bytes) in [(2, &[0xA0, 0xA1, 0xA2][..]), (10, &[0xB0, 0xB1, 0xB2, 0xB3, 0xB4][..]), (17, &[0xC0, 0xC1][..])] {
+                assert_eq!(view.write_at(addr, bytes).unwrap(), words.write_at(addr, bytes).unwrap());
+            }
+            for addr in [0, 3, 11, 13, 17] {
+                let (mut out, mut view_out) = ([0u8; 9], [0u8; 9]);
+                assert_eq!(view.read_at(addr, &mut view_out).unwrap(), words.read_at(addr, &mut out).unwrap());
+                assert_eq!(view_out, out);
+            }
+            assert_eq!(expected, words.into_inner());
+        }
+    }
+
+    #[test]
+    fn misalignment_policy_and_endian() {
+        let mut words =
+            ReversedBackend::new(vec![0u8; 8], 4).unwrap().with_misalignment_policy(MisalignmentPolicy::Reject).with_endian(Endian::Little);
+        words.write_value(4, 0x1122_3344u32).unwrap();
+        assert_eq!(0x1122_3344, words.read_value::<u32>(4).unwrap());
+        assert_eq!(0x4433_2211, words.read_value_at::<u32>(4, Endian::Big).unwrap());
+        let error = words.read_at(2, &mut [0; 2]).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::Misaligned { position: 2, word_size: 4 }), ReversedWordsError::from_io(&error));
+        words.seek(SeekFrom::Start(1)).unwrap();
+        assert!(words.write(&[0]).is_err());
+        assert_eq!(vec![0, 0, 0, 0, 0x11, 0x22, 0x33, 0x44], words.into_inner());
+    }
+
+    #[test]
+    fn values_in_a_trailing_partial_word_are_an_error() {
+        let mut words = ReversedBackend::new(vec![0u8; 6], 4).unwrap();
+        let error = words.write_value_at(4, 0xBEEFu16, Endian::Big).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::AddressOutOfRange { addr: 4, len: 6 }), ReversedWordsError::from_io(&error));
+        assert!(words.read_value_at::<u16>(4, Endian::Big).is_err());
+
+        let mut words = ReversedBackend::new(vec![0u8; 8], 4).unwrap().with_address_mode(AddressMode::Wrapping);
+        words.write_value_at(7, 0xBEEFu16, Endian::Big).unwrap();
+        assert_eq!(0xBEEF, words.read_value_at::<u16>(15, Endian::Big).unwrap());
+    }
+
+    // fails every operation with `kind` until `failures` runs out
+    struct Flaky {
+        data: Vec<u8>,
+        failures: u32,
+        kind: ErrorKind,
+    }
+
+    impl MemoryBackend for Flaky {
+        fn len(&self) -> u64 {
+            self.data.len() as u64
+        }
+
+        fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(self.kind.into());
+            }
+            self.data.read_raw(offset, buf)
+        }
+
+        fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+            self.data.write_raw(offset, buf)
+        }
+    }
+
+    #[test]
+    fn retries_transient_failures() {
+        let policy = RetryPolicy::default().with_backoff(Duration::from_millis(1), Duration::from_millis(2));
+        let flaky = Flaky { data: vec![0, 1, 2, 3], failures: 2, kind: ErrorKind::TimedOut };
+        let mut words = ReversedBackend::new(RetryBackend::new(flaky, policy), 4).unwrap();
+        assert_eq!(0x0302, words.read_value_at::<u16>(0, Endian::Big).unwrap());
+        assert_eq!(2, words.backend().retries());
+
+        words.backend_mut().backend.failures = 3;
+        let error = words.read_value_at::<u16>(0, Endian::Big).unwrap_err();
+        assert_eq!(ErrorKind::TimedOut, error.kind());
+        assert!(matches!(
+            ReversedWordsError::from_io(&error),
+            Some(ReversedWordsError::RetriesExhausted { operation: "read", offset: 0, attempts: 3, .. })
+        ));
+
+        let flaky = Flaky { data: vec![0; 4], failures: 1, kind: ErrorKind::PermissionDenied };
+        let mut backend = RetryBackend::new(flaky, policy);
+        assert_eq!(ErrorKind::PermissionDenied, backend.read_raw(0, &mut [0; 4]).unwrap_err().kind());
+        assert_eq!(0, backend.retries());
+
+        let flaky = Flaky { data: vec![0; 4], failures: 9, kind: ErrorKind::Interrupted };
+        let slow = policy.with_attempts(9).with_backoff(Duration::from_millis(20), Duration::from_millis(20));
+        let mut backend = RetryBackend::new(flaky, slow.with_timeout(Some(Duration::from_millis(30))));
+        let error = backend.read_raw(4, &mut [0; 4]).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::BackendTimeout { operation: "read", offset: 4, attempts: 2 }),
+            ReversedWordsError::from_io(&error)
+        );
+    }
+
+    #[test]
+    fn trivial_backends() {
+        let mut words = ReversedBackend::new(PatternBackend::new(&[1, 2, 3, 4], 8), 4).unwrap();
+        assert_eq!(0x0403_0201, words.read_value_at::<u32>(4, Endian::Big).unwrap());
+        words.write_value_at(0, 0u32, Endian::Big).unwrap();
+        assert_eq!(0x0403_0201, words.read_value_at::<u32>(0, Endian::Big).unwrap());
+        assert!(words.read_value_at::<u32>(6, Endian::Big).is_err());
+
+        let mut zeros = ReversedBackend::new(PatternBackend::zeros(16), 4).unwrap();
+        assert_eq!(0, zeros.read_value_at::<u64>(8, Endian::Big).unwrap());
+        let mut null = ReversedBackend::new(NullBackend, 4).unwrap();
+        assert!(null.is_empty());
+        assert!(null.read_value_at::<u8>(0, Endian::Big).is_err());
+        assert_eq!(0, null.write(&[1]).unwrap());
+    }
+
+    #[test]
+    fn shadow_catches_dropped_writes() {
+        let mut words = ReversedBackend::new(ShadowBackend::new(vec![0u8; 16]), 4).unwrap();
+        words.write_value_at(2, 0x1122_3344u32, Endian::Big).unwrap();
+        words.write_at(8, &[5, 6]).unwrap();
+        words.flush().unwrap();
+        // the unaligned write covered two words, the short one a third read back and rewritten
+        assert_eq!(12, words.backend().shadowed_len());
+        assert_eq!(Vec::<Range<u64>>::new(), words.backend_mut().verify().unwrap());
+
+        // something else clobbers bytes that were written
+        words.backend_mut().backend.write_raw(4, &[0xFF]).unwrap();
+        words.backend_mut().backend[10] = 0xFF;
+        words.backend_mut().backend[11] = 0xFF;
+        assert_eq!(vec![4..5, 10..12], words.backend_mut().verify().unwrap());
+        let error = words.flush().unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        assert_eq!(Some(&ReversedWordsError::ShadowMismatch { ranges: vec![4..5, 10..12] }), ReversedWordsError::from_io(&error));
+        words.backend_mut().clear();
+        words.flush().unwrap();
+
+        // a target that ignores writes entirely
+        let mut dropping = ReversedBackend::new(ShadowBackend::new(PatternBackend::zeros(8)), 4).unwrap();
+        dropping.write_value_at(4, 0x0100u16, Endian::Big).unwrap();
+        assert_eq!(vec![7..8], dropping.backend_mut().verify().unwrap());
+    }
+
+    #[test]
+    fn file_backend() {
+        let path = std::env::temp_dir().join(format!("reversed-backend-{}", std::process::id()));
+        std::fs::write(&path, [0, 1, 2, 3, 4, 5, 6, 7]).unwrap();
+        let file = std::fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+        let mut words = ReversedBackend::new(FileBackend::new(file).unwrap(), 4).unwrap();
+        assert_eq!(0x0706, words.read_value_at::<u16>(4, Endian::Big).unwrap());
+        words.write_value_at(2, 0xBEEFu16, Endian::Big).unwrap();
+        assert!(words.write_value_at(7, 0u16, Endian::Big).is_err());
+        drop(words);
+        assert_eq!(vec![0xEF, 0xBE, 2, 3, 4, 5, 6, 7], std::fs::read(&path).unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    struct CountingBackend {
+        data: Vec<u8>,
+        largest: usize,
+        calls: usize,
+    }
+
+    impl MemoryBackend for CountingBackend {
+        fn len(&self) -> u64 {
+            self.data.len() as u64
+        }
+
+        fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.largest = self.largest.max(buf.len());
+            self.calls += 1;
+            self.data.read_raw(offset, buf)
+        }
+
+        fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+            self.largest = self.largest.max(buf.len());
+            self.calls += 1;
+            self.data.write_raw(offset, buf)
+        }
+    }
+
+    #[test]
+    fn chunks_reuse_the_scratch_buffer() {
+        let backend = CountingBackend { data: (0..64).collect(), largest: 0, calls: 0 };
+        let mut words = ReversedBackend::new(backend, 4).unwrap().with_scratch(Vec::with_capacity(16)).with_chunk_size(18);
+        let mut out = [0u8; 30];
+        assert_eq!(30, words.read_at(2, &mut out).unwrap());
+        let mut expected = [0u8; 30];
+        ReversedWords::new(&mut (0..64).collect::<Vec<u8>>()).read_at(2, &mut expected).unwrap();
+        assert_eq!(expected, out);
+        // 16 byte chunks of whole words, the first starting mid-word
+        assert_eq!((16, 2), (words.backend().largest, words.backend().calls));
+        assert_eq!(14, words.write_at(50, &out[..20]).unwrap());
+        let mut back = [0u8; 14];
+        words.read_at(50, &mut back).unwrap();
+        assert_eq!(out[..14], back);
+        assert!(words.take_scratch().capacity() >= 16);
+
+        let mut expected: Vec<u8> = (0..16).collect();
+        let mut words = ReversedBackend::new(expected.clone(), 4).unwrap();
+        ReversedWords::new(&mut expected).copy_within(1..13, 3).unwrap();
+        words.copy_within(1..13, 3, &mut [0u8; 5]).unwrap();
+        assert!(words.copy_within(1..13, 3, &mut []).is_err());
+        assert!(words.copy_within(1..13, 5, &mut [0u8; 5]).is_err());
+        assert_eq!(expected, words.into_inner());
+    }
+
+    #[test]
+    fn prefetches_sequential_reads() {
+        let backend = CountingBackend { data: (0..64).collect(), largest: 0, calls: 0 };
+        let mut words = ReversedBackend::new(PrefetchBackend::new(backend, 32), 4).unwrap();
+        let mut expected = (0..64).collect::<Vec<u8>>();
+        let mut expected = ReversedWords::new(&mut expected);
+        for addr in (0..64).step_by(4) {
+            assert_eq!(expected.read_value_at::<u32>(addr, Endian::Big).unwrap(), words.read_value_at::<u32>(addr, Endian::Big).unwrap());
+        }
+        // the first read, then 36 bytes from 4, then the last 24
+        assert_eq!(3, words.backend().fetches());
+        assert_eq!(3, words.backend().backend().calls);
+
+        // a jump back is a hit if it's still cached, and fetches only itself if not
+        words.read_value_at::<u32>(48, Endian::Big).unwrap();
+        words.read_value_at::<u16>(9, Endian::Big).unwrap();
+        assert_eq!(4, words.backend().fetches());
+
+        // writes update what's cached
+        words.read_value_at::<u32>(12, Endian::Big).unwrap();
+        assert_eq!(5, words.backend().fetches());
+        words.write_value_at(14, 0xBEEFu16, Endian::Big).unwrap();
+        assert_eq!(0xBEEF, words.read_value_at::<u16>(14, Endian::Big).unwrap());
+        words.backend_mut().invalidate();
+        assert_eq!(0xBEEF, words.read_value_at::<u16>(14, Endian::Big).unwrap());
+    }
+}
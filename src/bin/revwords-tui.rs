@@ -0,0 +1,487 @@
+//! Terminal hex viewer and editor for word-swapped dumps and live memory.
+//!
+//! A dump is loaded into memory and only written back when saved; `--live` opens the file (a
+//! `/proc/<pid>/mem`, a device, a file an emulator maps) through a [`FileBackend`] instead, so
+//! every edit goes straight to it.
+//!
+//! Keys: arrows/hjkl and PageUp/PageDown move, hex digits overwrite the byte under the cursor,
+//! `g` goes to an address or label, `/` searches for hex bytes (or `"text`), `n` finds the next
+//! match, `s` saves a dump and `q` quits.
+
+use std::{fs, io, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    DefaultTerminal, Frame,
+};
+use reversed_word_byte_rw::{
+    backend::{FileBackend, ReversedBackend},
+    labels::Labels,
+    symbols::SymbolTable,
+    translate::AddressMap,
+};
+
+const BYTES_PER_ROW: u64 = 16;
+// how much is read at a time when searching
+const SEARCH_CHUNK: usize = 1 << 16;
+
+#[derive(Parser)]
+#[command(name = "revwords-tui", version, about = "View and edit word-swapped dumps in the terminal")]
+struct Cli {
+    input: PathBuf,
+    #[arg(short, long, default_value_t = 4)]
+    word_size: u8,
+    /// Edit the file in place as it is read, instead of loading it and saving on request
+    #[arg(long)]
+    live: bool,
+    /// A GNU ld map file to label addresses with
+    #[arg(long)]
+    symbols: Option<PathBuf>,
+    /// Virtual address the start of the file is mapped at, for the symbols and for goto
+    #[arg(long, default_value = "0", value_parser = parse_number)]
+    base: u64,
+}
+
+fn parse_number(s: &str) -> Result<u64, String> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|e| format!("invalid number {:?}: {}", s, e))
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u32> = s
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(16).ok_or_else(|| format!("invalid hex digit {:?}", c)))
+        .collect::<Result<_, _>>()?;
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits in {:?}", s));
+    }
+    Ok(digits.chunks(2).map(|pair| (pair[0] << 4 | pair[1]) as u8).collect())
+}
+
+enum Source {
+    Dump { words: ReversedBackend<Vec<u8>>, path: PathBuf },
+    Live(ReversedBackend<FileBackend>),
+}
+
+impl Source {
+    fn len(&self) -> u64 {
+        match self {
+            Source::Dump { words, .. } => words.len(),
+            Source::Live(words) => words.len(),
+        }
+    }
+
+    fn read_at(&mut self, addr: u64, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Source::Dump { words, .. } => words.read_at(addr, buf),
+            Source::Live(words) => words.read_at(addr, buf),
+        }
+    }
+
+    fn write_at(&mut self, addr: u64, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Source::Dump { words, .. } => words.write_at(addr, buf),
+            Source::Live(words) => words.write_at(addr, buf),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    Goto,
+    Search,
+}
+
+struct App {
+    source: Source,
+    labels: Labels,
+    base: u64,
+    cursor: u64,
+    top: u64,
+    rows: u64,
+    // the high nibble typed so far for the byte under the cursor
+    pending: Option<u8>,
+    mode: Mode,
+    input: String,
+    needle: Option<Vec<u8>>,
+    status: String,
+    modified: bool,
+    quit: bool,
+}
+
+impl App {
+    fn new(source: Source, labels: Labels, base: u64) -> App {
+        App {
+            source,
+            labels,
+            base,
+            cursor: 0,
+            top: 0,
+            rows: 16,
+            pending: None,
+            mode: Mode::Normal,
+            input: String::new(),
+            needle: None,
+            status: String::new(),
+            modified: false,
+            quit: false,
+        }
+    }
+
+    fn move_to(&mut self, addr: u64) {
+        let len = self.source.len();
+        self.cursor = addr.min(len.saturating_sub(1));
+        self.pending = None;
+        let row = self.cursor - self.cursor % BYTES_PER_ROW;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + self.rows * BYTES_PER_ROW {
+            self.top = row + BYTES_PER_ROW - self.rows * BYTES_PER_ROW;
+        }
+    }
+
+    fn move_by(&mut self, delta: i64) {
+        let target = if delta < 0 { self.cursor.saturating_sub(delta.unsigned_abs()) } else { self.cursor.saturating_add(delta as u64) };
+        self.move_to(target);
+    }
+
+    fn handle(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        match self.mode {
+            Mode::Normal => self.handle_normal(key.code),
+            Mode::Goto | Mode::Search => self.handle_prompt(key.code),
+        }
+    }
+
+    fn handle_normal(&mut self, code: KeyCode) {
+        let page = (self.rows * BYTES_PER_ROW) as i64;
+        match code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Left | KeyCode::Char('h') => self.move_by(-1),
+            KeyCode::Right | KeyCode::Char('l') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-(BYTES_PER_ROW as i64)),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(BYTES_PER_ROW as i64),
+            KeyCode::PageUp => self.move_by(-page),
+            KeyCode::PageDown => self.move_by(page),
+            KeyCode::Home => self.move_to(0),
+            KeyCode::End => self.move_to(u64::MAX),
+            KeyCode::Char('g') => self.prompt(Mode::Goto),
+            KeyCode::Char('/') => self.prompt(Mode::Search),
+            KeyCode::Char('n') => self.find_next(),
+            KeyCode::Char('s') => self.save(),
+            KeyCode::Char(c) if c.is_ascii_hexdigit() => self.type_nibble(c.to_digit(16).expect("a hex digit") as u8),
+            KeyCode::Esc => self.pending = None,
+            _ => {}
+        }
+    }
+
+    fn type_nibble(&mut self, nibble: u8) {
+        let high = match self.pending.take() {
+            Some(high) => high,
+            None => {
+                self.pending = Some(nibble);
+                return;
+            }
+        };
+        match self.source.write_at(self.cursor, &[high << 4 | nibble]) {
+            Ok(1) => {
+                self.modified = true;
+                self.move_by(1);
+            }
+            Ok(_) => self.status = format!("{:#x} is past the last whole word", self.cursor),
+            Err(e) => self.status = format!("write failed: {}", e),
+        }
+    }
+
+    fn prompt(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.input.clear();
+        self.pending = None;
+    }
+
+    fn handle_prompt(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Enter => {
+                let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+                let input = std::mem::take(&mut self.input);
+                if let Err(e) = match mode {
+                    Mode::Goto => self.goto(input.trim()),
+                    _ => self.search(&input),
+                } {
+                    self.status = e;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// A label name or a number; numbers at or above `--base` are virtual addresses.
+    fn goto(&mut self, input: &str) -> Result<(), String> {
+        let addr = match self.labels.get(input) {
+            Some(label) => label.range.start,
+            None => {
+                let addr = parse_number(input)?;
+                if self.base > 0 && addr >= self.base {
+                    addr - self.base
+                } else {
+                    addr
+                }
+            }
+        };
+        if addr >= self.source.len() {
+            return Err(format!("{:#x} is past the end", addr));
+        }
+        self.move_to(addr);
+        Ok(())
+    }
+
+    fn search(&mut self, input: &str) -> Result<(), String> {
+        let needle = match input.strip_prefix('"') {
+            Some(text) => text.as_bytes().to_vec(),
+            None => parse_hex(input)?,
+        };
+        if needle.is_empty() {
+            return Err("nothing to search for".to_string());
+        }
+        self.needle = Some(needle);
+        self.find_next();
+        Ok(())
+    }
+
+    // the next match of the last search after the cursor, wrapping around once
+    fn find_next(&mut self) {
+        let needle = match self.needle.clone() {
+            Some(needle) => needle,
+            None => return,
+        };
+        let len = self.source.len();
+        let found = match self.find_from(&needle, self.cursor + 1, len) {
+            Ok(None) => self.find_from(&needle, 0, (self.cursor + needle.len() as u64).min(len)),
+            found => found,
+        };
+        match found {
+            Ok(Some(addr)) => {
+                self.move_to(addr);
+                self.status = format!("found at {:#x}", addr);
+            }
+            Ok(None) => self.status = "not found".to_string(),
+            Err(e) => self.status = format!("search failed: {}", e),
+        }
+    }
+
+    fn find_from(&mut self, needle: &[u8], start: u64, end: u64) -> io::Result<Option<u64>> {
+        let finder = memchr::memmem::Finder::new(needle);
+        let mut chunk = vec![0u8; SEARCH_CHUNK + needle.len() - 1];
+        let mut addr = start;
+        while addr < end {
+            let want = chunk.len().min((end - addr) as usize);
+            let n = self.source.read_at(addr, &mut chunk[..want])?;
+            if n < needle.len() {
+                return Ok(None);
+            }
+            if let Some(offset) = finder.find(&chunk[..n]) {
+                return Ok(Some(addr + offset as u64));
+            }
+            addr += (n + 1 - needle.len()) as u64;
+        }
+        Ok(None)
+    }
+
+    fn save(&mut self) {
+        self.status = match &self.source {
+            Source::Dump { words, path } => match fs::write(path, words.backend()) {
+                Ok(()) => {
+                    self.modified = false;
+                    format!("saved {}", path.display())
+                }
+                Err(e) => format!("{}: {}", path.display(), e),
+            },
+            Source::Live(_) => "live edits are already written".to_string(),
+        };
+    }
+
+    fn row_line(&mut self, addr: u64) -> Line<'static> {
+        let mut bytes = [0u8; BYTES_PER_ROW as usize];
+        let n = self.source.read_at(addr, &mut bytes).unwrap_or(0);
+        let mut spans = vec![Span::raw(format!("{:08x}  ", self.base.wrapping_add(addr)))];
+        for (i, byte) in bytes.iter().enumerate() {
+            let at = addr + i as u64;
+            let text = if i < n { format!("{:02x}", byte) } else { "  ".to_string() };
+            let style = if at == self.cursor { Style::default().add_modifier(Modifier::REVERSED) } else { Style::default() };
+            spans.push(Span::styled(text, style));
+            spans.push(Span::raw(if i == 7 { "  " } else { " " }));
+        }
+        let ascii: String = bytes[..n].iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+        spans.push(Span::raw(format!(" |{}|", ascii)));
+        if let Some(label) = self.labels.starting_in(addr..addr + BYTES_PER_ROW).next() {
+            spans.push(Span::styled(format!("  {}", label.name), Style::default().add_modifier(Modifier::DIM)));
+        }
+        Line::from(spans)
+    }
+
+    fn status_line(&mut self) -> String {
+        match self.mode {
+            Mode::Goto => return format!("goto: {}", self.input),
+            Mode::Search => return format!("search (hex, or \"text): {}", self.input),
+            Mode::Normal => {}
+        }
+        let mut value = [0u8; 4];
+        let n = self.source.read_at(self.cursor, &mut value).unwrap_or(0);
+        let mut line = format!("{:#010x}", self.base.wrapping_add(self.cursor));
+        if let Some(name) = self.labels.describe(self.cursor) {
+            line += &format!(" {}", name);
+        }
+        if n >= 1 {
+            line += &format!("  u8 {}", value[0]);
+        }
+        if n >= 2 {
+            line += &format!("  u16 {:#06x}", u16::from_be_bytes([value[0], value[1]]));
+        }
+        if n == 4 {
+            line += &format!("  u32 {:#010x}", u32::from_be_bytes(value));
+        }
+        if self.modified {
+            line += "  [modified]";
+        }
+        if !self.status.is_empty() {
+            line += &format!("  {}", self.status);
+        }
+        line
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [view, status] = Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        self.rows = view.height.saturating_sub(2).max(1) as u64;
+        self.move_to(self.cursor);
+        let (top, len) = (self.top, self.source.len());
+        let lines: Vec<Line> = (0..self.rows)
+            .map(|row| top + row * BYTES_PER_ROW)
+            .take_while(|addr| *addr < len)
+            .map(|addr| self.row_line(addr))
+            .collect();
+        let title = match &self.source {
+            Source::Dump { path, .. } => format!(" {} ", path.display()),
+            Source::Live(_) => " live ".to_string(),
+        };
+        frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)), view);
+        frame.render_widget(Paragraph::new(self.status_line()), status);
+    }
+}
+
+fn run_app(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            app.status.clear();
+            app.handle(key);
+        }
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let open_error = |e: io::Error| format!("{}: {}", cli.input.display(), e);
+    let source = if cli.live {
+        let file = fs::OpenOptions::new().read(true).write(true).open(&cli.input).map_err(open_error)?;
+        Source::Live(ReversedBackend::new(FileBackend::new(file).map_err(open_error)?, cli.word_size).map_err(|e| e.to_string())?)
+    } else {
+        let data = fs::read(&cli.input).map_err(open_error)?;
+        Source::Dump { words: ReversedBackend::new(data, cli.word_size).map_err(|e| e.to_string())?, path: cli.input.clone() }
+    };
+    let labels = match &cli.symbols {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let map = AddressMap::new().with_segment(cli.base..cli.base.saturating_add(source.len()), 0);
+            Labels::from_symbols(&SymbolTable::from_map_file(&text).with_address_map(map), source.len())
+        }
+        None => Labels::new(),
+    };
+    let mut app = App::new(source, labels, cli.base);
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, &mut app);
+    ratatui::restore();
+    result.map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("revwords-tui: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::KeyModifiers;
+    use reversed_word_byte_rw::labels::Label;
+
+    use super::*;
+
+    fn app(data: Vec<u8>) -> App {
+        let words = ReversedBackend::new(data, 4).unwrap();
+        let mut labels = Labels::new();
+        labels.insert(Label::new("gPlayer", 0x20..0x30));
+        App::new(Source::Dump { words, path: PathBuf::from("unused") }, labels, 0x8000_0000)
+    }
+
+    fn keys(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            app.handle(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn edits_in_logical_order() {
+        let mut app = app(vec![0; 0x40]);
+        keys(&mut app, "l12ab");
+        assert_eq!(3, app.cursor);
+        assert!(app.modified);
+        let Source::Dump { words, .. } = &app.source else { unreachable!() };
+        assert_eq!(&[0, 0xAB, 0x12, 0][..], &words.backend()[..4]);
+    }
+
+    #[test]
+    fn goto_and_search() {
+        let mut words = ReversedBackend::new(vec![0u8; 0x40], 4).unwrap();
+        words.write_at(0x2A, b"hello").unwrap();
+        let mut app = app(words.into_inner());
+        keys(&mut app, "ggPlayer\n");
+        assert_eq!(0x20, app.cursor);
+        keys(&mut app, "g0x80000010\n");
+        assert_eq!(0x10, app.cursor);
+        keys(&mut app, "/\"llo\n");
+        assert_eq!(0x2C, app.cursor);
+        keys(&mut app, "/00 00\nn");
+        assert_eq!((0x30, Mode::Normal), (app.cursor, app.mode));
+        keys(&mut app, "g0x1000\n");
+        assert_eq!("0x1000 is past the end", app.status);
+        keys(&mut app, "ggPlayer\nl");
+        assert!(app.status_line().starts_with("0x80000021 gPlayer+0x1  u8 0"));
+        keys(&mut app, "q");
+        assert!(app.quit);
+    }
+}
@@ -0,0 +1,155 @@
+//! Command line front end for one-off jobs on word-swapped files.
+
+use std::{convert::TryFrom, fs, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+use reversed_word_byte_rw::{labels::hexdump, Endian, Primitive, ReversedWords};
+
+#[derive(Parser)]
+#[command(name = "revwords", version, about = "Convert, inspect, search and patch word-swapped dumps")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Rewrite a file stored with one word size as another (1 means unswapped)
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+        #[arg(long, default_value_t = 4)]
+        from: u8,
+        #[arg(long, default_value_t = 1)]
+        to: u8,
+    },
+    /// Print a hexdump in logical order
+    Hexdump {
+        input: PathBuf,
+        #[arg(short, long, default_value_t = 4)]
+        word_size: u8,
+        #[arg(long, default_value = "0", value_parser = parse_number)]
+        start: u64,
+        #[arg(long, value_parser = parse_number)]
+        length: Option<u64>,
+    },
+    /// Print the logical addresses where a value or byte pattern occurs
+    Search {
+        input: PathBuf,
+        #[arg(short, long, default_value_t = 4)]
+        word_size: u8,
+        /// Bytes to look for, e.g. "DE AD BE EF"
+        #[arg(long, conflicts_with_all = ["u16", "u32"])]
+        hex: Option<String>,
+        #[arg(long, value_parser = parse_number)]
+        u16: Option<u64>,
+        #[arg(long, value_parser = parse_number)]
+        u32: Option<u64>,
+        /// Encode --u16/--u32 as little endian instead of big endian
+        #[arg(long)]
+        little: bool,
+    },
+    /// Write bytes at a logical address
+    Patch {
+        input: PathBuf,
+        #[arg(short, long, default_value_t = 4)]
+        word_size: u8,
+        #[arg(long, value_parser = parse_number)]
+        at: u64,
+        /// Bytes to write, e.g. "DE AD BE EF"
+        #[arg(long)]
+        hex: String,
+        /// Where to write the patched file, defaults to patching in place
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn parse_number(s: &str) -> Result<u64, String> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|e| format!("invalid number {:?}: {}", s, e))
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>, String> {
+    reversed_word_byte_rw::parse_hex(s).map_err(|e| format!("{:?}: {}", s, e))
+}
+
+fn open(data: &mut [u8], word_size: u8) -> Result<ReversedWords<'_>, String> {
+    ReversedWords::try_new_with_word_size(data, word_size).map_err(|e| e.to_string())
+}
+
+fn read_file(path: &PathBuf) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_file(path: &PathBuf, data: &[u8]) -> Result<(), String> {
+    fs::write(path, data).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        Command::Convert { input, output, from, to } => {
+            let mut data = read_file(&input)?;
+            let mut logical = vec![0u8; data.len()];
+            let read = open(&mut data, from)?.read_at(0, &mut logical).map_err(|e| e.to_string())?;
+            let mut converted = vec![0u8; logical.len()];
+            let written = open(&mut converted, to)?.write_at(0, &logical).map_err(|e| e.to_string())?;
+            // a trailing partial word has no logical position, so it can't be converted
+            for (done, word_size) in [(read, from), (written, to)] {
+                if done < data.len() {
+                    return Err(format!("{} is {} bytes, not a whole number of {} byte words", input.display(), data.len(), word_size));
+                }
+            }
+            write_file(&output, &converted)
+        }
+        Command::Hexdump { input, word_size, start, length } => {
+            let mut data = read_file(&input)?;
+            let words = open(&mut data, word_size)?;
+            let end = length.map_or(words.len(), |length| start.saturating_add(length).min(words.len()));
+            print!("{}", hexdump(&words, start..end, None).map_err(|e| e.to_string())?);
+            Ok(())
+        }
+        Command::Search { input, word_size, hex, u16, u32, little } => {
+            let endian = if little { Endian::Little } else { Endian::Big };
+            let needle = match (hex, u16, u32) {
+                (Some(hex), _, _) => parse_hex(&hex)?,
+                (None, Some(value), _) => {
+                    let value = u16::try_from(value).map_err(|_| format!("{:#x} does not fit in a u16", value))?;
+                    value.to_bytes(endian).to_vec()
+                }
+                (None, None, Some(value)) => {
+                    let value = u32::try_from(value).map_err(|_| format!("{:#x} does not fit in a u32", value))?;
+                    value.to_bytes(endian).to_vec()
+                }
+                (None, None, None) => return Err("one of --hex, --u16 or --u32 is required".to_string()),
+            };
+            let mut data = read_file(&input)?;
+            for addr in open(&mut data, word_size)?.find_all(&needle) {
+                println!("{:#010x}", addr);
+            }
+            Ok(())
+        }
+        Command::Patch { input, word_size, at, hex, output } => {
+            let bytes = parse_hex(&hex)?;
+            let mut data = read_file(&input)?;
+            let written = open(&mut data, word_size)?.write_at(at, &bytes).map_err(|e| e.to_string())?;
+            if written < bytes.len() {
+                return Err(format!("patch at {:#x} runs past the end of the file", at));
+            }
+            write_file(output.as_ref().unwrap_or(&input), &data)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("revwords: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
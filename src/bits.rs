@@ -0,0 +1,84 @@
+//! Packed bitfields at logical addresses.
+//!
+//! Bits are numbered from the least significant bit of the byte at `addr` upwards, carrying on
+//! into the following logical bytes, so flag `n` of a flag array at `addr` is bit `n % 8` of byte
+//! `addr + n / 8`. Bits are read in that order into the low bits of the result.
+
+use crate::{ReversedWords, ReversedWordsError};
+
+fn check_width(width: u32) -> Result<(), ReversedWordsError> {
+    if width == 0 || width > 64 {
+        return Err(ReversedWordsError::InvalidBitWidth { width });
+    }
+    Ok(())
+}
+
+impl ReversedWords<'_> {
+    /// Read the `width` bit field starting `bit_offset` bits into the logical byte at `addr`.
+    pub fn read_bits(&self, addr: u64, bit_offset: u64, width: u32) -> std::io::Result<u64> {
+        check_width(width)?;
+        let mut value = 0u64;
+        for i in 0..width as u64 {
+            let bit = bit_offset + i;
+            let byte = self.get_byte(addr + bit / 8)?;
+            value |= (((byte >> (bit % 8)) & 1) as u64) << i;
+        }
+        Ok(value)
+    }
+
+    /// Write the low `width` bits of `value` to the field read by [`ReversedWords::read_bits`],
+    /// leaving the surrounding bits alone. Nothing is written if the field doesn't fit.
+    pub fn write_bits(&mut self, addr: u64, bit_offset: u64, width: u32, value: u64) -> std::io::Result<()> {
+        check_width(width)?;
+        let last = addr + (bit_offset + width as u64 - 1) / 8;
+        self.get_byte(last)?;
+        for i in 0..width as u64 {
+            let bit = bit_offset + i;
+            let byte_addr = addr + bit / 8;
+            let mask = 1u8 << (bit % 8);
+            let byte = self.get_byte(byte_addr)?;
+            let byte = if (value >> i) & 1 == 1 { byte | mask } else { byte & !mask };
+            self.set_byte(byte_addr, byte)?;
+        }
+        Ok(())
+    }
+
+    /// Flag `n` of the flag array at `addr`.
+    pub fn read_flag(&self, addr: u64, n: u64) -> std::io::Result<bool> {
+        Ok(self.read_bits(addr, n, 1)? == 1)
+    }
+
+    pub fn write_flag(&mut self, addr: u64, n: u64, set: bool) -> std::io::Result<()> {
+        self.write_bits(addr, n, 1, set as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn fields_cross_swapped_bytes() {
+        let mut data = vec![0u8; 8];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_bits(3, 4, 8, 0xAB).unwrap();
+        assert_eq!(0xB0, words.get_byte(3).unwrap());
+        assert_eq!(0x0A, words.get_byte(4).unwrap());
+        assert_eq!(0xAB, words.read_bits(3, 4, 8).unwrap());
+        assert_eq!(0xB, words.read_bits(3, 4, 4).unwrap());
+        assert_eq!([0xB0, 0, 0, 0, 0, 0, 0, 0x0A], data[..]);
+    }
+
+    #[test]
+    fn flags_and_bounds() {
+        let mut data = vec![0u8; 4];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_flag(0, 9, true).unwrap();
+        assert!(words.read_flag(0, 9).unwrap());
+        assert_eq!(0x02, words.get_byte(1).unwrap());
+        assert!(words.write_bits(3, 4, 8, 0xFF).is_err());
+        assert_eq!(0, words.get_byte(3).unwrap());
+        let error = words.read_bits(0, 0, 65).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::InvalidBitWidth { width: 65 }), ReversedWordsError::from_io(&error));
+    }
+}
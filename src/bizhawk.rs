@@ -0,0 +1,295 @@
+//! Memory domains of a BizHawk core, through its Lua socket interface.
+//!
+//! BizHawk connects out to a tool given `--socket_ip` and `--socket_port`, and a Lua script
+//! running in it answers requests. [`LUA_BRIDGE`] is that script: load it in the Lua console, then
+//! wrap the accepted connection in a [`BizHawkBridge`]. Messages are framed the way BizHawk
+//! 2.6.2 and later frame them, as the length in decimal, a space, then the message.
+//!
+//! Each domain (`"RDRAM"`, `"WRAM"`, `"System Bus"`, ...) is a [`MemoryBackend`] in storage
+//! order, so [`ReversedBackend`](crate::backend::ReversedBackend) and everything over it work on
+//! live memory of any system. [`BizHawkBridge::memory_map`] instead snapshots domains into the
+//! regions of a [`MemoryMap`] for tools built on one, and [`BizHawkBridge::write_region`] sends
+//! edits back.
+//!
+//! ```no_run
+//! # use reversed_word_byte_rw::{backend::ReversedBackend, bizhawk::BizHawkBridge, Endian};
+//! let (stream, _) = std::net::TcpListener::bind("127.0.0.1:9999")?.accept()?;
+//! let mut bridge = BizHawkBridge::new(stream)?;
+//! let mut rdram = ReversedBackend::new(bridge.domain("RDRAM")?, 4)?;
+//! let lives = rdram.read_value_at::<u8>(0x33B21D, Endian::Big)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::{Read, Write};
+
+use crate::{
+    backend::MemoryBackend,
+    memory_map::{MemoryMap, Region},
+    parse_hex, ReversedWordsError,
+};
+
+/// The script answering the bridge's requests inside BizHawk.
+pub const LUA_BRIDGE: &str = r#"-- answers requests from a reversed-word-byte-rw BizHawkBridge
+local function to_hex(bytes)
+  local parts = {}
+  for i = 1, #bytes do parts[i] = string.format("%02x", bytes[i]) end
+  return table.concat(parts)
+end
+
+local function from_hex(text)
+  local bytes = {}
+  for i = 1, #text, 2 do bytes[#bytes + 1] = tonumber(text:sub(i, i + 1), 16) end
+  return bytes
+end
+
+local function handle(request)
+  local op, rest = request:match("^(%u+) ?(.*)$")
+  if op == "DOMAINS" then
+    local domains = {}
+    for _, name in pairs(memory.getmemorydomainlist()) do
+      domains[#domains + 1] = name .. ":" .. memory.getmemorydomainsize(name)
+    end
+    return "OK " .. table.concat(domains, ",")
+  elseif op == "READ" then
+    local domain, addr, len = rest:match("^(.-) (%d+) (%d+)$")
+    return "OK " .. to_hex(memory.read_bytes_as_array(tonumber(addr), tonumber(len), domain))
+  elseif op == "WRITE" then
+    local domain, addr, data = rest:match("^(.-) (%d+) (%x*)$")
+    memory.write_bytes_as_array(tonumber(addr), from_hex(data), domain)
+    return "OK"
+  end
+  return "ERR unknown request " .. tostring(op)
+end
+
+while true do
+  local request = comm.socketServerResponse()
+  if request ~= nil and request ~= "" then
+    local ok, reply = pcall(handle, request)
+    comm.socketServerSend(ok and reply or ("ERR " .. tostring(reply)))
+  end
+  emu.yield()
+end
+"#;
+
+// the most bytes read or written per request
+const MAX_REQUEST: usize = 0x8000;
+// the longest message accepted, a read reply of `MAX_REQUEST` bytes in hex with room to spare
+const MAX_MESSAGE: usize = 4 * MAX_REQUEST;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Domain {
+    pub name: String,
+    pub size: u64,
+}
+
+/// A connection to [`LUA_BRIDGE`] running in BizHawk.
+pub struct BizHawkBridge<S> {
+    stream: S,
+    domains: Vec<Domain>,
+}
+
+fn invalid(reason: String) -> std::io::Error {
+    ReversedWordsError::InvalidBridgeMessage { reason }.into()
+}
+
+impl<S: Read + Write> BizHawkBridge<S> {
+    /// Start talking to the script over `stream`, asking it for the core's domains.
+    pub fn new(stream: S) -> std::io::Result<BizHawkBridge<S>> {
+        let mut bridge = BizHawkBridge { stream, domains: Vec::new() };
+        let reply = bridge.request("DOMAINS")?;
+        for domain in reply.split(',').filter(|domain| !domain.is_empty()) {
+            let (name, size) = domain.rsplit_once(':').ok_or_else(|| invalid(format!("{:?} is not a domain and size", domain)))?;
+            let size = size.parse().map_err(|_| invalid(format!("{:?} is not a domain size", size)))?;
+            bridge.domains.push(Domain { name: name.to_string(), size });
+        }
+        Ok(bridge)
+    }
+
+    pub fn domains(&self) -> &[Domain] {
+        &self.domains
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn find(&self, name: &str) -> Result<&Domain, ReversedWordsError> {
+        self.domains.iter().find(|domain| domain.name == name).ok_or_else(|| ReversedWordsError::UnknownDomain { name: name.to_string() })
+    }
+
+    // send `request` and return the text of the `OK` reply
+    fn request(&mut self, request: &str) -> std::io::Result<String> {
+        write!(self.stream, "{} {}", request.len(), request)?;
+        self.stream.flush()?;
+        let mut len = 0usize;
+        loop {
+            let mut byte = [0u8];
+            self.stream.read_exact(&mut byte)?;
+            match byte[0] {
+                b' ' => break,
+                digit @ b'0'..=b'9' => {
+                    len = len * 10 + (digit - b'0') as usize;
+                    if len > MAX_MESSAGE {
+                        return Err(invalid(format!("message longer than {} bytes", MAX_MESSAGE)));
+                    }
+                }
+                other => return Err(invalid(format!("{:?} in a message length", other as char))),
+            }
+        }
+        let mut message = vec![0u8; len];
+        self.stream.read_exact(&mut message)?;
+        let message = String::from_utf8(message).map_err(|_| invalid("message is not UTF-8".to_string()))?;
+        match message.split_once(' ').unwrap_or((&message, "")) {
+            ("OK", rest) => Ok(rest.to_string()),
+            ("ERR", reason) => Err(ReversedWordsError::BridgeFailed { reason: reason.to_string() }.into()),
+            _ => Err(invalid(format!("unexpected reply {:?}", message))),
+        }
+    }
+
+    /// Read the bytes of `domain` starting at `offset`, returning how many were in range.
+    pub fn read(&mut self, domain: &str, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = self.find(domain)?.size;
+        let n = (buf.len() as u64).min(size.saturating_sub(offset)) as usize;
+        let mut done = 0;
+        while done < n {
+            let count = (n - done).min(MAX_REQUEST);
+            let reply = self.request(&format!("READ {} {} {}", domain, offset + done as u64, count))?;
+            let bytes = parse_hex(&reply).map_err(|error| invalid(error.to_string()))?;
+            if bytes.len() != count {
+                return Err(invalid(format!("{} bytes read, {} asked for", bytes.len(), count)));
+            }
+            buf[done..done + count].copy_from_slice(&bytes);
+            done += count;
+        }
+        Ok(n)
+    }
+
+    /// Write bytes of `domain` starting at `offset`, returning how many were in range.
+    pub fn write(&mut self, domain: &str, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let size = self.find(domain)?.size;
+        let n = (buf.len() as u64).min(size.saturating_sub(offset)) as usize;
+        for (i, chunk) in buf[..n].chunks(MAX_REQUEST).enumerate() {
+            let mut request = format!("WRITE {} {} ", domain, offset + (i * MAX_REQUEST) as u64);
+            for byte in chunk {
+                request.push_str(&format!("{:02x}", byte));
+            }
+            self.request(&request)?;
+        }
+        Ok(n)
+    }
+
+    /// `domain` as a backend borrowing the connection.
+    pub fn domain(&mut self, name: &str) -> Result<BizHawkDomain<'_, S>, ReversedWordsError> {
+        let size = self.find(name)?.size;
+        Ok(BizHawkDomain { bridge: self, name: name.to_string(), size })
+    }
+
+    /// A snapshot of domains as regions of a map: each `(domain, base, word_size)` becomes a
+    /// region named after the domain at `base`.
+    pub fn memory_map(&mut self, layout: &[(&str, u64, u8)]) -> std::io::Result<MemoryMap> {
+        let mut map = MemoryMap::new();
+        for (name, base, word_size) in layout {
+            let mut data = vec![0u8; self.find(name)?.size as usize];
+            self.read(name, 0, &mut data)?;
+            map.add_region(Region::new(name, *base, *word_size, data)?)?;
+        }
+        Ok(map)
+    }
+
+    /// Write the whole of `region` back to the domain it is named after.
+    pub fn write_region(&mut self, region: &Region) -> std::io::Result<()> {
+        self.write(&region.name, 0, region.data())?;
+        Ok(())
+    }
+}
+
+/// One domain of a [`BizHawkBridge`].
+pub struct BizHawkDomain<'b, S> {
+    bridge: &'b mut BizHawkBridge<S>,
+    name: String,
+    size: u64,
+}
+
+impl<S: Read + Write> MemoryBackend for BizHawkDomain<'_, S> {
+    fn len(&self) -> u64 {
+        self.size
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.bridge.read(&self.name, offset, buf)
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        self.bridge.write(&self.name, offset, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, io::ErrorKind};
+
+    use crate::{backend::ReversedBackend, bizhawk::*, Endian};
+
+    // recorded requests and scripted replies, in place of BizHawk
+    #[derive(Default)]
+    struct Script {
+        sent: Vec<u8>,
+        replies: VecDeque<u8>,
+    }
+
+    impl Script {
+        fn reply(mut self, message: &str) -> Script {
+            self.replies.extend(format!("{} {}", message.len(), message).bytes());
+            self
+        }
+    }
+
+    impl Read for Script {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for Script {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn domains_as_backends() {
+        let script = Script::default().reply("OK RDRAM:8,System Bus:4294967296").reply("OK 03020100").reply("OK").reply("OK 0001020304050607");
+        let mut bridge = BizHawkBridge::new(script).unwrap();
+        assert_eq!(Domain { name: "System Bus".into(), size: 1 << 32 }, bridge.domains()[1]);
+        let mut rdram = ReversedBackend::new(bridge.domain("RDRAM").unwrap(), 4).unwrap();
+        assert_eq!(0x0001_0203, rdram.read_value_at::<u32>(0, Endian::Big).unwrap());
+        rdram.write_value_at(4, 0xA1A2_A3A4u32, Endian::Big).unwrap();
+        let mut map = bridge.memory_map(&[("RDRAM", 0x8000_0000, 4)]).unwrap();
+        assert_eq!([0, 1, 2, 3, 4, 5, 6, 7], map.region("RDRAM").unwrap().data()[..]);
+        assert_eq!(0x0302_0100, map.read_value_at::<u32>(0x8000_0000, Endian::Big).unwrap());
+        assert!(matches!(bridge.domain("VRAM").err(), Some(ReversedWordsError::UnknownDomain { .. })));
+        let sent = String::from_utf8(bridge.into_inner().sent).unwrap();
+        assert_eq!("7 DOMAINS14 READ RDRAM 0 422 WRITE RDRAM 4 a4a3a2a114 READ RDRAM 0 8", sent);
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        let script = Script::default().reply("OK RDRAM:8").reply("ERR domain is busy").reply("OK 00");
+        let mut bridge = BizHawkBridge::new(script).unwrap();
+        let error = bridge.read("RDRAM", 0, &mut [0u8; 4]).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::BridgeFailed { reason: "domain is busy".into() }), ReversedWordsError::from_io(&error));
+        let error = bridge.read("RDRAM", 0, &mut [0u8; 4]).unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+
+        let script = Script { sent: Vec::new(), replies: "99999999999 OK".bytes().collect() };
+        assert_eq!(ErrorKind::InvalidData, BizHawkBridge::new(script).err().unwrap().kind());
+    }
+}
@@ -0,0 +1,212 @@
+//! Views of storage reordered a whole block at a time.
+//!
+//! Word sizes are at most 255 bytes. Dumps of block storage instead reverse entire sectors (512
+//! bytes and up), store blocks out of order, or both; [`BlockWords`] handles those. Accesses are
+//! split at block boundaries and each piece is one slice copy (reversed in place if the bytes of
+//! a block are reversed), so large blocks cost no more than small ones.
+//!
+//! As with words, a trailing partial block can't be reached.
+
+use std::{
+    convert::TryInto,
+    io::{Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+use crate::ReversedWordsError;
+
+pub struct BlockWords<'a> {
+    data: &'a mut [u8],
+    block_size: usize,
+    reverse: bool,
+    // storage block of each logical block, if they are reordered
+    order: Option<Vec<usize>>,
+    position: u64,
+}
+
+impl<'a> BlockWords<'a> {
+    /// A view of `data` with the bytes of every `block_size` byte block reversed.
+    pub fn new(data: &'a mut [u8], block_size: usize) -> Result<BlockWords<'a>, ReversedWordsError> {
+        if block_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        Ok(BlockWords { data, block_size, reverse: true, order: None, position: 0 })
+    }
+
+    /// Whether the bytes within each block are reversed, on by default. Turn it off for storage
+    /// where only the order of the blocks changes.
+    pub fn with_byte_reversal(mut self, reverse: bool) -> BlockWords<'a> {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Logical block `i` is stored in storage block `order[i]`. `order` must name every whole
+    /// block of the data exactly once.
+    pub fn with_block_order(mut self, order: Vec<usize>) -> Result<BlockWords<'a>, ReversedWordsError> {
+        let blocks = self.data.len() / self.block_size;
+        let mut seen = vec![false; blocks];
+        if order.len() != blocks {
+            return Err(ReversedWordsError::InvalidBlockOrder { reason: format!("{} entries for {} blocks", order.len(), blocks) });
+        }
+        for block in &order {
+            match seen.get_mut(*block) {
+                Some(seen @ false) => *seen = true,
+                Some(true) => return Err(ReversedWordsError::InvalidBlockOrder { reason: format!("block {} appears twice", block) }),
+                None => return Err(ReversedWordsError::InvalidBlockOrder { reason: format!("there is no block {}", block) }),
+            }
+        }
+        self.order = Some(order);
+        Ok(self)
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// How many logical bytes can be reached: the whole blocks of the data.
+    pub fn len(&self) -> u64 {
+        (self.data.len() - self.data.len() % self.block_size) as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    // the pieces of `count` logical bytes from `addr`, one per block: the range of the caller's
+    // buffer and the storage range it maps to
+    fn pieces(&self, addr: u64, count: usize) -> Vec<(Range<usize>, Range<usize>)> {
+        let block_size = self.block_size;
+        let len = self.len();
+        let count = count.min(len.saturating_sub(addr) as usize);
+        let mut pieces = Vec::with_capacity(count / block_size + 2);
+        let mut done = 0;
+        while done < count {
+            let position: usize = (addr + done as u64).try_into().expect("below the data length");
+            let (block, offset) = (position / block_size, position % block_size);
+            let n = (block_size - offset).min(count - done);
+            let storage_block = self.order.as_ref().map_or(block, |order| order[block]) * block_size;
+            let storage = if self.reverse {
+                storage_block + block_size - offset - n..storage_block + block_size - offset
+            } else {
+                storage_block + offset..storage_block + offset + n
+            };
+            pieces.push((done..done + n, storage));
+            done += n;
+        }
+        pieces
+    }
+
+    /// Read the logical bytes starting at `addr` without moving the cursor, returning how many
+    /// were in range.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> usize {
+        let pieces = self.pieces(addr, buf.len());
+        for (target, storage) in &pieces {
+            let target = &mut buf[target.clone()];
+            target.copy_from_slice(&self.data[storage.clone()]);
+            if self.reverse {
+                target.reverse();
+            }
+        }
+        pieces.last().map_or(0, |(target, _)| target.end)
+    }
+
+    /// Write logical bytes starting at `addr` without moving the cursor, returning how many
+    /// were in range.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> usize {
+        let pieces = self.pieces(addr, buf.len());
+        for (source, storage) in &pieces {
+            let storage = &mut self.data[storage.clone()];
+            storage.copy_from_slice(&buf[source.clone()]);
+            if self.reverse {
+                storage.reverse();
+            }
+        }
+        pieces.last().map_or(0, |(source, _)| source.end)
+    }
+}
+
+impl Read for BlockWords<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.read_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for BlockWords<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.write_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for BlockWords<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.len() as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.position as i128 + offset as i128,
+        };
+        if target < 0 || target > u64::MAX as i128 {
+            return Err(ReversedWordsError::SeekOutOfRange { target, len: self.len() }.into());
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block::*, ReversedWords};
+
+    #[test]
+    fn matches_word_views() {
+        let mut expected: Vec<u8> = (0..30).collect();
+        let mut data = expected.clone();
+        let mut blocks = BlockWords::new(&mut data, 4).unwrap();
+        let mut words = ReversedWords::new(&mut expected);
+        for (addr, bytes) in [(1, &[0xA0, 0xA1][..]), (3, &[0xB0, 0xB1, 0xB2, 0xB3, 0xB4, 0xB5][..]), (26, &[0xC0, 0xC1, 0xC2, 0xC3][..])] {
+            assert_eq!(words.write_at(addr, bytes).unwrap(), blocks.write_at(addr, bytes));
+        }
+        let (mut out, mut words_out) = ([0u8; 30], [0u8; 30]);
+        assert_eq!(28, blocks.read(&mut out).unwrap());
+        assert_eq!(28, words.read_at(0, &mut words_out).unwrap());
+        assert_eq!(words_out, out);
+        assert_eq!(0, blocks.read(&mut out).unwrap());
+        assert_eq!(expected, data);
+    }
+
+    #[test]
+    fn sectors_and_block_order() {
+        let mut disk: Vec<u8> = (0..2048u32).map(|i| (i / 512 * 16 + i % 512 / 64) as u8).collect();
+        let sectors = BlockWords::new(&mut disk, 512).unwrap();
+        let mut out = [0u8; 4];
+        // the last byte of sector 1 comes first
+        assert_eq!(4, sectors.read_at(512, &mut out));
+        assert_eq!([0x17; 4], out);
+        assert_eq!(2, sectors.read_at(1023, &mut out[..2]));
+        assert_eq!([0x10, 0x27], out[..2]);
+
+        let mut shuffled = BlockWords::new(&mut disk, 512).unwrap().with_byte_reversal(false).with_block_order(vec![3, 0, 2, 1]).unwrap();
+        assert_eq!(1, shuffled.read_at(0, &mut out[..1]));
+        assert_eq!(0x30, out[0]);
+        shuffled.seek(SeekFrom::Start(1535)).unwrap();
+        shuffled.write_all(&[0xEE, 0xFF]).unwrap();
+        assert_eq!([0xEE, 0xFF], [disk[1535], disk[512]]);
+
+        let mut data = [0u8; 8];
+        assert!(BlockWords::new(&mut data, 0).is_err());
+        assert!(BlockWords::new(&mut data, 4).unwrap().with_block_order(vec![0, 0]).is_err());
+        assert!(BlockWords::new(&mut data, 4).unwrap().with_block_order(vec![0, 2]).is_err());
+        assert!(BlockWords::new(&mut data, 4).unwrap().with_block_order(vec![1]).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! Bulk operations over ranges of logical addresses which work in place, without scratch buffers.
+
+use std::ops::Range;
+use binread::Endian;
+
+use crate::{Access, Primitive, ReversedWords, ReversedWordsError};
+
+impl ReversedWords<'_> {
+    /// Fail with `ErrorKind::InvalidInput` unless `range` lies within the data.
+    pub(crate) fn check_range(&self, range: &Range<u64>) -> std::io::Result<()> {
+        if range.start > range.end || range.end > self.len {
+            return Err(ReversedWordsError::RangeOutOfRange { start: range.start, end: range.end, len: self.len }.into());
+        }
+        Ok(())
+    }
+
+    /// Set every logical byte in `range` to `byte`.
+    pub fn fill(&mut self, range: Range<u64>, byte: u8) -> std::io::Result<()> {
+        self.check_range(&range)?;
+        self.check_alignment(range.start, Access::Write)?;
+        for position in range {
+            if let Some(index) = self.storage_index(position) {
+                self.store(index, byte);
+            }
+        }
+        Ok(())
+    }
+
+    /// Repeat `word` across `range` in the given endianness, truncating the last copy if
+    /// the range isn't a multiple of its size.
+    pub fn fill_word<T: Primitive>(&mut self, range: Range<u64>, word: T, endian: Endian) -> std::io::Result<()> {
+        self.check_range(&range)?;
+        self.check_alignment(range.start, Access::Write)?;
+        let bytes = word.to_bytes(endian);
+        let pattern = bytes.as_ref();
+        for (position, byte) in range.clone().zip(pattern.iter().cycle()) {
+            if let Some(index) = self.storage_index(position) {
+                self.store(index, *byte);
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the logical bytes in `src` to start at `dst`, like `slice::copy_within`.
+    /// Overlapping ranges are handled as if the source were copied out first.
+    pub fn copy_within(&mut self, src: Range<u64>, dst: u64) -> std::io::Result<()> {
+        self.check_range(&src)?;
+        let count = src.end - src.start;
+        self.check_range(&(dst..dst.saturating_add(count)))?;
+        let mut copy_byte = |offset: u64| {
+            if let (Some(from), Some(to)) = (self.storage_index(src.start + offset), self.storage_index(dst + offset)) {
+                let storage = self.cursor.get_mut();
+                storage[to] = storage[from];
+            }
+        };
+        if dst > src.start {
+            // copy from the end so bytes aren't overwritten before they are read
+            (0..count).rev().for_each(&mut copy_byte);
+        } else {
+            (0..count).for_each(&mut copy_byte);
+        }
+        Ok(())
+    }
+
+    /// Whether the logical bytes starting at `addr` are exactly `expected`.
+    pub fn eq_at(&self, addr: u64, expected: &[u8]) -> bool {
+        self.compare(addr..addr.saturating_add(expected.len() as u64), expected).is_none()
+    }
+
+    /// Compare the logical bytes in `range` to `expected`, returning the offset of the first
+    /// difference. Bytes outside of the data, or a length difference, count as a difference.
+    pub fn compare(&self, range: Range<u64>, expected: &[u8]) -> Option<usize> {
+        let count = range.end.saturating_sub(range.start);
+        for offset in 0..count.min(expected.len() as u64) {
+            match self.storage_index(range.start + offset) {
+                Some(index) if self.load(index) == expected[offset as usize] => {}
+                _ => return Some(offset as usize),
+            }
+        }
+        if count != expected.len() as u64 {
+            return Some(count.min(expected.len() as u64) as usize);
+        }
+        None
+    }
+}
+
+/// Copy the logical bytes in `src_range` of `src` to start at `dst_addr` in `dst`, like a DMA
+/// between memories with different word sizes or nibble swapping. Each byte is unswapped and
+/// reswapped in one pass with no intermediate buffer, and when both views store words the same
+/// way and the range starts at the same offset within a word in each, the whole words in the
+/// middle are copied as one slice. Fails without copying anything unless both ranges are
+/// reachable.
+pub fn transfer(src: &ReversedWords, src_range: Range<u64>, dst: &mut ReversedWords, dst_addr: u64) -> std::io::Result<()> {
+    let unreachable = |words: &ReversedWords, range: &Range<u64>| ReversedWordsError::RangeOutOfRange { start: range.start, end: range.end, len: words.len };
+    if src_range.start > src_range.end || src_range.end > src.reachable_len() {
+        return Err(unreachable(src, &src_range).into());
+    }
+    let count = src_range.end - src_range.start;
+    let dst_range = dst_addr..dst_addr.saturating_add(count);
+    if dst_range.end - dst_range.start < count || dst_range.end > dst.reachable_len() {
+        return Err(unreachable(dst, &dst_range).into());
+    }
+    dst.check_alignment(dst_addr, Access::Write)?;
+
+    let word_size = src.word_size as u64;
+    let copy_bytes = |dst: &mut ReversedWords, offsets: Range<u64>| {
+        for offset in offsets {
+            let from = src.storage_index(src_range.start + offset).expect("reachable");
+            let to = dst.storage_index(dst_addr + offset).expect("reachable");
+            dst.store(to, src.load(from));
+        }
+    };
+    let same_layout = src.word_size == dst.word_size && src.nibble_swap == dst.nibble_swap && src.bit_reversal == dst.bit_reversal;
+    if !same_layout || src_range.start % word_size != dst_addr % word_size {
+        copy_bytes(dst, 0..count);
+        return Ok(());
+    }
+    // whole words keep their storage order, so a run of them is one slice in both views
+    let head = ((word_size - src_range.start % word_size) % word_size).min(count);
+    let words = (count - head) / word_size * word_size;
+    copy_bytes(dst, 0..head);
+    let (from, to) = ((src_range.start + head) as usize, (dst_addr + head) as usize);
+    dst.cursor.get_mut()[to..to + words as usize].copy_from_slice(&src.cursor.get_ref()[from..from + words as usize]);
+    copy_bytes(dst, head + words..count);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn fill_range() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.fill(2..5, 0xFF).unwrap();
+        assert_eq!(vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 0xFF], data);
+    }
+
+    #[test]
+    fn fill_word_pattern() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.fill_word(0..6, 0xDEADu16, Endian::Big).unwrap();
+        let mut out = [0u8; 8];
+        ram.read_at(0, &mut out).unwrap();
+        assert_eq!([0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0, 0], out);
+    }
+
+    #[test]
+    fn copy_within_overlapping() {
+        let source: Vec<u8> = (0..12).collect();
+        let mut data = vec![0u8; 12];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.write_at(0, &source).unwrap();
+
+        ram.copy_within(1..7, 3).unwrap();
+        let mut out = [0u8; 12];
+        ram.read_at(0, &mut out).unwrap();
+        assert_eq!([0, 1, 2, 1, 2, 3, 4, 5, 6, 9, 10, 11], out);
+
+        ram.copy_within(3..9, 0).unwrap();
+        ram.read_at(0, &mut out).unwrap();
+        assert_eq!([1, 2, 3, 4, 5, 6, 4, 5, 6, 9, 10, 11], out);
+    }
+
+    #[test]
+    fn copy_within_out_of_range_fails() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data);
+        assert!(ram.copy_within(0..4, 6).is_err());
+        assert!(ram.copy_within(6..10, 0).is_err());
+    }
+
+    #[test]
+    fn compare_logical_contents() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let ram = ReversedWords::new(&mut data);
+        assert!(ram.eq_at(2, &[1, 0, 7]));
+        assert!(!ram.eq_at(2, &[1, 0, 6]));
+        assert!(!ram.eq_at(7, &[4, 0]));
+        assert_eq!(None, ram.compare(0..4, &[3, 2, 1, 0]));
+        assert_eq!(Some(2), ram.compare(0..4, &[3, 2, 0, 0]));
+        assert_eq!(Some(2), ram.compare(0..2, &[3, 2, 1]));
+    }
+
+    #[test]
+    fn fill_out_of_range_fails() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data);
+        assert!(ram.fill(4..9, 1).is_err());
+        assert_eq!(vec![0u8; 8], data);
+    }
+
+    #[test]
+    fn transfer_between_layouts() {
+        let logical: Vec<u8> = (0..32).collect();
+        let mut source = vec![0u8; 32];
+        let mut src = ReversedWords::new(&mut source);
+        src.write_at(0, &logical).unwrap();
+
+        // same layout and word offset: the middle is one slice copy
+        let mut same = vec![0u8; 32];
+        let mut dst = ReversedWords::new(&mut same);
+        transfer(&src, 3..25, &mut dst, 7).unwrap();
+        let mut out = [0u8; 22];
+        dst.read_at(7, &mut out).unwrap();
+        assert_eq!(logical[3..25], out);
+
+        let mut other = vec![0u8; 40];
+        let mut dst = ReversedWords::new_with_word_size(&mut other, 8).with_nibble_swap(true);
+        transfer(&src, 1..31, &mut dst, 5).unwrap();
+        let mut out = [0u8; 30];
+        dst.read_at(5, &mut out).unwrap();
+        assert_eq!(logical[1..31], out);
+        assert!(transfer(&src, 0..8, &mut dst, 36).is_err());
+        assert!(transfer(&src, 30..33, &mut dst, 0).is_err());
+        assert!(transfer(&src, 0..0, &mut dst, 40).is_ok());
+        // logical byte 5 holds 1, nibble swapped and stored third
+        assert_eq!(0x10, other[2]);
+    }
+}
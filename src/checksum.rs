@@ -0,0 +1,125 @@
+//! Checksums that can be updated for a small edit without rescanning the data.
+//!
+//! Save data usually guards a block with a checksum field that the game verifies on load.
+//! [`MemoryMap::track_checksum`](crate::memory_map::MemoryMap::track_checksum) keeps one of these
+//! current as writes go through the map, using [`Checksum::update`] so an edit costs time in the
+//! size of the edit rather than the block.
+
+use std::fmt::Debug;
+
+/// A 32-bit checksum over a block of logical bytes.
+pub trait Checksum: Debug + Send + Sync {
+    fn checksum(&self, data: &[u8]) -> u32;
+
+    /// The checksum of a `len` byte block after the bytes at `offset` changed from `old` to `new`,
+    /// given its previous checksum `sum`. `None` (the default) means the block has to be scanned
+    /// again.
+    fn update(&self, sum: u32, len: u64, offset: u64, old: &[u8], new: &[u8]) -> Option<u32> {
+        let _ = (sum, len, offset, old, new);
+        None
+    }
+}
+
+const CRC32_POLY: u32 = 0xEDB8_8320;
+
+/// CRC-32 as used by zlib, PNG and most save formats (reflected polynomial `0xEDB88320`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Crc32;
+
+// run the CRC register over `data` without pre or post conditioning
+fn crc32_raw(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC32_POLY } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+// a * b modulo the polynomial, in the reflected bit order where 1 << 31 is x^0
+fn mult_mod_p(a: u32, mut b: u32) -> u32 {
+    let mut product = 0;
+    for bit in (0..32).rev() {
+        if a & (1 << bit) != 0 {
+            product ^= b;
+        }
+        b = if b & 1 == 1 { (b >> 1) ^ CRC32_POLY } else { b >> 1 };
+    }
+    product
+}
+
+// x^(8 * n), which multiplied into the register is the same as running it over n zero bytes
+fn x8n_mod_p(mut n: u64) -> u32 {
+    let mut power = 1 << 30; // x^1
+    for _ in 0..3 {
+        power = mult_mod_p(power, power);
+    }
+    let mut result = 1 << 31;
+    while n != 0 {
+        if n & 1 == 1 {
+            result = mult_mod_p(power, result);
+        }
+        power = mult_mod_p(power, power);
+        n >>= 1;
+    }
+    result
+}
+
+impl Checksum for Crc32 {
+    fn checksum(&self, data: &[u8]) -> u32 {
+        !crc32_raw(!0, data)
+    }
+
+    /// The CRC is linear: flipping bits changes it by the unconditioned CRC of the flipped bits,
+    /// which only needs to be shifted past the bytes after the edit.
+    fn update(&self, sum: u32, len: u64, offset: u64, old: &[u8], new: &[u8]) -> Option<u32> {
+        let diff: Vec<u8> = old.iter().zip(new).map(|(old, new)| old ^ new).collect();
+        let tail = len.checked_sub(offset + diff.len() as u64)?;
+        Some(sum ^ mult_mod_p(x8n_mod_p(tail), crc32_raw(0, &diff)))
+    }
+}
+
+/// The wrapping sum of all bytes, the simplest and still a common save checksum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ByteSum;
+
+fn byte_sum(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |sum, byte| sum.wrapping_add(*byte as u32))
+}
+
+impl Checksum for ByteSum {
+    fn checksum(&self, data: &[u8]) -> u32 {
+        byte_sum(data)
+    }
+
+    fn update(&self, sum: u32, _len: u64, _offset: u64, old: &[u8], new: &[u8]) -> Option<u32> {
+        Some(sum.wrapping_sub(byte_sum(old)).wrapping_add(byte_sum(new)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::*;
+
+    #[test]
+    fn crc32_updates_match_rescans() {
+        assert_eq!(0xCBF4_3926, Crc32.checksum(b"123456789"));
+        let mut data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut sum = Crc32.checksum(&data);
+        for (offset, new) in [(0usize, &[0xFF][..]), (500, &[1, 2, 3, 4]), (996, &[9, 9, 9, 9]), (10, &[])] {
+            let old = data[offset..offset + new.len()].to_vec();
+            data[offset..offset + new.len()].copy_from_slice(new);
+            sum = Crc32.update(sum, data.len() as u64, offset as u64, &old, new).unwrap();
+            assert_eq!(Crc32.checksum(&data), sum);
+        }
+        assert_eq!(None, Crc32.update(sum, 4, 2, &[0; 4], &[1; 4]));
+    }
+
+    #[test]
+    fn byte_sum_updates() {
+        let sum = ByteSum.checksum(&[0xFF; 4]);
+        assert_eq!(0x3FC, sum);
+        assert_eq!(Some(0x3FC - 0xFF + 1), ByteSum.update(sum, 4, 1, &[0xFF], &[1]));
+    }
+}
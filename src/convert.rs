@@ -0,0 +1,107 @@
+//! Standard conversions into views.
+//!
+//! `&mut ReversedWords` is already `Read + Write + Seek` through the standard library's blanket
+//! impls for `&mut R`, so a view can be handed by value to APIs that take a reader or writer
+//! (`io::copy`, `BinRead::read`, ...) with `&mut words` and used again afterwards.
+
+use std::{convert::TryFrom, io::Cursor};
+
+use crate::{ReversedVec, ReversedWords, ReversedWordsError};
+
+impl<'a> From<&'a mut [u8]> for ReversedWords<'a> {
+    fn from(ram: &'a mut [u8]) -> Self {
+        ReversedWords::new(ram)
+    }
+}
+
+impl<'a> From<&'a mut Vec<u8>> for ReversedWords<'a> {
+    fn from(ram: &'a mut Vec<u8>) -> Self {
+        ReversedWords::new(ram)
+    }
+}
+
+/// Keeps the cursor's position.
+impl<'a> From<Cursor<&'a mut [u8]>> for ReversedWords<'a> {
+    fn from(cursor: Cursor<&'a mut [u8]>) -> Self {
+        let position = cursor.position();
+        let mut words = ReversedWords::new(cursor.into_inner());
+        words.cursor.set_position(position);
+        words
+    }
+}
+
+/// A buffer and a word size.
+impl<'a> TryFrom<(&'a mut [u8], u8)> for ReversedWords<'a> {
+    type Error = ReversedWordsError;
+
+    fn try_from((ram, word_size): (&'a mut [u8], u8)) -> Result<Self, Self::Error> {
+        ReversedWords::try_new_with_word_size(ram, word_size)
+    }
+}
+
+impl<'a> ReversedWords<'a> {
+    /// Give the underlying buffer back.
+    pub fn into_inner(self) -> &'a mut [u8] {
+        self.cursor.into_inner()
+    }
+}
+
+impl From<Vec<u8>> for ReversedVec {
+    fn from(data: Vec<u8>) -> Self {
+        ReversedVec::new(data)
+    }
+}
+
+impl TryFrom<(Vec<u8>, u8)> for ReversedVec {
+    type Error = ReversedWordsError;
+
+    fn try_from((data, word_size): (Vec<u8>, u8)) -> Result<Self, Self::Error> {
+        ReversedVec::try_new_with_word_size(data, word_size)
+    }
+}
+
+impl From<ReversedVec> for Vec<u8> {
+    fn from(words: ReversedVec) -> Self {
+        words.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::TryInto, io::{Read, Seek, SeekFrom}};
+
+    use crate::*;
+
+    fn read_two<R: Read>(mut reader: R) -> [u8; 2] {
+        let mut out = [0u8; 2];
+        reader.read_exact(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn by_reference_readers_keep_the_view() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut words = ReversedWords::from(&mut data);
+        assert_eq!([3, 2], read_two(&mut words));
+        assert_eq!([1, 0], read_two(&mut words));
+        assert_eq!(4, words.stream_position().unwrap());
+    }
+
+    #[test]
+    fn conversions() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut cursor = Cursor::new(&mut data[..]);
+        cursor.seek(SeekFrom::Start(4)).unwrap();
+        let mut words = ReversedWords::from(cursor);
+        assert_eq!([7, 6], read_two(&mut words));
+        assert_eq!(8, words.into_inner().len());
+
+        let words: ReversedWords = (&mut data[..], 2).try_into().unwrap();
+        assert_eq!(2, words.word_size());
+        let result: Result<ReversedWords, _> = (&mut data[..], 0).try_into();
+        assert_eq!(Some(ReversedWordsError::InvalidWordSize), result.err());
+
+        let owned: ReversedVec = (vec![0, 1], 2).try_into().unwrap();
+        assert_eq!(vec![0, 1], Vec::from(owned));
+    }
+}
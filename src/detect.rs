@@ -0,0 +1,131 @@
+//! Guessing whether a dump is byteswapped.
+//!
+//! [`detect_swap_mode`] reads a dump as each candidate word size and scores how natural the result
+//! looks for a big endian target: known header magics decide outright, otherwise long runs of
+//! ASCII text and 32-bit values that look like KSEG0/MEM1 style pointers (`0x80xxxxxx`) count
+//! towards a candidate. This is a heuristic; zero filled or compressed data gives low confidence.
+
+use crate::storage_index;
+
+/// Word sizes tried by [`detect_swap_mode`].
+pub const CANDIDATE_WORD_SIZES: [u8; 4] = [1, 2, 4, 8];
+
+// how much of the dump is looked at
+const SAMPLE_LEN: usize = 1 << 16;
+const MIN_TEXT_RUN: usize = 4;
+
+// (magic in storage order, word size that reads it correctly)
+const MAGICS: [(&[u8], u8); 4] = [
+    // N64 ROM headers: .z64, .v64 and .n64
+    (&[0x80, 0x37, 0x12, 0x40], 1),
+    (&[0x37, 0x80, 0x40, 0x12], 2),
+    (&[0x40, 0x12, 0x37, 0x80], 4),
+    // ELF, swapped by 4
+    (&[b'F', b'L', b'E', 0x7F], 4),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Detection {
+    /// The most likely word size, 1 meaning not swapped.
+    pub word_size: u8,
+    /// The guess's share of the total score, from 0 (no signal) to 1 (only this candidate
+    /// looked plausible). 4 and 8 byte swaps keep aligned 32-bit values intact, so they tend to
+    /// score similarly.
+    pub confidence: f64,
+    /// Score of every candidate in [`CANDIDATE_WORD_SIZES`] order.
+    pub scores: Vec<(u8, f64)>,
+}
+
+fn logical_sample(data: &[u8], word_size: u8) -> Vec<u8> {
+    let len = data.len().min(SAMPLE_LEN) as u64;
+    (0..len).map_while(|position| storage_index(position, word_size, len).map(|index| data[index])).collect()
+}
+
+// frequent English letter pairs, which mostly turn into rare ones when text is swapped
+const COMMON_BIGRAMS: [&[u8; 2]; 30] = [
+    b"th", b"he", b"in", b"er", b"an", b"re", b"on", b"at", b"en", b"nd", b"ti", b"es", b"or", b"te", b"of",
+    b"ed", b"is", b"it", b"al", b"ar", b"st", b"to", b"nt", b"ng", b"se", b"ha", b"as", b"ou", b"io", b"le",
+];
+
+// fraction of bytes in runs of at least MIN_TEXT_RUN printable characters, weighted by how many
+// of the letter pairs in those runs are common in English
+fn text_score(sample: &[u8]) -> f64 {
+    let mut in_runs = 0;
+    let (mut pairs, mut common) = (0, 0);
+    for run in sample.split(|byte| !(byte.is_ascii_graphic() || *byte == b' ')) {
+        if run.len() < MIN_TEXT_RUN {
+            continue;
+        }
+        in_runs += run.len();
+        for pair in run.windows(2).filter(|pair| pair.iter().all(u8::is_ascii_alphabetic)) {
+            pairs += 1;
+            let pair = [pair[0].to_ascii_lowercase(), pair[1].to_ascii_lowercase()];
+            if COMMON_BIGRAMS.contains(&&pair) {
+                common += 1;
+            }
+        }
+    }
+    let bigram_ratio = common as f64 / pairs.max(1) as f64;
+    in_runs as f64 / sample.len().max(1) as f64 * bigram_ratio
+}
+
+// fraction of aligned big endian u32s that look like 0x80xxxxxx pointers into the first 8 MiB
+fn pointer_score(sample: &[u8]) -> f64 {
+    let words = sample.chunks_exact(4);
+    let count = words.len();
+    let pointers = words.filter(|word| word[0] == 0x80 && word[1] < 0x80).count();
+    pointers as f64 / count.max(1) as f64
+}
+
+/// Guess the word size `data` is swapped by.
+pub fn detect_swap_mode(data: &[u8]) -> Detection {
+    for (magic, word_size) in MAGICS {
+        if data.starts_with(magic) {
+            let scores = CANDIDATE_WORD_SIZES.iter().map(|&size| (size, if size == word_size { 1.0 } else { 0.0 })).collect();
+            return Detection { word_size, confidence: 1.0, scores };
+        }
+    }
+    let scores: Vec<(u8, f64)> = CANDIDATE_WORD_SIZES
+        .iter()
+        .map(|&word_size| {
+            let sample = logical_sample(data, word_size);
+            (word_size, text_score(&sample) + pointer_score(&sample))
+        })
+        .collect();
+    let (word_size, best) = scores.iter().copied().fold((1, 0.0), |best, score| if score.1 > best.1 { score } else { best });
+    let total: f64 = scores.iter().map(|(_, score)| score).sum();
+    let confidence = if total > 0.0 { best / total } else { 0.0 };
+    Detection { word_size, confidence, scores }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{detect::*, ReversedWords};
+
+    fn swapped(logical: &[u8], word_size: u8) -> Vec<u8> {
+        let mut storage = vec![0u8; logical.len()];
+        ReversedWords::new_with_word_size(&mut storage, word_size).write_at(0, logical).unwrap();
+        storage
+    }
+
+    #[test]
+    fn magics_decide() {
+        let detection = detect_swap_mode(&[0x37, 0x80, 0x40, 0x12, 0, 0, 0, 0]);
+        assert_eq!(2, detection.word_size);
+        assert_eq!(1.0, detection.confidence);
+    }
+
+    #[test]
+    fn text_and_pointers() {
+        let mut logical = b"THE LEGEND OF ZELDA save file, player name LINK. ".repeat(4);
+        for _ in 0..16 {
+            logical.extend_from_slice(&[0x80, 0x12, 0x34, 0x50]);
+        }
+        for word_size in [1, 2, 4, 8] {
+            let detection = detect_swap_mode(&swapped(&logical, word_size));
+            assert_eq!(word_size, detection.word_size);
+            assert!(detection.confidence > 0.3, "{:?}", detection);
+        }
+        assert_eq!(0.0, detect_swap_mode(&[0u8; 64]).confidence);
+    }
+}
@@ -0,0 +1,183 @@
+//! Dirty page tracking, and hashing that only revisits what changed.
+//!
+//! [`DirtyWords`] wraps a view, marks the storage pages its writes touch and keeps a digest of
+//! the whole buffer that [`DirtyWords::hash_incremental`] brings up to date by rehashing only the
+//! dirty pages. The digest is an XOR of per-page hashes keyed by page index, so swapping a page's
+//! contribution is constant time no matter how large the buffer is.
+//!
+//! Hashes are FNV-1a over storage bytes: stable across runs and platforms, but not collision
+//! resistant against an adversary.
+
+use std::ops::{Deref, Range};
+
+use binread::Endian;
+
+use crate::{Primitive, ReversedWords};
+
+pub const DEFAULT_PAGE_SIZE: u64 = 0x1000;
+
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x100_0000_01B3;
+
+fn fnv1a(mut hash: u64, data: &[u8]) -> u64 {
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+// a page's contribution to the digest
+fn page_digest(index: usize, data: &[u8]) -> u64 {
+    fnv1a(fnv1a(FNV_OFFSET, &(index as u64).to_le_bytes()), data)
+}
+
+/// The digest [`DirtyWords::hash_incremental`] maintains, computed from scratch over `storage`.
+pub fn hash_pages(storage: &[u8], page_size: u64) -> u64 {
+    storage.chunks(page_size.max(1) as usize).enumerate().fold(0, |digest, (index, page)| digest ^ page_digest(index, page))
+}
+
+/// A set of dirty pages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirtyPages {
+    page_size: u64,
+    dirty: Vec<bool>,
+}
+
+impl DirtyPages {
+    /// Pages of `page_size` bytes (at least 1) covering `len` bytes, all dirty.
+    pub fn new(len: u64, page_size: u64) -> DirtyPages {
+        let page_size = page_size.max(1);
+        DirtyPages { page_size, dirty: vec![true; len.div_ceil(page_size) as usize] }
+    }
+
+    pub fn page_size(&self) -> u64 {
+        self.page_size
+    }
+
+    /// Mark the pages overlapping storage `range`.
+    pub fn mark(&mut self, range: Range<u64>) {
+        if range.start >= range.end {
+            return;
+        }
+        let pages = (range.start / self.page_size) as usize..range.end.div_ceil(self.page_size) as usize;
+        let end = pages.end.min(self.dirty.len());
+        self.dirty[pages.start.min(end)..end].iter_mut().for_each(|dirty| *dirty = true);
+    }
+
+    pub fn is_dirty(&self, page: usize) -> bool {
+        self.dirty.get(page).copied().unwrap_or(false)
+    }
+
+    /// Indices of the dirty pages, in order.
+    pub fn dirty_pages(&self) -> impl Iterator<Item = usize> + '_ {
+        self.dirty.iter().enumerate().filter(|(_, dirty)| **dirty).map(|(page, _)| page)
+    }
+
+    pub fn clear(&mut self) {
+        self.dirty.iter_mut().for_each(|dirty| *dirty = false);
+    }
+}
+
+/// A view that tracks which pages its writes dirty. Reads go through `Deref`, writes through the
+/// methods here.
+pub struct DirtyWords<'w, 'a> {
+    words: &'w mut ReversedWords<'a>,
+    pages: DirtyPages,
+    // contribution of each page to `digest` as of the last hash
+    page_digests: Vec<u64>,
+    digest: u64,
+}
+
+impl<'w, 'a> DirtyWords<'w, 'a> {
+    pub fn new(words: &'w mut ReversedWords<'a>) -> DirtyWords<'w, 'a> {
+        DirtyWords::with_page_size(words, DEFAULT_PAGE_SIZE)
+    }
+
+    pub fn with_page_size(words: &'w mut ReversedWords<'a>, page_size: u64) -> DirtyWords<'w, 'a> {
+        let pages = DirtyPages::new(words.len(), page_size);
+        let page_digests = vec![0; pages.dirty.len()];
+        DirtyWords { words, pages, page_digests, digest: 0 }
+    }
+
+    pub fn pages(&self) -> &DirtyPages {
+        &self.pages
+    }
+
+    // mark the storage words holding logical `count` bytes from `addr`
+    fn mark(&mut self, addr: u64, count: usize) {
+        let word_size = self.words.word_size() as u64;
+        let start = addr - addr % word_size;
+        let end = addr.saturating_add(count as u64).div_ceil(word_size).saturating_mul(word_size);
+        self.pages.mark(start..end.min(self.words.len()));
+    }
+
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.words.write_at(addr, buf)?;
+        self.mark(addr, n);
+        Ok(n)
+    }
+
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        // marked even if the write fails partway
+        self.mark(addr, std::mem::size_of::<T>());
+        self.words.write_value_at(addr, value, endian)
+    }
+
+    pub fn fill(&mut self, range: Range<u64>, byte: u8) -> std::io::Result<()> {
+        self.mark(range.start, range.end.saturating_sub(range.start) as usize);
+        self.words.fill(range, byte)
+    }
+
+    /// Mark everything dirty, for when the buffer may have changed behind the view's back.
+    pub fn invalidate(&mut self) {
+        self.pages.mark(0..self.words.len());
+    }
+
+    /// Rehash the pages written since the last call (all of them the first time) and return the
+    /// digest of the whole buffer, equal to [`hash_pages`] over its storage.
+    pub fn hash_incremental(&mut self) -> u64 {
+        let storage = self.words.cursor.get_ref();
+        let page_size = self.pages.page_size as usize;
+        for page in self.pages.dirty_pages() {
+            let start = page * page_size;
+            let data = &storage[start..(start + page_size).min(storage.len())];
+            let contribution = page_digest(page, data);
+            self.digest ^= self.page_digests[page] ^ contribution;
+            self.page_digests[page] = contribution;
+        }
+        self.pages.clear();
+        self.digest
+    }
+}
+
+impl<'a> Deref for DirtyWords<'_, 'a> {
+    type Target = ReversedWords<'a>;
+
+    fn deref(&self) -> &ReversedWords<'a> {
+        self.words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{dirty::*, Endian};
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        let mut data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut words = ReversedWords::new(&mut data);
+        let mut tracked = DirtyWords::with_page_size(&mut words, 64);
+        let first = tracked.hash_incremental();
+        assert_eq!(hash_pages(tracked.cursor.get_ref(), 64), first);
+        assert_eq!(0, tracked.pages().dirty_pages().count());
+
+        tracked.write_value_at(126, 0xDEAD_BEEFu32, Endian::Big).unwrap();
+        assert_eq!(vec![1, 2], tracked.pages().dirty_pages().collect::<Vec<_>>());
+        tracked.write_at(998, &[1, 2]).unwrap();
+        let second = tracked.hash_incremental();
+        assert_ne!(first, second);
+        assert_eq!(hash_pages(tracked.cursor.get_ref(), 64), second);
+        assert_eq!(second, tracked.hash_incremental());
+    }
+}
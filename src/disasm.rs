@@ -0,0 +1,121 @@
+//! Disassembling code in the logical stream with capstone.
+//!
+//! A [`Disassembler`] is set up from an [`ArchProfile`] and turns a range of logical addresses
+//! into [`Instruction`]s. Bytes that don't decode become a `.word` (or `.byte` on x86) record
+//! and disassembly carries on after them, so data mixed into code doesn't end the listing.
+
+use std::ops::Range;
+
+use capstone::{
+    arch::{self, BuildsCapstone, BuildsCapstoneEndian},
+    Capstone,
+};
+
+use crate::{profile::ArchProfile, ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// Logical address of the first byte, plus the disassembler's base address.
+    pub addr: u64,
+    /// The instruction's bytes in logical order.
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+    pub operands: String,
+}
+
+pub struct Disassembler {
+    capstone: Capstone,
+    profile: ArchProfile,
+    base: u64,
+}
+
+fn capstone_error(error: capstone::Error) -> ReversedWordsError {
+    ReversedWordsError::Disassembly { reason: error.to_string() }
+}
+
+impl Disassembler {
+    /// A disassembler for `profile`'s CPU. SH-2 isn't supported by capstone and is an error.
+    pub fn new(profile: ArchProfile) -> Result<Disassembler, ReversedWordsError> {
+        let capstone = match profile {
+            ArchProfile::MipsBe => Capstone::new().mips().mode(arch::mips::ArchMode::Mips64).endian(capstone::Endian::Big).build(),
+            ArchProfile::PowerPcBe => Capstone::new().ppc().mode(arch::ppc::ArchMode::Mode32).endian(capstone::Endian::Big).build(),
+            ArchProfile::X86Le => Capstone::new().x86().mode(arch::x86::ArchMode::Mode32).build(),
+            ArchProfile::Sh2Be => return Err(ReversedWordsError::Disassembly { reason: "capstone has no SH-2 support".to_string() }),
+        }
+        .map_err(capstone_error)?;
+        Ok(Disassembler { capstone, profile, base: 0 })
+    }
+
+    /// Add `base` to every instruction address, e.g. the virtual address the view starts at, so
+    /// branch targets are printed the way the program sees them.
+    pub fn with_base_address(mut self, base: u64) -> Disassembler {
+        self.base = base;
+        self
+    }
+
+    // how far to skip past bytes that don't decode
+    fn skip(&self) -> usize {
+        match self.profile {
+            ArchProfile::X86Le => 1,
+            _ => 4,
+        }
+    }
+
+    /// The instructions in `range` (clamped to the view), in address order.
+    pub fn disassemble(&self, words: &ReversedWords, range: Range<u64>) -> Result<Vec<Instruction>, ReversedWordsError> {
+        let start = range.start.min(words.len());
+        let mut code = vec![0u8; range.end.saturating_sub(start).min(words.len() - start) as usize];
+        let n = words.read_at(start, &mut code).map_err(|_| ReversedWordsError::RangeOutOfRange { start, end: range.end, len: words.len() })?;
+        code.truncate(n);
+
+        let mut instructions = Vec::new();
+        let mut offset = 0;
+        while offset < code.len() {
+            let addr = self.base.wrapping_add(start + offset as u64);
+            let decoded = self.capstone.disasm_all(&code[offset..], addr).map_err(capstone_error)?;
+            for insn in decoded.iter() {
+                instructions.push(Instruction {
+                    addr: insn.address(),
+                    bytes: insn.bytes().to_vec(),
+                    mnemonic: insn.mnemonic().unwrap_or_default().to_string(),
+                    operands: insn.op_str().unwrap_or_default().to_string(),
+                });
+                offset += insn.bytes().len();
+            }
+            if offset < code.len() {
+                let bytes = code[offset..(offset + self.skip()).min(code.len())].to_vec();
+                let value = bytes.iter().fold(0u64, |value, byte| value << 8 | *byte as u64);
+                let mnemonic = if bytes.len() == 1 { ".byte" } else { ".word" };
+                instructions.push(Instruction {
+                    addr: self.base.wrapping_add(start + offset as u64),
+                    mnemonic: mnemonic.to_string(),
+                    operands: format!("{:#x}", value),
+                    bytes,
+                });
+                offset += self.skip();
+            }
+        }
+        Ok(instructions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{disasm::*, profile::ArchProfile};
+
+    #[test]
+    fn disassembles_swapped_mips() {
+        let mut rdram = vec![0u8; 16];
+        let mut words = ArchProfile::MipsBe.view(&mut rdram);
+        // addiu $sp, $sp, -0x18; an undecodable word; jr $ra; nop
+        words.write_at(0, &[0x27, 0xBD, 0xFF, 0xE8, 0xEC, 0x00, 0x00, 0x00, 0x03, 0xE0, 0x00, 0x08, 0, 0, 0, 0]).unwrap();
+        let disassembler = Disassembler::new(ArchProfile::MipsBe).unwrap().with_base_address(0x8000_0000);
+        let listing = disassembler.disassemble(&words, 0..16).unwrap();
+        let mnemonics: Vec<_> = listing.iter().map(|insn| (insn.addr, insn.mnemonic.as_str())).collect();
+        assert_eq!(vec![(0x8000_0000, "addiu"), (0x8000_0004, ".word"), (0x8000_0008, "jr"), (0x8000_000C, "nop")], mnemonics);
+        assert_eq!("$sp, $sp, -0x18", listing[0].operands);
+        assert_eq!(vec![0x27, 0xBD, 0xFF, 0xE8], listing[0].bytes);
+        assert_eq!(2, disassembler.disassemble(&words, 8..99).unwrap().len());
+        assert!(Disassembler::new(ArchProfile::Sh2Be).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! GameCube/Wii memory as exposed by Dolphin.
+//!
+//! Dolphin's `mem1.raw`/`mem2.raw` dumps (and its emulated RAM as seen by memory tools) hold the
+//! console's big endian memory unswapped. [`DolphinRam`] covers MEM1 and, on the Wii, MEM2 behind
+//! one set of typed accessors taking the addresses games use: `0x80000000`/`0xC0000000` for MEM1
+//! and `0x90000000`/`0xD0000000` for MEM2.
+
+use binread::Endian;
+
+use crate::{
+    platform::{Platform, PlatformView},
+    Primitive, ReversedWords, ReversedWordsError,
+};
+
+/// Size of MEM1 (24 MiB).
+pub const MEM1_SIZE: usize = 0x180_0000;
+/// Size of the Wii's MEM2 (64 MiB).
+pub const MEM2_SIZE: usize = 0x400_0000;
+
+pub struct DolphinRam<'a> {
+    mem1: PlatformView<'a>,
+    mem2: Option<PlatformView<'a>>,
+}
+
+macro_rules! dolphin_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            pub fn $read(&mut self, addr: u32) -> std::io::Result<$ty> {
+                self.read_value(addr)
+            }
+
+            pub fn $write(&mut self, addr: u32, value: $ty) -> std::io::Result<()> {
+                self.write_value(addr, value)
+            }
+        )*
+    };
+}
+
+impl<'a> DolphinRam<'a> {
+    pub fn gamecube(mem1: &'a mut [u8]) -> DolphinRam<'a> {
+        DolphinRam { mem1: Platform::GameCubeMem1.view(mem1), mem2: None }
+    }
+
+    pub fn wii(mem1: &'a mut [u8], mem2: &'a mut [u8]) -> DolphinRam<'a> {
+        DolphinRam { mem1: Platform::GameCubeMem1.view(mem1), mem2: Some(Platform::WiiMem2.view(mem2)) }
+    }
+
+    pub fn mem1(&mut self) -> &mut PlatformView<'a> {
+        &mut self.mem1
+    }
+
+    pub fn mem2(&mut self) -> Option<&mut PlatformView<'a>> {
+        self.mem2.as_mut()
+    }
+
+    // the view holding `addr` and the offset into it
+    fn locate(&mut self, addr: u32) -> Result<(&mut PlatformView<'a>, u64), ReversedWordsError> {
+        let addr = addr as u64;
+        if let Ok(offset) = self.mem1.address_map().translate(addr) {
+            return Ok((&mut self.mem1, offset));
+        }
+        if let Some(mem2) = self.mem2.as_mut() {
+            if let Ok(offset) = mem2.address_map().translate(addr) {
+                return Ok((mem2, offset));
+            }
+        }
+        Err(ReversedWordsError::UnmappedAddress { addr })
+    }
+
+    /// Whether `addr` is in MEM1 or MEM2.
+    pub fn is_valid_address(&mut self, addr: u32) -> bool {
+        self.locate(addr).is_ok()
+    }
+
+    pub fn read_value<T: Primitive>(&mut self, addr: u32) -> std::io::Result<T> {
+        let (view, offset) = self.locate(addr)?;
+        view.read_value_at(offset, Endian::Big)
+    }
+
+    pub fn write_value<T: Primitive>(&mut self, addr: u32, value: T) -> std::io::Result<()> {
+        let (view, offset) = self.locate(addr)?;
+        view.write_value_at(offset, value, Endian::Big)
+    }
+
+    /// Read a pointer at `addr`, failing if it doesn't point into MEM1 or MEM2.
+    pub fn read_pointer(&mut self, addr: u32) -> std::io::Result<u32> {
+        let pointer = self.read_u32(addr)?;
+        self.locate(pointer)?;
+        Ok(pointer)
+    }
+
+    dolphin_accessors! {
+        u8 => read_u8, write_u8;
+        u16 => read_u16, write_u16;
+        u32 => read_u32, write_u32;
+        u64 => read_u64, write_u64;
+        u128 => read_u128, write_u128;
+        i8 => read_i8, write_i8;
+        i16 => read_i16, write_i16;
+        i32 => read_i32, write_i32;
+        i64 => read_i64, write_i64;
+        i128 => read_i128, write_i128;
+        f32 => read_f32, write_f32;
+        f64 => read_f64, write_f64;
+    }
+}
+
+impl<'a> ReversedWords<'a> {
+    pub fn wii_mem2(ram: &'a mut [u8]) -> PlatformView<'a> {
+        Platform::WiiMem2.view(ram)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{dolphin::*, ReversedWordsError};
+
+    #[test]
+    fn wii_addresses() {
+        let mut mem1 = vec![0u8; 0x100];
+        let mut mem2 = vec![0u8; 0x100];
+        let mut ram = DolphinRam::wii(&mut mem1, &mut mem2);
+        ram.write_u32(0x8000_0010, 0x9000_0020).unwrap();
+        ram.write_f32(0xD000_0020, 1.5).unwrap();
+        let pointer = ram.read_pointer(0xC000_0010).unwrap();
+        assert_eq!(1.5, ram.read_f32(pointer).unwrap());
+        assert!(!ram.is_valid_address(0x8000_0100));
+        assert_eq!([0x90, 0, 0, 0x20], mem1[0x10..0x14]);
+        assert_eq!(1.5f32.to_be_bytes(), mem2[0x20..0x24]);
+    }
+
+    #[test]
+    fn gamecube_has_no_mem2() {
+        let mut mem1 = vec![0u8; 0x100];
+        let mut ram = DolphinRam::gamecube(&mut mem1);
+        ram.write_u32(0x8000_0000, 0x9000_0000).unwrap();
+        let error = ram.read_pointer(0x8000_0000).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnmappedAddress { addr: 0x9000_0000 }), ReversedWordsError::from_io(&error));
+        assert!(ram.mem2().is_none());
+    }
+}
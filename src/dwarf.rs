@@ -0,0 +1,443 @@
+//! Reading typed values described by DWARF debug info.
+//!
+//! [`DebugInfo::from_elf`] collects the global variables and the types reachable from them, then
+//! [`DebugInfo::read_variable`] decodes a variable through a view into a [`Value`] tree of
+//! structs, arrays, enums and scalars. Values are decoded with the ELF's byte order and addresses
+//! go through the [`AddressMap`], so a decomp build's ELF can be pointed straight at an RDRAM dump.
+//!
+//! Pointers are read as addresses and not followed. Bitfields, unions and variable length arrays
+//! are not decoded and come back as [`Value::Unsupported`].
+
+use std::{borrow::Cow, collections::HashMap};
+
+use binread::Endian;
+use gimli::AttributeValue;
+use object::{Object, ObjectSection};
+
+use crate::{translate::AddressMap, Primitive, ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Bool(bool),
+    /// The address a pointer holds.
+    Pointer(u64),
+    Enum { value: i64, name: Option<String> },
+    Struct { name: Option<String>, fields: Vec<(String, Value)> },
+    Array(Vec<Value>),
+    /// A type this module does not decode, with its size in bytes.
+    Unsupported { size: u64 },
+}
+
+impl Value {
+    /// The field called `name` of a struct value.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct { fields, .. } => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Unsigned,
+    Signed,
+    Float,
+    Bool,
+}
+
+#[derive(Clone, Debug)]
+enum Type {
+    Base { encoding: Encoding, size: u64 },
+    Pointer { size: u64 },
+    Enum { size: u64, enumerators: Vec<(String, i64)> },
+    Struct { name: Option<String>, size: u64, members: Vec<(String, u64, TypeId)> },
+    Array { element: TypeId, counts: Vec<u64> },
+    /// typedef, const and volatile
+    Alias(TypeId),
+    Unsupported { size: u64 },
+}
+
+/// Offset of a type's entry in `.debug_info`.
+type TypeId = usize;
+
+#[derive(Clone, Debug)]
+pub struct DebugInfo {
+    types: HashMap<TypeId, Type>,
+    variables: HashMap<String, (u64, TypeId)>,
+    endian: Endian,
+    address_map: AddressMap,
+}
+
+type EntryReader<'d> = gimli::EndianSlice<'d, gimli::RunTimeEndian>;
+
+fn dwarf_error(e: impl std::fmt::Display) -> ReversedWordsError {
+    ReversedWordsError::InvalidSymbolFile { reason: e.to_string() }
+}
+
+// DWARF allows multidimensional arrays as a single array type with several subranges, the
+// outermost first.
+fn subrange_counts(node: gimli::EntriesTreeNode<EntryReader>) -> gimli::Result<Vec<u64>> {
+    let mut counts = Vec::new();
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        if entry.tag() != gimli::DW_TAG_subrange_type {
+            continue;
+        }
+        let count = match (entry.attr_value(gimli::DW_AT_count)?, entry.attr_value(gimli::DW_AT_upper_bound)?) {
+            (Some(count), _) => count.udata_value().unwrap_or(0),
+            (None, Some(upper_bound)) => upper_bound.udata_value().map_or(0, |upper_bound| upper_bound + 1),
+            (None, None) => 0,
+        };
+        counts.push(count);
+    }
+    Ok(counts)
+}
+
+struct Collector<'d> {
+    dwarf: &'d gimli::Dwarf<EntryReader<'d>>,
+    info: DebugInfo,
+}
+
+impl<'d> Collector<'d> {
+    fn name(&self, unit: &gimli::Unit<EntryReader<'d>>, entry: &gimli::DebuggingInformationEntry<EntryReader<'d>>, attr: gimli::DwAt) -> gimli::Result<Option<String>> {
+        match entry.attr_value(attr)? {
+            Some(value) => Ok(Some(self.dwarf.attr_string(unit, value)?.to_string_lossy().into_owned())),
+            None => Ok(None),
+        }
+    }
+
+    fn type_ref(unit: &gimli::Unit<EntryReader<'d>>, entry: &gimli::DebuggingInformationEntry<EntryReader<'d>>) -> gimli::Result<Option<TypeId>> {
+        Ok(match entry.attr_value(gimli::DW_AT_type)? {
+            Some(AttributeValue::UnitRef(offset)) => offset.to_debug_info_offset(&unit.header).map(|offset| offset.0),
+            Some(AttributeValue::DebugInfoRef(offset)) => Some(offset.0),
+            _ => None,
+        })
+    }
+
+    fn collect(&mut self, unit: &gimli::Unit<EntryReader<'d>>, node: gimli::EntriesTreeNode<EntryReader<'d>>) -> gimli::Result<()> {
+        let entry = node.entry().clone();
+        let id = entry.offset().to_debug_info_offset(&unit.header).map_or(0, |offset| offset.0);
+        let size = entry.attr_value(gimli::DW_AT_byte_size)?.and_then(|size| size.udata_value()).unwrap_or(0);
+        let ty = match entry.tag() {
+            gimli::DW_TAG_base_type => {
+                let encoding = match entry.attr_value(gimli::DW_AT_encoding)? {
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_signed | gimli::DW_ATE_signed_char)) => Some(Encoding::Signed),
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char | gimli::DW_ATE_UTF)) => Some(Encoding::Unsigned),
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_float)) => Some(Encoding::Float),
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_boolean)) => Some(Encoding::Bool),
+                    _ => None,
+                };
+                Some(encoding.map_or(Type::Unsupported { size }, |encoding| Type::Base { encoding, size }))
+            }
+            gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => {
+                Some(Type::Pointer { size: if size == 0 { unit.encoding().address_size as u64 } else { size } })
+            }
+            gimli::DW_TAG_typedef | gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type => {
+                Some(Collector::type_ref(unit, &entry)?.map_or(Type::Unsupported { size: 0 }, Type::Alias))
+            }
+            gimli::DW_TAG_enumeration_type => {
+                let mut enumerators = Vec::new();
+                let mut children = node.children();
+                while let Some(child) = children.next()? {
+                    let child = child.entry();
+                    if child.tag() == gimli::DW_TAG_enumerator {
+                        let name = self.name(unit, child, gimli::DW_AT_name)?.unwrap_or_default();
+                        let value = child.attr_value(gimli::DW_AT_const_value)?.and_then(|value| value.sdata_value().or_else(|| value.udata_value().map(|value| value as i64)));
+                        enumerators.push((name, value.unwrap_or(0)));
+                    }
+                }
+                self.info.types.insert(id, Type::Enum { size, enumerators });
+                return Ok(());
+            }
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type => {
+                let name = self.name(unit, &entry, gimli::DW_AT_name)?;
+                let mut members = Vec::new();
+                let mut unsupported = false;
+                let mut children = node.children();
+                while let Some(child) = children.next()? {
+                    let child_entry = child.entry().clone();
+                    if child_entry.tag() != gimli::DW_TAG_member {
+                        // nested type definitions
+                        self.collect(unit, child)?;
+                        continue;
+                    }
+                    if child_entry.attr_value(gimli::DW_AT_bit_size)?.is_some() {
+                        unsupported = true;
+                    }
+                    let offset = child_entry.attr_value(gimli::DW_AT_data_member_location)?.and_then(|offset| offset.udata_value());
+                    match (offset, Collector::type_ref(unit, &child_entry)?) {
+                        (Some(offset), Some(ty)) => {
+                            let field = self.name(unit, &child_entry, gimli::DW_AT_name)?.unwrap_or_default();
+                            members.push((field, offset, ty));
+                        }
+                        _ => unsupported = true,
+                    }
+                }
+                let ty = if unsupported { Type::Unsupported { size } } else { Type::Struct { name, size, members } };
+                self.info.types.insert(id, ty);
+                return Ok(());
+            }
+            gimli::DW_TAG_union_type => Some(Type::Unsupported { size }),
+            gimli::DW_TAG_array_type => {
+                let element = Collector::type_ref(unit, &entry)?;
+                let counts = subrange_counts(node)?;
+                let ty = match element {
+                    Some(element) if !counts.is_empty() => Type::Array { element, counts },
+                    _ => Type::Unsupported { size },
+                };
+                self.info.types.insert(id, ty);
+                return Ok(());
+            }
+            gimli::DW_TAG_variable => {
+                let mut location = None;
+                if let Some(AttributeValue::Exprloc(expr)) = entry.attr_value(gimli::DW_AT_location)? {
+                    let mut ops = expr.operations(unit.encoding());
+                    if let Ok(Some(gimli::Operation::Address { address })) = ops.next() {
+                        location = Some(address);
+                    }
+                }
+                let name = self.name(unit, &entry, gimli::DW_AT_name)?;
+                if let (Some(name), Some(addr), Some(ty)) = (name, location, Collector::type_ref(unit, &entry)?) {
+                    self.info.variables.entry(name).or_insert((addr, ty));
+                }
+                None
+            }
+            _ => None,
+        };
+        if let Some(ty) = ty {
+            self.info.types.insert(id, ty);
+        }
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            self.collect(unit, child)?;
+        }
+        Ok(())
+    }
+}
+
+impl DebugInfo {
+    /// Collect the global variables and their types from an ELF's DWARF sections.
+    pub fn from_elf(data: &[u8]) -> Result<DebugInfo, ReversedWordsError> {
+        let file = object::File::parse(data).map_err(dwarf_error)?;
+        let (endian, runtime_endian) = if file.is_little_endian() {
+            (Endian::Little, gimli::RunTimeEndian::Little)
+        } else {
+            (Endian::Big, gimli::RunTimeEndian::Big)
+        };
+        let load_section = |id: gimli::SectionId| -> Result<Cow<[u8]>, ReversedWordsError> {
+            match file.section_by_name(id.name()) {
+                Some(section) => section.uncompressed_data().map_err(dwarf_error),
+                None => Ok(Cow::Borrowed(&[])),
+            }
+        };
+        let sections = gimli::DwarfSections::load(load_section)?;
+        let dwarf = sections.borrow(|section| gimli::EndianSlice::new(section, runtime_endian));
+
+        let info = DebugInfo { types: HashMap::new(), variables: HashMap::new(), endian, address_map: AddressMap::identity() };
+        let mut collector = Collector { dwarf: &dwarf, info };
+        let mut units = dwarf.units();
+        while let Some(header) = units.next().map_err(dwarf_error)? {
+            let unit = dwarf.unit(header).map_err(dwarf_error)?;
+            let mut tree = unit.entries_tree(None).map_err(dwarf_error)?;
+            let root = tree.root().map_err(dwarf_error)?;
+            collector.collect(&unit, root).map_err(dwarf_error)?;
+        }
+        Ok(collector.info)
+    }
+
+    pub fn with_address_map(mut self, address_map: AddressMap) -> DebugInfo {
+        self.address_map = address_map;
+        self
+    }
+
+    /// Byte order values are decoded with, the ELF's by default.
+    pub fn with_endian(mut self, endian: Endian) -> DebugInfo {
+        self.endian = endian;
+        self
+    }
+
+    /// Virtual address of the global variable `name`.
+    pub fn address_of(&self, name: &str) -> Option<u64> {
+        self.variables.get(name).map(|(addr, _)| *addr)
+    }
+
+    /// Decode the global variable `name` through `words`.
+    pub fn read_variable(&self, words: &ReversedWords, name: &str) -> std::io::Result<Value> {
+        let (addr, ty) = *self
+            .variables
+            .get(name)
+            .ok_or_else(|| ReversedWordsError::UnknownSymbol { name: name.to_string() })?;
+        let offset = self.address_map.translate(addr)?;
+        self.read_type(words, ty, offset)
+    }
+
+    fn read_scalar<T: Primitive>(&self, words: &ReversedWords, offset: u64) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        let len = bytes.as_ref().len();
+        if words.read_at(offset, bytes.as_mut())? < len {
+            return Err(ReversedWordsError::AddressOutOfRange { addr: offset + len as u64, len: words.len() }.into());
+        }
+        Ok(T::from_bytes(bytes, self.endian))
+    }
+
+    fn read_unsigned(&self, words: &ReversedWords, size: u64, offset: u64) -> std::io::Result<Option<u64>> {
+        Ok(Some(match size {
+            1 => self.read_scalar::<u8>(words, offset)? as u64,
+            2 => self.read_scalar::<u16>(words, offset)? as u64,
+            4 => self.read_scalar::<u32>(words, offset)? as u64,
+            8 => self.read_scalar::<u64>(words, offset)?,
+            _ => return Ok(None),
+        }))
+    }
+
+    fn read_type(&self, words: &ReversedWords, ty: TypeId, offset: u64) -> std::io::Result<Value> {
+        let ty = match self.types.get(&ty) {
+            Some(ty) => ty,
+            None => return Ok(Value::Unsupported { size: 0 }),
+        };
+        Ok(match ty {
+            Type::Base { encoding: Encoding::Float, size: 4 } => Value::Float(self.read_scalar::<f32>(words, offset)? as f64),
+            Type::Base { encoding: Encoding::Float, size: 8 } => Value::Float(self.read_scalar::<f64>(words, offset)?),
+            Type::Base { encoding, size } => match (encoding, self.read_unsigned(words, *size, offset)?) {
+                (Encoding::Unsigned, Some(value)) => Value::Unsigned(value),
+                (Encoding::Signed, Some(value)) => {
+                    let shift = 64 - size * 8;
+                    Value::Signed(((value << shift) as i64) >> shift)
+                }
+                (Encoding::Bool, Some(value)) => Value::Bool(value != 0),
+                _ => Value::Unsupported { size: *size },
+            },
+            Type::Pointer { size } => match self.read_unsigned(words, *size, offset)? {
+                Some(addr) => Value::Pointer(addr),
+                None => Value::Unsupported { size: *size },
+            },
+            Type::Enum { size, enumerators } => match self.read_unsigned(words, *size, offset)? {
+                Some(raw) => {
+                    let mask = if *size >= 8 { u64::MAX } else { (1 << (size * 8)) - 1 };
+                    let name = enumerators.iter().find(|(_, value)| *value as u64 & mask == raw).map(|(name, _)| name.clone());
+                    let shift = 64 - size * 8;
+                    Value::Enum { value: ((raw << shift) as i64) >> shift, name }
+                }
+                None => Value::Unsupported { size: *size },
+            },
+            Type::Struct { name, members, .. } => {
+                let mut fields = Vec::with_capacity(members.len());
+                for (field, member_offset, member_ty) in members {
+                    fields.push((field.clone(), self.read_type(words, *member_ty, offset + member_offset)?));
+                }
+                Value::Struct { name: name.clone(), fields }
+            }
+            Type::Array { element, counts } => self.read_array(words, *element, counts, offset)?,
+            Type::Alias(ty) => self.read_type(words, *ty, offset)?,
+            Type::Unsupported { size } => Value::Unsupported { size: *size },
+        })
+    }
+
+    fn size_of(&self, ty: TypeId) -> u64 {
+        match self.types.get(&ty) {
+            Some(Type::Base { size, .. } | Type::Pointer { size } | Type::Enum { size, .. } | Type::Unsupported { size }) => *size,
+            // the declared size includes trailing padding, which matters for arrays of structs
+            Some(Type::Struct { size, .. }) if *size > 0 => *size,
+            Some(Type::Struct { members, .. }) => {
+                members.iter().map(|(_, offset, ty)| offset + self.size_of(*ty)).max().unwrap_or(0)
+            }
+            Some(Type::Array { element, counts }) => counts.iter().product::<u64>() * self.size_of(*element),
+            Some(Type::Alias(ty)) => self.size_of(*ty),
+            None => 0,
+        }
+    }
+
+    fn read_array(&self, words: &ReversedWords, element: TypeId, counts: &[u64], offset: u64) -> std::io::Result<Value> {
+        let (count, inner) = match counts.split_first() {
+            Some(split) => split,
+            None => return self.read_type(words, element, offset),
+        };
+        let stride = inner.iter().product::<u64>() * self.size_of(element);
+        let mut values = Vec::new();
+        for i in 0..*count {
+            values.push(self.read_array(words, element, inner, offset + i * stride)?);
+        }
+        Ok(Value::Array(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{dwarf::*, translate::AddressMap};
+
+    #[repr(C)]
+    pub struct Fixture {
+        health: u16,
+        lives: i8,
+        alive: bool,
+        speed: f32,
+        position: [[i16; 2]; 2],
+    }
+
+    #[repr(C)]
+    pub struct Padded {
+        value: u32,
+        tag: u8,
+    }
+
+    #[no_mangle]
+    pub static DWARF_TEST_PADDED: [Padded; 2] = [Padded { value: 1, tag: 2 }, Padded { value: 3, tag: 4 }];
+
+    #[no_mangle]
+    pub static DWARF_TEST_FIXTURE: Fixture = Fixture { health: 0x1234, lives: -3, alive: true, speed: 1.5, position: [[1, -2], [3, -4]] };
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn read_struct_variable() {
+        let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let info = DebugInfo::from_elf(&exe).unwrap();
+        let addr = info.address_of("DWARF_TEST_FIXTURE").unwrap();
+        let info = info.with_address_map(AddressMap::new().with_segment(addr..addr + 0x20, 0));
+
+        // the fixture's host order bytes, as they'd be stored in a 4-byte swapped dump
+        let size = std::mem::size_of::<Fixture>();
+        let bytes = unsafe { std::slice::from_raw_parts(&DWARF_TEST_FIXTURE as *const Fixture as *const u8, size) };
+        let mut data = vec![0u8; size];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_at(0, bytes).unwrap();
+
+        let value = info.read_variable(&words, "DWARF_TEST_FIXTURE").unwrap();
+        assert_eq!(Some(&Value::Unsigned(0x1234)), value.field("health"));
+        assert_eq!(Some(&Value::Signed(-3)), value.field("lives"));
+        assert_eq!(Some(&Value::Bool(true)), value.field("alive"));
+        assert_eq!(Some(&Value::Float(1.5)), value.field("speed"));
+        let row = |a, b| Value::Array(vec![Value::Signed(a), Value::Signed(b)]);
+        assert_eq!(Some(&Value::Array(vec![row(1, -2), row(3, -4)])), value.field("position"));
+
+        let error = info.read_variable(&words, "DWARF_MISSING").unwrap_err();
+        assert_eq!(std::io::ErrorKind::NotFound, error.kind());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn arrays_of_structs_include_trailing_padding() {
+        let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let info = DebugInfo::from_elf(&exe).unwrap();
+        let addr = info.address_of("DWARF_TEST_PADDED").unwrap();
+        let info = info.with_address_map(AddressMap::new().with_segment(addr..addr + 0x10, 0));
+
+        let size = std::mem::size_of_val(&DWARF_TEST_PADDED);
+        assert_eq!(16, size);
+        let bytes = unsafe { std::slice::from_raw_parts(DWARF_TEST_PADDED.as_ptr() as *const u8, size) };
+        let mut data = vec![0u8; size];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_at(0, bytes).unwrap();
+
+        let value = info.read_variable(&words, "DWARF_TEST_PADDED").unwrap();
+        let element = |value, tag| Value::Struct {
+            name: Some("Padded".to_string()),
+            fields: vec![("value".to_string(), Value::Unsigned(value)), ("tag".to_string(), Value::Unsigned(tag))],
+        };
+        assert_eq!(Value::Array(vec![element(1, 2), element(3, 4)]), value);
+    }
+}
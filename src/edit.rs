@@ -0,0 +1,97 @@
+//! Temporary edits that undo themselves.
+
+use std::ops::{Deref, DerefMut, Range};
+
+use crate::{storage_index, ReversedWords};
+
+/// A view whose edits to a range are rolled back when it is dropped, see
+/// [`ReversedWords::scoped_edit`].
+pub struct ScopedEdit<'w, 'a> {
+    words: &'w mut ReversedWords<'a>,
+    range: Range<u64>,
+    // storage bytes of the range in logical order, as they were when the edit started
+    saved: Vec<u8>,
+    committed: bool,
+}
+
+impl<'a> ReversedWords<'a> {
+    /// Snapshot the logical bytes in `range` and return a guard which restores them when it is
+    /// dropped, unless [`ScopedEdit::commit`] is called first. Only `range` is restored; bytes
+    /// outside it keep any changes made through the guard.
+    ///
+    /// This makes poking at live memory safe by construction: an early return, `?` or panic
+    /// leaves the range as it was.
+    pub fn scoped_edit(&mut self, range: Range<u64>) -> std::io::Result<ScopedEdit<'_, 'a>> {
+        self.check_range(&range)?;
+        let saved = range.clone().filter_map(|position| self.storage_index(position)).map(|index| self.cursor.get_ref()[index]).collect();
+        Ok(ScopedEdit { words: self, range, saved, committed: false })
+    }
+}
+
+impl ScopedEdit<'_, '_> {
+    pub fn range(&self) -> Range<u64> {
+        self.range.clone()
+    }
+
+    /// Keep the changes.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /// Restore the range now and keep editing, as if the guard had just been created.
+    pub fn revert(&mut self) {
+        let (word_size, len) = (self.words.word_size, self.words.len);
+        let indices = self.range.clone().filter_map(|position| storage_index(position, word_size, len));
+        let storage = self.words.cursor.get_mut();
+        for (index, byte) in indices.zip(&self.saved) {
+            storage[index] = *byte;
+        }
+    }
+}
+
+impl<'a> Deref for ScopedEdit<'_, 'a> {
+    type Target = ReversedWords<'a>;
+
+    fn deref(&self) -> &ReversedWords<'a> {
+        self.words
+    }
+}
+
+impl<'a> DerefMut for ScopedEdit<'_, 'a> {
+    fn deref_mut(&mut self) -> &mut ReversedWords<'a> {
+        self.words
+    }
+}
+
+impl Drop for ScopedEdit<'_, '_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.revert();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn edits_roll_back_unless_committed() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut words = ReversedWords::new(&mut data);
+        {
+            let mut edit = words.scoped_edit(2..6).unwrap();
+            edit.write_u32_at(2, 0xFFFF_FFFF, Endian::Big).unwrap();
+            edit.set_byte(7, 0xAA).unwrap();
+            assert_eq!(0xFFFF_FFFF, edit.read_u32_at(2, Endian::Big).unwrap());
+        }
+        assert_eq!(0x0100_0706, words.read_u32_at(2, Endian::Big).unwrap());
+        assert_eq!(0xAA, words.get_byte(7).unwrap());
+
+        let mut edit = words.scoped_edit(0..2).unwrap();
+        edit.write_u16_at(0, 0xBEEF, Endian::Big).unwrap();
+        edit.commit();
+        assert_eq!(0xBEEF, words.read_u16_at(0, Endian::Big).unwrap());
+        assert!(words.scoped_edit(4..9).is_err());
+    }
+}
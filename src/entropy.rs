@@ -0,0 +1,58 @@
+//! Shannon entropy of the logical stream, page by page.
+
+use crate::ReversedWords;
+
+/// Entropy in bits per byte (0 to 8) of a byte frequency table covering `total` bytes.
+fn shannon(counts: &[u64; 256], total: u64) -> f64 {
+    let total = total as f64;
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+impl ReversedWords<'_> {
+    /// Entropy in bits per byte of each `page_size` page of the view, in address order: near 8
+    /// for compressed or encrypted data, 4 to 6 for code and text, near 0 for padding. The last
+    /// page may be shorter. A `page_size` of 0 is treated as 1.
+    pub fn entropy_map(&self, page_size: u64) -> Vec<f64> {
+        let page_size = page_size.max(1);
+        let mut pages = Vec::new();
+        let mut counts = [0u64; 256];
+        let mut in_page = 0;
+        for byte in self.bytes(0..u64::MAX) {
+            counts[byte as usize] += 1;
+            in_page += 1;
+            if in_page == page_size {
+                pages.push(shannon(&counts, in_page));
+                counts = [0; 256];
+                in_page = 0;
+            }
+        }
+        if in_page > 0 {
+            pages.push(shannon(&counts, in_page));
+        }
+        pages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ReversedWords;
+
+    #[test]
+    fn entropy_per_page() {
+        let mut data = vec![0u8; 256 + 256 + 8];
+        for (i, byte) in data[256..512].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        data[512..].copy_from_slice(&[1, 1, 2, 2, 1, 1, 2, 2]);
+        let words = ReversedWords::new(&mut data);
+        assert_eq!(vec![0.0, 8.0, 1.0], words.entropy_map(256));
+        assert_eq!(520, words.entropy_map(0).len());
+    }
+}
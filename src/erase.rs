@@ -0,0 +1,83 @@
+//! Erasing sensitive bytes so the writes can't be optimized away.
+//!
+//! [`ReversedWords::secure_erase`] and [`ReversedVec::secure_erase`] zero a logical range with
+//! `zeroize`'s volatile writes. [`ReversedVec`], [`SnapshotRing`](crate::history::SnapshotRing)
+//! and [`CompressedSnapshot`](crate::history::CompressedSnapshot) implement `Zeroize`, so they
+//! can also be wrapped in `zeroize::Zeroizing` to be erased when dropped. A
+//! [`FrozenView`](crate::FrozenView) shares its bytes between clones and can't be erased in place.
+
+use std::ops::Range;
+
+use zeroize::Zeroize;
+
+use crate::{ReversedVec, ReversedWords, ReversedWordsError};
+
+impl ReversedWords<'_> {
+    /// Zero the storage of the logical bytes in `range`. Fails without erasing anything if the
+    /// range isn't within the view.
+    pub fn secure_erase(&mut self, range: Range<u64>) -> Result<(), ReversedWordsError> {
+        if range.start > range.end || range.end > self.reachable_len() {
+            return Err(ReversedWordsError::RangeOutOfRange { start: range.start, end: range.end, len: self.len });
+        }
+        let word_size = self.word_size as u64;
+        // whole words are erased as one storage range, the ends of partly covered words byte by byte
+        let first_word = range.start.div_ceil(word_size) * word_size;
+        let last_word = range.end / word_size * word_size;
+        let (head, tail) = if first_word < last_word {
+            self.cursor.get_mut()[first_word as usize..last_word as usize].zeroize();
+            (range.start..first_word, last_word..range.end)
+        } else {
+            (range.clone(), range.end..range.end)
+        };
+        for position in head.chain(tail) {
+            let index = self.storage_index(position).expect("checked against the reachable length");
+            self.cursor.get_mut()[index].zeroize();
+        }
+        Ok(())
+    }
+}
+
+impl ReversedVec {
+    /// [`ReversedWords::secure_erase`] on the owned buffer.
+    pub fn secure_erase(&mut self, range: Range<u64>) -> Result<(), ReversedWordsError> {
+        self.words().secure_erase(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zeroize::Zeroizing;
+
+    use crate::{erase::*, history::SnapshotRing};
+
+    #[test]
+    fn erases_logical_ranges() {
+        let mut data: Vec<u8> = (1..=12).collect();
+        let mut words = ReversedWords::new(&mut data);
+        words.secure_erase(2..9).unwrap();
+        let mut out = [0u8; 12];
+        words.read_at(0, &mut out).unwrap();
+        assert_eq!([4, 3, 0, 0, 0, 0, 0, 0, 0, 11, 10, 9], out);
+        assert!(words.secure_erase(10..13).is_err());
+        assert_eq!([0, 0, 3, 4, 0, 0, 0, 0, 9, 10, 11, 0], data[..]);
+
+        let mut owned = ReversedVec::new((1..=8).collect());
+        owned.secure_erase(7..8).unwrap();
+        assert_eq!([1, 2, 3, 4, 0, 6, 7, 8], owned.as_slice());
+        owned.zeroize();
+        assert!(owned.is_empty());
+    }
+
+    #[test]
+    fn snapshots_zeroize() {
+        let mut data = vec![7u8; 8];
+        let mut ring = Zeroizing::new(SnapshotRing::new(4));
+        ring.capture(&ReversedWords::new(&mut data));
+        data[0] = 8;
+        ring.capture(&ReversedWords::new(&mut data));
+        assert_eq!(2, ring.len());
+        ring.zeroize();
+        assert!(ring.is_empty());
+        assert_eq!(0, ring.stored_bytes());
+    }
+}
@@ -0,0 +1,369 @@
+use std::io::ErrorKind;
+
+/// Configuration and addressing failures.
+///
+/// Methods whose signatures come from `Read`/`Write`/`Seek` (and the helpers built on them) return
+/// these wrapped in an `io::Error` with a matching `ErrorKind`; use [`ReversedWordsError::from_io`]
+/// to get the structured cause back out.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReversedWordsError {
+    #[error("word size must be at least 1 byte")]
+    InvalidWordSize,
+    #[error("address {addr:#x} is outside of the {len} byte buffer")]
+    AddressOutOfRange { addr: u64, len: u64 },
+    #[error("range {start:#x}..{end:#x} is outside of the {len} byte buffer")]
+    RangeOutOfRange { start: u64, end: u64, len: u64 },
+    #[error("seek to {target} is outside of the {len} byte buffer")]
+    SeekOutOfRange { target: i128, len: u64 },
+    #[error("position {position:#x} is not aligned to the {word_size} byte word size")]
+    Misaligned { position: u64, word_size: u8 },
+    #[error("unrecognized savestate format")]
+    UnknownSavestateFormat,
+    #[error("savestate is {len} bytes but its RDRAM block ends at {needed:#x}")]
+    TruncatedSavestate { needed: u64, len: u64 },
+    #[error("savestate is compressed, enable the compression feature to load it")]
+    CompressedSavestate,
+    #[error("virtual address {addr:#x} is not mapped into the view")]
+    UnmappedAddress { addr: u64 },
+    #[error("unknown symbol {name:?}")]
+    UnknownSymbol { name: String },
+    #[error("invalid symbol file: {reason}")]
+    InvalidSymbolFile { reason: String },
+    #[error("invalid expression at {position}: {reason}")]
+    InvalidExpression { position: usize, reason: String },
+    #[error("script error: {reason}")]
+    Script { reason: String },
+    #[error("region {name:?} overlaps region {other:?}")]
+    OverlappingRegion { name: String, other: String },
+    #[error("buffer at {addr:#x} overlaps the one before it")]
+    OverlappingBuffers { addr: u64 },
+    #[error("invalid memory map description: {reason}")]
+    InvalidConfig { reason: String },
+    #[error("bitfield width {width} is not between 1 and 64")]
+    InvalidBitWidth { width: u32 },
+    #[error("address {addr:#x} is in read-only region {region:?}")]
+    ReadOnly { addr: u64, region: String },
+    #[error("access to {addr:#x} hit guard {guard:?}")]
+    GuardHit { addr: u64, guard: String },
+    #[error("address {addr:#x} is not aligned to the {alignment} byte value")]
+    UnalignedValue { addr: u64, alignment: u64 },
+    #[error("checksum {name:?} would be stored at {addr:#x}, inside the range it covers")]
+    ChecksumFieldInRange { name: String, addr: u64 },
+    #[error("checksum {name:?} would be stored inside a range that depends on it through {through:?}")]
+    ChecksumCycle { name: String, through: String },
+    #[error("address {addr:#x} can't be expressed in a {format} patch")]
+    UnrepresentableAddress { addr: u64, format: &'static str },
+    #[error("invalid write journal: {reason}")]
+    InvalidJournal { reason: String },
+    #[error("replayed write expected different data at {addr:#x}")]
+    JournalMismatch { addr: u64 },
+    #[error("can't rewind {back} snapshots with {available} held")]
+    SnapshotUnavailable { back: usize, available: usize },
+    #[error("invalid delta: {reason}")]
+    InvalidDelta { reason: String },
+    #[error("invalid access trace: {reason}")]
+    InvalidTrace { reason: String },
+    #[error("traced read {index} at {addr:#x} returned different data")]
+    TraceMismatch { index: usize, addr: u64 },
+    #[error("backend {operation} at {offset:#x} timed out after {attempts} attempts")]
+    BackendTimeout { operation: &'static str, offset: u64, attempts: u32 },
+    #[error("backend {operation} at {offset:#x} still failing after {attempts} attempts: {last}")]
+    RetriesExhausted { operation: &'static str, offset: u64, attempts: u32, kind: ErrorKind, last: String },
+    #[error("pointer size must be 4 or 8 bytes, not {size}")]
+    InvalidPointerSize { size: u8 },
+    #[error("{text:?} can't be encoded as {encoding}")]
+    UnencodableText { text: String, encoding: &'static str },
+    #[error("invalid regex: {reason}")]
+    InvalidRegex { reason: String },
+    #[error("disassembly failed: {reason}")]
+    Disassembly { reason: String },
+    #[error("{}", shadow_mismatch(.ranges))]
+    ShadowMismatch { ranges: Vec<std::ops::Range<u64>> },
+    #[error("invalid hex at {position}: {reason}")]
+    InvalidHex { position: usize, reason: String },
+    #[error("invalid hex file at line {line}: {reason}")]
+    InvalidHexFile { line: usize, reason: String },
+    #[error("invalid controller pak: {reason}")]
+    InvalidMempak { reason: String },
+    #[error("note needs {pages} pages but only {free} are free")]
+    MempakFull { pages: usize, free: usize },
+    #[error("all 16 note slots are in use")]
+    NoteTableFull,
+    #[error("invalid block order: {reason}")]
+    InvalidBlockOrder { reason: String },
+    #[error("{value} doesn't fit in {bits} bits")]
+    ValueOutOfRange { value: i128, bits: u32 },
+    #[error("{value} can't be stored as fixed point with {fraction_bits} fraction bits")]
+    UnrepresentableFixed { value: String, fraction_bits: u32 },
+    #[error("color {rgba:?} is not in the palette")]
+    ColorNotInPalette { rgba: [u8; 4] },
+    #[error("invalid transform: {reason}")]
+    InvalidTransform { reason: String },
+    #[error("scratch buffer of {len} bytes, at least {needed} needed")]
+    ScratchTooSmall { len: usize, needed: usize },
+    #[error("invalid memory descriptor {index}: {reason}")]
+    InvalidMemoryDescriptor { index: usize, reason: String },
+    #[error("unknown memory domain {name:?}")]
+    UnknownDomain { name: String },
+    #[error("bridge request failed: {reason}")]
+    BridgeFailed { reason: String },
+    #[error("invalid bridge message: {reason}")]
+    InvalidBridgeMessage { reason: String },
+    #[error("invalid RAM recording: {reason}")]
+    InvalidRecording { reason: String },
+    #[error("frame {frame} of a {frames} frame recording")]
+    FrameOutOfRange { frame: u64, frames: u64 },
+    #[error("invalid scan session: {reason}")]
+    InvalidScanSession { reason: String },
+    #[error("range {start:#x}..{end:#x} ends before it starts")]
+    ReversedRange { start: u64, end: u64 },
+}
+
+// the message of `ShadowMismatch`, which may be built with no ranges
+fn shadow_mismatch(ranges: &[std::ops::Range<u64>]) -> String {
+    match ranges.first() {
+        Some(first) => format!("{} storage ranges don't hold what was written, the first at {:#x}", ranges.len(), first.start),
+        None => "storage doesn't hold what was written".to_string(),
+    }
+}
+
+impl ReversedWordsError {
+    /// The structured error inside an `io::Error` returned by this crate, if there is one,
+    /// looking through any [`ErrorContext`] wrapped around it.
+    pub fn from_io(error: &std::io::Error) -> Option<&ReversedWordsError> {
+        if let Some(context) = ErrorContext::from_io(error) {
+            return ReversedWordsError::from_io(context.source());
+        }
+        let inner = error.get_ref()?;
+        match inner.downcast_ref::<Sourced>() {
+            Some(sourced) => Some(&sourced.error),
+            None => inner.downcast_ref(),
+        }
+    }
+
+    /// This error as an `io::Error` whose `source()` is `source`, the backend failure that
+    /// caused it.
+    pub fn with_source(self, source: std::io::Error) -> std::io::Error {
+        std::io::Error::new(self.kind(), Sourced { error: self, source })
+    }
+
+    /// The error a backend originally returned, under every [`ErrorContext`] and
+    /// [`with_source`](ReversedWordsError::with_source) cause wrapped around it by this crate.
+    /// An error that didn't come from a backend is its own root cause.
+    ///
+    /// Backends with their own error types can have them back with
+    /// `root_cause(&error).get_ref()` and `downcast_ref`.
+    pub fn root_cause(error: &std::io::Error) -> &std::io::Error {
+        if let Some(context) = ErrorContext::from_io(error) {
+            return ReversedWordsError::root_cause(context.source());
+        }
+        match error.get_ref().and_then(|inner| inner.downcast_ref::<Sourced>()) {
+            Some(sourced) => ReversedWordsError::root_cause(&sourced.source),
+            None => error,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ReversedWordsError::AddressOutOfRange { .. } => ErrorKind::UnexpectedEof,
+            ReversedWordsError::UnknownSavestateFormat
+            | ReversedWordsError::TruncatedSavestate { .. }
+            | ReversedWordsError::CompressedSavestate
+            | ReversedWordsError::InvalidSymbolFile { .. }
+            | ReversedWordsError::InvalidConfig { .. }
+            | ReversedWordsError::InvalidJournal { .. }
+            | ReversedWordsError::JournalMismatch { .. }
+            | ReversedWordsError::InvalidDelta { .. }
+            | ReversedWordsError::InvalidTrace { .. }
+            | ReversedWordsError::TraceMismatch { .. }
+            | ReversedWordsError::ShadowMismatch { .. }
+            | ReversedWordsError::InvalidHexFile { .. }
+            | ReversedWordsError::InvalidMempak { .. }
+            | ReversedWordsError::InvalidBridgeMessage { .. }
+            | ReversedWordsError::InvalidRecording { .. }
+            | ReversedWordsError::InvalidScanSession { .. } => ErrorKind::InvalidData,
+            ReversedWordsError::UnknownSymbol { .. } | ReversedWordsError::UnknownDomain { .. } => ErrorKind::NotFound,
+            ReversedWordsError::BridgeFailed { .. } => ErrorKind::Other,
+            ReversedWordsError::ReadOnly { .. } | ReversedWordsError::GuardHit { .. } => ErrorKind::PermissionDenied,
+            ReversedWordsError::BackendTimeout { .. } => ErrorKind::TimedOut,
+            ReversedWordsError::MempakFull { .. } | ReversedWordsError::NoteTableFull => ErrorKind::StorageFull,
+            ReversedWordsError::RetriesExhausted { kind, .. } => *kind,
+            _ => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+impl From<ReversedWordsError> for std::io::Error {
+    fn from(error: ReversedWordsError) -> Self {
+        std::io::Error::new(error.kind(), error)
+    }
+}
+
+// a `ReversedWordsError` caused by a backend failure
+#[derive(Debug, thiserror::Error)]
+#[error("{error}")]
+struct Sourced {
+    error: ReversedWordsError,
+    source: std::io::Error,
+}
+
+/// Where a failure from a [`MemoryBackend`](crate::backend::MemoryBackend) happened: what was
+/// being done to which logical bytes, through which backend. It becomes an `io::Error` of the
+/// same kind as the backend's, which stays available as [`ErrorContext::source`].
+#[derive(Debug, thiserror::Error)]
+#[error("{operation} of {len} bytes at {addr:#x} through {backend} failed: {source}")]
+pub struct ErrorContext {
+    operation: &'static str,
+    addr: u64,
+    len: usize,
+    backend: String,
+    source: std::io::Error,
+}
+
+impl ErrorContext {
+    pub fn new(operation: &'static str, addr: u64, len: usize, backend: &str, source: std::io::Error) -> ErrorContext {
+        ErrorContext { operation, addr, len, backend: backend.to_string(), source }
+    }
+
+    /// The context inside an `io::Error`, if there is one.
+    pub fn from_io(error: &std::io::Error) -> Option<&ErrorContext> {
+        error.get_ref().and_then(|inner| inner.downcast_ref())
+    }
+
+    /// `"read"` or `"write"`.
+    pub fn operation(&self) -> &'static str {
+        self.operation
+    }
+
+    /// The logical address of the access.
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The [`name`](crate::backend::MemoryBackend::name) of the backend that failed.
+    pub fn backend(&self) -> &str {
+        &self.backend
+    }
+
+    /// The error the backend returned.
+    pub fn source(&self) -> &std::io::Error {
+        &self.source
+    }
+
+    pub fn into_source(self) -> std::io::Error {
+        self.source
+    }
+}
+
+impl From<ErrorContext> for std::io::Error {
+    fn from(context: ErrorContext) -> Self {
+        std::io::Error::new(context.source.kind(), context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn structured_error_survives_io_conversion() {
+        let mut data = vec![0u8; 8];
+        let ram = ReversedWords::new(&mut data);
+        let error = ram.get_byte(9).unwrap_err();
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, error.kind());
+        assert_eq!(
+            Some(&ReversedWordsError::AddressOutOfRange { addr: 9, len: 8 }),
+            ReversedWordsError::from_io(&error)
+        );
+        assert_eq!("address 0x9 is outside of the 8 byte buffer", error.to_string());
+    }
+
+    #[test]
+    fn shadow_mismatch_messages() {
+        assert_eq!(
+            "2 storage ranges don't hold what was written, the first at 0x4",
+            ReversedWordsError::ShadowMismatch { ranges: vec![4..5, 10..12] }.to_string()
+        );
+        assert_eq!("storage doesn't hold what was written", ReversedWordsError::ShadowMismatch { ranges: vec![] }.to_string());
+    }
+
+    #[test]
+    fn zero_word_size_is_rejected() {
+        let mut data = vec![0u8; 8];
+        assert_eq!(
+            Some(ReversedWordsError::InvalidWordSize),
+            ReversedWords::try_new_with_word_size(&mut data, 0).err()
+        );
+    }
+
+    #[test]
+    fn backend_failures_carry_context() {
+        let mock = mock::MockBackend::new(8).expect_read_error(4, 4, std::io::ErrorKind::ConnectionReset);
+        let mut words = backend::ReversedBackend::new(mock, 4).unwrap();
+        let error = words.read_value_at::<u16>(5, Endian::Big).unwrap_err();
+        assert_eq!(std::io::ErrorKind::ConnectionReset, error.kind());
+        let context = ErrorContext::from_io(&error).unwrap();
+        assert_eq!(("read", 5, 2, "mock"), (context.operation(), context.addr(), context.len(), context.backend()));
+        assert_eq!(std::io::ErrorKind::ConnectionReset, context.source().kind());
+        assert_eq!("read of 2 bytes at 0x5 through mock failed: connection reset", error.to_string());
+
+        let mock = mock::MockBackend::new(8)
+            .expect_read_error(0, 4, std::io::ErrorKind::TimedOut)
+            .expect_read_error(0, 4, std::io::ErrorKind::TimedOut);
+        let policy = backend::RetryPolicy::default().with_attempts(2).with_backoff(std::time::Duration::ZERO, std::time::Duration::ZERO);
+        let mut words = backend::ReversedBackend::new(backend::RetryBackend::new(mock, policy), 4).unwrap();
+        let error = words.read_value_at::<u8>(0, Endian::Big).unwrap_err();
+        assert!(matches!(ReversedWordsError::from_io(&error), Some(ReversedWordsError::RetriesExhausted { attempts: 2, .. })));
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("stub disconnected")]
+    struct Disconnected;
+
+    struct Dropping;
+
+    impl backend::MemoryBackend for Dropping {
+        fn len(&self) -> u64 {
+            8
+        }
+
+        fn read_raw(&mut self, _offset: u64, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, Disconnected))
+        }
+
+        fn write_raw(&mut self, _offset: u64, _buf: &[u8]) -> std::io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn backend_errors_are_chained_as_sources() {
+        let policy = backend::RetryPolicy::default().with_attempts(2).with_backoff(std::time::Duration::ZERO, std::time::Duration::ZERO);
+        let mut words = backend::ReversedBackend::new(backend::RetryBackend::new(Dropping, policy), 4).unwrap();
+        let error = words.read_value_at::<u32>(0, Endian::Big).unwrap_err();
+        assert_eq!(std::io::ErrorKind::ConnectionReset, error.kind());
+        assert!(matches!(ReversedWordsError::from_io(&error), Some(ReversedWordsError::RetriesExhausted { attempts: 2, .. })));
+        let root = ReversedWordsError::root_cause(&error);
+        assert!(root.get_ref().unwrap().downcast_ref::<Disconnected>().is_some());
+
+        // the standard chain reaches the same error
+        let mut source = std::error::Error::source(&error);
+        let mut found = false;
+        while let Some(error) = source {
+            found |= error.downcast_ref::<std::io::Error>().and_then(|error| error.get_ref()).is_some_and(|inner| inner.is::<Disconnected>());
+            source = error.source();
+        }
+        assert!(found);
+
+        let error: std::io::Error = ReversedWordsError::InvalidWordSize.into();
+        assert!(std::ptr::eq(&error, ReversedWordsError::root_cause(&error)));
+    }
+}
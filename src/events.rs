@@ -0,0 +1,178 @@
+//! One channel based feed for everything that reports memory activity.
+//!
+//! An [`EventBus`] fans [`Event`]s out to any number of subscribers, each a plain
+//! [`std::sync::mpsc::Receiver`], optionally limited to an address range so a subscriber acts
+//! like a watchpoint. [`Watcher::poll_into`] publishes its changes to a bus and
+//! [`RecordingWords::publish_to`](crate::journal::RecordingWords::publish_to) publishes every write
+//! it makes, so consumers read one stream of typed events instead of wiring a callback into each
+//! source. Subscribers that hang up are dropped on the next publish.
+
+use std::{
+    ops::Range,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+
+use crate::{
+    journal::WriteRecord,
+    trigger::Fired,
+    watch::{Change, WatchSource, Watcher},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A watched range differed between two polls.
+    Change(Change),
+    /// A write made through a publishing view.
+    Write(WriteRecord),
+    /// A trigger's condition became true, published by an [`Action::Publish`](crate::trigger::Action::Publish).
+    Trigger(Fired),
+}
+
+impl Event {
+    /// The logical addresses the event covers.
+    pub fn range(&self) -> Range<u64> {
+        match self {
+            Event::Change(change) => change.addr..change.addr + change.new.len() as u64,
+            Event::Write(record) => record.addr..record.addr + record.new.len() as u64,
+            Event::Trigger(fired) => fired.addr..fired.addr + fired.bytes.len() as u64,
+        }
+    }
+}
+
+enum Sink {
+    Channel(Sender<Event>),
+    #[cfg(feature = "tokio")]
+    Async(tokio::sync::mpsc::UnboundedSender<Event>),
+}
+
+struct Subscriber {
+    sink: Sink,
+    range: Option<Range<u64>>,
+}
+
+impl Subscriber {
+    // false once the receiving end is gone
+    fn send(&self, event: Event) -> bool {
+        match &self.sink {
+            Sink::Channel(sender) => sender.send(event).is_ok(),
+            #[cfg(feature = "tokio")]
+            Sink::Async(sender) => sender.send(event).is_ok(),
+        }
+    }
+
+    fn wants(&self, event: &Event) -> bool {
+        self.range.as_ref().is_none_or(|range| {
+            let covered = event.range();
+            covered.start < range.end && range.start < covered.end
+        })
+    }
+}
+
+/// A cheaply cloneable handle to a set of subscribers; every clone publishes to the same set.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus::default()
+    }
+
+    fn add(&self, range: Option<Range<u64>>) -> Receiver<Event> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().expect("poisoned").push(Subscriber { sink: Sink::Channel(sender), range });
+        receiver
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn add_async(&self, range: Option<Range<u64>>) -> tokio::sync::mpsc::UnboundedReceiver<Event> {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.subscribers.lock().expect("poisoned").push(Subscriber { sink: Sink::Async(sender), range });
+        receiver
+    }
+
+    /// Receive every event published from now on.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.add(None)
+    }
+
+    /// Receive only the events that touch `range`.
+    pub fn subscribe_range(&self, range: Range<u64>) -> Receiver<Event> {
+        self.add(Some(range))
+    }
+
+    /// Number of subscribers still connected as of the last publish.
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.lock().expect("poisoned").len()
+    }
+
+    /// Send `event` to every interested subscriber, returning how many received it.
+    pub fn publish(&self, event: Event) -> usize {
+        let mut subscribers = self.subscribers.lock().expect("poisoned");
+        let mut delivered = 0;
+        subscribers.retain(|subscriber| {
+            if !subscriber.wants(&event) {
+                return true;
+            }
+            let sent = subscriber.send(event.clone());
+            delivered += sent as usize;
+            sent
+        });
+        delivered
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus").field("subscribers", &self.subscribers()).finish()
+    }
+}
+
+impl Watcher {
+    /// [`Watcher::poll`], publishing each change to `bus` instead of returning it. Returns how
+    /// many ranges changed.
+    pub fn poll_into<S: WatchSource + ?Sized>(&mut self, source: &S, bus: &EventBus) -> std::io::Result<usize> {
+        let mut count = 0;
+        self.poll_with(source, |change| {
+            count += 1;
+            bus.publish(Event::Change(change));
+        })?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{events::*, journal::RecordingWords, Endian, ReversedWords};
+
+    #[test]
+    fn watcher_and_writes_share_a_bus() {
+        let bus = EventBus::new();
+        let everything = bus.subscribe();
+        let health = bus.subscribe_range(0..2);
+        let mut data = vec![0u8; 8];
+        let mut words = ReversedWords::new(&mut data);
+        let mut watcher = Watcher::new();
+        watcher.watch(4..8);
+        watcher.poll_into(&words, &bus).unwrap();
+
+        let mut recording = RecordingWords::new(&mut words).publish_to(bus.clone());
+        recording.write_value_at(0, 100u16, Endian::Big).unwrap();
+        recording.write_at(4, &[1]).unwrap();
+        assert_eq!(1, watcher.poll_into(&*recording, &bus).unwrap());
+
+        let events: Vec<Event> = everything.try_iter().collect();
+        assert_eq!(3, events.len());
+        assert_eq!(Event::Write(WriteRecord { addr: 0, old: vec![0, 0], new: vec![0, 100] }), events[0]);
+        assert!(matches!(&events[2], Event::Change(change) if change.new == [1, 0, 0, 0]));
+        assert_eq!(vec![events[0].clone()], health.try_iter().collect::<Vec<_>>());
+
+        drop(health);
+        recording.write_at(0, &[7]).unwrap();
+        assert_eq!(1, bus.subscribers());
+    }
+}
@@ -0,0 +1,207 @@
+//! Exporting a memory analysis session to static reverse engineering tools.
+//!
+//! A [`ScriptExport`] collects labels, scan hits and pointers found in a view and writes them out
+//! as a Ghidra Python script or an IDA IDC script. Run against a program loaded at the same
+//! virtual addresses, the script names and types the labelled addresses, comments them, and
+//! bookmarks (Ghidra) or comments (IDA) the hits. View offsets are turned back into virtual
+//! addresses through an [`AddressMap`]; anything it doesn't map is left out.
+
+use std::fmt::Write as _;
+
+use crate::{expr::ValueType, labels::Labels, pointer::PointerCandidate, scan::ScanResults, translate::AddressMap, Primitive};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptFormat {
+    /// A Python script for Ghidra's script manager, using the flat API.
+    GhidraPython,
+    /// An IDC script for IDA.
+    IdaIdc,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Item {
+    Label { name: String, value_type: Option<ValueType>, comment: Option<String> },
+    Bookmark { category: String, note: String },
+    Pointer { target: u64 },
+}
+
+#[derive(Clone, Debug)]
+pub struct ScriptExport {
+    map: AddressMap,
+    // virtual address and what to do there, in the order added
+    items: Vec<(u64, Item)>,
+}
+
+// `text` as a double quoted string literal, valid in both Python and IDC
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl ScriptExport {
+    pub fn new(map: AddressMap) -> ScriptExport {
+        ScriptExport { map, items: Vec::new() }
+    }
+
+    /// Name every label where it starts, typed and commented if the label is.
+    pub fn add_labels(&mut self, labels: &Labels) {
+        for label in labels.iter() {
+            if let Some(addr) = self.map.virtual_address(label.range.start) {
+                self.items.push((addr, Item::Label { name: label.name.clone(), value_type: label.value_type, comment: label.comment.clone() }));
+            }
+        }
+    }
+
+    /// Bookmark every address in `results` under `category`.
+    pub fn add_scan_results<T: Primitive>(&mut self, results: &ScanResults<T>, category: &str) {
+        for offset in results.addresses() {
+            if let Some(addr) = self.map.virtual_address(offset) {
+                self.items.push((addr, Item::Bookmark { category: category.to_string(), note: format!("{} scan hit", std::any::type_name::<T>()) }));
+            }
+        }
+    }
+
+    /// Mark each candidate's storage as a pointer, commented with where it points.
+    pub fn add_pointers(&mut self, pointers: &[PointerCandidate]) {
+        for pointer in pointers {
+            if let Some(addr) = self.map.virtual_address(pointer.addr) {
+                self.items.push((addr, Item::Pointer { target: pointer.value }));
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn to_script(&self, format: ScriptFormat) -> String {
+        match format {
+            ScriptFormat::GhidraPython => self.to_ghidra(),
+            ScriptFormat::IdaIdc => self.to_idc(),
+        }
+    }
+
+    fn to_ghidra(&self) -> String {
+        let mut script = String::from("# Labels exported from a memory analysis session\nfrom ghidra.program.model.symbol import SourceType\n\n");
+        for (addr, item) in &self.items {
+            let _ = match item {
+                Item::Label { name, value_type, comment } => {
+                    let _ = writeln!(script, "createLabel(toAddr({:#x}), {}, True, SourceType.USER_DEFINED)", addr, quote(name));
+                    if let Some(value_type) = value_type {
+                        let create = match value_type {
+                            ValueType::U8 | ValueType::I8 => "createByte",
+                            ValueType::U16 | ValueType::I16 => "createWord",
+                            ValueType::U32 | ValueType::I32 => "createDWord",
+                            ValueType::U64 | ValueType::I64 => "createQWord",
+                            ValueType::F32 => "createFloat",
+                            ValueType::F64 => "createDouble",
+                        };
+                        let _ = writeln!(script, "clearListing(toAddr({:#x}), toAddr({:#x}))", addr, addr + value_type.size() - 1);
+                        let _ = writeln!(script, "{}(toAddr({:#x}))", create, addr);
+                    }
+                    match comment {
+                        Some(comment) => writeln!(script, "setEOLComment(toAddr({:#x}), {})", addr, quote(comment)),
+                        None => Ok(()),
+                    }
+                }
+                Item::Bookmark { category, note } => writeln!(script, "createBookmark(toAddr({:#x}), {}, {})", addr, quote(category), quote(note)),
+                Item::Pointer { target } => writeln!(script, "setEOLComment(toAddr({:#x}), {})", addr, quote(&format!("pointer to {:#x}", target))),
+            };
+        }
+        script
+    }
+
+    fn to_idc(&self) -> String {
+        let mut script = String::from("// Labels exported from a memory analysis session\n#include <idc.idc>\n\nstatic main() {\n");
+        for (addr, item) in &self.items {
+            let _ = match item {
+                Item::Label { name, value_type, comment } => {
+                    let _ = writeln!(script, "    set_name({:#x}, {}, SN_NOWARN);", addr, quote(name));
+                    if let Some(value_type) = value_type {
+                        let create = match value_type {
+                            ValueType::U8 | ValueType::I8 => "create_byte",
+                            ValueType::U16 | ValueType::I16 => "create_word",
+                            ValueType::U32 | ValueType::I32 => "create_dword",
+                            ValueType::U64 | ValueType::I64 => "create_qword",
+                            ValueType::F32 => "create_float",
+                            ValueType::F64 => "create_double",
+                        };
+                        let _ = writeln!(script, "    {}({:#x});", create, addr);
+                    }
+                    match comment {
+                        Some(comment) => writeln!(script, "    set_cmt({:#x}, {}, 0);", addr, quote(comment)),
+                        None => Ok(()),
+                    }
+                }
+                Item::Bookmark { category, note } => writeln!(script, "    set_cmt({:#x}, {}, 1);", addr, quote(&format!("{}: {}", category, note))),
+                Item::Pointer { target } => writeln!(script, "    create_dword({:#x});\n    op_plain_offset({:#x}, 0, 0);\n    set_cmt({:#x}, {}, 1);", addr, addr, addr, quote(&format!("pointer to {:#x}", target))),
+            };
+        }
+        script.push_str("}\n");
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{export::*, labels::Label, scan::ValueScan, Endian, ReversedWords};
+
+    fn session() -> ScriptExport {
+        let mut labels = Labels::new();
+        labels.insert(Label::new("gPlayerHealth", 0x14..0x16).with_value_type(ValueType::I16).with_comment("half \"points\""));
+        labels.insert(Label::new("main", 0x20..0x40));
+        let mut data = vec![0u8; 0x40];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_u32_at(0x30, 99, Endian::Big).unwrap();
+        let hits = ValueScan::<u32>::new().scan(&words, |value| value == 99);
+
+        let mut export = ScriptExport::new(AddressMap::n64(0x40));
+        export.add_labels(&labels);
+        export.add_scan_results(&hits, "health search");
+        export.add_pointers(&[PointerCandidate { addr: 0x18, value: 0x8000_0020, target: 0x20 }, PointerCandidate { addr: 0x100, value: 0, target: 0 }]);
+        export
+    }
+
+    #[test]
+    fn ghidra_script() {
+        let export = session();
+        assert_eq!(4, export.len());
+        assert_eq!(
+            "# Labels exported from a memory analysis session\n\
+             from ghidra.program.model.symbol import SourceType\n\n\
+             createLabel(toAddr(0x80000014), \"gPlayerHealth\", True, SourceType.USER_DEFINED)\n\
+             clearListing(toAddr(0x80000014), toAddr(0x80000015))\n\
+             createWord(toAddr(0x80000014))\n\
+             setEOLComment(toAddr(0x80000014), \"half \\\"points\\\"\")\n\
+             createLabel(toAddr(0x80000020), \"main\", True, SourceType.USER_DEFINED)\n\
+             createBookmark(toAddr(0x80000030), \"health search\", \"u32 scan hit\")\n\
+             setEOLComment(toAddr(0x80000018), \"pointer to 0x80000020\")\n",
+            export.to_script(ScriptFormat::GhidraPython)
+        );
+    }
+
+    #[test]
+    fn idc_script() {
+        let script = session().to_script(ScriptFormat::IdaIdc);
+        assert!(script.starts_with("// Labels exported from a memory analysis session\n#include <idc.idc>\n\nstatic main() {\n"));
+        assert!(script.contains("    set_name(0x80000014, \"gPlayerHealth\", SN_NOWARN);\n    create_word(0x80000014);\n    set_cmt(0x80000014, \"half \\\"points\\\"\", 0);\n"));
+        assert!(script.contains("    set_cmt(0x80000030, \"health search: u32 scan hit\", 1);\n"));
+        assert!(script.contains("    op_plain_offset(0x80000018, 0, 0);\n"));
+        assert!(script.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,378 @@
+//! A small address expression language, for letting users type locations into a UI.
+//!
+//! ```text
+//! u16:[[0x80371240]+0x24]
+//! ```
+//!
+//! reads the pointer at `0x80371240`, adds `0x24`, reads the pointer there and finally reads a
+//! `u16` at the address it holds. Expressions are:
+//!
+//! - an optional type prefix (`u8`..`u64`, `i8`..`i64`, `f32`, `f64`) followed by `:`, `u32` if omitted
+//! - numbers, decimal or `0x` hex
+//! - symbol names, resolved through the [`Evaluator`]'s [`SymbolTable`]
+//! - `[addr]`, which dereferences a pointer
+//! - `+` and `-`
+//!
+//! Addresses are virtual and go through the evaluator's [`AddressMap`] whenever memory is read.
+//! Brackets and parentheses nest at most [`MAX_NESTING`] deep.
+
+use std::str::FromStr;
+
+use binread::Endian;
+
+use crate::{symbols::SymbolTable, translate::AddressMap, ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl ValueType {
+    /// Size in bytes.
+    pub fn size(self) -> u64 {
+        match self {
+            ValueType::U8 | ValueType::I8 => 1,
+            ValueType::U16 | ValueType::I16 => 2,
+            ValueType::U32 | ValueType::I32 | ValueType::F32 => 4,
+            ValueType::U64 | ValueType::I64 | ValueType::F64 => 8,
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<ValueType> {
+        Some(match name {
+            "u8" => ValueType::U8,
+            "u16" => ValueType::U16,
+            "u32" => ValueType::U32,
+            "u64" => ValueType::U64,
+            "i8" => ValueType::I8,
+            "i16" => ValueType::I16,
+            "i32" => ValueType::I32,
+            "i64" => ValueType::I64,
+            "f32" => ValueType::F32,
+            "f64" => ValueType::F64,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    Number(u64),
+    Symbol(String),
+    Deref(Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Sub(Box<Node>, Box<Node>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expression {
+    value_type: ValueType,
+    address: Node,
+}
+
+/// How deeply `[...]` and `(...)` may nest in an expression.
+pub const MAX_NESTING: usize = 64;
+
+struct Parser<'s> {
+    source: &'s str,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &str) -> ReversedWordsError {
+        ReversedWordsError::InvalidExpression { position: self.position, reason: reason.to_string() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.source[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.source[self.position..].chars().next()
+    }
+
+    fn word(&mut self) -> &str {
+        self.skip_whitespace();
+        let rest = &self.source[self.position..];
+        let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$')).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn sum(&mut self) -> Result<Node, ReversedWordsError> {
+        let mut node = self.term()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.position += 1;
+                    node = Node::Add(Box::new(node), Box::new(self.term()?));
+                }
+                Some('-') => {
+                    self.position += 1;
+                    node = Node::Sub(Box::new(node), Box::new(self.term()?));
+                }
+                _ => return Ok(node),
+            }
+        }
+    }
+
+    /// A bracketed sub-expression, after its opening bracket.
+    fn nested(&mut self) -> Result<Node, ReversedWordsError> {
+        if self.depth == MAX_NESTING {
+            return Err(self.error("expression nested too deeply"));
+        }
+        self.depth += 1;
+        let inner = self.sum();
+        self.depth -= 1;
+        inner
+    }
+
+    fn term(&mut self) -> Result<Node, ReversedWordsError> {
+        match self.peek() {
+            Some('[') => {
+                self.position += 1;
+                let inner = self.nested()?;
+                if self.peek() != Some(']') {
+                    return Err(self.error("expected `]`"));
+                }
+                self.position += 1;
+                Ok(Node::Deref(Box::new(inner)))
+            }
+            Some('(') => {
+                self.position += 1;
+                let inner = self.nested()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("expected `)`"));
+                }
+                self.position += 1;
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let start = self.position;
+                let word = self.word();
+                let number = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => word.parse(),
+                };
+                number.map(Node::Number).map_err(|_| {
+                    self.position = start;
+                    self.error("invalid number")
+                })
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '.' || c == '$' => Ok(Node::Symbol(self.word().to_string())),
+            _ => Err(self.error("expected a number, symbol or `[`")),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, ReversedWordsError> {
+        let (value_type, position) = match source.split_once(':') {
+            Some((prefix, _)) => match ValueType::from_name(prefix.trim()) {
+                Some(value_type) => (value_type, prefix.len() + 1),
+                None => return Err(ReversedWordsError::InvalidExpression { position: 0, reason: format!("unknown type {:?}", prefix.trim()) }),
+            },
+            None => (ValueType::U32, 0),
+        };
+        let mut parser = Parser { source, position, depth: 0 };
+        let address = parser.sum()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        Ok(Expression { value_type, address })
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ReversedWordsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Expression::parse(s)
+    }
+}
+
+/// How expressions are evaluated against a view.
+#[derive(Clone, Debug)]
+pub struct Evaluator {
+    address_map: AddressMap,
+    symbols: SymbolTable,
+    endian: Endian,
+    pointer_size: u8,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Evaluator { address_map: AddressMap::identity(), symbols: SymbolTable::new(), endian: Endian::Big, pointer_size: 4 }
+    }
+}
+
+impl Evaluator {
+    /// Identity address map, no symbols, big endian 4-byte pointers.
+    pub fn new() -> Evaluator {
+        Evaluator::default()
+    }
+
+    pub fn with_address_map(mut self, address_map: AddressMap) -> Evaluator {
+        self.address_map = address_map;
+        self
+    }
+
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Evaluator {
+        self.symbols = symbols;
+        self
+    }
+
+    pub fn with_endian(mut self, endian: Endian) -> Evaluator {
+        self.endian = endian;
+        self
+    }
+
+    /// Size of the pointers read by `[...]`, 4 or 8 bytes. Any other size fails with
+    /// [`ReversedWordsError::InvalidPointerSize`].
+    pub fn with_pointer_size(mut self, pointer_size: u8) -> Result<Evaluator, ReversedWordsError> {
+        if pointer_size != 4 && pointer_size != 8 {
+            return Err(ReversedWordsError::InvalidPointerSize { size: pointer_size });
+        }
+        self.pointer_size = pointer_size;
+        Ok(self)
+    }
+
+    fn address(&self, words: &mut ReversedWords, node: &Node) -> std::io::Result<u64> {
+        Ok(match node {
+            Node::Number(n) => *n,
+            Node::Symbol(name) => self
+                .symbols
+                .address_of(name)
+                .ok_or_else(|| ReversedWordsError::UnknownSymbol { name: name.clone() })?,
+            Node::Deref(inner) => {
+                let offset = self.address_map.translate(self.address(words, inner)?)?;
+                match self.pointer_size {
+                    8 => words.read_u64_at(offset, self.endian)?,
+                    _ => words.read_u32_at(offset, self.endian)? as u64,
+                }
+            }
+            Node::Add(a, b) => self.address(words, a)?.wrapping_add(self.address(words, b)?),
+            Node::Sub(a, b) => self.address(words, a)?.wrapping_sub(self.address(words, b)?),
+        })
+    }
+
+    /// The virtual address `expression` refers to, following any dereferences.
+    pub fn address_of(&self, words: &mut ReversedWords, expression: &Expression) -> std::io::Result<u64> {
+        self.address(words, &expression.address)
+    }
+
+    pub fn evaluate(&self, words: &mut ReversedWords, expression: &Expression) -> std::io::Result<Value> {
+        let offset = self.address_map.translate(self.address_of(words, expression)?)?;
+        let endian = self.endian;
+        Ok(match expression.value_type {
+            ValueType::U8 => Value::Unsigned(words.read_u8_at(offset, endian)? as u64),
+            ValueType::U16 => Value::Unsigned(words.read_u16_at(offset, endian)? as u64),
+            ValueType::U32 => Value::Unsigned(words.read_u32_at(offset, endian)? as u64),
+            ValueType::U64 => Value::Unsigned(words.read_u64_at(offset, endian)?),
+            ValueType::I8 => Value::Signed(words.read_i8_at(offset, endian)? as i64),
+            ValueType::I16 => Value::Signed(words.read_i16_at(offset, endian)? as i64),
+            ValueType::I32 => Value::Signed(words.read_i32_at(offset, endian)? as i64),
+            ValueType::I64 => Value::Signed(words.read_i64_at(offset, endian)?),
+            ValueType::F32 => Value::Float(words.read_f32_at(offset, endian)? as f64),
+            ValueType::F64 => Value::Float(words.read_f64_at(offset, endian)?),
+        })
+    }
+
+    /// Parse and evaluate `source` in one go.
+    pub fn evaluate_str(&self, words: &mut ReversedWords, source: &str) -> std::io::Result<Value> {
+        let expression = Expression::parse(source)?;
+        self.evaluate(words, &expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expr::*, translate::AddressMap};
+
+    fn rdram() -> Vec<u8> {
+        let mut data = vec![0u8; 0x40];
+        let mut words = ReversedWords::new(&mut data);
+        // pointer at 0x80000010 -> 0x80000020; pointer at 0x80000020 + 4 -> 0x80000030
+        words.write_u32_at(0x10, 0x8000_0020, Endian::Big).unwrap();
+        words.write_u32_at(0x24, 0x8000_0030, Endian::Big).unwrap();
+        words.write_u16_at(0x30, 0xBEEF, Endian::Big).unwrap();
+        words.write_i8_at(0x34, -2, Endian::Big).unwrap();
+        data
+    }
+
+    #[test]
+    fn nested_dereference() {
+        let mut data = rdram();
+        let mut words = ReversedWords::new(&mut data);
+        let evaluator = Evaluator::new().with_address_map(AddressMap::n64(0x40));
+        assert_eq!(Value::Unsigned(0xBEEF), evaluator.evaluate_str(&mut words, "u16:[[0x80000010]+0x4]").unwrap());
+        assert_eq!(Value::Signed(-2), evaluator.evaluate_str(&mut words, "i8: [[ 0x80000010 ] + 4] + 4").unwrap());
+        assert_eq!(Value::Unsigned(0x8000_0030), evaluator.evaluate_str(&mut words, "[0x80000010] + 0x4").unwrap());
+    }
+
+    #[test]
+    fn symbols_in_expressions() {
+        let mut data = rdram();
+        let mut words = ReversedWords::new(&mut data);
+        let mut symbols = SymbolTable::new();
+        symbols.insert("gPlayer", 0x8000_0010);
+        let evaluator = Evaluator::new().with_address_map(AddressMap::n64(0x40)).with_symbols(symbols);
+        assert_eq!(Value::Unsigned(0xBEEF), evaluator.evaluate_str(&mut words, "u16:[[gPlayer]+4]").unwrap());
+        let error = evaluator.evaluate_str(&mut words, "[gMissing]").unwrap_err();
+        assert_eq!(std::io::ErrorKind::NotFound, error.kind());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err(ReversedWordsError::InvalidExpression { position: 0, reason: "unknown type \"u7\"".to_string() }),
+            Expression::parse("u7:0x10")
+        );
+        assert_eq!(
+            Err(ReversedWordsError::InvalidExpression { position: 11, reason: "expected `]`".to_string() }),
+            Expression::parse("u16:[0x1234")
+        );
+        assert!(matches!(Expression::parse("0x10 0x20"), Err(ReversedWordsError::InvalidExpression { position: 5, .. })));
+        assert!(matches!(Expression::parse("0xZZ"), Err(ReversedWordsError::InvalidExpression { position: 0, .. })));
+
+        let deep = format!("{}0x10{}", "[".repeat(MAX_NESTING), "]".repeat(MAX_NESTING));
+        assert!(Expression::parse(&deep).is_ok());
+        let too_deep = format!("{}0x10{}", "(".repeat(MAX_NESTING + 1), ")".repeat(MAX_NESTING + 1));
+        assert_eq!(
+            Err(ReversedWordsError::InvalidExpression {
+                position: MAX_NESTING + 1,
+                reason: "expression nested too deeply".to_string()
+            }),
+            Expression::parse(&too_deep)
+        );
+    }
+
+    #[test]
+    fn pointer_sizes_are_checked() {
+        assert!(Evaluator::new().with_pointer_size(8).is_ok());
+        assert_eq!(Some(ReversedWordsError::InvalidPointerSize { size: 2 }), Evaluator::new().with_pointer_size(2).err());
+    }
+}
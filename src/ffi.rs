@@ -0,0 +1,297 @@
+//! C ABI for using a swapped view from C and C++, see `include/reversed_word_byte_rw.h`.
+//!
+//! Functions returning `int` return 0 on success and -1 on failure. A view borrows the caller's
+//! buffer, which must stay valid and not be accessed through other pointers until the view is freed.
+
+use std::os::raw::c_int;
+use binread::Endian;
+
+use crate::{history::SnapshotRing, Primitive, ReversedWords};
+
+/// Opaque handle to a view.
+pub struct RwView {
+    words: ReversedWords<'static>,
+}
+
+/// Opaque handle to a ring of snapshots of a view's buffer.
+pub struct RwSnapshots {
+    ring: SnapshotRing,
+}
+
+fn endian(big_endian: c_int) -> Endian {
+    if big_endian != 0 { Endian::Big } else { Endian::Little }
+}
+
+fn status(result: std::io::Result<()>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// Create a view over `len` bytes at `buf` with the given word size. Returns null on invalid arguments.
+///
+/// # Safety
+/// `buf` must point to `len` writable bytes which outlive the view and are not otherwise accessed while it exists.
+#[no_mangle]
+pub unsafe extern "C" fn rw_view_new(buf: *mut u8, len: usize, word_size: u8) -> *mut RwView {
+    if buf.is_null() {
+        return std::ptr::null_mut();
+    }
+    let ram = std::slice::from_raw_parts_mut(buf, len);
+    match ReversedWords::try_new_with_word_size(ram, word_size) {
+        Ok(words) => Box::into_raw(Box::new(RwView { words })),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Free a view created by `rw_view_new`. Passing null does nothing.
+///
+/// # Safety
+/// `view` must be null or a pointer returned by `rw_view_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn rw_view_free(view: *mut RwView) {
+    if !view.is_null() {
+        drop(Box::from_raw(view));
+    }
+}
+
+/// Length of the view in bytes.
+///
+/// # Safety
+/// `view` must be a live pointer returned by `rw_view_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rw_view_len(view: *const RwView) -> u64 {
+    (*view).words.len()
+}
+
+/// Copy up to `len` logical bytes starting at `addr` into `out`, returning how many were copied.
+/// Copies nothing if `out` is null or `len` is 0.
+///
+/// # Safety
+/// `view` must be a live pointer returned by `rw_view_new` and `out` must be null or point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rw_view_read_at(view: *const RwView, addr: u64, out: *mut u8, len: usize) -> usize {
+    if out.is_null() || len == 0 {
+        return 0;
+    }
+    let out = std::slice::from_raw_parts_mut(out, len);
+    (*view).words.read_at(addr, out).unwrap_or(0)
+}
+
+/// Copy up to `len` bytes from `src` to the logical bytes starting at `addr`, returning how many were copied.
+/// Copies nothing if `src` is null or `len` is 0.
+///
+/// # Safety
+/// `view` must be a live pointer returned by `rw_view_new` and `src` must be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rw_view_write_at(view: *mut RwView, addr: u64, src: *const u8, len: usize) -> usize {
+    if src.is_null() || len == 0 {
+        return 0;
+    }
+    let src = std::slice::from_raw_parts(src, len);
+    (*view).words.write_at(addr, src).unwrap_or(0)
+}
+
+/// Set every logical byte in `[start, end)` to `byte`.
+///
+/// # Safety
+/// `view` must be a live pointer returned by `rw_view_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rw_view_fill(view: *mut RwView, start: u64, end: u64, byte: u8) -> c_int {
+    status((*view).words.fill(start..end, byte))
+}
+
+/// Copy the logical bytes in `[start, end)` to `dst`, handling overlap.
+///
+/// # Safety
+/// `view` must be a live pointer returned by `rw_view_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rw_view_copy_within(view: *mut RwView, start: u64, end: u64, dst: u64) -> c_int {
+    status((*view).words.copy_within(start..end, dst))
+}
+
+/// Write up to `max` logical addresses where the `needle_len` bytes at `needle` occur to `out`,
+/// in increasing order and including overlapping matches. Returns the total number of matches,
+/// which may be more than `max`: call with `max` 0 to size `out`.
+///
+/// # Safety
+/// `view` must be a live pointer returned by `rw_view_new`, `needle` must point to `needle_len`
+/// readable bytes and `out` to `max` writable addresses. Either may be null when its length is 0.
+#[no_mangle]
+pub unsafe extern "C" fn rw_view_find_all(view: *const RwView, needle: *const u8, needle_len: usize, out: *mut u64, max: usize) -> usize {
+    if needle.is_null() || needle_len == 0 {
+        return 0;
+    }
+    let found = (*view).words.find_all(std::slice::from_raw_parts(needle, needle_len));
+    if !out.is_null() {
+        let n = found.len().min(max);
+        std::slice::from_raw_parts_mut(out, n).copy_from_slice(&found[..n]);
+    }
+    found.len()
+}
+
+/// Create a ring keeping up to `capacity` snapshots (at least 1), see [`SnapshotRing`].
+#[no_mangle]
+pub extern "C" fn rw_snapshots_new(capacity: usize) -> *mut RwSnapshots {
+    Box::into_raw(Box::new(RwSnapshots { ring: SnapshotRing::new(capacity) }))
+}
+
+/// Free a ring created by `rw_snapshots_new`. Passing null does nothing.
+///
+/// # Safety
+/// `snapshots` must be null or a pointer returned by `rw_snapshots_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn rw_snapshots_free(snapshots: *mut RwSnapshots) {
+    if !snapshots.is_null() {
+        drop(Box::from_raw(snapshots));
+    }
+}
+
+/// Number of snapshots held.
+///
+/// # Safety
+/// `snapshots` must be a live pointer returned by `rw_snapshots_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rw_snapshots_len(snapshots: *const RwSnapshots) -> usize {
+    (*snapshots).ring.len()
+}
+
+/// Take a snapshot of the view's buffer, dropping the oldest if the ring is full.
+///
+/// # Safety
+/// `snapshots` must be a live pointer returned by `rw_snapshots_new` and `view` one returned by `rw_view_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rw_snapshots_capture(snapshots: *mut RwSnapshots, view: *const RwView) {
+    (*snapshots).ring.capture(&(*view).words);
+}
+
+/// Restore the view's buffer to the snapshot `back` captures before the newest (0 being the
+/// newest), discarding the snapshots after it.
+///
+/// # Safety
+/// `snapshots` must be a live pointer returned by `rw_snapshots_new` and `view` one returned by `rw_view_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rw_snapshots_rewind(snapshots: *mut RwSnapshots, back: usize, view: *mut RwView) -> c_int {
+    status((*snapshots).ring.rewind(back, &mut (*view).words).map_err(Into::into))
+}
+
+fn read_value<T: Primitive>(words: &ReversedWords, addr: u64, big_endian: c_int) -> Option<T> {
+    let mut bytes = T::Bytes::default();
+    match words.read_at(addr, bytes.as_mut()) {
+        Ok(n) if n == bytes.as_ref().len() => Some(T::from_bytes(bytes, endian(big_endian))),
+        _ => None,
+    }
+}
+
+fn write_value<T: Primitive>(words: &mut ReversedWords, addr: u64, value: T, big_endian: c_int) -> c_int {
+    status(words.write_whole_value_at(addr, value, endian(big_endian)))
+}
+
+macro_rules! ffi_typed_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            /// Read a value at `addr` into `out`, big endian if `big_endian` is non-zero.
+            ///
+            /// # Safety
+            /// `view` must be a live pointer returned by `rw_view_new` and `out` must be writable.
+            #[no_mangle]
+            pub unsafe extern "C" fn $read(view: *const RwView, addr: u64, big_endian: c_int, out: *mut $ty) -> c_int {
+                match read_value::<$ty>(&(*view).words, addr, big_endian) {
+                    Some(value) => {
+                        *out = value;
+                        0
+                    }
+                    None => -1,
+                }
+            }
+
+            /// Write `value` at `addr`, big endian if `big_endian` is non-zero.
+            ///
+            /// # Safety
+            /// `view` must be a live pointer returned by `rw_view_new`.
+            #[no_mangle]
+            pub unsafe extern "C" fn $write(view: *mut RwView, addr: u64, big_endian: c_int, value: $ty) -> c_int {
+                write_value::<$ty>(&mut (*view).words, addr, value, big_endian)
+            }
+        )*
+    };
+}
+
+ffi_typed_accessors! {
+    u16 => rw_view_read_u16, rw_view_write_u16;
+    u32 => rw_view_read_u32, rw_view_write_u32;
+    u64 => rw_view_read_u64, rw_view_write_u64;
+    f32 => rw_view_read_f32, rw_view_write_f32;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ffi::*;
+
+    #[test]
+    fn round_trip_through_c_abi() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        unsafe {
+            let view = rw_view_new(data.as_mut_ptr(), data.len(), 4);
+            assert!(!view.is_null());
+            assert_eq!(8, rw_view_len(view));
+
+            let mut out = [0u8; 3];
+            assert_eq!(3, rw_view_read_at(view, 2, out.as_mut_ptr(), out.len()));
+            assert_eq!([1, 0, 7], out);
+
+            let mut value = 0u32;
+            assert_eq!(0, rw_view_read_u32(view, 0, 1, &mut value));
+            assert_eq!(0x03020100, value);
+            assert_eq!(0, rw_view_write_u32(view, 4, 1, 0xAABBCCDD));
+            assert_eq!(-1, rw_view_write_u32(view, 6, 1, 0));
+            rw_view_free(view);
+        }
+        assert_eq!(vec![0, 1, 2, 3, 0xDD, 0xCC, 0xBB, 0xAA], data);
+    }
+
+    #[test]
+    fn invalid_arguments_return_null() {
+        let mut data = vec![0u8; 4];
+        unsafe {
+            assert!(rw_view_new(std::ptr::null_mut(), 4, 4).is_null());
+            assert!(rw_view_new(data.as_mut_ptr(), data.len(), 0).is_null());
+            rw_view_free(std::ptr::null_mut());
+            rw_snapshots_free(std::ptr::null_mut());
+
+            let view = rw_view_new(data.as_mut_ptr(), data.len(), 4);
+            assert_eq!(0, rw_view_read_at(view, 0, std::ptr::null_mut(), 0));
+            assert_eq!(0, rw_view_write_at(view, 0, std::ptr::null(), 0));
+            assert_eq!(0, rw_view_read_at(view, 0, std::ptr::null_mut(), 4));
+            rw_view_free(view);
+        }
+    }
+
+    #[test]
+    fn search_and_snapshots() {
+        let mut data: Vec<u8> = vec![0xAD, 0xDE, 0, 0, 0, 0, 0xEF, 0xBE];
+        unsafe {
+            let view = rw_view_new(data.as_mut_ptr(), data.len(), 2);
+            let needle = [0xDE, 0xAD];
+            assert_eq!(1, rw_view_find_all(view, needle.as_ptr(), needle.len(), std::ptr::null_mut(), 0));
+            assert_eq!(0, rw_view_write_u16(view, 4, 1, 0xDEAD));
+            let mut found = [0u64; 1];
+            assert_eq!(2, rw_view_find_all(view, needle.as_ptr(), needle.len(), found.as_mut_ptr(), found.len()));
+            assert_eq!([0], found);
+            assert_eq!(0, rw_view_find_all(view, std::ptr::null(), 0, found.as_mut_ptr(), found.len()));
+
+            let snapshots = rw_snapshots_new(4);
+            rw_snapshots_capture(snapshots, view);
+            assert_eq!(0, rw_view_fill(view, 0, 8, 0));
+            rw_snapshots_capture(snapshots, view);
+            assert_eq!(2, rw_snapshots_len(snapshots));
+            assert_eq!(-1, rw_snapshots_rewind(snapshots, 2, view));
+            assert_eq!(0, rw_snapshots_rewind(snapshots, 1, view));
+            assert_eq!(1, rw_snapshots_len(snapshots));
+            rw_snapshots_free(snapshots);
+            rw_view_free(view);
+        }
+        assert_eq!(vec![0xAD, 0xDE, 0, 0, 0xAD, 0xDE, 0xEF, 0xBE], data);
+    }
+}
@@ -0,0 +1,96 @@
+//! Fixed-point numbers, which N64-era games use for most positions and velocities.
+//!
+//! A fixed-point value is a two's complement integer scaled by `2^fraction_bits`: 16.16 is an
+//! `i32` with 16 fraction bits, 4.12 an `i16` with 12. Values are read and written in the view's
+//! [`endian`](ReversedWords::with_endian), and written rounded to the nearest step.
+
+use crate::{ReversedWords, ReversedWordsError};
+
+// `value` scaled and rounded to an integer in `min..=max`
+fn to_fixed(value: f64, fraction_bits: u32, min: i64, max: i64) -> Result<i64, ReversedWordsError> {
+    let scaled = (value * (1u64 << fraction_bits) as f64).round();
+    if !(min as f64..=max as f64).contains(&scaled) {
+        return Err(ReversedWordsError::UnrepresentableFixed { value: value.to_string(), fraction_bits });
+    }
+    Ok(scaled as i64)
+}
+
+impl ReversedWords<'_> {
+    /// Read the `i16` at `addr` as a fixed-point number with `fraction_bits` fraction bits.
+    pub fn read_fixed_i16_at(&mut self, addr: u64, fraction_bits: u32) -> std::io::Result<f64> {
+        Ok(self.read_value::<i16>(addr)? as f64 / (1u64 << fraction_bits) as f64)
+    }
+
+    /// Write `value` as a fixed-point `i16` with `fraction_bits` fraction bits, failing without
+    /// writing if it's out of range or not a number.
+    pub fn write_fixed_i16_at(&mut self, addr: u64, value: f64, fraction_bits: u32) -> std::io::Result<()> {
+        let fixed = to_fixed(value, fraction_bits, i16::MIN as i64, i16::MAX as i64)?;
+        self.write_value(addr, fixed as i16)
+    }
+
+    /// Read the `i32` at `addr` as a fixed-point number with `fraction_bits` fraction bits.
+    pub fn read_fixed_i32_at(&mut self, addr: u64, fraction_bits: u32) -> std::io::Result<f64> {
+        Ok(self.read_value::<i32>(addr)? as f64 / (1u64 << fraction_bits) as f64)
+    }
+
+    pub fn write_fixed_i32_at(&mut self, addr: u64, value: f64, fraction_bits: u32) -> std::io::Result<()> {
+        let fixed = to_fixed(value, fraction_bits, i32::MIN as i64, i32::MAX as i64)?;
+        self.write_value(addr, fixed as i32)
+    }
+
+    /// A signed 16.16 value, as used for coordinates.
+    pub fn read_fixed_16_16_at(&mut self, addr: u64) -> std::io::Result<f32> {
+        Ok(self.read_fixed_i32_at(addr, 16)? as f32)
+    }
+
+    pub fn write_fixed_16_16_at(&mut self, addr: u64, value: f32) -> std::io::Result<()> {
+        self.write_fixed_i32_at(addr, value as f64, 16)
+    }
+
+    /// A signed 4.12 value, as used for normals and rotation matrix entries.
+    pub fn read_fixed_4_12_at(&mut self, addr: u64) -> std::io::Result<f32> {
+        Ok(self.read_fixed_i16_at(addr, 12)? as f32)
+    }
+
+    pub fn write_fixed_4_12_at(&mut self, addr: u64, value: f32) -> std::io::Result<()> {
+        self.write_fixed_i16_at(addr, value as f64, 12)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Endian, ReversedWords, ReversedWordsError};
+
+    #[test]
+    fn fixed_point_round_trips() {
+        let mut data = vec![0u8; 16];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_fixed_16_16_at(2, -1.5).unwrap();
+        assert_eq!(-0x18000, words.read_i32_at(2, Endian::Big).unwrap());
+        assert_eq!(-1.5, words.read_fixed_16_16_at(2).unwrap());
+        words.write_fixed_4_12_at(8, 0.25).unwrap();
+        assert_eq!(0x0400, words.read_u16_at(8, Endian::Big).unwrap());
+        assert_eq!(0.25, words.read_fixed_4_12_at(8).unwrap());
+        // rounded to the nearest 1/4096
+        words.write_fixed_4_12_at(8, 0.1).unwrap();
+        assert_eq!(410, words.read_i16_at(8, Endian::Big).unwrap());
+        words.write_fixed_i16_at(10, -3.0, 8).unwrap();
+        assert_eq!(-3.0, words.read_fixed_i16_at(10, 8).unwrap());
+
+        let mut words = words.with_endian(Endian::Little);
+        words.write_fixed_16_16_at(12, 1.0).unwrap();
+        assert_eq!(0x0001_0000, words.read_i32_at(12, Endian::Little).unwrap());
+    }
+
+    #[test]
+    fn fixed_point_rejects_unrepresentable_values() {
+        let mut data = vec![0u8; 8];
+        let mut words = ReversedWords::new(&mut data);
+        let error = words.write_fixed_4_12_at(0, 8.0).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnrepresentableFixed { value: "8".to_string(), fraction_bits: 12 }), ReversedWordsError::from_io(&error));
+        assert!(words.write_fixed_4_12_at(0, -8.0).is_ok());
+        assert!(words.write_fixed_16_16_at(4, f32::NAN).is_err());
+        assert!(words.write_fixed_16_16_at(4, 32768.0).is_err());
+        assert!(words.write_fixed_16_16_at(6, 1.0).is_err());
+    }
+}
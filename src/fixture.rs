@@ -0,0 +1,163 @@
+//! Deterministic swapped images for testing tools built on this crate, without real dumps.
+//!
+//! A [`TestImage`] is a list of fills applied in order, later ones over earlier ones, each in
+//! logical order: words tagged with their own address, ascending bytes, seeded noise and ASCII
+//! markers. [`TestImage::build`] writes them through a view and hands back the storage, so the
+//! result is swapped exactly the way a dump from the target would be. The same description and
+//! seed always give the same bytes.
+//!
+//! ```
+//! # use reversed_word_byte_rw::{fixture::TestImage, Endian};
+//! let mut image = TestImage::new(64, 4).unwrap().address_tagged(0..32).random(32..64).marker(40, "HEAD").build();
+//! assert_eq!(0x1C, image.words().read_value_at::<u32>(0x1C, Endian::Big).unwrap());
+//! assert_eq!(&[b'D', b'A', b'E', b'H'], &image.as_slice()[40..44]);
+//! ```
+
+use std::ops::Range;
+
+use binread::Endian;
+
+use crate::{ReversedVec, ReversedWordsError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Fill {
+    AddressTagged,
+    Ascending,
+    Random,
+    Byte(u8),
+    Marker(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestImage {
+    len: u64,
+    word_size: u8,
+    endian: Endian,
+    seed: u64,
+    fills: Vec<(Range<u64>, Fill)>,
+}
+
+// splitmix64, for noise that only depends on the seed and the address
+fn noise(seed: u64, addr: u64) -> u8 {
+    let mut z = seed.wrapping_add(addr.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (z ^ (z >> 31)) as u8
+}
+
+impl TestImage {
+    /// An all zero image of `len` bytes stored as `word_size` byte words.
+    pub fn new(len: u64, word_size: u8) -> Result<TestImage, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        Ok(TestImage { len, word_size, endian: Endian::Big, seed: 0, fills: Vec::new() })
+    }
+
+    /// The byte order address tags are written in. Defaults to big endian.
+    pub fn with_endian(mut self, endian: Endian) -> TestImage {
+        self.endian = endian;
+        self
+    }
+
+    /// The seed for [`TestImage::random`]. Defaults to 0.
+    pub fn with_seed(mut self, seed: u64) -> TestImage {
+        self.seed = seed;
+        self
+    }
+
+    /// Every 4 byte aligned group in `range` holds its own logical address as a `u32`, so any
+    /// value read back says where it came from.
+    pub fn address_tagged(mut self, range: Range<u64>) -> TestImage {
+        self.fills.push((range, Fill::AddressTagged));
+        self
+    }
+
+    /// Every byte in `range` is the low byte of its logical address.
+    pub fn ascending(mut self, range: Range<u64>) -> TestImage {
+        self.fills.push((range, Fill::Ascending));
+        self
+    }
+
+    /// Bytes in `range` are noise determined by the seed and their address, standing in for
+    /// compressed or encrypted data.
+    pub fn random(mut self, range: Range<u64>) -> TestImage {
+        self.fills.push((range, Fill::Random));
+        self
+    }
+
+    pub fn fill(mut self, range: Range<u64>, byte: u8) -> TestImage {
+        self.fills.push((range, Fill::Byte(byte)));
+        self
+    }
+
+    /// `text` as ASCII at logical `addr`, for something a string search or a reviewer can find.
+    pub fn marker(mut self, addr: u64, text: &str) -> TestImage {
+        let bytes = text.as_bytes().to_vec();
+        self.fills.push((addr..addr.saturating_add(bytes.len() as u64), Fill::Marker(bytes)));
+        self
+    }
+
+    /// The image in storage order. Fills are clipped to the image.
+    pub fn build(&self) -> ReversedVec {
+        let mut image = ReversedVec::try_new_with_word_size(vec![0; self.len as usize], self.word_size).expect("word size is never 0");
+        let mut words = image.words();
+        for (range, fill) in &self.fills {
+            let range = range.start.min(self.len)..range.end.min(self.len);
+            let bytes: Vec<u8> = range
+                .clone()
+                .map(|addr| match fill {
+                    Fill::AddressTagged => {
+                        let tag = ((addr - addr % 4) as u32).to_be_bytes();
+                        let i = (addr % 4) as usize;
+                        match self.endian {
+                            Endian::Little => tag[3 - i],
+                            _ => tag[i],
+                        }
+                    }
+                    Fill::Ascending => addr as u8,
+                    Fill::Random => noise(self.seed, addr),
+                    Fill::Byte(byte) => *byte,
+                    Fill::Marker(text) => text[(addr - range.start) as usize],
+                })
+                .collect();
+            words.write_at(range.start, &bytes).expect("misaligned writes are realigned");
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fixture::*, Endian};
+
+    #[test]
+    fn fills_in_logical_order() {
+        let image = TestImage::new(16, 4).unwrap().address_tagged(0..16).ascending(10..14).marker(1, "ab").build();
+        assert_eq!(
+            vec![
+                0, b'b', b'a', 0, // tag 0 under the marker
+                4, 0, 0, 0, //
+                11, 10, 0, 0, // tag 8 partly overwritten by the ascending bytes
+                12, 0, 13, 12,
+            ],
+            image.into_inner()
+        );
+
+        let mut little = TestImage::new(8, 2).unwrap().with_endian(Endian::Little).address_tagged(4..8).build();
+        assert_eq!(4, little.words().read_value_at::<u32>(4, Endian::Little).unwrap());
+        assert_eq!(vec![0, 0, 0, 0, 0, 4, 0, 0], little.into_inner());
+    }
+
+    #[test]
+    fn noise_is_seeded() {
+        let image = |seed| TestImage::new(64, 8).unwrap().with_seed(seed).random(0..64).build().into_inner();
+        assert_eq!(image(1), image(1));
+        assert_ne!(image(1), image(2));
+        // clipped to the image, and independent of what range produced it
+        let clipped = TestImage::new(64, 8).unwrap().with_seed(1).random(32..99).build().into_inner();
+        assert_eq!(image(1)[32..], clipped[32..]);
+        assert_eq!(vec![0; 32], clipped[..32]);
+        assert!(TestImage::new(8, 0).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Immutable snapshots that can be shared between threads.
+
+use std::{
+    io::{Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+use binread::Endian;
+
+use crate::{atomic::seek_target, storage_index, Primitive, ReversedVec, ReversedWords, ReversedWordsError};
+
+/// How the bytes of a [`FrozenView`] are laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrozenLayout {
+    /// Already in logical order, so reads are plain copies.
+    Logical,
+    /// As they were stored, with what's needed to unswap them on each read.
+    Storage { word_size: u8, nibble_swap: bool, bit_reversal: bool },
+}
+
+/// A read-only copy of a view, taken with [`ReversedWords::freeze`] or
+/// [`ReversedWords::freeze_logical`].
+///
+/// The bytes are behind an `Arc`, so clones are cheap and the view is `Send + Sync`: background
+/// threads can analyse a stable copy, each with its own [`FrozenView::cursor`], while the live
+/// view keeps changing. Equality is of the logical bytes, whatever the layout.
+#[derive(Clone, Debug)]
+pub struct FrozenView {
+    data: Arc<[u8]>,
+    layout: FrozenLayout,
+    endian: Endian,
+}
+
+impl ReversedWords<'_> {
+    /// Copy the storage as it is, keeping the word size, nibble swap and endianness.
+    pub fn freeze(&self) -> FrozenView {
+        let layout = FrozenLayout::Storage { word_size: self.word_size, nibble_swap: self.nibble_swap, bit_reversal: self.bit_reversal };
+        FrozenView { data: self.cursor.get_ref().to_vec().into(), layout, endian: self.endian }
+    }
+
+    /// Copy the reachable bytes in logical order, paying for the unswapping once instead of on
+    /// every read.
+    pub fn freeze_logical(&self) -> FrozenView {
+        let data: Vec<u8> = (0..self.reachable_len()).map(|position| self.load(self.storage_index(position).expect("reachable"))).collect();
+        FrozenView { data: data.into(), layout: FrozenLayout::Logical, endian: self.endian }
+    }
+}
+
+impl From<ReversedVec> for FrozenView {
+    fn from(words: ReversedVec) -> FrozenView {
+        let layout = FrozenLayout::Storage { word_size: words.word_size(), nibble_swap: false, bit_reversal: false };
+        FrozenView { data: words.into_inner().into(), layout, endian: Endian::Big }
+    }
+}
+
+impl FrozenView {
+    /// The length of the frozen bytes: a trailing partial word of a storage order copy counts,
+    /// though it can't be read.
+    pub fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn layout(&self) -> FrozenLayout {
+        self.layout
+    }
+
+    /// The endianness of the view it was frozen from, used by [`FrozenView::read_value`].
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// The frozen bytes, in the order given by [`FrozenView::layout`].
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// This snapshot in logical order, sharing the bytes if it already is.
+    pub fn to_logical(&self) -> FrozenView {
+        if self.layout == FrozenLayout::Logical {
+            return self.clone();
+        }
+        let len = self.reachable_len();
+        let mut data = vec![0u8; len as usize];
+        self.read_at(0, &mut data);
+        FrozenView { data: data.into(), layout: FrozenLayout::Logical, endian: self.endian }
+    }
+
+    fn reachable_len(&self) -> u64 {
+        match self.layout {
+            FrozenLayout::Logical => self.len(),
+            FrozenLayout::Storage { word_size, .. } => self.len() - self.len() % word_size as u64,
+        }
+    }
+
+    /// Read the logical bytes starting at `addr`, returning how many were in range.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> usize {
+        match self.layout {
+            FrozenLayout::Logical => {
+                let start = addr.min(self.len()) as usize;
+                let n = buf.len().min(self.data.len() - start);
+                buf[..n].copy_from_slice(&self.data[start..start + n]);
+                n
+            }
+            FrozenLayout::Storage { word_size, nibble_swap, bit_reversal } => {
+                for (i, byte) in buf.iter_mut().enumerate() {
+                    let index = match addr.checked_add(i as u64).and_then(|position| storage_index(position, word_size, self.len())) {
+                        Some(index) => index,
+                        None => return i,
+                    };
+                    *byte = if bit_reversal { self.data[index].reverse_bits() } else { self.data[index] };
+                    if nibble_swap {
+                        *byte = byte.rotate_left(4);
+                    }
+                }
+                buf.len()
+            }
+        }
+    }
+
+    pub fn get_byte(&self, addr: u64) -> std::io::Result<u8> {
+        let mut byte = [0];
+        if self.read_at(addr, &mut byte) == 0 {
+            return Err(ReversedWordsError::AddressOutOfRange { addr, len: self.len() }.into());
+        }
+        Ok(byte[0])
+    }
+
+    /// The `N` logical bytes at `addr`. Fails unless all of them are in range.
+    pub fn read_array<const N: usize>(&self, addr: u64) -> std::io::Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        if self.read_at(addr, &mut bytes) < N {
+            return Err(ReversedWordsError::RangeOutOfRange { start: addr, end: addr.saturating_add(N as u64), len: self.len() }.into());
+        }
+        Ok(bytes)
+    }
+
+    pub fn read_value_at<T: Primitive>(&self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        if self.read_at(addr, bytes.as_mut()) < bytes.as_ref().len() {
+            return Err(ReversedWordsError::AddressOutOfRange { addr, len: self.len() }.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    /// [`FrozenView::read_value_at`] in the frozen view's endianness.
+    pub fn read_value<T: Primitive>(&self, addr: u64) -> std::io::Result<T> {
+        self.read_value_at(addr, self.endian)
+    }
+
+    /// A new cursor at position 0, holding its own reference to the bytes.
+    pub fn cursor(&self) -> FrozenCursor {
+        FrozenCursor { view: self.clone(), position: 0 }
+    }
+}
+
+impl PartialEq for FrozenView {
+    fn eq(&self, other: &FrozenView) -> bool {
+        let (len, other_len) = (self.reachable_len(), other.reachable_len());
+        len == other_len && (0..len).all(|addr| self.get_byte(addr).ok() == other.get_byte(addr).ok())
+    }
+}
+
+impl Eq for FrozenView {}
+
+/// A position in a [`FrozenView`], implementing `Read + Seek`.
+#[derive(Clone, Debug)]
+pub struct FrozenCursor {
+    view: FrozenView,
+    position: u64,
+}
+
+impl FrozenCursor {
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn view(&self) -> &FrozenView {
+        &self.view
+    }
+}
+
+impl Read for FrozenCursor {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.view.read_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for FrozenCursor {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = seek_target(pos, self.position, self.view.len())?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{frozen::*, ReversedWords};
+
+    #[test]
+    fn frozen_views_keep_the_snapshot() {
+        let mut data: Vec<u8> = (0..10).collect();
+        let mut words = ReversedWords::new(&mut data).with_endian(Endian::Little);
+        let frozen = words.freeze();
+        let logical = words.freeze_logical();
+        words.set_byte(0, 0xFF).unwrap();
+
+        assert_eq!(FrozenLayout::Storage { word_size: 4, nibble_swap: false, bit_reversal: false }, frozen.layout());
+        assert_eq!(3, frozen.get_byte(0).unwrap());
+        assert_eq!(0x0607_0001, frozen.read_value::<u32>(2).unwrap());
+        assert!(frozen.get_byte(8).is_err());
+        assert_eq!([3, 2, 1, 0, 7, 6, 5, 4][..], logical.data()[..]);
+        assert_eq!(frozen, logical);
+        assert_eq!(logical, frozen.to_logical());
+        assert_ne!(words.freeze(), frozen);
+
+        let mut out = Vec::new();
+        let mut cursor = frozen.cursor();
+        cursor.seek(SeekFrom::Start(5)).unwrap();
+        cursor.read_to_end(&mut out).unwrap();
+        assert_eq!(vec![6, 5, 4], out);
+    }
+
+    #[test]
+    fn frozen_views_are_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FrozenView>();
+        assert_send_sync::<FrozenCursor>();
+
+        let mut data: Vec<u8> = (0..64).collect();
+        let frozen = ReversedWords::new(&mut data).with_nibble_swap(true).with_bit_reversal(true).freeze();
+        let sums: Vec<u32> = (0..4)
+            .map(|thread| {
+                let frozen = frozen.clone();
+                std::thread::spawn(move || (thread * 16..thread * 16 + 16).map(|addr| frozen.get_byte(addr).unwrap() as u32).sum())
+            })
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!((0..64u8).map(|byte| byte.reverse_bits().rotate_left(4) as u32).sum::<u32>(), sums.iter().sum::<u32>());
+    }
+}
@@ -0,0 +1,202 @@
+//! Rendering a region as a greyscale image, one pixel per N bytes.
+//!
+//! A [`Heatmap`] is built from the byte values, entropy or traced access counts of a region and
+//! written as a binary PGM or an (uncompressed) PNG. Rows run left to right in address order, so
+//! tables, padding and compressed blobs show up as stripes and blocks.
+
+use std::{io::Write, ops::Range};
+
+use crate::{trace::AccessTrace, ReversedWords};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Heatmap {
+    width: u32,
+    height: u32,
+    /// One byte per pixel, row by row; pixels past the end of the region are 0.
+    pixels: Vec<u8>,
+}
+
+impl Heatmap {
+    // lay `values` out in rows of `width`, scaling them so that `max` is white
+    fn from_values(values: Vec<f64>, max: f64, width: u32) -> Heatmap {
+        let width = width.max(1);
+        let height = (values.len() as u64).div_ceil(width as u64).max(1) as u32;
+        let mut pixels: Vec<u8> =
+            values.iter().map(|value| if max > 0.0 { (value / max * 255.0).round().clamp(0.0, 255.0) as u8 } else { 0 }).collect();
+        pixels.resize(width as usize * height as usize, 0);
+        Heatmap { width, height, pixels }
+    }
+
+    fn cells(range: &Range<u64>, bytes_per_pixel: u64) -> impl Iterator<Item = Range<u64>> + '_ {
+        let bytes_per_pixel = bytes_per_pixel.max(1);
+        (range.start..range.end).step_by(bytes_per_pixel as usize).map(move |start| start..(start + bytes_per_pixel).min(range.end))
+    }
+
+    /// The average byte value of every `bytes_per_pixel` bytes of `range`.
+    pub fn magnitude(words: &ReversedWords, range: Range<u64>, bytes_per_pixel: u64, width: u32) -> Heatmap {
+        let range = range.start..range.end.min(words.len());
+        let values = Heatmap::cells(&range, bytes_per_pixel)
+            .map(|cell| {
+                let len = cell.end - cell.start;
+                words.bytes(cell).map(|byte| byte as f64).sum::<f64>() / len as f64
+            })
+            .collect();
+        Heatmap::from_values(values, 255.0, width)
+    }
+
+    /// The Shannon entropy of every `bytes_per_pixel` bytes of `range`, white being 8 bits per
+    /// byte. Cells of a few hundred bytes or more give a meaningful value.
+    pub fn entropy(words: &ReversedWords, range: Range<u64>, bytes_per_pixel: u64, width: u32) -> Heatmap {
+        let range = range.start..range.end.min(words.len());
+        let values = Heatmap::cells(&range, bytes_per_pixel)
+            .map(|cell| {
+                let mut counts = [0u64; 256];
+                let len = cell.end - cell.start;
+                words.bytes(cell).for_each(|byte| counts[byte as usize] += 1);
+                counts
+                    .iter()
+                    .filter(|count| **count > 0)
+                    .map(|count| {
+                        let p = *count as f64 / len as f64;
+                        -p * p.log2()
+                    })
+                    .sum()
+            })
+            .collect();
+        Heatmap::from_values(values, 8.0, width)
+    }
+
+    /// How many traced reads and writes touched each `bytes_per_pixel` bytes of `range`, white
+    /// being the most accessed pixel.
+    pub fn access_counts(trace: &AccessTrace, range: Range<u64>, bytes_per_pixel: u64, width: u32) -> Heatmap {
+        let bytes_per_pixel = bytes_per_pixel.max(1);
+        let cells = (range.end.saturating_sub(range.start)).div_ceil(bytes_per_pixel) as usize;
+        let mut counts = vec![0u64; cells];
+        for record in trace.records() {
+            let end = record.addr.saturating_add(record.data.len() as u64).min(range.end);
+            let start = record.addr.max(range.start);
+            if start >= end {
+                continue;
+            }
+            let first = ((start - range.start) / bytes_per_pixel) as usize;
+            let last = ((end - 1 - range.start) / bytes_per_pixel) as usize;
+            counts[first..=last].iter_mut().for_each(|count| *count += 1);
+        }
+        let max = counts.iter().copied().max().unwrap_or(0) as f64;
+        Heatmap::from_values(counts.into_iter().map(|count| count as f64).collect(), max, width)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Write a binary (`P5`) PGM.
+    pub fn write_pgm<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write!(writer, "P5\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.pixels)
+    }
+
+    /// Write an 8-bit greyscale PNG. The image data is stored without compression, which keeps
+    /// this free of dependencies; run it through an optimizer if size matters.
+    pub fn write_png<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per sample, greyscale, deflate, adaptive filtering, no interlace
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+        write_chunk(&mut writer, b"IHDR", &header)?;
+
+        let mut raw = Vec::with_capacity(self.pixels.len() + self.height as usize);
+        for row in self.pixels.chunks(self.width as usize) {
+            raw.push(0); // filter type None
+            raw.extend_from_slice(row);
+        }
+        write_chunk(&mut writer, b"IDAT", &zlib_stored(&raw))?;
+        write_chunk(&mut writer, b"IEND", &[])
+    }
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(&[&kind[..], data]);
+    writer.write_all(&crc.to_be_bytes())
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// a zlib stream of stored (uncompressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&(b << 16 | a).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        heatmap::*,
+        trace::{TraceOp, TraceRecord},
+        ReversedWords,
+    };
+
+    #[test]
+    fn renders_values_entropy_and_accesses() {
+        let mut data = vec![0u8; 48];
+        data[16..32].iter_mut().for_each(|byte| *byte = 0xFF);
+        let words = ReversedWords::new(&mut data);
+        let image = Heatmap::magnitude(&words, 0..48, 8, 4);
+        assert_eq!((4, 2), (image.width(), image.height()));
+        assert_eq!([0, 0, 255, 255, 0, 0, 0, 0], image.pixels());
+        assert_eq!(vec![0; 3], Heatmap::entropy(&words, 0..48, 16, 3).pixels());
+
+        let mut trace = AccessTrace::new();
+        trace.push(TraceRecord { op: TraceOp::Read, addr: 4, data: vec![0; 8] });
+        trace.push(TraceRecord { op: TraceOp::Write, addr: 10, data: vec![0; 1] });
+        assert_eq!([128, 255, 0, 0], Heatmap::access_counts(&trace, 0..32, 8, 4).pixels());
+
+        let mut pgm = Vec::new();
+        image.write_pgm(&mut pgm).unwrap();
+        assert_eq!(b"P5\n4 2\n255\n\0\0\xFF\xFF\0\0\0\0", &pgm[..]);
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+        // IEND's fixed CRC
+        assert_eq!([0xAE, 0x42, 0x60, 0x82], png[png.len() - 4..]);
+    }
+}
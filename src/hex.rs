@@ -0,0 +1,89 @@
+//! Hex text in and out of the logical stream, for copying bytes between tools by hand.
+
+use std::ops::Range;
+
+use crate::{ReversedWords, ReversedWordsError};
+
+/// Parse hex digits into bytes, two digits per byte. Whitespace anywhere is ignored, so
+/// `"DE AD BE EF"`, `"deadbeef"` and a multi-line hex dump column all parse the same; an odd
+/// number of digits or any other character is an error giving its character position.
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, ReversedWordsError> {
+    let mut bytes = Vec::with_capacity(text.len() / 2);
+    let mut high: Option<(usize, u8)> = None;
+    for (position, c) in text.chars().enumerate() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let digit = c.to_digit(16).ok_or_else(|| ReversedWordsError::InvalidHex { position, reason: format!("{:?} is not a hex digit", c) })? as u8;
+        match high.take() {
+            Some((_, high)) => bytes.push(high << 4 | digit),
+            None => high = Some((position, digit)),
+        }
+    }
+    if let Some((position, _)) = high {
+        return Err(ReversedWordsError::InvalidHex { position, reason: "odd number of digits".to_string() });
+    }
+    Ok(bytes)
+}
+
+/// `bytes` as upper case hex, one space between bytes.
+pub fn format_hex(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 3);
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            text.push(' ');
+        }
+        text.push_str(&format!("{:02X}", byte));
+    }
+    text
+}
+
+impl ReversedWords<'_> {
+    /// Write the bytes of `hex` (see [`parse_hex`]) in logical order starting at `addr`,
+    /// returning how many there were. Nothing is written if the text doesn't parse or the
+    /// bytes don't fit.
+    pub fn write_hex_at(&mut self, addr: u64, hex: &str) -> std::io::Result<usize> {
+        let bytes = parse_hex(hex)?;
+        self.check_range(&(addr..addr.saturating_add(bytes.len() as u64)))?;
+        self.write_at(addr, &bytes)
+    }
+
+    /// The logical bytes in `range` as hex, formatted like [`format_hex`]. Bytes of a trailing
+    /// partial word, which can't be reached, are left out.
+    pub fn read_hex(&self, range: Range<u64>) -> std::io::Result<String> {
+        self.check_range(&range)?;
+        let mut bytes = vec![0u8; (range.end - range.start) as usize];
+        let n = self.read_at(range.start, &mut bytes)?;
+        Ok(format_hex(&bytes[..n]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hex::*, ReversedWords};
+
+    #[test]
+    fn parses_loose_hex() {
+        assert_eq!(vec![0xDE, 0xAD, 0xBE, 0xEF], parse_hex("DE AD BE EF").unwrap());
+        assert_eq!(vec![0xDE, 0xAD, 0xBE, 0xEF], parse_hex(" deadbeef\n").unwrap());
+        assert_eq!(vec![0xDE, 0xAD, 0xBE, 0xEF], parse_hex("de ad\r\n\tb e ef").unwrap());
+        assert_eq!(Vec::<u8>::new(), parse_hex("  ").unwrap());
+        assert!(matches!(parse_hex("DE A"), Err(ReversedWordsError::InvalidHex { position: 3, .. })));
+        assert!(matches!(parse_hex("DE ÄD"), Err(ReversedWordsError::InvalidHex { position: 3, .. })));
+        assert_eq!("00 0A FF", format_hex(&[0, 10, 255]));
+    }
+
+    #[test]
+    fn round_trips_through_a_view() {
+        let mut data = vec![0u8; 10];
+        let mut words = ReversedWords::new(&mut data);
+        assert_eq!(4, words.write_hex_at(2, "DE AD BE EF").unwrap());
+        assert_eq!("00 00 DE AD BE EF 00 00", words.read_hex(0..8).unwrap());
+        // the last two bytes are a partial word
+        assert_eq!("00 00", words.read_hex(6..10).unwrap());
+        assert!(words.write_hex_at(7, "01 02 03 04").is_err());
+        assert!(words.write_hex_at(0, "0").is_err());
+        assert!(words.read_hex(4..11).is_err());
+        assert_eq!([0xAD, 0xDE, 0, 0, 0, 0, 0xEF, 0xBE], data[..8]);
+    }
+}
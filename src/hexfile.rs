@@ -0,0 +1,307 @@
+//! Intel HEX and Motorola S-record files, for moving data to and from flashing tools.
+//!
+//! A [`HexFile`] is the data records of a file merged into runs of consecutive addresses, plus the
+//! start address if the file has one. Files address the target's memory, not the view: loading
+//! and exporting take the address the view starts at (`base`), the same way
+//! [`crate::journal::WriteJournal::to_gameshark`] does.
+//!
+//! Intel HEX files may use extended segment or extended linear addressing; written files use
+//! linear addressing, 16 data bytes per record. S-record files are written with the smallest
+//! address size that fits (`S1`, `S2` or `S3`), an empty `S0` header and an `S5` record count.
+
+use std::{fmt::Write, ops::Range};
+
+use crate::{hex::parse_hex, ReversedWords, ReversedWordsError};
+
+const BYTES_PER_RECORD: usize = 16;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HexFile {
+    /// Data as `(address, bytes)` runs, in the order the file gives them.
+    pub segments: Vec<(u64, Vec<u8>)>,
+    /// The start (entry point) address record, if any.
+    pub start: Option<u32>,
+}
+
+fn invalid(line: usize, reason: &str) -> ReversedWordsError {
+    ReversedWordsError::InvalidHexFile { line, reason: reason.to_string() }
+}
+
+fn be_value(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |value, byte| value << 8 | *byte as u64)
+}
+
+fn push_hex(line: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        let _ = write!(line, "{:02X}", byte);
+    }
+}
+
+impl HexFile {
+    fn push(&mut self, addr: u64, data: &[u8]) {
+        match self.segments.last_mut() {
+            Some((start, run)) if *start + run.len() as u64 == addr => run.extend_from_slice(data),
+            _ => self.segments.push((addr, data.to_vec())),
+        }
+    }
+
+    /// Parse an Intel HEX file. Every record's checksum is checked, and the file must end with
+    /// an end of file record.
+    pub fn parse_ihex(text: &str) -> Result<HexFile, ReversedWordsError> {
+        let mut file = HexFile::default();
+        let mut upper = 0u64;
+        for (i, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())).filter(|(_, line)| !line.is_empty()) {
+            let digits = line.strip_prefix(':').ok_or_else(|| invalid(i, "record doesn't start with ':'"))?;
+            let bytes = parse_hex(digits).map_err(|e| invalid(i, &e.to_string()))?;
+            if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+                return Err(invalid(i, "record length doesn't match its byte count"));
+            }
+            if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+                return Err(invalid(i, "bad checksum"));
+            }
+            let offset = be_value(&bytes[1..3]);
+            let data = &bytes[4..bytes.len() - 1];
+            match (bytes[3], data.len()) {
+                (0x00, _) => file.push(upper + offset, data),
+                (0x01, _) => return Ok(file),
+                (0x02, 2) => upper = be_value(data) << 4,
+                (0x03, 4) => file.start = Some(((be_value(&data[..2]) << 4) + be_value(&data[2..])) as u32),
+                (0x04, 2) => upper = be_value(data) << 16,
+                (0x05, 4) => file.start = Some(be_value(data) as u32),
+                (0x02..=0x05, _) => return Err(invalid(i, "address record has the wrong length")),
+                _ => return Err(invalid(i, "unknown record type")),
+            }
+        }
+        Err(invalid(text.lines().count(), "missing end of file record"))
+    }
+
+    /// Parse a Motorola S-record file. Every record's checksum is checked; header and count
+    /// records are skipped.
+    pub fn parse_srec(text: &str) -> Result<HexFile, ReversedWordsError> {
+        let mut file = HexFile::default();
+        for (i, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())).filter(|(_, line)| !line.is_empty()) {
+            let mut chars = line.chars();
+            if chars.next() != Some('S') {
+                return Err(invalid(i, "record doesn't start with 'S'"));
+            }
+            let kind = chars.next().and_then(|c| c.to_digit(10)).ok_or_else(|| invalid(i, "unknown record type"))?;
+            let address_len = match kind {
+                0 | 1 | 5 | 9 => 2,
+                2 | 6 | 8 => 3,
+                3 | 7 => 4,
+                _ => return Err(invalid(i, "unknown record type")),
+            };
+            let bytes = parse_hex(chars.as_str()).map_err(|e| invalid(i, &e.to_string()))?;
+            if bytes.len() < address_len + 2 || bytes.len() != bytes[0] as usize + 1 {
+                return Err(invalid(i, "record length doesn't match its byte count"));
+            }
+            if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0xFF {
+                return Err(invalid(i, "bad checksum"));
+            }
+            let addr = be_value(&bytes[1..1 + address_len]);
+            match kind {
+                1..=3 => file.push(addr, &bytes[1 + address_len..bytes.len() - 1]),
+                7..=9 => file.start = Some(addr as u32),
+                _ => {}
+            }
+        }
+        Ok(file)
+    }
+
+    // fail if any data is at or past 2^`bits`
+    fn check_end(&self, bits: u32, format: &'static str) -> Result<(), ReversedWordsError> {
+        for (addr, data) in &self.segments {
+            if addr.saturating_add(data.len() as u64) > 1 << bits {
+                let addr = (*addr).max(1 << bits);
+                return Err(ReversedWordsError::UnrepresentableAddress { addr, format });
+            }
+        }
+        Ok(())
+    }
+
+    fn end(&self) -> u64 {
+        self.segments.iter().map(|(addr, data)| addr + data.len() as u64).max().unwrap_or(0)
+    }
+
+    /// Write the file as Intel HEX. Addresses must be below 4 GiB.
+    pub fn to_ihex(&self) -> Result<String, ReversedWordsError> {
+        self.check_end(32, "Intel HEX")?;
+        let mut text = String::new();
+        let mut record = |kind: u8, offset: u16, data: &[u8]| {
+            let mut bytes = vec![data.len() as u8];
+            bytes.extend_from_slice(&offset.to_be_bytes());
+            bytes.push(kind);
+            bytes.extend_from_slice(data);
+            let sum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+            bytes.push(sum.wrapping_neg());
+            text.push(':');
+            push_hex(&mut text, &bytes);
+            text.push('\n');
+        };
+        let mut upper = 0;
+        for (addr, data) in &self.segments {
+            let mut addr = *addr;
+            let mut rest = &data[..];
+            while !rest.is_empty() {
+                if addr >> 16 != upper {
+                    upper = addr >> 16;
+                    record(0x04, 0, &(upper as u16).to_be_bytes());
+                }
+                // records can't cross a 64 KiB boundary
+                let n = rest.len().min(BYTES_PER_RECORD).min((0x1_0000 - (addr & 0xFFFF)) as usize);
+                record(0x00, addr as u16, &rest[..n]);
+                addr += n as u64;
+                rest = &rest[n..];
+            }
+        }
+        if let Some(start) = self.start {
+            record(0x05, 0, &start.to_be_bytes());
+        }
+        record(0x01, 0, &[]);
+        Ok(text)
+    }
+
+    /// Write the file as S-records. Addresses must be below 4 GiB.
+    pub fn to_srec(&self) -> Result<String, ReversedWordsError> {
+        self.check_end(32, "S-record")?;
+        let last = self.end().max(self.start.unwrap_or(0) as u64 + 1) - 1;
+        let (data_kind, start_kind, address_len) = match last {
+            0..=0xFFFF => (1, 9, 2),
+            0x1_0000..=0xFF_FFFF => (2, 8, 3),
+            _ => (3, 7, 4),
+        };
+        let mut text = String::new();
+        let mut record = |kind: u8, address_len: usize, addr: u64, data: &[u8]| {
+            let mut bytes = vec![(address_len + data.len() + 1) as u8];
+            bytes.extend_from_slice(&addr.to_be_bytes()[8 - address_len..]);
+            bytes.extend_from_slice(data);
+            let sum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+            bytes.push(!sum);
+            let _ = write!(text, "S{}", kind);
+            push_hex(&mut text, &bytes);
+            text.push('\n');
+        };
+        record(0, 2, 0, &[]);
+        let mut count = 0u64;
+        for (addr, data) in &self.segments {
+            for (i, chunk) in data.chunks(BYTES_PER_RECORD).enumerate() {
+                record(data_kind, address_len, addr + (i * BYTES_PER_RECORD) as u64, chunk);
+                count += 1;
+            }
+        }
+        if count <= 0xFFFF {
+            record(5, 2, count, &[]);
+        } else if count <= 0xFF_FFFF {
+            record(6, 3, count, &[]);
+        }
+        record(start_kind, address_len, self.start.unwrap_or(0) as u64, &[]);
+        Ok(text)
+    }
+
+    /// The logical bytes of `range` as a single segment, at file address `base + range.start`.
+    pub fn from_region(words: &ReversedWords, range: Range<u64>, base: u64) -> std::io::Result<HexFile> {
+        words.check_range(&range)?;
+        let mut data = vec![0u8; (range.end - range.start) as usize];
+        let n = words.read_at(range.start, &mut data)?;
+        data.truncate(n);
+        Ok(HexFile { segments: vec![(base + range.start, data)], start: None })
+    }
+
+    /// Write every segment into `words`, which starts at file address `base`. Nothing is
+    /// written unless every segment falls inside the view.
+    pub fn load_into(&self, words: &mut ReversedWords, base: u64) -> std::io::Result<()> {
+        let ranges = self
+            .segments
+            .iter()
+            .map(|(addr, data)| {
+                let start = addr.checked_sub(base).ok_or(ReversedWordsError::UnmappedAddress { addr: *addr })?;
+                let range = start..start.saturating_add(data.len() as u64);
+                words.check_range(&range)?;
+                Ok(range)
+            })
+            .collect::<std::io::Result<Vec<Range<u64>>>>()?;
+        for (range, (_, data)) in ranges.into_iter().zip(&self.segments) {
+            words.write_at(range.start, data)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hexfile::*, ReversedWords};
+
+    const IHEX: &str = ":10010000214601360121470136007EFE09D2190140
+:100110002146017E17C20001FF5F16002148011928
+:10012000194E79234623965778239EDA3F01B2CAA7
+:100130003F0156702B5E712B722B732146013421C7
+:00000001FF
+";
+
+    const SREC: &str = "S00F000068656C6C6F202020202000003C
+S11F00007C0802A6900100049421FFF07C6C1B787C8C23783C6000003863000026
+S11F001C4BFFFFE5398000007D83637880010014382100107C0803A64E800020E9
+S111003848656C6C6F20776F726C642E0A0042
+S5030003F9
+S9030000FC
+";
+
+    #[test]
+    fn intel_hex() {
+        let file = HexFile::parse_ihex(IHEX).unwrap();
+        assert_eq!(1, file.segments.len());
+        assert_eq!((0x100, 64), (file.segments[0].0, file.segments[0].1.len()));
+        assert_eq!([0x21, 0x46, 0x01, 0x36], file.segments[0].1[..4]);
+        assert_eq!(IHEX, file.to_ihex().unwrap());
+
+        let crossing = HexFile { segments: vec![(0x1_FFF8, (0..12).collect())], start: Some(0x8000_0400) };
+        let text = crossing.to_ihex().unwrap();
+        assert_eq!(
+            vec![":020000040001F9", ":08FFF8000001020304050607E5", ":020000040002F8", ":0400000008090A0BD6", ":040000058000040073", ":00000001FF"],
+            text.lines().collect::<Vec<_>>()
+        );
+        assert_eq!(crossing, HexFile::parse_ihex(&text).unwrap());
+
+        // extended segment addressing: 0x1000 << 4 + 0x0010
+        let segmented = HexFile::parse_ihex(":020000021000EC\n:01001000559A\n:00000001FF").unwrap();
+        assert_eq!(vec![(0x1_0010, vec![0x55])], segmented.segments);
+        assert!(matches!(HexFile::parse_ihex(":0100100055"), Err(ReversedWordsError::InvalidHexFile { line: 1, .. })));
+        assert!(matches!(HexFile::parse_ihex(":0100100055FF\n:00000001FF"), Err(ReversedWordsError::InvalidHexFile { line: 1, .. })));
+        assert!(matches!(HexFile::parse_ihex(":0100100055\n:00000001FF"), Err(ReversedWordsError::InvalidHexFile { line: 1, .. })));
+        assert!(matches!(HexFile::parse_ihex(":0100100055\n"), Err(ReversedWordsError::InvalidHexFile { line: 1, .. })));
+        let too_far = HexFile { segments: vec![(0xFFFF_FFFF, vec![1, 2])], start: None };
+        assert!(matches!(too_far.to_ihex(), Err(ReversedWordsError::UnrepresentableAddress { addr: 0x1_0000_0000, .. })));
+    }
+
+    #[test]
+    fn s_records() {
+        let file = HexFile::parse_srec(SREC).unwrap();
+        assert_eq!(Some(0), file.start);
+        assert_eq!(1, file.segments.len());
+        assert_eq!(0x46, file.segments[0].1.len());
+        assert_eq!(b"Hello world.\n", &file.segments[0].1[0x38..0x45]);
+        let text = file.to_srec().unwrap();
+        assert_eq!(Some("S0030000FC"), text.lines().next());
+        assert_eq!(Some("S9030000FC"), text.lines().last());
+        assert_eq!(file, HexFile::parse_srec(&text).unwrap());
+
+        let wide = HexFile { segments: vec![(0x8000_0000, vec![0xAB])], start: Some(0x8000_0000) };
+        let text = wide.to_srec().unwrap();
+        assert_eq!(vec!["S0030000FC", "S30680000000ABCE", "S5030001FB", "S705800000007A"], text.lines().collect::<Vec<_>>());
+        assert!(matches!(HexFile::parse_srec("S1030000FD"), Err(ReversedWordsError::InvalidHexFile { line: 1, .. })));
+        assert!(matches!(HexFile::parse_srec("S4030000FC"), Err(ReversedWordsError::InvalidHexFile { line: 1, .. })));
+    }
+
+    #[test]
+    fn loads_and_exports_logical_bytes() {
+        let mut rdram = vec![0u8; 16];
+        let mut words = ReversedWords::new(&mut rdram);
+        let file = HexFile::parse_ihex(":048002000102030470\n:00000001FF").unwrap();
+        file.load_into(&mut words, 0x8000).unwrap();
+        let exported = HexFile::from_region(&words, 2..6, 0x8000).unwrap();
+        assert_eq!(vec![(0x8002, vec![1, 2, 3, 4])], exported.segments);
+        assert!(file.load_into(&mut words, 0x8004).is_err());
+        assert!(file.load_into(&mut words, 0x7FF0).is_err());
+        assert_eq!([2, 1, 0, 0, 0, 0, 4, 3, 0, 0, 0, 0, 0, 0, 0, 0][..], rdram[..]);
+    }
+}
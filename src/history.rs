@@ -0,0 +1,348 @@
+//! A bounded history of snapshots of a buffer, for undoing recent edits.
+//!
+//! [`SnapshotRing`] keeps the newest snapshot whole and every older one as the bytes that differ
+//! from the snapshot after it, so a long history of a mostly idle buffer stays small. Once the
+//! ring is full the oldest snapshot is dropped. Snapshots are of the storage bytes, so they
+//! don't depend on the view's word size.
+//!
+//! With the `lz4` feature the older snapshots can also be kept LZ4 compressed
+//! ([`SnapshotRing::with_compression`]), and [`CompressedSnapshot`] holds a single one. RAM is
+//! mostly zeroes and repeated structures, so this typically shrinks full snapshots many times
+//! over, which is what makes keeping a hundred copies of an 8 MiB RDRAM practical.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "lz4")]
+use std::convert::TryInto;
+
+use crate::{ReversedWords, ReversedWordsError};
+
+/// How the snapshots older than the newest are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SnapshotMode {
+    /// Every snapshot is a complete copy.
+    Full,
+    /// Only the bytes that differ from the next snapshot are kept.
+    #[default]
+    Delta,
+}
+
+// (storage offset, bytes) runs turning one snapshot into the one before it
+type Undo = Vec<(usize, Vec<u8>)>;
+
+#[derive(Clone, Debug)]
+enum Stored {
+    Plain(Undo),
+    #[cfg(feature = "lz4")]
+    Compressed(Vec<u8>),
+}
+
+impl Stored {
+    fn new(undo: Undo, compress: bool) -> Stored {
+        #[cfg(feature = "lz4")]
+        if compress {
+            let mut bytes = Vec::new();
+            for (offset, run) in &undo {
+                bytes.extend_from_slice(&(*offset as u64).to_le_bytes());
+                bytes.extend_from_slice(&(run.len() as u64).to_le_bytes());
+                bytes.extend_from_slice(run);
+            }
+            return Stored::Compressed(lz4_flex::compress_prepend_size(&bytes));
+        }
+        let _ = compress;
+        Stored::Plain(undo)
+    }
+
+    fn into_undo(self) -> Undo {
+        match self {
+            Stored::Plain(undo) => undo,
+            #[cfg(feature = "lz4")]
+            Stored::Compressed(compressed) => {
+                let bytes = lz4_flex::decompress_size_prepended(&compressed).expect("compressed by Stored::new");
+                let mut rest = &bytes[..];
+                let mut undo = Vec::new();
+                while !rest.is_empty() {
+                    let field = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().expect("8 bytes")) as usize;
+                    let (offset, len) = (field(&rest[..8]), field(&rest[8..16]));
+                    undo.push((offset, rest[16..16 + len].to_vec()));
+                    rest = &rest[16 + len..];
+                }
+                undo
+            }
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Stored::Plain(undo) => undo.iter().map(|(_, run)| run.len() + std::mem::size_of::<usize>()).sum(),
+            #[cfg(feature = "lz4")]
+            Stored::Compressed(compressed) => compressed.len(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SnapshotRing {
+    mode: SnapshotMode,
+    capacity: usize,
+    interval: Option<Duration>,
+    last_capture: Option<Instant>,
+    latest: Option<Vec<u8>>,
+    // oldest first, the last one undoes `latest`
+    older: VecDeque<Stored>,
+    compress: bool,
+}
+
+fn undo(from: &[u8], to: &[u8], mode: SnapshotMode) -> Undo {
+    if mode == SnapshotMode::Full {
+        return vec![(0, to.to_vec())];
+    }
+    let mut runs: Undo = Vec::new();
+    for (offset, (_, byte)) in from.iter().zip(to).enumerate().filter(|(_, (from, to))| from != to) {
+        match runs.last_mut() {
+            Some((start, run)) if *start + run.len() == offset => run.push(*byte),
+            _ => runs.push((offset, vec![*byte])),
+        }
+    }
+    runs
+}
+
+impl SnapshotRing {
+    /// A ring keeping up to `capacity` snapshots, at least 1.
+    pub fn new(capacity: usize) -> SnapshotRing {
+        SnapshotRing {
+            mode: SnapshotMode::default(),
+            capacity: capacity.max(1),
+            interval: None,
+            last_capture: None,
+            latest: None,
+            older: VecDeque::new(),
+            compress: false,
+        }
+    }
+
+    /// Keep the snapshots older than the newest LZ4 compressed.
+    #[cfg(feature = "lz4")]
+    pub fn with_compression(mut self, compress: bool) -> SnapshotRing {
+        self.compress = compress;
+        self
+    }
+
+    /// Approximate bytes used by the held snapshots.
+    pub fn stored_bytes(&self) -> usize {
+        self.latest.as_ref().map_or(0, Vec::len) + self.older.iter().map(Stored::size).sum::<usize>()
+    }
+
+    pub fn with_mode(mut self, mode: SnapshotMode) -> SnapshotRing {
+        self.mode = mode;
+        self
+    }
+
+    /// How often [`SnapshotRing::poll`] captures.
+    pub fn with_interval(mut self, interval: Duration) -> SnapshotRing {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of snapshots held.
+    pub fn len(&self) -> usize {
+        self.older.len() + self.latest.is_some() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.older.clear();
+        self.last_capture = None;
+    }
+
+    /// Take a snapshot of `words`. A buffer of a different length than the previous snapshots
+    /// starts a new history.
+    pub fn capture(&mut self, words: &ReversedWords) {
+        let snapshot = words.cursor.get_ref().to_vec();
+        match self.latest.take() {
+            Some(latest) if latest.len() == snapshot.len() => {
+                self.older.push_back(Stored::new(undo(&snapshot, &latest, self.mode), self.compress))
+            }
+            _ => self.older.clear(),
+        }
+        self.latest = Some(snapshot);
+        while self.len() > self.capacity {
+            self.older.pop_front();
+        }
+        self.last_capture = Some(Instant::now());
+    }
+
+    /// Capture if the interval has passed since the last capture (or there is none yet), returning
+    /// whether it did. Without an interval this always captures.
+    pub fn poll(&mut self, words: &ReversedWords) -> bool {
+        let due = match (self.interval, self.last_capture) {
+            (Some(interval), Some(last)) => last.elapsed() >= interval,
+            _ => true,
+        };
+        if due {
+            self.capture(words);
+        }
+        due
+    }
+
+    /// Restore `words` to the snapshot `back` captures before the newest (0 being the newest),
+    /// discarding the snapshots after it.
+    pub fn rewind(&mut self, back: usize, words: &mut ReversedWords) -> Result<(), ReversedWordsError> {
+        let available = self.len();
+        let latest = match self.latest.as_mut() {
+            Some(latest) if back < available => latest,
+            _ => return Err(ReversedWordsError::SnapshotUnavailable { back, available }),
+        };
+        let storage = words.cursor.get_mut();
+        if storage.len() != latest.len() {
+            return Err(ReversedWordsError::RangeOutOfRange { start: 0, end: latest.len() as u64, len: storage.len() as u64 });
+        }
+        for _ in 0..back {
+            for (offset, run) in self.older.pop_back().expect("counted above").into_undo() {
+                latest[offset..offset + run.len()].copy_from_slice(&run);
+            }
+        }
+        storage.copy_from_slice(latest);
+        Ok(())
+    }
+}
+
+/// Zeroes every held snapshot and empties the ring.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for SnapshotRing {
+    fn zeroize(&mut self) {
+        self.latest.zeroize();
+        for stored in &mut self.older {
+            match stored {
+                Stored::Plain(undo) => undo.iter_mut().for_each(|(_, run)| run.zeroize()),
+                #[cfg(feature = "lz4")]
+                Stored::Compressed(compressed) => compressed.zeroize(),
+            }
+        }
+        self.clear();
+    }
+}
+
+/// One LZ4 compressed snapshot of a view's storage.
+#[cfg(feature = "lz4")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressedSnapshot {
+    compressed: Vec<u8>,
+    len: usize,
+}
+
+#[cfg(feature = "lz4")]
+impl CompressedSnapshot {
+    pub fn capture(words: &ReversedWords) -> CompressedSnapshot {
+        let storage = words.cursor.get_ref();
+        CompressedSnapshot { compressed: lz4_flex::compress_prepend_size(storage), len: storage.len() }
+    }
+
+    /// Length of the snapshot uncompressed.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn compressed_len(&self) -> usize {
+        self.compressed.len()
+    }
+
+    /// The snapshot's storage bytes.
+    pub fn decompress(&self) -> Vec<u8> {
+        lz4_flex::decompress_size_prepended(&self.compressed).expect("compressed by capture")
+    }
+
+    /// Put the snapshot back into `words`, which must be the same length.
+    pub fn restore(&self, words: &mut ReversedWords) -> Result<(), ReversedWordsError> {
+        let storage = words.cursor.get_mut();
+        if storage.len() != self.len {
+            return Err(ReversedWordsError::RangeOutOfRange { start: 0, end: self.len as u64, len: storage.len() as u64 });
+        }
+        storage.copy_from_slice(&self.decompress());
+        Ok(())
+    }
+}
+
+/// Zeroes the compressed bytes, leaving an empty snapshot.
+#[cfg(all(feature = "lz4", feature = "zeroize"))]
+impl zeroize::Zeroize for CompressedSnapshot {
+    fn zeroize(&mut self) {
+        self.compressed.zeroize();
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{history::*, Endian};
+
+    #[test]
+    fn rewinds_through_deltas() {
+        for mode in [SnapshotMode::Delta, SnapshotMode::Full] {
+            let mut data = vec![0u8; 16];
+            let mut words = ReversedWords::new(&mut data);
+            let mut ring = SnapshotRing::new(3).with_mode(mode);
+            for value in 1..=4u32 {
+                words.write_u32_at(4, value, Endian::Big).unwrap();
+                ring.capture(&words);
+            }
+            assert_eq!(3, ring.len());
+            words.write_u32_at(0, 0xFFFF_FFFF, Endian::Big).unwrap();
+            ring.rewind(0, &mut words).unwrap();
+            assert_eq!(0, words.read_u32_at(0, Endian::Big).unwrap());
+            ring.rewind(1, &mut words).unwrap();
+            assert_eq!(3, words.read_u32_at(4, Endian::Big).unwrap());
+            assert_eq!(2, ring.len());
+            assert_eq!(Err(ReversedWordsError::SnapshotUnavailable { back: 2, available: 2 }), ring.rewind(2, &mut words));
+            ring.rewind(1, &mut words).unwrap();
+            assert_eq!(2, words.read_u32_at(4, Endian::Big).unwrap());
+        }
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn compressed_history() {
+        let mut data = vec![0u8; 0x1_0000];
+        let mut words = ReversedWords::new(&mut data);
+        let mut ring = SnapshotRing::new(8).with_mode(SnapshotMode::Full).with_compression(true);
+        for value in 0..8u32 {
+            words.write_u32_at(value as u64 * 4, value + 1, Endian::Big).unwrap();
+            ring.capture(&words);
+        }
+        assert!(ring.stored_bytes() < 0x1_0000 + 0x1000, "{} bytes", ring.stored_bytes());
+        ring.rewind(7, &mut words).unwrap();
+        assert_eq!(1, words.read_u32_at(0, Endian::Big).unwrap());
+        assert_eq!(0, words.read_u32_at(4, Endian::Big).unwrap());
+
+        let snapshot = CompressedSnapshot::capture(&words);
+        assert!(snapshot.compressed_len() < 0x400);
+        words.fill(0..16, 0xFF).unwrap();
+        snapshot.restore(&mut words).unwrap();
+        assert_eq!(1, words.read_u32_at(0, Endian::Big).unwrap());
+    }
+
+    #[test]
+    fn polls_on_an_interval() {
+        let mut data = vec![0u8; 4];
+        let words = ReversedWords::new(&mut data);
+        let mut ring = SnapshotRing::new(4).with_interval(Duration::from_secs(3600));
+        assert!(ring.poll(&words));
+        assert!(!ring.poll(&words));
+        assert_eq!(1, ring.len());
+    }
+}
@@ -0,0 +1,363 @@
+//! Recording writes made through a view, and sharing them as patches.
+//!
+//! [`RecordingWords`] wraps a [`ReversedWords`] and logs every write it makes, with the bytes
+//! that were overwritten, into a [`WriteJournal`]. The journal can then be exported as the net
+//! changes it made: an IPS patch, a GameShark code list, or plain `address: bytes` text.
+//!
+//! A journal can also be saved with [`WriteJournal::write_to`] and replayed later against a fresh
+//! buffer, which makes an editing session reproducible and usable as a test fixture. The format
+//! is the magic `RWJ1`, then for every record its address as a little endian `u64`, its length
+//! as a little endian `u32`, the old bytes and the new bytes.
+
+use std::{
+    collections::BTreeMap,
+    convert::{TryFrom, TryInto},
+    fmt::Write as _,
+    io::{Read, Write},
+    ops::Deref,
+};
+
+use binread::Endian;
+
+use crate::{
+    events::{Event, EventBus},
+    Primitive, ReversedWords, ReversedWordsError,
+};
+
+/// One write, in logical bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WriteRecord {
+    pub addr: u64,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+impl WriteRecord {
+    /// Make the write, failing at the last address if it doesn't all fit.
+    fn write(&self, words: &mut ReversedWords) -> std::io::Result<()> {
+        if words.write_at(self.addr, &self.new)? < self.new.len() {
+            return Err(words.out_of_range(self.addr.saturating_add(self.new.len() as u64 - 1)));
+        }
+        Ok(())
+    }
+}
+
+/// Writes in the order they were made.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteJournal {
+    records: Vec<WriteRecord>,
+}
+
+// IPS offsets are 24 bits, and this one reads as the "EOF" marker
+const IPS_EOF_OFFSET: u64 = 0x45_4F46;
+const IPS_MAX_RECORD: usize = 0xFFFF;
+
+const JOURNAL_MAGIC: &[u8; 4] = b"RWJ1";
+
+fn invalid_journal(reason: &str) -> std::io::Error {
+    ReversedWordsError::InvalidJournal { reason: reason.to_string() }.into()
+}
+
+impl WriteJournal {
+    pub fn new() -> WriteJournal {
+        WriteJournal::default()
+    }
+
+    pub fn push(&mut self, record: WriteRecord) {
+        self.records.push(record);
+    }
+
+    pub fn records(&self) -> &[WriteRecord] {
+        &self.records
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(JOURNAL_MAGIC)?;
+        for record in &self.records {
+            let len = u32::try_from(record.new.len()).map_err(|_| invalid_journal("record longer than 4 GiB"))?;
+            writer.write_all(&record.addr.to_le_bytes())?;
+            writer.write_all(&len.to_le_bytes())?;
+            writer.write_all(&record.old)?;
+            writer.write_all(&record.new)?;
+        }
+        Ok(())
+    }
+
+    /// Read a journal saved by [`WriteJournal::write_to`].
+    pub fn read_from<R: Read>(mut reader: R) -> std::io::Result<WriteJournal> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut rest = data.strip_prefix(JOURNAL_MAGIC).ok_or_else(|| invalid_journal("missing RWJ1 magic"))?;
+        let mut journal = WriteJournal::new();
+        while !rest.is_empty() {
+            if rest.len() < 12 {
+                return Err(invalid_journal("truncated record header"));
+            }
+            let (header, tail) = rest.split_at(12);
+            let addr = u64::from_le_bytes(header[..8].try_into().expect("8 bytes"));
+            let len = u32::from_le_bytes(header[8..].try_into().expect("4 bytes")) as usize;
+            if tail.len() / 2 < len {
+                return Err(invalid_journal("truncated record data"));
+            }
+            let (old, tail) = tail.split_at(len);
+            let (new, tail) = tail.split_at(len);
+            journal.push(WriteRecord { addr, old: old.to_vec(), new: new.to_vec() });
+            rest = tail;
+        }
+        Ok(journal)
+    }
+
+    /// Make every recorded write again, in order.
+    pub fn replay(&self, words: &mut ReversedWords) -> std::io::Result<()> {
+        for record in &self.records {
+            record.write(words)?;
+        }
+        Ok(())
+    }
+
+    /// Like [`WriteJournal::replay`], but first check each write finds the bytes it originally
+    /// overwrote, failing with [`ReversedWordsError::JournalMismatch`] at the first that doesn't.
+    /// Writes before the mismatch have been made.
+    pub fn replay_verified(&self, words: &mut ReversedWords) -> std::io::Result<()> {
+        for record in &self.records {
+            let mut current = vec![0u8; record.old.len()];
+            let n = words.read_at(record.addr, &mut current)?;
+            if let Some(i) = (0..record.old.len()).find(|&i| i >= n || current[i] != record.old[i]) {
+                return Err(ReversedWordsError::JournalMismatch { addr: record.addr.saturating_add(i as u64) }.into());
+            }
+            record.write(words)?;
+        }
+        Ok(())
+    }
+
+    /// The net effect of the journal: runs of consecutive addresses whose final value differs
+    /// from the value before the first write to them, in address order.
+    pub fn changes(&self) -> Vec<(u64, Vec<u8>)> {
+        // address -> (original, final)
+        let mut bytes = BTreeMap::new();
+        for record in &self.records {
+            for (i, (old, new)) in record.old.iter().zip(&record.new).enumerate() {
+                bytes.entry(record.addr + i as u64).or_insert((*old, *new)).1 = *new;
+            }
+        }
+        let mut runs: Vec<(u64, Vec<u8>)> = Vec::new();
+        for (addr, (_, new)) in bytes.into_iter().filter(|(_, (old, new))| old != new) {
+            match runs.last_mut() {
+                Some((start, run)) if *start + run.len() as u64 == addr => run.push(new),
+                _ => runs.push((addr, vec![new])),
+            }
+        }
+        runs
+    }
+
+    /// An IPS patch applying [`WriteJournal::changes`] to the buffer the view was over.
+    pub fn to_ips(&self) -> Result<Vec<u8>, ReversedWordsError> {
+        let mut patch = b"PATCH".to_vec();
+        for (addr, run) in self.changes() {
+            for (i, chunk) in run.chunks(IPS_MAX_RECORD).enumerate() {
+                let offset = addr + (i * IPS_MAX_RECORD) as u64;
+                if offset >= 1 << 24 || offset == IPS_EOF_OFFSET {
+                    return Err(ReversedWordsError::UnrepresentableAddress { addr: offset, format: "IPS" });
+                }
+                patch.extend_from_slice(&offset.to_be_bytes()[5..]);
+                patch.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+                patch.extend_from_slice(chunk);
+            }
+        }
+        patch.extend_from_slice(b"EOF");
+        Ok(patch)
+    }
+
+    /// N64 GameShark codes writing [`WriteJournal::changes`], for a view mapped at virtual
+    /// address `base` (`0x80000000` for RDRAM). Aligned byte pairs become 16-bit `81` codes, the
+    /// rest 8-bit `80` codes. Only KSEG0 addresses below `0x81000000` can be expressed.
+    pub fn to_gameshark(&self, base: u64) -> Result<String, ReversedWordsError> {
+        let mut codes = String::new();
+        for (addr, run) in self.changes() {
+            let mut i = 0;
+            while i < run.len() {
+                let vaddr = base + addr + i as u64;
+                if vaddr >> 24 != 0x80 {
+                    return Err(ReversedWordsError::UnrepresentableAddress { addr: vaddr, format: "GameShark" });
+                }
+                if vaddr.is_multiple_of(2) && i + 1 < run.len() {
+                    let _ = writeln!(codes, "81{:06X} {:02X}{:02X}", vaddr & 0xFF_FFFF, run[i], run[i + 1]);
+                    i += 2;
+                } else {
+                    let _ = writeln!(codes, "80{:06X} 00{:02X}", vaddr & 0xFF_FFFF, run[i]);
+                    i += 1;
+                }
+            }
+        }
+        Ok(codes)
+    }
+
+    /// One `address: bytes` line per run of [`WriteJournal::changes`], addresses offset by `base`.
+    pub fn to_text(&self, base: u64) -> String {
+        let mut text = String::new();
+        for (addr, run) in self.changes() {
+            let _ = write!(text, "{:#010x}:", base + addr);
+            for byte in run {
+                let _ = write!(text, " {:02x}", byte);
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// A view that records its writes. Reads go through `Deref`; writes only through the methods
+/// here, so nothing escapes the journal.
+pub struct RecordingWords<'w, 'a> {
+    words: &'w mut ReversedWords<'a>,
+    journal: WriteJournal,
+    bus: Option<EventBus>,
+}
+
+impl<'w, 'a> RecordingWords<'w, 'a> {
+    pub fn new(words: &'w mut ReversedWords<'a>) -> RecordingWords<'w, 'a> {
+        RecordingWords { words, journal: WriteJournal::new(), bus: None }
+    }
+
+    /// Also publish every recorded write to `bus` as an [`Event::Write`].
+    pub fn publish_to(mut self, bus: EventBus) -> RecordingWords<'w, 'a> {
+        self.bus = Some(bus);
+        self
+    }
+
+    pub fn journal(&self) -> &WriteJournal {
+        &self.journal
+    }
+
+    pub fn into_journal(self) -> WriteJournal {
+        self.journal
+    }
+
+    // run `write` and record whatever it changed in the `len` bytes at `addr`, even if it failed
+    fn recorded<T>(
+        &mut self,
+        addr: u64,
+        len: usize,
+        write: impl FnOnce(&mut ReversedWords<'a>) -> std::io::Result<T>,
+    ) -> std::io::Result<T> {
+        let mut old = vec![0u8; len];
+        let n = self.words.read_at(addr, &mut old)?;
+        old.truncate(n);
+        let result = write(self.words);
+        let mut new = vec![0u8; n];
+        self.words.read_at(addr, &mut new)?;
+        if result.is_ok() || old != new {
+            let record = WriteRecord { addr, old, new };
+            if let Some(bus) = &self.bus {
+                bus.publish(Event::Write(record.clone()));
+            }
+            self.journal.push(record);
+        }
+        result
+    }
+
+    /// [`ReversedWords::write_at`], recorded.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        self.recorded(addr, buf.len(), |words| words.write_at(addr, buf))
+    }
+
+    /// [`ReversedWords::write_value_at`], recorded.
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        self.recorded(addr, std::mem::size_of::<T>(), |words| words.write_value_at(addr, value, endian))
+    }
+
+    pub fn fill(&mut self, range: std::ops::Range<u64>, byte: u8) -> std::io::Result<()> {
+        let len = range.end.saturating_sub(range.start) as usize;
+        self.recorded(range.start, len, |words| words.fill(range, byte))
+    }
+}
+
+impl<'a> Deref for RecordingWords<'_, 'a> {
+    type Target = ReversedWords<'a>;
+
+    fn deref(&self) -> &ReversedWords<'a> {
+        self.words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{journal::*, ReversedWords};
+
+    #[test]
+    fn journal_exports_net_changes() {
+        let mut data = vec![0u8; 16];
+        let mut words = ReversedWords::new(&mut data);
+        let mut recording = RecordingWords::new(&mut words);
+        recording.write_value_at(4, 0xBEEFu16, Endian::Big).unwrap();
+        recording.write_at(6, &[0x12, 0]).unwrap();
+        recording.write_at(9, &[0xFF]).unwrap();
+        recording.write_at(9, &[0]).unwrap();
+        assert_eq!(0x12, recording.get_byte(6).unwrap());
+        let journal = recording.into_journal();
+        assert_eq!(4, journal.len());
+        assert_eq!(vec![(4, vec![0xBE, 0xEF, 0x12])], journal.changes());
+
+        assert_eq!(b"PATCH\x00\x00\x04\x00\x03\xBE\xEF\x12EOF".to_vec(), journal.to_ips().unwrap());
+        assert_eq!("81000004 BEEF\n80000006 0012\n", journal.to_gameshark(0x8000_0000).unwrap());
+        assert_eq!("0x80000004: be ef 12\n", journal.to_text(0x8000_0000));
+        assert_eq!(
+            Err(ReversedWordsError::UnrepresentableAddress { addr: 0x1000_0004, format: "GameShark" }),
+            journal.to_gameshark(0x1000_0000)
+        );
+    }
+
+    #[test]
+    fn saved_journal_replays() {
+        let mut data = vec![0u8; 8];
+        let mut words = ReversedWords::new(&mut data);
+        let mut recording = RecordingWords::new(&mut words);
+        recording.write_value_at(2, 0x1234_5678u32, Endian::Big).unwrap();
+        recording.fill(0..2, 0xAA).unwrap();
+        let mut saved = Vec::new();
+        recording.journal().write_to(&mut saved).unwrap();
+        let journal = WriteJournal::read_from(&saved[..]).unwrap();
+        assert_eq!(recording.journal(), &journal);
+
+        let mut fresh = vec![0u8; 8];
+        journal.replay_verified(&mut ReversedWords::new(&mut fresh)).unwrap();
+        assert_eq!(data, fresh);
+        let error = journal.replay_verified(&mut ReversedWords::new(&mut fresh)).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::JournalMismatch { addr: 2 }), ReversedWordsError::from_io(&error));
+        journal.replay(&mut ReversedWords::new(&mut fresh)).unwrap();
+
+        assert!(WriteJournal::read_from(&saved[..saved.len() - 1]).is_err());
+        assert!(WriteJournal::read_from(&b"RWJ2"[..]).is_err());
+    }
+
+    #[test]
+    fn replays_fail_on_short_writes() {
+        let mut data = vec![0u8; 8];
+        let mut journal = WriteJournal::new();
+        journal.push(WriteRecord { addr: 7, old: vec![0], new: vec![1, 2] });
+        let error = journal.replay_verified(&mut ReversedWords::new(&mut data)).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::AddressOutOfRange { addr: 8, len: 8 }), ReversedWordsError::from_io(&error));
+
+        let mut journal = WriteJournal::new();
+        journal.push(WriteRecord { addr: u64::MAX, old: vec![0, 0], new: vec![1, 2] });
+        let error = journal.replay(&mut ReversedWords::new(&mut data)).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::AddressOutOfRange { addr: u64::MAX, len: 8 }),
+            ReversedWordsError::from_io(&error)
+        );
+        let error = journal.replay_verified(&mut ReversedWords::new(&mut data)).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::JournalMismatch { addr: u64::MAX }), ReversedWordsError::from_io(&error));
+    }
+}
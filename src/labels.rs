@@ -0,0 +1,218 @@
+//! Names, comments and types for addresses, for human readable output.
+//!
+//! Labels cover a range of logical addresses in a view. [`Labels::describe`] turns an address into
+//! `name+0x4` form, [`hexdump`] prints headers where labels start, and [`Watcher::watch_label`]
+//! watches a label's range by name.
+
+use std::{collections::HashMap, fmt::Write as _, ops::Range};
+
+use crate::{expr::ValueType, symbols::SymbolTable, watch::{WatchId, Watcher}, ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    pub name: String,
+    pub range: Range<u64>,
+    pub comment: Option<String>,
+    pub value_type: Option<ValueType>,
+}
+
+impl Label {
+    pub fn new(name: &str, range: Range<u64>) -> Label {
+        Label { name: name.to_string(), range, comment: None, value_type: None }
+    }
+
+    pub fn with_comment(mut self, comment: &str) -> Label {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    pub fn with_value_type(mut self, value_type: ValueType) -> Label {
+        self.value_type = Some(value_type);
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Labels {
+    // sorted by range start
+    labels: Vec<Label>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Labels {
+    pub fn new() -> Labels {
+        Labels::default()
+    }
+
+    /// Add a label, replacing any existing label with the same name.
+    pub fn insert(&mut self, label: Label) {
+        if let Some(index) = self.by_name.get(&label.name) {
+            self.labels.remove(*index);
+        }
+        let index = self.labels.partition_point(|existing| existing.range.start <= label.range.start);
+        self.labels.insert(index, label);
+        self.by_name = self.labels.iter().enumerate().map(|(index, label)| (label.name.clone(), index)).collect();
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Label> {
+        let index = self.by_name.remove(name)?;
+        let label = self.labels.remove(index);
+        self.by_name = self.labels.iter().enumerate().map(|(index, label)| (label.name.clone(), index)).collect();
+        Some(label)
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Label> {
+        self.by_name.get(name).map(|index| &self.labels[*index])
+    }
+
+    /// The label containing `addr`. Where labels overlap, the one starting closest to `addr` wins.
+    pub fn at(&self, addr: u64) -> Option<&Label> {
+        let end = self.labels.partition_point(|label| label.range.start <= addr);
+        self.labels[..end].iter().rev().find(|label| label.range.contains(&addr))
+    }
+
+    /// Labels that start inside `range`, in address order.
+    pub fn starting_in(&self, range: Range<u64>) -> impl Iterator<Item = &Label> {
+        let start = self.labels.partition_point(|label| label.range.start < range.start);
+        self.labels[start..].iter().take_while(move |label| label.range.start < range.end)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Label> {
+        self.labels.iter()
+    }
+
+    /// A label for every symbol that maps into a view of `len` bytes, each running up to the next
+    /// symbol (or the end of the view), since map files don't record sizes.
+    pub fn from_symbols(symbols: &SymbolTable, len: u64) -> Labels {
+        let mut starts: Vec<(u64, &str)> = symbols
+            .iter()
+            .filter_map(|(name, addr)| symbols.address_map().translate(addr).ok().filter(|offset| *offset < len).map(|offset| (offset, name)))
+            .collect();
+        starts.sort_unstable();
+        let mut labels = Labels::new();
+        for (i, (start, name)) in starts.iter().enumerate() {
+            let end = starts[i + 1..].iter().map(|(next, _)| *next).find(|next| next > start).unwrap_or(len);
+            labels.insert(Label::new(name, *start..end));
+        }
+        labels
+    }
+
+    /// `addr` as `name` or `name+0x4` if it is labelled.
+    pub fn describe(&self, addr: u64) -> Option<String> {
+        let label = self.at(addr)?;
+        Some(match addr - label.range.start {
+            0 => label.name.clone(),
+            offset => format!("{}+{:#x}", label.name, offset),
+        })
+    }
+}
+
+impl Watcher {
+    /// Watch the range of the label called `name`.
+    pub fn watch_label(&mut self, labels: &Labels, name: &str) -> Result<WatchId, ReversedWordsError> {
+        let label = labels
+            .get(name)
+            .ok_or_else(|| ReversedWordsError::UnknownSymbol { name: name.to_string() })?;
+        Ok(self.watch(label.range.clone()))
+    }
+}
+
+/// Hexdump `range` in logical order, 16 bytes per line, with a `; name` header line before each
+/// line a label starts in.
+pub fn hexdump(words: &ReversedWords, range: Range<u64>, labels: Option<&Labels>) -> std::io::Result<String> {
+    let mut out = String::new();
+    let end = range.end.min(words.len());
+    let mut line = [0u8; 16];
+    let mut addr = range.start;
+    while addr < end {
+        let count = ((end - addr) as usize).min(line.len());
+        let n = words.read_at(addr, &mut line[..count])?;
+        if n == 0 {
+            break;
+        }
+        for label in labels.into_iter().flat_map(|labels| labels.starting_in(addr..addr + n as u64)) {
+            match &label.comment {
+                Some(comment) => writeln!(out, "; {} @ {:08x}: {}", label.name, label.range.start, comment),
+                None => writeln!(out, "; {} @ {:08x}", label.name, label.range.start),
+            }
+            .expect("writing to a String");
+        }
+        let hex: Vec<String> = line[..n].iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = line[..n]
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        writeln!(out, "{:08x}  {:<47}  |{}|", addr, hex.join(" "), ascii).expect("writing to a String");
+        addr += n as u64;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::labels::*;
+
+    fn labels() -> Labels {
+        let mut labels = Labels::new();
+        labels.insert(Label::new("gPlayer", 0x10..0x30).with_comment("player struct"));
+        labels.insert(Label::new("gPlayerHealth", 0x14..0x16).with_value_type(ValueType::U16));
+        labels.insert(Label::new("gFrameCount", 0..4));
+        labels
+    }
+
+    #[test]
+    fn lookup_by_address_and_name() {
+        let labels = labels();
+        assert_eq!(Some(0x14..0x16), labels.get("gPlayerHealth").map(|label| label.range.clone()));
+        assert_eq!(Some("gPlayerHealth+0x1".to_string()), labels.describe(0x15));
+        assert_eq!(Some("gPlayer+0x6".to_string()), labels.describe(0x16));
+        assert_eq!(Some("gFrameCount".to_string()), labels.describe(0));
+        assert_eq!(None, labels.describe(0x8));
+
+        let mut labels = labels;
+        labels.insert(Label::new("gFrameCount", 0x40..0x44));
+        assert_eq!(3, labels.len());
+        assert_eq!(None, labels.describe(0));
+        assert!(labels.remove("gPlayer").is_some());
+        assert_eq!(Some("gPlayerHealth".to_string()), labels.describe(0x14));
+    }
+
+    #[test]
+    fn labels_from_a_map_file() {
+        let map = "0x80000010 gPlayer\n0x80000004 gFrameCount\n0x80001000 gOutside\n";
+        let symbols = SymbolTable::from_map_file(map).with_address_map(crate::translate::AddressMap::n64(0x40));
+        let labels = Labels::from_symbols(&symbols, 0x40);
+        assert_eq!(2, labels.len());
+        assert_eq!(Some("gFrameCount+0xb".to_string()), labels.describe(0xF));
+        assert_eq!(Some(0x10..0x40), labels.get("gPlayer").map(|label| label.range.clone()));
+    }
+
+    #[test]
+    fn hexdump_with_label_headers() {
+        let mut data: Vec<u8> = (0..0x20).collect();
+        let words = ReversedWords::new(&mut data);
+        let dump = hexdump(&words, 0x10..0x20, Some(&labels())).unwrap();
+        assert_eq!(
+            "; gPlayer @ 00000010: player struct\n\
+             ; gPlayerHealth @ 00000014\n\
+             00000010  13 12 11 10 17 16 15 14 1b 1a 19 18 1f 1e 1d 1c  |................|\n",
+            dump
+        );
+    }
+
+    #[test]
+    fn watch_label_by_name() {
+        let mut watcher = Watcher::new();
+        let id = watcher.watch_label(&labels(), "gPlayerHealth").unwrap();
+        assert_eq!(Some(0x14..0x16), watcher.range(id));
+        assert!(watcher.watch_label(&labels(), "gMissing").is_err());
+    }
+}
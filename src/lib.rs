@@ -1,36 +1,733 @@
-use std::{io::{Cursor, Read, Seek, SeekFrom, Write}};
-use binread::{BinReaderExt};
+#![cfg_attr(all(feature = "read_buf", nightly), feature(read_buf, core_io_borrowed_buf))]
 
+use std::{
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+pub use binread::Endian;
+
+mod typed;
+mod vector;
+pub use typed::{Pod, Primitive, SwappedLayout};
+pub use vector::MatrixOrder;
+
+mod error;
+pub use error::{ErrorContext, ReversedWordsError};
+
+mod atomic;
+mod bits;
+mod bulk;
+mod convert;
+mod edit;
+mod entropy;
+mod fixed;
+mod frozen;
+mod hex;
+mod odd_width;
+mod owned;
+mod region_file;
+mod reverse;
+mod search;
+mod shared;
+mod span;
+mod word;
+pub use atomic::{AtomicReversedWords, ReadHalf, SeqLockWords, WriteHalf};
+pub use bulk::transfer;
+pub use edit::ScopedEdit;
+pub use frozen::{FrozenCursor, FrozenLayout, FrozenView};
+pub use hex::{format_hex, parse_hex};
+pub use owned::{PartialWordPolicy, ReversedVec};
+pub use region_file::ByteOrder;
+pub use reverse::{LogicalBytes, LogicalValues};
+pub use search::{Encoding, Strings};
+pub use shared::{ReversedWordsRef, WordCursor};
+pub use span::{RawSpan, RawSpanMut};
+pub use word::{Word, WordGuard, WordStream};
+
+pub mod backend;
+pub mod bizhawk;
+pub mod block;
+pub mod checksum;
+pub mod detect;
+pub mod dirty;
+pub mod dolphin;
+pub mod events;
+pub mod export;
+pub mod expr;
+pub mod fixture;
+pub mod heatmap;
+pub mod hexfile;
+pub mod history;
+pub mod journal;
+pub mod labels;
+pub mod libretro;
+pub mod memory_map;
+pub mod mempak;
+pub mod mock;
+pub mod n64save;
+pub mod platform;
+pub mod pointer;
+pub mod poll;
+pub mod profile;
+pub mod recording;
+pub mod savestate;
+pub mod scan;
+pub mod scatter;
+pub mod sparse;
+pub mod symbols;
+pub mod symfile;
+pub mod texel;
+pub mod trace;
+pub mod transform;
+pub mod translate;
+pub mod trigger;
+pub mod vcdiff;
+pub mod watch;
+
+#[cfg(feature = "serde")]
+pub mod overlay;
+
+#[cfg(feature = "dwarf")]
+pub mod dwarf;
+
+#[cfg(feature = "scripting")]
+pub mod script;
+
+#[cfg(feature = "mupen64plus")]
+pub mod mupen64plus;
+
+#[cfg(feature = "positioned")]
+mod positioned;
+
+#[cfg(feature = "zeroize")]
+mod erase;
+
+#[cfg(feature = "tokio")]
+pub mod stream;
+
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "server")]
+pub mod websocket;
+
+#[cfg(feature = "regex")]
+pub mod regex;
+
+#[cfg(feature = "disasm")]
+pub mod disasm;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "python")]
+mod python;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "derive")]
+pub use reversed_word_byte_rw_derive::SwappedLayout;
+
+/// Storage offset of the byte at logical `position`, ignoring bounds.
+///
+/// Reversing a word maps offset `i` within it to `word_size - 1 - i`. For power of two word
+/// sizes that's the same as flipping the low bits, so the whole mapping is an XOR with
+/// `word_size - 1` and needs no division.
+#[inline]
+pub(crate) fn swap_index(position: u64, word_size: u8) -> u64 {
+    let word_size = word_size as u64;
+    if word_size.is_power_of_two() {
+        position ^ (word_size - 1)
+    } else {
+        let position_within_word = position % word_size;
+        position - position_within_word + (word_size - 1 - position_within_word)
+    }
+}
+
+// each byte with its bits in the opposite order
+const BIT_REVERSED: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = (i as u8).reverse_bits();
+        i += 1;
+    }
+    table
+};
+
+/// Apply a view's nibble swap and bit reversal flags to `byte`, in either direction.
+#[inline]
+pub(crate) fn transform_byte(byte: u8, (nibble_swap, bit_reversal): (bool, bool)) -> u8 {
+    let byte = if bit_reversal { BIT_REVERSED[byte as usize] } else { byte };
+    if nibble_swap {
+        byte.rotate_left(4)
+    } else {
+        byte
+    }
+}
+
+/// Index into the underlying storage of the byte at logical `position`, if it is in range.
+#[inline]
+pub(crate) fn storage_index(position: u64, word_size: u8, len: u64) -> Option<usize> {
+    storage_offset(position, word_size, len).map(|index| index as usize)
+}
+
+/// [`storage_index`] for storage that may not fit in memory, such as a file or another process.
+pub(crate) fn storage_offset(position: u64, word_size: u8, len: u64) -> Option<u64> {
+    let index = swap_index(position, word_size);
+    if position < len && index < len {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+/// What to do when a read or write starts partway through a word.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MisalignmentPolicy {
+    #[default]
+    /// Back the cursor up to the start of the word internally and skip the leading bytes.
+    /// The bytes read or written are still the ones at the logical position. This is the default.
+    Realign,
+    /// Fail the read or write with `ErrorKind::InvalidInput` without touching any data.
+    Reject,
+}
+
+/// How many reads and writes started partway through a word and were realigned under
+/// [`MisalignmentPolicy::Realign`], see [`ReversedWords::misalignment_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MisalignmentStats {
+    pub reads: u64,
+    pub writes: u64,
+    /// The logical address of the most recent one.
+    pub last_addr: Option<u64>,
+}
+
+// counters behind `&self`, since the addressed reads don't take `&mut self`
+#[derive(Debug, Default)]
+struct MisalignmentCounters {
+    reads: AtomicU64,
+    writes: AtomicU64,
+    // `u64::MAX` until the first one
+    last_addr: AtomicU64,
+}
+
+impl MisalignmentCounters {
+    fn new() -> MisalignmentCounters {
+        MisalignmentCounters { last_addr: AtomicU64::new(u64::MAX), ..MisalignmentCounters::default() }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+}
+
+/// What to do when a seek targets a position before the start or past the end of the data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SeekBoundsPolicy {
+    #[default]
+    /// Same as `std::io::Cursor`: seeking before the start is an error, seeking past the end is
+    /// allowed and subsequent reads return 0 bytes. This is the default.
+    Unbounded,
+    /// Clamp the target position to `[0, len]`.
+    Clamp,
+    /// Fail with `ErrorKind::InvalidInput` if the target position is outside `[0, len]`,
+    /// leaving the position unchanged.
+    Reject,
+}
+
+/// How the addressed accessors (`read_at`, `get_byte`, the typed `*_at` accessors, ...) treat
+/// addresses past the end of the data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressMode {
+    #[default]
+    /// Addresses past the end are out of range. This is the default.
+    Bounded,
+    /// Addresses wrap modulo the length, like hardware that mirrors the whole space, so guest
+    /// pointers can be used without masking them first. Seeking and `Read`/`Write` are unaffected.
+    Wrapping,
+}
+
+/// How a view maps logical bytes to the bytes in storage: the word swap, the nibble swap and bit
+/// reversal, the address mode and the misalignment policy. Every swapped view goes through this
+/// whatever holds its bytes, so a borrowed slice and a [`backend::MemoryBackend`] agree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub(crate) word_size: u8,
+    pub(crate) nibble_swap: bool,
+    pub(crate) bit_reversal: bool,
+    pub(crate) address_mode: AddressMode,
+    pub(crate) misalignment_policy: MisalignmentPolicy,
+}
+
+impl Layout {
+    pub(crate) fn new(word_size: u8) -> Layout {
+        Layout {
+            word_size,
+            nibble_swap: false,
+            bit_reversal: false,
+            address_mode: AddressMode::default(),
+            misalignment_policy: MisalignmentPolicy::default(),
+        }
+    }
+
+    /// Whether storage order is logical order, so accesses can copy bytes directly.
+    pub(crate) fn is_identity(&self) -> bool {
+        self.word_size == 1 && !self.nibble_swap && !self.bit_reversal
+    }
+
+    pub(crate) fn storage_offset(&self, position: u64, len: u64) -> Option<u64> {
+        storage_offset(position, self.word_size, len)
+    }
+
+    /// The address `offset` bytes after `addr` in `len` bytes of data, wrapped if the address
+    /// mode says so.
+    pub(crate) fn offset_address(&self, addr: u64, offset: u64, len: u64) -> u64 {
+        match self.address_mode {
+            AddressMode::Wrapping if len > 0 => (addr % len + offset) % len,
+            _ => addr.saturating_add(offset),
+        }
+    }
+
+    // swapping nibbles and reversing bits are their own inverses and commute, so the same
+    // transform applies both ways
+    pub(crate) fn transform(&self, byte: u8) -> u8 {
+        transform_byte(byte, (self.nibble_swap, self.bit_reversal))
+    }
+
+    /// Whether an access at `addr` starts partway through a word, failing it under
+    /// [`MisalignmentPolicy::Reject`].
+    pub(crate) fn check_alignment(&self, addr: u64) -> std::io::Result<bool> {
+        if addr.is_multiple_of(self.word_size as u64) {
+            return Ok(false);
+        }
+        if self.misalignment_policy == MisalignmentPolicy::Reject {
+            return Err(ReversedWordsError::Misaligned { position: addr, word_size: self.word_size }.into());
+        }
+        Ok(true)
+    }
+
+    /// Copy the logical bytes at `addr` in `len` bytes of data out of `storage`, which holds the
+    /// storage bytes from offset `base` on. Stops at the first byte out of range and returns how
+    /// many were copied.
+    pub(crate) fn load(&self, storage: &[u8], base: u64, len: u64, addr: u64, buf: &mut [u8]) -> usize {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            match self.storage_offset(self.offset_address(addr, i as u64, len), len) {
+                Some(index) => *byte = self.transform(storage[(index - base) as usize]),
+                None => return i,
+            }
+        }
+        buf.len()
+    }
+
+    /// The inverse of [`Layout::load`].
+    pub(crate) fn store(&self, storage: &mut [u8], base: u64, len: u64, addr: u64, buf: &[u8]) -> usize {
+        for (i, byte) in buf.iter().enumerate() {
+            match self.storage_offset(self.offset_address(addr, i as u64, len), len) {
+                Some(index) => storage[(index - base) as usize] = self.transform(*byte),
+                None => return i,
+            }
+        }
+        buf.len()
+    }
+}
+
+/// `Read + Write + Seek` over data stored as opposite endian words.
+///
+/// Reads and writes may start at any logical position: by default a misaligned access is
+/// handled by internally rewinding to the start of the containing word, see
+/// [`MisalignmentPolicy`] to make misaligned accesses an error instead, or
+/// [`ReversedWords::misalignment_stats`] to find out how often it happens.
+///
+/// That makes the view a valid stream for `binread`: a `BinRead` struct parses the same as from
+/// its logical bytes for every word size, with fields starting partway through words and spanning
+/// word boundaries. A parse leaves the position just past the last byte it read, and a derived
+/// struct that fails to parse seeks back to where it started, as it would in a `Cursor`. Under
+/// [`MisalignmentPolicy::Reject`] only parsers that read in whole words succeed.
 pub struct ReversedWords<'a> {
     cursor: Cursor<&'a mut [u8]>,
     word_size: u8,
     len: u64,
+    misalignment_policy: MisalignmentPolicy,
+    seek_bounds_policy: SeekBoundsPolicy,
+    nibble_swap: bool,
+    bit_reversal: bool,
+    address_mode: AddressMode,
+    endian: Endian,
+    misalignments: MisalignmentCounters,
 }
 
 impl<'a> ReversedWords<'a> {
-    pub fn new(ram: &'a mut [u8]) -> ReversedWords {
+    pub fn new(ram: &'a mut [u8]) -> ReversedWords<'a> {
         let len: u64 = ram.len() as u64;
         ReversedWords {
             cursor: Cursor::new(ram),
             word_size: 4, // read u32 words at a time
             len,
+            misalignment_policy: MisalignmentPolicy::default(),
+            seek_bounds_policy: SeekBoundsPolicy::default(),
+            nibble_swap: false,
+            bit_reversal: false,
+            address_mode: AddressMode::default(),
+            endian: Endian::Big,
+            misalignments: MisalignmentCounters::new(),
+        }
+    }
+
+    /// Panics if `word_size` is 0, see [`ReversedWords::try_new_with_word_size`].
+    pub fn new_with_word_size(ram: &'a mut [u8], word_size: u8) -> ReversedWords<'a> {
+        match ReversedWords::try_new_with_word_size(ram, word_size) {
+            Ok(words) => words,
+            Err(e) => panic!("{}", e),
         }
     }
 
-    pub fn new_with_word_size(ram: &'a mut [u8], word_size: u8) -> ReversedWords {
+    pub fn try_new_with_word_size(ram: &'a mut [u8], word_size: u8) -> Result<ReversedWords<'a>, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
         let len: u64 = ram.len() as u64;
-        ReversedWords {
+        Ok(ReversedWords {
             cursor: Cursor::new(ram),
             word_size,
             len,
+            misalignment_policy: MisalignmentPolicy::default(),
+            seek_bounds_policy: SeekBoundsPolicy::default(),
+            nibble_swap: false,
+            bit_reversal: false,
+            address_mode: AddressMode::default(),
+            endian: Endian::Big,
+            misalignments: MisalignmentCounters::new(),
+        })
+    }
+
+    pub fn with_misalignment_policy(mut self, policy: MisalignmentPolicy) -> ReversedWords<'a> {
+        self.misalignment_policy = policy;
+        self
+    }
+
+    pub fn with_seek_bounds_policy(mut self, policy: SeekBoundsPolicy) -> ReversedWords<'a> {
+        self.seek_bounds_policy = policy;
+        self
+    }
+
+    pub fn with_address_mode(mut self, mode: AddressMode) -> ReversedWords<'a> {
+        self.address_mode = mode;
+        self
+    }
+
+    /// Also swap the high and low nibble of every byte, for dumps that store them reversed.
+    /// Applies on top of the word swap to everything except raw spans, which stay in storage order.
+    pub fn with_nibble_swap(mut self, nibble_swap: bool) -> ReversedWords<'a> {
+        self.nibble_swap = nibble_swap;
+        self
+    }
+
+    /// Also reverse the order of the bits in every byte, for EPROM and flash dumps captured with
+    /// the data lines the wrong way round. Composes with the word and nibble swaps the same way.
+    pub fn with_bit_reversal(mut self, bit_reversal: bool) -> ReversedWords<'a> {
+        self.bit_reversal = bit_reversal;
+        self
+    }
+
+    /// The byte order of the target's values, used by the typed accessors that don't take one
+    /// (`read_u32`, `read_value`, ...). Defaults to big endian; the `*_at` accessors still take
+    /// an explicit endianness for the odd value stored the other way.
+    pub fn with_endian(mut self, endian: Endian) -> ReversedWords<'a> {
+        self.endian = endian;
+        self
+    }
+
+    /// Reinterpret the same buffer with a different word size, keeping the logical position.
+    ///
+    /// With [`MisalignmentPolicy::Reject`] the current position must be aligned to the new word
+    /// size, otherwise nothing changes and [`ReversedWordsError::Misaligned`] is returned.
+    pub fn set_word_size(&mut self, word_size: u8) -> Result<(), ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        let position = self.cursor.position();
+        if self.misalignment_policy == MisalignmentPolicy::Reject && !position.is_multiple_of(word_size as u64) {
+            return Err(ReversedWordsError::Misaligned { position, word_size });
+        }
+        self.word_size = word_size;
+        Ok(())
+    }
+
+    /// Turn the nibble swap on or off, see [`ReversedWords::with_nibble_swap`].
+    pub fn set_nibble_swap(&mut self, nibble_swap: bool) {
+        self.nibble_swap = nibble_swap;
+    }
+
+    pub fn set_bit_reversal(&mut self, bit_reversal: bool) {
+        self.bit_reversal = bit_reversal;
+    }
+
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// How many accesses so far started partway through a word and were realigned. With the
+    /// `log` feature each one is also logged as a warning with its address.
+    pub fn misalignment_stats(&self) -> MisalignmentStats {
+        let last_addr = self.misalignments.last_addr.load(Ordering::Relaxed);
+        MisalignmentStats {
+            reads: self.misalignments.reads.load(Ordering::Relaxed),
+            writes: self.misalignments.writes.load(Ordering::Relaxed),
+            last_addr: if last_addr == u64::MAX { None } else { Some(last_addr) },
         }
     }
 
+    pub fn reset_misalignment_stats(&self) {
+        self.misalignments.reads.store(0, Ordering::Relaxed);
+        self.misalignments.writes.store(0, Ordering::Relaxed);
+        self.misalignments.last_addr.store(u64::MAX, Ordering::Relaxed);
+    }
+
+    /// Length of the data in bytes, the equivalent of `Seek::stream_len` without seeking.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Current logical position, the equivalent of `Seek::stream_position` without `&mut self`.
+    pub fn position(&self) -> u64 {
+        self.cursor.position()
+    }
+
+    /// Number of bytes between the current position and the end, 0 if positioned past the end.
+    pub fn remaining(&self) -> u64 {
+        self.len.saturating_sub(self.cursor.position())
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    pub fn nibble_swap(&self) -> bool {
+        self.nibble_swap
+    }
+
+    pub fn bit_reversal(&self) -> bool {
+        self.bit_reversal
+    }
+
+    /// Seek to the start of word `n`.
+    pub fn seek_to_word(&mut self, n: u64) -> std::io::Result<u64> {
+        let position = n.checked_mul(self.word_size as u64).ok_or(ReversedWordsError::SeekOutOfRange {
+            target: n as i128 * self.word_size as i128,
+            len: self.len,
+        })?;
+        self.seek(SeekFrom::Start(position))
+    }
+
+    /// Seek back to the start of the word containing the current position.
+    pub fn align_down(&mut self) -> std::io::Result<u64> {
+        let misalignment = self.cursor.position() % self.word_size as u64;
+        self.seek(SeekFrom::Current(-(misalignment as i64)))
+    }
+
+    /// Seek forward to the next word boundary, or stay put if already on one.
+    pub fn align_up(&mut self) -> std::io::Result<u64> {
+        let misalignment = self.cursor.position() % self.word_size as u64;
+        if misalignment == 0 {
+            return Ok(self.cursor.position());
+        }
+        self.seek(SeekFrom::Current((self.word_size as u64 - misalignment) as i64))
+    }
+
+    /// Read from the current position like `read`, without moving the cursor.
+    pub fn peek(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let position = self.cursor.position();
+        let result = self.read(buf);
+        self.cursor.set_position(position);
+        result
+    }
+
+    /// Read from `addr` like `read`, without moving the cursor.
+    pub fn peek_at(&mut self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let position = self.cursor.position();
+        let result = self.seek(SeekFrom::Start(addr)).and_then(|_| self.read(buf));
+        self.cursor.set_position(position);
+        result
+    }
+
+    /// Read from the current position into possibly uninitialized memory, so large buffers don't
+    /// need zero filling first. Returns the initialized prefix of `buf`, which is shorter than
+    /// `buf` if the end of the data is reached.
+    pub fn read_uninit<'b>(&mut self, buf: &'b mut [MaybeUninit<u8>]) -> std::io::Result<&'b mut [u8]> {
+        let position = self.cursor.position();
+        self.check_alignment(position, Access::Read)?;
+        let mut n = 0;
+        for (i, byte) in buf.iter_mut().enumerate() {
+            match self.storage_index(position + i as u64) {
+                Some(index) => byte.write(self.load(index)),
+                None => break,
+            };
+            n = i + 1;
+        }
+        self.cursor.set_position(position + n as u64);
+        let filled = &mut buf[..n];
+        // SAFETY: the first `n` bytes were all written above
+        Ok(unsafe { &mut *(filled as *mut [MaybeUninit<u8>] as *mut [u8]) })
+    }
+
+    /// Read the logical bytes starting at `addr` without using or moving the cursor.
+    ///
+    /// Returns the number of bytes read, which is less than `buf.len()` if the end of the data is reached.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.is_identity() && self.address_mode == AddressMode::Bounded {
+            let range = self.identity_range(addr, buf.len());
+            buf[..range.len()].copy_from_slice(&self.cursor.get_ref()[range.clone()]);
+            return Ok(range.len());
+        }
+        self.check_alignment(addr, Access::Read)?;
+        Ok(self.layout().load(self.cursor.get_ref(), 0, self.len, addr, buf))
+    }
+
+    /// Write logical bytes starting at `addr` without using or moving the cursor.
+    ///
+    /// Returns the number of bytes written, which is less than `buf.len()` if the end of the data is reached.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        if self.is_identity() && self.address_mode == AddressMode::Bounded {
+            let range = self.identity_range(addr, buf.len());
+            let n = range.len();
+            self.cursor.get_mut()[range].copy_from_slice(&buf[..n]);
+            return Ok(n);
+        }
+        self.check_alignment(addr, Access::Write)?;
+        let (layout, len) = (self.layout(), self.len);
+        Ok(layout.store(self.cursor.get_mut(), 0, len, addr, buf))
+    }
+
+    /// The logical byte at `addr`.
+    pub fn get_byte(&self, addr: u64) -> std::io::Result<u8> {
+        match self.storage_index(self.offset_address(addr, 0)) {
+            Some(index) => Ok(self.load(index)),
+            None => Err(self.out_of_range(addr)),
+        }
+    }
+
+    /// Set the logical byte at `addr`.
+    pub fn set_byte(&mut self, addr: u64, byte: u8) -> std::io::Result<()> {
+        match self.storage_index(self.offset_address(addr, 0)) {
+            Some(index) => {
+                self.store(index, byte);
+                Ok(())
+            }
+            None => Err(self.out_of_range(addr)),
+        }
+    }
+
+    pub(crate) fn out_of_range(&self, addr: u64) -> std::io::Error {
+        ReversedWordsError::AddressOutOfRange { addr, len: self.len }.into()
+    }
+
+    /// The address `offset` bytes after `addr`, wrapped if the address mode says so.
+    pub(crate) fn offset_address(&self, addr: u64, offset: u64) -> u64 {
+        self.layout().offset_address(addr, offset, self.len)
+    }
+
+    pub fn address_mode(&self) -> AddressMode {
+        self.address_mode
+    }
+
+    /// The mapping from logical to storage bytes this view's settings make up.
+    pub(crate) fn layout(&self) -> Layout {
+        Layout {
+            word_size: self.word_size,
+            nibble_swap: self.nibble_swap,
+            bit_reversal: self.bit_reversal,
+            address_mode: self.address_mode,
+            misalignment_policy: self.misalignment_policy,
+        }
+    }
+
+    /// Whether storage order is logical order, so accesses can copy slices directly.
+    fn is_identity(&self) -> bool {
+        self.layout().is_identity()
+    }
+
+    // the storage range of up to `count` bytes from `addr` when nothing is swapped
+    fn identity_range(&self, addr: u64, count: usize) -> std::ops::Range<usize> {
+        let start = addr.min(self.len) as usize;
+        start..start + count.min(self.len as usize - start)
+    }
+
+    pub(crate) fn storage_index(&self, position: u64) -> Option<usize> {
+        self.layout().storage_offset(position, self.len).map(|index| index as usize)
+    }
+
+    pub(crate) fn byte_transform(&self) -> (bool, bool) {
+        (self.nibble_swap, self.bit_reversal)
+    }
+
+    fn transform(&self, byte: u8) -> u8 {
+        self.layout().transform(byte)
+    }
+
+    /// The logical value of the byte at storage `index`.
+    pub(crate) fn load(&self, index: usize) -> u8 {
+        self.transform(self.cursor.get_ref()[index])
+    }
+
+    /// Store logical `byte` at storage `index`.
+    pub(crate) fn store(&mut self, index: usize, byte: u8) {
+        let byte = self.transform(byte);
+        self.cursor.get_mut()[index] = byte;
+    }
+
+    /// Fail a misaligned `access` at `addr` under [`MisalignmentPolicy::Reject`], count it otherwise.
+    pub(crate) fn check_alignment(&self, addr: u64, access: Access) -> std::io::Result<()> {
+        if !self.layout().check_alignment(addr)? {
+            return Ok(());
+        }
+        let counter = match access {
+            Access::Read => &self.misalignments.reads,
+            Access::Write => &self.misalignments.writes,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.misalignments.last_addr.store(addr, Ordering::Relaxed);
+        #[cfg(feature = "log")]
+        log::warn!("misaligned {:?} at {:#x} realigned to the {} byte word", access, addr, self.word_size);
+        Ok(())
+    }
+
+    /// How far into a word the cursor currently is, or an error if that isn't allowed.
+    fn misalignment(&self, access: Access) -> std::io::Result<usize> {
+        self.check_alignment(self.cursor.position(), access)?;
+        Ok(self.cursor.position() as usize % (self.word_size as usize))
+    }
 }
 
 impl Seek for ReversedWords<'_> {
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
-        self.cursor.seek(pos)
+        let target: i128 = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.len as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.cursor.position() as i128 + offset as i128,
+        };
+        let out_of_bounds = || std::io::Error::from(ReversedWordsError::SeekOutOfRange { target, len: self.len });
+        let position = match self.seek_bounds_policy {
+            SeekBoundsPolicy::Unbounded if target < 0 || target > u64::MAX as i128 => return Err(out_of_bounds()),
+            SeekBoundsPolicy::Unbounded => target as u64,
+            SeekBoundsPolicy::Clamp => target.clamp(0, self.len as i128) as u64,
+            SeekBoundsPolicy::Reject if target < 0 || target > self.len as i128 => return Err(out_of_bounds()),
+            SeekBoundsPolicy::Reject => target as u64,
+        };
+        self.cursor.set_position(position);
+        Ok(position)
     }
 
     fn stream_position(&mut self) -> std::io::Result<u64> {
@@ -39,37 +736,30 @@ impl Seek for ReversedWords<'_> {
 }
 
 impl Write for ReversedWords<'_> {
+    /// Writes as much of `buf` as fits. A write running past the end stops at the last byte
+    /// that can be stored, even partway through a word, and returns the shorter count; at the
+    /// end it returns `Ok(0)`, so `write_all` fails with `WriteZero`. Bytes of a trailing
+    /// partial word are never reachable and end the write the same way.
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let mut misalignment = self.cursor.position() as usize % (self.word_size as usize);
-        if misalignment > 0 {
-            // back up by the amount of the misalignment
-            self.seek(SeekFrom::Current(misalignment as i64 * -1))?;
-        }
-
-        let mut writes: Vec<(usize, &u8)> = buf
-            .iter()
-            .enumerate() // Add index
-            .map(|(index, byte)| { // Write a word's bytes in reverse order, use cursor position to determine where we are within a word
-                let word_num = (index + misalignment) / (self.word_size as usize);
-                let word_start_index = word_num * self.word_size as usize;
-                let position_within_unflipped_word = ((index + misalignment) % self.word_size as usize) as usize;
-                let position_within_flipped_word = self.word_size as usize - 1 - position_within_unflipped_word;
-                (word_start_index + position_within_flipped_word, byte)
-            }).collect();
-
-        // sort so the smallest target indices are first.
-        writes.sort_by(|(a_index, _), (b_index, _)| a_index.cmp(b_index));
-
         let start_position = self.cursor.position();
+        if self.is_identity() {
+            let range = self.identity_range(start_position, buf.len());
+            let n = range.len();
+            self.cursor.get_mut()[range].copy_from_slice(&buf[..n]);
+            self.cursor.set_position(start_position + n as u64);
+            return Ok(n);
+        }
+        self.misalignment(Access::Write)?;
         let mut num_bytes_written = 0;
-        for (write_index, write_data) in writes {
-            let target_position = write_index as u64 + start_position;
-            // If the target position is not the current position + 1, move forward
-            if self.cursor.position() < target_position {
-                self.cursor.seek(SeekFrom::Current((target_position - self.cursor.position()) as i64))?;
+        for byte in buf {
+            match start_position.checked_add(num_bytes_written as u64).and_then(|position| self.storage_index(position)) {
+                Some(index) => self.store(index, *byte),
+                None => break,
             }
-            num_bytes_written += self.cursor.write(&[*write_data])?;
+            num_bytes_written += 1;
         }
+        // leave the cursor just past the last logical byte written
+        self.cursor.set_position(start_position + num_bytes_written as u64);
         Ok(num_bytes_written)
     }
 
@@ -79,43 +769,75 @@ impl Write for ReversedWords<'_> {
 }
 
 impl Read for ReversedWords<'_> {
+    /// Reads as much of `buf` as is left before the end, returning the shorter count. Bytes of a
+    /// trailing partial word are never reachable, so a read stops before them.
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let start_position = self.cursor.position();
+        if self.is_identity() {
+            let range = self.identity_range(start_position, buf.len());
+            let n = range.len();
+            buf[..n].copy_from_slice(&self.cursor.get_ref()[range]);
+            self.cursor.set_position(start_position + n as u64);
+            return Ok(n);
+        }
+        let num_bytes_read = self.read_whole_words(buf)?;
+        // whole words are consumed, leave the cursor just past the last logical byte read
+        self.cursor.set_position(start_position + num_bytes_read as u64);
+        Ok(num_bytes_read)
+    }
+    /// Reserves the remaining length up front and reads it in one pass, instead of the default
+    /// of growing `buf` a chunk at a time.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> std::io::Result<usize> {
+        let remaining = self.remaining() as usize;
+        buf.reserve_exact(remaining);
+        let n = self.read_uninit(&mut buf.spare_capacity_mut()[..remaining])?.len();
+        // SAFETY: read_uninit initialized the first `n` bytes of the spare capacity
+        unsafe { buf.set_len(buf.len() + n) };
+        Ok(n)
+    }
+
+    #[cfg(all(feature = "read_buf", nightly))]
+    // Some nightlies have made `BorrowedCursor::advance` safe.
+    #[allow(unused_unsafe)]
+    fn read_buf(&mut self, mut cursor: std::io::BorrowedCursor<'_>) -> std::io::Result<()> {
+        // SAFETY: read_uninit only writes to the buffer, never uninitializes it
+        let n = self.read_uninit(unsafe { cursor.as_mut() })?.len();
+        // SAFETY: read_uninit initialized the first `n` bytes
+        unsafe { cursor.advance(n) };
+        Ok(())
+    }
+}
+
+impl ReversedWords<'_> {
+    fn read_whole_words(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         // test alignment
-        let mut misalignment = self.cursor.position() as usize % (self.word_size as usize);
+        let mut misalignment = self.misalignment(Access::Read)?;
         if misalignment > 0 {
             // back up by the amount of the misalignment
-            self.seek(SeekFrom::Current(misalignment as i64 * -1))?;
+            self.seek(SeekFrom::Current(-(misalignment as i64)))?;
         }
         let mut write_index = 0;
+        // word sizes are at most 255 bytes, so the word fits on the stack
+        let mut word_buffer = [0u8; u8::MAX as usize];
+        let word = &mut word_buffer[..self.word_size as usize];
         loop {
-            // Stop reading if we are at the end of the slice, or if the read buffer is full.
-            if self.cursor.position() >= self.len || write_index >= buf.len() {
+            // Stop reading at the last whole word, or if the read buffer is full.
+            if self.cursor.position() >= self.reachable_len() || write_index >= buf.len() {
                 return Ok(write_index);
             }
 
-            match self.cursor.read_be::<u32>(){
-                Ok(word) => {
-                    let word = word.to_le_bytes();
-
-                    for i in misalignment..word.len() {
-                        if write_index >= buf.len() { // Exit if we would be writing past the end of the read buffer.
-                            return Ok(write_index);
-                        }
-                        buf[write_index] = word[i];
-
-                        if misalignment > 0 {
-                            misalignment -= 1;
-                        }
-
-                        write_index += 1;
-                    }
-                },
-                Err(e) => match e {
-                    binread::Error::Io(e) => {return Err(e);}, // io errors pass through
-                    e => {panic!("unexpected binrw error: {:?}", e)} // not expecting to hit any of these since we are simply reading a u32
+            self.cursor.read_exact(word)?;
+            word.reverse();
+
+            for byte in &word[misalignment..] {
+                if write_index >= buf.len() { // Exit if we would be writing past the end of the read buffer.
+                    return Ok(write_index);
                 }
+                buf[write_index] = self.transform(*byte);
+                write_index += 1;
             }
-
+            // only the first word read can be misaligned
+            misalignment = 0;
         }
     }
 }
@@ -124,6 +846,97 @@ impl Read for ReversedWords<'_> {
 mod tests {
     use crate::*;
 
+    mod binread_structs {
+        use binread::{io::Cursor, BinRead, BinReaderExt};
+
+        use crate::*;
+
+        #[derive(BinRead, Debug, PartialEq)]
+        struct Inner {
+            flags: u8,
+            #[br(little)]
+            scale: f32,
+        }
+
+        #[derive(BinRead, Debug, PartialEq)]
+        #[br(big)]
+        struct Record {
+            tag: [u8; 3],
+            id: u16,
+            counter: u32,
+            #[br(pad_before = 1)]
+            timestamp: u64,
+            inner: Inner,
+            len: u8,
+            #[br(count = len)]
+            payload: Vec<u8>,
+            #[br(little)]
+            delta: i32,
+        }
+
+        // storage for logical `bytes`, reversing each word by hand
+        fn stored(logical: &[u8], word_size: usize) -> Vec<u8> {
+            let mut storage = vec![0u8; logical.len()];
+            for (word, chunk) in logical.chunks(word_size).enumerate() {
+                for (i, byte) in chunk.iter().enumerate() {
+                    storage[word * word_size + word_size - 1 - i] = *byte;
+                }
+            }
+            storage
+        }
+
+        #[test]
+        fn parses_structs_at_any_offset_and_word_size() {
+            let record: Vec<u8> = (0..40).map(|i| (i * 37 + 11) as u8).collect();
+            let mut record = record;
+            record[23] = 5; // `len`
+            let expected: Record = Cursor::new(&record[..]).read_be().unwrap();
+            assert_eq!(5, expected.payload.len());
+            let used: usize = 3 + 2 + 4 + 1 + 8 + 1 + 4 + 1 + 5 + 4;
+
+            for word_size in [1usize, 2, 3, 4, 5, 8, 16] {
+                for offset in 0..2 * word_size {
+                    // pad to whole words so the struct can end at the very end of the view
+                    let len = (offset + used).div_ceil(word_size) * word_size;
+                    let mut logical = vec![0xEEu8; len];
+                    logical[offset..offset + used].copy_from_slice(&record[..used]);
+                    let mut storage = stored(&logical, word_size);
+                    // a trailing partial word the struct stops short of
+                    storage.extend(std::iter::repeat_n(0xAA, word_size - 1));
+                    let mut ram = ReversedWords::new_with_word_size(&mut storage, word_size as u8);
+                    ram.seek(SeekFrom::Start(offset as u64)).unwrap();
+                    let parsed: Record = ram.read_be().unwrap();
+                    assert_eq!(expected, parsed, "word size {word_size} offset {offset}");
+                    assert_eq!((offset + used) as u64, ram.stream_position().unwrap());
+                }
+            }
+        }
+
+        #[test]
+        fn failed_parses_restore_the_position() {
+            #[derive(BinRead, Debug)]
+            #[br(big, magic = b"OK")]
+            struct Tagged {
+                _value: u32,
+            }
+
+            #[derive(BinRead, Debug)]
+            struct Pair {
+                _first: u32,
+                _second: u32,
+            }
+
+            let mut storage = stored(b"xxNO\x01\x02\x03\x04", 4);
+            let mut ram = ReversedWords::new(&mut storage);
+            ram.seek(SeekFrom::Start(2)).unwrap();
+            assert!(ram.read_be::<Tagged>().is_err());
+            assert_eq!(2, ram.stream_position().unwrap());
+            // running off the end is an error too, from the same place
+            assert!(ram.read_be::<Pair>().is_err());
+            assert_eq!(2, ram.stream_position().unwrap());
+        }
+    }
+
     #[test]
     fn read_simple_sequential() {
         let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
@@ -146,6 +959,31 @@ mod tests {
     }
 
     #[test]
+    fn read_other_word_sizes() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let mut out = vec![0u8; 8];
+        ReversedWords::new_with_word_size(&mut data, 2).read_exact(&mut out).unwrap();
+        assert_eq!(vec![1, 0, 3, 2, 5, 4, 7, 6], out);
+        ReversedWords::new_with_word_size(&mut data, 1).read_exact(&mut out).unwrap();
+        assert_eq!(vec![0, 1, 2, 3, 4, 5, 6, 7], out);
+    }
+
+    #[test]
+    fn read_small_sequential_reads_advance_logically() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let mut ram = ReversedWords::new(&mut data);
+        let mut out = [0u8; 1];
+        ram.seek(SeekFrom::Start(1)).unwrap();
+        ram.read_exact(&mut out).unwrap();
+        assert_eq!([2], out);
+        assert_eq!(2, ram.stream_position().unwrap());
+        ram.read_exact(&mut out).unwrap();
+        assert_eq!([1], out);
+        assert_eq!(3, ram.stream_position().unwrap());
+    }
+
+    #[test]
+    #[allow(clippy::unnecessary_mut_passed)]
     fn write_simple_sequential() {
         let mut source: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
         let mut target = vec![0u8; 8];
@@ -188,6 +1026,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unused_io_amount)]
     fn read_and_write_unaligned_blocks() {
         let mut target = vec![0u8; 128];
         let target_len = target.len();
@@ -218,9 +1057,332 @@ mod tests {
         ram.read_to_end(&mut read_buffer).unwrap();
         assert_eq!(expected_result, read_buffer);
     }
+    #[test]
+    fn reject_misaligned_read_and_write() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let mut ram = ReversedWords::new(&mut data).with_misalignment_policy(MisalignmentPolicy::Reject);
+        let mut out = [0u8; 2];
+        ram.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidInput, ram.read(&mut out).unwrap_err().kind());
+        assert_eq!(std::io::ErrorKind::InvalidInput, ram.write(&[9, 9]).unwrap_err().kind());
+        // nothing moved or changed
+        assert_eq!(2, ram.stream_position().unwrap());
+        ram.seek(SeekFrom::Start(4)).unwrap();
+        ram.read_exact(&mut out).unwrap();
+        assert_eq!([7, 6], out);
+        assert_eq!(vec![0, 1, 2, 3, 4, 5, 6, 7], data);
+    }
+
+    #[test]
+    fn seek_bounds_unbounded() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data);
+        assert_eq!(20, ram.seek(SeekFrom::Start(20)).unwrap());
+        assert_eq!(0, ram.read(&mut [0u8; 4]).unwrap());
+        assert!(ram.seek(SeekFrom::End(-9)).is_err());
+        assert_eq!(20, ram.stream_position().unwrap());
+    }
+
+    #[test]
+    fn seek_bounds_clamp() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data).with_seek_bounds_policy(SeekBoundsPolicy::Clamp);
+        assert_eq!(8, ram.seek(SeekFrom::Start(20)).unwrap());
+        assert_eq!(0, ram.seek(SeekFrom::End(-100)).unwrap());
+        assert_eq!(3, ram.seek(SeekFrom::Current(3)).unwrap());
+    }
+
+    #[test]
+    fn seek_bounds_reject() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data).with_seek_bounds_policy(SeekBoundsPolicy::Reject);
+        assert_eq!(8, ram.seek(SeekFrom::End(0)).unwrap());
+        ram.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidInput, ram.seek(SeekFrom::Start(9)).unwrap_err().kind());
+        assert_eq!(std::io::ErrorKind::InvalidInput, ram.seek(SeekFrom::Current(-3)).unwrap_err().kind());
+        assert_eq!(2, ram.stream_position().unwrap());
+    }
+
+    #[test]
+    fn len_and_remaining() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data);
+        assert_eq!(8, ram.len());
+        assert_eq!(8, ram.remaining());
+        ram.seek(SeekFrom::Start(3)).unwrap();
+        assert_eq!(5, ram.remaining());
+        assert_eq!(3, ram.position());
+        ram.seek(SeekFrom::Start(12)).unwrap();
+        assert_eq!(0, ram.remaining());
+        assert_eq!(8, ram.len());
+    }
+
+    #[test]
+    fn word_alignment_helpers() {
+        let mut data = vec![0u8; 16];
+        let mut ram = ReversedWords::new_with_word_size(&mut data, 2);
+        assert_eq!(6, ram.seek_to_word(3).unwrap());
+        assert_eq!(6, ram.align_up().unwrap());
+        assert_eq!(6, ram.align_down().unwrap());
+        ram.seek(SeekFrom::Start(7)).unwrap();
+        assert_eq!(8, ram.align_up().unwrap());
+        ram.seek(SeekFrom::Start(7)).unwrap();
+        assert_eq!(6, ram.align_down().unwrap());
+    }
+
+    #[test]
+    fn peek_does_not_move_cursor() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let mut ram = ReversedWords::new(&mut data);
+        let mut out = [0u8; 3];
+        ram.seek(SeekFrom::Start(1)).unwrap();
+        assert_eq!(3, ram.peek(&mut out).unwrap());
+        assert_eq!([2, 1, 0], out);
+        assert_eq!(1, ram.position());
+        assert_eq!(3, ram.peek_at(5, &mut out).unwrap());
+        assert_eq!([6, 5, 4], out);
+        assert_eq!(1, ram.position());
+    }
+
+    #[test]
+    fn read_at_and_write_at_leave_cursor_alone() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.seek(SeekFrom::Start(5)).unwrap();
+        let mut out = [0u8; 4];
+        assert_eq!(4, ram.read_at(2, &mut out).unwrap());
+        assert_eq!([1, 0, 7, 6], out);
+        assert_eq!(2, ram.write_at(3, &[10, 11]).unwrap());
+        assert_eq!(5, ram.position());
+        assert_eq!(vec![10, 1, 2, 3, 4, 5, 6, 11], data);
+    }
+
+    #[test]
+    fn read_at_and_write_at_stop_at_end() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3];
+        let mut ram = ReversedWords::new(&mut data);
+        let mut out = [0u8; 4];
+        assert_eq!(2, ram.read_at(2, &mut out).unwrap());
+        assert_eq!([1, 0], out[..2]);
+        assert_eq!(0, ram.read_at(9, &mut out).unwrap());
+        assert_eq!(1, ram.write_at(3, &[9, 9]).unwrap());
+        assert_eq!(vec![9, 1, 2, 3], data);
+    }
+
+    #[test]
+    fn get_and_set_single_bytes() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.seek(SeekFrom::Start(6)).unwrap();
+        assert_eq!(2, ram.get_byte(1).unwrap());
+        ram.set_byte(5, 42).unwrap();
+        assert_eq!(42, ram.get_byte(5).unwrap());
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, ram.get_byte(8).unwrap_err().kind());
+        assert!(ram.set_byte(8, 0).is_err());
+        assert_eq!(6, ram.position());
+        assert_eq!(vec![0, 1, 2, 3, 4, 5, 42, 7], data);
+    }
+
+    #[test]
+    fn nibble_swap_composes_with_word_swap() {
+        let mut data: Vec<u8> = vec![0x01, 0x23, 0x45, 0x67];
+        let mut ram = ReversedWords::new(&mut data).with_nibble_swap(true);
+        let mut out = [0u8; 4];
+        ram.read_exact(&mut out).unwrap();
+        assert_eq!([0x76, 0x54, 0x32, 0x10], out);
+        assert_eq!(0x32, ram.get_byte(2).unwrap());
+        ram.seek(SeekFrom::Start(1)).unwrap();
+        ram.write_all(&[0xAB]).unwrap();
+        ram.set_byte(3, 0xCD).unwrap();
+        assert_eq!(vec![0xDC, 0x23, 0xBA, 0x67], data);
+    }
+
+    #[test]
+    fn xor_index_matches_reversal() {
+        for word_size in 1..=16u8 {
+            for position in 0..64u64 {
+                let ws = word_size as u64;
+                let reversed = position / ws * ws + (ws - 1 - position % ws);
+                assert_eq!(reversed, swap_index(position, word_size), "word size {}, position {}", word_size, position);
+            }
+        }
+    }
+
+    #[test]
+    fn read_to_end_reserves_once() {
+        let mut data: Vec<u8> = (0..64).collect();
+        let mut ram = ReversedWords::new(&mut data);
+        ram.seek(SeekFrom::Start(2)).unwrap();
+        let mut out = vec![0xAA];
+        assert_eq!(62, ram.read_to_end(&mut out).unwrap());
+        assert_eq!(63, out.capacity());
+        assert_eq!([0xAA, 1, 0, 7, 6], out[..5]);
+        assert_eq!(64, ram.position());
+
+        let mut ram = ReversedWords::new(&mut data).with_misalignment_policy(MisalignmentPolicy::Reject);
+        ram.seek(SeekFrom::Start(1)).unwrap();
+        let mut out = vec![0xAA];
+        assert!(ram.read_to_end(&mut out).is_err());
+        assert_eq!(vec![0xAA], out);
+    }
+
+    #[test]
+    fn read_into_uninitialized_memory() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut ram = ReversedWords::new(&mut data);
+        ram.seek(SeekFrom::Start(2)).unwrap();
+        let mut buf = [MaybeUninit::uninit(); 8];
+        assert_eq!([1, 0, 7, 6, 5, 4], ram.read_uninit(&mut buf).unwrap());
+        assert_eq!(8, ram.position());
+    }
+
+    #[cfg(all(feature = "read_buf", nightly))]
+    #[test]
+    fn read_buf_fills_borrowed_cursor() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut ram = ReversedWords::new(&mut data);
+        let mut storage = [MaybeUninit::uninit(); 6];
+        let mut buf = std::io::BorrowedBuf::from(&mut storage[..]);
+        ram.read_buf(buf.unfilled()).unwrap();
+        assert_eq!([3, 2, 1, 0, 7, 6], buf.filled());
+    }
+
+    #[test]
+    fn reconfigure_in_place() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut ram = ReversedWords::new(&mut data);
+        ram.seek(SeekFrom::Start(2)).unwrap();
+        ram.set_word_size(2).unwrap();
+        let mut out = [0u8; 2];
+        ram.read_exact(&mut out).unwrap();
+        assert_eq!([3, 2], out);
+        assert_eq!(Err(ReversedWordsError::InvalidWordSize), ram.set_word_size(0));
+        assert_eq!(2, ram.word_size());
+
+        let mut ram = ram.with_misalignment_policy(MisalignmentPolicy::Reject);
+        assert_eq!(Err(ReversedWordsError::Misaligned { position: 4, word_size: 8 }), ram.set_word_size(8));
+        ram.set_word_size(4).unwrap();
+        ram.set_nibble_swap(true);
+        assert_eq!(0x70, ram.get_byte(4).unwrap());
+    }
+
+    #[test]
+    fn bit_reversal_composes_with_swaps() {
+        let mut data = vec![0x01, 0x03, 0x80, 0xF0];
+        let mut ram = ReversedWords::new(&mut data).with_bit_reversal(true);
+        let mut out = [0u8; 4];
+        ram.read_at(0, &mut out).unwrap();
+        assert_eq!([0x0F, 0x01, 0xC0, 0x80], out);
+        ram.set_nibble_swap(true);
+        assert_eq!(0xF0, ram.get_byte(0).unwrap());
+        ram.set_byte(3, 0x12).unwrap();
+        assert_eq!(0x12, ram.get_byte(3).unwrap());
+        ram.set_nibble_swap(false);
+        ram.write_at(1, &[0x01]).unwrap();
+        assert!(ram.bit_reversal());
+        assert_eq!([0x84, 0x03, 0x80, 0xF0], data[..]);
+    }
+
+    #[test]
+    fn wrapping_addresses() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut ram = ReversedWords::new(&mut data).with_address_mode(AddressMode::Wrapping);
+        assert_eq!(3, ram.get_byte(0x8000_0000).unwrap());
+        let mut out = [0u8; 4];
+        assert_eq!(4, ram.read_at(6, &mut out).unwrap());
+        assert_eq!([5, 4, 3, 2], out);
+        assert_eq!(0x0504_0302, ram.read_u32_at(14, Endian::Big).unwrap());
+        ram.write_u16_at(7, 0xAABB, Endian::Big).unwrap();
+        assert_eq!(1, ram.position());
+        assert_eq!(vec![0, 1, 2, 0xBB, 0xAA, 5, 6, 7], data);
+    }
+
     #[test]
     fn write_past_end_fails() {
-        // todo: write
-        assert!(true)
+        let mut target = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut target);
+        // clipped partway through the second word
+        ram.seek(SeekFrom::Start(5)).unwrap();
+        assert_eq!(3, ram.write(&[1, 2, 3, 4, 5]).unwrap());
+        assert_eq!(8, ram.stream_position().unwrap());
+        assert_eq!(vec![0, 0, 0, 0, 3, 2, 1, 0], target);
+
+        let mut ram = ReversedWords::new(&mut target);
+        ram.seek(SeekFrom::End(0)).unwrap();
+        assert_eq!(0, ram.write(&[9]).unwrap());
+        ram.seek(SeekFrom::Start(6)).unwrap();
+        let error = ram.write_all(&[7, 8, 9]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::WriteZero, error.kind());
+        // what fit was still written
+        assert_eq!(vec![0, 0, 0, 0, 8, 7, 1, 0], target);
+    }
+
+    #[test]
+    fn counts_realigned_accesses() {
+        let mut data = vec![0u8; 16];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.write_at(0, &[1, 2, 3, 4]).unwrap();
+        ram.read_value_at::<u32>(0, Endian::Big).unwrap();
+        assert_eq!(MisalignmentStats::default(), ram.misalignment_stats());
+
+        ram.write_at(5, &[1]).unwrap();
+        ram.seek(SeekFrom::Start(2)).unwrap();
+        ram.read_exact(&mut [0; 2]).unwrap();
+        ram.read_at(9, &mut [0]).unwrap();
+        assert_eq!(MisalignmentStats { reads: 2, writes: 1, last_addr: Some(9) }, ram.misalignment_stats());
+        ram.reset_misalignment_stats();
+        assert_eq!(MisalignmentStats::default(), ram.misalignment_stats());
+
+        // rejected accesses aren't counted
+        let ram = ram.with_misalignment_policy(MisalignmentPolicy::Reject);
+        assert!(ram.read_at(3, &mut [0]).is_err());
+        assert_eq!(0, ram.misalignment_stats().reads);
+    }
+
+    #[test]
+    fn word_size_1_is_a_plain_copy() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut ram = ReversedWords::new_with_word_size(&mut data, 1);
+        ram.seek(SeekFrom::Start(3)).unwrap();
+        let mut out = [0u8; 8];
+        assert_eq!(5, ram.read(&mut out).unwrap());
+        assert_eq!([3, 4, 5, 6, 7, 0, 0, 0], out);
+        assert_eq!(0, ram.read(&mut out).unwrap());
+        ram.seek(SeekFrom::Start(6)).unwrap();
+        assert_eq!(2, ram.write(&[0xA0, 0xA1, 0xA2]).unwrap());
+        assert_eq!(8, ram.stream_position().unwrap());
+        assert_eq!(2, ram.write_at(1, &[0xB0, 0xB1]).unwrap());
+        assert_eq!(0, ram.write_at(9, &[0xC0]).unwrap());
+        assert_eq!(1, ram.read_at(7, &mut out).unwrap());
+        assert_eq!(0xA1, out[0]);
+
+        // wrapping and nibble swapped views still take the general path
+        let ram = ram.with_address_mode(AddressMode::Wrapping).with_nibble_swap(true);
+        assert_eq!(2, ram.read_at(15, &mut out[..2]).unwrap());
+        assert_eq!([0x1A, 0x00], out[..2]);
+        assert_eq!(vec![0, 0xB0, 0xB1, 3, 4, 5, 0xA0, 0xA1], data);
+    }
+
+    #[test]
+    fn write_stops_before_partial_trailing_word() {
+        let mut target = vec![0u8; 6];
+        let mut ram = ReversedWords::new(&mut target);
+        ram.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(2, ram.write(&[1, 2, 3, 4]).unwrap());
+        assert_eq!(vec![2, 1, 0, 0, 0, 0], target);
+    }
+
+    #[test]
+    fn read_stops_before_partial_trailing_word() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5];
+        let mut ram = ReversedWords::new(&mut data);
+        let mut out = [9u8; 4];
+        ram.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(2, ram.read(&mut out).unwrap());
+        assert_eq!([1, 0, 9, 9], out);
+        assert_eq!(4, ram.stream_position().unwrap());
+        assert_eq!(0, ram.read(&mut out).unwrap());
+        let error = ram.read_exact(&mut out).unwrap_err();
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, error.kind());
     }
 }
\ No newline at end of file
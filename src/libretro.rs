@@ -0,0 +1,402 @@
+//! Guest memory of a libretro core, from the `retro_memory_map` it hands the frontend.
+//!
+//! Each `retro_memory_descriptor` maps the guest addresses whose `select` bits equal `start`
+//! onto `len` bytes at `ptr + offset`, with the `disconnect` bits taken out of the address and
+//! anything still past `len` mirrored by clearing its highest bits. Descriptors are tried in
+//! order and the first match wins, as in RetroArch, so a core's mirrors and banked regions come
+//! out as one guest address space with no manual mapping.
+//!
+//! A [`LibretroMemory`] is made from the raw map with [`LibretroMemory::from_raw`], or from
+//! [`MemoryDescriptor`]s over borrowed blocks. Like [`ReversedWords`](crate::ReversedWords), it
+//! can swap the bytes of words within each block, for cores that keep guest RAM in host order.
+
+use std::{
+    ffi::CStr,
+    marker::PhantomData,
+    os::raw::{c_char, c_uint, c_void},
+};
+
+use binread::Endian;
+
+use crate::{swap_index, Primitive, ReversedWordsError};
+
+/// Writes are rejected.
+pub const RETRO_MEMDESC_CONST: u64 = 1 << 0;
+/// Values are big endian.
+pub const RETRO_MEMDESC_BIGENDIAN: u64 = 1 << 1;
+pub const RETRO_MEMDESC_SYSTEM_RAM: u64 = 1 << 2;
+pub const RETRO_MEMDESC_SAVE_RAM: u64 = 1 << 3;
+pub const RETRO_MEMDESC_VIDEO_RAM: u64 = 1 << 4;
+
+/// `struct retro_memory_descriptor` from `libretro.h`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RetroMemoryDescriptor {
+    pub flags: u64,
+    pub ptr: *mut c_void,
+    pub offset: usize,
+    pub start: usize,
+    pub select: usize,
+    pub disconnect: usize,
+    pub len: usize,
+    pub addrspace: *const c_char,
+}
+
+/// `struct retro_memory_map` from `libretro.h`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RetroMemoryMap {
+    pub descriptors: *const RetroMemoryDescriptor,
+    pub num_descriptors: c_uint,
+}
+
+/// A descriptor over one of the blocks given to [`LibretroMemory::new`], with the same fields as
+/// a `retro_memory_descriptor` but a block index in place of `ptr`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryDescriptor {
+    pub flags: u64,
+    pub block: usize,
+    pub offset: u64,
+    pub start: u64,
+    pub select: u64,
+    pub disconnect: u64,
+    pub len: u64,
+    pub name: Option<String>,
+}
+
+// the bits below the highest set bit of `n` set too
+fn add_bits_down(mut n: u64) -> u64 {
+    for shift in [1, 2, 4, 8, 16, 32] {
+        n |= n >> shift;
+    }
+    n
+}
+
+fn highest_bit(n: u64) -> u64 {
+    let n = add_bits_down(n);
+    n ^ (n >> 1)
+}
+
+// `addr` with a zero inserted at every set bit of `mask`
+fn inflate(mut addr: u64, mut mask: u64) -> u64 {
+    while mask != 0 {
+        let below = (mask - 1) & !mask;
+        addr = ((addr & !below) << 1) | (addr & below);
+        mask &= mask - 1;
+    }
+    addr
+}
+
+// `addr` with the bits at every set bit of `mask` taken out
+fn reduce(mut addr: u64, mut mask: u64) -> u64 {
+    while mask != 0 {
+        let below = (mask - 1) & !mask;
+        addr = (addr & below) | ((addr >> 1) & !below);
+        mask = (mask & (mask - 1)) >> 1;
+    }
+    addr
+}
+
+struct Descriptor {
+    flags: u64,
+    // start of the block, `offset` and `len` bytes within it are mapped
+    memory: *mut u8,
+    offset: u64,
+    start: u64,
+    select: u64,
+    disconnect: u64,
+    len: u64,
+    name: String,
+}
+
+/// A libretro core's guest address space.
+pub struct LibretroMemory<'a> {
+    descriptors: Vec<Descriptor>,
+    word_size: u8,
+    memory: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> LibretroMemory<'a> {
+    /// The address space of `descriptors` over `blocks`. Fails if a descriptor names a block
+    /// that isn't there or maps bytes past its end, or can't be completed the way libretro
+    /// completes them (a zero `select` needs a power of two `len`).
+    pub fn new(blocks: Vec<&'a mut [u8]>, descriptors: &[MemoryDescriptor]) -> Result<LibretroMemory<'a>, ReversedWordsError> {
+        // one pointer per block, shared by every descriptor over it
+        let blocks: Vec<(*mut u8, usize)> = blocks.into_iter().map(|block| (block.as_mut_ptr(), block.len())).collect();
+        let mut raw = Vec::with_capacity(descriptors.len());
+        for (index, descriptor) in descriptors.iter().enumerate() {
+            let invalid = |reason: String| ReversedWordsError::InvalidMemoryDescriptor { index, reason };
+            let (memory, len) = *blocks.get(descriptor.block).ok_or_else(|| invalid(format!("there is no block {}", descriptor.block)))?;
+            if descriptor.offset.saturating_add(descriptor.len) > len as u64 {
+                return Err(invalid(format!("maps {} bytes at {:#x} of a {} byte block", descriptor.len, descriptor.offset, len)));
+            }
+            raw.push(Descriptor {
+                flags: descriptor.flags,
+                memory,
+                offset: descriptor.offset,
+                start: descriptor.start,
+                select: descriptor.select,
+                disconnect: descriptor.disconnect,
+                len: descriptor.len,
+                name: descriptor.name.clone().unwrap_or_else(|| format!("descriptor {}", index)),
+            });
+        }
+        LibretroMemory::complete(raw)
+    }
+
+    /// The address space of a map a core passed to `RETRO_ENVIRONMENT_SET_MEMORY_MAPS`.
+    ///
+    /// # Safety
+    ///
+    /// `map` must point to `num_descriptors` valid descriptors, and for `'a` the memory each
+    /// maps must stay valid and not be accessed except through the returned value. Descriptors
+    /// with a null `ptr` are skipped.
+    pub unsafe fn from_raw(map: &RetroMemoryMap) -> Result<LibretroMemory<'a>, ReversedWordsError> {
+        let descriptors = match map.num_descriptors {
+            0 => &[][..],
+            count => std::slice::from_raw_parts(map.descriptors, count as usize),
+        };
+        let mut raw = Vec::with_capacity(descriptors.len());
+        for (index, descriptor) in descriptors.iter().enumerate().filter(|(_, descriptor)| !descriptor.ptr.is_null()) {
+            let name = match descriptor.addrspace.is_null() {
+                false => CStr::from_ptr(descriptor.addrspace).to_string_lossy().into_owned(),
+                true => format!("descriptor {}", index),
+            };
+            raw.push(Descriptor {
+                flags: descriptor.flags,
+                memory: descriptor.ptr as *mut u8,
+                offset: descriptor.offset as u64,
+                start: descriptor.start as u64,
+                select: descriptor.select as u64,
+                disconnect: descriptor.disconnect as u64,
+                len: descriptor.len as u64,
+                name,
+            });
+        }
+        LibretroMemory::complete(raw)
+    }
+
+    // fill in a zero `select` or `len` from the rest of the map, as the frontend does
+    fn complete(mut descriptors: Vec<Descriptor>) -> Result<LibretroMemory<'a>, ReversedWordsError> {
+        let top = add_bits_down(descriptors.iter().fold(1, |top, descriptor| match descriptor.select {
+            0 => top | descriptor.start.saturating_add(descriptor.len.saturating_sub(1)),
+            select => top | select,
+        }));
+        for (index, descriptor) in descriptors.iter_mut().enumerate() {
+            let invalid = |reason: &str| ReversedWordsError::InvalidMemoryDescriptor { index, reason: reason.to_string() };
+            if descriptor.select == 0 {
+                if !descriptor.len.is_power_of_two() {
+                    return Err(invalid("a descriptor without select bits needs a power of two length"));
+                }
+                descriptor.select = top & !inflate(add_bits_down(descriptor.len - 1), descriptor.disconnect);
+            }
+            if descriptor.len == 0 {
+                descriptor.len = add_bits_down(reduce(top & !descriptor.select, descriptor.disconnect)) + 1;
+            }
+            if descriptor.start & !descriptor.select != 0 {
+                return Err(invalid("start has bits outside of select"));
+            }
+        }
+        Ok(LibretroMemory { descriptors, word_size: 1, memory: PhantomData })
+    }
+
+    /// Swap the bytes of every `word_size` byte word of each block, counted from the start of
+    /// the block. 1 by default, which leaves the bytes as they are.
+    pub fn with_word_size(mut self, word_size: u8) -> Result<LibretroMemory<'a>, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        self.word_size = word_size;
+        Ok(self)
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    /// The `addrspace` (or given name) of the descriptor `addr` is mapped by.
+    pub fn region_name(&self, addr: u64) -> Option<&str> {
+        self.resolve(addr).map(|(descriptor, _)| descriptor.name.as_str())
+    }
+
+    /// Whether `addr` is mapped by a descriptor flagged [`RETRO_MEMDESC_CONST`].
+    pub fn is_read_only(&self, addr: u64) -> bool {
+        self.resolve(addr).is_some_and(|(descriptor, _)| descriptor.flags & RETRO_MEMDESC_CONST != 0)
+    }
+
+    // the descriptor mapping `addr` and the index of its byte in the descriptor's block
+    fn resolve(&self, addr: u64) -> Option<(&Descriptor, u64)> {
+        let descriptor = self.descriptors.iter().find(|descriptor| (descriptor.start ^ addr) & descriptor.select == 0)?;
+        let mut offset = reduce(addr & !descriptor.select, descriptor.disconnect);
+        while offset >= descriptor.len {
+            offset &= !highest_bit(offset);
+        }
+        let index = swap_index(descriptor.offset + offset, self.word_size);
+        // a partial trailing word can't be reached
+        (descriptor.offset..descriptor.offset + descriptor.len).contains(&index).then_some((descriptor, index))
+    }
+
+    /// Read the bytes at the guest addresses starting at `addr`, stopping at an unmapped one.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            match addr.checked_add(i as u64).and_then(|addr| self.resolve(addr)) {
+                // SAFETY: `index` is within the mapped bytes, which are valid for 'a
+                Some((descriptor, index)) => *byte = unsafe { *descriptor.memory.add(index as usize) },
+                None if i > 0 => return Ok(i),
+                None => return Err(ReversedWordsError::UnmappedAddress { addr }.into()),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    /// Write bytes at the guest addresses starting at `addr`, stopping at an unmapped or
+    /// read-only one.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        for (i, byte) in buf.iter().enumerate() {
+            let position = addr.checked_add(i as u64);
+            let error = match position.and_then(|addr| self.resolve(addr)) {
+                Some((descriptor, _)) if descriptor.flags & RETRO_MEMDESC_CONST != 0 => {
+                    ReversedWordsError::ReadOnly { addr: addr + i as u64, region: descriptor.name.clone() }
+                }
+                // SAFETY: as for reads, and nothing else accesses the memory while `self` is borrowed mutably
+                Some((descriptor, index)) => {
+                    unsafe { *descriptor.memory.add(index as usize) = *byte };
+                    continue;
+                }
+                None => ReversedWordsError::UnmappedAddress { addr: position.unwrap_or(u64::MAX) },
+            };
+            if i > 0 {
+                return Ok(i);
+            }
+            return Err(error.into());
+        }
+        Ok(buf.len())
+    }
+
+    /// Read a value at `addr`, failing unless all of it is mapped.
+    pub fn read_value_at<T: Primitive>(&self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        let n = self.read_at(addr, bytes.as_mut())?;
+        if n < bytes.as_ref().len() {
+            return Err(ReversedWordsError::UnmappedAddress { addr: addr + n as u64 }.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    /// Write a value at `addr`, failing without writing anything unless all of it is mapped and
+    /// writable.
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        let bytes = value.to_bytes(endian);
+        for i in 0..bytes.as_ref().len() as u64 {
+            let position = addr.checked_add(i).ok_or(ReversedWordsError::UnmappedAddress { addr: u64::MAX })?;
+            match self.resolve(position) {
+                Some((descriptor, _)) if descriptor.flags & RETRO_MEMDESC_CONST != 0 => {
+                    return Err(ReversedWordsError::ReadOnly { addr: position, region: descriptor.name.clone() }.into())
+                }
+                Some(_) => {}
+                None => return Err(ReversedWordsError::UnmappedAddress { addr: position }.into()),
+            }
+        }
+        self.write_at(addr, bytes.as_ref())?;
+        Ok(())
+    }
+
+    /// Read a value in the endianness its descriptor is flagged with: big with
+    /// [`RETRO_MEMDESC_BIGENDIAN`], little without.
+    pub fn read_value<T: Primitive>(&self, addr: u64) -> std::io::Result<T> {
+        self.read_value_at(addr, self.endian_at(addr))
+    }
+
+    pub fn write_value<T: Primitive>(&mut self, addr: u64, value: T) -> std::io::Result<()> {
+        self.write_value_at(addr, value, self.endian_at(addr))
+    }
+
+    fn endian_at(&self, addr: u64) -> Endian {
+        match self.resolve(addr) {
+            Some((descriptor, _)) if descriptor.flags & RETRO_MEMDESC_BIGENDIAN != 0 => Endian::Big,
+            _ => Endian::Little,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{libretro::*, ReversedWords};
+
+    #[test]
+    fn snes_mirrors() {
+        let mut wram = vec![0u8; 0x2_0000];
+        let mut rom = vec![0xEAu8; 0x8000];
+        let descriptors = [
+            MemoryDescriptor { flags: RETRO_MEMDESC_SYSTEM_RAM, block: 0, start: 0x7E_0000, len: 0x2_0000, name: Some("wram".into()), ..Default::default() },
+            // the first 8 KiB of WRAM shows up in the low half of every bank 00-3F and 80-BF
+            MemoryDescriptor { block: 0, start: 0, select: 0x40_E000, len: 0x2000, ..Default::default() },
+            MemoryDescriptor { flags: RETRO_MEMDESC_CONST, block: 1, start: 0x8000, select: 0x40_8000, len: 0x8000, name: Some("rom".into()), ..Default::default() },
+        ];
+        let mut memory = LibretroMemory::new(vec![&mut wram, &mut rom], &descriptors).unwrap();
+        memory.write_value::<u16>(0x7E_0010, 0x1234).unwrap();
+        assert_eq!(0x1234, memory.read_value::<u16>(0x00_0010).unwrap());
+        assert_eq!(0x1234, memory.read_value::<u16>(0x3F_0010).unwrap());
+        assert_eq!(0x1234, memory.read_value::<u16>(0x80_0010).unwrap());
+        assert_eq!(Some("descriptor 1"), memory.region_name(0x80_0010));
+        memory.write_value::<u8>(0x7F_FFFF, 7).unwrap();
+        assert_eq!(7, memory.read_value::<u8>(0x7F_FFFF).unwrap());
+        // WRAM beyond 8 KiB isn't mirrored
+        memory.write_value::<u8>(0x7E_2010, 9).unwrap();
+        assert_eq!(0x34, memory.read_value::<u8>(0x01_0010).unwrap());
+
+        assert_eq!(0xEAEA, memory.read_value::<u16>(0x21_8000).unwrap());
+        assert!(memory.is_read_only(0x80_FFFF));
+        let error = memory.write_value::<u16>(0x80_FFFF, 0).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::ReadOnly { addr: 0x80_FFFF, region: "rom".into() }), ReversedWordsError::from_io(&error));
+        assert!(memory.read_value::<u8>(0x40_4000).is_err());
+        drop(memory);
+        assert_eq!([0x34, 0x12], wram[0x10..0x12]);
+        assert_eq!([7, 9], [wram[0x1_FFFF], wram[0x2010]]);
+    }
+
+    #[test]
+    fn raw_maps_with_disconnected_bits_and_swapped_words() {
+        let mut rdram: Vec<u8> = vec![0; 0x400];
+        let mut expected = rdram.clone();
+        let name = b"rdram\0";
+        let raw = [
+            RetroMemoryDescriptor {
+                flags: RETRO_MEMDESC_BIGENDIAN,
+                ptr: rdram.as_mut_ptr() as *mut c_void,
+                offset: 0x100,
+                start: 0x1000,
+                select: 0xF000,
+                disconnect: 0x100,
+                len: 0x200,
+                addrspace: name.as_ptr() as *const c_char,
+            },
+            RetroMemoryDescriptor { flags: 0, ptr: std::ptr::null_mut(), offset: 0, start: 0, select: 0, disconnect: 0, len: 0, addrspace: std::ptr::null() },
+        ];
+        let map = RetroMemoryMap { descriptors: raw.as_ptr(), num_descriptors: raw.len() as c_uint };
+        let mut memory = unsafe { LibretroMemory::from_raw(&map) }.unwrap().with_word_size(4).unwrap();
+        memory.write_value::<u32>(0x1204, 0xA1A2_A3A4).unwrap();
+        // bit 8 is disconnected, so 0x1100 is 0x1000 again and 0x1204 is offset 0x104
+        memory.write_value::<u32>(0x1100, 0x0102_0304).unwrap();
+        assert_eq!(0x0102_0304, memory.read_value::<u32>(0x1000).unwrap());
+        assert_eq!(Some("rdram"), memory.region_name(0x1000));
+        assert!(memory.read_value::<u8>(0x2000).is_err());
+        drop(memory);
+
+        let mut view = ReversedWords::new(&mut expected);
+        view.write_value_at(0x100, 0x0102_0304u32, Endian::Big).unwrap();
+        view.write_value_at(0x204, 0xA1A2_A3A4u32, Endian::Big).unwrap();
+        assert_eq!(expected, rdram);
+    }
+
+    #[test]
+    fn incomplete_descriptors_are_rejected() {
+        let mut block = vec![0u8; 0x300];
+        let odd = MemoryDescriptor { len: 0x300, ..Default::default() };
+        assert!(LibretroMemory::new(vec![&mut block], &[odd]).is_err());
+        let past_end = MemoryDescriptor { offset: 0x200, len: 0x200, ..Default::default() };
+        assert!(LibretroMemory::new(vec![&mut block], &[past_end]).is_err());
+        let missing = MemoryDescriptor { block: 1, len: 0x100, ..Default::default() };
+        assert!(LibretroMemory::new(vec![&mut block], &[missing]).is_err());
+    }
+}
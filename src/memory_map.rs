@@ -0,0 +1,922 @@
+//! A composite memory map of separately stored regions at virtual base addresses.
+//!
+//! Each [`Region`] owns its bytes and has its own word size, so e.g. 4-byte swapped RDRAM can sit
+//! next to unswapped PIF RAM. Accesses on the [`MemoryMap`] take virtual addresses and are routed
+//! to the region containing them, and an access running across directly adjacent regions swaps
+//! each byte according to the region it lands in.
+//!
+//! Regions can be marked read-only to model ROM: writes touching them fail with
+//! `ErrorKind::PermissionDenied` and change nothing, until protection is lifted for a
+//! [`MemoryMap::patch_session`].
+//!
+//! Guard ranges catch stray accesses: any read or write touching one fails (and reports a
+//! [`GuardHit`] to an optional channel) instead of reading zeroes from a hole or scribbling on
+//! something that shouldn't be touched.
+//!
+//! A [`Checksum`] over a range can be kept up to date as writes go through the map, optionally
+//! stored back into the checksum field the data is verified against, see
+//! [`MemoryMap::track_checksum`].
+//!
+//! With the `config` feature a map and its labels can be described in TOML or YAML:
+//!
+//! ```toml
+//! [[segments]]
+//! name = "rdram"
+//! base = 0x80000000
+//! size = 0x800000
+//! word_size = 4
+//!
+//! [[labels]]
+//! name = "gPlayerHealth"
+//! addr = 0x80371240
+//! size = 2
+//! type = "u16"
+//! comment = "current health"
+//! ```
+
+use std::{
+    ops::{Deref, DerefMut, Range},
+    sync::{mpsc::Sender, Arc},
+};
+
+use binread::Endian;
+
+use crate::{checksum::Checksum, Primitive, ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    base: u64,
+    word_size: u8,
+    data: Vec<u8>,
+    read_only: bool,
+}
+
+impl Region {
+    /// The largest region [`Region::zeroed`] allocates.
+    pub const MAX_ZEROED_LEN: u64 = 1 << 30;
+
+    /// Fails with [`ReversedWordsError::InvalidConfig`] if the region would run past the end of
+    /// the address space.
+    pub fn new(name: &str, base: u64, word_size: u8, data: Vec<u8>) -> Result<Region, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        if base.checked_add(data.len() as u64).is_none() {
+            let reason = format!("region {:?} at {:#x} runs past the end of the address space", name, base);
+            return Err(ReversedWordsError::InvalidConfig { reason });
+        }
+        Ok(Region { name: name.to_string(), base, word_size, data, read_only: false })
+    }
+
+    /// A zero filled region of `size` bytes, at most [`Region::MAX_ZEROED_LEN`].
+    pub fn zeroed(name: &str, base: u64, word_size: u8, size: u64) -> Result<Region, ReversedWordsError> {
+        if size > Region::MAX_ZEROED_LEN {
+            let reason = format!("region {:?} is {:#x} bytes, more than the {:#x} byte limit", name, size, Region::MAX_ZEROED_LEN);
+            return Err(ReversedWordsError::InvalidConfig { reason });
+        }
+        if base.checked_add(size).is_none() {
+            let reason = format!("region {:?} at {:#x} runs past the end of the address space", name, base);
+            return Err(ReversedWordsError::InvalidConfig { reason });
+        }
+        Region::new(name, base, word_size, vec![0u8; size as usize])
+    }
+
+    /// Reject writes through the [`MemoryMap`], see [`MemoryMap::patch_session`].
+    pub fn with_read_only(mut self, read_only: bool) -> Region {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    pub fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Virtual addresses covered by the region. Constructing a region checks they don't overflow.
+    pub fn range(&self) -> Range<u64> {
+        self.base..self.base + self.len()
+    }
+
+    /// The region's bytes in storage order.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Change how the region's bytes are swapped. The stored bytes are left as they are, so their
+    /// logical contents change.
+    pub fn set_word_size(&mut self, word_size: u8) -> Result<(), ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        self.word_size = word_size;
+        Ok(())
+    }
+
+    /// A view of the region, addressed from 0.
+    pub fn words(&mut self) -> ReversedWords<'_> {
+        ReversedWords::new_with_word_size(&mut self.data, self.word_size)
+    }
+}
+
+/// A range of virtual addresses that must not be accessed, see [`MemoryMap::add_guard`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Guard {
+    pub name: String,
+    pub range: Range<u64>,
+}
+
+/// An access that touched a guard.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuardHit {
+    pub guard: String,
+    /// First guarded address the access touched.
+    pub addr: u64,
+    pub write: bool,
+}
+
+/// A checksum kept current by [`MemoryMap::track_checksum`].
+#[derive(Clone, Debug)]
+pub struct TrackedChecksum {
+    pub name: String,
+    pub range: Range<u64>,
+    /// Where the checksum is stored as a `u32`.
+    pub field: Option<(u64, Endian)>,
+    algorithm: Arc<dyn Checksum>,
+    value: u32,
+}
+
+impl TrackedChecksum {
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+}
+
+/// Compares everything but the algorithm.
+impl PartialEq for TrackedChecksum {
+    fn eq(&self, other: &TrackedChecksum) -> bool {
+        self.name == other.name && self.range == other.range && self.field == other.field && self.value == other.value
+    }
+}
+
+impl Eq for TrackedChecksum {}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryMap {
+    // sorted by base, never overlapping
+    regions: Vec<Region>,
+    // read-only regions are writable while set
+    patching: bool,
+    guards: Vec<Guard>,
+    guard_events: Option<Sender<GuardHit>>,
+    checksums: Vec<TrackedChecksum>,
+}
+
+/// Compares the regions, guards, checksums and protection state, not where guard hits are sent.
+impl PartialEq for MemoryMap {
+    fn eq(&self, other: &MemoryMap) -> bool {
+        self.regions == other.regions
+            && self.patching == other.patching
+            && self.guards == other.guards
+            && self.checksums == other.checksums
+    }
+}
+
+impl Eq for MemoryMap {}
+
+impl MemoryMap {
+    pub fn new() -> MemoryMap {
+        MemoryMap::default()
+    }
+
+    pub fn with_region(mut self, region: Region) -> Result<MemoryMap, ReversedWordsError> {
+        self.add_region(region)?;
+        Ok(self)
+    }
+
+    pub fn add_region(&mut self, region: Region) -> Result<(), ReversedWordsError> {
+        let range = region.range();
+        if let Some(existing) = self.regions.iter().find(|existing| {
+            let existing = existing.range();
+            existing.start < range.end && range.start < existing.end
+        }) {
+            return Err(ReversedWordsError::OverlappingRegion { name: region.name, other: existing.name.clone() });
+        }
+        let index = self.regions.partition_point(|existing| existing.base < region.base);
+        self.regions.insert(index, region);
+        Ok(())
+    }
+
+    /// Make any access touching `range` fail with [`ReversedWordsError::GuardHit`], whether or not
+    /// it is mapped.
+    pub fn add_guard(&mut self, name: &str, range: Range<u64>) {
+        self.guards.push(Guard { name: name.to_string(), range });
+    }
+
+    pub fn with_guard(mut self, name: &str, range: Range<u64>) -> MemoryMap {
+        self.add_guard(name, range);
+        self
+    }
+
+    pub fn guards(&self) -> &[Guard] {
+        &self.guards
+    }
+
+    /// Also send every guard hit to `events`. Sending stops silently once the receiver is gone.
+    pub fn with_guard_events(mut self, events: Sender<GuardHit>) -> MemoryMap {
+        self.guard_events = Some(events);
+        self
+    }
+
+    // fail if an access of `len` bytes from `addr` touches a guard
+    fn check_guards(&self, addr: u64, len: u64, write: bool) -> Result<(), ReversedWordsError> {
+        // the access stops short at a gap, so only the part before it counts
+        let end = addr.saturating_add(len.min(self.contiguous_len(addr).unwrap_or(1)));
+        let hit = self
+            .guards
+            .iter()
+            .filter(|guard| guard.range.start < end && addr < guard.range.end)
+            .min_by_key(|guard| guard.range.start.max(addr));
+        let Some(guard) = hit else { return Ok(()) };
+        let hit = GuardHit { guard: guard.name.clone(), addr: guard.range.start.max(addr), write };
+        if let Some(events) = &self.guard_events {
+            let _ = events.send(hit.clone());
+        }
+        Err(ReversedWordsError::GuardHit { addr: hit.addr, guard: hit.guard })
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.regions.iter().find(|region| region.name == name)
+    }
+
+    pub fn region_mut(&mut self, name: &str) -> Option<&mut Region> {
+        self.regions.iter_mut().find(|region| region.name == name)
+    }
+
+    fn index_at(&self, addr: u64) -> Result<usize, ReversedWordsError> {
+        let index = self.regions.partition_point(|region| region.base <= addr);
+        match index.checked_sub(1) {
+            Some(index) if self.regions[index].range().contains(&addr) => Ok(index),
+            _ => Err(ReversedWordsError::UnmappedAddress { addr }),
+        }
+    }
+
+    /// The region containing virtual address `addr`.
+    pub fn region_at(&self, addr: u64) -> Option<&Region> {
+        self.index_at(addr).ok().map(|index| &self.regions[index])
+    }
+
+    /// Bytes of directly adjacent regions available from `addr` onwards.
+    fn contiguous_len(&self, addr: u64) -> Result<u64, ReversedWordsError> {
+        let mut index = self.index_at(addr)?;
+        let mut end = self.regions[index].range().end;
+        while let Some(next) = self.regions.get(index + 1).filter(|next| next.base == end) {
+            end = next.range().end;
+            index += 1;
+        }
+        Ok(end - addr)
+    }
+
+    /// Read logical bytes starting at virtual address `addr`.
+    ///
+    /// Reads continue into directly adjacent regions, each byte transformed according to the word
+    /// size of the region it is in. Returns the number of bytes read, which is less than
+    /// `buf.len()` if a gap or the end of the map is reached.
+    pub fn read_at(&mut self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.check_guards(addr, buf.len() as u64, false)?;
+        self.read_unchecked(addr, buf)
+    }
+
+    fn read_unchecked(&mut self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut index = self.index_at(addr)?;
+        let mut done = 0;
+        while done < buf.len() {
+            let position = addr + done as u64;
+            let region = match self.regions.get_mut(index) {
+                Some(region) if region.range().contains(&position) => region,
+                _ => break,
+            };
+            let offset = position - region.base;
+            done += region.words().read_at(offset, &mut buf[done..])?;
+            index += 1;
+        }
+        Ok(done)
+    }
+
+    // fail if writing `len` bytes from `addr` would touch a protected region
+    fn check_writable(&self, addr: u64, len: u64) -> Result<(), ReversedWordsError> {
+        if self.patching || len == 0 {
+            return Ok(());
+        }
+        let end = addr + len.min(self.contiguous_len(addr)?);
+        let first = self.index_at(addr)?;
+        for region in self.regions[first..].iter().take_while(|region| region.base < end) {
+            if region.read_only {
+                return Err(ReversedWordsError::ReadOnly { addr: addr.max(region.base), region: region.name.clone() });
+            }
+        }
+        Ok(())
+    }
+
+    /// Write logical bytes starting at virtual address `addr`, continuing into directly adjacent
+    /// regions like [`MemoryMap::read_at`].
+    ///
+    /// Returns the number of bytes written, which is less than `buf.len()` if a gap or the end of the map is reached.
+    /// Nothing is written if any of the bytes would land in a read-only region.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        self.check_guards(addr, buf.len() as u64, true)?;
+        self.check_writable(addr, buf.len() as u64)?;
+        let old = self.checksummed_bytes(addr, buf.len() as u64)?;
+        let done = self.write_unchecked(addr, buf)?;
+        self.update_checksums(addr + done as u64, old)?;
+        Ok(done)
+    }
+
+    fn write_unchecked(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let mut index = self.index_at(addr)?;
+        let mut done = 0;
+        while done < buf.len() {
+            let position = addr + done as u64;
+            let region = match self.regions.get_mut(index) {
+                Some(region) if region.range().contains(&position) => region,
+                _ => break,
+            };
+            let offset = position - region.base;
+            done += region.words().write_at(offset, &buf[done..])?;
+            index += 1;
+        }
+        Ok(done)
+    }
+
+    pub fn read_value_at<T: Primitive>(&mut self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        let len = bytes.as_ref().len();
+        let read = self.read_at(addr, bytes.as_mut())?;
+        if read < len {
+            return Err(ReversedWordsError::UnmappedAddress { addr: addr + read as u64 }.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        let bytes = value.to_bytes(endian);
+        self.check_guards(addr, bytes.as_ref().len() as u64, true)?;
+        // check up front so a value running into a gap isn't partially written
+        let available = self.contiguous_len(addr)?;
+        if available < bytes.as_ref().len() as u64 {
+            return Err(ReversedWordsError::UnmappedAddress { addr: addr + available }.into());
+        }
+        self.write_at(addr, bytes.as_ref()).map(|_| ())
+    }
+
+    /// Keep the `algorithm` checksum of the logical bytes in `range` current as writes go through
+    /// the map, returning its current value. With a `field`, the checksum is also written there
+    /// now and after every change; the field may be covered by another tracked checksum, but not
+    /// by its own, or by any checksum whose field is in turn covered by this one, directly or through
+    /// others, since neither could ever settle.
+    ///
+    /// The field must be mapped, outside every guard and not read-only unless the whole range is
+    /// too, so a write landing in the range can always store the new value; track one stored in a
+    /// read-only region from inside a [`MemoryMap::patch_session`]. Changes made directly
+    /// to a region's data (through [`MemoryMap::region_mut`]) or its protection aren't seen.
+    pub fn track_checksum(
+        &mut self,
+        name: &str,
+        range: Range<u64>,
+        algorithm: Arc<dyn Checksum>,
+        field: Option<(u64, Endian)>,
+    ) -> std::io::Result<u32> {
+        if range.start > range.end {
+            return Err(ReversedWordsError::ReversedRange { start: range.start, end: range.end }.into());
+        }
+        if let Some((addr, _)) = field.filter(|(addr, _)| *addr < range.end && range.start < addr.saturating_add(4)) {
+            return Err(ReversedWordsError::ChecksumFieldInRange { name: name.to_string(), addr: addr.max(range.start) }.into());
+        }
+        if let Some((addr, _)) = field {
+            self.check_checksum_field(&range, addr)?;
+        }
+        if let Some(through) = self.checksum_cycle(&range, field) {
+            return Err(ReversedWordsError::ChecksumCycle { name: name.to_string(), through }.into());
+        }
+        let value = self.scan_checksum(&range, &*algorithm)?;
+        if let Some((addr, endian)) = field {
+            self.write_value_at(addr, value, endian)?;
+        }
+        self.checksums.push(TrackedChecksum { name: name.to_string(), range, field, algorithm, value });
+        Ok(value)
+    }
+
+    /// Stop tracking checksum `name`, returning it.
+    pub fn untrack_checksum(&mut self, name: &str) -> Option<TrackedChecksum> {
+        let index = self.checksums.iter().position(|tracked| tracked.name == name)?;
+        Some(self.checksums.remove(index))
+    }
+
+    pub fn checksums(&self) -> &[TrackedChecksum] {
+        &self.checksums
+    }
+
+    /// Current value of checksum `name`.
+    pub fn checksum(&self, name: &str) -> Option<u32> {
+        self.checksums.iter().find(|tracked| tracked.name == name).map(TrackedChecksum::value)
+    }
+
+    fn scan_checksum(&mut self, range: &Range<u64>, algorithm: &dyn Checksum) -> std::io::Result<u32> {
+        let mut data = vec![0u8; (range.end - range.start) as usize];
+        let read = self.read_unchecked(range.start, &mut data)?;
+        if read < data.len() {
+            return Err(ReversedWordsError::UnmappedAddress { addr: range.start + read as u64 }.into());
+        }
+        Ok(algorithm.checksum(&data))
+    }
+
+    // fail unless a checksum of `range` can always be stored in the 4 bytes at `addr`
+    fn check_checksum_field(&self, range: &Range<u64>, addr: u64) -> Result<(), ReversedWordsError> {
+        let available = self.contiguous_len(addr)?;
+        if available < 4 {
+            return Err(ReversedWordsError::UnmappedAddress { addr: addr + available });
+        }
+        let end = addr + 4;
+        if let Some(guard) = self.guards.iter().find(|guard| guard.range.start < end && addr < guard.range.end) {
+            return Err(ReversedWordsError::GuardHit { addr: guard.range.start.max(addr), guard: guard.name.clone() });
+        }
+        let overlaps = |region: &&Region, range: &Range<u64>| region.base < range.end && range.start < region.range().end;
+        let range_writable = self.regions.iter().filter(|region| overlaps(region, range)).any(|region| !region.read_only);
+        match self.regions.iter().filter(|region| overlaps(region, &(addr..end))).find(|region| region.read_only) {
+            Some(region) if range_writable => Err(ReversedWordsError::ReadOnly { addr: addr.max(region.base), region: region.name.clone() }),
+            _ => Ok(()),
+        }
+    }
+
+    // name of a tracked checksum that a new one over `range` stored at `field` would end up updating
+    // itself through: storing the new field changes a chain of tracked checksums that leads back
+    // into `range`
+    fn checksum_cycle(&self, range: &Range<u64>, field: Option<(u64, Endian)>) -> Option<String> {
+        let covers = |range: &Range<u64>, field: u64| field < range.end && range.start < field.saturating_add(4);
+        let covering = |field: u64| (0..self.checksums.len()).filter(move |&index| covers(&self.checksums[index].range, field));
+        let mut pending: Vec<usize> = covering(field?.0).collect();
+        let mut seen = vec![false; self.checksums.len()];
+        while let Some(index) = pending.pop() {
+            if std::mem::replace(&mut seen[index], true) {
+                continue;
+            }
+            let tracked = &self.checksums[index];
+            if let Some((addr, _)) = tracked.field {
+                if covers(range, addr) {
+                    return Some(tracked.name.clone());
+                }
+                pending.extend(covering(addr));
+            }
+        }
+        None
+    }
+
+    // (checksum index, address, bytes) of every tracked range a write of `len` bytes from `addr` touches
+    fn checksummed_bytes(&mut self, addr: u64, len: u64) -> std::io::Result<Vec<(usize, u64, Vec<u8>)>> {
+        let end = addr.saturating_add(len);
+        let touched: Vec<(usize, Range<u64>)> = self
+            .checksums
+            .iter()
+            .enumerate()
+            .map(|(index, tracked)| (index, addr.max(tracked.range.start)..end.min(tracked.range.end)))
+            .filter(|(_, overlap)| overlap.start < overlap.end)
+            .collect();
+        let mut old = Vec::with_capacity(touched.len());
+        for (index, overlap) in touched {
+            let mut bytes = vec![0u8; (overlap.end - overlap.start) as usize];
+            self.read_unchecked(overlap.start, &mut bytes)?;
+            old.push((index, overlap.start, bytes));
+        }
+        Ok(old)
+    }
+
+    // bring the checksums up to date after a write that ended at `end`
+    fn update_checksums(&mut self, end: u64, old: Vec<(usize, u64, Vec<u8>)>) -> std::io::Result<()> {
+        for (index, start, mut old) in old {
+            old.truncate(end.saturating_sub(start).min(old.len() as u64) as usize);
+            let mut new = vec![0u8; old.len()];
+            self.read_unchecked(start, &mut new)?;
+            let tracked = &self.checksums[index];
+            let (range, algorithm) = (tracked.range.clone(), tracked.algorithm.clone());
+            let updated = algorithm.update(tracked.value, range.end - range.start, start - range.start, &old, &new);
+            let value = match updated {
+                Some(value) => value,
+                None => self.scan_checksum(&range, &*algorithm)?,
+            };
+            self.checksums[index].value = value;
+            if let Some((addr, endian)) = self.checksums[index].field {
+                self.write_value_at(addr, value, endian)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Lift write protection until the returned session is dropped.
+    pub fn patch_session(&mut self) -> PatchSession<'_> {
+        self.patching = true;
+        PatchSession { map: self }
+    }
+}
+
+/// A [`MemoryMap`] with writes to read-only regions allowed, see [`MemoryMap::patch_session`].
+pub struct PatchSession<'m> {
+    map: &'m mut MemoryMap,
+}
+
+impl Deref for PatchSession<'_> {
+    type Target = MemoryMap;
+
+    fn deref(&self) -> &MemoryMap {
+        self.map
+    }
+}
+
+impl DerefMut for PatchSession<'_> {
+    fn deref_mut(&mut self) -> &mut MemoryMap {
+        self.map
+    }
+}
+
+impl Drop for PatchSession<'_> {
+    fn drop(&mut self) {
+        self.map.patching = false;
+    }
+}
+
+#[cfg(feature = "config")]
+pub use config::{LabelDescription, MemoryMapDescription, SegmentDescription};
+
+#[cfg(feature = "config")]
+mod config {
+    use serde::Deserialize;
+
+    use crate::{
+        expr::ValueType,
+        labels::{Label, Labels},
+        memory_map::{MemoryMap, Region},
+        ReversedWordsError,
+    };
+
+    fn unswapped() -> u8 {
+        1
+    }
+
+    fn one_byte() -> u64 {
+        1
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+    pub struct SegmentDescription {
+        pub name: String,
+        pub base: u64,
+        pub size: u64,
+        /// 1 (unswapped) if omitted.
+        #[serde(default = "unswapped")]
+        pub word_size: u8,
+        #[serde(default)]
+        pub read_only: bool,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+    pub struct LabelDescription {
+        pub name: String,
+        pub addr: u64,
+        #[serde(default = "one_byte")]
+        pub size: u64,
+        #[serde(default, rename = "type")]
+        pub value_type: Option<String>,
+        #[serde(default)]
+        pub comment: Option<String>,
+    }
+
+    /// A target's memory layout as data, see the [module docs](crate::memory_map).
+    #[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+    pub struct MemoryMapDescription {
+        pub segments: Vec<SegmentDescription>,
+        #[serde(default)]
+        pub labels: Vec<LabelDescription>,
+    }
+
+    fn config_error(e: impl std::fmt::Display) -> ReversedWordsError {
+        ReversedWordsError::InvalidConfig { reason: e.to_string() }
+    }
+
+    impl MemoryMapDescription {
+        pub fn from_toml(text: &str) -> Result<MemoryMapDescription, ReversedWordsError> {
+            toml::from_str(text).map_err(config_error)
+        }
+
+        pub fn from_yaml(text: &str) -> Result<MemoryMapDescription, ReversedWordsError> {
+            serde_yaml::from_str(text).map_err(config_error)
+        }
+
+        /// A map with a zero filled region per segment, and the labels at their virtual addresses.
+        pub fn build(&self) -> Result<(MemoryMap, Labels), ReversedWordsError> {
+            let mut map = MemoryMap::new();
+            for segment in &self.segments {
+                let region = Region::zeroed(&segment.name, segment.base, segment.word_size, segment.size)?;
+                map.add_region(region.with_read_only(segment.read_only))?;
+            }
+            let mut labels = Labels::new();
+            for label in &self.labels {
+                let end = label
+                    .addr
+                    .checked_add(label.size)
+                    .ok_or_else(|| config_error(format!("label {:?} runs past the end of the address space", label.name)))?;
+                let mut built = Label::new(&label.name, label.addr..end);
+                if let Some(name) = &label.value_type {
+                    let value_type = ValueType::from_name(name)
+                        .ok_or_else(|| config_error(format!("label {:?} has unknown type {:?}", label.name, name)))?;
+                    built = built.with_value_type(value_type);
+                }
+                if let Some(comment) = &label.comment {
+                    built = built.with_comment(comment);
+                }
+                labels.insert(built);
+            }
+            Ok((map, labels))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory_map::*;
+
+    fn n64() -> MemoryMap {
+        MemoryMap::new()
+            .with_region(Region::zeroed("rdram", 0x8000_0000, 4, 0x10).unwrap())
+            .unwrap()
+            .with_region(Region::zeroed("pif_ram", 0x1FC0_07C0, 1, 0x40).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn regions_must_fit_the_address_space() {
+        assert!(matches!(Region::new("high", u64::MAX - 1, 1, vec![0; 4]), Err(ReversedWordsError::InvalidConfig { .. })));
+        assert!(matches!(Region::zeroed("huge", 0, 1, u64::MAX), Err(ReversedWordsError::InvalidConfig { .. })));
+        assert_eq!(u64::MAX - 4..u64::MAX, Region::zeroed("top", u64::MAX - 4, 1, 4).unwrap().range());
+    }
+
+    #[test]
+    fn accesses_route_to_regions() {
+        let mut map = n64();
+        map.write_value_at(0x8000_0004, 0xDEADBEEFu32, Endian::Big).unwrap();
+        map.write_value_at(0x1FC0_07C4, 0xDEADBEEFu32, Endian::Big).unwrap();
+        assert_eq!([0xEF, 0xBE, 0xAD, 0xDE], map.region("rdram").unwrap().data()[4..8]);
+        assert_eq!([0xDE, 0xAD, 0xBE, 0xEF], map.region("pif_ram").unwrap().data()[4..8]);
+        assert_eq!(0xDEADBEEF, map.read_value_at::<u32>(0x8000_0004, Endian::Big).unwrap());
+        assert_eq!("rdram", map.region_at(0x8000_000F).unwrap().name);
+    }
+
+    #[test]
+    fn accesses_span_adjacent_regions() {
+        let mut map = MemoryMap::new()
+            .with_region(Region::zeroed("swapped", 0x1000, 4, 4).unwrap())
+            .unwrap()
+            .with_region(Region::zeroed("plain", 0x1004, 1, 4).unwrap())
+            .unwrap();
+        map.write_value_at(0x1002, 0x0102_0304u32, Endian::Big).unwrap();
+        assert_eq!([0x02, 0x01, 0, 0], map.region("swapped").unwrap().data());
+        assert_eq!([0x03, 0x04, 0, 0], map.region("plain").unwrap().data());
+        assert_eq!(0x0102_0304, map.read_value_at::<u32>(0x1002, Endian::Big).unwrap());
+
+        map.region_mut("plain").unwrap().set_word_size(2).unwrap();
+        assert_eq!(0x0102_0403, map.read_value_at::<u32>(0x1002, Endian::Big).unwrap());
+        let error = map.write_value_at(0x1006, 0u32, Endian::Big).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnmappedAddress { addr: 0x1008 }), ReversedWordsError::from_io(&error));
+    }
+
+    #[test]
+    fn unmapped_and_overlapping() {
+        let mut map = n64();
+        let error = map.read_value_at::<u32>(0x8000_0010, Endian::Big).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnmappedAddress { addr: 0x8000_0010 }), ReversedWordsError::from_io(&error));
+        assert!(map.write_value_at(0x8000_000E, 0u32, Endian::Big).is_err());
+        assert_eq!([0, 0], map.region("rdram").unwrap().data()[..2]);
+        assert_eq!(
+            Err(ReversedWordsError::OverlappingRegion { name: "mirror".to_string(), other: "rdram".to_string() }),
+            map.add_region(Region::zeroed("mirror", 0x8000_0008, 4, 0x10).unwrap())
+        );
+    }
+
+    #[test]
+    fn read_only_regions() {
+        let mut map = MemoryMap::new()
+            .with_region(Region::zeroed("ram", 0x1000, 4, 4).unwrap())
+            .unwrap()
+            .with_region(Region::zeroed("rom", 0x1004, 4, 4).unwrap().with_read_only(true))
+            .unwrap();
+        let error = map.write_value_at(0x1002, 0xFFFF_FFFFu32, Endian::Big).unwrap_err();
+        assert_eq!(std::io::ErrorKind::PermissionDenied, error.kind());
+        assert_eq!(
+            Some(&ReversedWordsError::ReadOnly { addr: 0x1004, region: "rom".to_string() }),
+            ReversedWordsError::from_io(&error)
+        );
+        assert_eq!([0; 4], map.region("ram").unwrap().data());
+        assert_eq!(2, map.write_at(0x1000, &[1, 2]).unwrap());
+
+        map.patch_session().write_value_at(0x1004, 0xBEEFu16, Endian::Big).unwrap();
+        assert_eq!(0xBEEF, map.read_value_at::<u16>(0x1004, Endian::Big).unwrap());
+        assert!(map.write_at(0x1007, &[0]).is_err());
+    }
+
+    #[test]
+    fn guards_catch_stray_accesses() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut map = n64().with_guard("null page", 0..0x1000).with_guard("rdram tail", 0x8000_000C..0x8000_0010);
+        map = map.with_guard_events(sender);
+        let error = map.read_value_at::<u32>(0x10, Endian::Big).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::GuardHit { addr: 0x10, guard: "null page".to_string() }),
+            ReversedWordsError::from_io(&error)
+        );
+        assert!(map.write_value_at(0x8000_000A, 0u32, Endian::Big).is_err());
+        map.write_value_at(0x8000_0008, 1u32, Endian::Big).unwrap();
+        let error = map.read_at(u64::MAX, &mut [0; 4]).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnmappedAddress { addr: u64::MAX }), ReversedWordsError::from_io(&error));
+        let hits: Vec<GuardHit> = receiver.try_iter().collect();
+        assert_eq!(
+            vec![
+                GuardHit { guard: "null page".to_string(), addr: 0x10, write: false },
+                GuardHit { guard: "rdram tail".to_string(), addr: 0x8000_000C, write: true },
+            ],
+            hits
+        );
+    }
+
+    #[test]
+    fn tracked_checksums_follow_writes() {
+        use crate::checksum::{ByteSum, Crc32};
+
+        let mut map = MemoryMap::new().with_region(Region::new("save", 0, 2, (0..32).collect()).unwrap()).unwrap();
+        let crc = map.track_checksum("block", 0..16, Arc::new(Crc32), Some((28, Endian::Big))).unwrap();
+        let mut block = [0u8; 16];
+        map.read_at(0, &mut block).unwrap();
+        assert_eq!(Crc32.checksum(&block), crc);
+        assert_eq!(crc, map.read_value_at::<u32>(28, Endian::Big).unwrap());
+        // the field itself is covered by a checksum of the rest
+        map.track_checksum("file", 16..32, Arc::new(ByteSum), None).unwrap();
+
+        map.write_value_at(14, 0xDEAD_BEEFu32, Endian::Big).unwrap();
+        map.read_at(0, &mut block).unwrap();
+        let crc = Crc32.checksum(&block);
+        assert_eq!(Some(crc), map.checksum("block"));
+        assert_eq!(crc, map.read_value_at::<u32>(28, Endian::Big).unwrap());
+        let mut file = [0u8; 16];
+        map.read_at(16, &mut file).unwrap();
+        assert_eq!(Some(ByteSum.checksum(&file)), map.checksum("file"));
+
+        let error = map.track_checksum("bad", 0..8, Arc::new(Crc32), Some((6, Endian::Big))).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::ChecksumFieldInRange { name: "bad".to_string(), addr: 6 }),
+            ReversedWordsError::from_io(&error)
+        );
+        assert!(map.track_checksum("unmapped", 16..40, Arc::new(Crc32), None).is_err());
+        assert_eq!(Some(crc), map.untrack_checksum("block").map(|tracked| tracked.value()));
+    }
+
+    #[test]
+    fn checksum_cycles_are_rejected() {
+        use crate::checksum::{ByteSum, Crc32};
+
+        let mut map = MemoryMap::new().with_region(Region::new("save", 0, 2, (0..48).collect()).unwrap()).unwrap();
+        map.track_checksum("a", 0..16, Arc::new(Crc32), Some((16, Endian::Big))).unwrap();
+        map.track_checksum("b", 16..32, Arc::new(ByteSum), Some((32, Endian::Big))).unwrap();
+        // storing c changes a, storing a changes b, and b is stored inside c
+        let error = map.track_checksum("c", 32..48, Arc::new(Crc32), Some((4, Endian::Big))).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::ChecksumCycle { name: "c".to_string(), through: "b".to_string() }),
+            ReversedWordsError::from_io(&error)
+        );
+        // two checksums covering each other's fields directly
+        let error = map.track_checksum("d", 16..24, Arc::new(Crc32), Some((0, Endian::Big))).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::ChecksumCycle { name: "d".to_string(), through: "a".to_string() }),
+            ReversedWordsError::from_io(&error)
+        );
+        // nothing was stored or tracked by the rejected calls
+        assert_eq!(2, map.checksums().len());
+        map.write_at(0, &[1, 2, 3]).unwrap();
+        let mut block = [0u8; 16];
+        map.read_at(16, &mut block).unwrap();
+        assert_eq!(Some(ByteSum.checksum(&block)), map.checksum("b"));
+    }
+
+    #[test]
+    fn checksum_fields_must_stay_writable() {
+        use crate::checksum::Crc32;
+
+        let mut map = MemoryMap::new()
+            .with_region(Region::new("save", 0, 2, (0..32).collect()).unwrap())
+            .unwrap()
+            .with_region(Region::zeroed("rom", 0x100, 1, 0x10).unwrap().with_read_only(true))
+            .unwrap()
+            .with_guard("mmio", 0x108..0x10C);
+        let error = map.track_checksum("block", 0..16, Arc::new(Crc32), Some((0x100, Endian::Big))).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::ReadOnly { addr: 0x100, region: "rom".to_string() }),
+            ReversedWordsError::from_io(&error)
+        );
+        let error = map.track_checksum("block", 0..16, Arc::new(Crc32), Some((0x10A, Endian::Big))).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::GuardHit { addr: 0x10A, guard: "mmio".to_string() }),
+            ReversedWordsError::from_io(&error)
+        );
+        assert!(map.track_checksum("block", 0..16, Arc::new(Crc32), Some((0x10E, Endian::Big))).is_err());
+        #[allow(clippy::reversed_empty_ranges)]
+        let error = map.track_checksum("block", 16..0, Arc::new(Crc32), None).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::ReversedRange { start: 16, end: 0 }), ReversedWordsError::from_io(&error));
+        assert!(map.checksums().is_empty());
+
+        // a read-only range with its field read-only too only changes inside a patch session
+        assert!(map.track_checksum("rom", 0x100..0x104, Arc::new(Crc32), Some((0x104, Endian::Big))).is_err());
+        assert!(map.checksums().is_empty());
+        map.patch_session().track_checksum("rom", 0x100..0x104, Arc::new(Crc32), Some((0x104, Endian::Big))).unwrap();
+        assert!(map.write_at(0x100, &[1]).is_err());
+        map.patch_session().write_at(0x100, &[1]).unwrap();
+        let mut block = [0u8; 4];
+        map.read_at(0x100, &mut block).unwrap();
+        assert_eq!(Crc32.checksum(&block), map.read_value_at::<u32>(0x104, Endian::Big).unwrap());
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn build_from_toml_and_yaml() {
+        let toml = r#"
+            [[segments]]
+            name = "rdram"
+            base = 0x80000000
+            size = 0x10
+            word_size = 4
+
+            [[segments]]
+            name = "pif_ram"
+            base = 0x1FC007C0
+            size = 0x40
+
+            [[labels]]
+            name = "gPlayerHealth"
+            addr = 0x80000004
+            size = 2
+            type = "u16"
+            comment = "current health"
+        "#;
+        let (map, labels) = MemoryMapDescription::from_toml(toml).unwrap().build().unwrap();
+        assert_eq!(n64(), map);
+        let label = labels.get("gPlayerHealth").unwrap();
+        assert_eq!(0x8000_0004..0x8000_0006, label.range);
+        assert_eq!(Some(crate::expr::ValueType::U16), label.value_type);
+
+        let yaml = "
+segments:
+  - { name: rdram, base: 0x80000000, size: 0x10, word_size: 4 }
+  - { name: pif_ram, base: 0x1FC007C0, size: 0x40 }
+labels:
+  - { name: gFrameCount, addr: 0x80000000, size: 4, type: u24 }
+";
+        assert!(matches!(
+            MemoryMapDescription::from_yaml(yaml).unwrap().build(),
+            Err(ReversedWordsError::InvalidConfig { .. })
+        ));
+        assert!(MemoryMapDescription::from_toml("segments = 3").is_err());
+
+        for yaml in [
+            "segments: [{ name: huge, base: 0, size: 0x100000000000 }]",
+            "segments: [{ name: high, base: 0xFFFFFFFFFFFFFFF0, size: 0x20 }]",
+            "segments: []\nlabels: [{ name: high, addr: 0xFFFFFFFFFFFFFFF0, size: 0x20 }]",
+        ] {
+            assert!(matches!(
+                MemoryMapDescription::from_yaml(yaml).unwrap().build(),
+                Err(ReversedWordsError::InvalidConfig { .. })
+            ));
+        }
+    }
+}
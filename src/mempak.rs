@@ -0,0 +1,352 @@
+//! The N64 Controller Pak (mempak) filesystem, read and written through a swapped view.
+//!
+//! A pak is 32 KiB in 128 pages of 256 bytes. Page 0 holds the ID block (four copies, each with
+//! its own checksum), page 1 the inode table that chains each note's pages together and page 2
+//! a backup of it, pages 3 and 4 the 16 entry note table, and pages 5 to 127 note data. Dumps are
+//! often stored word swapped, so [`ControllerPak`] works on the logical bytes of a
+//! [`ReversedWords`] and never touches storage directly.
+//!
+//! Text in note names uses the pak's own font: digits, upper case letters and a little
+//! punctuation. Other characters can't be stored, and ones outside this set in existing notes
+//! read as spaces.
+
+use std::convert::TryInto;
+
+use crate::{ReversedWords, ReversedWordsError};
+
+pub const PAK_SIZE: u64 = 0x8000;
+pub const PAGE_SIZE: usize = 0x100;
+pub const PAGES: usize = 128;
+pub const FIRST_DATA_PAGE: usize = 5;
+pub const NOTES: usize = 16;
+
+const ID_BLOCKS: [u64; 4] = [0x20, 0x60, 0x80, 0xC0];
+const INODE_PAGE: u64 = 1;
+const INODE_BACKUP_PAGE: u64 = 2;
+const NOTE_TABLE: u64 = 3 * PAGE_SIZE as u64;
+const NOTE_ENTRY_SIZE: u64 = 32;
+
+const INODE_LAST: u16 = 0x0001;
+const INODE_FREE: u16 = 0x0003;
+const NOTE_VALID: u8 = 0x02;
+
+// the pak font's punctuation, starting at code 0x34
+const PUNCTUATION: &str = "!\"#'*+,-./:=?@";
+
+fn decode_char(code: u8) -> Option<char> {
+    match code {
+        0x00 => None,
+        0x10..=0x19 => Some((b'0' + code - 0x10) as char),
+        0x1A..=0x33 => Some((b'A' + code - 0x1A) as char),
+        0x34..=0x41 => PUNCTUATION.chars().nth((code - 0x34) as usize),
+        _ => Some(' '),
+    }
+}
+
+fn encode_char(c: char) -> Option<u8> {
+    match c.to_ascii_uppercase() {
+        ' ' => Some(0x0F),
+        c @ '0'..='9' => Some(c as u8 - b'0' + 0x10),
+        c @ 'A'..='Z' => Some(c as u8 - b'A' + 0x1A),
+        c => PUNCTUATION.chars().position(|p| p == c).map(|i| 0x34 + i as u8),
+    }
+}
+
+fn decode_text(codes: &[u8]) -> String {
+    codes.iter().map_while(|code| decode_char(*code)).collect()
+}
+
+fn encode_text(text: &str, len: usize) -> Result<Vec<u8>, ReversedWordsError> {
+    let unencodable = || ReversedWordsError::UnencodableText { text: text.to_string(), encoding: "the Controller Pak font" };
+    let mut codes = text.chars().map(encode_char).collect::<Option<Vec<u8>>>().ok_or_else(unencodable)?;
+    if codes.len() > len {
+        return Err(unencodable());
+    }
+    codes.resize(len, 0);
+    Ok(codes)
+}
+
+fn invalid(reason: &str) -> ReversedWordsError {
+    ReversedWordsError::InvalidMempak { reason: reason.to_string() }
+}
+
+fn id_block_checksums(block: &[u8]) -> (u16, u16) {
+    let sum = block[..0x1C].chunks(2).fold(0u16, |sum, pair| sum.wrapping_add(u16::from_be_bytes([pair[0], pair[1]])));
+    (sum, 0xFFF2u16.wrapping_sub(sum))
+}
+
+fn inode_checksum(table: &[u8]) -> u8 {
+    table[FIRST_DATA_PAGE * 2..].iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Who a note belongs to and what it is called.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteInfo {
+    /// The game's four character code, e.g. `NSME`.
+    pub game_code: [u8; 4],
+    /// The publisher's two character code, e.g. `01`.
+    pub publisher: [u8; 2],
+    /// Up to 16 characters.
+    pub name: String,
+    /// Up to 4 characters, usually empty.
+    pub extension: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Note {
+    /// The note's slot in the note table, from 0 to 15.
+    pub index: usize,
+    pub info: NoteInfo,
+    pub start_page: u8,
+    /// How many pages the note's data takes.
+    pub pages: usize,
+}
+
+/// A Controller Pak filesystem over the first [`PAK_SIZE`] logical bytes of a view.
+pub struct ControllerPak<'w, 'a> {
+    words: &'w mut ReversedWords<'a>,
+    inodes: [u16; PAGES],
+}
+
+impl<'w, 'a> ControllerPak<'w, 'a> {
+    fn read(words: &ReversedWords, addr: u64, len: usize) -> std::io::Result<Vec<u8>> {
+        let mut bytes = vec![0u8; len];
+        if words.read_at(addr, &mut bytes)? < len {
+            return Err(words.out_of_range(addr + len as u64 - 1));
+        }
+        Ok(bytes)
+    }
+
+    fn write(&mut self, addr: u64, bytes: &[u8]) -> std::io::Result<()> {
+        if self.words.write_at(addr, bytes)? < bytes.len() {
+            return Err(self.words.out_of_range(addr + bytes.len() as u64 - 1));
+        }
+        Ok(())
+    }
+
+    /// Open the filesystem in `words`. At least one ID block copy must be intact, and the inode
+    /// table or its backup must match its checksum; a bad primary table is replaced by the
+    /// backup on the next write.
+    pub fn open(words: &'w mut ReversedWords<'a>) -> std::io::Result<ControllerPak<'w, 'a>> {
+        let page0 = ControllerPak::read(words, 0, PAGE_SIZE)?;
+        let id_valid = ID_BLOCKS.iter().any(|offset| {
+            let block = &page0[*offset as usize..*offset as usize + 0x20];
+            let (sum, complement) = id_block_checksums(block);
+            block[0x1C..0x1E] == sum.to_be_bytes() && block[0x1E..0x20] == complement.to_be_bytes()
+        });
+        if !id_valid {
+            return Err(invalid("no intact ID block").into());
+        }
+        let mut table = None;
+        for page in [INODE_PAGE, INODE_BACKUP_PAGE] {
+            let candidate = ControllerPak::read(words, page * PAGE_SIZE as u64, PAGE_SIZE)?;
+            if candidate[1] == inode_checksum(&candidate) {
+                table = Some(candidate);
+                break;
+            }
+        }
+        let table = table.ok_or_else(|| invalid("inode table and its backup are both corrupt"))?;
+        let mut inodes = [0u16; PAGES];
+        for (inode, pair) in inodes.iter_mut().zip(table.chunks(2)) {
+            *inode = u16::from_be_bytes([pair[0], pair[1]]);
+        }
+        Ok(ControllerPak { words, inodes })
+    }
+
+    /// Write an empty filesystem to `words` and open it.
+    pub fn format(words: &'w mut ReversedWords<'a>) -> std::io::Result<ControllerPak<'w, 'a>> {
+        if words.len() < PAK_SIZE {
+            return Err(words.out_of_range(PAK_SIZE - 1));
+        }
+        let mut page0 = vec![0u8; PAGE_SIZE];
+        let mut block = [0u8; 0x20];
+        // device id 1, one bank
+        block[0x19] = 0x01;
+        block[0x1A] = 0x01;
+        let (sum, complement) = id_block_checksums(&block);
+        block[0x1C..0x1E].copy_from_slice(&sum.to_be_bytes());
+        block[0x1E..0x20].copy_from_slice(&complement.to_be_bytes());
+        for offset in ID_BLOCKS {
+            page0[offset as usize..offset as usize + 0x20].copy_from_slice(&block);
+        }
+        words.write_at(0, &page0)?;
+        words.write_at(NOTE_TABLE, &[0u8; NOTES * NOTE_ENTRY_SIZE as usize])?;
+        let mut inodes = [INODE_FREE; PAGES];
+        inodes[..FIRST_DATA_PAGE].iter_mut().for_each(|inode| *inode = 0);
+        let mut pak = ControllerPak { words, inodes };
+        pak.write_inodes()?;
+        Ok(pak)
+    }
+
+    // write the inode table and its backup
+    fn write_inodes(&mut self) -> std::io::Result<()> {
+        let mut table: Vec<u8> = self.inodes.iter().flat_map(|inode| inode.to_be_bytes()).collect();
+        table[1] = inode_checksum(&table);
+        self.write(INODE_PAGE * PAGE_SIZE as u64, &table)?;
+        self.write(INODE_BACKUP_PAGE * PAGE_SIZE as u64, &table)
+    }
+
+    /// Pages not used by any note.
+    pub fn free_pages(&self) -> usize {
+        self.inodes[FIRST_DATA_PAGE..].iter().filter(|inode| **inode == INODE_FREE).count()
+    }
+
+    // the pages of the chain starting at `start`, in order
+    fn chain(&self, start: u8) -> Result<Vec<u8>, ReversedWordsError> {
+        let mut pages = Vec::new();
+        let mut page = start as usize;
+        loop {
+            if !(FIRST_DATA_PAGE..PAGES).contains(&page) || pages.len() >= PAGES - FIRST_DATA_PAGE {
+                return Err(invalid("note's page chain is broken"));
+            }
+            pages.push(page as u8);
+            match self.inodes[page] {
+                INODE_LAST => return Ok(pages),
+                next => page = next as usize,
+            }
+        }
+    }
+
+    fn entry(&self, index: usize) -> std::io::Result<Vec<u8>> {
+        ControllerPak::read(self.words, NOTE_TABLE + index as u64 * NOTE_ENTRY_SIZE, NOTE_ENTRY_SIZE as usize)
+    }
+
+    /// The notes in the note table, in slot order.
+    pub fn notes(&self) -> std::io::Result<Vec<Note>> {
+        let mut notes = Vec::new();
+        for index in 0..NOTES {
+            let entry = self.entry(index)?;
+            if entry[0x08] & NOTE_VALID == 0 {
+                continue;
+            }
+            let start_page = entry[0x07];
+            notes.push(Note {
+                index,
+                info: NoteInfo {
+                    game_code: entry[0..4].try_into().expect("4 bytes"),
+                    publisher: entry[4..6].try_into().expect("2 bytes"),
+                    extension: decode_text(&entry[0x0C..0x10]),
+                    name: decode_text(&entry[0x10..0x20]),
+                },
+                start_page,
+                pages: self.chain(start_page)?.len(),
+            });
+        }
+        Ok(notes)
+    }
+
+    fn note(&self, index: usize) -> std::io::Result<Note> {
+        self.notes()?.into_iter().find(|note| note.index == index).ok_or_else(|| invalid("no note in that slot").into())
+    }
+
+    /// The data of the note in slot `index`, a whole number of pages.
+    pub fn read_note(&self, index: usize) -> std::io::Result<Vec<u8>> {
+        let note = self.note(index)?;
+        let mut data = Vec::with_capacity(note.pages * PAGE_SIZE);
+        for page in self.chain(note.start_page)? {
+            data.extend(ControllerPak::read(self.words, page as u64 * PAGE_SIZE as u64, PAGE_SIZE)?);
+        }
+        Ok(data)
+    }
+
+    /// Store `data` (padded with zeros to whole pages) as a new note in the first free slot,
+    /// returning the slot. Fails with [`ReversedWordsError::NoteTableFull`] or
+    /// [`ReversedWordsError::MempakFull`] if there isn't room.
+    pub fn insert_note(&mut self, info: &NoteInfo, data: &[u8]) -> std::io::Result<usize> {
+        let name = encode_text(&info.name, 16)?;
+        let extension = encode_text(&info.extension, 4)?;
+        let used: Vec<usize> = self.notes()?.iter().map(|note| note.index).collect();
+        let index = (0..NOTES).find(|index| !used.contains(index)).ok_or(ReversedWordsError::NoteTableFull)?;
+        let needed = data.len().div_ceil(PAGE_SIZE).max(1);
+        let pages: Vec<usize> = (FIRST_DATA_PAGE..PAGES).filter(|page| self.inodes[*page] == INODE_FREE).take(needed).collect();
+        if pages.len() < needed {
+            return Err(ReversedWordsError::MempakFull { pages: needed, free: pages.len() }.into());
+        }
+
+        for (i, page) in pages.iter().enumerate() {
+            let mut chunk = data.get(i * PAGE_SIZE..).unwrap_or_default().iter().copied().take(PAGE_SIZE).collect::<Vec<u8>>();
+            chunk.resize(PAGE_SIZE, 0);
+            self.write(*page as u64 * PAGE_SIZE as u64, &chunk)?;
+            self.inodes[*page] = pages.get(i + 1).map_or(INODE_LAST, |next| *next as u16);
+        }
+        self.write_inodes()?;
+
+        let mut entry = vec![0u8; NOTE_ENTRY_SIZE as usize];
+        entry[0..4].copy_from_slice(&info.game_code);
+        entry[4..6].copy_from_slice(&info.publisher);
+        entry[0x07] = pages[0] as u8;
+        entry[0x08] = NOTE_VALID;
+        entry[0x0C..0x10].copy_from_slice(&extension);
+        entry[0x10..0x20].copy_from_slice(&name);
+        self.write(NOTE_TABLE + index as u64 * NOTE_ENTRY_SIZE, &entry)?;
+        Ok(index)
+    }
+
+    /// Delete the note in slot `index`, freeing its pages.
+    pub fn delete_note(&mut self, index: usize) -> std::io::Result<()> {
+        let note = self.note(index)?;
+        for page in self.chain(note.start_page)? {
+            self.inodes[page as usize] = INODE_FREE;
+        }
+        self.write_inodes()?;
+        self.write(NOTE_TABLE + index as u64 * NOTE_ENTRY_SIZE, &[0u8; NOTE_ENTRY_SIZE as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mempak::*, ReversedWords};
+
+    fn info(name: &str) -> NoteInfo {
+        NoteInfo { game_code: *b"NSME", publisher: *b"01", name: name.to_string(), extension: String::new() }
+    }
+
+    #[test]
+    fn notes_round_trip_through_a_swapped_pak() {
+        let mut dump = vec![0u8; PAK_SIZE as usize];
+        let mut words = ReversedWords::new(&mut dump);
+        let mut pak = ControllerPak::format(&mut words).unwrap();
+        assert_eq!(123, pak.free_pages());
+        let data: Vec<u8> = (0..600).map(|i| i as u8).collect();
+        assert_eq!(0, pak.insert_note(&info("Mario 64"), &data).unwrap());
+        assert_eq!(1, pak.insert_note(&info("ghost"), &[0xAA]).unwrap());
+        assert_eq!(119, pak.free_pages());
+
+        let mut pak = ControllerPak::open(&mut words).unwrap();
+        let notes = pak.notes().unwrap();
+        assert_eq!(2, notes.len());
+        assert_eq!(Note { index: 0, info: info("MARIO 64"), start_page: 5, pages: 3 }, notes[0]);
+        assert_eq!(info("GHOST"), notes[1].info);
+        assert_eq!(data, pak.read_note(0).unwrap()[..600]);
+        assert_eq!(768, pak.read_note(0).unwrap().len());
+
+        pak.delete_note(0).unwrap();
+        assert_eq!(122, pak.free_pages());
+        // the freed pages are reused first
+        assert_eq!(0, pak.insert_note(&info("AGAIN"), &[1; 300]).unwrap());
+        assert_eq!(5, pak.notes().unwrap()[0].start_page);
+        assert!(pak.read_note(2).is_err());
+        assert!(pak.insert_note(&info("lowercase é"), &[]).is_err());
+        let error = pak.insert_note(&info("BIG"), &[0; 200 * PAGE_SIZE]).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::MempakFull { pages: 200, free: 120 }), ReversedWordsError::from_io(&error));
+
+        // the name is stored swapped, in the pak font: "AGAI" is 1A 20 1A 22
+        assert_eq!([0x22, 0x1A, 0x20, 0x1A], dump[0x310..0x314]);
+    }
+
+    #[test]
+    fn falls_back_to_the_inode_backup() {
+        let mut dump = vec![0u8; PAK_SIZE as usize];
+        let mut words = ReversedWords::new(&mut dump);
+        ControllerPak::format(&mut words).unwrap().insert_note(&info("SAVE"), &[7; 10]).unwrap();
+        words.write_at(0x100 + 20, &[0xFF]).unwrap();
+        let pak = ControllerPak::open(&mut words).unwrap();
+        assert_eq!(1, pak.notes().unwrap().len());
+
+        words.write_at(0x200 + 20, &[0xFF]).unwrap();
+        assert!(ControllerPak::open(&mut words).is_err());
+        let mut blank = vec![0u8; PAK_SIZE as usize];
+        let error = ControllerPak::open(&mut ReversedWords::new(&mut blank)).err().unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+    }
+}
@@ -0,0 +1,173 @@
+//! A scripted [`MemoryBackend`] for testing tools that drive remote memory.
+//!
+//! A [`MockBackend`] is given the storage-order reads and writes it should see, in order, and
+//! what to answer each with. Any call that doesn't match the next expectation panics with both
+//! sides, which fails the test at the point the tool went wrong instead of much later on wrong
+//! data; so does dropping the backend with expectations left over.
+//!
+//! Expectations are of the calls the backend itself receives: a [`crate::backend::ReversedBackend`]
+//! reads and writes whole words, and reads a partly covered word before writing it.
+//!
+//! ```
+//! # use reversed_word_byte_rw::{backend::ReversedBackend, mock::MockBackend, Endian};
+//! let mock = MockBackend::new(8).expect_read(4, &[0x78, 0x56, 0x34, 0x12]).expect_write(0, &[0, 0, 0, 1]);
+//! let mut words = ReversedBackend::new(mock, 4).unwrap();
+//! assert_eq!(0x1234_5678, words.read_value_at::<u32>(4, Endian::Big).unwrap());
+//! words.write_value_at(0, 0x0100_0000u32, Endian::Big).unwrap();
+//! words.into_inner().finish();
+//! ```
+
+use std::{collections::VecDeque, io::ErrorKind};
+
+use crate::backend::MemoryBackend;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expected {
+    /// A read of `data.len()` bytes at `offset`, answered with `data` or failing with `error`.
+    Read { offset: u64, data: Vec<u8>, error: Option<ErrorKind> },
+    /// A write of `data` at `offset`, failing with `error` if set.
+    Write { offset: u64, data: Vec<u8>, error: Option<ErrorKind> },
+}
+
+pub struct MockBackend {
+    len: u64,
+    script: VecDeque<Expected>,
+    calls: usize,
+}
+
+impl MockBackend {
+    /// A backend of `len` bytes expecting no calls yet.
+    pub fn new(len: u64) -> MockBackend {
+        MockBackend { len, script: VecDeque::new(), calls: 0 }
+    }
+
+    /// Expect a read of `data.len()` bytes at `offset` and answer it with `data`.
+    pub fn expect_read(mut self, offset: u64, data: &[u8]) -> MockBackend {
+        self.script.push_back(Expected::Read { offset, data: data.to_vec(), error: None });
+        self
+    }
+
+    /// Expect a read of `len` bytes at `offset` and fail it with `kind`.
+    pub fn expect_read_error(mut self, offset: u64, len: usize, kind: ErrorKind) -> MockBackend {
+        self.script.push_back(Expected::Read { offset, data: vec![0; len], error: Some(kind) });
+        self
+    }
+
+    /// Expect exactly `data` to be written at `offset`.
+    pub fn expect_write(mut self, offset: u64, data: &[u8]) -> MockBackend {
+        self.script.push_back(Expected::Write { offset, data: data.to_vec(), error: None });
+        self
+    }
+
+    /// Expect `data` to be written at `offset` and fail the write with `kind`.
+    pub fn expect_write_error(mut self, offset: u64, data: &[u8], kind: ErrorKind) -> MockBackend {
+        self.script.push_back(Expected::Write { offset, data: data.to_vec(), error: Some(kind) });
+        self
+    }
+
+    /// How many expectations haven't been met yet.
+    pub fn remaining(&self) -> usize {
+        self.script.len()
+    }
+
+    /// Panic unless every expectation has been met.
+    pub fn finish(mut self) {
+        self.check_done();
+        self.script.clear();
+    }
+
+    fn check_done(&self) {
+        if let Some(next) = self.script.front() {
+            panic!("mock backend: {} expected calls not made, the next being {:?}", self.script.len(), next);
+        }
+    }
+
+    fn next(&mut self, actual: &str) -> Expected {
+        self.calls += 1;
+        match self.script.pop_front() {
+            Some(expected) => expected,
+            None => panic!("mock backend: unexpected call {} after the script ended: {}", self.calls, actual),
+        }
+    }
+}
+
+impl MemoryBackend for MockBackend {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let actual = format!("read of {} bytes at {:#x}", buf.len(), offset);
+        match self.next(&actual) {
+            Expected::Read { offset: expected, data, error } if expected == offset && data.len() == buf.len() => {
+                if let Some(kind) = error {
+                    return Err(kind.into());
+                }
+                buf.copy_from_slice(&data);
+                Ok(data.len())
+            }
+            expected => panic!("mock backend: call {} was a {}, expected {:?}", self.calls, actual, expected),
+        }
+    }
+
+    fn write_raw(&mut self, offset: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let actual = format!("write of {:02x?} at {:#x}", buf, offset);
+        match self.next(&actual) {
+            Expected::Write { offset: expected, data, error } if expected == offset && data == buf => match error {
+                Some(kind) => Err(kind.into()),
+                None => Ok(buf.len()),
+            },
+            expected => panic!("mock backend: call {} was a {}, expected {:?}", self.calls, actual, expected),
+        }
+    }
+}
+
+impl Drop for MockBackend {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.check_done();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::{ReversedBackend, RetryBackend, RetryPolicy},
+        mock::*,
+        Endian,
+    };
+
+    #[test]
+    fn scripted_calls_are_answered() {
+        let mock = MockBackend::new(8)
+            .expect_read_error(0, 4, ErrorKind::TimedOut)
+            .expect_read(0, &[1, 2, 3, 4])
+            .expect_read(4, &[0; 4])
+            .expect_write(4, &[0, 0, 0xAA, 0]);
+        let policy = RetryPolicy::default().with_backoff(std::time::Duration::ZERO, std::time::Duration::ZERO);
+        let mut words = ReversedBackend::new(RetryBackend::new(mock, policy), 4).unwrap();
+        assert_eq!(0x0403, words.read_value_at::<u16>(0, Endian::Big).unwrap());
+        assert_eq!(1, words.backend().retries());
+        words.write_value_at(5, 0xAAu8, Endian::Big).unwrap();
+        assert_eq!(0, words.backend().backend().remaining());
+        words.into_inner().into_inner().finish();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected Write")]
+    fn deviations_panic() {
+        let mut mock = MockBackend::new(8).expect_write(0, &[1, 2, 3, 4]);
+        let _ = mock.write_raw(0, &[1, 2, 3, 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected calls not made")]
+    fn unmet_expectations_panic() {
+        MockBackend::new(8).expect_read(0, &[0; 4]).finish();
+    }
+}
@@ -0,0 +1,147 @@
+//! Live emulator memory through the mupen64plus core's debugger API.
+//!
+//! A frontend or plugin looks up `DebugMemGetPointer`, `DebugMemRead8` and `DebugMemWrite8` in
+//! the core library (the same way it finds `CoreDoCommand`) and hands them to
+//! [`DebugMemory::new`]. RDRAM itself is stored in host order, so [`DebugMemory::rdram`] wraps
+//! it in a 4-byte swapped [`ReversedWords`]. The byte accessors go through the core and take
+//! virtual addresses, which the core translates (including the TLB) and returns in N64 order.
+
+use std::{
+    convert::TryFrom,
+    os::raw::{c_int, c_uint, c_void},
+};
+
+use binread::Endian;
+
+use crate::{watch::WatchSource, Primitive, ReversedWords};
+
+/// `m64p_dbg_memptr_type::M64P_DBG_PTR_RDRAM`
+const M64P_DBG_PTR_RDRAM: c_int = 1;
+
+/// RDRAM size with the Expansion Pak.
+pub const RDRAM_SIZE: usize = 0x80_0000;
+
+pub type DebugMemGetPointer = unsafe extern "C" fn(c_int) -> *mut c_void;
+pub type DebugMemRead8 = unsafe extern "C" fn(c_uint) -> u8;
+pub type DebugMemWrite8 = unsafe extern "C" fn(c_uint, u8);
+
+/// The mupen64plus debugger memory functions.
+#[derive(Clone, Copy)]
+pub struct DebugMemory {
+    get_pointer: DebugMemGetPointer,
+    read8: DebugMemRead8,
+    write8: DebugMemWrite8,
+}
+
+impl DebugMemory {
+    /// # Safety
+    ///
+    /// The functions must be the core's debugger functions (or have the same behaviour), and the
+    /// core must stay loaded for as long as the `DebugMemory` is used.
+    pub unsafe fn new(get_pointer: DebugMemGetPointer, read8: DebugMemRead8, write8: DebugMemWrite8) -> DebugMemory {
+        DebugMemory { get_pointer, read8, write8 }
+    }
+
+    /// A view over the emulator's RDRAM, `None` if the core hasn't allocated it yet.
+    ///
+    /// # Safety
+    ///
+    /// `len` must not exceed the core's RDRAM size (4 MiB without the Expansion Pak), and the
+    /// emulator must not touch RDRAM while the view is alive, for example by only using it while
+    /// the core is paused or from a debugger callback.
+    pub unsafe fn rdram(&mut self, len: usize) -> Option<ReversedWords<'_>> {
+        let pointer = (self.get_pointer)(M64P_DBG_PTR_RDRAM) as *mut u8;
+        if pointer.is_null() {
+            return None;
+        }
+        Some(ReversedWords::new(std::slice::from_raw_parts_mut(pointer, len)))
+    }
+
+    /// Read the bytes starting at virtual address `addr`, returning how many were below 4 GiB.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> usize {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let Ok(addr) = c_uint::try_from(addr + i as u64) else { return i };
+            // SAFETY: guaranteed by the caller of `new`
+            *byte = unsafe { (self.read8)(addr) };
+        }
+        buf.len()
+    }
+
+    /// Write bytes starting at virtual address `addr`, returning how many were below 4 GiB.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> usize {
+        for (i, byte) in buf.iter().enumerate() {
+            let Ok(addr) = c_uint::try_from(addr + i as u64) else { return i };
+            // SAFETY: guaranteed by the caller of `new`
+            unsafe { (self.write8)(addr, *byte) };
+        }
+        buf.len()
+    }
+
+    pub fn read_value_at<T: Primitive>(&self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        if self.read_at(addr, bytes.as_mut()) < bytes.as_ref().len() {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        let bytes = value.to_bytes(endian);
+        if self.write_at(addr, bytes.as_ref()) < bytes.as_ref().len() {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+/// Watches virtual addresses of the running emulator.
+impl WatchSource for DebugMemory {
+    fn len(&self) -> u64 {
+        1 << 32
+    }
+
+    fn read_watched(&self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.read_at(addr, buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicPtr, Ordering};
+
+    use crate::mupen64plus::*;
+
+    // a fake core: 16 bytes of host order RDRAM at KSEG0
+    static RDRAM: AtomicPtr<u8> = AtomicPtr::new(std::ptr::null_mut());
+
+    fn rdram() -> &'static mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(RDRAM.load(Ordering::SeqCst), 16) }
+    }
+
+    unsafe extern "C" fn get_pointer(kind: c_int) -> *mut c_void {
+        assert_eq!(M64P_DBG_PTR_RDRAM, kind);
+        RDRAM.load(Ordering::SeqCst) as *mut c_void
+    }
+
+    unsafe extern "C" fn read8(addr: c_uint) -> u8 {
+        let offset = usize::try_from(addr - 0x8000_0000).unwrap();
+        rdram()[offset ^ 3]
+    }
+
+    unsafe extern "C" fn write8(addr: c_uint, value: u8) {
+        let offset = usize::try_from(addr - 0x8000_0000).unwrap();
+        rdram()[offset ^ 3] = value;
+    }
+
+    #[test]
+    fn core_accessors_and_rdram_view_agree() {
+        RDRAM.store(Box::leak(vec![0u8; 16].into_boxed_slice()).as_mut_ptr(), Ordering::SeqCst);
+        let mut memory = unsafe { DebugMemory::new(get_pointer, read8, write8) };
+        memory.write_value_at(0x8000_0006, 0xBEEFu16, Endian::Big).unwrap();
+        let mut rdram = unsafe { memory.rdram(16) }.unwrap();
+        assert_eq!(0xBEEF, rdram.read_u16_at(6, Endian::Big).unwrap());
+        rdram.write_u32_at(8, 0x1234_5678, Endian::Big).unwrap();
+        assert_eq!(0x1234_5678u32, memory.read_value_at::<u32>(0x8000_0008, Endian::Big).unwrap());
+        assert_eq!(0, memory.read_at(1 << 32, &mut [0u8; 4]));
+    }
+}
@@ -0,0 +1,150 @@
+//! Converting N64 cartridge saves (EEPROM, SRAM, FlashRAM) between emulator byte orders.
+//!
+//! Saves are the same logical bytes everywhere, but emulators write them with different word
+//! swapping: Mupen64Plus (and flashcarts) store them in cartridge order, while Project64 stores
+//! SRAM and FlashRAM as 32-bit little endian words. Moving a save between them without swapping
+//! corrupts it.
+
+use std::path::Path;
+
+use crate::{ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveType {
+    /// 4 kbit EEPROM, `.eep`
+    Eeprom4k,
+    /// 16 kbit EEPROM, `.eep`
+    Eeprom16k,
+    /// 256 kbit SRAM, `.sra`
+    Sram,
+    /// 1 Mbit FlashRAM, `.fla`
+    FlashRam,
+}
+
+impl SaveType {
+    /// Size of the save in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            SaveType::Eeprom4k => 0x200,
+            SaveType::Eeprom16k => 0x800,
+            SaveType::Sram => 0x8000,
+            SaveType::FlashRam => 0x20000,
+        }
+    }
+
+    pub fn from_len(len: usize) -> Option<SaveType> {
+        [SaveType::Eeprom4k, SaveType::Eeprom16k, SaveType::Sram, SaveType::FlashRam]
+            .iter()
+            .copied()
+            .find(|save_type| save_type.size() == len)
+    }
+
+    /// Detect the save type from a file's extension and length.
+    pub fn detect<P: AsRef<Path>>(path: P, len: usize) -> Option<SaveType> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match (extension.as_str(), SaveType::from_len(len)?) {
+            ("eep", save_type @ (SaveType::Eeprom4k | SaveType::Eeprom16k)) => Some(save_type),
+            ("sra", SaveType::Sram) => Some(SaveType::Sram),
+            ("fla", SaveType::FlashRam) => Some(SaveType::FlashRam),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveLayout {
+    /// Cartridge order, as written by Mupen64Plus and flashcarts.
+    CartridgeOrder,
+    /// Project64: EEPROM in cartridge order, SRAM and FlashRAM as 32-bit little endian words.
+    Project64,
+    /// Every `n` bytes reversed, for emulators not covered above.
+    Swapped(u8),
+}
+
+impl SaveLayout {
+    /// The word size saves of `save_type` are stored with in this layout.
+    pub fn word_size(self, save_type: SaveType) -> u8 {
+        match (self, save_type) {
+            (SaveLayout::CartridgeOrder, _) => 1,
+            (SaveLayout::Project64, SaveType::Eeprom4k | SaveType::Eeprom16k) => 1,
+            (SaveLayout::Project64, SaveType::Sram | SaveType::FlashRam) => 4,
+            (SaveLayout::Swapped(word_size), _) => word_size,
+        }
+    }
+}
+
+/// Rewrite `data` stored with `from_word_size` swapping in place so it is stored with `to_word_size`.
+///
+/// Fails with [`ReversedWordsError::Misaligned`] if `data` isn't a whole number of words of
+/// either size, as the bytes of a trailing partial word have no logical position.
+pub fn convert_word_size(data: &mut [u8], from_word_size: u8, to_word_size: u8) -> Result<(), ReversedWordsError> {
+    let len = data.len() as u64;
+    for word_size in [from_word_size, to_word_size] {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        if !len.is_multiple_of(word_size as u64) {
+            return Err(ReversedWordsError::Misaligned { position: len, word_size });
+        }
+    }
+    let logical: Vec<u8> = ReversedWords::new_with_word_size(data, from_word_size).bytes(0..len).collect();
+    for (word, logical) in data.chunks_exact_mut(to_word_size as usize).zip(logical.chunks_exact(to_word_size as usize)) {
+        word.copy_from_slice(logical);
+        word.reverse();
+    }
+    Ok(())
+}
+
+/// Convert a save of `save_type` in place from one emulator's layout to another's.
+pub fn convert_save(data: &mut [u8], save_type: SaveType, from: SaveLayout, to: SaveLayout) -> Result<(), ReversedWordsError> {
+    convert_word_size(data, from.word_size(save_type), to.word_size(save_type))
+}
+
+/// A view of the save's logical (cartridge order) contents.
+pub fn save_view(data: &mut [u8], save_type: SaveType, layout: SaveLayout) -> Result<ReversedWords<'_>, ReversedWordsError> {
+    ReversedWords::try_new_with_word_size(data, layout.word_size(save_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::n64save::*;
+
+    #[test]
+    fn detect_save_types() {
+        assert_eq!(Some(SaveType::Sram), SaveType::detect("game.SRA", 0x8000));
+        assert_eq!(Some(SaveType::Eeprom16k), SaveType::detect("game.eep", 0x800));
+        assert_eq!(None, SaveType::detect("game.eep", 0x8000));
+        assert_eq!(None, SaveType::detect("game.bin", 0x8000));
+    }
+
+    #[test]
+    fn project64_sram_to_mupen64plus() {
+        let mut data: Vec<u8> = (0..0x8000).map(|i| i as u8).collect();
+        convert_save(&mut data, SaveType::Sram, SaveLayout::Project64, SaveLayout::CartridgeOrder).unwrap();
+        assert_eq!([3, 2, 1, 0, 7, 6, 5, 4], data[..8]);
+        convert_save(&mut data, SaveType::Sram, SaveLayout::CartridgeOrder, SaveLayout::Project64).unwrap();
+        assert_eq!([0, 1, 2, 3, 4, 5, 6, 7], data[..8]);
+    }
+
+    #[test]
+    fn eeprom_is_unswapped_for_project64() {
+        let mut data: Vec<u8> = (0..0x200).map(|i| i as u8).collect();
+        convert_save(&mut data, SaveType::Eeprom4k, SaveLayout::Project64, SaveLayout::CartridgeOrder).unwrap();
+        assert_eq!([0, 1, 2, 3], data[..4]);
+        convert_save(&mut data, SaveType::Eeprom4k, SaveLayout::Swapped(8), SaveLayout::CartridgeOrder).unwrap();
+        assert_eq!([7, 6, 5, 4], data[..4]);
+        let view = save_view(&mut data, SaveType::Eeprom4k, SaveLayout::CartridgeOrder).unwrap();
+        assert_eq!(7, view.get_byte(0).unwrap());
+    }
+
+    #[test]
+    fn partial_words_are_an_error() {
+        let mut data: Vec<u8> = (0..10).collect();
+        assert_eq!(Err(ReversedWordsError::Misaligned { position: 10, word_size: 4 }), convert_word_size(&mut data, 1, 4));
+        assert_eq!(Err(ReversedWordsError::Misaligned { position: 10, word_size: 8 }), convert_word_size(&mut data, 8, 2));
+        assert_eq!(Err(ReversedWordsError::InvalidWordSize), convert_word_size(&mut data, 2, 0));
+        assert_eq!((0..10).collect::<Vec<u8>>(), data);
+        convert_word_size(&mut data, 2, 1).unwrap();
+        assert_eq!(vec![1, 0, 3, 2, 5, 4, 7, 6, 9, 8], data);
+    }
+}
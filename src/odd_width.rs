@@ -0,0 +1,128 @@
+//! 3 and 6 byte integers, as packed into game structures and audio and graphics data.
+//!
+//! There are no Rust types of these widths, so they are read into the next wider one. The bytes
+//! are taken from the logical stream like any other value, so they may straddle words.
+
+use binread::Endian;
+
+use crate::{ReversedWords, ReversedWordsError};
+
+fn little(endian: Endian) -> bool {
+    match endian {
+        Endian::Big => false,
+        Endian::Little => true,
+        Endian::Native => cfg!(target_endian = "little"),
+    }
+}
+
+fn from_bytes<const N: usize>(bytes: [u8; N], endian: Endian) -> u64 {
+    let fold = |value: u64, byte: &u8| value << 8 | *byte as u64;
+    if little(endian) {
+        bytes.iter().rev().fold(0, fold)
+    } else {
+        bytes.iter().fold(0, fold)
+    }
+}
+
+fn to_bytes<const N: usize>(value: u64, endian: Endian) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (value >> (8 * i)) as u8;
+    }
+    if !little(endian) {
+        bytes.reverse();
+    }
+    bytes
+}
+
+// sign extend the low `bits` bits of `value`
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    ((value << (64 - bits)) as i64) >> (64 - bits)
+}
+
+fn check_fits(value: i128, bits: u32, signed: bool) -> Result<(), ReversedWordsError> {
+    let (min, max) = if signed { (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1) } else { (0, (1i128 << bits) - 1) };
+    if value < min || value > max {
+        return Err(ReversedWordsError::ValueOutOfRange { value, bits });
+    }
+    Ok(())
+}
+
+impl ReversedWords<'_> {
+    /// Read the 3 byte unsigned integer at `addr`, without moving the cursor.
+    pub fn read_u24_at(&self, addr: u64, endian: Endian) -> std::io::Result<u32> {
+        Ok(from_bytes(self.read_array::<3>(addr)?, endian) as u32)
+    }
+
+    /// Write a 3 byte unsigned integer at `addr`. Fails without writing anything if `value`
+    /// doesn't fit in 24 bits or the bytes aren't all in range.
+    pub fn write_u24_at(&mut self, addr: u64, value: u32, endian: Endian) -> std::io::Result<()> {
+        check_fits(value as i128, 24, false)?;
+        self.write_array(addr, to_bytes::<3>(value as u64, endian))
+    }
+
+    /// Read the 3 byte two's complement integer at `addr`, sign extended.
+    pub fn read_i24_at(&self, addr: u64, endian: Endian) -> std::io::Result<i32> {
+        Ok(sign_extend(from_bytes(self.read_array::<3>(addr)?, endian), 24) as i32)
+    }
+
+    /// Write a 3 byte two's complement integer at `addr`, failing if `value` is outside
+    /// `-0x80_0000..=0x7F_FFFF`.
+    pub fn write_i24_at(&mut self, addr: u64, value: i32, endian: Endian) -> std::io::Result<()> {
+        check_fits(value as i128, 24, true)?;
+        self.write_array(addr, to_bytes::<3>(value as u64, endian))
+    }
+
+    /// Read the 6 byte unsigned integer at `addr`.
+    pub fn read_u48_at(&self, addr: u64, endian: Endian) -> std::io::Result<u64> {
+        Ok(from_bytes(self.read_array::<6>(addr)?, endian))
+    }
+
+    /// Write a 6 byte unsigned integer at `addr`, failing if `value` doesn't fit in 48 bits.
+    pub fn write_u48_at(&mut self, addr: u64, value: u64, endian: Endian) -> std::io::Result<()> {
+        check_fits(value as i128, 48, false)?;
+        self.write_array(addr, to_bytes::<6>(value, endian))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{odd_width::*, ReversedWords};
+
+    #[test]
+    fn odd_widths_across_words() {
+        let mut data = vec![0u8; 12];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_u24_at(3, 0x12_3456, Endian::Big).unwrap();
+        assert_eq!([0x12, 0x34, 0x56], [words.get_byte(3).unwrap(), words.get_byte(4).unwrap(), words.get_byte(5).unwrap()]);
+        assert_eq!(0x12_3456, words.read_u24_at(3, Endian::Big).unwrap());
+        assert_eq!(0x56_3412, words.read_u24_at(3, Endian::Little).unwrap());
+
+        words.write_i24_at(5, -2, Endian::Little).unwrap();
+        assert_eq!(-2, words.read_i24_at(5, Endian::Little).unwrap());
+        assert_eq!(0xFF_FFFE, words.read_u24_at(5, Endian::Little).unwrap());
+        words.write_i24_at(5, 0x7F_FFFF, Endian::Big).unwrap();
+        assert_eq!(0x7F_FFFF, words.read_i24_at(5, Endian::Big).unwrap());
+        words.write_i24_at(5, -0x80_0000, Endian::Big).unwrap();
+        assert_eq!(-0x80_0000, words.read_i24_at(5, Endian::Big).unwrap());
+
+        words.write_u48_at(6, 0xA1A2_A3A4_A5A6, Endian::Big).unwrap();
+        assert_eq!(0xA1A2_A3A4_A5A6, words.read_u48_at(6, Endian::Big).unwrap());
+        assert_eq!(0xA6A5_A4A3_A2A1, words.read_u48_at(6, Endian::Little).unwrap());
+        assert_eq!([0x12, 0, 0, 0, 0xA2, 0xA1, 0x80, 0x34, 0xA6, 0xA5, 0xA4, 0xA3], data[..]);
+    }
+
+    #[test]
+    fn odd_widths_reject_what_doesnt_fit() {
+        let mut data = vec![0u8; 8];
+        let mut words = ReversedWords::new(&mut data);
+        let error = words.write_u24_at(0, 0x100_0000, Endian::Big).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::ValueOutOfRange { value: 0x100_0000, bits: 24 }), ReversedWordsError::from_io(&error));
+        assert!(words.write_i24_at(0, 0x80_0000, Endian::Big).is_err());
+        assert!(words.write_i24_at(0, -0x80_0001, Endian::Big).is_err());
+        assert!(words.write_u48_at(0, 1 << 48, Endian::Big).is_err());
+        assert!(words.write_u48_at(3, 1, Endian::Big).is_err());
+        assert!(words.read_u24_at(6, Endian::Big).is_err());
+        assert_eq!([0; 8], data[..]);
+    }
+}
@@ -0,0 +1,828 @@
+//! Serde (de)serialization of structs directly against the logical byte stream.
+//!
+//! Fields are read and written sequentially with no padding, using fixed-width
+//! integers in the requested endianness. This matches how most game state is laid
+//! out in RAM, so a `#[derive(Serialize, Deserialize)]` struct mirroring the in-memory
+//! layout (with explicit padding fields where the original has them) can be loaded
+//! straight out of a dump.
+//!
+//! Encoding rules:
+//! - integers and floats are their fixed-width representation in the chosen endianness
+//! - `bool` is one byte, `0` or `1`
+//! - `char` is a `u32` scalar value
+//! - `Option` is a one byte tag followed by the value if the tag is `1`
+//! - enums are a `u32` variant index followed by the variant's fields
+//! - tuples, arrays and structs are their elements in order
+//! - strings, byte buffers, sequences and maps are prefixed with a `u32` length
+//!
+//! The format is not self-describing, so `deserialize_any` is unsupported.
+//!
+//! A [`StructWatch`] polls a struct through any [`WatchSource`] and reports the old and new
+//! decoded struct along with the names of the fields that changed.
+
+use std::{fmt::{self, Display}, io::{Read, Seek, SeekFrom, Write}};
+use binread::Endian;
+use serde::{de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor}, ser::{self, Serialize}};
+
+use crate::{watch::WatchSource, Primitive, ReversedWords};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Message(String),
+    InvalidBool(u8),
+    InvalidChar(u32),
+    InvalidOptionTag(u8),
+    UnknownLength,
+    LengthOverflow(usize),
+    Unsupported(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Message(m) => f.write_str(m),
+            Error::InvalidBool(b) => write!(f, "invalid bool byte {:#04x}", b),
+            Error::InvalidChar(c) => write!(f, "invalid char value {:#010x}", c),
+            Error::InvalidOptionTag(t) => write!(f, "invalid option tag {:#04x}", t),
+            Error::UnknownLength => f.write_str("sequences must have a known length"),
+            Error::LengthOverflow(len) => write!(f, "length {} does not fit in a u32 prefix", len),
+            Error::Unsupported(what) => write!(f, "{} is not supported by the overlay format", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+/// Deserialize a `T` from the logical bytes starting at `addr`.
+pub fn load_at<T: DeserializeOwned>(words: &mut ReversedWords, addr: u64, endian: Endian) -> Result<T> {
+    words.seek(SeekFrom::Start(addr))?;
+    from_reader(words, endian)
+}
+
+/// Serialize `value` into the logical bytes starting at `addr`.
+pub fn store_at<T: Serialize + ?Sized>(words: &mut ReversedWords, addr: u64, value: &T, endian: Endian) -> Result<()> {
+    words.seek(SeekFrom::Start(addr))?;
+    to_writer(words, value, endian)
+}
+
+/// Deserialize a `T` from the current position of any reader.
+pub fn from_reader<R: Read, T: DeserializeOwned>(reader: R, endian: Endian) -> Result<T> {
+    let mut deserializer = Deserializer::new(reader, endian);
+    T::deserialize(&mut deserializer)
+}
+
+/// Serialize `value` at the current position of any writer.
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T, endian: Endian) -> Result<()> {
+    let mut serializer = Serializer::new(writer, endian);
+    value.serialize(&mut serializer)
+}
+
+pub struct Serializer<W> {
+    writer: W,
+    endian: Endian,
+}
+
+impl<W: Write> Serializer<W> {
+    pub fn new(writer: W, endian: Endian) -> Self {
+        Serializer { writer, endian }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_len(&mut self, len: usize) -> Result<()> {
+        if len > u32::MAX as usize {
+            return Err(Error::LengthOverflow(len));
+        }
+        self.writer.write_all(&(len as u32).to_bytes(self.endian))?;
+        Ok(())
+    }
+}
+
+macro_rules! serialize_primitive {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<()> {
+                self.writer.write_all(v.to_bytes(self.endian).as_ref())?;
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<W: Write> ser::Serializer for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    serialize_primitive!(
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64, serialize_i128: i128,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64, serialize_u128: u128,
+        serialize_f32: f32, serialize_f64: f64
+    );
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.serialize_u8(v as u8)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_len(v.len())?;
+        self.writer.write_all(v)?;
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_u8(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        self.serialize_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str) -> Result<()> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, variant_index: u32, _variant: &'static str, value: &T) -> Result<()> {
+        self.serialize_u32(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        self.write_len(len.ok_or(Error::UnknownLength)?)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+        self.write_len(len.ok_or(Error::UnknownLength)?)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! serialize_compound {
+    ($($trait:ident::$method:ident),*) => {
+        $(
+            impl<W: Write> ser::$trait for &mut Serializer<W> {
+                type Ok = ();
+                type Error = Error;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<()> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+serialize_compound!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+impl<W: Write> ser::SerializeMap for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Deserializer<R> {
+    reader: R,
+    endian: Endian,
+}
+
+impl<R: Read> Deserializer<R> {
+    pub fn new(reader: R, endian: Endian) -> Self {
+        Deserializer { reader, endian }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_primitive<T: Primitive>(&mut self) -> Result<T> {
+        let mut bytes = T::Bytes::default();
+        self.reader.read_exact(bytes.as_mut())?;
+        Ok(T::from_bytes(bytes, self.endian))
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        self.read_primitive()
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        self.read_primitive()
+    }
+
+    fn read_vec(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_u32()? as u64;
+        // the length comes from the data, so only allocate for the bytes that are really there
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut bytes)?;
+        if (bytes.len() as u64) < len {
+            return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        Ok(bytes)
+    }
+}
+
+macro_rules! deserialize_primitive {
+    ($($method:ident: $ty:ty => $visit:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.$visit(self.read_primitive::<$ty>()?)
+            }
+        )*
+    };
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    deserialize_primitive!(
+        deserialize_i8: i8 => visit_i8, deserialize_i16: i16 => visit_i16, deserialize_i32: i32 => visit_i32,
+        deserialize_i64: i64 => visit_i64, deserialize_i128: i128 => visit_i128,
+        deserialize_u8: u8 => visit_u8, deserialize_u16: u16 => visit_u16, deserialize_u32: u32 => visit_u32,
+        deserialize_u64: u64 => visit_u64, deserialize_u128: u128 => visit_u128,
+        deserialize_f32: f32 => visit_f32, deserialize_f64: f64 => visit_f64
+    );
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Unsupported("deserialize_any"))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.read_u8()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            b => Err(Error::InvalidBool(b)),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = self.read_u32()?;
+        match char::from_u32(value) {
+            Some(c) => visitor.visit_char(c),
+            None => Err(Error::InvalidChar(value)),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let bytes = self.read_vec()?;
+        match String::from_utf8(bytes) {
+            Ok(s) => visitor.visit_string(s),
+            Err(e) => Err(de::Error::custom(e)),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.read_vec()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.read_u8()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            t => Err(Error::InvalidOptionTag(t)),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_u32()? as usize;
+        visitor.visit_seq(Fields { deserializer: self, remaining: len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Fields { deserializer: self, remaining: len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_u32()? as usize;
+        visitor.visit_map(Fields { deserializer: self, remaining: len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Unsupported("deserialize_identifier"))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Unsupported("deserialize_ignored_any"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Access to a known number of consecutive elements, shared by sequences, tuples, structs and maps.
+struct Fields<'d, R> {
+    deserializer: &'d mut Deserializer<R>,
+    remaining: usize,
+}
+
+impl<'de, 'd, R: Read> de::SeqAccess<'de> for Fields<'d, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, 'd, R: Read> de::MapAccess<'de> for Fields<'d, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.deserializer)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, R: Read> de::EnumAccess<'de> for &mut Deserializer<R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index = self.read_u32()?;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+// reads a watch source sequentially from `position`
+struct SourceReader<'s, S: ?Sized> {
+    source: &'s S,
+    position: u64,
+}
+
+impl<S: WatchSource + ?Sized> Read for SourceReader<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.source.read_watched(self.position, buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+// each field of a struct with its encoding
+type FieldEncodings = Vec<(&'static str, Vec<u8>)>;
+
+// collects each field of a struct serialized on its own
+struct FieldSplitter {
+    endian: Endian,
+    fields: FieldEncodings,
+}
+
+macro_rules! not_a_struct {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method(self, _v: $ty) -> Result<()> {
+                Err(Error::Unsupported("watching values other than structs"))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for &mut FieldSplitter {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = ser::Impossible<(), Error>;
+    type SerializeTuple = ser::Impossible<(), Error>;
+    type SerializeTupleStruct = ser::Impossible<(), Error>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    not_a_struct!(
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64,
+        serialize_f32: f32, serialize_f64: f64, serialize_bool: bool, serialize_char: char,
+        serialize_str: &str, serialize_bytes: &[u8], serialize_unit_struct: &'static str
+    );
+
+    fn serialize_none(self) -> Result<()> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<()> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str) -> Result<()> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<()> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant> {
+        Err(Error::Unsupported("watching values other than structs"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeStruct for &mut FieldSplitter {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        let mut bytes = Vec::new();
+        to_writer(&mut bytes, value, self.endian)?;
+        self.fields.push((key, bytes));
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A watched struct that differed between two polls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructChange<T> {
+    pub addr: u64,
+    pub old: T,
+    pub new: T,
+    /// Names of the struct's fields whose encoding changed, in declaration order. Changes inside
+    /// nested structs, arrays and enums are reported as their outermost field.
+    pub fields: Vec<&'static str>,
+}
+
+impl<T> StructChange<T> {
+    pub fn changed(&self, field: &str) -> bool {
+        self.fields.contains(&field)
+    }
+}
+
+/// Polls a `#[derive(Serialize, Deserialize)]` struct laid out at an address, like
+/// [`Watcher`](crate::watch::Watcher) does for byte ranges.
+pub struct StructWatch<T> {
+    addr: u64,
+    endian: Endian,
+    last: Option<(T, FieldEncodings)>,
+}
+
+impl<T: Serialize + DeserializeOwned> StructWatch<T> {
+    pub fn new(addr: u64, endian: Endian) -> StructWatch<T> {
+        StructWatch { addr, endian, last: None }
+    }
+
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    /// The struct as of the last poll.
+    pub fn last(&self) -> Option<&T> {
+        self.last.as_ref().map(|(value, _)| value)
+    }
+
+    /// Decode the struct and compare it field by field with the previous poll. The first poll
+    /// only records it.
+    pub fn poll<S: WatchSource + ?Sized>(&mut self, source: &S) -> Result<Option<StructChange<T>>> {
+        let new: T = from_reader(SourceReader { source, position: self.addr }, self.endian)?;
+        let mut splitter = FieldSplitter { endian: self.endian, fields: Vec::new() };
+        new.serialize(&mut splitter)?;
+        let Some((old, old_fields)) = self.last.replace((new, splitter.fields)) else {
+            return Ok(None);
+        };
+        let new_fields = &self.last.as_ref().expect("just replaced").1;
+        let fields: Vec<&'static str> = old_fields
+            .iter()
+            .zip(new_fields)
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((name, _), _)| *name)
+            .collect();
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        // decode a second copy for the change rather than requiring `T: Clone`
+        let bytes: Vec<u8> = new_fields.iter().flat_map(|(_, bytes)| bytes.iter().copied()).collect();
+        let new = from_reader(&bytes[..], self.endian)?;
+        Ok(Some(StructChange { addr: self.addr, old, new, fields }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use crate::*;
+    use crate::overlay::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Player {
+        health: u16,
+        lives: u8,
+        alive: bool,
+        position: [f32; 2],
+        state: State,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum State {
+        Idle,
+        Jumping { height: i16 },
+    }
+
+    #[test]
+    fn load_struct_at_unaligned_address() {
+        // logical layout: 2 bytes padding, health (BE u16 0x0102), lives 3, alive 1
+        let mut data: Vec<u8> = vec![0x02, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03];
+        let mut ram = ReversedWords::new(&mut data);
+        let (health, lives, alive): (u16, u8, bool) = load_at(&mut ram, 2, Endian::Big).unwrap();
+        assert_eq!((0x0102, 3, true), (health, lives, alive));
+    }
+
+    #[test]
+    fn store_then_load_round_trip() {
+        let player = Player {
+            health: 0x1234,
+            lives: 3,
+            alive: true,
+            position: [1.5, -2.0],
+            state: State::Jumping { height: -7 },
+        };
+        let mut data = vec![0u8; 32];
+        let mut ram = ReversedWords::new(&mut data);
+        store_at(&mut ram, 3, &player, Endian::Big).unwrap();
+        let loaded: Player = load_at(&mut ram, 3, Endian::Big).unwrap();
+        assert_eq!(player, loaded);
+
+        // health is stored big-endian at logical 3 and 4, which land in storage words 0 and 1
+        assert_eq!(0x12, data[0]);
+        assert_eq!(0x34, data[7]);
+    }
+
+    #[test]
+    fn struct_watch_names_changed_fields() {
+        let player = Player { health: 100, lives: 3, alive: true, position: [0.0, 0.0], state: State::Idle };
+        let mut data = vec![0u8; 32];
+        store_at(&mut ReversedWords::new(&mut data), 4, &player, Endian::Big).unwrap();
+        let mut watch = StructWatch::<Player>::new(4, Endian::Big);
+        assert_eq!(None, watch.poll(&ReversedWords::new(&mut data)).unwrap());
+        assert_eq!(None, watch.poll(&ReversedWords::new(&mut data)).unwrap());
+
+        let mut ram = ReversedWords::new(&mut data);
+        ram.write_u16_at(4, 90, Endian::Big).unwrap();
+        store_at(&mut ram, 16, &State::Jumping { height: 5 }, Endian::Big).unwrap();
+        let change = watch.poll(&ram).unwrap().unwrap();
+        assert_eq!(vec!["health", "state"], change.fields);
+        assert!(change.changed("health") && !change.changed("lives"));
+        assert_eq!((100, 90), (change.old.health, change.new.health));
+        assert_eq!(State::Jumping { height: 5 }, change.new.state);
+        assert_eq!(Some(&change.new), watch.last());
+
+        let mut tuple = StructWatch::<(u8, u8)>::new(0, Endian::Big);
+        assert!(matches!(tuple.poll(&ram), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn invalid_bool_is_an_error() {
+        let mut data = vec![2u8, 0, 0, 0];
+        let mut ram = ReversedWords::new(&mut data);
+        let result: Result<bool> = load_at(&mut ram, 3, Endian::Little);
+        assert!(matches!(result, Err(Error::InvalidBool(2))));
+    }
+
+    #[test]
+    fn lengths_past_the_end_are_an_error() {
+        // a string claiming 4 GiB with only 4 bytes after it
+        let mut data = vec![0xFF, 0xFF, 0xFF, 0xFF, b'l', b'l', b'e', b'h'];
+        let mut ram = ReversedWords::new(&mut data);
+        let result: Result<String> = load_at(&mut ram, 0, Endian::Little);
+        assert!(matches!(result, Err(Error::Io(error)) if error.kind() == std::io::ErrorKind::UnexpectedEof));
+        let mut data = vec![0, 0, 0, 4, b'l', b'l', b'e', b'h'];
+        let mut ram = ReversedWords::new(&mut data);
+        assert_eq!("hell", load_at::<String>(&mut ram, 0, Endian::Little).unwrap());
+    }
+}
@@ -0,0 +1,286 @@
+//! A view that owns its buffer.
+
+use std::{
+    convert::TryFrom,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+use crate::{ReversedWords, ReversedWordsError, ReversedWordsRef};
+
+/// How [`ReversedVec::truncate`] and [`ReversedVec::resize`] treat a length that isn't a whole
+/// number of words. The bytes of a trailing partial word can't be reached through the view.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PartialWordPolicy {
+    /// Round the length up to the next word boundary. This is the default.
+    #[default]
+    RoundUp,
+    /// Round the length down, dropping the partial word.
+    RoundDown,
+    /// Fail with [`ReversedWordsError::Misaligned`], leaving the buffer unchanged.
+    Reject,
+}
+
+/// [`ReversedWords`] over an owned `Vec<u8>`.
+///
+/// By default it behaves exactly like a view over a slice. With [`ReversedVec::with_growth`],
+/// writes past the end grow the buffer (zero filled, rounded up to whole words) instead of
+/// stopping short, for assembling a swapped image from pieces of unknown total size.
+/// [`ReversedVec::truncate`], [`ReversedVec::resize`] and [`ReversedVec::set_len_words`] change
+/// the length directly, keeping it to whole words under the [`PartialWordPolicy`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReversedVec {
+    data: Vec<u8>,
+    word_size: u8,
+    position: u64,
+    growable: bool,
+    partial_word_policy: PartialWordPolicy,
+}
+
+impl ReversedVec {
+    pub fn new(data: Vec<u8>) -> ReversedVec {
+        ReversedVec { data, word_size: 4, position: 0, growable: false, partial_word_policy: PartialWordPolicy::default() }
+    }
+
+    pub fn try_new_with_word_size(data: Vec<u8>, word_size: u8) -> Result<ReversedVec, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        Ok(ReversedVec { word_size, ..ReversedVec::new(data) })
+    }
+
+    pub fn with_partial_word_policy(mut self, policy: PartialWordPolicy) -> ReversedVec {
+        self.partial_word_policy = policy;
+        self
+    }
+
+    pub fn partial_word_policy(&self) -> PartialWordPolicy {
+        self.partial_word_policy
+    }
+
+    /// Let writes past the end grow the buffer.
+    pub fn with_growth(mut self, growable: bool) -> ReversedVec {
+        self.growable = growable;
+        self
+    }
+
+    pub fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    /// Reinterpret the data with a different word size, keeping the logical position.
+    pub fn set_word_size(&mut self, word_size: u8) -> Result<(), ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        self.word_size = word_size;
+        Ok(())
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// The data in storage order.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// A read-only view of the data, with cursors that don't move this one.
+    pub fn as_ref_view(&self) -> ReversedWordsRef<'_> {
+        ReversedWordsRef::try_new_with_word_size(&self.data, self.word_size).expect("word size is never 0")
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// A borrowed view of the data, positioned at the current position.
+    pub fn words(&mut self) -> ReversedWords<'_> {
+        let position = self.position;
+        let mut words = ReversedWords::new_with_word_size(&mut self.data, self.word_size);
+        words.cursor.set_position(position);
+        words
+    }
+
+    // zero fill up to `end` rounded up to a whole word
+    fn grow_to(&mut self, end: u64) {
+        let word_size = self.word_size as u64;
+        let end = end.div_ceil(word_size) * word_size;
+        if end > self.len() {
+            self.data.resize(end as usize, 0);
+        }
+    }
+
+    // `len` made whole words under the policy
+    fn whole_words(&self, len: u64) -> Result<u64, ReversedWordsError> {
+        let word_size = self.word_size as u64;
+        if len.is_multiple_of(word_size) {
+            return Ok(len);
+        }
+        match self.partial_word_policy {
+            PartialWordPolicy::RoundUp => len
+                .checked_next_multiple_of(word_size)
+                .ok_or(ReversedWordsError::AddressOutOfRange { addr: len, len: self.len() }),
+            PartialWordPolicy::RoundDown => Ok(len - len % word_size),
+            PartialWordPolicy::Reject => Err(ReversedWordsError::Misaligned { position: len, word_size: self.word_size }),
+        }
+    }
+
+    /// Shorten the buffer to `len` bytes, or to the word boundary the policy picks, keeping the
+    /// logical bytes before it. Does nothing if the buffer is no longer than that, and leaves the
+    /// position alone even if it ends up past the end.
+    pub fn truncate(&mut self, len: u64) -> Result<(), ReversedWordsError> {
+        if len >= self.len() {
+            return Ok(());
+        }
+        let len = self.whole_words(len)?.min(self.len());
+        self.data.truncate(len as usize);
+        Ok(())
+    }
+
+    /// Grow or shrink the buffer to `len` bytes, or to the word boundary the policy picks,
+    /// filling new bytes with `fill`.
+    pub fn resize(&mut self, len: u64, fill: u8) -> Result<(), ReversedWordsError> {
+        let len = self.whole_words(len)?;
+        let len = usize::try_from(len).map_err(|_| ReversedWordsError::AddressOutOfRange { addr: len, len: self.len() })?;
+        self.data.resize(len, fill);
+        Ok(())
+    }
+
+    /// Number of whole words in the buffer.
+    pub fn len_words(&self) -> u64 {
+        self.len() / self.word_size as u64
+    }
+
+    /// Grow or shrink the buffer to `words` whole words, zero filling new ones.
+    pub fn set_len_words(&mut self, words: u64) -> Result<(), ReversedWordsError> {
+        let len = words
+            .checked_mul(self.word_size as u64)
+            .ok_or(ReversedWordsError::AddressOutOfRange { addr: u64::MAX, len: self.len() })?;
+        self.resize(len, 0)
+    }
+
+    pub fn read_at(&mut self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.words().read_at(addr, buf)
+    }
+
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        if self.growable {
+            self.grow_to(addr + buf.len() as u64);
+        }
+        self.words().write_at(addr, buf)
+    }
+}
+
+/// Zeroes the whole buffer, including any spare capacity, and leaves it empty.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for ReversedVec {
+    fn zeroize(&mut self) {
+        self.data.zeroize();
+        self.position = 0;
+    }
+}
+
+impl Read for ReversedVec {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut words = self.words();
+        let n = words.read(buf)?;
+        self.position = words.position();
+        Ok(n)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> std::io::Result<usize> {
+        let mut words = self.words();
+        let n = words.read_to_end(buf)?;
+        self.position = words.position();
+        Ok(n)
+    }
+}
+
+impl Write for ReversedVec {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.growable {
+            self.grow_to(self.position + buf.len() as u64);
+        }
+        let mut words = self.words();
+        let n = words.write(buf)?;
+        self.position = words.position();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ReversedVec {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let mut words = self.words();
+        self.position = words.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::owned::*;
+
+    #[test]
+    fn fixed_size_by_default() {
+        let mut words = ReversedVec::new(vec![0u8; 4]);
+        words.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(2, words.write(&[1, 2, 3]).unwrap());
+        assert_eq!(4, words.len());
+        assert_eq!(vec![2, 1, 0, 0], words.into_inner());
+    }
+
+    #[test]
+    fn growth_rounds_to_whole_words() {
+        let mut words = ReversedVec::new(Vec::new()).with_growth(true);
+        words.write_all(&[0, 1, 2, 3, 4]).unwrap();
+        assert_eq!(8, words.len());
+        assert_eq!(5, words.position());
+        words.write_at(10, &[0xAA]).unwrap();
+        assert_eq!(12, words.len());
+
+        let mut out = [0u8; 5];
+        words.seek(SeekFrom::Start(0)).unwrap();
+        words.read_exact(&mut out).unwrap();
+        assert_eq!([0, 1, 2, 3, 4], out);
+        assert_eq!(vec![3, 2, 1, 0, 0, 0, 0, 4, 0, 0xAA, 0, 0], words.into_inner());
+    }
+
+    #[test]
+    fn resizing_keeps_whole_words() {
+        let mut words = ReversedVec::new((0..12).collect());
+        words.truncate(6).unwrap();
+        assert_eq!(8, words.len());
+        words.resize(10, 0xFF).unwrap();
+        assert_eq!(vec![0, 1, 2, 3, 4, 5, 6, 7, 0xFF, 0xFF, 0xFF, 0xFF], words.as_slice());
+        let mut out = [0u8; 4];
+        words.read_at(4, &mut out).unwrap();
+        assert_eq!([7, 6, 5, 4], out);
+
+        let mut words = words.with_partial_word_policy(PartialWordPolicy::RoundDown);
+        words.truncate(11).unwrap();
+        assert_eq!(8, words.len());
+        assert_eq!(2, words.len_words());
+        words.set_len_words(3).unwrap();
+        assert_eq!(vec![0, 1, 2, 3, 4, 5, 6, 7, 0, 0, 0, 0], words.as_slice());
+        assert!(words.set_len_words(u64::MAX).is_err());
+
+        let mut words = words.with_partial_word_policy(PartialWordPolicy::Reject);
+        assert_eq!(Err(ReversedWordsError::Misaligned { position: 5, word_size: 4 }), words.resize(5, 0));
+        assert_eq!(12, words.len());
+        words.truncate(4).unwrap();
+        assert_eq!(vec![0, 1, 2, 3], words.into_inner());
+    }
+}
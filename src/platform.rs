@@ -0,0 +1,224 @@
+//! Presets for common targets' RAM dumps.
+//!
+//! Each [`Platform`] knows how its RAM is usually dumped (word size), the byte order of its
+//! values and where the RAM sits in the CPU's address space. [`PlatformView`] wires these up so
+//! typed accessors take the addresses found in the game's code or a debugger:
+//!
+//! ```
+//! # use reversed_word_byte_rw::ReversedWords;
+//! let mut rdram = vec![0u8; 0x80_0000];
+//! let mut ram = ReversedWords::n64_rdram(&mut rdram);
+//! ram.write_u32(0x8037_1240, 100).unwrap();
+//! assert_eq!(100, ram.read_u32(0xA037_1240).unwrap());
+//! ```
+
+use std::ops::{Deref, DerefMut};
+
+use binread::Endian;
+
+use crate::{translate::AddressMap, Primitive, ReversedWords};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    /// N64 RDRAM as dumped by emulators on little endian hosts: 4-byte swapped, mapped through
+    /// KSEG0 (`0x80000000`) and KSEG1 (`0xA0000000`).
+    N64Rdram,
+    /// GameCube/Wii MEM1 as dumped by Dolphin: unswapped big endian, mapped at `0x80000000`
+    /// (cached) and `0xC0000000` (uncached).
+    GameCubeMem1,
+    /// Wii MEM2 as dumped by Dolphin: unswapped big endian, mapped at `0x90000000` (cached) and
+    /// `0xD0000000` (uncached).
+    WiiMem2,
+    /// Mega Drive/Genesis 68000 work RAM as most emulators store it: 2-byte swapped, at
+    /// `0xFF0000` and mirrored every 64 KiB down to `0xE00000`.
+    GenesisWorkRam,
+    /// PS2 EE RAM dumped in 128-bit quadwords: 16-byte swapped little endian values, at `0x0`,
+    /// with the uncached (`0x20000000`) and accelerated (`0x30000000`) mirrors.
+    Ps2EeRam,
+}
+
+impl Platform {
+    pub fn word_size(self) -> u8 {
+        match self {
+            Platform::N64Rdram => 4,
+            Platform::GameCubeMem1 | Platform::WiiMem2 => 1,
+            Platform::GenesisWorkRam => 2,
+            Platform::Ps2EeRam => 16,
+        }
+    }
+
+    /// Byte order of the target's values.
+    pub fn endian(self) -> Endian {
+        match self {
+            Platform::Ps2EeRam => Endian::Little,
+            _ => Endian::Big,
+        }
+    }
+
+    /// Where a dump of `len` bytes appears in the target's address space.
+    pub fn address_map(self, len: u64) -> AddressMap {
+        let segment = |base: u64| base..base + len;
+        match self {
+            Platform::N64Rdram => AddressMap::n64(len),
+            Platform::GameCubeMem1 => {
+                AddressMap::new().with_segment(segment(0x8000_0000), 0).with_segment(segment(0xC000_0000), 0)
+            }
+            Platform::WiiMem2 => {
+                AddressMap::new().with_segment(segment(0x9000_0000), 0).with_segment(segment(0xD000_0000), 0)
+            }
+            Platform::GenesisWorkRam => {
+                let len = len.min(0x1_0000);
+                let mut map = AddressMap::new().with_segment(0xFF_0000..0xFF_0000 + len, 0);
+                for mirror in (0xE0..0xFF).map(|bank| bank << 16) {
+                    map.add_segment(mirror..mirror + len, 0);
+                }
+                map
+            }
+            Platform::Ps2EeRam => AddressMap::new()
+                .with_segment(segment(0), 0)
+                .with_segment(segment(0x2000_0000), 0)
+                .with_segment(segment(0x3000_0000), 0),
+        }
+    }
+
+    /// A view over `ram` configured for this platform.
+    pub fn view(self, ram: &mut [u8]) -> PlatformView<'_> {
+        let address_map = self.address_map(ram.len() as u64);
+        let words = ReversedWords::new_with_word_size(ram, self.word_size()).with_endian(self.endian());
+        PlatformView { words, address_map, endian: self.endian() }
+    }
+}
+
+/// A view plus the address translation and value byte order of its platform.
+///
+/// The typed accessors here take virtual addresses and use the platform's byte order. The
+/// underlying [`ReversedWords`] (addressed from 0) is reachable through `Deref`.
+pub struct PlatformView<'a> {
+    words: ReversedWords<'a>,
+    address_map: AddressMap,
+    endian: Endian,
+}
+
+macro_rules! virtual_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            pub fn $read(&mut self, addr: u64) -> std::io::Result<$ty> {
+                self.read_value(addr)
+            }
+
+            pub fn $write(&mut self, addr: u64, value: $ty) -> std::io::Result<()> {
+                self.write_value(addr, value)
+            }
+        )*
+    };
+}
+
+impl<'a> PlatformView<'a> {
+    pub fn new(words: ReversedWords<'a>, address_map: AddressMap, endian: Endian) -> PlatformView<'a> {
+        PlatformView { words, address_map, endian }
+    }
+
+    pub fn address_map(&self) -> &AddressMap {
+        &self.address_map
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub fn into_inner(self) -> ReversedWords<'a> {
+        self.words
+    }
+
+    /// Offset into the view of virtual address `addr`.
+    pub fn offset_of(&self, addr: u64) -> std::io::Result<u64> {
+        Ok(self.address_map.translate(addr)?)
+    }
+
+    pub fn read_value<T: Primitive>(&mut self, addr: u64) -> std::io::Result<T> {
+        let offset = self.offset_of(addr)?;
+        self.words.read_value_at(offset, self.endian)
+    }
+
+    pub fn write_value<T: Primitive>(&mut self, addr: u64, value: T) -> std::io::Result<()> {
+        let offset = self.offset_of(addr)?;
+        self.words.write_value_at(offset, value, self.endian)
+    }
+
+    virtual_accessors! {
+        u8 => read_u8, write_u8;
+        u16 => read_u16, write_u16;
+        u32 => read_u32, write_u32;
+        u64 => read_u64, write_u64;
+        u128 => read_u128, write_u128;
+        i8 => read_i8, write_i8;
+        i16 => read_i16, write_i16;
+        i32 => read_i32, write_i32;
+        i64 => read_i64, write_i64;
+        i128 => read_i128, write_i128;
+        f32 => read_f32, write_f32;
+        f64 => read_f64, write_f64;
+    }
+}
+
+impl<'a> Deref for PlatformView<'a> {
+    type Target = ReversedWords<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.words
+    }
+}
+
+impl DerefMut for PlatformView<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.words
+    }
+}
+
+impl<'a> ReversedWords<'a> {
+    pub fn n64_rdram(ram: &'a mut [u8]) -> PlatformView<'a> {
+        Platform::N64Rdram.view(ram)
+    }
+
+    pub fn gamecube_mem1(ram: &'a mut [u8]) -> PlatformView<'a> {
+        Platform::GameCubeMem1.view(ram)
+    }
+
+    pub fn genesis_work_ram(ram: &'a mut [u8]) -> PlatformView<'a> {
+        Platform::GenesisWorkRam.view(ram)
+    }
+
+    pub fn ps2_ee_ram(ram: &'a mut [u8]) -> PlatformView<'a> {
+        Platform::Ps2EeRam.view(ram)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{platform::*, ReversedWordsError};
+
+    #[test]
+    fn n64_rdram_preset() {
+        let mut rdram = vec![0u8; 0x100];
+        let mut ram = ReversedWords::n64_rdram(&mut rdram);
+        ram.write_u16(0x8000_0010, 0x1234).unwrap();
+        assert_eq!(0x1234, ram.read_u16(0xA000_0010).unwrap());
+        assert_eq!(4, ram.word_size());
+        let error = ram.read_u8(0x8000_0100).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnmappedAddress { addr: 0x8000_0100 }), ReversedWordsError::from_io(&error));
+        assert_eq!([0x34, 0x12], rdram[0x12..0x14]);
+    }
+
+    #[test]
+    fn gamecube_and_genesis_presets() {
+        let mut mem1 = vec![0u8; 0x100];
+        ReversedWords::gamecube_mem1(&mut mem1).write_u32(0xC000_0020, 0xDEADBEEF).unwrap();
+        assert_eq!([0xDE, 0xAD, 0xBE, 0xEF], mem1[0x20..0x24]);
+
+        let mut work_ram = vec![0u8; 0x1_0000];
+        let mut ram = ReversedWords::genesis_work_ram(&mut work_ram);
+        ram.write_u16(0xFF_0100, 0xBEEF).unwrap();
+        assert_eq!(0xBEEF, ram.read_u16(0xE0_0100).unwrap());
+        assert_eq!([0xEF, 0xBE], work_ram[0x100..0x102]);
+    }
+}
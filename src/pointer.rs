@@ -0,0 +1,295 @@
+//! Finding words that look like pointers.
+//!
+//! A [`PointerScan`] reads every aligned pointer-sized value of a view and keeps the ones whose
+//! value is a virtual address inside a given range that the [`AddressMap`] maps into the view.
+//! Each [`PointerCandidate`] is where the pointer is stored (a view offset), its value and the
+//! view offset it points at, which is the raw material for pointer maps and pointer chains.
+//!
+//! A [`PointerPathFinder`] builds on that: given several captures of the same program, each with
+//! the address the value of interest was at in that capture, it finds chains of a pointer stored
+//! in a static region and offsets ([`PointerPath`]) that lead to the value in every capture.
+
+use std::{fmt, ops::Range};
+
+use binread::Endian;
+
+use crate::{translate::AddressMap, ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PointerCandidate {
+    /// View offset the pointer is stored at.
+    pub addr: u64,
+    /// The pointer, a virtual address.
+    pub value: u64,
+    /// View offset `value` translates to.
+    pub target: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointerScan {
+    pointer_size: u8,
+    endian: Endian,
+    step: u64,
+    region: Range<u64>,
+}
+
+impl PointerScan {
+    /// Scan for `pointer_size` byte pointers (4 or 8), big endian and naturally aligned.
+    pub fn new(pointer_size: u8) -> Result<PointerScan, ReversedWordsError> {
+        if pointer_size != 4 && pointer_size != 8 {
+            return Err(ReversedWordsError::InvalidPointerSize { size: pointer_size });
+        }
+        Ok(PointerScan { pointer_size, endian: Endian::Big, step: pointer_size as u64, region: 0..u64::MAX })
+    }
+
+    pub fn with_endian(mut self, endian: Endian) -> PointerScan {
+        self.endian = endian;
+        self
+    }
+
+    /// Look at values every `step` bytes instead of every pointer size, for targets that store
+    /// pointers unaligned. 0 is treated as 1.
+    pub fn with_step(mut self, step: u64) -> PointerScan {
+        self.step = step.max(1);
+        self
+    }
+
+    /// Only look for pointers stored inside `region`, a range of view offsets.
+    pub fn with_region(mut self, region: Range<u64>) -> PointerScan {
+        self.region = region;
+        self
+    }
+
+    pub fn pointer_size(&self) -> u8 {
+        self.pointer_size
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// The pointer stored at view offset `addr`, if a whole one is in range.
+    pub fn read_pointer(&self, words: &ReversedWords, addr: u64) -> Option<u64> {
+        let size = self.pointer_size as usize;
+        let mut bytes = [0u8; 8];
+        let end = addr.checked_add(size as u64)?;
+        let mut n = 0;
+        for (byte, value) in bytes.iter_mut().zip(words.bytes(addr..end)) {
+            *byte = value;
+            n += 1;
+        }
+        if n < size {
+            return None;
+        }
+        let bytes = &bytes[..size];
+        Some(match self.endian {
+            Endian::Little => bytes.iter().rev().fold(0, |value, byte| value << 8 | *byte as u64),
+            _ => bytes.iter().fold(0, |value, byte| value << 8 | *byte as u64),
+        })
+    }
+
+    /// Every stored value inside the virtual range `targets` that `map` translates to an offset
+    /// inside the view, in address order.
+    pub fn scan(&self, words: &ReversedWords, map: &AddressMap, targets: Range<u64>) -> Vec<PointerCandidate> {
+        let start = self.region.start;
+        let end = self.region.end.min(words.len());
+        let mut candidates = Vec::new();
+        let mut addr = start;
+        while addr < end {
+            if let Some(value) = self.read_pointer(words, addr).filter(|value| targets.contains(value)) {
+                if let Some(target) = map.translate(value).ok().filter(|target| *target < words.len()) {
+                    candidates.push(PointerCandidate { addr, value, target });
+                }
+            }
+            addr = match addr.checked_add(self.step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        candidates
+    }
+}
+
+/// A pointer chain: read the pointer at view offset `base`, add `offsets[0]` and read the pointer
+/// there, and so on; the last offset is added without reading.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PointerPath {
+    pub base: u64,
+    pub offsets: Vec<u64>,
+}
+
+impl PointerPath {
+    /// The view offset the path leads to in `words`, or `None` if a pointer along it is outside
+    /// the view or unmapped.
+    pub fn resolve(&self, scan: &PointerScan, words: &ReversedWords, map: &AddressMap) -> Option<u64> {
+        let mut addr = self.base;
+        for offset in &self.offsets {
+            let pointer = scan.read_pointer(words, addr)?;
+            addr = map.translate(pointer.checked_add(*offset)?).ok()?;
+        }
+        Some(addr)
+    }
+}
+
+impl fmt::Display for PointerPath {
+    /// `[[0x100]+0x10]+0x4` for a base of 0x100 and offsets 0x10 and 0x4.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for _ in &self.offsets {
+            write!(f, "[")?;
+        }
+        write!(f, "{:#x}", self.base)?;
+        for offset in &self.offsets {
+            write!(f, "]+{:#x}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Searches for [`PointerPath`]s to a value that moves between captures.
+#[derive(Clone, Debug)]
+pub struct PointerPathFinder {
+    scan: PointerScan,
+    map: AddressMap,
+    static_region: Range<u64>,
+    max_depth: usize,
+    max_offset: u64,
+    max_results: usize,
+}
+
+impl PointerPathFinder {
+    /// Find paths of up to 3 pointers with offsets up to 0x1000, with bases anywhere in the view.
+    pub fn new(scan: PointerScan, map: AddressMap) -> PointerPathFinder {
+        PointerPathFinder { scan, map, static_region: 0..u64::MAX, max_depth: 3, max_offset: 0x1000, max_results: 1000 }
+    }
+
+    /// Only accept bases stored inside `region` (view offsets), usually the program's data and
+    /// bss sections, which don't move between runs.
+    pub fn with_static_region(mut self, region: Range<u64>) -> PointerPathFinder {
+        self.static_region = region;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> PointerPathFinder {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The largest offset added to a pointer at any step.
+    pub fn with_max_offset(mut self, max_offset: u64) -> PointerPathFinder {
+        self.max_offset = max_offset;
+        self
+    }
+
+    /// Stop searching the first capture once this many paths are found.
+    pub fn with_max_results(mut self, max_results: usize) -> PointerPathFinder {
+        self.max_results = max_results;
+        self
+    }
+
+    /// Paths that lead to the paired virtual address in every capture, shortest first. Paths are
+    /// searched for backwards from the target in the first capture and then checked against the
+    /// rest.
+    pub fn find(&self, captures: &[(&ReversedWords, u64)]) -> Vec<PointerPath> {
+        let (first, target) = match captures.first() {
+            Some(first) => *first,
+            None => return Vec::new(),
+        };
+        let target = match self.map.translate(target) {
+            Ok(target) => target,
+            Err(_) => return Vec::new(),
+        };
+        let mut pointers = self.scan.clone().with_region(0..u64::MAX).scan(first, &self.map, 0..u64::MAX);
+        pointers.sort_by_key(|candidate| candidate.target);
+
+        let mut found = Vec::new();
+        // where a pointer has to lead at this depth, and the offsets from there to the target
+        let mut frontier = vec![(target, Vec::new())];
+        for _ in 0..self.max_depth {
+            let mut next = Vec::new();
+            for (to, suffix) in &frontier {
+                let low = to.saturating_sub(self.max_offset);
+                let from = pointers.partition_point(|candidate| candidate.target < low);
+                for candidate in pointers[from..].iter().take_while(|candidate| candidate.target <= *to) {
+                    let mut offsets = vec![to - candidate.target];
+                    offsets.extend_from_slice(suffix);
+                    let path = PointerPath { base: candidate.addr, offsets };
+                    if self.static_region.contains(&path.base) {
+                        if found.len() == self.max_results {
+                            return self.verified(found, captures);
+                        }
+                        found.push(path.clone());
+                    }
+                    next.push((path.base, path.offsets));
+                }
+            }
+            frontier = next;
+        }
+        self.verified(found, captures)
+    }
+
+    fn verified(&self, found: Vec<PointerPath>, captures: &[(&ReversedWords, u64)]) -> Vec<PointerPath> {
+        let targets: Vec<Option<u64>> = captures.iter().map(|(_, target)| self.map.translate(*target).ok()).collect();
+        found
+            .into_iter()
+            .filter(|path| {
+                captures.iter().zip(&targets).all(|((words, _), target)| {
+                    target.is_some() && path.resolve(&self.scan, words, &self.map) == *target
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{pointer::*, ReversedWords};
+
+    #[test]
+    fn finds_mapped_pointers() {
+        let mut data = vec![0u8; 32];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_value_at(0, 0x8000_0010u32, Endian::Big).unwrap();
+        words.write_value_at(8, 0x8000_1000u32, Endian::Big).unwrap();
+        words.write_value_at(12, 0x1234_5678u32, Endian::Big).unwrap();
+        words.write_value_at(17, 0x8000_0004u32, Endian::Big).unwrap();
+        let map = AddressMap::n64(32);
+        let scan = PointerScan::new(4).unwrap();
+        assert_eq!(
+            vec![PointerCandidate { addr: 0, value: 0x8000_0010, target: 0x10 }],
+            scan.scan(&words, &map, 0x8000_0000..0x8080_0000)
+        );
+        let unaligned = scan.clone().with_step(1).with_region(4..32);
+        assert_eq!(vec![17], unaligned.scan(&words, &map, 0x8000_0000..0x8080_0000).iter().map(|c| c.addr).collect::<Vec<_>>());
+        assert_eq!(Some(0x8000_0010), scan.read_pointer(&words, 0));
+        assert_eq!(None, scan.read_pointer(&words, 30));
+        assert_eq!(Err(ReversedWordsError::InvalidPointerSize { size: 2 }), PointerScan::new(2));
+    }
+
+    #[test]
+    fn finds_paths_stable_across_captures() {
+        // a static pointer at 0x10 to a struct holding a pointer at +8 to the value's struct, whose
+        // value of interest is at +4; the structs are somewhere else in each capture
+        fn capture(outer: u64, inner: u64) -> Vec<u8> {
+            let mut data = vec![0u8; 0x100];
+            let mut words = ReversedWords::new(&mut data);
+            words.write_value_at(0x10, 0x8000_0000 + outer as u32, Endian::Big).unwrap();
+            words.write_value_at(outer + 8, 0x8000_0000 + inner as u32, Endian::Big).unwrap();
+            data
+        }
+        let mut one = capture(0x40, 0x80);
+        let mut two = capture(0x60, 0xC0);
+        let one = ReversedWords::new(&mut one);
+        let two = ReversedWords::new(&mut two);
+        let map = AddressMap::n64(0x100);
+        let finder = PointerPathFinder::new(PointerScan::new(4).unwrap(), map.clone()).with_static_region(0..0x20).with_max_offset(0x10);
+        let paths = finder.find(&[(&one, 0x8000_0084), (&two, 0x8000_00C4)]);
+        assert_eq!(vec![PointerPath { base: 0x10, offsets: vec![8, 4] }], paths);
+        assert_eq!("[[0x10]+0x8]+0x4", paths[0].to_string());
+        assert_eq!(Some(0xC4), paths[0].resolve(&PointerScan::new(4).unwrap(), &two, &map));
+
+        // with only the first capture the path is still found, and nothing is without a target
+        assert_eq!(paths, finder.find(&[(&one, 0x8000_0084)]));
+        assert!(finder.find(&[(&one, 0x8000_0084), (&two, 0x8000_0084)]).is_empty());
+        assert!(finder.find(&[]).is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+//! Batched, rate limited reads of a watch list from a slow backend.
+//!
+//! Reading hundreds of small ranges one by one from another process or over a socket costs a
+//! round trip each. A [`PollScheduler`] merges the ranges it is given into as few reads as it can
+//! (ranges closer than [`PollScheduler::with_max_gap`] become one read, up to
+//! [`PollScheduler::with_max_batch`] bytes) and refuses to read more often than its rate. Each
+//! poll returns a [`PolledMemory`] holding what was read, which a
+//! [`Watcher`](crate::watch::Watcher) can poll in place of the backend.
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::{MemoryBackend, ReversedBackend},
+    watch::WatchSource,
+};
+
+pub const DEFAULT_RATE_HZ: f64 = 60.0;
+pub const DEFAULT_MAX_GAP: u64 = 16;
+pub const DEFAULT_MAX_BATCH: u64 = 4096;
+
+#[derive(Clone, Debug)]
+pub struct PollScheduler {
+    ranges: Vec<Range<u64>>,
+    batches: Vec<Range<u64>>,
+    interval: Duration,
+    max_gap: u64,
+    max_batch: u64,
+    last: Option<Instant>,
+}
+
+impl Default for PollScheduler {
+    fn default() -> PollScheduler {
+        PollScheduler::new()
+    }
+}
+
+impl PollScheduler {
+    pub fn new() -> PollScheduler {
+        PollScheduler {
+            ranges: Vec::new(),
+            batches: Vec::new(),
+            interval: Duration::from_secs_f64(1.0 / DEFAULT_RATE_HZ),
+            max_gap: DEFAULT_MAX_GAP,
+            max_batch: DEFAULT_MAX_BATCH,
+            last: None,
+        }
+    }
+
+    /// Poll at most `hz` times a second.
+    pub fn with_rate(mut self, hz: f64) -> PollScheduler {
+        self.interval = Duration::from_secs_f64(1.0 / hz);
+        self
+    }
+
+    /// Merge ranges separated by at most `gap` unwatched bytes.
+    pub fn with_max_gap(mut self, gap: u64) -> PollScheduler {
+        self.max_gap = gap;
+        self.batches = self.coalesce();
+        self
+    }
+
+    /// Stop merging once a read would exceed `len` bytes. A single range longer than this is
+    /// still read whole.
+    pub fn with_max_batch(mut self, len: u64) -> PollScheduler {
+        self.max_batch = len;
+        self.batches = self.coalesce();
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn add(&mut self, range: Range<u64>) {
+        self.ranges.push(range);
+        self.batches = self.coalesce();
+    }
+
+    /// Add several ranges at once, such as the ranges of a watcher's watches.
+    pub fn add_all(&mut self, ranges: impl IntoIterator<Item = Range<u64>>) {
+        self.ranges.extend(ranges);
+        self.batches = self.coalesce();
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+        self.batches.clear();
+    }
+
+    /// The reads a poll makes, in address order.
+    pub fn batches(&self) -> &[Range<u64>] {
+        &self.batches
+    }
+
+    fn coalesce(&self) -> Vec<Range<u64>> {
+        let mut ranges: Vec<Range<u64>> = self.ranges.iter().filter(|range| range.start < range.end).cloned().collect();
+        ranges.sort_by_key(|range| range.start);
+        let mut batches: Vec<Range<u64>> = Vec::new();
+        for range in ranges {
+            match batches.last_mut() {
+                Some(batch)
+                    if range.start <= batch.end.saturating_add(self.max_gap)
+                        && range.end.max(batch.end) - batch.start <= self.max_batch.max(batch.end - batch.start) =>
+                {
+                    batch.end = batch.end.max(range.end)
+                }
+                _ => batches.push(range),
+            }
+        }
+        batches
+    }
+
+    /// How long until the next poll is allowed, zero if it already is.
+    pub fn time_until_due(&self, now: Instant) -> Duration {
+        self.last.map_or(Duration::ZERO, |last| (last + self.interval).saturating_duration_since(now))
+    }
+
+    /// Read every batch if the rate allows it, or return `None` without touching the backend.
+    pub fn poll<B: MemoryBackend>(&mut self, backend: &mut ReversedBackend<B>) -> std::io::Result<Option<PolledMemory>> {
+        let now = Instant::now();
+        if !self.time_until_due(now).is_zero() {
+            return Ok(None);
+        }
+        self.last = Some(now);
+        self.read(backend).map(Some)
+    }
+
+    /// Wait until the next poll is allowed, then poll.
+    pub fn poll_blocking<B: MemoryBackend>(&mut self, backend: &mut ReversedBackend<B>) -> std::io::Result<PolledMemory> {
+        std::thread::sleep(self.time_until_due(Instant::now()));
+        self.last = Some(Instant::now());
+        self.read(backend)
+    }
+
+    fn read<B: MemoryBackend>(&self, backend: &mut ReversedBackend<B>) -> std::io::Result<PolledMemory> {
+        let mut chunks = Vec::with_capacity(self.batches.len());
+        for batch in &self.batches {
+            let mut bytes = vec![0u8; (batch.end - batch.start) as usize];
+            let n = backend.read_at(batch.start, &mut bytes)?;
+            bytes.truncate(n);
+            chunks.push((batch.start, bytes));
+        }
+        Ok(PolledMemory { len: backend.len(), chunks })
+    }
+}
+
+/// The logical bytes one poll read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolledMemory {
+    len: u64,
+    chunks: Vec<(u64, Vec<u8>)>,
+}
+
+impl PolledMemory {
+    /// The bytes of `range`, if a single read covered all of it.
+    pub fn get(&self, range: Range<u64>) -> Option<&[u8]> {
+        self.chunks.iter().find_map(|(start, bytes)| {
+            let end = *start + bytes.len() as u64;
+            (range.start >= *start && range.end <= end && range.start <= range.end)
+                .then(|| &bytes[(range.start - start) as usize..(range.end - start) as usize])
+        })
+    }
+}
+
+/// Reads outside what was polled stop short, which the watcher reports as out of range.
+impl WatchSource for PolledMemory {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn read_watched(&self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        for (start, bytes) in &self.chunks {
+            if addr >= *start && addr < start + bytes.len() as u64 {
+                let available = &bytes[(addr - start) as usize..];
+                let n = buf.len().min(available.len());
+                buf[..n].copy_from_slice(&available[..n]);
+                return Ok(n);
+            }
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{poll::*, watch::Watcher, Endian};
+
+    #[test]
+    fn coalesces_nearby_ranges() {
+        let mut scheduler = PollScheduler::new().with_max_gap(8).with_max_batch(32);
+        scheduler.add_all([0x10..0x14, 0x00..0x04, 0x06..0x08, 0x42..0x44, 0x20..0x40, 0x1000..0x2000]);
+        assert_eq!(&[0x00..0x14, 0x20..0x40, 0x42..0x44, 0x1000..0x2000], scheduler.batches());
+    }
+
+    #[test]
+    fn rate_limits_and_feeds_a_watcher() {
+        let mut backend = ReversedBackend::new(vec![0u8; 64], 4).unwrap();
+        let mut watcher = Watcher::new();
+        let ids = [watcher.watch_value::<u32>(0), watcher.watch_value::<u16>(8)];
+        let mut scheduler = PollScheduler::new().with_rate(1.0);
+        scheduler.add_all(ids.iter().map(|id| watcher.range(*id).unwrap()));
+        assert_eq!(1, scheduler.batches().len());
+        assert_eq!(0..10, scheduler.batches()[0]);
+
+        let memory = scheduler.poll(&mut backend).unwrap().unwrap();
+        watcher.poll(&memory).unwrap();
+        assert_eq!(None, scheduler.poll(&mut backend).unwrap());
+
+        backend.write_value_at(8, 0x1234u16, Endian::Big).unwrap();
+        let memory = PollScheduler { last: None, ..scheduler.clone() }.poll(&mut backend).unwrap().unwrap();
+        assert_eq!(Some(&[0x12, 0x34][..]), memory.get(8..10));
+        assert_eq!(None, memory.get(8..12));
+        let changes = watcher.poll(&memory).unwrap();
+        assert_eq!(vec![ids[1]], changes.iter().map(|change| change.id).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,93 @@
+//! `positioned-io` trait implementations, so the views plug into libraries written against
+//! `ReadAt`/`WriteAt` instead of `Read + Seek`.
+//!
+//! Positions are logical addresses, and like the inherent `read_at`/`write_at` the cursor is
+//! neither used nor moved.
+
+use positioned_io::{ReadAt, Size, WriteAt};
+
+use crate::{ReversedVec, ReversedWords, ReversedWordsRef};
+
+impl ReadAt for ReversedWords<'_> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        ReversedWords::read_at(self, pos, buf)
+    }
+}
+
+impl WriteAt for ReversedWords<'_> {
+    fn write_at(&mut self, pos: u64, buf: &[u8]) -> std::io::Result<usize> {
+        ReversedWords::write_at(self, pos, buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Size for ReversedWords<'_> {
+    fn size(&self) -> std::io::Result<Option<u64>> {
+        Ok(Some(self.len()))
+    }
+}
+
+impl ReadAt for ReversedWordsRef<'_> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(ReversedWordsRef::read_at(self, pos, buf))
+    }
+}
+
+impl Size for ReversedWordsRef<'_> {
+    fn size(&self) -> std::io::Result<Option<u64>> {
+        Ok(Some(self.len()))
+    }
+}
+
+impl ReadAt for ReversedVec {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.as_ref_view().read_at(pos, buf))
+    }
+}
+
+impl WriteAt for ReversedVec {
+    fn write_at(&mut self, pos: u64, buf: &[u8]) -> std::io::Result<usize> {
+        ReversedVec::write_at(self, pos, buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Size for ReversedVec {
+    fn size(&self) -> std::io::Result<Option<u64>> {
+        Ok(Some(self.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use positioned_io::{ReadAt, Size, WriteAt};
+
+    use crate::*;
+
+    fn checksum_at<R: ReadAt + Size>(source: &R, pos: u64) -> u32 {
+        let mut word = [0u8; 4];
+        source.read_exact_at(pos, &mut word).unwrap();
+        u32::from_be_bytes(word)
+    }
+
+    #[test]
+    fn generic_positioned_access() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut words = ReversedWords::new(&mut data);
+        WriteAt::write_all_at(&mut words, 4, &[0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+        assert_eq!(0xDEAD_BEEF, checksum_at(&words, 4));
+        assert_eq!(Some(8), words.size().unwrap());
+        assert_eq!(0x0302_0100, checksum_at(&words.as_ref_view(), 0));
+        assert!(words.as_ref_view().read_exact_at(6, &mut [0u8; 4]).is_err());
+
+        let mut owned = ReversedVec::new(vec![0; 4]);
+        owned.write_all_at(0, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(0x0102_0304, checksum_at(&owned, 0));
+    }
+}
@@ -0,0 +1,123 @@
+//! CPU architecture presets for the settings every higher level feature needs.
+//!
+//! An [`ArchProfile`] bundles how the target's memory is usually dumped (word size), the byte
+//! order and width of its values and pointers, and where RAM sits in its address space, so a
+//! view and an [`Evaluator`] can be set up for a target in one place instead of repeating
+//! `Endian::Big` and `0x80000000` at every call site.
+//!
+//! ```
+//! # use reversed_word_byte_rw::profile::ArchProfile;
+//! let profile = ArchProfile::MipsBe;
+//! let mut rdram = vec![0u8; 0x40_0000];
+//! let mut words = profile.view(&mut rdram);
+//! words.write_u32(0x1240, 0x8000_1000).unwrap();
+//! words.write_u16(0x1000 + 0x24, 7).unwrap();
+//! let value = profile.evaluator(0x40_0000).evaluate_str(&mut words, "u16:[0x80001240]+0x24").unwrap();
+//! assert_eq!(reversed_word_byte_rw::expr::Value::Unsigned(7), value);
+//! ```
+
+use binread::Endian;
+
+use crate::{expr::Evaluator, platform::Platform, translate::AddressMap, ReversedWords};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchProfile {
+    /// N64 VR4300: big endian, 32-bit pointers, RDRAM 4-byte swapped when dumped by emulators on
+    /// little endian hosts, mapped at KSEG0 (`0x80000000`) and KSEG1 (`0xA0000000`).
+    MipsBe,
+    /// GameCube/Wii Gekko and Broadway: big endian, 32-bit pointers, dumped unswapped, MEM1 at
+    /// `0x80000000` (cached) and `0xC0000000` (uncached).
+    PowerPcBe,
+    /// Saturn SH-2: big endian, 32-bit pointers, dumped as swapped 16-bit words, high work RAM at
+    /// `0x06000000` and its cache-through mirror `0x26000000`.
+    Sh2Be,
+    /// 32-bit x86: little endian, 32-bit pointers, dumped unswapped with addresses as is.
+    X86Le,
+}
+
+impl ArchProfile {
+    /// Word size memory dumps of this target are usually swapped by.
+    pub fn word_size(self) -> u8 {
+        match self {
+            ArchProfile::MipsBe => 4,
+            ArchProfile::Sh2Be => 2,
+            ArchProfile::PowerPcBe | ArchProfile::X86Le => 1,
+        }
+    }
+
+    pub fn endian(self) -> Endian {
+        match self {
+            ArchProfile::X86Le => Endian::Little,
+            _ => Endian::Big,
+        }
+    }
+
+    /// Size of a pointer in bytes.
+    pub fn pointer_size(self) -> u8 {
+        4
+    }
+
+    /// Virtual addresses main RAM is mapped at, the preferred (cached) one first.
+    pub fn base_addresses(self) -> &'static [u64] {
+        match self {
+            ArchProfile::MipsBe => &[0x8000_0000, 0xA000_0000],
+            ArchProfile::PowerPcBe => &[0x8000_0000, 0xC000_0000],
+            ArchProfile::Sh2Be => &[0x0600_0000, 0x2600_0000],
+            ArchProfile::X86Le => &[0],
+        }
+    }
+
+    /// Where a RAM dump of `len` bytes appears at every one of the [`ArchProfile::base_addresses`].
+    pub fn address_map(self, len: u64) -> AddressMap {
+        self.base_addresses().iter().fold(AddressMap::new(), |map, base| map.with_segment(*base..*base + len, 0))
+    }
+
+    /// A view over `ram` with this profile's word size and endianness.
+    pub fn view(self, ram: &mut [u8]) -> ReversedWords<'_> {
+        ReversedWords::new_with_word_size(ram, self.word_size()).with_endian(self.endian())
+    }
+
+    /// An expression evaluator for a RAM dump of `len` bytes, with this profile's address map,
+    /// endianness and pointer size.
+    pub fn evaluator(self, len: u64) -> Evaluator {
+        Evaluator::new()
+            .with_address_map(self.address_map(len))
+            .with_endian(self.endian())
+            .with_pointer_size(self.pointer_size())
+            .expect("profiles use 4 or 8 byte pointers")
+    }
+}
+
+impl Platform {
+    /// The CPU profile of the platform, if there is one.
+    pub fn profile(self) -> Option<ArchProfile> {
+        match self {
+            Platform::N64Rdram => Some(ArchProfile::MipsBe),
+            Platform::GameCubeMem1 | Platform::WiiMem2 => Some(ArchProfile::PowerPcBe),
+            Platform::GenesisWorkRam | Platform::Ps2EeRam => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expr::Value, profile::*};
+
+    #[test]
+    fn profiles_configure_views_and_evaluators() {
+        let mut ram = vec![0u8; 0x100];
+        let mut words = ArchProfile::Sh2Be.view(&mut ram);
+        words.write_u32(0x10, 0x0600_0020).unwrap();
+        words.write_u16(0x20, 0x1234).unwrap();
+        assert_eq!(Value::Unsigned(0x1234), ArchProfile::Sh2Be.evaluator(0x100).evaluate_str(&mut words, "u16:[0x26000010]").unwrap());
+        assert_eq!([0x00, 0x06, 0x20, 0x00], ram[0x10..0x14]);
+
+        let mut ram = vec![0u8; 8];
+        let mut words = ArchProfile::X86Le.view(&mut ram);
+        words.write_u32(0, 4).unwrap();
+        words.write_u32(4, 0xDEAD_BEEF).unwrap();
+        assert_eq!(Value::Unsigned(0xDEAD_BEEF), ArchProfile::X86Le.evaluator(8).evaluate_str(&mut words, "[0]").unwrap());
+        assert_eq!([4, 0, 0, 0], ram[..4]);
+        assert_eq!(Some(ArchProfile::MipsBe), Platform::N64Rdram.profile());
+    }
+}
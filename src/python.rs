@@ -0,0 +1,137 @@
+//! Python bindings, built as the `reversed_word_byte_rw` extension module.
+//!
+//! ```python
+//! from reversed_word_byte_rw import ReversedWords
+//! ram = ReversedWords(open("rdram.bin", "rb").read(), word_size=4)
+//! health = ram.read_u32(0x33B238)
+//! ram.write_u16(0x33B240, 999)
+//! open("rdram.bin", "wb").write(ram.storage())
+//! ```
+//!
+//! The view owns a copy of the data; `storage()` returns it in storage order and `logical()` in logical order.
+
+use binread::Endian;
+use pyo3::{exceptions::{PyIOError, PyValueError}, prelude::*, types::PyBytes};
+
+use crate::{Primitive, ReversedWords};
+
+#[pyclass(name = "ReversedWords")]
+struct PyReversedWords {
+    data: Vec<u8>,
+    word_size: u8,
+}
+
+fn io_error(e: std::io::Error) -> PyErr {
+    PyIOError::new_err(e.to_string())
+}
+
+fn endian(big_endian: bool) -> Endian {
+    if big_endian { Endian::Big } else { Endian::Little }
+}
+
+impl PyReversedWords {
+    fn words(&mut self) -> ReversedWords<'_> {
+        ReversedWords::new_with_word_size(&mut self.data, self.word_size)
+    }
+
+    fn read_value<T: Primitive>(&mut self, addr: u64, big_endian: bool) -> PyResult<T> {
+        self.words().read_value_at(addr, endian(big_endian)).map_err(io_error)
+    }
+
+    fn write_value<T: Primitive>(&mut self, addr: u64, value: T, big_endian: bool) -> PyResult<()> {
+        self.words().write_whole_value_at(addr, value, endian(big_endian)).map_err(io_error)
+    }
+}
+
+macro_rules! py_typed_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        #[pymethods]
+        impl PyReversedWords {
+            #[new]
+            #[pyo3(signature = (data, word_size = 4))]
+            fn new(data: Vec<u8>, word_size: u8) -> PyResult<Self> {
+                if word_size == 0 {
+                    return Err(PyValueError::new_err("word size must be at least 1 byte"));
+                }
+                Ok(PyReversedWords { data, word_size })
+            }
+
+            fn __len__(&self) -> usize {
+                self.data.len()
+            }
+
+            #[getter]
+            fn word_size(&self) -> u8 {
+                self.word_size
+            }
+
+            /// Read up to `length` logical bytes starting at `addr`.
+            fn read<'py>(&mut self, py: Python<'py>, addr: u64, length: usize) -> PyResult<Bound<'py, PyBytes>> {
+                let mut buf = vec![0u8; length];
+                let n = self.words().read_at(addr, &mut buf).map_err(io_error)?;
+                Ok(PyBytes::new(py, &buf[..n]))
+            }
+
+            /// Write logical bytes starting at `addr`, returning how many fit.
+            fn write(&mut self, addr: u64, data: Vec<u8>) -> PyResult<usize> {
+                self.words().write_at(addr, &data).map_err(io_error)
+            }
+
+            fn fill(&mut self, start: u64, end: u64, byte: u8) -> PyResult<()> {
+                self.words().fill(start..end, byte).map_err(io_error)
+            }
+
+            fn copy_within(&mut self, start: u64, end: u64, dst: u64) -> PyResult<()> {
+                self.words().copy_within(start..end, dst).map_err(io_error)
+            }
+
+            /// Logical addresses of every occurrence of `needle`, including overlapping ones.
+            fn find_all(&mut self, needle: Vec<u8>) -> Vec<u64> {
+                self.words().find_all(&needle)
+            }
+
+            /// The data in storage order, for writing back to a file.
+            fn storage<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+                PyBytes::new(py, &self.data)
+            }
+
+            /// The data in logical order.
+            fn logical<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+                let mut buf = vec![0u8; self.data.len()];
+                let n = self.words().read_at(0, &mut buf).map_err(io_error)?;
+                Ok(PyBytes::new(py, &buf[..n]))
+            }
+
+            $(
+                #[pyo3(signature = (addr, big_endian = true))]
+                fn $read(&mut self, addr: u64, big_endian: bool) -> PyResult<$ty> {
+                    self.read_value(addr, big_endian)
+                }
+
+                #[pyo3(signature = (addr, value, big_endian = true))]
+                fn $write(&mut self, addr: u64, value: $ty, big_endian: bool) -> PyResult<()> {
+                    self.write_value(addr, value, big_endian)
+                }
+            )*
+        }
+    };
+}
+
+py_typed_accessors! {
+    u8 => read_u8, write_u8;
+    u16 => read_u16, write_u16;
+    u32 => read_u32, write_u32;
+    u64 => read_u64, write_u64;
+    i8 => read_i8, write_i8;
+    i16 => read_i16, write_i16;
+    i32 => read_i32, write_i32;
+    i64 => read_i64, write_i64;
+    f32 => read_f32, write_f32;
+    f64 => read_f64, write_f64;
+}
+
+#[pymodule]
+fn reversed_word_byte_rw(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyReversedWords>()?;
+    Ok(())
+}
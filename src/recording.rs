@@ -0,0 +1,386 @@
+//! Long recordings of RAM, for analysing gameplay after the fact.
+//!
+//! [`RamRecorder`] captures a view's storage into a stream, periodically or on every call, and
+//! [`RamPlayback`] reconstructs any captured frame from it. Most frames are stored as the XOR
+//! against the frame before, run length encoded, so a recording of a mostly idle RAM costs little
+//! more than the bytes that changed; every so often a whole keyframe is stored instead, bounding
+//! how many deltas reconstructing a frame has to apply.
+//!
+//! ```
+//! # use std::io::Cursor;
+//! # use reversed_word_byte_rw::{Endian, ReversedWords, recording::{RamPlayback, RamRecorder}};
+//! let mut ram = vec![0u8; 0x100];
+//! let mut words = ReversedWords::new(&mut ram);
+//! let mut recorder = RamRecorder::new(Vec::new()).unwrap();
+//! for frame in 0..10u32 {
+//!     words.write_u32_at(0x40, frame, Endian::Big).unwrap();
+//!     recorder.capture(&words).unwrap();
+//! }
+//! let mut playback = RamPlayback::new(Cursor::new(recorder.finish().unwrap())).unwrap();
+//! playback.restore(3, &mut words).unwrap();
+//! assert_eq!(3, words.read_u32_at(0x40, Endian::Big).unwrap());
+//! ```
+//!
+//! The stream is the magic `RWR1`, then for every frame a kind byte (0 for a keyframe, 1 for a
+//! delta), the microseconds since the first frame and the payload length, both little endian
+//! `u64`s, and the payload. A keyframe's payload is the storage bytes. A delta's is a sequence of
+//! runs, each the number of unchanged bytes to skip and the number of changed bytes as little
+//! endian `u64`s followed by those bytes XORed with the previous frame's.
+
+use std::{
+    convert::TryInto,
+    io::{Read, Seek, SeekFrom, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{ReversedWords, ReversedWordsError};
+
+const RECORDING_MAGIC: &[u8; 4] = b"RWR1";
+const KEYFRAME: u8 = 0;
+const DELTA: u8 = 1;
+const HEADER_LEN: u64 = 17;
+// a run costs 16 bytes of header, so gaps shorter than that are cheaper stored as zero XORs
+const RUN_HEADER_LEN: usize = 16;
+
+/// How often a keyframe is written if not set with [`RamRecorder::with_keyframe_interval`].
+pub const DEFAULT_KEYFRAME_INTERVAL: u64 = 600;
+
+fn invalid_recording(reason: &str) -> std::io::Error {
+    ReversedWordsError::InvalidRecording { reason: reason.to_string() }.into()
+}
+
+// the XOR of `previous` and `current` as (skip, changed bytes) runs
+fn encode_delta(previous: &[u8], current: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (offset, _) in previous.iter().zip(current).enumerate().filter(|(_, (previous, current))| previous != current) {
+        match runs.last_mut() {
+            Some((start, end)) if offset - *end < RUN_HEADER_LEN => *end = offset + 1,
+            _ => runs.push((offset, offset + 1)),
+        }
+    }
+    let mut position = 0;
+    for (start, end) in runs {
+        payload.extend_from_slice(&((start - position) as u64).to_le_bytes());
+        payload.extend_from_slice(&((end - start) as u64).to_le_bytes());
+        payload.extend(previous[start..end].iter().zip(&current[start..end]).map(|(previous, current)| previous ^ current));
+        position = end;
+    }
+    payload
+}
+
+fn apply_delta(frame: &mut [u8], mut payload: &[u8]) -> std::io::Result<()> {
+    let mut position = 0usize;
+    while !payload.is_empty() {
+        if payload.len() < RUN_HEADER_LEN {
+            return Err(invalid_recording("truncated delta run"));
+        }
+        let field = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().expect("8 bytes"));
+        let (skip, len) = (field(&payload[..8]), field(&payload[8..16]));
+        let start = position as u64 + skip;
+        let available = (frame.len() - position) as u64;
+        if skip > available || len > available - skip || len > (payload.len() - RUN_HEADER_LEN) as u64 {
+            return Err(invalid_recording("delta run outside of the frame"));
+        }
+        let (start, len) = (start as usize, len as usize);
+        for (byte, xor) in frame[start..start + len].iter_mut().zip(&payload[RUN_HEADER_LEN..]) {
+            *byte ^= xor;
+        }
+        position = start + len;
+        payload = &payload[RUN_HEADER_LEN + len..];
+    }
+    Ok(())
+}
+
+/// Writes captures of a view to a recording stream.
+#[derive(Debug)]
+pub struct RamRecorder<W: Write> {
+    writer: W,
+    previous: Option<Vec<u8>>,
+    frames: u64,
+    since_keyframe: u64,
+    keyframe_interval: u64,
+    interval: Option<Duration>,
+    started: Option<Instant>,
+    last_capture: Option<Instant>,
+}
+
+impl<W: Write> RamRecorder<W> {
+    /// Start a recording on `writer`, writing the magic.
+    pub fn new(mut writer: W) -> std::io::Result<RamRecorder<W>> {
+        writer.write_all(RECORDING_MAGIC)?;
+        Ok(RamRecorder {
+            writer,
+            previous: None,
+            frames: 0,
+            since_keyframe: 0,
+            keyframe_interval: DEFAULT_KEYFRAME_INTERVAL,
+            interval: None,
+            started: None,
+            last_capture: None,
+        })
+    }
+
+    /// Write a keyframe every `interval` frames, at least 1. Larger intervals make recordings
+    /// smaller and seeking to a frame slower.
+    pub fn with_keyframe_interval(mut self, interval: u64) -> RamRecorder<W> {
+        self.keyframe_interval = interval.max(1);
+        self
+    }
+
+    /// How often [`RamRecorder::poll`] captures.
+    pub fn with_interval(mut self, interval: Duration) -> RamRecorder<W> {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Number of frames captured.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Append a frame of `words`. A buffer of a different length than the previous frame is
+    /// written as a keyframe.
+    pub fn capture(&mut self, words: &ReversedWords) -> std::io::Result<()> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(*self.started.get_or_insert(now));
+        let current = words.cursor.get_ref();
+        let delta = match &self.previous {
+            Some(previous) if previous.len() == current.len() && self.since_keyframe < self.keyframe_interval => {
+                Some(encode_delta(previous, current))
+            }
+            _ => None,
+        };
+        let (kind, payload) = match &delta {
+            Some(payload) => (DELTA, &payload[..]),
+            None => (KEYFRAME, &current[..]),
+        };
+        self.writer.write_all(&[kind])?;
+        self.writer.write_all(&(elapsed.as_micros() as u64).to_le_bytes())?;
+        self.writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+        self.writer.write_all(payload)?;
+
+        self.since_keyframe = if delta.is_some() { self.since_keyframe + 1 } else { 1 };
+        match &mut self.previous {
+            Some(previous) if previous.len() == current.len() => previous.copy_from_slice(current),
+            previous => *previous = Some(current.to_vec()),
+        }
+        self.frames += 1;
+        self.last_capture = Some(now);
+        Ok(())
+    }
+
+    /// Capture if the interval has passed since the last capture (or there is none yet), returning
+    /// whether it did. Without an interval this always captures.
+    pub fn poll(&mut self, words: &ReversedWords) -> std::io::Result<bool> {
+        let due = match (self.interval, self.last_capture) {
+            (Some(interval), Some(last)) => last.elapsed() >= interval,
+            _ => true,
+        };
+        if due {
+            self.capture(words)?;
+        }
+        Ok(due)
+    }
+
+    /// Flush the recording and give back the writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FrameHeader {
+    kind: u8,
+    elapsed: Duration,
+    payload: u64,
+    len: u64,
+}
+
+/// Reconstructs frames of a recording made by [`RamRecorder`].
+#[derive(Debug)]
+pub struct RamPlayback<R: Read + Seek> {
+    reader: R,
+    // frame headers with their payload's position in the stream
+    index: Vec<FrameHeader>,
+}
+
+impl<R: Read + Seek> RamPlayback<R> {
+    /// Index the frames of a recording, reading only their headers.
+    pub fn new(mut reader: R) -> std::io::Result<RamPlayback<R>> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(|_| invalid_recording("missing RWR1 magic"))?;
+        if &magic != RECORDING_MAGIC {
+            return Err(invalid_recording("missing RWR1 magic"));
+        }
+        let end = reader.seek(SeekFrom::End(0))?;
+        let mut position = reader.seek(SeekFrom::Start(4))?;
+        let mut index = Vec::new();
+        while position < end {
+            if end - position < HEADER_LEN {
+                return Err(invalid_recording("truncated frame header"));
+            }
+            let mut header = [0u8; HEADER_LEN as usize];
+            reader.read_exact(&mut header)?;
+            let kind = header[0];
+            let elapsed = Duration::from_micros(u64::from_le_bytes(header[1..9].try_into().expect("8 bytes")));
+            let len = u64::from_le_bytes(header[9..].try_into().expect("8 bytes"));
+            match kind {
+                KEYFRAME => {}
+                DELTA if !index.is_empty() => {}
+                DELTA => return Err(invalid_recording("recording starts with a delta")),
+                _ => return Err(invalid_recording("unknown frame kind")),
+            }
+            let payload = position + HEADER_LEN;
+            if end - payload < len {
+                return Err(invalid_recording("truncated frame"));
+            }
+            index.push(FrameHeader { kind, elapsed, payload, len });
+            position = reader.seek(SeekFrom::Start(payload + len))?;
+        }
+        Ok(RamPlayback { reader, index })
+    }
+
+    /// Number of frames in the recording.
+    pub fn len(&self) -> u64 {
+        self.index.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    fn header(&self, frame: u64) -> Result<FrameHeader, ReversedWordsError> {
+        self.index
+            .get(frame as usize)
+            .copied()
+            .ok_or(ReversedWordsError::FrameOutOfRange { frame, frames: self.len() })
+    }
+
+    /// Time from the first frame to `frame`.
+    pub fn elapsed(&self, frame: u64) -> Result<Duration, ReversedWordsError> {
+        self.header(frame).map(|header| header.elapsed)
+    }
+
+    fn payload(&mut self, header: FrameHeader) -> std::io::Result<Vec<u8>> {
+        self.reader.seek(SeekFrom::Start(header.payload))?;
+        let mut payload = Vec::new();
+        (&mut self.reader).take(header.len).read_to_end(&mut payload)?;
+        if (payload.len() as u64) < header.len {
+            return Err(invalid_recording("truncated frame"));
+        }
+        Ok(payload)
+    }
+
+    /// The storage bytes captured at `frame`, rebuilt from the keyframe before it.
+    pub fn frame(&mut self, frame: u64) -> std::io::Result<Vec<u8>> {
+        self.header(frame)?;
+        let keyframe = self.index[..=frame as usize]
+            .iter()
+            .rposition(|header| header.kind == KEYFRAME)
+            .expect("recordings start with a keyframe");
+        let mut storage = self.payload(self.index[keyframe])?;
+        for i in keyframe + 1..=frame as usize {
+            let payload = self.payload(self.index[i])?;
+            apply_delta(&mut storage, &payload)?;
+        }
+        Ok(storage)
+    }
+
+    /// Put `frame` into `words`, which must be the length it was captured at.
+    pub fn restore(&mut self, frame: u64, words: &mut ReversedWords) -> std::io::Result<()> {
+        let captured = self.frame(frame)?;
+        let storage = words.cursor.get_mut();
+        if storage.len() != captured.len() {
+            return Err(ReversedWordsError::RangeOutOfRange {
+                start: 0,
+                end: captured.len() as u64,
+                len: storage.len() as u64,
+            }
+            .into());
+        }
+        storage.copy_from_slice(&captured);
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{recording::*, Endian};
+
+    #[test]
+    fn reconstructs_every_frame() {
+        let mut data = vec![0u8; 0x1000];
+        let mut words = ReversedWords::new(&mut data);
+        let mut recorder = RamRecorder::new(Vec::new()).unwrap().with_keyframe_interval(4);
+        let mut expected = Vec::new();
+        for frame in 0..10u32 {
+            words.write_u32_at(0x100, frame, Endian::Big).unwrap();
+            words.write_u16_at(0x10 + frame as u64 * 0x20, 0xFFFF, Endian::Big).unwrap();
+            recorder.capture(&words).unwrap();
+            expected.push(words.cursor.get_ref().to_vec());
+        }
+        assert_eq!(10, recorder.frames());
+        let recording = recorder.finish().unwrap();
+        // three keyframes, the rest small deltas
+        assert!(recording.len() < 3 * 0x1000 + 10 * 64);
+
+        let mut playback = RamPlayback::new(Cursor::new(recording)).unwrap();
+        assert_eq!(10, playback.len());
+        for frame in (0..10).rev() {
+            assert_eq!(expected[frame as usize], playback.frame(frame).unwrap());
+        }
+        assert!(playback.elapsed(9).unwrap() >= playback.elapsed(0).unwrap());
+        let error = playback.frame(10).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::FrameOutOfRange { frame: 10, frames: 10 }),
+            ReversedWordsError::from_io(&error)
+        );
+    }
+
+    #[test]
+    fn length_changes_start_a_keyframe() {
+        let mut recorder = RamRecorder::new(Vec::new()).unwrap();
+        let mut small = vec![1u8; 8];
+        recorder.capture(&ReversedWords::new(&mut small)).unwrap();
+        let mut large = vec![2u8; 16];
+        recorder.capture(&ReversedWords::new(&mut large)).unwrap();
+        recorder.capture(&ReversedWords::new(&mut large)).unwrap();
+
+        let mut playback = RamPlayback::new(Cursor::new(recorder.finish().unwrap())).unwrap();
+        assert_eq!(vec![2u8; 16], playback.frame(2).unwrap());
+        let error = playback.restore(0, &mut ReversedWords::new(&mut large)).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+        playback.restore(0, &mut ReversedWords::new(&mut small)).unwrap();
+    }
+
+    #[test]
+    fn rejects_corrupt_recordings() {
+        let mut data = vec![0u8; 0x40];
+        let mut words = ReversedWords::new(&mut data);
+        let mut recorder = RamRecorder::new(Vec::new()).unwrap();
+        recorder.capture(&words).unwrap();
+        words.write_u32_at(0x20, 1, Endian::Big).unwrap();
+        recorder.capture(&words).unwrap();
+        let recording = recorder.finish().unwrap();
+
+        let invalid = |recording: Vec<u8>| {
+            let error = RamPlayback::new(Cursor::new(recording)).and_then(|mut playback| playback.frame(1)).unwrap_err();
+            matches!(ReversedWordsError::from_io(&error), Some(ReversedWordsError::InvalidRecording { .. }))
+        };
+        assert!(invalid(b"RWJ1".to_vec()));
+        assert!(invalid(recording[..recording.len() - 1].to_vec()));
+        // a delta run skipping past the end of the frame
+        let mut skipping = recording.clone();
+        let run = 4 + 17 + 0x40 + 17;
+        skipping[run..run + 8].copy_from_slice(&0x100u64.to_le_bytes());
+        assert!(invalid(skipping));
+    }
+}
@@ -0,0 +1,178 @@
+//! Regular expression search over the logical byte stream.
+//!
+//! A [`ByteRegex`] holds a forward and a reverse DFA built by `regex-automata`. Searching feeds
+//! them one logical byte at a time straight from storage, so a region is never copied out: the
+//! forward DFA finds where the leftmost match ends and the reverse one walks back from there to
+//! where it starts. Patterns match bytes, not UTF-8: `.` is any byte but `\n` and `\xFF` is the
+//! byte 0xFF.
+
+use std::ops::Range;
+
+use regex_automata::{
+    dfa::{dense, Automaton, StartKind},
+    nfa::thompson,
+    util::{start, syntax},
+    Anchored, MatchKind,
+};
+
+use crate::{ReversedWords, ReversedWordsError};
+
+// patterns that need more than this to determinize are rejected rather than eating memory
+const DFA_SIZE_LIMIT: usize = 16 << 20;
+
+pub struct ByteRegex {
+    forward: dense::DFA<Vec<u32>>,
+    reverse: dense::DFA<Vec<u32>>,
+}
+
+impl ByteRegex {
+    pub fn new(pattern: &str) -> Result<ByteRegex, ReversedWordsError> {
+        let invalid = |error: regex_automata::dfa::dense::BuildError| ReversedWordsError::InvalidRegex { reason: error.to_string() };
+        let syntax = syntax::Config::new().unicode(false).utf8(false);
+        let forward = dense::Builder::new()
+            .configure(dense::Config::new().match_kind(MatchKind::LeftmostFirst).dfa_size_limit(Some(DFA_SIZE_LIMIT)).determinize_size_limit(Some(DFA_SIZE_LIMIT)))
+            .syntax(syntax)
+            .thompson(thompson::Config::new().utf8(false))
+            .build(pattern)
+            .map_err(invalid)?;
+        let reverse = dense::Builder::new()
+            .configure(
+                dense::Config::new()
+                    .match_kind(MatchKind::All)
+                    .start_kind(StartKind::Anchored)
+                    .dfa_size_limit(Some(DFA_SIZE_LIMIT))
+                    .determinize_size_limit(Some(DFA_SIZE_LIMIT)),
+            )
+            .syntax(syntax)
+            .thompson(thompson::Config::new().utf8(false).reverse(true))
+            .build(pattern)
+            .map_err(invalid)?;
+        Ok(ByteRegex { forward, reverse })
+    }
+
+    // end of the leftmost-first match starting at or after `start`
+    fn match_end(&self, words: &ReversedWords, start: u64, end: u64) -> Option<u64> {
+        let dfa = &self.forward;
+        let config = start::Config::new().anchored(Anchored::No).look_behind(byte_at(words, start.checked_sub(1)));
+        let mut state = dfa.start_state(&config).ok()?;
+        let mut last = None;
+        // matches are reported one byte late: a match state after byte `i` is a match ending at `i`
+        for (i, byte) in (start..).zip(words.bytes(start..end)) {
+            state = dfa.next_state(state, byte);
+            if dfa.is_special_state(state) {
+                if dfa.is_match_state(state) {
+                    last = Some(i);
+                } else if dfa.is_dead_state(state) || dfa.is_quit_state(state) {
+                    return last;
+                }
+            }
+        }
+        state = match byte_at(words, Some(end)) {
+            Some(byte) => dfa.next_state(state, byte),
+            None => dfa.next_eoi_state(state),
+        };
+        if dfa.is_match_state(state) {
+            last = Some(end);
+        }
+        last
+    }
+
+    // start of the leftmost match ending at `end`, searching no further back than `floor`
+    fn match_start(&self, words: &ReversedWords, floor: u64, end: u64) -> u64 {
+        let dfa = &self.reverse;
+        let config = start::Config::new().anchored(Anchored::Yes).look_behind(byte_at(words, Some(end)));
+        let mut state = match dfa.start_state(&config) {
+            Ok(state) => state,
+            Err(_) => return end,
+        };
+        let mut first = end;
+        let mut i = end;
+        for byte in words.bytes(floor..end).rev() {
+            state = dfa.next_state(state, byte);
+            if dfa.is_special_state(state) {
+                if dfa.is_match_state(state) {
+                    first = i;
+                } else if dfa.is_dead_state(state) || dfa.is_quit_state(state) {
+                    return first;
+                }
+            }
+            i -= 1;
+        }
+        state = match byte_at(words, floor.checked_sub(1)) {
+            Some(byte) => dfa.next_state(state, byte),
+            None => dfa.next_eoi_state(state),
+        };
+        if dfa.is_match_state(state) {
+            first = floor;
+        }
+        first
+    }
+}
+
+fn byte_at(words: &ReversedWords, addr: Option<u64>) -> Option<u8> {
+    addr.and_then(|addr| words.bytes(addr..addr.saturating_add(1)).next())
+}
+
+/// Matches of a [`ByteRegex`] in a region, see [`ReversedWords::find_regex`].
+pub struct RegexMatches<'r, 'w, 'a> {
+    regex: &'r ByteRegex,
+    words: &'w ReversedWords<'a>,
+    pos: u64,
+    end: u64,
+    last_end: Option<u64>,
+}
+
+impl Iterator for RegexMatches<'_, '_, '_> {
+    type Item = Range<u64>;
+
+    fn next(&mut self) -> Option<Range<u64>> {
+        while self.pos <= self.end {
+            let end = self.regex.match_end(self.words, self.pos, self.end)?;
+            let start = self.regex.match_start(self.words, self.pos, end);
+            if start == end && self.last_end == Some(end) {
+                // an empty match right after the previous one, try again one byte on
+                self.pos = end + 1;
+                continue;
+            }
+            self.pos = if start == end { end + 1 } else { end };
+            self.last_end = Some(end);
+            return Some(start..end);
+        }
+        None
+    }
+}
+
+impl<'a> ReversedWords<'a> {
+    /// Non-overlapping matches of `regex` in `range` (clamped to the view), leftmost first, as
+    /// ranges of logical addresses. Assertions like `^` and `\b` see the bytes around the range,
+    /// so `^` only matches at address 0.
+    pub fn find_regex<'r, 'w>(&'w self, regex: &'r ByteRegex, range: Range<u64>) -> RegexMatches<'r, 'w, 'a> {
+        let end = range.end.min(self.reachable_len());
+        RegexMatches { regex, words: self, pos: range.start.min(end), end, last_end: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{regex::*, ReversedWords};
+
+    #[test]
+    fn matches_across_word_boundaries() {
+        let mut data = vec![0u8; 16];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_at(2, b"ab12cd345").unwrap();
+        let digits = ByteRegex::new("[0-9]+").unwrap();
+        assert_eq!(vec![4..6, 8..11], words.find_regex(&digits, 0..16).collect::<Vec<_>>());
+        assert_eq!(vec![5..6, 8..9], words.find_regex(&digits, 5..9).collect::<Vec<_>>());
+
+        let binary = ByteRegex::new(r"\x00\x00a.").unwrap();
+        assert_eq!(vec![0..4], words.find_regex(&binary, 0..16).collect::<Vec<_>>());
+        let anchored = ByteRegex::new(r"^\x00b?").unwrap();
+        assert_eq!(vec![0..1], words.find_regex(&anchored, 0..16).collect::<Vec<_>>());
+        assert_eq!(0, words.find_regex(&anchored, 1..16).count());
+
+        let empty = ByteRegex::new("[a-z]*").unwrap();
+        assert_eq!(vec![0..0, 1..1, 2..4, 5..5, 6..8, 9..9], words.find_regex(&empty, 0..9).collect::<Vec<_>>());
+        assert!(matches!(ByteRegex::new("("), Err(ReversedWordsError::InvalidRegex { .. })));
+    }
+}
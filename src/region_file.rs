@@ -0,0 +1,117 @@
+//! Dumping a range of the view to a file and loading it back.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    ops::Range,
+    path::Path,
+};
+
+use crate::{ReversedWords, ReversedWordsError};
+
+// bytes copied per step when converting to or from logical order
+const CHUNK_SIZE: usize = 0x1_0000;
+
+/// Which order the bytes of a region file are in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// As the target sees them, what most tools and other emulators expect.
+    #[default]
+    Logical,
+    /// Exactly as stored, swapped words (and nibbles) included. The range must be word aligned.
+    Storage,
+}
+
+impl ReversedWords<'_> {
+    /// Write the bytes in `range` to the file at `path`, replacing it.
+    pub fn save_region<P: AsRef<Path>>(&self, range: Range<u64>, path: P, order: ByteOrder) -> std::io::Result<()> {
+        self.check_range(&range)?;
+        if order == ByteOrder::Storage {
+            self.check_raw_region(&range)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        match order {
+            ByteOrder::Logical => {
+                let mut chunk = vec![0u8; CHUNK_SIZE.min((range.end - range.start) as usize)];
+                let mut position = range.start;
+                while position < range.end {
+                    let len = chunk.len().min((range.end - position) as usize);
+                    self.read_at(position, &mut chunk[..len])?;
+                    file.write_all(&chunk[..len])?;
+                    position += len as u64;
+                }
+            }
+            ByteOrder::Storage => {
+                file.write_all(&self.cursor.get_ref()[range.start as usize..range.end as usize])?;
+            }
+        }
+        file.flush()
+    }
+
+    /// Load the whole file at `path` to start at `addr`, returning how many bytes it had. Fails
+    /// without changing anything if it doesn't fit.
+    pub fn load_region<P: AsRef<Path>>(&mut self, path: P, addr: u64, order: ByteOrder) -> std::io::Result<u64> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let range = addr..addr.saturating_add(len);
+        self.check_range(&range)?;
+        if order == ByteOrder::Storage {
+            self.check_raw_region(&range)?;
+        }
+        let mut file = BufReader::new(file);
+        match order {
+            ByteOrder::Logical => {
+                let mut chunk = vec![0u8; CHUNK_SIZE.min(len as usize)];
+                let mut position = addr;
+                while position < range.end {
+                    let len = chunk.len().min((range.end - position) as usize);
+                    file.read_exact(&mut chunk[..len])?;
+                    self.write_at(position, &chunk[..len])?;
+                    position += len as u64;
+                }
+            }
+            ByteOrder::Storage => {
+                file.read_exact(&mut self.cursor.get_mut()[addr as usize..range.end as usize])?;
+            }
+        }
+        Ok(len)
+    }
+
+    // a storage order region has to cover whole words; a partial last word is fine at the end
+    fn check_raw_region(&self, range: &Range<u64>) -> Result<(), ReversedWordsError> {
+        let word_size = self.word_size as u64;
+        for position in [range.start, range.end] {
+            if !position.is_multiple_of(word_size) && position != self.len {
+                return Err(ReversedWordsError::Misaligned { position, word_size: self.word_size });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn save_and_load_regions() {
+        let path = std::env::temp_dir().join(format!("reversed-region-{}", std::process::id()));
+        let mut data: Vec<u8> = (0..16).collect();
+        let mut words = ReversedWords::new(&mut data);
+
+        words.save_region(2..6, &path, ByteOrder::Logical).unwrap();
+        assert_eq!(vec![1, 0, 7, 6], std::fs::read(&path).unwrap());
+        assert_eq!(4, words.load_region(&path, 12, ByteOrder::Logical).unwrap());
+        assert_eq!(vec![1, 0, 7, 6], words.to_logical_vec(12..16).unwrap());
+
+        words.save_region(4..8, &path, ByteOrder::Storage).unwrap();
+        assert_eq!(vec![4, 5, 6, 7], std::fs::read(&path).unwrap());
+        words.load_region(&path, 0, ByteOrder::Storage).unwrap();
+        assert_eq!(0x0706_0504, words.read_u32(0).unwrap());
+
+        assert!(words.save_region(2..6, &path, ByteOrder::Storage).is_err());
+        assert!(words.load_region(&path, 14, ByteOrder::Logical).is_err());
+        assert_eq!(vec![1, 0, 7, 6], words.to_logical_vec(12..16).unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+}
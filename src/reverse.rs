@@ -0,0 +1,262 @@
+//! Iterating the logical stream from either end, and reading backwards.
+//!
+//! [`ReversedWords::bytes`] and [`ReversedWords::values`] are double ended, so `.rev()` walks a
+//! region downwards (a stack growing towards lower addresses, say) straight from storage, and
+//! [`ReversedWords::read_backwards`] fills a buffer with the bytes below an address, nearest
+//! first. None of them use or move the cursor.
+//!
+//! [`ReversedWords::logical_eq`] and [`ReversedWords::logical_hash`] compare and hash what the
+//! bytes mean rather than how they are stored, so views with different word sizes over
+//! differently swapped buffers of the same memory are equal.
+
+use std::{hash::Hasher, iter::FusedIterator, marker::PhantomData, ops::Range};
+
+use binread::Endian;
+
+use crate::{Primitive, ReversedWords, ReversedWordsError};
+
+/// The logical bytes of a range, see [`ReversedWords::bytes`].
+#[derive(Clone)]
+pub struct LogicalBytes<'w, 'a> {
+    words: &'w ReversedWords<'a>,
+    front: u64,
+    back: u64,
+}
+
+impl Iterator for LogicalBytes<'_, '_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.front == self.back {
+            return None;
+        }
+        let byte = self.words.load(self.words.storage_index(self.front).expect("clamped to the view"));
+        self.front += 1;
+        Some(byte)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front) as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for LogicalBytes<'_, '_> {
+    fn next_back(&mut self) -> Option<u8> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.words.load(self.words.storage_index(self.back).expect("clamped to the view")))
+    }
+}
+
+impl ExactSizeIterator for LogicalBytes<'_, '_> {}
+impl FusedIterator for LogicalBytes<'_, '_> {}
+
+/// `(address, value)` for each whole `T` of a range, see [`ReversedWords::values`].
+pub struct LogicalValues<'w, 'a, T> {
+    words: &'w ReversedWords<'a>,
+    endian: Endian,
+    // addresses of the next value from the front, and one past the last from the back
+    front: u64,
+    back: u64,
+    value: PhantomData<T>,
+}
+
+impl<T: Primitive> LogicalValues<'_, '_, T> {
+    const SIZE: u64 = std::mem::size_of::<T>() as u64;
+
+    fn value_at(&self, addr: u64) -> T {
+        let mut bytes = T::Bytes::default();
+        for (i, byte) in bytes.as_mut().iter_mut().enumerate() {
+            *byte = self.words.load(self.words.storage_index(addr + i as u64).expect("clamped to the view"));
+        }
+        T::from_bytes(bytes, self.endian)
+    }
+}
+
+impl<T: Primitive> Iterator for LogicalValues<'_, '_, T> {
+    type Item = (u64, T);
+
+    fn next(&mut self) -> Option<(u64, T)> {
+        if self.front == self.back {
+            return None;
+        }
+        let addr = self.front;
+        self.front += Self::SIZE;
+        Some((addr, self.value_at(addr)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = ((self.back - self.front) / Self::SIZE) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<T: Primitive> DoubleEndedIterator for LogicalValues<'_, '_, T> {
+    fn next_back(&mut self) -> Option<(u64, T)> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= Self::SIZE;
+        Some((self.back, self.value_at(self.back)))
+    }
+}
+
+impl<T: Primitive> ExactSizeIterator for LogicalValues<'_, '_, T> {}
+impl<T: Primitive> FusedIterator for LogicalValues<'_, '_, T> {}
+
+/// Hash a logical byte stream of `len` bytes the same way for every kind of view.
+pub(crate) fn hash_logical<H: Hasher>(bytes: impl Iterator<Item = u8>, len: u64, state: &mut H) {
+    state.write_u64(len);
+    let mut chunk = [0u8; 256];
+    let mut n = 0;
+    for byte in bytes {
+        chunk[n] = byte;
+        n += 1;
+        if n == chunk.len() {
+            state.write(&chunk);
+            n = 0;
+        }
+    }
+    state.write(&chunk[..n]);
+}
+
+impl<'a> ReversedWords<'a> {
+    // the logical bytes that can be reached: a partial word at the end has no logical
+    // position for its first byte, so none of it is
+    pub(crate) fn reachable_len(&self) -> u64 {
+        self.len - self.len % self.word_size as u64
+    }
+
+    // `range` clamped to the reachable bytes, empty if it is reversed
+    fn clamp(&self, range: Range<u64>) -> Range<u64> {
+        let end = range.end.min(self.reachable_len());
+        range.start.min(end)..end
+    }
+
+    /// The logical bytes of `range`, clamped to the view. A partial word at the end of the
+    /// storage is left out, as it is by [`ReversedWords::read_at`].
+    pub fn bytes(&self, range: Range<u64>) -> LogicalBytes<'_, 'a> {
+        let range = self.clamp(range);
+        LogicalBytes { words: self, front: range.start, back: range.end }
+    }
+
+    /// Every whole `T` in `range` (clamped to the view), starting at `range.start` and stepping by
+    /// `size_of::<T>()`. A partial value at the end is left out, so `.rev()` starts from the last
+    /// whole one.
+    pub fn values<T: Primitive>(&self, range: Range<u64>, endian: Endian) -> LogicalValues<'_, 'a, T> {
+        let range = self.clamp(range);
+        let size = std::mem::size_of::<T>() as u64;
+        let back = range.start + (range.end - range.start) / size * size;
+        LogicalValues { words: self, endian, front: range.start, back, value: PhantomData }
+    }
+
+    /// Read the logical bytes below `end` into `buf`, nearest first: `buf[0]` is the byte at
+    /// `end - 1`, `buf[1]` the one at `end - 2` and so on. Returns how many were read, less than
+    /// `buf.len()` if address 0 is reached.
+    pub fn read_backwards(&self, end: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        if end > self.reachable_len() {
+            return Err(ReversedWordsError::RangeOutOfRange { start: end.saturating_sub(buf.len() as u64), end, len: self.len }.into());
+        }
+        let mut n = 0;
+        for (byte, value) in buf.iter_mut().zip(self.bytes(0..end).rev()) {
+            *byte = value;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// Whether both views read as the same logical bytes, whatever their word size, nibble
+    /// swapping or storage.
+    pub fn logical_eq(&self, other: &ReversedWords) -> bool {
+        self.reachable_len() == other.reachable_len() && self.bytes(0..u64::MAX).eq(other.bytes(0..u64::MAX))
+    }
+
+    /// Hash the logical bytes, consistent with [`ReversedWords::logical_eq`] and with the `Hash`
+    /// of a [`crate::ReversedWordsRef`] over the same memory.
+    pub fn logical_hash<H: Hasher>(&self, state: &mut H) {
+        hash_logical(self.bytes(0..u64::MAX), self.reachable_len(), state);
+    }
+
+    /// Logical address of the last occurrence of `needle` that ends at or before `end`.
+    pub fn rfind(&self, needle: &[u8], end: u64) -> Option<u64> {
+        let end = end.min(self.reachable_len());
+        let len = needle.len() as u64;
+        if needle.is_empty() || len > end {
+            return None;
+        }
+        (0..=end - len).rev().find(|&start| self.bytes(start..start + len).eq(needle.iter().copied()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{reverse::*, ReversedWords};
+    use std::hash::Hasher;
+
+    #[test]
+    fn iterates_from_both_ends() {
+        let mut data: Vec<u8> = (0..10).collect();
+        let words = ReversedWords::new(&mut data);
+        assert_eq!(vec![3, 2, 1, 0, 7, 6, 5, 4], words.bytes(0..99).collect::<Vec<_>>());
+        assert_eq!(vec![4, 5, 6, 7], words.bytes(0..99).rev().take(4).collect::<Vec<_>>());
+        let mut bytes = words.bytes(2..6);
+        assert_eq!((Some(1), Some(6), 2), (bytes.next(), bytes.next_back(), bytes.len()));
+        assert_eq!(0, words.bytes(6..99).rev().skip(2).len());
+        assert_eq!(0, words.bytes(9..99).len());
+
+        let values: Vec<(u64, u16)> = words.values(1..10, Endian::Big).rev().collect();
+        assert_eq!(vec![(5, 0x0605), (3, 0x0007), (1, 0x0201)], values);
+        assert_eq!(3, words.values::<u16>(1..10, Endian::Big).len());
+    }
+
+    #[test]
+    fn reads_backwards_and_searches_from_the_end() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let words = ReversedWords::new(&mut data);
+        let mut buf = [0u8; 6];
+        assert_eq!(3, words.read_backwards(3, &mut buf).unwrap());
+        assert_eq!([1, 2, 3], buf[..3]);
+        assert!(words.read_backwards(9, &mut buf).is_err());
+
+        let mut data = vec![0xAA, 0, 0xAA, 0];
+        let words = ReversedWords::new_with_word_size(&mut data, 1);
+        assert_eq!(Some(2), words.rfind(&[0xAA], 4));
+        assert_eq!(Some(0), words.rfind(&[0xAA], 2));
+        assert_eq!(Some(1), words.rfind(&[0, 0xAA], 4));
+        assert_eq!(None, words.rfind(&[0xAA, 0, 0xAA, 0, 0], 9));
+    }
+
+    #[test]
+    fn differently_stored_views_are_equal() {
+        fn hash(words: &ReversedWords) -> u64 {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            words.logical_hash(&mut hasher);
+            hasher.finish()
+        }
+        let memory: Vec<u8> = (0..600u32).map(|i| (i * 13) as u8).collect();
+        let mut by_four = vec![0u8; 600];
+        ReversedWords::new(&mut by_four).write_at(0, &memory).unwrap();
+        let mut by_two = vec![0u8; 600];
+        ReversedWords::new_with_word_size(&mut by_two, 2).write_at(0, &memory).unwrap();
+        let mut plain = memory.clone();
+        let four = ReversedWords::new(&mut by_four);
+        let two = ReversedWords::new_with_word_size(&mut by_two, 2);
+        assert!(four.logical_eq(&two));
+        assert_eq!(hash(&four), hash(&two));
+        assert_eq!(hash(&four), {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            std::hash::Hash::hash(&four.as_ref_view(), &mut hasher);
+            hasher.finish()
+        });
+        assert_eq!(four.as_ref_view(), two.as_ref_view());
+        let one = ReversedWords::new_with_word_size(&mut plain, 1);
+        assert!(one.logical_eq(&four));
+        let mut changed = memory;
+        changed[599] ^= 1;
+        assert!(!ReversedWords::new_with_word_size(&mut changed, 1).logical_eq(&four));
+        assert!(!ReversedWords::new_with_word_size(&mut [0u8; 4], 1).logical_eq(&ReversedWords::new(&mut [0u8; 8])));
+    }
+}
@@ -0,0 +1,282 @@
+//! Locating RDRAM inside N64 emulator savestates.
+//!
+//! Both Project64 and Mupen64Plus dump RDRAM as host order (little endian) 32-bit words, which is
+//! exactly the 4-byte reversed layout, so the block is exposed as a [`ReversedWords`] view with
+//! N64 (big endian) addresses starting at 0. Edits go straight into the savestate buffer and are
+//! written back with [`Savestate::save`] or [`Savestate::flush`].
+//!
+//! With the `compression` feature, gzip (Mupen64Plus' default) and zlib compressed savestates are
+//! decompressed on load and recompressed the same way when saved.
+
+use std::{ops::Range, path::{Path, PathBuf}};
+
+use crate::{ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SavestateFormat {
+    /// Uncompressed Project64 `.pj` savestate.
+    Project64,
+    /// Uncompressed Mupen64Plus `.st` savestate (as found inside the gzip container).
+    Mupen64Plus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zlib,
+}
+
+impl Compression {
+    pub fn detect(data: &[u8]) -> Compression {
+        match data {
+            [0x1F, 0x8B, ..] => Compression::Gzip,
+            // deflate method with a valid header checksum
+            [cmf, flg, ..] if cmf & 0x0F == 8 && (*cmf as u16 * 256 + *flg as u16).is_multiple_of(31) => Compression::Zlib,
+            _ => Compression::None,
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    fn decompress(self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        use std::io::Read;
+        let mut out = Vec::new();
+        match self {
+            Compression::None => return Ok(data),
+            Compression::Gzip => flate2::read::MultiGzDecoder::new(&data[..]).read_to_end(&mut out)?,
+            Compression::Zlib => flate2::read::ZlibDecoder::new(&data[..]).read_to_end(&mut out)?,
+        };
+        Ok(out)
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn decompress(self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data),
+            _ => Err(ReversedWordsError::CompressedSavestate.into()),
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        use std::io::Write;
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Compression::Zlib => {
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            _ => Err(ReversedWordsError::CompressedSavestate.into()),
+        }
+    }
+}
+
+const PROJECT64_MAGIC: u32 = 0x23D8A6C8;
+const PROJECT64_RDRAM_OFFSET: usize = 0x75C;
+const MUPEN64PLUS_MAGIC: &[u8] = b"M64+SAVE";
+const MUPEN64PLUS_RDRAM_OFFSET: usize = 0x1B0;
+const MUPEN64PLUS_RDRAM_SIZE: usize = 0x800000;
+
+impl SavestateFormat {
+    /// Guess the format from the start of an uncompressed savestate.
+    pub fn detect(data: &[u8]) -> Option<SavestateFormat> {
+        if data.starts_with(MUPEN64PLUS_MAGIC) {
+            Some(SavestateFormat::Mupen64Plus)
+        } else if data.len() >= 4 && u32::from_le_bytes([data[0], data[1], data[2], data[3]]) == PROJECT64_MAGIC {
+            Some(SavestateFormat::Project64)
+        } else {
+            None
+        }
+    }
+
+    /// Where the RDRAM block lives in a savestate of this format.
+    fn rdram_range(self, data: &[u8]) -> Result<Range<usize>, ReversedWordsError> {
+        let range = match self {
+            SavestateFormat::Project64 => {
+                let size = data.get(4..8).map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+                PROJECT64_RDRAM_OFFSET..PROJECT64_RDRAM_OFFSET + size
+            }
+            SavestateFormat::Mupen64Plus => MUPEN64PLUS_RDRAM_OFFSET..MUPEN64PLUS_RDRAM_OFFSET + MUPEN64PLUS_RDRAM_SIZE,
+        };
+        if range.end > data.len() {
+            return Err(ReversedWordsError::TruncatedSavestate { needed: range.end as u64, len: data.len() as u64 });
+        }
+        Ok(range)
+    }
+}
+
+/// A decompressed savestate held in memory, with its RDRAM block located.
+pub struct Savestate {
+    data: Vec<u8>,
+    format: SavestateFormat,
+    compression: Compression,
+    rdram: Range<usize>,
+    path: Option<PathBuf>,
+}
+
+impl Savestate {
+    /// Load a savestate from the contents of a savestate file, decompressing it if needed.
+    pub fn from_file_bytes(data: Vec<u8>) -> std::io::Result<Savestate> {
+        let compression = match SavestateFormat::detect(&data) {
+            Some(_) => Compression::None,
+            None => Compression::detect(&data),
+        };
+        let mut state = Savestate::from_bytes(compression.decompress(data)?)?;
+        state.compression = compression;
+        Ok(state)
+    }
+
+    /// Load an uncompressed savestate.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Savestate, ReversedWordsError> {
+        let format = SavestateFormat::detect(&data).ok_or(ReversedWordsError::UnknownSavestateFormat)?;
+        Savestate::from_bytes_with_format(data, format)
+    }
+
+    pub fn from_bytes_with_format(data: Vec<u8>, format: SavestateFormat) -> Result<Savestate, ReversedWordsError> {
+        let rdram = format.rdram_range(&data)?;
+        Ok(Savestate { data, format, compression: Compression::None, rdram, path: None })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Savestate> {
+        let mut state = Savestate::from_file_bytes(std::fs::read(path.as_ref())?)?;
+        state.path = Some(path.as_ref().to_path_buf());
+        Ok(state)
+    }
+
+    /// Write the savestate, including any edits made through [`Savestate::rdram`],
+    /// compressed the same way it was when loaded.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_file_bytes()?)
+    }
+
+    /// Write the savestate back to the file it was opened from.
+    pub fn flush(&self) -> std::io::Result<()> {
+        match &self.path {
+            Some(path) => self.save(path),
+            None => Err(std::io::Error::new(std::io::ErrorKind::NotFound, "savestate was not opened from a file")),
+        }
+    }
+
+    /// The savestate as it would be written to a file, compressed the same way it was when loaded.
+    pub fn to_file_bytes(&self) -> std::io::Result<Vec<u8>> {
+        self.compression.compress(&self.data)
+    }
+
+    pub fn format(&self) -> SavestateFormat {
+        self.format
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// A view of RDRAM, addressed from 0 in N64 byte order.
+    pub fn rdram(&mut self) -> ReversedWords<'_> {
+        ReversedWords::new(&mut self.data[self.rdram.clone()])
+    }
+
+    /// The decompressed savestate.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use crate::savestate::*;
+    use crate::*;
+
+    fn project64_state(rdram_size: u32) -> Vec<u8> {
+        let mut data = vec![0u8; PROJECT64_RDRAM_OFFSET + rdram_size as usize + 0x2000];
+        data[0..4].copy_from_slice(&PROJECT64_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&rdram_size.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn project64_rdram_is_word_swapped() {
+        let mut data = project64_state(0x400000);
+        // N64 word 0x80001234 at RDRAM offset 0x10, stored little endian
+        data[PROJECT64_RDRAM_OFFSET + 0x10..PROJECT64_RDRAM_OFFSET + 0x14].copy_from_slice(&0x80001234u32.to_le_bytes());
+        let mut state = Savestate::from_bytes(data).unwrap();
+        assert_eq!(SavestateFormat::Project64, state.format());
+        let mut rdram = state.rdram();
+        assert_eq!(0x400000, rdram.len());
+        assert_eq!(0x80001234, rdram.read_u32_at(0x10, Endian::Big).unwrap());
+        rdram.write_u16_at(0x12, 0xBEEF, Endian::Big).unwrap();
+        let bytes = state.into_bytes();
+        assert_eq!(0x8000BEEF, u32::from_le_bytes(bytes[PROJECT64_RDRAM_OFFSET + 0x10..PROJECT64_RDRAM_OFFSET + 0x14].try_into().unwrap()));
+    }
+
+    #[test]
+    fn mupen64plus_rdram() {
+        let mut data = vec![0u8; MUPEN64PLUS_RDRAM_OFFSET + MUPEN64PLUS_RDRAM_SIZE + 0x100];
+        data[..8].copy_from_slice(MUPEN64PLUS_MAGIC);
+        data[MUPEN64PLUS_RDRAM_OFFSET..MUPEN64PLUS_RDRAM_OFFSET + 4].copy_from_slice(&[4, 3, 2, 1]);
+        let mut state = Savestate::from_bytes(data).unwrap();
+        assert_eq!(SavestateFormat::Mupen64Plus, state.format());
+        assert_eq!(0x01020304, state.rdram().read_u32_at(0, Endian::Big).unwrap());
+    }
+
+    #[test]
+    fn uncompressed_file_round_trip() {
+        let data = project64_state(0x1000);
+        let state = Savestate::from_file_bytes(data.clone()).unwrap();
+        assert_eq!(Compression::None, state.compression());
+        assert_eq!(data, state.to_file_bytes().unwrap());
+        assert_eq!(std::io::ErrorKind::NotFound, state.flush().unwrap_err().kind());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn gzip_state_is_recompressed() {
+        use std::io::Write;
+        let mut data = project64_state(0x1000);
+        data[PROJECT64_RDRAM_OFFSET..PROJECT64_RDRAM_OFFSET + 4].copy_from_slice(&[4, 3, 2, 1]);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut state = Savestate::from_file_bytes(compressed).unwrap();
+        assert_eq!(Compression::Gzip, state.compression());
+        assert_eq!(0x01020304, state.rdram().read_u32_at(0, Endian::Big).unwrap());
+        state.rdram().write_u32_at(0, 0xAABBCCDD, Endian::Big).unwrap();
+
+        let reloaded = Savestate::from_file_bytes(state.to_file_bytes().unwrap()).unwrap();
+        assert_eq!(Compression::Gzip, reloaded.compression());
+        assert_eq!(&[0xDD, 0xCC, 0xBB, 0xAA], &reloaded.as_bytes()[PROJECT64_RDRAM_OFFSET..PROJECT64_RDRAM_OFFSET + 4]);
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn compressed_state_needs_feature() {
+        let error = Savestate::from_file_bytes(vec![0x1F, 0x8B, 8, 0]).err().unwrap();
+        assert_eq!(Some(&ReversedWordsError::CompressedSavestate), ReversedWordsError::from_io(&error));
+    }
+
+    #[test]
+    fn truncated_and_unknown_states_are_rejected() {
+        let mut data = project64_state(0x400000);
+        data.truncate(0x1000);
+        assert!(matches!(Savestate::from_bytes(data), Err(ReversedWordsError::TruncatedSavestate { .. })));
+        assert!(matches!(Savestate::from_bytes(vec![0u8; 16]), Err(ReversedWordsError::UnknownSavestateFormat)));
+    }
+}
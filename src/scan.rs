@@ -0,0 +1,645 @@
+//! Scanning a view for typed values, cheat search style.
+//!
+//! A [`ValueScan`] reads a value of type `T` at every `step` bytes of a region and keeps the
+//! addresses where a predicate holds. First scans over a whole RAM match millions of addresses, so
+//! [`ScanResults`] doesn't keep a `Vec<u64>`: matches are stored as a bitmap with one bit per
+//! scanned address, or as delta encoded varints when that is smaller, and decoded as they are
+//! iterated.
+//!
+//! [`ValueScan::scan_backend`] scans storage that isn't in memory, such as a dump file larger than
+//! the address space, reading a window at a time.
+//!
+//! A [`ScanSession`] carries a search across restarts of the tool: it keeps the results with the
+//! value each match had at the last scan, narrows them by comparing old and new values (for
+//! searching for unknown values that "decreased" or "didn't change"), and saves to and loads from
+//! any writer and reader.
+
+use std::{
+    convert::TryInto,
+    io::{Read, Write},
+    marker::PhantomData,
+    ops::Range,
+};
+
+use binread::Endian;
+
+use crate::{
+    backend::{MemoryBackend, ReversedBackend},
+    Primitive, ReversedWords, ReversedWordsError,
+};
+
+// logical bytes read at once by `scan_backend`
+const WINDOW_SIZE: u64 = 0x1_0000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValueScan<T> {
+    step: u64,
+    endian: Endian,
+    region: (u64, u64),
+    value: PhantomData<T>,
+}
+
+impl<T: Primitive> ValueScan<T> {
+    /// Scan for big endian `T`s at naturally aligned addresses.
+    pub fn new() -> ValueScan<T> {
+        ValueScan { step: std::mem::size_of::<T>() as u64, endian: Endian::Big, region: (0, u64::MAX), value: PhantomData }
+    }
+
+    /// How far apart the addresses read are, usually 1, 2, 4 or 8. A step of 0 is treated as 1.
+    pub fn with_step(mut self, step: u64) -> ValueScan<T> {
+        self.step = step.max(1);
+        self
+    }
+
+    pub fn with_endian(mut self, endian: Endian) -> ValueScan<T> {
+        self.endian = endian;
+        self
+    }
+
+    /// Only scan addresses in `region`, starting at its start. Defaults to the whole view.
+    pub fn with_region(mut self, region: Range<u64>) -> ValueScan<T> {
+        self.region = (region.start, region.end);
+        self
+    }
+
+    // how many addresses are scanned in a view of `len` bytes
+    fn slots(&self, len: u64) -> u64 {
+        let size = std::mem::size_of::<T>() as u64;
+        let (start, end) = (self.region.0, self.region.1.min(len));
+        match start.checked_add(size) {
+            Some(first_end) if end >= first_end => (end - first_end) / self.step + 1,
+            _ => 0,
+        }
+    }
+
+    /// The addresses in the view whose value satisfies `predicate`.
+    pub fn scan(&self, words: &ReversedWords, mut predicate: impl FnMut(T) -> bool) -> ScanResults<T> {
+        let start = self.region.0;
+        let slots = self.slots(words.reachable_len());
+        let mut bitmap = vec![0u64; slots.div_ceil(64) as usize];
+        let mut count = 0;
+        for slot in 0..slots {
+            let addr = start + slot * self.step;
+            if words.read_pod::<T>(addr, self.endian).map(&mut predicate).unwrap_or(false) {
+                bitmap[(slot / 64) as usize] |= 1 << (slot % 64);
+                count += 1;
+            }
+        }
+        ScanResults::from_bitmap(*self, start, slots, bitmap, count)
+    }
+
+    /// [`ValueScan::scan`] over a backend, which fails if the backend does.
+    pub fn scan_backend<B: MemoryBackend>(&self, words: &mut ReversedBackend<B>, mut predicate: impl FnMut(T) -> bool) -> std::io::Result<ScanResults<T>> {
+        let size = std::mem::size_of::<T>() as u64;
+        let start = self.region.0;
+        let slots = self.slots(words.len());
+        let mut bitmap = vec![0u64; slots.div_ceil(64) as usize];
+        let mut count = 0;
+        let per_window = (WINDOW_SIZE / self.step).max(1);
+        let mut window = Vec::new();
+        let mut slot = 0;
+        while slot < slots {
+            let n = per_window.min(slots - slot);
+            window.resize(((n - 1) * self.step + size) as usize, 0);
+            let read = words.read_at(start + slot * self.step, &mut window)? as u64;
+            for i in 0..n {
+                let at = i * self.step;
+                if at + size > read {
+                    break;
+                }
+                let mut bytes = T::Bytes::default();
+                bytes.as_mut().copy_from_slice(&window[at as usize..(at + size) as usize]);
+                if predicate(T::from_bytes(bytes, self.endian)) {
+                    bitmap[((slot + i) / 64) as usize] |= 1 << ((slot + i) % 64);
+                    count += 1;
+                }
+            }
+            slot += n;
+        }
+        Ok(ScanResults::from_bitmap(*self, start, slots, bitmap, count))
+    }
+}
+
+impl<T: Primitive> Default for ValueScan<T> {
+    fn default() -> ValueScan<T> {
+        ValueScan::new()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Matches {
+    /// Bit `i` is set if the `i`th scanned address matched.
+    Bitmap(Vec<u64>),
+    /// LEB128 gaps between the slot numbers of consecutive matches, the first counted from 0.
+    Deltas(Vec<u8>),
+}
+
+/// The addresses a [`ValueScan`] matched, in address order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanResults<T> {
+    scan: ValueScan<T>,
+    start: u64,
+    slots: u64,
+    count: usize,
+    matches: Matches,
+}
+
+fn push_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+impl<T: Primitive> ScanResults<T> {
+    // pick whichever encoding of the set bits is smaller
+    fn from_bitmap(scan: ValueScan<T>, start: u64, slots: u64, bitmap: Vec<u64>, count: usize) -> ScanResults<T> {
+        let mut results = ScanResults { scan, start, slots, count, matches: Matches::Bitmap(bitmap) };
+        // a varint gap is at least a byte, so only sparse results can be smaller as deltas
+        if count < results.bitmap_bytes() {
+            let mut deltas = Vec::new();
+            let mut previous = 0;
+            for slot in results.slots() {
+                push_varint(&mut deltas, slot - previous);
+                previous = slot;
+            }
+            if deltas.len() < results.bitmap_bytes() {
+                deltas.shrink_to_fit();
+                results.matches = Matches::Deltas(deltas);
+            }
+        }
+        results
+    }
+
+    fn bitmap_bytes(&self) -> usize {
+        self.slots.div_ceil(64) as usize * 8
+    }
+
+    fn slots(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+        match &self.matches {
+            Matches::Bitmap(bitmap) => Box::new(bitmap.iter().enumerate().flat_map(|(i, bits)| {
+                let mut bits = *bits;
+                std::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let bit = bits.trailing_zeros() as u64;
+                    bits &= bits - 1;
+                    Some(i as u64 * 64 + bit)
+                })
+            })),
+            Matches::Deltas(deltas) => {
+                let mut pos = 0;
+                let mut slot = 0;
+                Box::new(std::iter::from_fn(move || {
+                    if pos >= deltas.len() {
+                        return None;
+                    }
+                    slot += read_varint(deltas, &mut pos);
+                    Some(slot)
+                }))
+            }
+        }
+    }
+
+    /// Re-read every match in `words` and drop those whose value no longer satisfies
+    /// `predicate`, or can no longer be read. Narrowing a search is a first scan followed by
+    /// revalidating after each change in the target.
+    pub fn revalidate(&mut self, words: &ReversedWords, mut predicate: impl FnMut(T) -> bool) {
+        let endian = self.scan.endian;
+        self.retain(|addr| words.read_pod::<T>(addr, endian).map(&mut predicate).unwrap_or(false));
+    }
+
+    // keep the matches `keep` returns true for, calling it once per match in address order
+    fn retain(&mut self, mut keep: impl FnMut(u64) -> bool) {
+        let (start, step) = (self.start, self.scan.step);
+        let mut still_matches = |slot: u64| keep(start + slot * step);
+        let mut count = 0;
+        match &mut self.matches {
+            Matches::Bitmap(bitmap) => {
+                for (i, bits) in bitmap.iter_mut().enumerate() {
+                    let mut remaining = *bits;
+                    while remaining != 0 {
+                        let bit = remaining.trailing_zeros() as u64;
+                        remaining &= remaining - 1;
+                        if still_matches(i as u64 * 64 + bit) {
+                            count += 1;
+                        } else {
+                            *bits &= !(1 << bit);
+                        }
+                    }
+                }
+            }
+            Matches::Deltas(deltas) => {
+                let mut kept = Vec::new();
+                let (mut pos, mut slot, mut previous) = (0, 0, 0);
+                while pos < deltas.len() {
+                    slot += read_varint(deltas, &mut pos);
+                    if still_matches(slot) {
+                        push_varint(&mut kept, slot - previous);
+                        previous = slot;
+                        count += 1;
+                    }
+                }
+                kept.shrink_to_fit();
+                *deltas = kept;
+            }
+        }
+        self.count = count;
+        if let Matches::Bitmap(bitmap) = &mut self.matches {
+            let bitmap = std::mem::take(bitmap);
+            *self = ScanResults::from_bitmap(self.scan, start, self.slots, bitmap, count);
+        }
+    }
+
+    /// How many addresses matched.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The step and endianness the scan used.
+    pub fn scan(&self) -> ValueScan<T> {
+        self.scan
+    }
+
+    /// Bytes used to store the matches.
+    pub fn heap_size(&self) -> usize {
+        match &self.matches {
+            Matches::Bitmap(bitmap) => bitmap.len() * 8,
+            Matches::Deltas(deltas) => deltas.len(),
+        }
+    }
+
+    /// The matched addresses, in order.
+    pub fn addresses(&self) -> impl Iterator<Item = u64> + '_ {
+        let (start, step) = (self.start, self.scan.step);
+        self.slots().map(move |slot| start + slot * step)
+    }
+
+    /// Each matched address with the value now at it in `words`, skipping any that can't be read.
+    pub fn values<'r>(&'r self, words: &'r ReversedWords) -> impl Iterator<Item = (u64, T)> + 'r {
+        let endian = self.scan.endian;
+        self.addresses().filter_map(move |addr| words.read_pod::<T>(addr, endian).ok().map(|value| (addr, value)))
+    }
+
+    pub fn contains(&self, addr: u64) -> bool {
+        let offset = match addr.checked_sub(self.start) {
+            Some(offset) if offset.is_multiple_of(self.scan.step) && offset / self.scan.step < self.slots => offset / self.scan.step,
+            _ => return false,
+        };
+        match &self.matches {
+            Matches::Bitmap(bitmap) => bitmap[(offset / 64) as usize] & 1 << (offset % 64) != 0,
+            Matches::Deltas(_) => self.slots().take_while(|slot| *slot <= offset).any(|slot| slot == offset),
+        }
+    }
+}
+
+const SESSION_MAGIC: &[u8; 4] = b"RWS1";
+
+fn invalid_session(reason: &str) -> std::io::Error {
+    ReversedWordsError::InvalidScanSession { reason: reason.to_string() }.into()
+}
+
+/// A search in progress: the results so far and the value at each match when it was last read.
+///
+/// Saved sessions are the magic `RWS1`, then as little endian `u64`s the size of `T`, the step,
+/// the region start and end, the first scanned address, the number of scanned addresses and the
+/// number of matches, then the endianness as a byte (0 big, 1 little, 2 native), the match
+/// encoding as a byte (0 for a bitmap of `u64`s, 1 for varint deltas) and its length in bytes as a
+/// `u64` followed by it, and finally the previous value of each match in address order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanSession<T> {
+    results: ScanResults<T>,
+    // `T::to_bytes` of the value at each match, in address order
+    previous: Vec<u8>,
+}
+
+impl<T: Primitive> ScanSession<T> {
+    /// Start a search with the addresses whose value satisfies `predicate`.
+    pub fn new(scan: ValueScan<T>, words: &ReversedWords, predicate: impl FnMut(T) -> bool) -> ScanSession<T> {
+        let results = scan.scan(words, predicate);
+        let mut previous = Vec::with_capacity(results.len() * std::mem::size_of::<T>());
+        for (_, value) in results.values(words) {
+            previous.extend_from_slice(value.to_bytes(scan.endian).as_ref());
+        }
+        ScanSession { results, previous }
+    }
+
+    /// Start a search for a value that isn't known yet: every address matches.
+    pub fn unknown(scan: ValueScan<T>, words: &ReversedWords) -> ScanSession<T> {
+        ScanSession::new(scan, words, |_| true)
+    }
+
+    pub fn results(&self) -> &ScanResults<T> {
+        &self.results
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Each match with its value as of the last scan.
+    pub fn previous(&self) -> impl Iterator<Item = (u64, T)> + '_ {
+        let endian = self.results.scan.endian;
+        self.results.addresses().zip(self.previous.chunks_exact(std::mem::size_of::<T>())).map(move |(addr, bytes)| {
+            let mut value = T::Bytes::default();
+            value.as_mut().copy_from_slice(bytes);
+            (addr, T::from_bytes(value, endian))
+        })
+    }
+
+    /// Re-read every match and keep those for which `predicate(previous, current)` holds,
+    /// remembering the current values for the next narrowing.
+    pub fn narrow(&mut self, words: &ReversedWords, mut predicate: impl FnMut(T, T) -> bool) {
+        let previous: Vec<T> = self.previous().map(|(_, value)| value).collect();
+        let endian = self.results.scan.endian;
+        let mut kept = Vec::new();
+        let mut index = 0;
+        self.results.retain(|addr| {
+            let old = previous[index];
+            index += 1;
+            match words.read_pod::<T>(addr, endian) {
+                Ok(new) if predicate(old, new) => {
+                    kept.extend_from_slice(new.to_bytes(endian).as_ref());
+                    true
+                }
+                _ => false,
+            }
+        });
+        kept.shrink_to_fit();
+        self.previous = kept;
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let results = &self.results;
+        writer.write_all(SESSION_MAGIC)?;
+        for field in [
+            std::mem::size_of::<T>() as u64,
+            results.scan.step,
+            results.scan.region.0,
+            results.scan.region.1,
+            results.start,
+            results.slots,
+            results.count as u64,
+        ] {
+            writer.write_all(&field.to_le_bytes())?;
+        }
+        let endian = match results.scan.endian {
+            Endian::Big => 0u8,
+            Endian::Little => 1,
+            Endian::Native => 2,
+        };
+        match &results.matches {
+            Matches::Bitmap(bitmap) => {
+                writer.write_all(&[endian, 0])?;
+                writer.write_all(&(bitmap.len() as u64 * 8).to_le_bytes())?;
+                for bits in bitmap {
+                    writer.write_all(&bits.to_le_bytes())?;
+                }
+            }
+            Matches::Deltas(deltas) => {
+                writer.write_all(&[endian, 1])?;
+                writer.write_all(&(deltas.len() as u64).to_le_bytes())?;
+                writer.write_all(deltas)?;
+            }
+        }
+        writer.write_all(&self.previous)
+    }
+
+    /// Load a session saved by [`ScanSession::write_to`] with the same `T`.
+    pub fn read_from<R: Read>(mut reader: R) -> std::io::Result<ScanSession<T>> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let rest = data.strip_prefix(SESSION_MAGIC).ok_or_else(|| invalid_session("missing RWS1 magic"))?;
+        if rest.len() < 7 * 8 + 2 + 8 {
+            return Err(invalid_session("truncated header"));
+        }
+        let field = |i: usize| u64::from_le_bytes(rest[i * 8..i * 8 + 8].try_into().expect("8 bytes"));
+        let size = std::mem::size_of::<T>() as u64;
+        if field(0) != size {
+            return Err(invalid_session("saved for a value of a different size"));
+        }
+        let (step, region, start, slots, count) = (field(1), (field(2), field(3)), field(4), field(5), field(6));
+        if step == 0 {
+            return Err(invalid_session("step of 0"));
+        }
+        let endian = match rest[56] {
+            0 => Endian::Big,
+            1 => Endian::Little,
+            2 => Endian::Native,
+            _ => return Err(invalid_session("unknown endianness")),
+        };
+        let encoding = rest[57];
+        let matches_len = u64::from_le_bytes(rest[58..66].try_into().expect("8 bytes"));
+        let rest = &rest[66..];
+        if (rest.len() as u64) < matches_len {
+            return Err(invalid_session("truncated matches"));
+        }
+        let (encoded, previous) = rest.split_at(matches_len as usize);
+        let mut found = 0u64;
+        let matches = match encoding {
+            0 => {
+                if matches_len != slots.div_ceil(64) * 8 {
+                    return Err(invalid_session("bitmap doesn't cover the scanned addresses"));
+                }
+                let bitmap: Vec<u64> = encoded.chunks_exact(8).map(|bits| u64::from_le_bytes(bits.try_into().expect("8 bytes"))).collect();
+                if slots % 64 != 0 && bitmap.last().is_some_and(|bits| bits >> (slots % 64) != 0) {
+                    return Err(invalid_session("match past the scanned addresses"));
+                }
+                found = bitmap.iter().map(|bits| bits.count_ones() as u64).sum();
+                Matches::Bitmap(bitmap)
+            }
+            1 => {
+                let (mut pos, mut slot) = (0, 0u64);
+                while pos < encoded.len() {
+                    // a slot below `slots` fits in 10 varint bytes
+                    if !encoded[pos..].iter().take(10).any(|byte| byte & 0x80 == 0) {
+                        return Err(invalid_session("truncated varint"));
+                    }
+                    let delta = read_varint(encoded, &mut pos);
+                    slot = match slot.checked_add(delta) {
+                        Some(next) if next < slots && (found == 0 || delta > 0) => next,
+                        _ => return Err(invalid_session("match past the scanned addresses")),
+                    };
+                    found += 1;
+                }
+                Matches::Deltas(encoded.to_vec())
+            }
+            _ => return Err(invalid_session("unknown match encoding")),
+        };
+        if found != count || previous.len() as u64 != count * size {
+            return Err(invalid_session("match count doesn't agree with the matches"));
+        }
+        let scan = ValueScan { step, endian, region, value: PhantomData };
+        let results = ScanResults { scan, start, slots, count: count as usize, matches };
+        Ok(ScanSession { results, previous: previous.to_vec() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scan::*, ReversedWords};
+
+    #[test]
+    fn scans_with_step_and_type() {
+        let mut data = vec![0u8; 64];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_value_at(8, 100u32, Endian::Big).unwrap();
+        words.write_value_at(22, 356u16, Endian::Big).unwrap();
+        words.write_value_at(41, 100u32, Endian::Big).unwrap();
+
+        let aligned = ValueScan::<u32>::new().scan(&words, |value| value == 100);
+        assert_eq!(vec![8], aligned.addresses().collect::<Vec<_>>());
+        let bytewise = ValueScan::<u32>::new().with_step(1).scan(&words, |value| value == 100);
+        assert_eq!(vec![8, 41], bytewise.addresses().collect::<Vec<_>>());
+        assert!(bytewise.contains(41) && !bytewise.contains(40) && !bytewise.contains(1000));
+        let halves = ValueScan::<u16>::new().scan(&words, |value| value == 100 || value == 356);
+        assert_eq!(vec![(10, 100), (22, 356)], halves.values(&words).collect::<Vec<_>>());
+        let region = ValueScan::<u32>::new().with_step(1).with_region(9..45).scan(&words, |value| value == 100);
+        assert_eq!(vec![41], region.addresses().collect::<Vec<_>>());
+        assert!(ValueScan::<u32>::new().with_region(62..64).scan(&words, |_| true).is_empty());
+        let little = ValueScan::<u32>::new().with_endian(Endian::Little).scan(&words, |value| value == 100 << 24);
+        assert_eq!(1, little.len());
+    }
+
+    #[test]
+    fn stores_matches_compactly() {
+        let mut data = vec![0u8; 1 << 16];
+        let mut words = ReversedWords::new(&mut data);
+        for addr in [3, 900, 40_000, 65_535] {
+            words.set_byte(addr, 1).unwrap();
+        }
+        // every address matches: a bitmap of a bit each
+        let dense = ValueScan::<u8>::new().scan(&words, |_| true);
+        assert_eq!(1 << 16, dense.len());
+        assert_eq!(8192, dense.heap_size());
+        assert_eq!((0..1 << 16).collect::<Vec<u64>>(), dense.addresses().collect::<Vec<_>>());
+
+        let sparse = ValueScan::<u8>::new().scan(&words, |value| value == 1);
+        assert_eq!(vec![3, 900, 40_000, 65_535], sparse.addresses().collect::<Vec<_>>());
+        assert!(sparse.heap_size() < 16);
+        assert!(sparse.contains(40_000) && !sparse.contains(40_001));
+    }
+
+    #[test]
+    fn revalidating_narrows_results() {
+        let mut data = vec![0u8; 4096];
+        let mut words = ReversedWords::new(&mut data);
+        let mut results = ValueScan::<u16>::new().scan(&words, |value| value == 0);
+        assert_eq!(2048, results.len());
+        let dense_size = results.heap_size();
+
+        // the value being searched for goes from 0 to 5 at two addresses
+        words.write_value_at(100, 5u16, Endian::Big).unwrap();
+        words.write_value_at(3000, 5u16, Endian::Big).unwrap();
+        results.revalidate(&words, |value| value == 5);
+        assert_eq!(vec![100, 3000], results.addresses().collect::<Vec<_>>());
+        assert!(results.heap_size() < dense_size);
+
+        words.write_value_at(3000, 6u16, Endian::Big).unwrap();
+        results.revalidate(&words, |value| value == 5);
+        assert_eq!(vec![(100, 5)], results.values(&words).collect::<Vec<_>>());
+        results.revalidate(&words, |value| value == 6);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn sessions_survive_a_restart() {
+        let mut data = vec![0u8; 0x400];
+        let mut words = ReversedWords::new(&mut data);
+        for addr in (0..0x400).step_by(4) {
+            words.write_u32_at(addr, 50, Endian::Big).unwrap();
+        }
+        let mut session = ScanSession::unknown(ValueScan::<u32>::new().with_region(0x100..0x400), &words);
+        assert_eq!(0xC0, session.len());
+
+        // the unknown value decreased at two addresses
+        words.write_u32_at(0x120, 40, Endian::Big).unwrap();
+        words.write_u32_at(0x300, 45, Endian::Big).unwrap();
+        session.narrow(&words, |old, new| new < old);
+        assert_eq!(vec![(0x120, 40), (0x300, 45)], session.previous().collect::<Vec<_>>());
+
+        let mut saved = Vec::new();
+        session.write_to(&mut saved).unwrap();
+        let mut loaded = ScanSession::<u32>::read_from(&saved[..]).unwrap();
+        assert_eq!(session, loaded);
+        words.write_u32_at(0x300, 44, Endian::Big).unwrap();
+        loaded.narrow(&words, |old, new| new < old);
+        assert_eq!(vec![0x300], loaded.results().addresses().collect::<Vec<_>>());
+
+        // a dense session saves its bitmap
+        let dense = ScanSession::unknown(ValueScan::<u8>::new(), &words);
+        saved.clear();
+        dense.write_to(&mut saved).unwrap();
+        assert_eq!(dense, ScanSession::<u8>::read_from(&saved[..]).unwrap());
+    }
+
+    #[test]
+    fn rejects_corrupt_sessions() {
+        let mut data = vec![0u8; 0x40];
+        let words = ReversedWords::new(&mut data);
+        let session = ScanSession::new(ValueScan::<u16>::new(), &words, |_| true);
+        let mut saved = Vec::new();
+        session.write_to(&mut saved).unwrap();
+
+        let invalid = |saved: &[u8]| {
+            let error = ScanSession::<u16>::read_from(saved).unwrap_err();
+            matches!(ReversedWordsError::from_io(&error), Some(ReversedWordsError::InvalidScanSession { .. }))
+        };
+        assert!(invalid(&saved[..saved.len() - 1]));
+        assert!(invalid(&saved[..20]));
+        let error = ScanSession::<u32>::read_from(&saved[..]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        // claim more matches than the bitmap holds
+        let mut miscounted = saved.clone();
+        miscounted[4 + 48..4 + 56].copy_from_slice(&33u64.to_le_bytes());
+        assert!(invalid(&miscounted));
+    }
+
+    #[test]
+    fn scans_backends_past_4_gib() {
+        use std::io::{Read, Seek, SeekFrom};
+
+        use crate::backend::PatternBackend;
+
+        let len = 6 << 30;
+        let mut words = ReversedBackend::new(PatternBackend::new(&[1, 2, 3, 4], len), 4).unwrap();
+        let region = 0x1_0000_0000 - 8..0x1_0000_0010;
+        let results = ValueScan::<u32>::new().with_region(region.clone()).scan_backend(&mut words, |value| value == 0x0403_0201).unwrap();
+        assert_eq!(vec![0xFFFF_FFF8, 0xFFFF_FFFC, 0x1_0000_0000, 0x1_0000_0004, 0x1_0000_0008, 0x1_0000_000C], results.addresses().collect::<Vec<u64>>());
+        let results = ValueScan::<u16>::new().with_step(1).with_region(region).scan_backend(&mut words, |value| value == 0x0302).unwrap();
+        assert_eq!(6, results.len());
+        assert!(results.contains(0x1_0000_0005));
+        let top = ValueScan::<u32>::new().with_region(len - 6..u64::MAX).scan_backend(&mut words, |_| true).unwrap();
+        assert_eq!(vec![len - 6], top.addresses().collect::<Vec<u64>>());
+        assert!(ValueScan::<u32>::new().with_region(u64::MAX - 1..u64::MAX).scan_backend(&mut words, |_| true).unwrap().is_empty());
+
+        assert_eq!(len - 3, words.seek(SeekFrom::End(-3)).unwrap());
+        let mut out = [0u8; 4];
+        assert_eq!(3, words.read(&mut out).unwrap());
+        assert_eq!([3, 2, 1, 0], out);
+    }
+}
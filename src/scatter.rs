@@ -0,0 +1,215 @@
+//! One logical address space over several separately captured buffers.
+//!
+//! [`ScatterWords`] takes `(base_address, buffer)` pairs, e.g. separate dumps of a few regions,
+//! and presents them as a single `Read + Write + Seek` space addressed by base address. Accessing
+//! an address between buffers fails with [`ReversedWordsError::UnmappedAddress`], and an access
+//! that runs into a gap stops short at it.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::{storage_index, ReversedWordsError};
+
+pub struct ScatterWords<'a> {
+    // sorted by base, never overlapping
+    pieces: Vec<(u64, &'a mut [u8])>,
+    word_size: u8,
+    position: u64,
+}
+
+impl<'a> ScatterWords<'a> {
+    /// Every base must be a multiple of `word_size` so words line up across the space.
+    pub fn new(mut pieces: Vec<(u64, &'a mut [u8])>, word_size: u8) -> Result<ScatterWords<'a>, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        pieces.sort_by_key(|(base, _)| *base);
+        for (base, _) in &pieces {
+            if !base.is_multiple_of(word_size as u64) {
+                return Err(ReversedWordsError::Misaligned { position: *base, word_size });
+            }
+        }
+        for pair in pieces.windows(2) {
+            if pair[0].0 + pair[0].1.len() as u64 > pair[1].0 {
+                return Err(ReversedWordsError::OverlappingBuffers { addr: pair[1].0 });
+            }
+        }
+        Ok(ScatterWords { pieces, word_size, position: 0 })
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    /// End of the last buffer, gaps included.
+    pub fn len(&self) -> u64 {
+        self.pieces.last().map_or(0, |(base, data)| base + data.len() as u64)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.iter().all(|(_, data)| data.is_empty())
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Whether `addr` is inside one of the buffers.
+    pub fn is_mapped(&self, addr: u64) -> bool {
+        self.piece_at(addr).is_some()
+    }
+
+    fn piece_at(&self, addr: u64) -> Option<usize> {
+        let index = self.pieces.partition_point(|(base, _)| *base <= addr).checked_sub(1)?;
+        let (base, data) = &self.pieces[index];
+        if addr - base < data.len() as u64 {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    // Bytes starting at `addr` that are in the same buffer, with the buffer's index.
+    fn span_at(&self, addr: u64) -> Result<(usize, u64), ReversedWordsError> {
+        let index = self.piece_at(addr).ok_or(ReversedWordsError::UnmappedAddress { addr })?;
+        let (base, data) = &self.pieces[index];
+        Ok((index, base + data.len() as u64 - addr))
+    }
+
+    /// Read the logical bytes starting at `addr`, stopping at a gap or the end.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut done = 0;
+        while done < buf.len() {
+            let position = addr + done as u64;
+            let (index, available) = match self.span_at(position) {
+                Ok(span) => span,
+                Err(_) if done > 0 => break,
+                Err(e) => return Err(e.into()),
+            };
+            let (base, data) = &self.pieces[index];
+            let count = available.min((buf.len() - done) as u64) as usize;
+            for i in 0..count {
+                let offset = position - base + i as u64;
+                let storage = storage_index(offset, self.word_size, data.len() as u64)
+                    .ok_or(ReversedWordsError::AddressOutOfRange { addr: position + i as u64, len: self.len() })?;
+                buf[done + i] = data[storage];
+            }
+            done += count;
+        }
+        Ok(done)
+    }
+
+    /// Write logical bytes starting at `addr`, stopping at a gap or the end.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        let mut done = 0;
+        let len = self.len();
+        while done < buf.len() {
+            let position = addr + done as u64;
+            let (index, available) = match self.span_at(position) {
+                Ok(span) => span,
+                Err(_) if done > 0 => break,
+                Err(e) => return Err(e.into()),
+            };
+            let word_size = self.word_size;
+            let (base, data) = &mut self.pieces[index];
+            let count = available.min((buf.len() - done) as u64) as usize;
+            for i in 0..count {
+                let offset = position - *base + i as u64;
+                let storage = storage_index(offset, word_size, data.len() as u64)
+                    .ok_or(ReversedWordsError::AddressOutOfRange { addr: position + i as u64, len })?;
+                data[storage] = buf[done + i];
+            }
+            done += count;
+        }
+        Ok(done)
+    }
+}
+
+impl Read for ScatterWords<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len() {
+            return Ok(0);
+        }
+        let n = self.read_at(self.position, buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for ScatterWords<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len() {
+            return Ok(0);
+        }
+        let n = self.write_at(self.position, buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ScatterWords<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.len() as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.position as i128 + offset as i128,
+        };
+        if target < 0 || target > u64::MAX as i128 {
+            return Err(ReversedWordsError::SeekOutOfRange { target, len: self.len() }.into());
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scatter::*;
+
+    #[test]
+    fn pieces_form_one_space() {
+        let mut low: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let mut high: Vec<u8> = vec![8, 9, 10, 11];
+        let mut words = ScatterWords::new(vec![(0x108, &mut high[..]), (0x100, &mut low[..])], 4).unwrap();
+        assert_eq!(0x10C, words.len());
+
+        let mut out = [0u8; 6];
+        words.seek(SeekFrom::Start(0x106)).unwrap();
+        words.read_exact(&mut out).unwrap();
+        assert_eq!([5, 4, 11, 10, 9, 8], out);
+
+        words.seek(SeekFrom::Start(0x107)).unwrap();
+        words.write_all(&[0xAA, 0xBB]).unwrap();
+        assert_eq!(0x109, words.position());
+        drop(words);
+        assert_eq!([0xAA, 5, 6, 7], low[4..]);
+        assert_eq!([8, 9, 10, 0xBB], high[..]);
+    }
+
+    #[test]
+    fn gaps_are_errors() {
+        let mut low = [0u8; 4];
+        let mut high = [0u8; 4];
+        let mut words = ScatterWords::new(vec![(0, &mut low[..]), (8, &mut high[..])], 4).unwrap();
+        let mut out = [0u8; 8];
+        assert_eq!(4, words.read_at(0, &mut out).unwrap());
+        let error = words.read_at(4, &mut out).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnmappedAddress { addr: 4 }), ReversedWordsError::from_io(&error));
+        assert!(words.read_exact(&mut out).is_err());
+        assert!(!words.is_mapped(6));
+
+        let mut a = [0u8; 8];
+        let mut b = [0u8; 4];
+        assert_eq!(
+            Some(ReversedWordsError::OverlappingBuffers { addr: 4 }),
+            ScatterWords::new(vec![(0, &mut a[..]), (4, &mut b[..])], 4).err()
+        );
+        assert_eq!(
+            Some(ReversedWordsError::Misaligned { position: 2, word_size: 4 }),
+            ScatterWords::new(vec![(2, &mut b[..])], 4).err()
+        );
+    }
+}
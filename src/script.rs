@@ -0,0 +1,194 @@
+//! Running rhai scripts against a buffer, for automation without recompiling the host tool.
+//!
+//! Scripts see the buffer as `mem`, a word-swapped view with the usual accessors:
+//!
+//! ```rhai
+//! if mem.read_u16(0x1c) < 10 {
+//!     mem.write_u16(0x1c, 999);
+//! }
+//! mem.find(blob(2, 0xFF))  // logical addresses of a byte pattern
+//! ```
+//!
+//! `read_*`/`write_*` are big endian and the `_le` variants little endian, integers are `i64`
+//! and floats `f64` on the script side. `read(addr, n)` and `write(addr, blob)` move raw logical
+//! bytes, and `fill(start, end, byte)` and `len()` work as they do on the view.
+
+use std::{cell::RefCell, convert::TryFrom, rc::Rc};
+
+use binread::Endian;
+use rhai::{Blob, Dynamic, Engine, EvalAltResult, Scope, AST, INT};
+
+use crate::{Primitive, ReversedWords, ReversedWordsError};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+#[derive(Clone)]
+struct Memory {
+    data: Rc<RefCell<Vec<u8>>>,
+    word_size: u8,
+}
+
+fn script_error(e: std::io::Error) -> Box<EvalAltResult> {
+    e.to_string().into()
+}
+
+fn address(addr: INT) -> ScriptResult<u64> {
+    u64::try_from(addr).map_err(|_| format!("negative address {}", addr).into())
+}
+
+impl Memory {
+    fn with_words<T>(&mut self, f: impl FnOnce(&mut ReversedWords) -> std::io::Result<T>) -> ScriptResult<T> {
+        let mut data = self.data.borrow_mut();
+        f(&mut ReversedWords::new_with_word_size(&mut data, self.word_size)).map_err(script_error)
+    }
+
+    fn read_value<T: Primitive>(&mut self, addr: INT, endian: Endian) -> ScriptResult<T> {
+        let addr = address(addr)?;
+        self.with_words(|words| words.read_value_at(addr, endian))
+    }
+
+    fn write_value<T: Primitive>(&mut self, addr: INT, value: T, endian: Endian) -> ScriptResult<()> {
+        let addr = address(addr)?;
+        self.with_words(|words| {
+            // check up front so a value running off the end isn't partially written
+            let size = value.to_bytes(endian).as_ref().len() as u64;
+            if addr.saturating_add(size) > words.len() {
+                return Err(ReversedWordsError::AddressOutOfRange { addr: addr.saturating_add(size), len: words.len() }.into());
+            }
+            words.write_value_at(addr, value, endian)
+        })
+    }
+}
+
+macro_rules! register_typed_accessors {
+    ($engine:expr, $($ty:ty => $read:literal, $write:literal;)*) => {
+        $(
+            $engine.register_fn($read, |mem: &mut Memory, addr: INT| mem.read_value::<$ty>(addr, Endian::Big).map(|v| v as INT));
+            $engine.register_fn(concat!($read, "_le"), |mem: &mut Memory, addr: INT| mem.read_value::<$ty>(addr, Endian::Little).map(|v| v as INT));
+            $engine.register_fn($write, |mem: &mut Memory, addr: INT, value: INT| mem.write_value(addr, value as $ty, Endian::Big));
+            $engine.register_fn(concat!($write, "_le"), |mem: &mut Memory, addr: INT, value: INT| mem.write_value(addr, value as $ty, Endian::Little));
+        )*
+    };
+}
+
+pub struct ScriptEngine {
+    engine: Engine,
+    word_size: u8,
+}
+
+/// A compiled script, reusable across runs.
+pub struct Script {
+    ast: AST,
+}
+
+impl ScriptEngine {
+    pub fn new(word_size: u8) -> Result<ScriptEngine, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        let mut engine = Engine::new();
+        engine.register_type_with_name::<Memory>("Memory");
+        engine.register_fn("len", |mem: &mut Memory| mem.data.borrow().len() as INT);
+        engine.register_fn("read", |mem: &mut Memory, addr: INT, length: INT| -> ScriptResult<Blob> {
+            let addr = address(addr)?;
+            let mut buf = vec![0u8; length.max(0) as usize];
+            let n = mem.with_words(|words| words.read_at(addr, &mut buf))?;
+            buf.truncate(n);
+            Ok(buf)
+        });
+        engine.register_fn("write", |mem: &mut Memory, addr: INT, bytes: Blob| -> ScriptResult<INT> {
+            let addr = address(addr)?;
+            mem.with_words(|words| words.write_at(addr, &bytes)).map(|n| n as INT)
+        });
+        engine.register_fn("fill", |mem: &mut Memory, start: INT, end: INT, byte: INT| -> ScriptResult<()> {
+            let range = address(start)?..address(end)?;
+            mem.with_words(|words| words.fill(range, byte as u8))
+        });
+        engine.register_fn("find", |mem: &mut Memory, needle: Blob| -> ScriptResult<rhai::Array> {
+            let found = mem.with_words(|words| Ok(words.find_all(&needle)))?;
+            Ok(found.into_iter().map(|addr| Dynamic::from(addr as INT)).collect())
+        });
+        register_typed_accessors! {
+            engine,
+            u8 => "read_u8", "write_u8";
+            u16 => "read_u16", "write_u16";
+            u32 => "read_u32", "write_u32";
+            u64 => "read_u64", "write_u64";
+            i8 => "read_i8", "write_i8";
+            i16 => "read_i16", "write_i16";
+            i32 => "read_i32", "write_i32";
+            i64 => "read_i64", "write_i64";
+        }
+        engine.register_fn("read_f32", |mem: &mut Memory, addr: INT| mem.read_value::<f32>(addr, Endian::Big).map(|v| v as rhai::FLOAT));
+        engine.register_fn("read_f32_le", |mem: &mut Memory, addr: INT| mem.read_value::<f32>(addr, Endian::Little).map(|v| v as rhai::FLOAT));
+        engine.register_fn("write_f32", |mem: &mut Memory, addr: INT, value: rhai::FLOAT| mem.write_value(addr, value as f32, Endian::Big));
+        engine.register_fn("write_f32_le", |mem: &mut Memory, addr: INT, value: rhai::FLOAT| mem.write_value(addr, value as f32, Endian::Little));
+        engine.register_fn("read_f64", |mem: &mut Memory, addr: INT| mem.read_value::<f64>(addr, Endian::Big));
+        engine.register_fn("read_f64_le", |mem: &mut Memory, addr: INT| mem.read_value::<f64>(addr, Endian::Little));
+        engine.register_fn("write_f64", |mem: &mut Memory, addr: INT, value: rhai::FLOAT| mem.write_value(addr, value, Endian::Big));
+        engine.register_fn("write_f64_le", |mem: &mut Memory, addr: INT, value: rhai::FLOAT| mem.write_value(addr, value, Endian::Little));
+        Ok(ScriptEngine { engine, word_size })
+    }
+
+    /// The underlying engine, to register host functions.
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    pub fn compile(&self, source: &str) -> Result<Script, ReversedWordsError> {
+        self.engine
+            .compile(source)
+            .map(|ast| Script { ast })
+            .map_err(|e| ReversedWordsError::Script { reason: e.to_string() })
+    }
+
+    /// Run `script` with `data` as `mem`, returning the script's final value.
+    ///
+    /// `data` is moved into the script for the duration of the run and is handed back with any
+    /// writes the script made, even if it fails partway through.
+    pub fn run(&self, script: &Script, data: &mut Vec<u8>) -> Result<Dynamic, ReversedWordsError> {
+        let memory = Memory { data: Rc::new(RefCell::new(std::mem::take(data))), word_size: self.word_size };
+        let mut scope = Scope::new();
+        scope.push("mem", memory.clone());
+        let result = self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, &script.ast);
+        drop(scope);
+        *data = memory.data.take();
+        result.map_err(|e| ReversedWordsError::Script { reason: e.to_string() })
+    }
+
+    /// Compile and run `source` once.
+    pub fn eval(&self, source: &str, data: &mut Vec<u8>) -> Result<Dynamic, ReversedWordsError> {
+        let script = self.compile(source)?;
+        self.run(&script, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::script::*;
+
+    #[test]
+    fn poll_and_patch() {
+        let engine = ScriptEngine::new(4).unwrap();
+        let script = engine.compile("if mem.read_u16(2) < 10 { mem.write_u16(2, 999); } mem.read_u16(2)").unwrap();
+        let mut data = vec![0u8; 8];
+        assert_eq!(999, engine.run(&script, &mut data).unwrap().as_int().unwrap());
+        assert_eq!([0xE7, 0x03, 0, 0], data[..4]);
+        assert_eq!(999, engine.run(&script, &mut data).unwrap().as_int().unwrap());
+    }
+
+    #[test]
+    fn bytes_search_and_errors() {
+        let engine = ScriptEngine::new(4).unwrap();
+        let mut data = vec![0u8; 8];
+        engine.eval("mem.write(1, blob(2, 0xAA)); mem.write_f32_le(4, 1.5)", &mut data).unwrap().as_unit().unwrap();
+        let found = engine.eval("mem.find(blob(2, 0xAA))", &mut data).unwrap().into_array().unwrap();
+        assert_eq!(vec![1], found.into_iter().map(|addr| addr.as_int().unwrap()).collect::<Vec<_>>());
+        assert_eq!(1.5, engine.eval("mem.read_f32_le(4)", &mut data).unwrap().as_float().unwrap());
+
+        let error = engine.eval("mem.write_u32(6, 1)", &mut data).unwrap_err();
+        assert!(matches!(error, ReversedWordsError::Script { .. }));
+        assert_eq!(8, data.len());
+        assert!(matches!(engine.compile("mem.read_u8("), Err(ReversedWordsError::Script { .. })));
+    }
+}
@@ -0,0 +1,261 @@
+//! Searching the logical byte stream.
+
+use crate::{swap_index, ReversedWords, ReversedWordsError};
+
+/// Text encodings [`ReversedWords::find_string`] can search for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// 7 bit ASCII, one byte per character.
+    Ascii,
+    /// Shift-JIS as used by Japanese console games, including half width katakana.
+    ShiftJis,
+    /// UTF-16, big endian, two or four bytes per character.
+    Utf16Be,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Ascii => "ASCII",
+            Encoding::ShiftJis => "Shift-JIS",
+            Encoding::Utf16Be => "UTF-16BE",
+        }
+    }
+
+    /// The bytes of `text` in this encoding, or [`ReversedWordsError::UnencodableText`] if a
+    /// character has none.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, ReversedWordsError> {
+        let unencodable = || ReversedWordsError::UnencodableText { text: text.to_string(), encoding: self.name() };
+        match self {
+            Encoding::Ascii if text.is_ascii() => Ok(text.as_bytes().to_vec()),
+            Encoding::Ascii => Err(unencodable()),
+            Encoding::ShiftJis => {
+                let (bytes, _, unmappable) = encoding_rs::SHIFT_JIS.encode(text);
+                if unmappable {
+                    Err(unencodable())
+                } else {
+                    Ok(bytes.into_owned())
+                }
+            }
+            Encoding::Utf16Be => Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+        }
+    }
+}
+
+/// Printable runs of text, see [`ReversedWords::strings`].
+pub struct Strings<'w, 'a> {
+    words: &'w ReversedWords<'a>,
+    encoding: Encoding,
+    min_len: usize,
+    pos: u64,
+    end: u64,
+}
+
+impl Strings<'_, '_> {
+    fn byte(&self, addr: u64) -> Option<u8> {
+        if addr < self.end {
+            self.words.bytes(addr..addr + 1).next()
+        } else {
+            None
+        }
+    }
+
+    fn unit(&self, addr: u64) -> Option<u16> {
+        Some(u16::from_be_bytes([self.byte(addr)?, self.byte(addr + 1)?]))
+    }
+
+    // the printable character at `addr` and how many bytes it takes
+    fn char_at(&self, addr: u64) -> Option<(char, u64)> {
+        let (c, width) = match self.encoding {
+            Encoding::Ascii => (self.byte(addr).filter(u8::is_ascii)? as char, 1),
+            Encoding::ShiftJis => {
+                let lead = self.byte(addr)?;
+                let bytes = match lead {
+                    0x81..=0x9F | 0xE0..=0xFC => vec![lead, self.byte(addr + 1)?],
+                    _ => vec![lead],
+                };
+                let text = encoding_rs::SHIFT_JIS.decode_without_bom_handling_and_without_replacement(&bytes)?;
+                (text.chars().next()?, bytes.len() as u64)
+            }
+            Encoding::Utf16Be => {
+                let first = self.unit(addr)?;
+                match char::decode_utf16([first]).next()? {
+                    Ok(c) => (c, 2),
+                    Err(_) => (char::decode_utf16([first, self.unit(addr + 2)?]).next()?.ok()?, 4),
+                }
+            }
+        };
+        if c == '\t' || !c.is_control() {
+            Some((c, width))
+        } else {
+            None
+        }
+    }
+}
+
+impl Iterator for Strings<'_, '_> {
+    type Item = (u64, String);
+
+    fn next(&mut self) -> Option<(u64, String)> {
+        // UTF-16 is read in aligned units, the others a byte at a time
+        let step = if self.encoding == Encoding::Utf16Be { 2 } else { 1 };
+        while self.pos < self.end {
+            let start = self.pos;
+            let mut text = String::new();
+            let mut chars = 0;
+            while let Some((c, width)) = self.char_at(self.pos) {
+                text.push(c);
+                chars += 1;
+                self.pos += width;
+            }
+            if self.pos == start {
+                self.pos += step;
+            }
+            if chars >= self.min_len {
+                return Some((start, text));
+            }
+        }
+        None
+    }
+}
+
+impl<'a> ReversedWords<'a> {
+    /// Logical addresses of every occurrence of `needle`, including overlapping ones.
+    ///
+    /// Nothing is copied out: for each offset the needle could start at within a word, the
+    /// longest run of it that is contiguous in storage is found with `memchr::memmem` and only
+    /// those hits are checked byte by byte through the swap.
+    pub fn find_all(&self, needle: &[u8]) -> Vec<u64> {
+        let reachable = self.reachable_len();
+        let len = needle.len() as u64;
+        if needle.is_empty() || len > reachable {
+            return Vec::new();
+        }
+        let storage = &self.cursor.get_ref()[..];
+        let word_size = self.word_size as u64;
+        let mut found = Vec::new();
+        for start_in_word in 0..word_size {
+            let (run_offset, run) = self.storage_run(needle, start_in_word);
+            let finder = memchr::memmem::Finder::new(&run);
+            // find_iter skips overlapping hits, so restart one byte after each instead
+            let mut from = 0;
+            while let Some(hit) = finder.find(&storage[from..]).map(|hit| from + hit) {
+                from = hit + 1;
+                // the word the match would start in, and the match's logical address
+                let base = match (hit as u64).checked_sub(run_offset) {
+                    Some(base) if base.is_multiple_of(word_size) => base,
+                    _ => continue,
+                };
+                let addr = base + start_in_word;
+                if addr + len <= reachable && self.matches_at(addr, needle) {
+                    found.push(addr);
+                }
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+
+    // the longest part of `needle` that is contiguous in storage when it starts `start_in_word`
+    // bytes into a word: its storage offset from the start of that word, and its stored bytes
+    fn storage_run(&self, needle: &[u8], start_in_word: u64) -> (u64, Vec<u8>) {
+        let mut stored: Vec<(u64, u8)> =
+            needle.iter().enumerate().map(|(i, byte)| (swap_index(start_in_word + i as u64, self.word_size), self.transform(*byte))).collect();
+        stored.sort_unstable_by_key(|(offset, _)| *offset);
+        let mut best = 0..1;
+        let mut run_start = 0;
+        for i in 1..stored.len() {
+            if stored[i].0 != stored[i - 1].0 + 1 {
+                run_start = i;
+            }
+            if i + 1 - run_start > best.len() {
+                best = run_start..i + 1;
+            }
+        }
+        (stored[best.start].0, stored[best].iter().map(|(_, byte)| *byte).collect())
+    }
+
+    fn matches_at(&self, addr: u64, needle: &[u8]) -> bool {
+        needle.iter().enumerate().all(|(i, byte)| self.storage_index(addr + i as u64).map(|index| self.load(index)) == Some(*byte))
+    }
+
+    /// Logical addresses of every occurrence of `needle` encoded as `encoding`. UTF-16 matches
+    /// can start at odd addresses, as text packed into a struct sometimes does.
+    pub fn find_string(&self, needle: &str, encoding: Encoding) -> Result<Vec<u64>, ReversedWordsError> {
+        Ok(self.find_all(&encoding.encode(needle)?))
+    }
+
+    /// `(address, text)` for every run of at least `min_len` printable characters (tabs count)
+    /// in `encoding`, like the `strings` utility run on the logical stream. UTF-16 runs are only
+    /// looked for at even addresses. A `min_len` of 0 is treated as 1.
+    pub fn strings(&self, min_len: usize, encoding: Encoding) -> Strings<'_, 'a> {
+        Strings { words: self, encoding, min_len: min_len.max(1), pos: 0, end: self.reachable_len() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn find_across_word_boundaries() {
+        let mut data: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 3];
+        let ram = ReversedWords::new(&mut data);
+        assert_eq!(vec![1], ram.find_all(&[2, 1, 0, 3]));
+        assert_eq!(vec![0, 4], ram.find_all(&[3]));
+        assert!(ram.find_all(&[9]).is_empty());
+        assert!(ram.find_all(&[]).is_empty());
+    }
+
+    #[test]
+    fn find_matches_the_copying_search() {
+        let mut data: Vec<u8> = (0..200u32).map(|i| (i * 7 % 5) as u8).collect();
+        for word_size in [1, 2, 3, 4, 8] {
+            for nibble_swap in [false, true] {
+                let mut ram = ReversedWords::new_with_word_size(&mut data, word_size);
+                ram.set_nibble_swap(nibble_swap);
+                let mut logical = vec![0u8; 200];
+                let n = ram.read_at(0, &mut logical).unwrap();
+                logical.truncate(n);
+                for needle in [&[0][..], &[2, 4], &[1, 3, 0, 2, 4, 1, 3, 0, 2], &[4, 4], &logical[190.min(n - 3)..]] {
+                    let expected: Vec<u64> =
+                        logical.windows(needle.len()).enumerate().filter(|(_, window)| *window == needle).map(|(addr, _)| addr as u64).collect();
+                    assert_eq!(expected, ram.find_all(needle), "word size {} needle {:?}", word_size, needle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_encoded_strings() {
+        let mut data = vec![0u8; 24];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.write_at(1, b"MARIO").unwrap();
+        ram.write_at(8, &[0x83, 0x7D, 0x83, 0x8A, 0x83, 0x49]).unwrap();
+        ram.write_at(16, &[0, b'H', 0, b'i', 0xD8, 0x3D, 0xDE, 0x00]).unwrap();
+        assert_eq!(Ok(vec![1]), ram.find_string("MARIO", Encoding::Ascii));
+        assert_eq!(Ok(vec![8]), ram.find_string("マリオ", Encoding::ShiftJis));
+        assert_eq!(Ok(vec![16]), ram.find_string("Hi😀", Encoding::Utf16Be));
+        assert_eq!(Ok(vec![]), ram.find_string("mario", Encoding::Ascii));
+        assert_eq!(
+            Err(ReversedWordsError::UnencodableText { text: "マ".to_string(), encoding: "ASCII" }),
+            ram.find_string("マ", Encoding::Ascii)
+        );
+        assert!(ram.find_string("😀", Encoding::ShiftJis).is_err());
+    }
+
+    #[test]
+    fn printable_runs() {
+        let mut data = vec![0u8; 40];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.write_at(1, b"Hi\tthere\0ab\0long text").unwrap();
+        ram.write_at(24, &[0x83, 0x7D, 0x83, 0x8A, 0x83, 0x49]).unwrap();
+        ram.write_at(32, &[0, b'o', 0, b'k', 0x30, 0x42, 0, 0]).unwrap();
+        let ascii: Vec<_> = ram.strings(4, Encoding::Ascii).collect();
+        assert_eq!(vec![(1, "Hi\tthere".to_string()), (13, "long text".to_string())], ascii);
+        assert_eq!(3, ram.strings(2, Encoding::Ascii).take_while(|(addr, _)| *addr < 24).count());
+        let sjis: Vec<_> = ram.strings(3, Encoding::ShiftJis).filter(|(addr, _)| (24..32).contains(addr)).collect();
+        assert_eq!(vec![(24, "マリオ".to_string())], sjis);
+        assert_eq!(vec![(32, "okあ".to_string())], ram.strings(3, Encoding::Utf16Be).filter(|(addr, _)| *addr >= 32).collect::<Vec<_>>());
+    }
+}
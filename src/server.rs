@@ -0,0 +1,444 @@
+//! A small HTTP bridge to a shared view, with the `server` feature.
+//!
+//! [`BridgeServer`] answers plain HTTP/1.1 requests against an [`AtomicReversedWords`], so a web
+//! dashboard or stream overlay can read live memory without linking this crate. Every response
+//! is JSON with bytes as lowercase hex, in logical order:
+//!
+//! - `GET /info`: `{"len": 8388608, "word_size": 4}`
+//! - `GET /read?addr=0x1000&len=16`: `{"addr": 4096, "data": "…"}`, stopping short at the end
+//! - `POST /write?addr=0x1000` with a hex body and `Content-Type: application/x-hex`: `{"written": 4}`
+//! - `GET /search?pattern=dead&start=0&end=0x1000`: `{"matches": [4096]}`, `start`/`end` optional
+//! - `GET /watch?addr=0x1000&len=4&timeout_ms=1000`: waits for the bytes to change and returns
+//!   `{"changed": true, "old": "…", "new": "…"}`, or `"changed": false` after the timeout
+//!
+//! - `GET /events?watch=0x1000:4`: a WebSocket pushing changes to the listed ranges, see
+//!   [`crate::websocket`] for the message format
+//!
+//! Numbers are decimal or `0x` hex. Failures are `{"error": "…"}` with a 4xx status. Each
+//! connection gets its own thread and handles one request, or one WebSocket.
+//!
+//! There is no authentication, so web pages are kept out instead: responses carry no CORS headers
+//! unless the page's origin is listed with [`BridgeServer::with_allowed_origins`], and writes
+//! need a content type a browser can't send to another origin without asking first.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::AtomicReversedWords;
+
+// request bodies and reads are capped at this
+const MAX_TRANSFER: usize = 16 << 20;
+// and the request line and headers at these
+const MAX_LINE: usize = 8 << 10;
+const MAX_HEADERS: usize = 100;
+const WRITE_CONTENT_TYPE: &str = "application/x-hex";
+const MAX_WATCH_TIMEOUT: Duration = Duration::from_secs(60);
+const WATCH_POLL: Duration = Duration::from_millis(5);
+const ACCEPT_POLL: Duration = Duration::from_millis(10);
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) query: Vec<(String, String)>,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Request {
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn number(&self, name: &str) -> Result<Option<u64>, String> {
+        self.param(name).map(|value| parse_number(value).ok_or_else(|| format!("invalid {}: {:?}", name, value))).transpose()
+    }
+
+    pub(crate) fn required(&self, name: &str) -> Result<u64, String> {
+        self.number(name)?.ok_or_else(|| format!("missing {}", name))
+    }
+}
+
+pub(crate) fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn from_hex(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u32> = text.trim().chars().map(|c| c.to_digit(16)).collect::<Option<_>>()?;
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    Some(digits.chunks(2).map(|pair| (pair[0] << 4 | pair[1]) as u8).collect())
+}
+
+pub(crate) fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// read one line of at most `MAX_LINE` bytes, returning 0 at the end of the stream
+fn read_line(stream: &mut BufReader<TcpStream>, line: &mut String) -> Result<usize, String> {
+    let n = stream.take(MAX_LINE as u64 + 1).read_line(line).map_err(|error| error.to_string())?;
+    if n > MAX_LINE {
+        return Err("request line or header too long".to_string());
+    }
+    Ok(n)
+}
+
+pub(crate) fn read_request(stream: &mut BufReader<TcpStream>) -> Result<Request, String> {
+    let mut line = String::new();
+    read_line(stream, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Err("malformed request line".to_string()),
+    };
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if read_line(stream, &mut line)? == 0 {
+            return Err("connection closed in headers".to_string());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err("too many headers".to_string());
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    let mut request = Request { method, path: path.to_string(), query, headers, body: Vec::new() };
+    let len = match request.header("content-length") {
+        Some(len) => len.parse::<usize>().map_err(|_| "invalid content-length".to_string())?,
+        None => 0,
+    };
+    if len > MAX_TRANSFER {
+        return Err("request body too large".to_string());
+    }
+    request.body = vec![0; len];
+    stream.read_exact(&mut request.body).map_err(|error| error.to_string())?;
+    Ok(request)
+}
+
+pub(crate) fn respond(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    respond_with_headers(stream, status, "", body)
+}
+
+// `headers` are extra header lines, each ending in `\r\n`
+fn respond_with_headers(stream: &mut TcpStream, status: &str, headers: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        headers,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn error_body(message: &str) -> String {
+    format!("{{\"error\": {}}}", json_string(message))
+}
+
+/// Serves one shared view over HTTP.
+#[derive(Clone, Copy)]
+pub struct BridgeServer<'a> {
+    words: AtomicReversedWords<'a>,
+    allowed_origins: &'a [&'a str],
+}
+
+impl<'a> BridgeServer<'a> {
+    pub fn new(words: AtomicReversedWords<'a>) -> BridgeServer<'a> {
+        BridgeServer { words, allowed_origins: &[] }
+    }
+
+    /// Let web pages from `origins` (such as `"http://localhost:8080"`) use the bridge, by
+    /// answering their CORS preflights and requests. None can by default.
+    pub fn with_allowed_origins(mut self, origins: &'a [&'a str]) -> BridgeServer<'a> {
+        self.allowed_origins = origins;
+        self
+    }
+
+    // CORS headers for `request`, empty unless it comes from an allowed origin
+    fn cors_headers(&self, request: &Request) -> String {
+        match request.header("origin").filter(|origin| self.allowed_origins.contains(origin)) {
+            Some(origin) => format!("Access-Control-Allow-Origin: {}\r\nVary: Origin\r\n", origin),
+            None => String::new(),
+        }
+    }
+
+    pub fn words(&self) -> AtomicReversedWords<'a> {
+        self.words
+    }
+
+    /// Accept connections until `stop` is set, handling each on its own thread. The listener is
+    /// switched to non-blocking mode so `stop` is noticed promptly.
+    pub fn serve(&self, listener: &TcpListener, stop: &AtomicBool) -> std::io::Result<()> {
+        listener.set_nonblocking(true)?;
+        std::thread::scope(|scope| {
+            while !stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let server = *self;
+                        scope.spawn(move || {
+                            let _ = server.handle(stream, stop);
+                        });
+                    }
+                    Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_POLL),
+                    Err(error) => return Err(error),
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Answer the one request on `stream`.
+    pub fn handle(&self, stream: TcpStream, stop: &AtomicBool) -> std::io::Result<()> {
+        stream.set_nonblocking(false)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut stream = stream;
+        let request = match read_request(&mut reader) {
+            Ok(request) => request,
+            Err(message) => return respond(&mut stream, "400 Bad Request", &error_body(&message)),
+        };
+        if (request.method.as_str(), request.path.as_str()) == ("GET", "/events") {
+            return crate::websocket::serve_events(self.words, &request, stream, stop);
+        }
+        let cors = self.cors_headers(&request);
+        if request.method == "OPTIONS" && !cors.is_empty() {
+            let preflight = format!("{}Access-Control-Allow-Methods: GET, POST\r\nAccess-Control-Allow-Headers: Content-Type\r\n", cors);
+            return respond_with_headers(&mut stream, "204 No Content", &preflight, "");
+        }
+        match self.route(&request, stop) {
+            Ok(body) => respond_with_headers(&mut stream, "200 OK", &cors, &body),
+            Err((status, message)) => respond_with_headers(&mut stream, status, &cors, &error_body(&message)),
+        }
+    }
+
+    fn route(&self, request: &Request, stop: &AtomicBool) -> Result<String, (&'static str, String)> {
+        let bad = |message: String| ("400 Bad Request", message);
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/info") => Ok(format!("{{\"len\": {}, \"word_size\": {}}}", self.words.len(), self.words.word_size())),
+            ("GET", "/read") => {
+                let addr = request.required("addr").map_err(bad)?;
+                let len = request.required("len").map_err(bad)?;
+                let available = self.words.len().saturating_sub(addr).min(len).min(MAX_TRANSFER as u64);
+                let mut data = vec![0u8; available as usize];
+                let n = self.words.read_at(addr, &mut data);
+                Ok(format!("{{\"addr\": {}, \"data\": \"{}\"}}", addr, to_hex(&data[..n])))
+            }
+            ("POST", "/write") => {
+                // anything else could come from a page on another origin without a preflight
+                let content_type = request.header("content-type").map(|value| value.split(';').next().unwrap_or("").trim());
+                if !content_type.is_some_and(|content_type| content_type.eq_ignore_ascii_case(WRITE_CONTENT_TYPE)) {
+                    return Err(("415 Unsupported Media Type", format!("writes must be sent as {}", WRITE_CONTENT_TYPE)));
+                }
+                let addr = request.required("addr").map_err(bad)?;
+                let text = std::str::from_utf8(&request.body).map_err(|_| bad("body is not hex".to_string()))?;
+                let data = from_hex(text).ok_or_else(|| bad("body is not hex".to_string()))?;
+                Ok(format!("{{\"written\": {}}}", self.words.write_at(addr, &data)))
+            }
+            ("GET", "/search") => {
+                let pattern = request.param("pattern").and_then(from_hex).filter(|pattern| !pattern.is_empty());
+                let pattern = pattern.ok_or_else(|| bad("pattern must be non-empty hex".to_string()))?;
+                let start = request.number("start").map_err(bad)?.unwrap_or(0).min(self.words.len());
+                let end = request.number("end").map_err(bad)?.unwrap_or(u64::MAX).min(self.words.len()).max(start);
+                if end - start > MAX_TRANSFER as u64 {
+                    return Err(bad("search range too large".to_string()));
+                }
+                let mut data = vec![0u8; (end - start) as usize];
+                let n = self.words.read_at(start, &mut data);
+                let matches: Vec<String> = data[..n]
+                    .windows(pattern.len())
+                    .enumerate()
+                    .filter(|(_, window)| *window == &pattern[..])
+                    .map(|(offset, _)| (start + offset as u64).to_string())
+                    .collect();
+                Ok(format!("{{\"matches\": [{}]}}", matches.join(", ")))
+            }
+            ("GET", "/watch") => {
+                let addr = request.required("addr").map_err(bad)?;
+                let len = request.required("len").map_err(bad)?;
+                if addr.checked_add(len).is_none_or(|end| end > self.words.len()) {
+                    return Err(bad(format!("{:#x}+{} is outside of the {} byte buffer", addr, len, self.words.len())));
+                }
+                let timeout = Duration::from_millis(request.number("timeout_ms").map_err(bad)?.unwrap_or(1000)).min(MAX_WATCH_TIMEOUT);
+                let mut old = vec![0u8; len as usize];
+                self.words.read_at(addr, &mut old);
+                let deadline = Instant::now() + timeout;
+                let mut new = old.clone();
+                while Instant::now() < deadline && !stop.load(Ordering::Relaxed) {
+                    self.words.read_at(addr, &mut new);
+                    if new != old {
+                        return Ok(format!("{{\"changed\": true, \"old\": \"{}\", \"new\": \"{}\"}}", to_hex(&old), to_hex(&new)));
+                    }
+                    std::thread::sleep(WATCH_POLL);
+                }
+                Ok(format!("{{\"changed\": false, \"old\": \"{}\", \"new\": \"{}\"}}", to_hex(&old), to_hex(&new)))
+            }
+            (_, "/info" | "/read" | "/write" | "/search" | "/watch") => Err(("405 Method Not Allowed", "method not allowed".to_string())),
+            _ => Err(("404 Not Found", format!("no route {}", request.path))),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::AtomicU8;
+
+    use crate::{server::*, Endian};
+
+    // send `request` and return the status line and body
+    pub(crate) fn fetch(addr: std::net::SocketAddr, request: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+
+    pub(crate) fn get(addr: std::net::SocketAddr, target: &str) -> (String, String) {
+        fetch(addr, &format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", target))
+    }
+
+    #[test]
+    fn serves_reads_writes_searches_and_watches() {
+        let ram: Vec<AtomicU8> = (0..16).map(AtomicU8::new).collect();
+        let words = AtomicReversedWords::new(&ram);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stop = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            scope.spawn(|| BridgeServer::new(words).serve(&listener, &stop).unwrap());
+
+            assert_eq!(("HTTP/1.1 200 OK".to_string(), "{\"len\": 16, \"word_size\": 4}".to_string()), get(addr, "/info"));
+            assert_eq!("{\"addr\": 14, \"data\": \"0d0c\"}", get(addr, "/read?addr=0xe&len=99").1);
+            let (status, body) = fetch(addr, "POST /write?addr=0 HTTP/1.1\r\nContent-Type: application/x-hex\r\nContent-Length: 4\r\n\r\nbeef");
+            assert_eq!(("HTTP/1.1 200 OK", "{\"written\": 2}"), (status.as_str(), body.as_str()));
+            assert_eq!(0xBEEF, words.read_value_at::<u16>(0, Endian::Big).unwrap());
+            assert_eq!("{\"matches\": [0]}", get(addr, "/search?pattern=beef").1);
+            assert_eq!("{\"matches\": []}", get(addr, "/search?pattern=beef&start=1").1);
+
+            let watcher = scope.spawn(|| get(addr, "/watch?addr=8&len=4&timeout_ms=5000"));
+            std::thread::sleep(Duration::from_millis(50));
+            words.write_value_at(8, 0u32, Endian::Big).unwrap();
+            assert_eq!("{\"changed\": true, \"old\": \"0b0a0908\", \"new\": \"00000000\"}", watcher.join().unwrap().1);
+            assert_eq!("{\"changed\": false, \"old\": \"00000000\", \"new\": \"00000000\"}", get(addr, "/watch?addr=8&len=4&timeout_ms=1").1);
+
+            assert!(get(addr, "/read?addr=zz&len=1").0.starts_with("HTTP/1.1 400"));
+            assert!(get(addr, "/watch?addr=8&len=99").0.starts_with("HTTP/1.1 400"));
+            assert!(get(addr, "/write").0.starts_with("HTTP/1.1 405"));
+            assert!(get(addr, "/nowhere").0.starts_with("HTTP/1.1 404"));
+            stop.store(true, Ordering::Relaxed);
+        });
+    }
+
+    #[test]
+    fn keeps_other_origins_out() {
+        let ram: Vec<AtomicU8> = (0..16).map(AtomicU8::new).collect();
+        let words = AtomicReversedWords::new(&ram);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stop = AtomicBool::new(false);
+        let origins = ["http://localhost:8080"];
+        std::thread::scope(|scope| {
+            scope.spawn(|| BridgeServer::new(words).with_allowed_origins(&origins).serve(&listener, &stop).unwrap());
+
+            // a form or fetch() with a simple content type from any page
+            let simple = "POST /write?addr=0 HTTP/1.1\r\nOrigin: http://evil.example\r\nContent-Type: text/plain\r\nContent-Length: 4\r\n\r\nbeef";
+            assert!(fetch(addr, simple).0.starts_with("HTTP/1.1 415"));
+            assert_eq!(0x0302, words.read_value_at::<u16>(0, Endian::Big).unwrap());
+
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"GET /info HTTP/1.1\r\nOrigin: http://evil.example\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(!response.contains("Access-Control-Allow-Origin"));
+
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"OPTIONS /write HTTP/1.1\r\nOrigin: http://localhost:8080\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 204"));
+            assert!(response.contains("Access-Control-Allow-Origin: http://localhost:8080\r\n"));
+
+            let long = format!("GET /info?{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+            assert!(fetch(addr, &long).0.starts_with("HTTP/1.1 400"));
+            let many = format!("GET /info HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS + 1));
+            assert!(fetch(addr, &many).0.starts_with("HTTP/1.1 400"));
+            stop.store(true, Ordering::Relaxed);
+        });
+    }
+
+    #[test]
+    fn parses_helpers() {
+        assert_eq!(Some(0x10), parse_number("0x10"));
+        assert_eq!(Some(10), parse_number("10"));
+        assert_eq!(Some(vec![0xDE, 0xAD]), from_hex("DEad"));
+        assert_eq!(None, from_hex("abc"));
+        assert_eq!(None, from_hex("é0"));
+        assert_eq!("a b/c", percent_decode("a+b%2Fc"));
+        assert_eq!(r#""q\"\u000a""#, json_string("q\"\n"));
+    }
+}
@@ -0,0 +1,180 @@
+//! A read-only view that can be shared, with any number of independent cursors.
+
+use std::{
+    hash::{Hash, Hasher},
+    io::{Read, Seek, SeekFrom},
+};
+
+use binread::Endian;
+
+use crate::{atomic::seek_target, reverse::hash_logical, storage_index, Primitive, ReversedWords, ReversedWordsError};
+
+/// Read-only access to reversed words in a shared slice.
+///
+/// The view is `Copy` and only borrows the data, so parsers running in parallel over one dump can
+/// each take a [`ReversedWordsRef::cursor`] without copying it. Equality and hashing are of the
+/// logical bytes, so views of one memory stored with different word sizes are equal.
+#[derive(Clone, Copy, Debug)]
+pub struct ReversedWordsRef<'a> {
+    data: &'a [u8],
+    word_size: u8,
+}
+
+impl<'a> ReversedWordsRef<'a> {
+    pub fn new(data: &'a [u8]) -> ReversedWordsRef<'a> {
+        ReversedWordsRef { data, word_size: 4 }
+    }
+
+    pub fn try_new_with_word_size(data: &'a [u8], word_size: u8) -> Result<ReversedWordsRef<'a>, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        Ok(ReversedWordsRef { data, word_size })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    /// The underlying storage.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// A new cursor at position 0.
+    pub fn cursor(&self) -> WordCursor<'a> {
+        WordCursor { words: *self, position: 0 }
+    }
+
+    /// Read the logical bytes starting at `addr`, returning how many were in range.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> usize {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            match storage_index(addr + i as u64, self.word_size, self.len()) {
+                Some(index) => *byte = self.data[index],
+                None => return i,
+            }
+        }
+        buf.len()
+    }
+
+    pub fn get_byte(&self, addr: u64) -> std::io::Result<u8> {
+        match storage_index(addr, self.word_size, self.len()) {
+            Some(index) => Ok(self.data[index]),
+            None => Err(ReversedWordsError::AddressOutOfRange { addr, len: self.len() }.into()),
+        }
+    }
+
+    pub fn read_value_at<T: Primitive>(&self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        if self.read_at(addr, bytes.as_mut()) < bytes.as_ref().len() {
+            return Err(ReversedWordsError::AddressOutOfRange { addr, len: self.len() }.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+}
+
+impl ReversedWordsRef<'_> {
+    // the whole words, in logical order
+    fn logical_bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        let len = self.len() - self.len() % self.word_size as u64;
+        (0..len).map(move |position| self.data[storage_index(position, self.word_size, len).expect("a whole word")])
+    }
+}
+
+impl PartialEq for ReversedWordsRef<'_> {
+    fn eq(&self, other: &ReversedWordsRef) -> bool {
+        self.logical_bytes().eq(other.logical_bytes())
+    }
+}
+
+impl Eq for ReversedWordsRef<'_> {}
+
+impl Hash for ReversedWordsRef<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let len = self.len() - self.len() % self.word_size as u64;
+        hash_logical(self.logical_bytes(), len, state);
+    }
+}
+
+impl ReversedWords<'_> {
+    /// A read-only view of the same data and word size, for as long as this one is borrowed.
+    /// Nibble swapping and the address mode don't carry over.
+    pub fn as_ref_view(&self) -> ReversedWordsRef<'_> {
+        ReversedWordsRef { data: self.cursor.get_ref(), word_size: self.word_size }
+    }
+}
+
+/// A position in a [`ReversedWordsRef`], implementing `Read + Seek`.
+#[derive(Clone, Copy, Debug)]
+pub struct WordCursor<'a> {
+    words: ReversedWordsRef<'a>,
+    position: u64,
+}
+
+impl<'a> WordCursor<'a> {
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn words(&self) -> ReversedWordsRef<'a> {
+        self.words
+    }
+
+    /// Read a value at the cursor and move past it.
+    pub fn read_value<T: Primitive>(&mut self, endian: Endian) -> std::io::Result<T> {
+        let value = self.words.read_value_at(self.position, endian)?;
+        self.position += std::mem::size_of::<T>() as u64;
+        Ok(value)
+    }
+}
+
+impl Read for WordCursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.words.read_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for WordCursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = seek_target(pos, self.position, self.words.len())?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn parallel_cursors() {
+        let data: Vec<u8> = (0..64).collect();
+        let words = ReversedWordsRef::new(&data);
+        std::thread::scope(|scope| {
+            for start in [0u64, 16, 32, 48] {
+                scope.spawn(move || {
+                    let mut cursor = words.cursor();
+                    cursor.seek(SeekFrom::Start(start)).unwrap();
+                    for word in 0..4 {
+                        let expected = u32::from_le_bytes([0, 1, 2, 3].map(|i| (start + word * 4) as u8 + i));
+                        assert_eq!(expected, cursor.read_value::<u32>(Endian::Big).unwrap());
+                    }
+                    assert_eq!(start + 16, cursor.position());
+                });
+            }
+        });
+        let mut cursor = words.cursor();
+        assert_eq!(60, cursor.seek(SeekFrom::End(-4)).unwrap());
+        assert!(cursor.read_value::<u64>(Endian::Big).is_err());
+        assert_eq!(3, words.get_byte(0).unwrap());
+    }
+}
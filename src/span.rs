@@ -0,0 +1,229 @@
+//! Copying ranges out in logical order, and borrowing aligned ranges in storage order.
+
+use std::{io::Cursor, ops::Range};
+
+use crate::{swap_index, ReversedWords, ReversedWordsError};
+
+/// Storage order bytes of a word aligned logical range, borrowed from a view.
+///
+/// Whole words keep their bytes within the word, so the storage bytes of an aligned span are
+/// exactly `start..end` of the underlying buffer, only permuted inside each word. Logical offset
+/// `i` into the span is at storage offset [`RawSpan::storage_offset`]`(i)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawSpan<'s> {
+    bytes: &'s [u8],
+    start: u64,
+    word_size: u8,
+}
+
+/// Mutable counterpart of [`RawSpan`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct RawSpanMut<'s> {
+    bytes: &'s mut [u8],
+    start: u64,
+    word_size: u8,
+}
+
+fn storage_offset(offset: usize, word_size: u8) -> usize {
+    swap_index(offset as u64, word_size) as usize
+}
+
+impl<'s> RawSpan<'s> {
+    /// The span's bytes in storage order.
+    pub fn bytes(&self) -> &'s [u8] {
+        self.bytes
+    }
+
+    /// Logical address of the first byte of the span.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Index into [`RawSpan::bytes`] of the byte at logical offset `offset` into the span.
+    pub fn storage_offset(&self, offset: usize) -> usize {
+        storage_offset(offset, self.word_size)
+    }
+
+    /// The byte at logical offset `offset` into the span.
+    pub fn logical_byte(&self, offset: usize) -> u8 {
+        self.bytes[self.storage_offset(offset)]
+    }
+}
+
+impl RawSpanMut<'_> {
+    pub fn bytes(&self) -> &[u8] {
+        self.bytes
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        self.bytes
+    }
+
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn storage_offset(&self, offset: usize) -> usize {
+        storage_offset(offset, self.word_size)
+    }
+}
+
+impl ReversedWords<'_> {
+    /// Copy the logical bytes in `range` into a new `Vec`.
+    pub fn to_logical_vec(&self, range: Range<u64>) -> std::io::Result<Vec<u8>> {
+        self.check_range(&range)?;
+        let mut out = vec![0u8; (range.end - range.start) as usize];
+        for (i, position) in range.enumerate() {
+            let index = self
+                .storage_index(position)
+                .ok_or(ReversedWordsError::AddressOutOfRange { addr: position, len: self.len })?;
+            out[i] = self.load(index);
+        }
+        Ok(out)
+    }
+
+    fn check_span(&self, range: &Range<u64>) -> Result<(), ReversedWordsError> {
+        if range.start > range.end || range.end > self.len {
+            return Err(ReversedWordsError::RangeOutOfRange { start: range.start, end: range.end, len: self.len });
+        }
+        for position in [range.start, range.end] {
+            if !position.is_multiple_of(self.word_size as u64) {
+                return Err(ReversedWordsError::Misaligned { position, word_size: self.word_size });
+            }
+        }
+        Ok(())
+    }
+
+    /// Borrow the storage order bytes of `range` without copying. Both ends must be word aligned.
+    pub fn raw_span(&self, range: Range<u64>) -> Result<RawSpan<'_>, ReversedWordsError> {
+        self.check_span(&range)?;
+        Ok(RawSpan {
+            bytes: &self.cursor.get_ref()[range.start as usize..range.end as usize],
+            start: range.start,
+            word_size: self.word_size,
+        })
+    }
+
+    pub fn raw_span_mut(&mut self, range: Range<u64>) -> Result<RawSpanMut<'_>, ReversedWordsError> {
+        self.check_span(&range)?;
+        let word_size = self.word_size;
+        Ok(RawSpanMut {
+            bytes: &mut self.cursor.get_mut()[range.start as usize..range.end as usize],
+            start: range.start,
+            word_size,
+        })
+    }
+
+    // a view over `storage` configured like this one
+    fn sibling<'s>(&self, storage: &'s mut [u8]) -> ReversedWords<'s> {
+        let mut words = ReversedWords::new_with_word_size(storage, self.word_size)
+            .with_misalignment_policy(self.misalignment_policy)
+            .with_seek_bounds_policy(self.seek_bounds_policy)
+            .with_address_mode(self.address_mode)
+            .with_endian(self.endian);
+        words.nibble_swap = self.nibble_swap;
+        words.bit_reversal = self.bit_reversal;
+        words
+    }
+
+    fn split_storage(&mut self, mid: u64) -> Result<(&mut [u8], &mut [u8]), ReversedWordsError> {
+        self.check_span(&(0..mid))?;
+        Ok(self.cursor.get_mut().split_at_mut(mid as usize))
+    }
+
+    /// Split at word aligned `mid` into a raw, storage order cursor over the bytes before it and
+    /// a swapped view of the rest, for copying a header verbatim while editing the body. The
+    /// second view is addressed from 0 and has the same settings as this one.
+    pub fn split_raw_at(&mut self, mid: u64) -> Result<(Cursor<&mut [u8]>, ReversedWords<'_>), ReversedWordsError> {
+        let template = self.sibling(&mut []);
+        let (raw, swapped) = self.split_storage(mid)?;
+        Ok((Cursor::new(raw), template.sibling(swapped)))
+    }
+
+    /// Like [`ReversedWords::split_raw_at`], with the swapped view first and the raw cursor
+    /// over the bytes from `mid` on.
+    pub fn split_swapped_at(&mut self, mid: u64) -> Result<(ReversedWords<'_>, Cursor<&mut [u8]>), ReversedWordsError> {
+        let template = self.sibling(&mut []);
+        let (swapped, raw) = self.split_storage(mid)?;
+        Ok((template.sibling(swapped), Cursor::new(raw)))
+    }
+
+    /// Split at word aligned `mid` into two swapped views, each addressed from 0.
+    pub fn split_at_mut(&mut self, mid: u64) -> Result<(ReversedWords<'_>, ReversedWords<'_>), ReversedWordsError> {
+        let template = self.sibling(&mut []);
+        let (head, tail) = self.split_storage(mid)?;
+        Ok((template.sibling(head), template.sibling(tail)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn logical_vec() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let words = ReversedWords::new(&mut data);
+        assert_eq!(vec![1, 0, 7, 6], words.to_logical_vec(2..6).unwrap());
+        assert!(words.to_logical_vec(6..9).is_err());
+    }
+
+    #[test]
+    fn raw_header_and_swapped_body() {
+        let mut data: Vec<u8> = (0..12).collect();
+        let mut words = ReversedWords::new(&mut data);
+        let (mut header, mut body) = words.split_raw_at(4).unwrap();
+        let mut magic = [0u8; 4];
+        std::io::Read::read_exact(&mut header, &mut magic).unwrap();
+        body.write_at(0, &magic).unwrap();
+        assert_eq!([0, 1, 2, 3], magic);
+        assert_eq!(8, body.len());
+        assert_eq!(Err(ReversedWordsError::Misaligned { position: 2, word_size: 4 }), words.split_raw_at(2).map(|_| ()));
+
+        let (mut head, mut tail) = words.split_at_mut(8).unwrap();
+        tail.set_byte(0, head.get_byte(0).unwrap()).unwrap();
+        head.set_byte(0, 0xAA).unwrap();
+        assert_eq!(vec![0, 1, 2, 0xAA, 3, 2, 1, 0, 8, 9, 10, 3], data);
+    }
+
+    #[test]
+    fn aligned_raw_spans() {
+        let mut data: Vec<u8> = (0..12).collect();
+        let mut words = ReversedWords::new(&mut data);
+        let span = words.raw_span(4..12).unwrap();
+        assert_eq!([4, 5, 6, 7, 8, 9, 10, 11], span.bytes());
+        assert_eq!(7, span.logical_byte(0));
+        assert_eq!(6, span.storage_offset(5));
+        assert_eq!(Err(ReversedWordsError::Misaligned { position: 2, word_size: 4 }), words.raw_span(2..8));
+        assert_eq!(Err(ReversedWordsError::Misaligned { position: 6, word_size: 4 }), words.raw_span(0..6));
+
+        let mut span = words.raw_span_mut(0..4).unwrap();
+        let offset = span.storage_offset(0);
+        span.bytes_mut()[offset] = 0xAA;
+        assert_eq!(0xAA, words.get_byte(0).unwrap());
+    }
+}
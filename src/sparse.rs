@@ -0,0 +1,170 @@
+//! A lazily allocated view for modelling large address spaces.
+//!
+//! [`SparseWords`] covers `len` bytes but only allocates 4 KiB pages of storage when a non-zero
+//! byte is first written to them. Untouched pages read as zeroes, so a full 4 GiB guest address
+//! space only costs the pages that are actually used.
+
+use std::{collections::HashMap, io::{Read, Seek, SeekFrom, Write}};
+
+use crate::{storage_offset, ReversedWordsError};
+
+pub const PAGE_SIZE: usize = 4096;
+
+pub struct SparseWords {
+    // keyed by storage index / PAGE_SIZE
+    pages: HashMap<u64, Box<[u8; PAGE_SIZE]>>,
+    len: u64,
+    word_size: u8,
+    position: u64,
+}
+
+impl SparseWords {
+    pub fn new(len: u64, word_size: u8) -> Result<SparseWords, ReversedWordsError> {
+        if word_size == 0 {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        Ok(SparseWords { pages: HashMap::new(), len, word_size, position: 0 })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Number of pages that have storage allocated.
+    pub fn allocated_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Start of each allocated page in storage order, sorted.
+    pub fn allocated_page_addresses(&self) -> Vec<u64> {
+        let mut addresses: Vec<u64> = self.pages.keys().map(|page| page * PAGE_SIZE as u64).collect();
+        addresses.sort_unstable();
+        addresses
+    }
+
+    /// Free every page, making the whole space read as zeroes again.
+    pub fn clear(&mut self) {
+        self.pages.clear();
+    }
+
+    /// Read the logical bytes starting at `addr`, returning how many were in range.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> usize {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let index = match addr.checked_add(i as u64).and_then(|position| storage_offset(position, self.word_size, self.len)) {
+                Some(index) => index,
+                None => return i,
+            };
+            let page = index / PAGE_SIZE as u64;
+            *byte = self.pages.get(&page).map_or(0, |page| page[(index % PAGE_SIZE as u64) as usize]);
+        }
+        buf.len()
+    }
+
+    /// Write logical bytes starting at `addr`, returning how many were in range.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> usize {
+        for (i, byte) in buf.iter().enumerate() {
+            let index = match addr.checked_add(i as u64).and_then(|position| storage_offset(position, self.word_size, self.len)) {
+                Some(index) => index,
+                None => return i,
+            };
+            let page = index / PAGE_SIZE as u64;
+            let offset = (index % PAGE_SIZE as u64) as usize;
+            match self.pages.get_mut(&page) {
+                Some(page) => page[offset] = *byte,
+                // an untouched page already reads as zero
+                None if *byte == 0 => {}
+                None => {
+                    let mut storage = Box::new([0u8; PAGE_SIZE]);
+                    storage[offset] = *byte;
+                    self.pages.insert(page, storage);
+                }
+            }
+        }
+        buf.len()
+    }
+}
+
+impl Read for SparseWords {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.read_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for SparseWords {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.write_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for SparseWords {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.len as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.position as i128 + offset as i128,
+        };
+        if target < 0 || target > u64::MAX as i128 {
+            return Err(ReversedWordsError::SeekOutOfRange { target, len: self.len }.into());
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sparse::*;
+
+    #[test]
+    fn four_gigabyte_space_allocates_on_write() {
+        let mut words = SparseWords::new(1 << 32, 4).unwrap();
+        let mut out = [0xFFu8; 4];
+        assert_eq!(4, words.read_at(0xFFFF_FFFC, &mut out));
+        assert_eq!([0; 4], out);
+        assert_eq!(0, words.allocated_pages());
+
+        words.seek(SeekFrom::Start(0x8037_1240)).unwrap();
+        words.write_all(&[0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+        words.write_at(0x1000, &[0, 0, 0, 0]);
+        assert_eq!(1, words.allocated_pages());
+        assert_eq!(vec![0x8037_1000], words.allocated_page_addresses());
+
+        words.seek(SeekFrom::Start(0x8037_1241)).unwrap();
+        let mut out = [0u8; 2];
+        words.read_exact(&mut out).unwrap();
+        assert_eq!([0xAD, 0xBE], out);
+        assert_eq!(2, words.write_at((1 << 32) - 2, &[1, 2, 3]));
+    }
+
+    #[test]
+    fn addresses_past_4_gib() {
+        let mut words = SparseWords::new(u64::MAX, 4).unwrap();
+        assert_eq!(4, words.write_at(0x1_0000_0002, &[1, 2, 3, 4]));
+        let mut out = [0u8; 4];
+        assert_eq!(4, words.read_at(0x1_0000_0002, &mut out));
+        assert_eq!([1, 2, 3, 4], out);
+        // stops at the end of the address space instead of overflowing
+        assert!(words.write_at(u64::MAX - 1, &[1, 2, 3]) < 3);
+        assert!(words.read_at(u64::MAX - 1, &mut out) < 4);
+    }
+}
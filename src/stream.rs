@@ -0,0 +1,203 @@
+//! Watchers and event bus subscriptions as async [`Stream`]s, with the `tokio` feature.
+//!
+//! [`Watcher::into_stream`] polls on a tokio interval and yields each [`Change`] as it is found;
+//! [`EventBus::subscribe_stream`] and [`EventBus::subscribe_range_stream`] receive published
+//! [`Event`]s without blocking a thread. Both are made inside a tokio runtime with the time
+//! driver enabled, and never end on their own.
+
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use tokio::{
+    sync::mpsc::UnboundedReceiver,
+    time::{Interval, MissedTickBehavior},
+};
+
+use crate::{
+    events::{Event, EventBus},
+    watch::{Change, WatchSource, Watcher},
+};
+
+/// A [`Watcher`] polling `source` every tick. A failed poll yields the error and the next tick
+/// tries again.
+pub struct ChangeStream<'s, S: ?Sized> {
+    watcher: Watcher,
+    source: &'s S,
+    interval: Interval,
+    pending: VecDeque<Change>,
+}
+
+impl<S: ?Sized> ChangeStream<'_, S> {
+    pub fn watcher(&self) -> &Watcher {
+        &self.watcher
+    }
+
+    pub fn watcher_mut(&mut self) -> &mut Watcher {
+        &mut self.watcher
+    }
+
+    pub fn into_watcher(self) -> Watcher {
+        self.watcher
+    }
+}
+
+impl<S: WatchSource + ?Sized> Stream for ChangeStream<'_, S> {
+    type Item = std::io::Result<Change>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(change) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(change)));
+            }
+            ready!(this.interval.poll_tick(cx));
+            let pending = &mut this.pending;
+            if let Err(error) = this.watcher.poll_with(this.source, |change| pending.push_back(change)) {
+                return Poll::Ready(Some(Err(error)));
+            }
+        }
+    }
+}
+
+impl Watcher {
+    /// Poll `source` every `period`, yielding changes as they are found.
+    pub fn into_stream<S: WatchSource + ?Sized>(self, source: &S, period: Duration) -> ChangeStream<'_, S> {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ChangeStream { watcher: self, source, interval, pending: VecDeque::new() }
+    }
+}
+
+/// Events published to an [`EventBus`] after subscribing.
+pub struct EventStream {
+    receiver: UnboundedReceiver<Event>,
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
+
+impl EventBus {
+    pub fn subscribe_stream(&self) -> EventStream {
+        EventStream { receiver: self.add_async(None) }
+    }
+
+    /// Only the events that touch `range`.
+    pub fn subscribe_range_stream(&self, range: Range<u64>) -> EventStream {
+        EventStream { receiver: self.add_async(Some(range)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::poll_fn,
+        sync::{
+            atomic::{AtomicU8, AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Wake, Waker},
+    };
+
+    use crate::{events::Event, journal::RecordingWords, stream::*, AtomicReversedWords, Endian, ReversedWords};
+
+    async fn next<T: Stream + Unpin>(stream: &mut T) -> Option<T::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap()
+    }
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn watcher_stream_yields_changes() {
+        let ram: Vec<AtomicU8> = (0..8).map(|_| AtomicU8::new(0)).collect();
+        let words = AtomicReversedWords::new(&ram);
+        let mut watcher = Watcher::new();
+        let id = watcher.watch_value::<u32>(4);
+        watcher.poll(&words).unwrap();
+        runtime().block_on(async {
+            let mut changes = watcher.into_stream(&words, Duration::from_millis(1));
+            words.write_value_at(4, 7u32, Endian::Big).unwrap();
+            let change = next(&mut changes).await.unwrap().unwrap();
+            assert_eq!(id, change.id);
+            assert_eq!(Some(7), change.new_value::<u32>(Endian::Big));
+
+            // nothing changed, so the stream waits for the timer instead of spinning
+            let counter = Arc::new(CountingWaker::default());
+            let waker = Waker::from(counter.clone());
+            let mut cx = Context::from_waker(&waker);
+            assert!(Pin::new(&mut changes).poll_next(&mut cx).is_pending());
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            assert!(counter.0.load(Ordering::SeqCst) > 0);
+            words.write_value_at(4, 8u32, Endian::Big).unwrap();
+            let change = next(&mut changes).await.unwrap().unwrap();
+            assert_eq!(Some(8), change.new_value::<u32>(Endian::Big));
+            assert_eq!(Some(4..8), changes.into_watcher().range(id));
+        });
+    }
+
+    #[test]
+    fn event_stream_wakes_on_publish() {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe_stream();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut events).poll_next(&mut cx).is_pending());
+        assert_eq!(0, counter.0.load(Ordering::SeqCst));
+
+        let mut data = vec![0u8; 8];
+        let mut words = ReversedWords::new(&mut data);
+        RecordingWords::new(&mut words).publish_to(bus.clone()).write_at(2, &[9]).unwrap();
+        assert_eq!(1, counter.0.load(Ordering::SeqCst));
+        match Pin::new(&mut events).poll_next(&mut cx) {
+            Poll::Ready(Some(Event::Write(record))) => assert_eq!((2, vec![9]), (record.addr, record.new)),
+            other => panic!("{:?}", other),
+        }
+        drop(events);
+        bus.publish(Event::Write(crate::journal::WriteRecord { addr: 0, old: vec![0], new: vec![1] }));
+        assert_eq!(0, bus.subscribers());
+    }
+
+    #[test]
+    fn bus_streams_under_a_runtime() {
+        let bus = EventBus::new();
+        runtime().block_on(async {
+            let mut everything = bus.subscribe_stream();
+            let mut high = bus.subscribe_range_stream(4..8);
+            let publisher = bus.clone();
+            let thread = std::thread::spawn(move || {
+                let mut data = vec![0u8; 8];
+                let mut words = ReversedWords::new(&mut data);
+                let mut recording = RecordingWords::new(&mut words).publish_to(publisher);
+                std::thread::sleep(Duration::from_millis(5));
+                recording.write_at(0, &[1]).unwrap();
+                recording.write_at(6, &[2]).unwrap();
+            });
+            assert!(matches!(next(&mut everything).await, Some(Event::Write(record)) if record.addr == 0));
+            assert!(matches!(next(&mut everything).await, Some(Event::Write(record)) if record.addr == 6));
+            assert!(matches!(next(&mut high).await, Some(Event::Write(record)) if record.addr == 6));
+            thread.join().unwrap();
+        });
+    }
+}
@@ -0,0 +1,459 @@
+//! Reading and writing values by symbol name.
+//!
+//! A [`SymbolTable`] is loaded from a GNU ld `.map` file (or an ELF with the `elf` feature) and
+//! resolves names to virtual addresses, which its [`AddressMap`] turns into view offsets. Addresses
+//! then follow the build instead of being hardcoded:
+//!
+//! ```
+//! # use reversed_word_byte_rw::{Endian, ReversedWords, symbols::SymbolTable, translate::AddressMap};
+//! let map = "                0x0000000080000010                gPlayerHealth\n";
+//! let symbols = SymbolTable::from_map_file(map).with_address_map(AddressMap::n64(0x80_0000));
+//! let mut rdram = vec![0u8; 0x20];
+//! let mut words = ReversedWords::new(&mut rdram);
+//! symbols.view(&mut words).write_u32_by_name("gPlayerHealth", 100, Endian::Big).unwrap();
+//! assert_eq!(100, symbols.view(&mut words).read_u32_by_name("gPlayerHealth", Endian::Big).unwrap());
+//! ```
+//!
+//! Tools built against a decomp can instead resolve symbols at compile time. [`MapConstants`] turns
+//! a map file into a [`TypedSymbol`] constant per symbol from a build script, so a symbol that
+//! disappears or changes type breaks the build rather than failing at runtime:
+//!
+//! ```no_run
+//! // build.rs
+//! use reversed_word_byte_rw::symbols::MapConstants;
+//!
+//! fn main() -> std::io::Result<()> {
+//!     MapConstants::from_map_path("build/game.map")?
+//!         .with_type("gFrameCount", "u32")
+//!         .with_type("gPlayerHealth", "u16")
+//!         .write_to_out_dir("symbols.rs")?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ```ignore
+//! // src/main.rs
+//! reversed_word_byte_rw::include_map_constants!("symbols.rs");
+//!
+//! let health: u16 = gPlayerHealth.read(&mut words, &AddressMap::n64(0x80_0000), Endian::Big)?;
+//! ```
+
+use std::{collections::HashMap, fmt::Write as _, io, marker::PhantomData, path::Path, path::PathBuf};
+
+use binread::Endian;
+
+use crate::{translate::AddressMap, Primitive, ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolTable {
+    symbols: HashMap<String, u64>,
+    address_map: AddressMap,
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        SymbolTable { symbols: HashMap::new(), address_map: AddressMap::identity() }
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.')
+}
+
+impl SymbolTable {
+    /// An empty table whose addresses are used as view offsets unchanged.
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    /// Collect the `address symbol` lines of a GNU ld map file, ignoring everything else
+    /// (sections, input files, fill).
+    pub fn from_map_file(text: &str) -> SymbolTable {
+        let mut table = SymbolTable::new();
+        for line in text.lines() {
+            let mut tokens = line.split_whitespace();
+            let (Some(addr), Some(name)) = (tokens.next(), tokens.next()) else {
+                continue;
+            };
+            // linker script assignments look like `0x80000400 _mainSegmentStart = .`
+            if !matches!(tokens.next(), None | Some("=")) || !is_identifier(name) {
+                continue;
+            }
+            let Some(addr) = addr.strip_prefix("0x").and_then(|hex| u64::from_str_radix(hex, 16).ok()) else {
+                continue;
+            };
+            table.insert(name, addr);
+        }
+        table
+    }
+
+    /// Collect the defined symbols of an ELF file.
+    #[cfg(feature = "elf")]
+    pub fn from_elf(data: &[u8]) -> Result<SymbolTable, ReversedWordsError> {
+        use object::{Object, ObjectSymbol};
+
+        let file = object::File::parse(data)
+            .map_err(|e| ReversedWordsError::InvalidSymbolFile { reason: e.to_string() })?;
+        let mut table = SymbolTable::new();
+        for symbol in file.symbols().filter(|symbol| symbol.is_definition()) {
+            if let Ok(name) = symbol.name() {
+                if !name.is_empty() {
+                    table.insert(name, symbol.address());
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    pub fn with_address_map(mut self, address_map: AddressMap) -> SymbolTable {
+        self.address_map = address_map;
+        self
+    }
+
+    pub fn address_map(&self) -> &AddressMap {
+        &self.address_map
+    }
+
+    pub fn insert(&mut self, name: &str, addr: u64) {
+        self.symbols.insert(name.to_string(), addr);
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Every symbol and its virtual address, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.symbols.iter().map(|(name, addr)| (name.as_str(), *addr))
+    }
+
+    /// Virtual address of `name`.
+    pub fn address_of(&self, name: &str) -> Option<u64> {
+        self.symbols.get(name).copied()
+    }
+
+    /// View offset of `name`.
+    pub fn offset_of(&self, name: &str) -> Result<u64, ReversedWordsError> {
+        let addr = self
+            .address_of(name)
+            .ok_or_else(|| ReversedWordsError::UnknownSymbol { name: name.to_string() })?;
+        self.address_map.translate(addr)
+    }
+
+    /// Access `words` through this table.
+    pub fn view<'s, 'v, 'a>(&'s self, words: &'v mut ReversedWords<'a>) -> SymbolView<'s, 'v, 'a> {
+        SymbolView { symbols: self, words }
+    }
+}
+
+/// A view borrowed together with the symbol table used to address it.
+pub struct SymbolView<'s, 'v, 'a> {
+    symbols: &'s SymbolTable,
+    words: &'v mut ReversedWords<'a>,
+}
+
+macro_rules! named_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            pub fn $read(&mut self, name: &str, endian: Endian) -> std::io::Result<$ty> {
+                self.read_value_by_name(name, endian)
+            }
+
+            pub fn $write(&mut self, name: &str, value: $ty, endian: Endian) -> std::io::Result<()> {
+                self.write_value_by_name(name, value, endian)
+            }
+        )*
+    };
+}
+
+impl SymbolView<'_, '_, '_> {
+    pub fn read_value_by_name<T: Primitive>(&mut self, name: &str, endian: Endian) -> std::io::Result<T> {
+        let addr = self.symbols.offset_of(name)?;
+        self.words.read_value_at(addr, endian)
+    }
+
+    pub fn write_value_by_name<T: Primitive>(&mut self, name: &str, value: T, endian: Endian) -> std::io::Result<()> {
+        let addr = self.symbols.offset_of(name)?;
+        self.words.write_value_at(addr, value, endian)
+    }
+
+    named_accessors! {
+        u8 => read_u8_by_name, write_u8_by_name;
+        u16 => read_u16_by_name, write_u16_by_name;
+        u32 => read_u32_by_name, write_u32_by_name;
+        u64 => read_u64_by_name, write_u64_by_name;
+        i8 => read_i8_by_name, write_i8_by_name;
+        i16 => read_i16_by_name, write_i16_by_name;
+        i32 => read_i32_by_name, write_i32_by_name;
+        i64 => read_i64_by_name, write_i64_by_name;
+        f32 => read_f32_by_name, write_f32_by_name;
+        f64 => read_f64_by_name, write_f64_by_name;
+    }
+}
+
+/// The virtual address of a `T`, usually generated from a map file by [`MapConstants`].
+pub struct TypedSymbol<T> {
+    name: &'static str,
+    addr: u64,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for TypedSymbol<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedSymbol<T> {}
+
+impl<T> std::fmt::Debug for TypedSymbol<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedSymbol").field("name", &self.name).field("addr", &self.addr).finish()
+    }
+}
+
+impl<T> TypedSymbol<T> {
+    pub const fn new(name: &'static str, addr: u64) -> TypedSymbol<T> {
+        TypedSymbol { name, addr, value: PhantomData }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub const fn addr(&self) -> u64 {
+        self.addr
+    }
+}
+
+impl<T: Primitive> TypedSymbol<T> {
+    /// View offset of the symbol under `address_map`.
+    pub fn offset(&self, address_map: &AddressMap) -> Result<u64, ReversedWordsError> {
+        address_map.translate(self.addr)
+    }
+
+    pub fn read(&self, words: &mut ReversedWords, address_map: &AddressMap, endian: Endian) -> io::Result<T> {
+        words.read_value_at(self.offset(address_map)?, endian)
+    }
+
+    pub fn write(&self, words: &mut ReversedWords, address_map: &AddressMap, value: T, endian: Endian) -> io::Result<()> {
+        words.write_value_at(self.offset(address_map)?, value, endian)
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "static", "struct", "super",
+    "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn is_rust_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && s != "_"
+        && !KEYWORDS.contains(&s)
+}
+
+/// Generates Rust source declaring a [`TypedSymbol`] constant for symbols of a map file, for use
+/// from a build script with [`include_map_constants!`](crate::include_map_constants).
+#[derive(Clone, Debug)]
+pub struct MapConstants {
+    symbols: SymbolTable,
+    types: Vec<(String, String)>,
+    default_type: Option<String>,
+    crate_path: String,
+}
+
+impl MapConstants {
+    pub fn new(symbols: SymbolTable) -> MapConstants {
+        MapConstants {
+            symbols,
+            types: Vec::new(),
+            default_type: None,
+            crate_path: "::reversed_word_byte_rw".to_string(),
+        }
+    }
+
+    pub fn from_map_file(text: &str) -> MapConstants {
+        MapConstants::new(SymbolTable::from_map_file(text))
+    }
+
+    /// Read the map file at `path`, asking cargo to rerun the build script when it changes.
+    pub fn from_map_path(path: impl AsRef<Path>) -> io::Result<MapConstants> {
+        let path = path.as_ref();
+        println!("cargo:rerun-if-changed={}", path.display());
+        Ok(MapConstants::from_map_file(&std::fs::read_to_string(path)?))
+    }
+
+    /// Declare `name` as a `ty`, which is any type path implementing `Primitive` in the including
+    /// crate. Generating fails if the map has no such symbol.
+    pub fn with_type(mut self, name: &str, ty: &str) -> MapConstants {
+        self.types.retain(|(existing, _)| existing != name);
+        self.types.push((name.to_string(), ty.to_string()));
+        self
+    }
+
+    /// Also declare every symbol without a [`with_type`](Self::with_type) as a `ty`. Symbols whose
+    /// names are not Rust identifiers are skipped.
+    pub fn with_default_type(mut self, ty: &str) -> MapConstants {
+        self.default_type = Some(ty.to_string());
+        self
+    }
+
+    /// Path of this crate in the generated code, for crates that rename the dependency.
+    pub fn with_crate_path(mut self, path: &str) -> MapConstants {
+        self.crate_path = path.to_string();
+        self
+    }
+
+    /// The generated constants, sorted by name.
+    pub fn generate(&self) -> Result<String, ReversedWordsError> {
+        let mut constants = Vec::new();
+        for (name, ty) in &self.types {
+            let addr = self
+                .symbols
+                .address_of(name)
+                .ok_or_else(|| ReversedWordsError::UnknownSymbol { name: name.clone() })?;
+            if !is_rust_identifier(name) {
+                return Err(ReversedWordsError::InvalidSymbolFile {
+                    reason: format!("{:?} is not a Rust identifier", name),
+                });
+            }
+            constants.push((name.as_str(), ty.as_str(), addr));
+        }
+        if let Some(default_type) = &self.default_type {
+            for (name, addr) in self.symbols.iter() {
+                if is_rust_identifier(name) && !self.types.iter().any(|(typed, _)| typed == name) {
+                    constants.push((name, default_type, addr));
+                }
+            }
+        }
+        constants.sort_unstable_by_key(|(name, _, _)| *name);
+
+        let mut source = String::new();
+        for (name, ty, addr) in constants {
+            let _ = writeln!(
+                source,
+                "#[allow(non_upper_case_globals, dead_code)]\npub const {name}: {path}::symbols::TypedSymbol<{ty}> = \
+                 {path}::symbols::TypedSymbol::new({name:?}, {addr:#x});",
+                path = self.crate_path,
+            );
+        }
+        Ok(source)
+    }
+
+    /// Write the generated constants to `file_name` in the build script's `OUT_DIR`.
+    pub fn write_to_out_dir(&self, file_name: &str) -> io::Result<PathBuf> {
+        let out_dir = std::env::var_os("OUT_DIR")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set; call this from a build script"))?;
+        let path = Path::new(&out_dir).join(file_name);
+        std::fs::write(&path, self.generate()?)?;
+        Ok(path)
+    }
+}
+
+/// Include the constants a build script wrote with [`MapConstants::write_to_out_dir`].
+#[macro_export]
+macro_rules! include_map_constants {
+    ($file_name:literal) => {
+        include!(concat!(env!("OUT_DIR"), "/", $file_name));
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{symbols::*, translate::AddressMap};
+
+    const MAP: &str = "
+ .data          0x0000000080000000       0x20 build/src/game.o
+                0x0000000080000000                gFrameCount
+                0x0000000080000006                gPlayerHealth
+ *fill*         0x0000000080000008        0x8
+                0x0000000080000400                _mainSegmentStart = .
+";
+
+    #[test]
+    fn map_file_symbols() {
+        let symbols = SymbolTable::from_map_file(MAP);
+        assert_eq!(3, symbols.len());
+        assert_eq!(Some(0x8000_0006), symbols.address_of("gPlayerHealth"));
+        assert_eq!(Some(0x8000_0400), symbols.address_of("_mainSegmentStart"));
+        assert_eq!(None, symbols.address_of(".data"));
+    }
+
+    #[test]
+    fn read_and_write_by_name() {
+        let symbols = SymbolTable::from_map_file(MAP).with_address_map(AddressMap::n64(0x10));
+        let mut data = vec![0u8; 0x10];
+        let mut words = ReversedWords::new(&mut data);
+        let mut view = symbols.view(&mut words);
+        view.write_u16_by_name("gPlayerHealth", 0x1234, Endian::Big).unwrap();
+        assert_eq!(0x1234, view.read_u16_by_name("gPlayerHealth", Endian::Big).unwrap());
+        assert_eq!([0x34, 0x12, 0, 0], data[4..8]);
+
+        let mut words = ReversedWords::new(&mut data);
+        let mut view = symbols.view(&mut words);
+        let error = view.read_u32_by_name("gMissing", Endian::Big).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::UnknownSymbol { name: "gMissing".to_string() }),
+            ReversedWordsError::from_io(&error)
+        );
+        let error = view.read_u32_by_name("_mainSegmentStart", Endian::Big).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::UnmappedAddress { addr: 0x8000_0400 }),
+            ReversedWordsError::from_io(&error)
+        );
+    }
+
+    #[allow(non_upper_case_globals)]
+    const gPlayerHealth: TypedSymbol<u16> = TypedSymbol::new("gPlayerHealth", 0x8000_0006);
+
+    #[test]
+    fn typed_symbols() {
+        let address_map = AddressMap::n64(0x10);
+        let mut data = vec![0u8; 0x10];
+        let mut words = ReversedWords::new(&mut data);
+        gPlayerHealth.write(&mut words, &address_map, 0x1234, Endian::Big).unwrap();
+        assert_eq!(0x1234, gPlayerHealth.read(&mut words, &address_map, Endian::Big).unwrap());
+        assert_eq!([0x34, 0x12, 0, 0], data[4..8]);
+    }
+
+    #[test]
+    fn generates_map_constants() {
+        let constants = MapConstants::from_map_file(MAP).with_type("gPlayerHealth", "u16");
+        assert_eq!(
+            "#[allow(non_upper_case_globals, dead_code)]\npub const gPlayerHealth: \
+             ::reversed_word_byte_rw::symbols::TypedSymbol<u16> = \
+             ::reversed_word_byte_rw::symbols::TypedSymbol::new(\"gPlayerHealth\", 0x80000006);\n",
+            constants.generate().unwrap()
+        );
+
+        let source = constants.clone().with_default_type("u32").with_crate_path("crate").generate().unwrap();
+        let names: Vec<_> = source.lines().filter_map(|line| line.strip_prefix("pub const ")).collect();
+        assert_eq!(3, names.len());
+        assert!(names[0].starts_with("_mainSegmentStart: crate::symbols::TypedSymbol<u32>"));
+        assert!(names[1].starts_with("gFrameCount: crate::symbols::TypedSymbol<u32>"));
+        assert!(names[2].starts_with("gPlayerHealth: crate::symbols::TypedSymbol<u16>"));
+
+        assert_eq!(
+            Err(ReversedWordsError::UnknownSymbol { name: "gMissing".to_string() }),
+            constants.with_type("gMissing", "u8").generate()
+        );
+    }
+
+    #[cfg(all(feature = "elf", target_os = "linux"))]
+    #[test]
+    fn elf_symbols() {
+        let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        assert!(!SymbolTable::from_elf(&exe).unwrap().is_empty());
+        assert!(matches!(SymbolTable::from_elf(b"not an elf"), Err(ReversedWordsError::InvalidSymbolFile { .. })));
+    }
+}
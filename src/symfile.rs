@@ -0,0 +1,237 @@
+//! Symbol and label files from emulators and decompilation projects.
+//!
+//! Three formats are read:
+//!
+//! - Project64 `.sym` files: `address,type,name[,description]` per line, with the address in hex
+//!   and a type of `code`, `data`, `u8`..`s64`, `float`, `double` or `v2`..`v4`.
+//! - `.lbl` label files, as used by mupen64plus front ends and other debuggers: `address name`
+//!   per line.
+//! - splat `symbol_addrs.txt` files from decomp projects: `name = 0x80123456; // type:s32 size:0x4`.
+//!
+//! Addresses are the target's virtual addresses. A [`SymbolFile`] becomes a [`SymbolTable`] for
+//! the name based accessors, or [`Labels`] (with the types, sizes and descriptions that the file
+//! records) through an [`AddressMap`].
+
+use crate::{
+    expr::ValueType,
+    labels::{Label, Labels},
+    symbols::SymbolTable,
+    translate::AddressMap,
+    ReversedWordsError,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolEntry {
+    pub name: String,
+    /// Virtual address.
+    pub addr: u64,
+    pub size: Option<u64>,
+    pub value_type: Option<ValueType>,
+    pub comment: Option<String>,
+}
+
+impl SymbolEntry {
+    fn new(name: &str, addr: u64) -> SymbolEntry {
+        SymbolEntry { name: name.to_string(), addr, size: None, value_type: None, comment: None }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SymbolFile {
+    entries: Vec<SymbolEntry>,
+}
+
+fn invalid(line: usize, reason: &str) -> ReversedWordsError {
+    ReversedWordsError::InvalidSymbolFile { reason: format!("line {}: {}", line + 1, reason) }
+}
+
+fn parse_address(text: &str, line: usize) -> Result<u64, ReversedWordsError> {
+    let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    u64::from_str_radix(hex, 16).map_err(|_| invalid(line, &format!("{:?} is not a hex address", text)))
+}
+
+// the value type for a type name, in either Rust (`i32`) or C-ish (`s32`, `float`) spelling
+fn value_type(name: &str) -> Option<ValueType> {
+    match name {
+        "s8" => Some(ValueType::I8),
+        "s16" => Some(ValueType::I16),
+        "s32" => Some(ValueType::I32),
+        "s64" => Some(ValueType::I64),
+        "float" => Some(ValueType::F32),
+        "double" => Some(ValueType::F64),
+        _ => ValueType::from_name(name),
+    }
+}
+
+// the text of `line` before any comment
+fn strip_comment(line: &str) -> &str {
+    let end = ["//", "#", ";"].iter().filter_map(|marker| line.find(marker)).min().unwrap_or(line.len());
+    line[..end].trim()
+}
+
+impl SymbolFile {
+    pub fn new() -> SymbolFile {
+        SymbolFile::default()
+    }
+
+    /// Parse a Project64 `.sym` file.
+    pub fn parse_project64(text: &str) -> Result<SymbolFile, ReversedWordsError> {
+        let mut file = SymbolFile::new();
+        for (line, text) in text.lines().enumerate() {
+            if text.trim().is_empty() {
+                continue;
+            }
+            let mut fields = text.splitn(4, ',').map(str::trim);
+            let (Some(addr), Some(kind), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(invalid(line, "expected address,type,name"));
+            };
+            let mut entry = SymbolEntry::new(name, parse_address(addr, line)?);
+            entry.comment = fields.next().filter(|comment| !comment.is_empty()).map(str::to_string);
+            match kind {
+                "code" | "data" => {}
+                "v2" | "v3" | "v4" => entry.size = Some(4 * (kind.as_bytes()[1] - b'0') as u64),
+                _ => {
+                    let value_type = value_type(kind).ok_or_else(|| invalid(line, &format!("unknown type {:?}", kind)))?;
+                    entry.size = Some(value_type.size());
+                    entry.value_type = Some(value_type);
+                }
+            }
+            file.entries.push(entry);
+        }
+        Ok(file)
+    }
+
+    /// Parse a `.lbl` file of `address name` lines. `#`, `;` and `//` start comments.
+    pub fn parse_labels(text: &str) -> Result<SymbolFile, ReversedWordsError> {
+        let mut file = SymbolFile::new();
+        for (line, text) in text.lines().enumerate() {
+            let text = strip_comment(text);
+            if text.is_empty() {
+                continue;
+            }
+            let mut tokens = text.split_whitespace();
+            let (Some(addr), Some(name), None) = (tokens.next(), tokens.next(), tokens.next()) else {
+                return Err(invalid(line, "expected an address and a name"));
+            };
+            file.entries.push(SymbolEntry::new(name, parse_address(addr, line)?));
+        }
+        Ok(file)
+    }
+
+    /// Parse a splat `symbol_addrs.txt`, keeping the `type:` and `size:` attributes of each line's
+    /// trailing comment and ignoring the rest.
+    pub fn parse_splat(text: &str) -> Result<SymbolFile, ReversedWordsError> {
+        let mut file = SymbolFile::new();
+        for (line, text) in text.lines().enumerate() {
+            let (assignment, attributes) = text.split_once("//").unwrap_or((text, ""));
+            let assignment = assignment.trim();
+            if assignment.is_empty() {
+                continue;
+            }
+            let Some((name, addr)) = assignment.strip_suffix(';').and_then(|assignment| assignment.split_once('=')) else {
+                return Err(invalid(line, "expected name = address;"));
+            };
+            let mut entry = SymbolEntry::new(name.trim(), parse_address(addr.trim(), line)?);
+            for attribute in attributes.split_whitespace() {
+                match attribute.split_once(':') {
+                    Some(("type", kind)) => entry.value_type = value_type(kind),
+                    Some(("size", size)) => entry.size = Some(parse_address(size, line)?),
+                    _ => {}
+                }
+            }
+            if entry.size.is_none() {
+                entry.size = entry.value_type.map(ValueType::size);
+            }
+            file.entries.push(entry);
+        }
+        Ok(file)
+    }
+
+    pub fn entries(&self) -> &[SymbolEntry] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, entry: SymbolEntry) {
+        self.entries.push(entry);
+    }
+
+    /// A symbol table of every entry, using `address_map` to find them in a view.
+    pub fn symbols(&self, address_map: AddressMap) -> SymbolTable {
+        let mut table = SymbolTable::new().with_address_map(address_map);
+        for entry in &self.entries {
+            table.insert(&entry.name, entry.addr);
+        }
+        table
+    }
+
+    /// A label for every entry that maps into a view of `len` bytes. Entries without a size run
+    /// up to the next entry, or the end of the view.
+    pub fn labels(&self, address_map: &AddressMap, len: u64) -> Labels {
+        let mut mapped: Vec<(u64, &SymbolEntry)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| address_map.translate(entry.addr).ok().filter(|offset| *offset < len).map(|offset| (offset, entry)))
+            .collect();
+        mapped.sort_by_key(|(offset, _)| *offset);
+        let mut labels = Labels::new();
+        for (i, (start, entry)) in mapped.iter().enumerate() {
+            let end = match entry.size {
+                Some(size) => start.saturating_add(size.max(1)).min(len),
+                None => mapped[i + 1..].iter().map(|(next, _)| *next).find(|next| next > start).unwrap_or(len),
+            };
+            let mut label = Label::new(&entry.name, *start..end);
+            label.comment = entry.comment.clone();
+            label.value_type = entry.value_type;
+            labels.insert(label);
+        }
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{symfile::*, Endian, ReversedWords};
+
+    #[test]
+    fn parses_emulator_formats() {
+        let sym = "80000010,u32,gFrameCount\n80000014,s16,gPlayerHealth,health, in half points\n\n80000020,code,main\n80000030,v3,gCameraPos\n";
+        let file = SymbolFile::parse_project64(sym).unwrap();
+        assert_eq!(4, file.entries().len());
+        let health = &file.entries()[1];
+        assert_eq!((0x8000_0014, Some(ValueType::I16), Some(2)), (health.addr, health.value_type, health.size));
+        assert_eq!(Some("health, in half points"), health.comment.as_deref());
+        assert_eq!(Some(12), file.entries()[3].size);
+        assert!(matches!(SymbolFile::parse_project64("80000010,u24,gBad"), Err(ReversedWordsError::InvalidSymbolFile { .. })));
+        assert!(SymbolFile::parse_project64("8000001G,u32,gBad").is_err());
+
+        let lbl = "# labels\n0x80000010 gFrameCount\n80000020 main ; entry point\n";
+        let file = SymbolFile::parse_labels(lbl).unwrap();
+        assert_eq!(vec![("gFrameCount", 0x8000_0010), ("main", 0x8000_0020)], file.entries().iter().map(|entry| (entry.name.as_str(), entry.addr)).collect::<Vec<_>>());
+        assert!(SymbolFile::parse_labels("80000010 two names").is_err());
+
+        let splat = "gFrameCount = 0x80000010; // type:u32\ngPlayer = 0x80000018; // size:0x8 rom:0x1000\n\n// comment\nmain = 0x80000020;\n";
+        let file = SymbolFile::parse_splat(splat).unwrap();
+        assert_eq!(Some(4), file.entries()[0].size);
+        assert_eq!((None, Some(8)), (file.entries()[1].value_type, file.entries()[1].size));
+        assert_eq!(3, file.entries().len());
+        assert!(SymbolFile::parse_splat("gFrameCount 0x80000010").is_err());
+    }
+
+    #[test]
+    fn feeds_symbols_and_labels() {
+        let file = SymbolFile::parse_project64("80000010,u32,gFrameCount\n80000014,s16,gPlayerHealth,health\n80000020,code,main\n90000000,u8,gUnmapped").unwrap();
+        let map = AddressMap::n64(0x40);
+        let mut data = vec![0u8; 0x40];
+        let mut words = ReversedWords::new(&mut data);
+        let symbols = file.symbols(map.clone());
+        symbols.view(&mut words).write_i16_by_name("gPlayerHealth", -3, Endian::Big).unwrap();
+        assert_eq!(-3, words.read_i16_at(0x14, Endian::Big).unwrap());
+
+        let labels = file.labels(&map, 0x40);
+        assert_eq!(3, labels.len());
+        let health = labels.get("gPlayerHealth").unwrap();
+        assert_eq!((0x14..0x16, Some(ValueType::I16), Some("health")), (health.range.clone(), health.value_type, health.comment.as_deref()));
+        assert_eq!(Some(0x20..0x40), labels.get("main").map(|label| label.range.clone()));
+        assert_eq!(Some("gFrameCount+0x3".to_string()), labels.describe(0x13));
+    }
+}
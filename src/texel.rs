@@ -0,0 +1,264 @@
+//! N64 texel formats, decoded to and encoded from RGBA8.
+//!
+//! Textures are laid out in logical order, so they are read through the view like any other
+//! data: texel `index` of a texture starting at `addr` is at `addr + index * bits / 8`, with the
+//! high nibble first in 4 bit formats, and 16 and 32 bit texels are big endian. Color indexed
+//! formats look their colors up in an RGBA5551 palette (a TLUT) elsewhere in the view.
+
+use std::convert::TryInto;
+
+use crate::{ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TexelFormat {
+    Rgba5551,
+    Rgba8888,
+    /// 3 bits of intensity and 1 of alpha.
+    Ia4,
+    /// 4 bits of intensity and 4 of alpha.
+    Ia8,
+    /// 8 bits of intensity and 8 of alpha.
+    Ia16,
+    /// Intensity only, which is also used as the alpha.
+    I4,
+    I8,
+    /// 4 bit indices into the 16 entry palette at `palette`.
+    Ci4 { palette: u64 },
+    /// 8 bit indices into the 256 entry palette at `palette`.
+    Ci8 { palette: u64 },
+}
+
+impl TexelFormat {
+    pub fn bits_per_texel(self) -> u64 {
+        match self {
+            TexelFormat::Ia4 | TexelFormat::I4 | TexelFormat::Ci4 { .. } => 4,
+            TexelFormat::Ia8 | TexelFormat::I8 | TexelFormat::Ci8 { .. } => 8,
+            TexelFormat::Rgba5551 | TexelFormat::Ia16 => 16,
+            TexelFormat::Rgba8888 => 32,
+        }
+    }
+}
+
+// widen an n bit channel to 8 bits, so the maximum maps to 255
+fn expand(value: u8, bits: u32) -> u8 {
+    ((value as u32 * 255 + ((1 << bits) - 1) / 2) / ((1 << bits) - 1)) as u8
+}
+
+// narrow an 8 bit channel to n bits, rounding
+fn narrow(value: u8, bits: u32) -> u8 {
+    ((value as u32 * ((1 << bits) - 1) + 127) / 255) as u8
+}
+
+fn intensity(rgba: [u8; 4]) -> u8 {
+    ((rgba[0] as u32 + rgba[1] as u32 + rgba[2] as u32) / 3) as u8
+}
+
+pub fn decode_rgba5551(texel: u16) -> [u8; 4] {
+    let channel = |shift: u32| expand((texel >> shift & 0x1F) as u8, 5);
+    [channel(11), channel(6), channel(1), if texel & 1 != 0 { 255 } else { 0 }]
+}
+
+/// The nearest RGBA5551 texel to `rgba`; alpha of 128 and up is opaque.
+pub fn encode_rgba5551(rgba: [u8; 4]) -> u16 {
+    let channel = |value: u8, shift: u32| (narrow(value, 5) as u16) << shift;
+    channel(rgba[0], 11) | channel(rgba[1], 6) | channel(rgba[2], 1) | (rgba[3] >= 128) as u16
+}
+
+pub fn decode_ia4(texel: u8) -> [u8; 4] {
+    let i = expand(texel >> 1 & 0x7, 3);
+    [i, i, i, if texel & 1 != 0 { 255 } else { 0 }]
+}
+
+/// The nearest IA4 texel to `rgba`, with the red, green and blue averaged for the intensity.
+pub fn encode_ia4(rgba: [u8; 4]) -> u8 {
+    narrow(intensity(rgba), 3) << 1 | (rgba[3] >= 128) as u8
+}
+
+pub fn decode_ia8(texel: u8) -> [u8; 4] {
+    let i = expand(texel >> 4, 4);
+    [i, i, i, expand(texel & 0xF, 4)]
+}
+
+pub fn encode_ia8(rgba: [u8; 4]) -> u8 {
+    narrow(intensity(rgba), 4) << 4 | narrow(rgba[3], 4)
+}
+
+impl ReversedWords<'_> {
+    // the logical byte holding the texel and, for 4 bit formats, whether it's the low nibble
+    // (saturating, so a position that overflows is just out of range)
+    fn texel_position(addr: u64, index: u64, format: TexelFormat) -> (u64, bool) {
+        let bit = index.saturating_mul(format.bits_per_texel());
+        (addr.saturating_add(bit / 8), !bit.is_multiple_of(8))
+    }
+
+    fn palette_entry(&self, palette: u64, index: u8) -> std::io::Result<[u8; 4]> {
+        Ok(decode_rgba5551(u16::from_be_bytes(self.read_array(palette.saturating_add(index as u64 * 2))?)))
+    }
+
+    /// Texel `index` of the `format` texture starting at `addr`, as RGBA8.
+    pub fn read_texel(&self, addr: u64, index: u64, format: TexelFormat) -> std::io::Result<[u8; 4]> {
+        let (position, low) = Self::texel_position(addr, index, format);
+        let byte = || self.get_byte(position);
+        let nibble = || -> std::io::Result<u8> { Ok(if low { byte()? & 0xF } else { byte()? >> 4 }) };
+        Ok(match format {
+            TexelFormat::Rgba5551 => decode_rgba5551(u16::from_be_bytes(self.read_array(position)?)),
+            TexelFormat::Rgba8888 => self.read_array(position)?,
+            TexelFormat::Ia4 => decode_ia4(nibble()?),
+            TexelFormat::Ia8 => decode_ia8(byte()?),
+            TexelFormat::Ia16 => {
+                let [i, a] = self.read_array(position)?;
+                [i, i, i, a]
+            }
+            TexelFormat::I4 => [expand(nibble()?, 4); 4],
+            TexelFormat::I8 => [byte()?; 4],
+            TexelFormat::Ci4 { palette } => self.palette_entry(palette, nibble()?)?,
+            TexelFormat::Ci8 { palette } => self.palette_entry(palette, byte()?)?,
+        })
+    }
+
+    /// The `width` by `height` `format` texture or framebuffer at `addr` as RGBA8, four bytes per
+    /// pixel, row by row. Fails unless the whole image is in range.
+    pub fn decode_image(&self, addr: u64, width: u32, height: u32, format: TexelFormat) -> std::io::Result<Vec<u8>> {
+        let texels = width as u64 * height as u64;
+        let too_big = ReversedWordsError::RangeOutOfRange { start: addr, end: u64::MAX, len: self.len() };
+        let len = texels.checked_mul(format.bits_per_texel()).ok_or_else(|| too_big.clone())?.div_ceil(8);
+        let end = addr.checked_add(len).ok_or(too_big)?;
+        self.check_range(&(addr..end))?;
+        let mut bytes = vec![0u8; len as usize];
+        self.read_at(addr, &mut bytes)?;
+        let palette = match format {
+            TexelFormat::Ci4 { palette } => (0..16).map(|entry| self.palette_entry(palette, entry)).collect::<std::io::Result<Vec<_>>>()?,
+            TexelFormat::Ci8 { palette } => (0..=255).map(|entry| self.palette_entry(palette, entry)).collect::<std::io::Result<Vec<_>>>()?,
+            _ => Vec::new(),
+        };
+        let mut image = Vec::with_capacity(texels as usize * 4);
+        for index in 0..texels as usize {
+            let nibble = || if index % 2 == 0 { bytes[index / 2] >> 4 } else { bytes[index / 2] & 0xF };
+            let rgba = match format {
+                TexelFormat::Rgba5551 => decode_rgba5551(u16::from_be_bytes([bytes[index * 2], bytes[index * 2 + 1]])),
+                TexelFormat::Rgba8888 => bytes[index * 4..index * 4 + 4].try_into().expect("4 bytes"),
+                TexelFormat::Ia4 => decode_ia4(nibble()),
+                TexelFormat::Ia8 => decode_ia8(bytes[index]),
+                TexelFormat::Ia16 => [bytes[index * 2], bytes[index * 2], bytes[index * 2], bytes[index * 2 + 1]],
+                TexelFormat::I4 => [expand(nibble(), 4); 4],
+                TexelFormat::I8 => [bytes[index]; 4],
+                TexelFormat::Ci4 { .. } => palette[nibble() as usize],
+                TexelFormat::Ci8 { .. } => palette[bytes[index] as usize],
+            };
+            image.extend_from_slice(&rgba);
+        }
+        Ok(image)
+    }
+
+    /// [`ReversedWords::decode_image`] as an `image` crate image, ready to save as a screenshot.
+    #[cfg(feature = "image")]
+    pub fn decode_dynamic_image(&self, addr: u64, width: u32, height: u32, format: TexelFormat) -> std::io::Result<image::DynamicImage> {
+        let rgba = self.decode_image(addr, width, height, format)?;
+        Ok(image::RgbaImage::from_raw(width, height, rgba).expect("four bytes per pixel").into())
+    }
+
+    /// Encode `rgba` into texel `index` of the `format` texture at `addr`, leaving the other
+    /// nibble of a 4 bit texel's byte alone. Color indexed texels are set to the first palette
+    /// entry holding the color, failing with [`ReversedWordsError::ColorNotInPalette`] if there
+    /// isn't one.
+    pub fn write_texel(&mut self, addr: u64, index: u64, format: TexelFormat, rgba: [u8; 4]) -> std::io::Result<()> {
+        let (position, low) = Self::texel_position(addr, index, format);
+        let palette_index = |words: &Self, palette: u64, entries: u16| -> std::io::Result<u8> {
+            for entry in 0..entries {
+                if words.palette_entry(palette, entry as u8)? == decode_rgba5551(encode_rgba5551(rgba)) {
+                    return Ok(entry as u8);
+                }
+            }
+            Err(ReversedWordsError::ColorNotInPalette { rgba }.into())
+        };
+        let nibble = match format {
+            TexelFormat::Rgba5551 => return self.write_array(position, encode_rgba5551(rgba).to_be_bytes()),
+            TexelFormat::Rgba8888 => return self.write_array(position, rgba),
+            TexelFormat::Ia8 => return self.set_byte(position, encode_ia8(rgba)),
+            TexelFormat::Ia16 => return self.write_array(position, [intensity(rgba), rgba[3]]),
+            TexelFormat::I8 => return self.set_byte(position, intensity(rgba)),
+            TexelFormat::Ci8 { palette } => {
+                let entry = palette_index(self, palette, 256)?;
+                return self.set_byte(position, entry);
+            }
+            TexelFormat::Ia4 => encode_ia4(rgba),
+            TexelFormat::I4 => narrow(intensity(rgba), 4),
+            TexelFormat::Ci4 { palette } => palette_index(self, palette, 16)?,
+        };
+        let byte = self.get_byte(position)?;
+        self.set_byte(position, if low { byte & 0xF0 | nibble } else { byte & 0x0F | nibble << 4 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{texel::*, ReversedWords};
+
+    #[test]
+    fn texel_bit_layouts() {
+        assert_eq!([255, 0, 0, 255], decode_rgba5551(0xF801));
+        assert_eq!([0, 255, 0, 0], decode_rgba5551(0x07C0));
+        assert_eq!(0x07C1, encode_rgba5551([0, 255, 0, 200]));
+        for texel in [0x0000u16, 0xF801, 0x1234, 0xFFFF] {
+            assert_eq!(texel, encode_rgba5551(decode_rgba5551(texel)));
+        }
+        assert_eq!([0x88, 0x88, 0x88, 0xFF], decode_ia8(0x8F));
+        assert_eq!(0x8F, encode_ia8([0x88, 0x88, 0x88, 0xFF]));
+        assert_eq!([0xFF, 0xFF, 0xFF, 0], decode_ia4(0xE));
+        assert_eq!(0x9, encode_ia4([0x92, 0x92, 0x92, 255]));
+    }
+
+    #[test]
+    fn texels_through_a_view() {
+        let mut data = vec![0u8; 64];
+        let mut words = ReversedWords::new(&mut data);
+        let palette = 32;
+        words.write_texel(palette, 1, TexelFormat::Rgba5551, [255, 0, 0, 255]).unwrap();
+        words.write_texel(palette, 2, TexelFormat::Rgba5551, [0, 0, 255, 255]).unwrap();
+        let ci4 = TexelFormat::Ci4 { palette };
+        words.write_texel(0, 0, ci4, [0, 0, 255, 255]).unwrap();
+        words.write_texel(0, 1, ci4, [255, 0, 0, 255]).unwrap();
+        assert_eq!(0x21, words.get_byte(0).unwrap());
+        assert_eq!([0, 0, 255, 255], words.read_texel(0, 0, ci4).unwrap());
+        assert_eq!([255, 0, 0, 255], words.read_texel(0, 1, ci4).unwrap());
+        let error = words.write_texel(0, 2, ci4, [0, 255, 0, 255]).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::ColorNotInPalette { rgba: [0, 255, 0, 255] }), ReversedWordsError::from_io(&error));
+        assert_eq!([0, 0, 0, 0], words.read_texel(0, 2, TexelFormat::Ci8 { palette }).unwrap());
+
+        words.write_texel(8, 3, TexelFormat::Ia8, [0x88, 0x88, 0x88, 0xFF]).unwrap();
+        assert_eq!([0x88, 0x88, 0x88, 0xFF], words.read_texel(8, 3, TexelFormat::Ia8).unwrap());
+        assert_eq!([0x88; 4], words.read_texel(8, 6, TexelFormat::I4).unwrap());
+        words.write_texel(12, 0, TexelFormat::Rgba8888, [1, 2, 3, 4]).unwrap();
+        assert_eq!([1, 2, 3, 4], words.read_texel(12, 0, TexelFormat::Rgba8888).unwrap());
+        assert!(words.read_texel(60, 1, TexelFormat::Rgba8888).is_err());
+        // the palette entry is stored big endian, then word swapped
+        assert_eq!([0x01, 0xF8, 0x00, 0x00], data[32..36]);
+        assert_eq!(0x8F, data[8]);
+    }
+
+    #[test]
+    fn decodes_whole_images() {
+        let mut data = vec![0u8; 64];
+        let mut words = ReversedWords::new(&mut data);
+        // a 2x2 RGBA5551 framebuffer: red, green, blue, transparent
+        for (index, rgba) in [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [0, 0, 0, 0]].iter().enumerate() {
+            words.write_texel(16, index as u64, TexelFormat::Rgba5551, *rgba).unwrap();
+        }
+        let image = words.decode_image(16, 2, 2, TexelFormat::Rgba5551).unwrap();
+        assert_eq!(vec![255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 0, 0, 0, 0], image);
+        // the same texels as the palette of a 3x1 CI4 texture
+        words.set_byte(0, 0x21).unwrap();
+        words.set_byte(1, 0x30).unwrap();
+        let image = words.decode_image(0, 3, 1, TexelFormat::Ci4 { palette: 16 }).unwrap();
+        assert_eq!(vec![0, 0, 255, 255, 0, 255, 0, 255, 0, 0, 0, 0], image);
+        assert_eq!(vec![0x00; 4], words.decode_image(40, 1, 1, TexelFormat::I4).unwrap());
+        assert!(words.decode_image(48, 4, 3, TexelFormat::Rgba5551).is_err());
+        assert!(words.decode_image(0, u32::MAX, u32::MAX, TexelFormat::Rgba8888).is_err());
+
+        #[cfg(feature = "image")]
+        {
+            let image = words.decode_dynamic_image(16, 2, 2, TexelFormat::Rgba5551).unwrap().into_rgba8();
+            assert_eq!(image::Rgba([0, 0, 255, 255]), *image.get_pixel(0, 1));
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Recording every access made through a view, and replaying it.
+//!
+//! [`TracingWords`] wraps a [`ReversedWords`] and appends each read and write it makes (the
+//! operation, address and the bytes read or written) to an [`AccessTrace`]. A trace saved with
+//! [`AccessTrace::write_to`] can be replayed against a fresh buffer: [`AccessTrace::replay`]
+//! makes the writes again, and [`AccessTrace::replay_verified`] also checks every read still
+//! returns what was captured, which turns a recorded session into a regression test for a tool.
+//!
+//! The format is the magic `RWT1`, then for every record a byte `0` (read) or `1` (write), the
+//! address as a little endian `u64`, the length as a little endian `u32` and the bytes.
+
+use std::{
+    convert::{TryFrom, TryInto},
+    io::{Read, Write},
+    ops::{Deref, Range},
+};
+
+use binread::Endian;
+
+use crate::{Primitive, ReversedWords, ReversedWordsError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TraceOp {
+    Read,
+    Write,
+}
+
+/// One access, in logical bytes. For a read `data` is what was read, which may be shorter than
+/// requested at the end of the buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub op: TraceOp,
+    pub addr: u64,
+    pub data: Vec<u8>,
+}
+
+/// Accesses in the order they were made.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessTrace {
+    records: Vec<TraceRecord>,
+}
+
+const TRACE_MAGIC: &[u8; 4] = b"RWT1";
+
+fn invalid_trace(reason: &str) -> std::io::Error {
+    ReversedWordsError::InvalidTrace { reason: reason.to_string() }.into()
+}
+
+impl AccessTrace {
+    pub fn new() -> AccessTrace {
+        AccessTrace::default()
+    }
+
+    pub fn push(&mut self, record: TraceRecord) {
+        self.records.push(record);
+    }
+
+    pub fn records(&self) -> &[TraceRecord] {
+        &self.records
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(TRACE_MAGIC)?;
+        for record in &self.records {
+            let len = u32::try_from(record.data.len()).map_err(|_| invalid_trace("record longer than 4 GiB"))?;
+            writer.write_all(&[(record.op == TraceOp::Write) as u8])?;
+            writer.write_all(&record.addr.to_le_bytes())?;
+            writer.write_all(&len.to_le_bytes())?;
+            writer.write_all(&record.data)?;
+        }
+        Ok(())
+    }
+
+    /// Read a trace saved by [`AccessTrace::write_to`].
+    pub fn read_from<R: Read>(mut reader: R) -> std::io::Result<AccessTrace> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut rest = data.strip_prefix(TRACE_MAGIC).ok_or_else(|| invalid_trace("missing RWT1 magic"))?;
+        let mut trace = AccessTrace::new();
+        while !rest.is_empty() {
+            if rest.len() < 13 {
+                return Err(invalid_trace("truncated record header"));
+            }
+            let (header, tail) = rest.split_at(13);
+            let op = match header[0] {
+                0 => TraceOp::Read,
+                1 => TraceOp::Write,
+                _ => return Err(invalid_trace("unknown operation")),
+            };
+            let addr = u64::from_le_bytes(header[1..9].try_into().expect("8 bytes"));
+            let len = u32::from_le_bytes(header[9..].try_into().expect("4 bytes")) as usize;
+            if tail.len() < len {
+                return Err(invalid_trace("truncated record data"));
+            }
+            let (bytes, tail) = tail.split_at(len);
+            trace.push(TraceRecord { op, addr, data: bytes.to_vec() });
+            rest = tail;
+        }
+        Ok(trace)
+    }
+
+    fn replay_with(&self, words: &mut ReversedWords, verify: bool) -> std::io::Result<()> {
+        for (index, record) in self.records.iter().enumerate() {
+            match record.op {
+                TraceOp::Write => {
+                    if words.write_at(record.addr, &record.data)? < record.data.len() {
+                        let end = record.addr.saturating_add(record.data.len() as u64);
+                        return Err(ReversedWordsError::RangeOutOfRange { start: record.addr, end, len: words.len() }.into());
+                    }
+                }
+                TraceOp::Read if verify => {
+                    let mut current = vec![0u8; record.data.len()];
+                    let n = words.read_at(record.addr, &mut current)?;
+                    if current[..n] != record.data[..] {
+                        return Err(ReversedWordsError::TraceMismatch { index, addr: record.addr }.into());
+                    }
+                }
+                TraceOp::Read => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Make every traced write again, in order. Reads are skipped.
+    pub fn replay(&self, words: &mut ReversedWords) -> std::io::Result<()> {
+        self.replay_with(words, false)
+    }
+
+    /// Like [`AccessTrace::replay`], but also make every traced read and fail with
+    /// [`ReversedWordsError::TraceMismatch`] at the first one that returns different bytes.
+    pub fn replay_verified(&self, words: &mut ReversedWords) -> std::io::Result<()> {
+        self.replay_with(words, true)
+    }
+}
+
+/// A view that traces its accesses. Reads and writes go through the methods here; `Deref` gives
+/// the rest of the read-only API, untraced.
+pub struct TracingWords<'w, 'a> {
+    words: &'w mut ReversedWords<'a>,
+    trace: AccessTrace,
+}
+
+impl<'w, 'a> TracingWords<'w, 'a> {
+    pub fn new(words: &'w mut ReversedWords<'a>) -> TracingWords<'w, 'a> {
+        TracingWords { words, trace: AccessTrace::new() }
+    }
+
+    pub fn trace(&self) -> &AccessTrace {
+        &self.trace
+    }
+
+    pub fn into_trace(self) -> AccessTrace {
+        self.trace
+    }
+
+    /// [`ReversedWords::read_at`], traced.
+    pub fn read_at(&mut self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.words.read_at(addr, buf)?;
+        self.trace.push(TraceRecord { op: TraceOp::Read, addr, data: buf[..n].to_vec() });
+        Ok(n)
+    }
+
+    /// [`ReversedWords::read_value_at`], traced.
+    pub fn read_value_at<T: Primitive>(&mut self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let value = self.words.read_value_at::<T>(addr, endian)?;
+        let data = value.to_bytes(endian).as_ref().to_vec();
+        self.trace.push(TraceRecord { op: TraceOp::Read, addr, data });
+        Ok(value)
+    }
+
+    // run `write` and trace the bytes it left at `addr`, if it made them
+    fn traced<T>(&mut self, addr: u64, len: usize, write: impl FnOnce(&mut ReversedWords<'a>) -> std::io::Result<T>) -> std::io::Result<T> {
+        let result = write(self.words)?;
+        let mut data = vec![0u8; len];
+        let n = self.words.read_at(addr, &mut data)?;
+        data.truncate(n);
+        self.trace.push(TraceRecord { op: TraceOp::Write, addr, data });
+        Ok(result)
+    }
+
+    /// [`ReversedWords::write_at`], traced.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<usize> {
+        self.traced(addr, buf.len(), |words| words.write_at(addr, buf))
+    }
+
+    /// [`ReversedWords::write_value_at`], traced.
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        self.traced(addr, std::mem::size_of::<T>(), |words| words.write_value_at(addr, value, endian))
+    }
+
+    pub fn fill(&mut self, range: Range<u64>, byte: u8) -> std::io::Result<()> {
+        let len = range.end.saturating_sub(range.start) as usize;
+        self.traced(range.start, len, |words| words.fill(range, byte))
+    }
+}
+
+impl<'a> Deref for TracingWords<'_, 'a> {
+    type Target = ReversedWords<'a>;
+
+    fn deref(&self) -> &ReversedWords<'a> {
+        self.words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{trace::*, ReversedWords};
+
+    #[test]
+    fn traced_session_replays_and_verifies() {
+        let mut data = vec![0u8; 8];
+        let mut words = ReversedWords::new(&mut data);
+        let mut tracing = TracingWords::new(&mut words);
+        tracing.write_value_at(0, 0x1234u16, Endian::Big).unwrap();
+        assert_eq!(0x12, tracing.read_value_at::<u8>(0, Endian::Big).unwrap());
+        tracing.fill(4..8, 0xAA).unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(2, tracing.read_at(6, &mut buf).unwrap());
+        let trace = tracing.into_trace();
+        assert_eq!(
+            vec![TraceOp::Write, TraceOp::Read, TraceOp::Write, TraceOp::Read],
+            trace.records().iter().map(|record| record.op).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![0xAA, 0xAA], trace.records()[3].data);
+
+        let mut saved = Vec::new();
+        trace.write_to(&mut saved).unwrap();
+        let loaded = AccessTrace::read_from(&saved[..]).unwrap();
+        assert_eq!(trace, loaded);
+        assert!(AccessTrace::read_from(&saved[..saved.len() - 1]).is_err());
+
+        let mut fresh = vec![0u8; 8];
+        loaded.replay_verified(&mut ReversedWords::new(&mut fresh)).unwrap();
+        assert_eq!(data, fresh);
+
+        // a tool that now writes something different fails the verified replay at the read
+        let mut changed = trace.clone();
+        changed.records[0].data = vec![0x99, 0x34];
+        let error = changed.replay_verified(&mut ReversedWords::new(&mut [0u8; 8])).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::TraceMismatch { index: 1, addr: 0 }), ReversedWordsError::from_io(&error));
+        changed.replay(&mut ReversedWords::new(&mut [0u8; 8])).unwrap();
+
+        let mut short = AccessTrace::new();
+        short.push(TraceRecord { op: TraceOp::Write, addr: u64::MAX, data: vec![1, 2] });
+        assert!(short.replay(&mut ReversedWords::new(&mut [0u8; 8])).is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! Stacked transforms for dumps with more than one layer of scrambling.
+//!
+//! A [`Pipeline`] lists [`Transform`]s in the order they are undone when reading: the first one
+//! is applied to the stored bytes, the next to its output and so on, and the last one's output is
+//! the logical data. Writes run the pipeline backwards. [`TransformedWords`] is a view through a
+//! pipeline, e.g. a dump that was 16-bit swapped and then 32-bit swapped:
+//!
+//! ```
+//! # use reversed_word_byte_rw::{transform::{Pipeline, Transform, TransformedWords}, Endian};
+//! let mut data = vec![0x3, 0x4, 0x1, 0x2];
+//! let pipeline = Pipeline::new().then(Transform::SwapWords(4)).then(Transform::SwapWords(2));
+//! let mut words = TransformedWords::new(&mut data, pipeline).unwrap();
+//! assert_eq!(0x0102_0304, words.read_u32_at(0, Endian::Big).unwrap());
+//! ```
+//!
+//! Besides swaps, bytes can be XORed with a repeating key or put through any reversible
+//! [`ByteTransform`], before or after the swap depending on their place in the pipeline, and
+//! [`Transform::SkipSpare`] drops the spare (OOB) area that raw NAND dumps interleave with the
+//! data pages, so a dump read as 512 data bytes then 16 spare bytes, 16-bit swapped, is:
+//!
+//! ```
+//! # use reversed_word_byte_rw::transform::{Pipeline, Transform};
+//! let pipeline = Pipeline::new().then(Transform::SkipSpare { data: 512, spare: 16 }).then(Transform::SwapWords(2));
+//! assert_eq!(Some(528), pipeline.storage_index(513, 1056));
+//! assert_eq!(1024, pipeline.logical_len(1056));
+//! ```
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use binread::Endian;
+
+use crate::{swap_index, Primitive, ReversedWordsError};
+
+/// A caller provided byte transform and its inverse. Both get the byte's position at their
+/// place in the pipeline.
+#[derive(Clone, Copy, Debug)]
+pub struct ByteTransform {
+    pub decode: fn(u8, u64) -> u8,
+    pub encode: fn(u8, u64) -> u8,
+}
+
+/// Compares the function addresses, which can differ between codegen units for the same function.
+impl PartialEq for ByteTransform {
+    fn eq(&self, other: &ByteTransform) -> bool {
+        std::ptr::fn_addr_eq(self.decode, other.decode) && std::ptr::fn_addr_eq(self.encode, other.encode)
+    }
+}
+
+impl Eq for ByteTransform {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transform {
+    /// Reverse the bytes of every word of this many bytes.
+    SwapWords(u8),
+    /// Swap the high and low nibble of every byte.
+    SwapNibbles,
+    /// XOR the byte at position `i` with `key[i % key.len()]`. An empty key does nothing.
+    Xor(Vec<u8>),
+    Custom(ByteTransform),
+    /// Keep `data` bytes, then skip `spare` bytes, and so on. `data` must not be 0.
+    SkipSpare { data: u64, spare: u64 },
+}
+
+impl Transform {
+    // where the byte at `position` of this transform's output comes from in its input
+    fn source(&self, position: u64) -> u64 {
+        match self {
+            Transform::SwapWords(word_size) => swap_index(position, *word_size),
+            Transform::SkipSpare { data, spare } => position / data * (data + spare) + position % data,
+            _ => position,
+        }
+    }
+
+    // how many bytes this transform outputs from `len` input bytes
+    fn output_len(&self, len: u64) -> u64 {
+        match self {
+            Transform::SkipSpare { data, spare } => len / (data + spare) * data + (len % (data + spare)).min(*data),
+            _ => len,
+        }
+    }
+
+    // the output byte for input `byte` at `position`
+    fn decode(&self, byte: u8, position: u64) -> u8 {
+        match self {
+            Transform::SwapWords(_) | Transform::SkipSpare { .. } => byte,
+            Transform::SwapNibbles => byte.rotate_left(4),
+            Transform::Xor(key) if key.is_empty() => byte,
+            Transform::Xor(key) => byte ^ key[(position % key.len() as u64) as usize],
+            Transform::Custom(transform) => (transform.decode)(byte, position),
+        }
+    }
+
+    fn encode(&self, byte: u8, position: u64) -> u8 {
+        match self {
+            Transform::Custom(transform) => (transform.encode)(byte, position),
+            // the rest are their own inverse
+            _ => self.decode(byte, position),
+        }
+    }
+}
+
+/// Transforms from the stored bytes to the logical bytes, see the [module docs](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    transforms: Vec<Transform>,
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Add a transform applied after the ones already in the pipeline when reading.
+    pub fn then(mut self, transform: Transform) -> Pipeline {
+        self.transforms.push(transform);
+        self
+    }
+
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+
+    fn validate(&self) -> Result<(), ReversedWordsError> {
+        if self.transforms.contains(&Transform::SwapWords(0)) {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        for transform in &self.transforms {
+            if let Transform::SkipSpare { data, spare } = transform {
+                if *data == 0 || data.checked_add(*spare).is_none() {
+                    return Err(ReversedWordsError::InvalidTransform { reason: format!("can't keep {} bytes and skip {}", data, spare) });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// How many logical bytes there are in `len` stored bytes.
+    pub fn logical_len(&self, len: u64) -> u64 {
+        self.transforms.iter().fold(len, |len, transform| transform.output_len(len))
+    }
+
+    // positions at the input of every transform, first the storage index, if all are in range
+    fn positions(&self, position: u64, len: u64) -> Option<Vec<u64>> {
+        // the length of every stage's input, and of the logical bytes last
+        let mut lens = vec![len; self.transforms.len() + 1];
+        for (i, transform) in self.transforms.iter().enumerate() {
+            lens[i + 1] = transform.output_len(lens[i]);
+        }
+        if position >= lens[self.transforms.len()] {
+            return None;
+        }
+        let mut positions = vec![position; self.transforms.len() + 1];
+        for (i, transform) in self.transforms.iter().enumerate().rev() {
+            let source = transform.source(positions[i + 1]);
+            if source >= lens[i] {
+                return None;
+            }
+            positions[i] = source;
+        }
+        Some(positions)
+    }
+
+    /// Index into storage of logical `position`, if it is in range.
+    pub fn storage_index(&self, position: u64, len: u64) -> Option<usize> {
+        self.positions(position, len).map(|positions| positions[0] as usize)
+    }
+
+    fn read(&self, storage: &[u8], position: u64) -> Option<u8> {
+        let positions = self.positions(position, storage.len() as u64)?;
+        let stages = self.transforms.iter().zip(&positions);
+        Some(stages.fold(storage[positions[0] as usize], |byte, (transform, position)| transform.decode(byte, *position)))
+    }
+
+    fn write(&self, storage: &mut [u8], position: u64, byte: u8) -> bool {
+        let Some(positions) = self.positions(position, storage.len() as u64) else { return false };
+        let stages = self.transforms.iter().zip(&positions).rev();
+        storage[positions[0] as usize] = stages.fold(byte, |byte, (transform, position)| transform.encode(byte, *position));
+        true
+    }
+}
+
+/// `Read + Write + Seek` and typed accessors over a buffer through a [`Pipeline`].
+pub struct TransformedWords<'a> {
+    data: &'a mut [u8],
+    pipeline: Pipeline,
+    position: u64,
+}
+
+macro_rules! transformed_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            pub fn $read(&mut self, addr: u64, endian: Endian) -> std::io::Result<$ty> {
+                self.read_value_at(addr, endian)
+            }
+
+            pub fn $write(&mut self, addr: u64, value: $ty, endian: Endian) -> std::io::Result<()> {
+                self.write_value_at(addr, value, endian)
+            }
+        )*
+    };
+}
+
+impl<'a> TransformedWords<'a> {
+    pub fn new(data: &'a mut [u8], pipeline: Pipeline) -> Result<TransformedWords<'a>, ReversedWordsError> {
+        pipeline.validate()?;
+        Ok(TransformedWords { data, pipeline, position: 0 })
+    }
+
+    pub fn pipeline(&self) -> &Pipeline {
+        &self.pipeline
+    }
+
+    /// The length of the stored data.
+    pub fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// How many logical bytes there are, less than [`TransformedWords::len`] if the pipeline
+    /// skips spare areas.
+    pub fn logical_len(&self) -> u64 {
+        self.pipeline.logical_len(self.len())
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn into_inner(self) -> &'a mut [u8] {
+        self.data
+    }
+
+    /// Read the logical bytes starting at `addr`, returning how many were in range.
+    pub fn read_at(&self, addr: u64, buf: &mut [u8]) -> usize {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            match self.pipeline.read(self.data, addr + i as u64) {
+                Some(value) => *byte = value,
+                None => return i,
+            }
+        }
+        buf.len()
+    }
+
+    /// Write logical bytes starting at `addr`, returning how many were in range.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> usize {
+        for (i, byte) in buf.iter().enumerate() {
+            if !self.pipeline.write(self.data, addr + i as u64, *byte) {
+                return i;
+            }
+        }
+        buf.len()
+    }
+
+    pub fn read_value_at<T: Primitive>(&mut self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        if self.read_at(addr, bytes.as_mut()) < bytes.as_ref().len() {
+            return Err(ReversedWordsError::AddressOutOfRange { addr, len: self.len() }.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        let bytes = value.to_bytes(endian);
+        let end = addr.saturating_add(bytes.as_ref().len() as u64);
+        if (addr..end).any(|position| self.pipeline.storage_index(position, self.len()).is_none()) {
+            return Err(ReversedWordsError::RangeOutOfRange { start: addr, end, len: self.len() }.into());
+        }
+        self.write_at(addr, bytes.as_ref());
+        Ok(())
+    }
+
+    transformed_accessors! {
+        u8 => read_u8_at, write_u8_at;
+        u16 => read_u16_at, write_u16_at;
+        u32 => read_u32_at, write_u32_at;
+        u64 => read_u64_at, write_u64_at;
+        i8 => read_i8_at, write_i8_at;
+        i16 => read_i16_at, write_i16_at;
+        i32 => read_i32_at, write_i32_at;
+        i64 => read_i64_at, write_i64_at;
+        f32 => read_f32_at, write_f32_at;
+        f64 => read_f64_at, write_f64_at;
+    }
+}
+
+impl Read for TransformedWords<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.read_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for TransformedWords<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.write_at(self.position, buf);
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for TransformedWords<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.logical_len() as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.position as i128 + offset as i128,
+        };
+        if target < 0 || target > u64::MAX as i128 {
+            return Err(ReversedWordsError::SeekOutOfRange { target, len: self.logical_len() }.into());
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{transform::*, ReversedWords};
+
+    #[test]
+    fn single_swap_matches_reversed_words() {
+        let mut expected: Vec<u8> = (0..8).collect();
+        let mut data = expected.clone();
+        ReversedWords::new(&mut expected).with_nibble_swap(true).write_u32_at(2, 0x1234_5678, Endian::Big).unwrap();
+        let pipeline = Pipeline::new().then(Transform::SwapWords(4)).then(Transform::SwapNibbles);
+        let mut words = TransformedWords::new(&mut data, pipeline).unwrap();
+        words.write_u32_at(2, 0x1234_5678, Endian::Big).unwrap();
+        assert_eq!(0x1234_5678, words.read_u32_at(2, Endian::Big).unwrap());
+        assert_eq!(expected, data);
+    }
+
+    #[test]
+    fn xor_and_custom_layers() {
+        fn add_decode(byte: u8, position: u64) -> u8 {
+            byte.wrapping_sub(position as u8)
+        }
+        fn add_encode(byte: u8, position: u64) -> u8 {
+            byte.wrapping_add(position as u8)
+        }
+        // the key applies to storage positions, before the swap
+        let mut data = vec![3 ^ 0xAA, 2 ^ 0x55, 1 ^ 0xAA, 0x55];
+        let pipeline = Pipeline::new().then(Transform::Xor(vec![0xAA, 0x55])).then(Transform::SwapWords(4));
+        let mut words = TransformedWords::new(&mut data, pipeline).unwrap();
+        assert_eq!(0x0001_0203, words.read_u32_at(0, Endian::Big).unwrap());
+
+        let custom = Transform::Custom(ByteTransform { decode: add_decode, encode: add_encode });
+        let mut data = vec![0u8; 4];
+        let mut words = TransformedWords::new(&mut data, Pipeline::new().then(Transform::SwapWords(2)).then(custom)).unwrap();
+        words.write_at(0, &[10, 10, 10, 10]);
+        let mut out = [0u8; 4];
+        words.read_at(0, &mut out);
+        assert_eq!([10; 4], out);
+        assert_eq!(vec![11, 10, 13, 12], data);
+    }
+
+    #[test]
+    fn stacked_swaps() {
+        // logical 0..8, swapped by 2 and then by 4 when it was dumped
+        let mut data = vec![2, 3, 0, 1, 6, 7, 4, 5];
+        let pipeline = Pipeline::new().then(Transform::SwapWords(4)).then(Transform::SwapWords(2));
+        let mut words = TransformedWords::new(&mut data, pipeline).unwrap();
+        let mut out = [0u8; 8];
+        words.read_exact(&mut out).unwrap();
+        assert_eq!([0, 1, 2, 3, 4, 5, 6, 7], out);
+        assert!(words.write_u16_at(7, 0, Endian::Big).is_err());
+        assert!(TransformedWords::new(&mut [], Pipeline::new().then(Transform::SwapWords(0))).is_err());
+    }
+
+    #[test]
+    fn skips_nand_spare_areas() {
+        // pages of 8 data bytes, 16-bit swapped, each followed by 2 bytes of ECC
+        let mut data = vec![1, 0, 3, 2, 5, 4, 7, 6, 0xEC, 0xC0, 9, 8, 11, 10, 13, 12, 15, 14, 0xEC, 0xC1];
+        let pipeline = Pipeline::new().then(Transform::SkipSpare { data: 8, spare: 2 }).then(Transform::SwapWords(2));
+        let mut words = TransformedWords::new(&mut data, pipeline).unwrap();
+        assert_eq!(16, words.logical_len());
+        let mut out = Vec::new();
+        words.read_to_end(&mut out).unwrap();
+        assert_eq!((0..16).collect::<Vec<u8>>(), out);
+        words.write_u32_at(6, 0xA0A1_A2A3, Endian::Big).unwrap();
+        assert!(words.write_u16_at(15, 0, Endian::Big).is_err());
+        words.seek(SeekFrom::End(-1)).unwrap();
+        assert_eq!(15, words.position());
+        assert_eq!([1, 0, 3, 2, 5, 4, 0xA1, 0xA0, 0xEC, 0xC0, 0xA3, 0xA2, 11, 10, 13, 12, 15, 14, 0xEC, 0xC1], words.into_inner()[..]);
+
+        let mut data = [0u8; 4];
+        assert!(TransformedWords::new(&mut data, Pipeline::new().then(Transform::SkipSpare { data: 0, spare: 4 })).is_err());
+    }
+}
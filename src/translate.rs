@@ -0,0 +1,87 @@
+//! Translating target virtual addresses to offsets into a view.
+//!
+//! Code, symbol files and debuggers talk in the target's addresses (`0x80371240` on an N64), while
+//! a view is indexed from 0. An [`AddressMap`] is a list of segments that each map a virtual
+//! range onto a range of offsets.
+
+use std::ops::Range;
+
+use crate::ReversedWordsError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub virtual_range: Range<u64>,
+    /// Offset into the view that `virtual_range.start` maps to.
+    pub offset: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AddressMap {
+    segments: Vec<Segment>,
+}
+
+impl AddressMap {
+    /// A map with no segments, every address is unmapped until segments are added.
+    pub fn new() -> AddressMap {
+        AddressMap::default()
+    }
+
+    /// Virtual addresses are already view offsets.
+    pub fn identity() -> AddressMap {
+        AddressMap::new().with_segment(0..u64::MAX, 0)
+    }
+
+    /// N64 RDRAM, directly mapped through both KSEG0 (cached) and KSEG1 (uncached).
+    pub fn n64(rdram_size: u64) -> AddressMap {
+        AddressMap::new()
+            .with_segment(0x8000_0000..0x8000_0000 + rdram_size, 0)
+            .with_segment(0xA000_0000..0xA000_0000 + rdram_size, 0)
+    }
+
+    pub fn with_segment(mut self, virtual_range: Range<u64>, offset: u64) -> AddressMap {
+        self.add_segment(virtual_range, offset);
+        self
+    }
+
+    /// Add a segment. Earlier segments take priority where they overlap.
+    pub fn add_segment(&mut self, virtual_range: Range<u64>, offset: u64) {
+        self.segments.push(Segment { virtual_range, offset });
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The view offset of virtual address `addr`.
+    pub fn translate(&self, addr: u64) -> Result<u64, ReversedWordsError> {
+        self.segments
+            .iter()
+            .find(|segment| segment.virtual_range.contains(&addr))
+            .map(|segment| addr - segment.virtual_range.start + segment.offset)
+            .ok_or(ReversedWordsError::UnmappedAddress { addr })
+    }
+
+    /// The virtual address of view offset `offset`, through the first segment that maps it.
+    pub fn virtual_address(&self, offset: u64) -> Option<u64> {
+        self.segments.iter().find_map(|segment| {
+            let within = offset.checked_sub(segment.offset)?;
+            (within < segment.virtual_range.end - segment.virtual_range.start).then(|| segment.virtual_range.start + within)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::translate::*;
+
+    #[test]
+    fn n64_segments_alias_rdram() {
+        let map = AddressMap::n64(0x80_0000);
+        assert_eq!(Ok(0x37_1240), map.translate(0x8037_1240));
+        assert_eq!(Ok(0x37_1240), map.translate(0xA037_1240));
+        assert_eq!(Err(ReversedWordsError::UnmappedAddress { addr: 0x8080_0000 }), map.translate(0x8080_0000));
+        assert_eq!(Ok(0x1234), AddressMap::identity().translate(0x1234));
+        assert_eq!(Some(0x8037_1240), map.virtual_address(0x37_1240));
+        assert_eq!(None, map.virtual_address(0x80_0000));
+    }
+}
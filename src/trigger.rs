@@ -0,0 +1,398 @@
+//! Conditions over memory that run actions when they become true.
+//!
+//! A [`Triggers`] set holds conditions on the value at an address (it equals something, crosses a
+//! threshold, has a bitmask set), each with the actions to run when it fires: write bytes,
+//! publish an [`Event::Trigger`] to a bus, or call back into the application. Conditions are
+//! evaluated against a view on [`Triggers::poll`], or after every write made through
+//! [`Triggers::writes`], so simple automation like "when the loading flag is set, apply this
+//! patch" needs no polling loop of its own:
+//!
+//! ```
+//! # use reversed_word_byte_rw::{Endian, ReversedWords, expr::{Value, ValueType}, trigger::{Action, Condition, Triggers}};
+//! let mut ram = vec![0u8; 0x20];
+//! let mut words = ReversedWords::new(&mut ram);
+//! let mut triggers = Triggers::new();
+//! let loading = Condition::mask_set(0x10, ValueType::U8, Endian::Big, 0x80);
+//! triggers.add(loading, vec![Action::write_value(0x04, 99u32, Endian::Big)]);
+//!
+//! triggers.writes(&mut words).write_value_at(0x10, 0x81u8, Endian::Big).unwrap();
+//! assert_eq!(99, words.read_u32_at(0x04, Endian::Big).unwrap());
+//! ```
+//!
+//! Equality and bitmask conditions fire when they become true, not on every evaluation while they
+//! stay true; a crossing fires when the value moves past its threshold between two evaluations.
+//! Writes made by actions don't evaluate triggers themselves, so triggers can't set each other off
+//! in a loop.
+
+use std::{cmp::Ordering, ops::Deref, ops::Range};
+
+use binread::Endian;
+
+use crate::{
+    events::{Event, EventBus},
+    expr::{Value, ValueType},
+    watch::{decode, decode_value},
+    Primitive, ReversedWords, ReversedWordsError,
+};
+
+/// Which way a value has to move past a threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// From below the threshold to at or above it.
+    Rising,
+    /// From at or above the threshold to below it.
+    Falling,
+    Either,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Test {
+    Equals(Value),
+    Crosses { threshold: Value, direction: Direction },
+    /// Every bit of the mask is set in the value's bits.
+    MaskSet(u64),
+}
+
+/// A test of the value at an address.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Condition {
+    pub addr: u64,
+    pub value_type: ValueType,
+    pub endian: Endian,
+    pub test: Test,
+}
+
+impl Condition {
+    pub fn equals(addr: u64, value_type: ValueType, endian: Endian, value: Value) -> Condition {
+        Condition { addr, value_type, endian, test: Test::Equals(value) }
+    }
+
+    pub fn crosses(addr: u64, value_type: ValueType, endian: Endian, threshold: Value, direction: Direction) -> Condition {
+        Condition { addr, value_type, endian, test: Test::Crosses { threshold, direction } }
+    }
+
+    pub fn mask_set(addr: u64, value_type: ValueType, endian: Endian, mask: u64) -> Condition {
+        Condition { addr, value_type, endian, test: Test::MaskSet(mask) }
+    }
+
+    /// The logical bytes the condition reads.
+    pub fn range(&self) -> Range<u64> {
+        self.addr..self.addr.saturating_add(self.value_type.size())
+    }
+}
+
+fn compare(a: Value, b: Value) -> Option<Ordering> {
+    let float = |value| match value {
+        Value::Unsigned(value) => value as f64,
+        Value::Signed(value) => value as f64,
+        Value::Float(value) => value,
+    };
+    match (a, b) {
+        (Value::Unsigned(a), Value::Unsigned(b)) => Some(a.cmp(&b)),
+        (Value::Signed(a), Value::Signed(b)) => Some(a.cmp(&b)),
+        (Value::Unsigned(a), Value::Signed(b)) => Some((a as i128).cmp(&(b as i128))),
+        (Value::Signed(a), Value::Unsigned(b)) => Some((a as i128).cmp(&(b as i128))),
+        _ => float(a).partial_cmp(&float(b)),
+    }
+}
+
+fn bits(bytes: &[u8], endian: Endian) -> u64 {
+    match bytes.len() {
+        1 => bytes[0] as u64,
+        2 => decode::<u16>(bytes, endian).expect("2 bytes") as u64,
+        4 => decode::<u32>(bytes, endian).expect("4 bytes") as u64,
+        _ => decode::<u64>(bytes, endian).expect("8 bytes"),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TriggerId(u64);
+
+/// A trigger firing: the value that set it off.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fired {
+    pub id: TriggerId,
+    /// Address of the condition.
+    pub addr: u64,
+    pub bytes: Vec<u8>,
+}
+
+impl Fired {
+    /// The bytes decoded as a `T`, if there are enough of them.
+    pub fn value<T: Primitive>(&self, endian: Endian) -> Option<T> {
+        decode(&self.bytes, endian)
+    }
+}
+
+type Callback = Box<dyn FnMut(&Fired, &mut ReversedWords) -> std::io::Result<()> + Send>;
+
+/// What a trigger does when it fires.
+pub enum Action {
+    /// Write logical bytes.
+    Write { addr: u64, bytes: Vec<u8> },
+    /// Publish an [`Event::Trigger`].
+    Publish(EventBus),
+    Callback(Callback),
+}
+
+impl Action {
+    pub fn write_value<T: Primitive>(addr: u64, value: T, endian: Endian) -> Action {
+        Action::Write { addr, bytes: value.to_bytes(endian).as_ref().to_vec() }
+    }
+
+    pub fn callback(callback: impl FnMut(&Fired, &mut ReversedWords) -> std::io::Result<()> + Send + 'static) -> Action {
+        Action::Callback(Box::new(callback))
+    }
+
+    fn run(&mut self, fired: &Fired, words: &mut ReversedWords) -> std::io::Result<()> {
+        match self {
+            Action::Write { addr, bytes } => {
+                if words.write_at(*addr, bytes)? < bytes.len() {
+                    return Err(words.out_of_range(*addr + bytes.len() as u64 - 1));
+                }
+            }
+            Action::Publish(bus) => {
+                bus.publish(Event::Trigger(fired.clone()));
+            }
+            Action::Callback(callback) => callback(fired, words)?,
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Write { addr, bytes } => f.debug_struct("Write").field("addr", addr).field("bytes", bytes).finish(),
+            Action::Publish(bus) => f.debug_tuple("Publish").field(bus).finish(),
+            Action::Callback(_) => f.write_str("Callback"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Trigger {
+    id: TriggerId,
+    condition: Condition,
+    actions: Vec<Action>,
+    previous: Option<Vec<u8>>,
+    active: bool,
+}
+
+impl Trigger {
+    // evaluate the condition, running the actions if it fires
+    fn evaluate(&mut self, words: &mut ReversedWords) -> std::io::Result<Option<Fired>> {
+        let condition = self.condition;
+        let mut bytes = vec![0u8; condition.value_type.size() as usize];
+        if words.read_at(condition.addr, &mut bytes)? < bytes.len() {
+            let range = condition.range();
+            return Err(ReversedWordsError::RangeOutOfRange { start: range.start, end: range.end, len: words.len() }.into());
+        }
+        let value = decode_value(&bytes, condition.value_type, condition.endian).expect("read the value's size");
+        let fires = match condition.test {
+            Test::Equals(expected) => self.edge(compare(value, expected) == Some(Ordering::Equal)),
+            Test::MaskSet(mask) => self.edge(bits(&bytes, condition.endian) & mask == mask),
+            Test::Crosses { threshold, direction } => match &self.previous {
+                Some(previous) => {
+                    let previous = decode_value(previous, condition.value_type, condition.endian).expect("same size");
+                    let below = |value| compare(value, threshold) == Some(Ordering::Less);
+                    let rising = below(previous) && !below(value);
+                    let falling = !below(previous) && below(value);
+                    match direction {
+                        Direction::Rising => rising,
+                        Direction::Falling => falling,
+                        Direction::Either => rising || falling,
+                    }
+                }
+                None => false,
+            },
+        };
+        self.previous = Some(bytes.clone());
+        if !fires {
+            return Ok(None);
+        }
+        let fired = Fired { id: self.id, addr: condition.addr, bytes };
+        for action in &mut self.actions {
+            action.run(&fired, words)?;
+        }
+        Ok(Some(fired))
+    }
+
+    fn edge(&mut self, now: bool) -> bool {
+        let fires = now && !self.active;
+        self.active = now;
+        fires
+    }
+}
+
+/// A set of conditions and their actions.
+#[derive(Debug, Default)]
+pub struct Triggers {
+    triggers: Vec<Trigger>,
+    next_id: u64,
+}
+
+impl Triggers {
+    pub fn new() -> Triggers {
+        Triggers::default()
+    }
+
+    /// Run `actions`, in order, whenever `condition` fires.
+    pub fn add(&mut self, condition: Condition, actions: Vec<Action>) -> TriggerId {
+        let id = TriggerId(self.next_id);
+        self.next_id += 1;
+        self.triggers.push(Trigger { id, condition, actions, previous: None, active: false });
+        id
+    }
+
+    /// Remove `id`, returning whether it was registered.
+    pub fn remove(&mut self, id: TriggerId) -> bool {
+        let count = self.triggers.len();
+        self.triggers.retain(|trigger| trigger.id != id);
+        self.triggers.len() != count
+    }
+
+    pub fn len(&self) -> usize {
+        self.triggers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+
+    /// Evaluate every condition in the order they were added, running the actions of those that
+    /// fire. Returns what fired.
+    pub fn poll(&mut self, words: &mut ReversedWords) -> std::io::Result<Vec<Fired>> {
+        self.evaluate(words, None)
+    }
+
+    fn evaluate(&mut self, words: &mut ReversedWords, written: Option<Range<u64>>) -> std::io::Result<Vec<Fired>> {
+        let mut fired = Vec::new();
+        for trigger in &mut self.triggers {
+            if let Some(written) = &written {
+                let range = trigger.condition.range();
+                if range.start >= written.end || written.start >= range.end {
+                    continue;
+                }
+            }
+            fired.extend(trigger.evaluate(words)?);
+        }
+        Ok(fired)
+    }
+
+    /// Write to `words` through these triggers, evaluating the conditions each write touches.
+    pub fn writes<'t, 'w, 'a>(&'t mut self, words: &'w mut ReversedWords<'a>) -> TriggeredWords<'t, 'w, 'a> {
+        TriggeredWords { triggers: self, words }
+    }
+}
+
+/// A view whose writes evaluate triggers. Reads go through `Deref`.
+pub struct TriggeredWords<'t, 'w, 'a> {
+    triggers: &'t mut Triggers,
+    words: &'w mut ReversedWords<'a>,
+}
+
+impl TriggeredWords<'_, '_, '_> {
+    /// [`ReversedWords::write_at`], then evaluate the triggers on the bytes written, returning
+    /// how many were written and what fired.
+    pub fn write_at(&mut self, addr: u64, buf: &[u8]) -> std::io::Result<(usize, Vec<Fired>)> {
+        let n = self.words.write_at(addr, buf)?;
+        let fired = self.triggers.evaluate(self.words, Some(addr..addr + n as u64))?;
+        Ok((n, fired))
+    }
+
+    /// [`ReversedWords::write_value_at`], then evaluate the triggers on the bytes written.
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<Vec<Fired>> {
+        self.words.write_value_at(addr, value, endian)?;
+        let size = std::mem::size_of::<T>() as u64;
+        self.triggers.evaluate(self.words, Some(addr..addr + size))
+    }
+}
+
+impl<'a> Deref for TriggeredWords<'_, '_, 'a> {
+    type Target = ReversedWords<'a>;
+
+    fn deref(&self) -> &ReversedWords<'a> {
+        self.words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::trigger::*;
+
+    #[test]
+    fn conditions_fire_on_edges() {
+        let mut data = vec![0u8; 0x10];
+        let mut words = ReversedWords::new(&mut data);
+        let mut triggers = Triggers::new();
+        let level = triggers.add(Condition::equals(0, ValueType::U16, Endian::Big, Value::Unsigned(3)), vec![]);
+        let low = triggers.add(
+            Condition::crosses(4, ValueType::I32, Endian::Big, Value::Signed(10), Direction::Falling),
+            vec![Action::write_value(8, 0xAAu8, Endian::Big)],
+        );
+        assert!(triggers.poll(&mut words).unwrap().is_empty());
+
+        words.write_u16_at(0, 3, Endian::Big).unwrap();
+        words.write_i32_at(4, 50, Endian::Big).unwrap();
+        let fired = triggers.poll(&mut words).unwrap();
+        assert_eq!(vec![level], fired.iter().map(|fired| fired.id).collect::<Vec<_>>());
+        assert_eq!(Some(3u16), fired[0].value(Endian::Big));
+        // still equal, and above the threshold: nothing new
+        assert!(triggers.poll(&mut words).unwrap().is_empty());
+
+        words.write_i32_at(4, -1, Endian::Big).unwrap();
+        let fired = triggers.poll(&mut words).unwrap();
+        assert_eq!(vec![low], fired.iter().map(|fired| fired.id).collect::<Vec<_>>());
+        assert_eq!(0xAA, words.read_u8_at(8, Endian::Big).unwrap());
+
+        // leaving and re-entering the value fires again
+        words.write_u16_at(0, 4, Endian::Big).unwrap();
+        triggers.poll(&mut words).unwrap();
+        words.write_u16_at(0, 3, Endian::Big).unwrap();
+        assert_eq!(1, triggers.poll(&mut words).unwrap().len());
+
+        assert!(triggers.remove(level));
+        assert!(!triggers.remove(level));
+        triggers.add(Condition::equals(0xF, ValueType::U16, Endian::Big, Value::Unsigned(0)), vec![]);
+        assert!(triggers.poll(&mut words).is_err());
+    }
+
+    #[test]
+    fn writes_evaluate_overlapping_triggers() {
+        let bus = EventBus::new();
+        let events = bus.subscribe();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut data = vec![0u8; 0x10];
+        let mut words = ReversedWords::new(&mut data);
+        let mut triggers = Triggers::new();
+        let callback_seen = seen.clone();
+        triggers.add(
+            Condition::mask_set(2, ValueType::U16, Endian::Big, 0x0101),
+            vec![
+                Action::Publish(bus),
+                Action::callback(move |fired, words| {
+                    callback_seen.lock().unwrap().push(fired.addr);
+                    words.write_u8_at(0xC, 1, Endian::Big)
+                }),
+            ],
+        );
+        let mut unrelated = Triggers::new();
+        unrelated.add(Condition::equals(8, ValueType::U8, Endian::Big, Value::Unsigned(0)), vec![]);
+
+        let mut writer = triggers.writes(&mut words);
+        assert!(writer.write_value_at(2, 0x0100u16, Endian::Big).unwrap().is_empty());
+        assert!(writer.write_at(6, &[0xFF]).unwrap().1.is_empty());
+        let fired = writer.write_at(3, &[0x01]).unwrap().1;
+        assert_eq!(1, fired.len());
+        assert_eq!(1, writer.get_byte(0xC).unwrap());
+        assert_eq!(vec![2], *seen.lock().unwrap());
+        assert_eq!(vec![Event::Trigger(fired[0].clone())], events.try_iter().collect::<Vec<_>>());
+
+        // a write elsewhere doesn't evaluate the equality, which would fire on first sight
+        assert!(unrelated.writes(&mut words).write_at(0, &[1]).unwrap().1.is_empty());
+        assert_eq!(1, unrelated.poll(&mut words).unwrap().len());
+    }
+}
@@ -0,0 +1,441 @@
+//! Typed accessors for reading and writing primitive values at logical addresses.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use binread::Endian;
+
+use crate::{AddressMode, ReversedWords, ReversedWordsError};
+
+/// A fixed-width value which can be converted to and from bytes in a given endianness.
+pub trait Primitive: Sized + Copy {
+    type Bytes: AsRef<[u8]> + AsMut<[u8]> + Default;
+
+    fn from_bytes(bytes: Self::Bytes, endian: Endian) -> Self;
+    fn to_bytes(self, endian: Endian) -> Self::Bytes;
+}
+
+macro_rules! impl_primitive {
+    ($($ty:ty),*) => {
+        $(
+            impl Primitive for $ty {
+                type Bytes = [u8; std::mem::size_of::<$ty>()];
+
+                fn from_bytes(bytes: Self::Bytes, endian: Endian) -> Self {
+                    match endian {
+                        Endian::Big => <$ty>::from_be_bytes(bytes),
+                        Endian::Little => <$ty>::from_le_bytes(bytes),
+                        Endian::Native => <$ty>::from_ne_bytes(bytes),
+                    }
+                }
+
+                fn to_bytes(self, endian: Endian) -> Self::Bytes {
+                    match endian {
+                        Endian::Big => self.to_be_bytes(),
+                        Endian::Little => self.to_le_bytes(),
+                        Endian::Native => self.to_ne_bytes(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// Primitives and arrays of them (nested to any depth), laid out back to back with no padding, so
+/// that [`ReversedWords::read_pod`] can return a `[u32; 4]` or a `[[f32; 3]; 3]` directly.
+pub trait Pod: Sized + Copy {
+    /// Size in bytes.
+    const SIZE: usize;
+
+    fn read_from(words: &ReversedWords, addr: u64, endian: Endian) -> std::io::Result<Self>;
+    fn write_to(self, words: &mut ReversedWords, addr: u64, endian: Endian) -> std::io::Result<()>;
+}
+
+impl<T: Primitive> Pod for T {
+    const SIZE: usize = std::mem::size_of::<T>();
+
+    fn read_from(words: &ReversedWords, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        if words.read_at(addr, bytes.as_mut())? < Self::SIZE {
+            return Err(ReversedWordsError::RangeOutOfRange { start: addr, end: addr.saturating_add(Self::SIZE as u64), len: words.len() }.into());
+        }
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    fn write_to(self, words: &mut ReversedWords, addr: u64, endian: Endian) -> std::io::Result<()> {
+        if words.write_at(addr, self.to_bytes(endian).as_ref())? < Self::SIZE {
+            return Err(ReversedWordsError::RangeOutOfRange { start: addr, end: addr.saturating_add(Self::SIZE as u64), len: words.len() }.into());
+        }
+        Ok(())
+    }
+}
+
+impl<T: Pod, const N: usize> Pod for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+
+    fn read_from(words: &ReversedWords, addr: u64, endian: Endian) -> std::io::Result<[T; N]> {
+        let mut values = [None; N];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = Some(T::read_from(words, addr.saturating_add((i * T::SIZE) as u64), endian)?);
+        }
+        Ok(values.map(|value| value.expect("every value was read")))
+    }
+
+    fn write_to(self, words: &mut ReversedWords, addr: u64, endian: Endian) -> std::io::Result<()> {
+        let end = addr.saturating_add(Self::SIZE as u64);
+        if end > words.len() && words.address_mode() == AddressMode::Bounded {
+            return Err(ReversedWordsError::RangeOutOfRange { start: addr, end, len: words.len() }.into());
+        }
+        for (i, value) in self.iter().copied().enumerate() {
+            value.write_to(words, addr + (i * T::SIZE) as u64, endian)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! typed_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            pub fn $read(&mut self, addr: u64, endian: Endian) -> std::io::Result<$ty> {
+                self.read_value_at(addr, endian)
+            }
+
+            pub fn $write(&mut self, addr: u64, value: $ty, endian: Endian) -> std::io::Result<()> {
+                self.write_value_at(addr, value, endian)
+            }
+        )*
+    };
+}
+
+macro_rules! aligned_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            pub fn $read(&mut self, addr: u64, endian: Endian) -> std::io::Result<$ty> {
+                self.read_value_aligned_at(addr, endian)
+            }
+
+            pub fn $write(&mut self, addr: u64, value: $ty, endian: Endian) -> std::io::Result<()> {
+                self.write_value_aligned_at(addr, value, endian)
+            }
+        )*
+    };
+}
+
+// the same accessors in the view's own endianness
+macro_rules! endian_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            pub fn $read(&mut self, addr: u64) -> std::io::Result<$ty> {
+                self.read_value(addr)
+            }
+
+            pub fn $write(&mut self, addr: u64, value: $ty) -> std::io::Result<()> {
+                self.write_value(addr, value)
+            }
+        )*
+    };
+}
+
+pub(crate) fn check_natural_alignment<T>(addr: u64) -> Result<(), ReversedWordsError> {
+    let alignment = std::mem::size_of::<T>() as u64;
+    if !addr.is_multiple_of(alignment) {
+        return Err(ReversedWordsError::UnalignedValue { addr, alignment });
+    }
+    Ok(())
+}
+
+impl ReversedWords<'_> {
+    /// Read a value from the logical bytes starting at `addr`, leaving the cursor just past it.
+    ///
+    /// `addr` doesn't need to be aligned to anything: a value may start partway through a word
+    /// and straddle any number of word boundaries (a `u32` at offset 2 with 4-byte words takes two
+    /// bytes from each of two words), and every byte is taken from its own logical position. This
+    /// holds for every word size and is covered by `unaligned_values_across_word_sizes`.
+    ///
+    /// With [`AddressMode::Wrapping`] the value may wrap around the end of the data.
+    pub fn read_value_at<T: Primitive>(&mut self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        let mut bytes = T::Bytes::default();
+        if self.address_mode() == AddressMode::Wrapping {
+            let size = bytes.as_ref().len();
+            if self.read_at(addr, bytes.as_mut())? < size {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            self.seek(SeekFrom::Start(self.offset_address(addr, size as u64)))?;
+            return Ok(T::from_bytes(bytes, endian));
+        }
+        self.seek(SeekFrom::Start(addr))?;
+        self.read_exact(bytes.as_mut())?;
+        Ok(T::from_bytes(bytes, endian))
+    }
+
+    /// Write a value to the logical bytes starting at `addr`, leaving the cursor just past it.
+    pub fn write_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        let bytes = value.to_bytes(endian);
+        if self.address_mode() == AddressMode::Wrapping {
+            let size = bytes.as_ref().len();
+            if self.write_at(addr, bytes.as_ref())? < size {
+                return Err(std::io::ErrorKind::WriteZero.into());
+            }
+            self.seek(SeekFrom::Start(self.offset_address(addr, size as u64)))?;
+            return Ok(());
+        }
+        self.seek(SeekFrom::Start(addr))?;
+        self.write_all(bytes.as_ref())
+    }
+
+    /// [`ReversedWords::write_value_at`], but nothing is written unless the whole value is in
+    /// range: a value running past the end fails with [`ReversedWordsError::RangeOutOfRange`]
+    /// instead of leaving its first bytes behind. The C, Python and wasm bindings write through this.
+    pub fn write_whole_value_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        let size = T::Bytes::default().as_ref().len() as u64;
+        if self.address_mode() == AddressMode::Bounded && addr.checked_add(size).is_none_or(|end| end > self.reachable_len()) {
+            let len = self.len();
+            return Err(ReversedWordsError::RangeOutOfRange { start: addr, end: addr.saturating_add(size), len }.into());
+        }
+        self.write_value_at(addr, value, endian)
+    }
+
+    /// [`ReversedWords::read_value_at`] in the view's [`endian`](ReversedWords::with_endian).
+    pub fn read_value<T: Primitive>(&mut self, addr: u64) -> std::io::Result<T> {
+        self.read_value_at(addr, self.endian())
+    }
+
+    pub fn write_value<T: Primitive>(&mut self, addr: u64, value: T) -> std::io::Result<()> {
+        self.write_value_at(addr, value, self.endian())
+    }
+
+    /// The `N` logical bytes at `addr` as an array, without moving the cursor. Fails unless all of
+    /// them are in range.
+    pub fn read_array<const N: usize>(&self, addr: u64) -> std::io::Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        if self.read_at(addr, &mut bytes)? < N {
+            return Err(ReversedWordsError::RangeOutOfRange { start: addr, end: addr.saturating_add(N as u64), len: self.len() }.into());
+        }
+        Ok(bytes)
+    }
+
+    /// Write `bytes` at `addr` without moving the cursor. Fails without writing anything unless
+    /// all of them are in range.
+    pub fn write_array<const N: usize>(&mut self, addr: u64, bytes: [u8; N]) -> std::io::Result<()> {
+        [bytes].write_to(self, addr, Endian::Big)
+    }
+
+    /// A [`Pod`] value, such as an array of primitives, read from `addr` without moving the cursor
+    /// or allocating.
+    pub fn read_pod<T: Pod>(&self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        T::read_from(self, addr, endian)
+    }
+
+    /// Write a [`Pod`] value at `addr` without moving the cursor. Fails without writing anything
+    /// unless all of it is in range.
+    pub fn write_pod<T: Pod>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        [value].write_to(self, addr, endian)
+    }
+
+    /// Like [`ReversedWords::read_value_at`], but fails with [`ReversedWordsError::UnalignedValue`]
+    /// unless `addr` is a multiple of the value's size, like hardware that faults on unaligned
+    /// loads.
+    pub fn read_value_aligned_at<T: Primitive>(&mut self, addr: u64, endian: Endian) -> std::io::Result<T> {
+        check_natural_alignment::<T>(addr)?;
+        self.read_value_at(addr, endian)
+    }
+
+    pub fn write_value_aligned_at<T: Primitive>(&mut self, addr: u64, value: T, endian: Endian) -> std::io::Result<()> {
+        check_natural_alignment::<T>(addr)?;
+        self.write_value_at(addr, value, endian)
+    }
+
+    aligned_accessors! {
+        u16 => read_u16_aligned_at, write_u16_aligned_at;
+        u32 => read_u32_aligned_at, write_u32_aligned_at;
+        u64 => read_u64_aligned_at, write_u64_aligned_at;
+        u128 => read_u128_aligned_at, write_u128_aligned_at;
+        i16 => read_i16_aligned_at, write_i16_aligned_at;
+        i32 => read_i32_aligned_at, write_i32_aligned_at;
+        i64 => read_i64_aligned_at, write_i64_aligned_at;
+        i128 => read_i128_aligned_at, write_i128_aligned_at;
+        f32 => read_f32_aligned_at, write_f32_aligned_at;
+        f64 => read_f64_aligned_at, write_f64_aligned_at;
+    }
+
+    typed_accessors! {
+        u8 => read_u8_at, write_u8_at;
+        u16 => read_u16_at, write_u16_at;
+        u32 => read_u32_at, write_u32_at;
+        u64 => read_u64_at, write_u64_at;
+        u128 => read_u128_at, write_u128_at;
+        i8 => read_i8_at, write_i8_at;
+        i16 => read_i16_at, write_i16_at;
+        i32 => read_i32_at, write_i32_at;
+        i64 => read_i64_at, write_i64_at;
+        i128 => read_i128_at, write_i128_at;
+        f32 => read_f32_at, write_f32_at;
+        f64 => read_f64_at, write_f64_at;
+    }
+
+    endian_accessors! {
+        u8 => read_u8, write_u8;
+        u16 => read_u16, write_u16;
+        u32 => read_u32, write_u32;
+        u64 => read_u64, write_u64;
+        u128 => read_u128, write_u128;
+        i8 => read_i8, write_i8;
+        i16 => read_i16, write_i16;
+        i32 => read_i32, write_i32;
+        i64 => read_i64, write_i64;
+        i128 => read_i128, write_i128;
+        f32 => read_f32, write_f32;
+        f64 => read_f64, write_f64;
+    }
+}
+
+/// A struct whose fields live at fixed offsets from a base address, see `#[derive(SwappedLayout)]`.
+pub trait SwappedLayout: Sized {
+    fn load(words: &mut ReversedWords, base: u64) -> std::io::Result<Self>;
+    fn store(&self, words: &mut ReversedWords, base: u64) -> std::io::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use crate::*;
+
+    #[test]
+    fn arrays_and_pods() {
+        let mut data: Vec<u8> = (0..16).collect();
+        let mut ram = ReversedWords::new(&mut data);
+        assert_eq!([1, 0, 7, 6], ram.read_array::<4>(2).unwrap());
+        assert!(ram.read_array::<4>(13).is_err());
+        ram.write_array(6, [0xAA, 0xBB]).unwrap();
+        assert_eq!([0xAA, 0xBB], ram.read_array(6).unwrap());
+        assert!(ram.write_array(15, [0xCC, 0xCC]).is_err());
+        assert_eq!([12], ram.read_array(15).unwrap());
+        assert_eq!(0, ram.position());
+
+        let words: [u16; 2] = ram.read_pod(0, Endian::Big).unwrap();
+        assert_eq!([0x0302, 0x0100], words);
+        ram.write_pod(8, [[1u16, 2], [3, 4]], Endian::Little).unwrap();
+        assert_eq!([[1, 2], [3, 4]], ram.read_pod::<[[u16; 2]; 2]>(8, Endian::Little).unwrap());
+        assert_eq!(0x0002_0001, ram.read_pod::<u32>(8, Endian::Little).unwrap());
+        assert!(ram.write_pod(12, [0u32; 2], Endian::Big).is_err());
+        assert_eq!(8, <[[u16; 2]; 2] as Pod>::SIZE);
+    }
+
+    #[test]
+    fn view_endianness_is_the_default() {
+        let mut data: Vec<u8> = vec![0x78, 0x56, 0x34, 0x12];
+        let mut ram = ReversedWords::new(&mut data);
+        assert_eq!(Endian::Big, ram.endian());
+        assert_eq!(0x1234_5678, ram.read_u32(0).unwrap());
+        ram.set_endian(Endian::Little);
+        assert_eq!(0x7856_3412, ram.read_u32(0).unwrap());
+        ram.write_u16(2, 0xBEEF).unwrap();
+        assert_eq!(0xBEEF, ram.read_u16_at(2, Endian::Little).unwrap());
+        assert_eq!(0xEFBE, ram.read_u16_at(2, Endian::Big).unwrap());
+        assert_eq!(Endian::Little, platform::Platform::Ps2EeRam.view(&mut [0u8; 16]).into_inner().endian());
+    }
+
+    #[test]
+    fn read_u32_at_both_endians() {
+        let mut data: Vec<u8> = vec![0x78, 0x56, 0x34, 0x12];
+        let mut ram = ReversedWords::new(&mut data);
+        assert_eq!(0x12345678, ram.read_u32_at(0, Endian::Big).unwrap());
+        assert_eq!(0x78563412, ram.read_u32_at(0, Endian::Little).unwrap());
+    }
+
+    #[test]
+    fn write_then_read_unaligned() {
+        let mut data = vec![0u8; 8];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.write_u16_at(3, 0xBEEF, Endian::Big).unwrap();
+        assert_eq!(0xBEEF, ram.read_u16_at(3, Endian::Big).unwrap());
+        assert_eq!(vec![0xBE, 0, 0, 0, 0, 0, 0, 0xEF], data);
+    }
+
+    #[test]
+    fn read_past_end_fails() {
+        let mut data = vec![0u8; 4];
+        let mut ram = ReversedWords::new(&mut data);
+        assert!(ram.read_u32_at(2, Endian::Big).is_err());
+    }
+
+    #[test]
+    fn whole_value_writes_are_all_or_nothing() {
+        // the last two bytes are a partial word, so only 0..4 is reachable
+        let mut data = vec![0u8; 6];
+        let mut ram = ReversedWords::new(&mut data);
+        let error = ram.write_whole_value_at(2, 0xAABB_CCDDu32, Endian::Big).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::RangeOutOfRange { start: 2, end: 6, len: 6 }), ReversedWordsError::from_io(&error));
+        assert!(ram.write_whole_value_at(u64::MAX, 1u16, Endian::Big).is_err());
+        ram.write_whole_value_at(2, 0xAABBu16, Endian::Big).unwrap();
+        assert_eq!(vec![0xBB, 0xAA, 0, 0, 0, 0], data);
+    }
+
+    #[test]
+    fn aligned_accessors_fault_like_hardware() {
+        let mut data = vec![0u8; 16];
+        let mut ram = ReversedWords::new(&mut data);
+        ram.write_u32_aligned_at(4, 0xDEADBEEF, Endian::Big).unwrap();
+        assert_eq!(0xDEADBEEF, ram.read_u32_aligned_at(4, Endian::Big).unwrap());
+        let error = ram.read_u32_aligned_at(6, Endian::Big).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnalignedValue { addr: 6, alignment: 4 }), ReversedWordsError::from_io(&error));
+        assert!(ram.write_u64_aligned_at(4, 0, Endian::Big).is_err());
+        assert_eq!(0xBEEF, ram.read_u16_aligned_at(6, Endian::Big).unwrap());
+    }
+
+    // logical -> storage without going through the view: byte `i` of word `w` is stored at
+    // `w * word_size + (word_size - 1 - i)`
+    fn reference_storage(logical: &[u8], word_size: usize) -> Vec<u8> {
+        let mut storage = vec![0u8; logical.len()];
+        for (position, byte) in logical.iter().enumerate() {
+            storage[position / word_size * word_size + (word_size - 1 - position % word_size)] = *byte;
+        }
+        storage
+    }
+
+    #[test]
+    fn unaligned_values_across_word_sizes() {
+        for word_size in [1usize, 2, 3, 4, 8, 16] {
+            let len = 24usize.div_ceil(word_size) * word_size;
+            let logical: Vec<u8> = (0..len as u8).collect();
+            for addr in 0..=(len - 8) as u64 {
+                let mut data = reference_storage(&logical, word_size);
+                let mut ram = ReversedWords::new_with_word_size(&mut data, word_size as u8);
+                let at = addr as usize;
+                let expected_u16 = u16::from_be_bytes(logical[at..at + 2].try_into().unwrap());
+                let expected_u32 = u32::from_be_bytes(logical[at..at + 4].try_into().unwrap());
+                let expected_u64 = u64::from_be_bytes(logical[at..at + 8].try_into().unwrap());
+                assert_eq!(expected_u16, ram.read_u16_at(addr, Endian::Big).unwrap(), "u16, word size {}, addr {}", word_size, addr);
+                assert_eq!(expected_u32, ram.read_u32_at(addr, Endian::Big).unwrap(), "u32, word size {}, addr {}", word_size, addr);
+                assert_eq!(expected_u64, ram.read_u64_at(addr, Endian::Big).unwrap(), "u64, word size {}, addr {}", word_size, addr);
+                assert_eq!(expected_u32.swap_bytes(), ram.read_u32_at(addr, Endian::Little).unwrap());
+
+                let value = 0xA1A2_A3A4_A5A6_A7A8u64;
+                ram.write_u64_at(addr, value, Endian::Big).unwrap();
+                assert_eq!(addr + 8, ram.position());
+                let mut expected = logical.clone();
+                expected[at..at + 8].copy_from_slice(&value.to_be_bytes());
+                assert_eq!(reference_storage(&expected, word_size), data, "write, word size {}, addr {}", word_size, addr);
+            }
+        }
+    }
+
+    #[test]
+    fn quadwords() {
+        let mut data: Vec<u8> = (0..32).collect();
+        let mut ram = ReversedWords::new_with_word_size(&mut data, 16);
+        assert_eq!(u128::from_be_bytes(core::array::from_fn(|i| 31 - i as u8)), ram.read_u128_at(16, Endian::Big).unwrap());
+
+        // seeking into the middle of a quadword reads its remaining bytes, then the next quadword's
+        ram.seek(SeekFrom::Start(12)).unwrap();
+        let mut out = [0u8; 8];
+        ram.read_exact(&mut out).unwrap();
+        assert_eq!([3, 2, 1, 0, 31, 30, 29, 28], out);
+
+        ram.write_u128_at(8, u128::MAX, Endian::Little).unwrap();
+        assert_eq!([0xFF; 8], data[0..8]);
+        assert_eq!([16, 17, 18, 19, 20, 21, 22, 23], data[16..24]);
+        assert_eq!([0xFF; 8], data[24..32]);
+    }
+}
@@ -0,0 +1,375 @@
+//! VCDIFF (RFC 3284) deltas between snapshots.
+//!
+//! [`encode`] produces a delta turning one snapshot into another, in the standard format that
+//! `xdelta3` and `open-vcdiff` read. It is tuned for RAM snapshots, where changes happen in
+//! place: unchanged stretches are copied from the same offset of the source, changed bytes are
+//! added literally and repeated bytes become runs. [`decode`] applies any delta using the default
+//! code table without secondary compression, including ones from other encoders.
+//!
+//! Snapshots are storage bytes, so deltas don't depend on the word size of the view.
+
+use std::convert::TryFrom;
+
+use crate::{ReversedWords, ReversedWordsError};
+
+const MAGIC: [u8; 4] = [0xD6, 0xC3, 0xC4, 0x00];
+
+const VCD_DECOMPRESS: u8 = 0x01;
+const VCD_CODETABLE: u8 = 0x02;
+const VCD_APPHEADER: u8 = 0x04;
+
+const VCD_SOURCE: u8 = 0x01;
+const VCD_TARGET: u8 = 0x02;
+// open-vcdiff extension: an Adler-32 of the target window follows the section lengths
+const VCD_ADLER32: u8 = 0x04;
+
+// default code table entries the encoder uses, each with its size given separately
+const RUN: u8 = 0;
+const ADD: u8 = 1;
+const COPY_SELF: u8 = 19;
+
+const NEAR_SIZE: usize = 4;
+const SAME_SIZE: usize = 3;
+
+// shorter unchanged or repeated stretches aren't worth an instruction of their own
+const MIN_MATCH: usize = 8;
+
+fn invalid(reason: &str) -> ReversedWordsError {
+    ReversedWordsError::InvalidDelta { reason: reason.to_string() }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value != 0 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Noop,
+    Add,
+    Run,
+    Copy(u8),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Instruction {
+    op: Op,
+    // 0 means the size follows in the instruction section
+    size: u8,
+}
+
+const NOOP: Instruction = Instruction { op: Op::Noop, size: 0 };
+
+// the default code table of RFC 3284 section 5.6
+fn default_code_table() -> Vec<[Instruction; 2]> {
+    let single = |op, size| [Instruction { op, size }, NOOP];
+    let mut table = vec![single(Op::Run, 0)];
+    table.extend((0..=17).map(|size| single(Op::Add, size)));
+    for mode in 0..9 {
+        table.push(single(Op::Copy(mode), 0));
+        table.extend((4..=18).map(|size| single(Op::Copy(mode), size)));
+    }
+    for mode in 0..6 {
+        for add in 1..=4 {
+            for copy in 4..=6 {
+                table.push([Instruction { op: Op::Add, size: add }, Instruction { op: Op::Copy(mode), size: copy }]);
+            }
+        }
+    }
+    for mode in 6..9 {
+        for add in 1..=4 {
+            table.push([Instruction { op: Op::Add, size: add }, Instruction { op: Op::Copy(mode), size: 4 }]);
+        }
+    }
+    for mode in 0..9 {
+        table.push([Instruction { op: Op::Copy(mode), size: 4 }, Instruction { op: Op::Add, size: 1 }]);
+    }
+    table
+}
+
+/// A delta turning `source` into `target`.
+pub fn encode(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let (mut data, mut instructions, mut addresses) = (Vec::new(), Vec::new(), Vec::new());
+    let matches_at = |i: usize| i < source.len() && source[i] == target[i];
+    let mut i = 0;
+    let mut literal_start = 0;
+    let flush_literal = |start: usize, end: usize, data: &mut Vec<u8>, instructions: &mut Vec<u8>| {
+        let mut j = start;
+        while j < end {
+            let run = target[j..end].iter().take_while(|byte| **byte == target[j]).count();
+            if run >= MIN_MATCH {
+                instructions.push(RUN);
+                write_varint(instructions, run as u64);
+                data.push(target[j]);
+                j += run;
+                continue;
+            }
+            let add_end = (j..end)
+                .find(|&k| k + MIN_MATCH <= end && target[k..k + MIN_MATCH].iter().all(|byte| *byte == target[k]))
+                .unwrap_or(end)
+                .max(j + 1);
+            instructions.push(ADD);
+            write_varint(instructions, (add_end - j) as u64);
+            data.extend_from_slice(&target[j..add_end]);
+            j = add_end;
+        }
+    };
+    while i < target.len() {
+        let same = (i..target.len()).take_while(|&k| matches_at(k)).count();
+        if same >= MIN_MATCH || (same > 0 && i + same == target.len()) {
+            flush_literal(literal_start, i, &mut data, &mut instructions);
+            instructions.push(COPY_SELF);
+            write_varint(&mut instructions, same as u64);
+            write_varint(&mut addresses, i as u64);
+            i += same;
+            literal_start = i;
+        } else {
+            i += same.max(1);
+        }
+    }
+    flush_literal(literal_start, target.len(), &mut data, &mut instructions);
+
+    let mut window = Vec::new();
+    write_varint(&mut window, target.len() as u64);
+    window.push(0); // no compressed sections
+    for section in [&data, &instructions, &addresses] {
+        write_varint(&mut window, section.len() as u64);
+    }
+    window.extend_from_slice(&data);
+    window.extend_from_slice(&instructions);
+    window.extend_from_slice(&addresses);
+
+    let mut delta = MAGIC.to_vec();
+    delta.push(0); // no header extensions
+    if source.is_empty() {
+        delta.push(0);
+    } else {
+        delta.push(VCD_SOURCE);
+        write_varint(&mut delta, source.len() as u64);
+        write_varint(&mut delta, 0);
+    }
+    write_varint(&mut delta, window.len() as u64);
+    delta.extend_from_slice(&window);
+    delta
+}
+
+struct Reader<'d> {
+    data: &'d [u8],
+}
+
+impl<'d> Reader<'d> {
+    fn byte(&mut self) -> Result<u8, ReversedWordsError> {
+        let (&byte, rest) = self.data.split_first().ok_or_else(|| invalid("unexpected end of delta"))?;
+        self.data = rest;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, ReversedWordsError> {
+        let mut value: u64 = 0;
+        loop {
+            let byte = self.byte()?;
+            value = value.checked_mul(128).ok_or_else(|| invalid("integer overflows 64 bits"))? | (byte & 0x7F) as u64;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn size(&mut self) -> Result<usize, ReversedWordsError> {
+        usize::try_from(self.varint()?).map_err(|_| invalid("size doesn't fit in memory"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'d [u8], ReversedWordsError> {
+        if self.data.len() < len {
+            return Err(invalid("unexpected end of delta"));
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+}
+
+struct AddressCache {
+    near: [u64; NEAR_SIZE],
+    next_near: usize,
+    same: Vec<u64>,
+}
+
+impl AddressCache {
+    fn new() -> AddressCache {
+        AddressCache { near: [0; NEAR_SIZE], next_near: 0, same: vec![0; SAME_SIZE * 256] }
+    }
+
+    fn decode(&mut self, mode: u8, here: u64, addresses: &mut Reader) -> Result<u64, ReversedWordsError> {
+        let addr = match mode as usize {
+            0 => addresses.varint()?,
+            1 => here.checked_sub(addresses.varint()?).ok_or_else(|| invalid("copy address before the window"))?,
+            m if m < 2 + NEAR_SIZE => self.near[m - 2].wrapping_add(addresses.varint()?),
+            m => self.same[(m - 2 - NEAR_SIZE) * 256 + addresses.byte()? as usize],
+        };
+        self.near[self.next_near] = addr;
+        self.next_near = (self.next_near + 1) % NEAR_SIZE;
+        let same_len = self.same.len() as u64;
+        self.same[(addr % same_len) as usize] = addr;
+        Ok(addr)
+    }
+}
+
+/// Apply `delta` to `source`, returning the target.
+pub fn decode(source: &[u8], delta: &[u8]) -> Result<Vec<u8>, ReversedWordsError> {
+    let mut reader = Reader { data: delta };
+    if reader.take(4).ok() != Some(&MAGIC[..]) {
+        return Err(invalid("not a VCDIFF delta"));
+    }
+    let header = reader.byte()?;
+    if header & (VCD_DECOMPRESS | VCD_CODETABLE) != 0 {
+        return Err(invalid("secondary compression and custom code tables are unsupported"));
+    }
+    if header & VCD_APPHEADER != 0 {
+        let len = reader.size()?;
+        reader.take(len)?;
+    }
+    let table = default_code_table();
+    let mut output = Vec::new();
+    while !reader.data.is_empty() {
+        let indicator = reader.byte()?;
+        let segment: &[u8] = if indicator & (VCD_SOURCE | VCD_TARGET) != 0 {
+            let len = reader.size()?;
+            let position = reader.size()?;
+            let end = position.checked_add(len).ok_or_else(|| invalid("segment overflows"))?;
+            let data = if indicator & VCD_SOURCE != 0 { source } else { &output[..] };
+            data.get(position..end).ok_or_else(|| invalid("segment outside its data"))?
+        } else {
+            &[]
+        };
+        let segment = segment.to_vec();
+        let _delta_len = reader.varint()?;
+        let target_len = reader.size()?;
+        if reader.byte()? != 0 {
+            return Err(invalid("compressed sections are unsupported"));
+        }
+        let (data_len, instructions_len, addresses_len) = (reader.size()?, reader.size()?, reader.size()?);
+        if indicator & VCD_ADLER32 != 0 {
+            reader.take(4)?;
+        }
+        let mut data = Reader { data: reader.take(data_len)? };
+        let mut instructions = Reader { data: reader.take(instructions_len)? };
+        let mut addresses = Reader { data: reader.take(addresses_len)? };
+        let mut cache = AddressCache::new();
+        // target_len comes from the delta, so the window grows as instructions are checked against it
+        let mut window: Vec<u8> = Vec::new();
+        while !instructions.data.is_empty() {
+            for instruction in table[instructions.byte()? as usize] {
+                let size = match (instruction.op, instruction.size) {
+                    (Op::Noop, _) => continue,
+                    (_, 0) => instructions.size()?,
+                    (_, size) => size as usize,
+                };
+                if window.len().checked_add(size).is_none_or(|len| len > target_len) {
+                    return Err(invalid("window longer than declared"));
+                }
+                match instruction.op {
+                    Op::Noop => {}
+                    Op::Add => window.extend_from_slice(data.take(size)?),
+                    Op::Run => {
+                        let byte = data.byte()?;
+                        window.resize(window.len() + size, byte);
+                    }
+                    Op::Copy(mode) => {
+                        let here = (segment.len() + window.len()) as u64;
+                        let addr = cache.decode(mode, here, &mut addresses)?;
+                        if addr >= here {
+                            return Err(invalid("copy from after the current position"));
+                        }
+                        for i in 0..size as u64 {
+                            let from = (addr + i) as usize;
+                            let byte = match from.checked_sub(segment.len()) {
+                                None => segment[from],
+                                Some(offset) => window[offset],
+                            };
+                            window.push(byte);
+                        }
+                    }
+                }
+            }
+        }
+        if window.len() != target_len {
+            return Err(invalid("window shorter than declared"));
+        }
+        output.extend_from_slice(&window);
+    }
+    Ok(output)
+}
+
+impl ReversedWords<'_> {
+    /// A delta turning `snapshot` (storage bytes, as from [`ReversedWords::raw_span`] or a
+    /// snapshot ring) into the current contents of the view.
+    pub fn vcdiff_from(&self, snapshot: &[u8]) -> Vec<u8> {
+        encode(snapshot, self.cursor.get_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{vcdiff::*, Endian};
+
+    #[test]
+    fn round_trips() {
+        let source: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let mut current = source.clone();
+        let mut words = ReversedWords::new(&mut current);
+        words.write_u32_at(100, 0xDEAD_BEEF, Endian::Big).unwrap();
+        words.fill(2000..2100, 0).unwrap();
+        words.write_at(4090, &[1, 2, 3, 4, 5, 6]).unwrap();
+        let delta = words.vcdiff_from(&source);
+        assert!(delta.len() < 64, "{} byte delta", delta.len());
+        assert_eq!(current, decode(&source, &delta).unwrap());
+
+        for (source, target) in [(&[][..], &b"fresh"[..]), (b"shrinks", b"shr"), (b"same", b"same"), (b"grows", b"grows and grows")] {
+            assert_eq!(target, &decode(source, &encode(source, target)).unwrap()[..]);
+        }
+        assert!(decode(&source, &delta[..delta.len() - 1]).is_err());
+        assert!(decode(&source, b"BSDIFF40").is_err());
+    }
+
+    #[test]
+    fn decodes_the_default_code_table() {
+        // ADD 2 + COPY 4 mode 0 (index 163 + 1 * 3), then COPY 4 from HERE - 6 + ADD 1 (index 248)
+        let mut window = Vec::new();
+        write_varint(&mut window, 11);
+        window.extend_from_slice(&[0, 3, 2, 2]);
+        window.extend_from_slice(b"ab!");
+        window.extend_from_slice(&[166, 248]);
+        window.extend_from_slice(&[0, 6]);
+        let mut delta = MAGIC.to_vec();
+        delta.extend_from_slice(&[0, VCD_SOURCE, 4, 0, window.len() as u8]);
+        delta.extend_from_slice(&window);
+        assert_eq!(b"abWXYZabWX!".to_vec(), decode(b"WXYZ", &delta).unwrap());
+    }
+
+    #[test]
+    fn rejects_windows_other_than_declared() {
+        let delta = |target_len: u64, instructions: &[u8]| {
+            let mut window = Vec::new();
+            write_varint(&mut window, target_len);
+            window.extend_from_slice(&[0, 1, instructions.len() as u8, 0, 7]);
+            window.extend_from_slice(instructions);
+            let mut delta = MAGIC.to_vec();
+            delta.extend_from_slice(&[0, 0]);
+            write_varint(&mut delta, window.len() as u64);
+            delta.extend_from_slice(&window);
+            delta
+        };
+        let invalid = |reason: &str| ReversedWordsError::InvalidDelta { reason: reason.to_string() };
+        // ADD 1 (index 2) into a window claiming to be enormous isn't allocated up front
+        assert_eq!(invalid("window shorter than declared"), decode(b"", &delta(u64::MAX >> 1, &[2])).unwrap_err());
+        // RUN 5 (index 0 with an explicit size) into a 4 byte window
+        assert_eq!(invalid("window longer than declared"), decode(b"", &delta(4, &[0, 5])).unwrap_err());
+        assert_eq!(vec![7; 4], decode(b"", &delta(4, &[0, 4])).unwrap());
+    }
+}
@@ -0,0 +1,97 @@
+//! Float vectors and matrices, for camera and position hacking.
+//!
+//! Components are `f32`s and must be aligned to 4 bytes, as the hardware requires; a misaligned
+//! address is almost always a wrong offset, and fails with [`ReversedWordsError::UnalignedValue`].
+
+use binread::Endian;
+
+use crate::{typed::check_natural_alignment, ReversedWords};
+
+/// How a 4x4 matrix is laid out in memory. Matrices are always returned and taken as
+/// `matrix[row][column]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatrixOrder {
+    /// Each row's four entries are consecutive, as in Direct3D.
+    RowMajor,
+    /// Each column's four entries are consecutive, as in OpenGL and the N64 SDK's float matrices.
+    ColumnMajor,
+}
+
+fn transpose(matrix: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut transposed = [[0.0; 4]; 4];
+    for (row, entries) in matrix.iter().enumerate() {
+        for (column, entry) in entries.iter().enumerate() {
+            transposed[column][row] = *entry;
+        }
+    }
+    transposed
+}
+
+impl ReversedWords<'_> {
+    /// The three `f32`s at `addr`, without moving the cursor.
+    pub fn read_vec3_at(&self, addr: u64, endian: Endian) -> std::io::Result<[f32; 3]> {
+        check_natural_alignment::<f32>(addr)?;
+        self.read_pod(addr, endian)
+    }
+
+    /// Write three `f32`s at `addr`. Fails without writing anything unless all of them are in
+    /// range.
+    pub fn write_vec3_at(&mut self, addr: u64, vector: [f32; 3], endian: Endian) -> std::io::Result<()> {
+        check_natural_alignment::<f32>(addr)?;
+        self.write_pod(addr, vector, endian)
+    }
+
+    /// The 4x4 matrix of `f32`s at `addr`, stored in `order`.
+    pub fn read_matrix_at(&self, addr: u64, order: MatrixOrder, endian: Endian) -> std::io::Result<[[f32; 4]; 4]> {
+        check_natural_alignment::<f32>(addr)?;
+        let matrix = self.read_pod(addr, endian)?;
+        Ok(match order {
+            MatrixOrder::RowMajor => matrix,
+            MatrixOrder::ColumnMajor => transpose(matrix),
+        })
+    }
+
+    pub fn write_matrix_at(&mut self, addr: u64, matrix: [[f32; 4]; 4], order: MatrixOrder, endian: Endian) -> std::io::Result<()> {
+        check_natural_alignment::<f32>(addr)?;
+        let matrix = match order {
+            MatrixOrder::RowMajor => matrix,
+            MatrixOrder::ColumnMajor => transpose(matrix),
+        };
+        self.write_pod(addr, matrix, endian)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{vector::*, ReversedWords, ReversedWordsError};
+
+    #[test]
+    fn vectors_and_matrices() {
+        let mut data = vec![0u8; 96];
+        let mut words = ReversedWords::new(&mut data);
+        words.write_vec3_at(4, [1.0, -2.5, 3.0], Endian::Big).unwrap();
+        assert_eq!(-2.5, words.read_f32_at(8, Endian::Big).unwrap());
+        assert_eq!([1.0, -2.5, 3.0], words.read_vec3_at(4, Endian::Big).unwrap());
+
+        // a translation by (5, 6, 7)
+        let matrix = [[1.0, 0.0, 0.0, 5.0], [0.0, 1.0, 0.0, 6.0], [0.0, 0.0, 1.0, 7.0], [0.0, 0.0, 0.0, 1.0]];
+        words.write_matrix_at(16, matrix, MatrixOrder::ColumnMajor, Endian::Big).unwrap();
+        assert_eq!([5.0, 6.0, 7.0], words.read_vec3_at(16 + 48, Endian::Big).unwrap());
+        assert_eq!(matrix, words.read_matrix_at(16, MatrixOrder::ColumnMajor, Endian::Big).unwrap());
+        assert_eq!(5.0, words.read_matrix_at(16, MatrixOrder::RowMajor, Endian::Big).unwrap()[3][0]);
+        words.write_matrix_at(16, matrix, MatrixOrder::RowMajor, Endian::Little).unwrap();
+        assert_eq!(5.0, words.read_f32_at(28, Endian::Little).unwrap());
+    }
+
+    #[test]
+    fn vectors_must_be_aligned_and_in_range() {
+        let mut data = vec![0u8; 16];
+        let mut words = ReversedWords::new(&mut data);
+        let error = words.read_vec3_at(2, Endian::Big).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::UnalignedValue { addr: 2, alignment: 4 }), ReversedWordsError::from_io(&error));
+        assert!(words.write_vec3_at(6, [0.0; 3], Endian::Big).is_err());
+        assert!(words.write_vec3_at(8, [1.0; 3], Endian::Big).is_err());
+        assert!(words.read_matrix_at(0, MatrixOrder::RowMajor, Endian::Big).is_err());
+        assert_eq!([0; 16], data[..]);
+    }
+}
@@ -0,0 +1,131 @@
+//! wasm-bindgen wrapper for browser based tools.
+//!
+//! ```js
+//! const ram = new ReversedWords(new Uint8Array(await file.arrayBuffer()), 4);
+//! ram.write_u16(0x1c, 999, true);
+//! download(ram.storage());
+//! ```
+//!
+//! The view owns a copy of the JS buffer; `storage()` returns it in storage order and `logical()` in logical order.
+
+use binread::Endian;
+use wasm_bindgen::prelude::*;
+
+use crate::{Primitive, ReversedWords};
+
+#[wasm_bindgen(js_name = ReversedWords)]
+pub struct WasmReversedWords {
+    data: Vec<u8>,
+    word_size: u8,
+}
+
+fn js_error(e: std::io::Error) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+fn endian(big_endian: bool) -> Endian {
+    if big_endian { Endian::Big } else { Endian::Little }
+}
+
+impl WasmReversedWords {
+    fn words(&mut self) -> ReversedWords<'_> {
+        ReversedWords::new_with_word_size(&mut self.data, self.word_size)
+    }
+
+    fn read_value<T: Primitive>(&mut self, addr: u64, big_endian: bool) -> Result<T, JsError> {
+        self.words().read_value_at(addr, endian(big_endian)).map_err(js_error)
+    }
+
+    fn write_value<T: Primitive>(&mut self, addr: u64, value: T, big_endian: bool) -> Result<(), JsError> {
+        self.words().write_whole_value_at(addr, value, endian(big_endian)).map_err(js_error)
+    }
+}
+
+macro_rules! wasm_typed_accessors {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        #[wasm_bindgen(js_class = ReversedWords)]
+        impl WasmReversedWords {
+            #[wasm_bindgen(constructor)]
+            pub fn new(data: &[u8], word_size: u8) -> Result<WasmReversedWords, JsError> {
+                if word_size == 0 {
+                    return Err(JsError::new("word size must be at least 1 byte"));
+                }
+                Ok(WasmReversedWords { data: data.to_vec(), word_size })
+            }
+
+            #[wasm_bindgen(getter)]
+            pub fn length(&self) -> usize {
+                self.data.len()
+            }
+
+            #[wasm_bindgen(getter)]
+            pub fn word_size(&self) -> u8 {
+                self.word_size
+            }
+
+            /// Read up to `length` logical bytes starting at `addr`.
+            pub fn read(&mut self, addr: u64, length: usize) -> Result<Vec<u8>, JsError> {
+                let mut buf = vec![0u8; length];
+                let n = self.words().read_at(addr, &mut buf).map_err(js_error)?;
+                buf.truncate(n);
+                Ok(buf)
+            }
+
+            /// Write logical bytes starting at `addr`, returning how many fit.
+            pub fn write(&mut self, addr: u64, data: &[u8]) -> Result<usize, JsError> {
+                self.words().write_at(addr, data).map_err(js_error)
+            }
+
+            pub fn fill(&mut self, start: u64, end: u64, byte: u8) -> Result<(), JsError> {
+                self.words().fill(start..end, byte).map_err(js_error)
+            }
+
+            /// The data in storage order, for saving back to a file.
+            pub fn storage(&self) -> Vec<u8> {
+                self.data.clone()
+            }
+
+            /// The data in logical order.
+            pub fn logical(&mut self) -> Result<Vec<u8>, JsError> {
+                let len = self.data.len();
+                self.read(0, len)
+            }
+
+            $(
+                pub fn $read(&mut self, addr: u64, big_endian: bool) -> Result<$ty, JsError> {
+                    self.read_value(addr, big_endian)
+                }
+
+                pub fn $write(&mut self, addr: u64, value: $ty, big_endian: bool) -> Result<(), JsError> {
+                    self.write_value(addr, value, big_endian)
+                }
+            )*
+        }
+    };
+}
+
+wasm_typed_accessors! {
+    u8 => read_u8, write_u8;
+    u16 => read_u16, write_u16;
+    u32 => read_u32, write_u32;
+    i8 => read_i8, write_i8;
+    i16 => read_i16, write_i16;
+    i32 => read_i32, write_i32;
+    f32 => read_f32, write_f32;
+    f64 => read_f64, write_f64;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wasm::*;
+
+    // error paths construct JS values, so only the successful paths can run off wasm32
+    #[test]
+    fn wrapper_round_trip() {
+        let mut ram = WasmReversedWords::new(&[0, 1, 2, 3, 4, 5, 6, 7], 4).ok().unwrap();
+        assert_eq!(vec![1, 0, 7], ram.read(2, 3).ok().unwrap());
+        ram.write_u16(3, 0xBEEF, true).ok().unwrap();
+        assert_eq!(vec![0xBE, 1, 2, 3, 4, 5, 6, 0xEF], ram.storage());
+        assert_eq!(0xEFBE, ram.read_u16(3, false).ok().unwrap());
+    }
+}
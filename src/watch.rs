@@ -0,0 +1,493 @@
+//! Polling addresses for changes.
+//!
+//! Register ranges with a [`Watcher`], then [`Watcher::poll`] it against a view whenever memory
+//! may have changed (or let [`Watcher::run_every`] do so on a timer from another thread against
+//! shared [`AtomicReversedWords`]). Each poll compares the watched bytes with the previous poll
+//! and reports a [`Change`] for every range that differs. The first poll of a range only records
+//! its contents.
+//!
+//! A [`SampleLog`] instead records the value of each of its series on every sample, with the time
+//! it was taken, and writes them out as CSV (or Parquet, with the `parquet` feature) for charting.
+
+use std::{
+    io::Write,
+    ops::Range,
+    sync::{atomic::{AtomicBool, Ordering}, mpsc::Sender},
+    time::{Duration, Instant},
+};
+
+use binread::Endian;
+
+use crate::{
+    expr::{Value, ValueType},
+    AtomicReversedWords, Primitive, ReversedWords, ReversedWordsError,
+};
+
+/// Something the watcher can read logical bytes from.
+pub trait WatchSource {
+    fn len(&self) -> u64;
+
+    /// Read the logical bytes starting at `addr`, returning how many were in range.
+    fn read_watched(&self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl WatchSource for ReversedWords<'_> {
+    fn len(&self) -> u64 {
+        ReversedWords::len(self)
+    }
+
+    fn read_watched(&self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.read_at(addr, buf)
+    }
+}
+
+impl WatchSource for AtomicReversedWords<'_> {
+    fn len(&self) -> u64 {
+        AtomicReversedWords::len(self)
+    }
+
+    fn read_watched(&self, addr: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.read_at(addr, buf))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WatchId(u64);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub id: WatchId,
+    /// Start of the watched range.
+    pub addr: u64,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+impl Change {
+    /// The old bytes decoded as a `T`, if the range is big enough to hold one.
+    pub fn old_value<T: Primitive>(&self, endian: Endian) -> Option<T> {
+        decode(&self.old, endian)
+    }
+
+    pub fn new_value<T: Primitive>(&self, endian: Endian) -> Option<T> {
+        decode(&self.new, endian)
+    }
+}
+
+pub(crate) fn decode<T: Primitive>(bytes: &[u8], endian: Endian) -> Option<T> {
+    let mut value = T::Bytes::default();
+    let len = value.as_ref().len();
+    value.as_mut().copy_from_slice(bytes.get(..len)?);
+    Some(T::from_bytes(value, endian))
+}
+
+struct Watch {
+    id: WatchId,
+    range: Range<u64>,
+    last: Option<Vec<u8>>,
+}
+
+#[derive(Default)]
+pub struct Watcher {
+    watches: Vec<Watch>,
+    next_id: u64,
+}
+
+impl Watcher {
+    pub fn new() -> Watcher {
+        Watcher::default()
+    }
+
+    pub fn watch(&mut self, range: Range<u64>) -> WatchId {
+        let id = WatchId(self.next_id);
+        self.next_id += 1;
+        self.watches.push(Watch { id, range, last: None });
+        id
+    }
+
+    /// Watch the `size_of::<T>()` bytes at `addr`.
+    pub fn watch_value<T: Primitive>(&mut self, addr: u64) -> WatchId {
+        let size = T::Bytes::default().as_ref().len() as u64;
+        self.watch(addr..addr + size)
+    }
+
+    /// Stop watching `id`, returning whether it was registered.
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        let count = self.watches.len();
+        self.watches.retain(|watch| watch.id != id);
+        self.watches.len() != count
+    }
+
+    pub fn range(&self, id: WatchId) -> Option<Range<u64>> {
+        self.watches.iter().find(|watch| watch.id == id).map(|watch| watch.range.clone())
+    }
+
+    /// Compare every watched range against its previous contents, calling `on_change` for each
+    /// one that differs.
+    pub fn poll_with<S: WatchSource + ?Sized>(&mut self, source: &S, mut on_change: impl FnMut(Change)) -> std::io::Result<()> {
+        for watch in &mut self.watches {
+            let mut current = vec![0u8; (watch.range.end.saturating_sub(watch.range.start)) as usize];
+            if source.read_watched(watch.range.start, &mut current)? < current.len() {
+                return Err(ReversedWordsError::RangeOutOfRange {
+                    start: watch.range.start,
+                    end: watch.range.end,
+                    len: source.len(),
+                }
+                .into());
+            }
+            match &mut watch.last {
+                Some(last) if *last != current => {
+                    let old = std::mem::replace(last, current.clone());
+                    on_change(Change { id: watch.id, addr: watch.range.start, old, new: current });
+                }
+                Some(_) => {}
+                None => watch.last = Some(current),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn poll<S: WatchSource + ?Sized>(&mut self, source: &S) -> std::io::Result<Vec<Change>> {
+        let mut changes = Vec::new();
+        self.poll_with(source, |change| changes.push(change))?;
+        Ok(changes)
+    }
+
+    /// Poll every `interval` until `stop` is set or the receiving end of `changes` hangs up.
+    pub fn run_every<S: WatchSource + ?Sized>(
+        &mut self,
+        source: &S,
+        interval: Duration,
+        changes: &Sender<Change>,
+        stop: &AtomicBool,
+    ) -> std::io::Result<()> {
+        let mut disconnected = false;
+        while !stop.load(Ordering::Relaxed) && !disconnected {
+            self.poll_with(source, |change| disconnected |= changes.send(change).is_err())?;
+            std::thread::sleep(interval);
+        }
+        Ok(())
+    }
+}
+
+/// One logged value: a name, where it lives and how to decode it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Series {
+    pub name: String,
+    pub addr: u64,
+    pub value_type: ValueType,
+    pub endian: Endian,
+}
+
+/// The values of every series at one point in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// Time since the log's first sample.
+    pub elapsed: Duration,
+    pub values: Vec<Value>,
+}
+
+pub(crate) fn decode_value(bytes: &[u8], value_type: ValueType, endian: Endian) -> Option<Value> {
+    Some(match value_type {
+        ValueType::U8 => Value::Unsigned(decode::<u8>(bytes, endian)? as u64),
+        ValueType::U16 => Value::Unsigned(decode::<u16>(bytes, endian)? as u64),
+        ValueType::U32 => Value::Unsigned(decode::<u32>(bytes, endian)? as u64),
+        ValueType::U64 => Value::Unsigned(decode::<u64>(bytes, endian)?),
+        ValueType::I8 => Value::Signed(decode::<i8>(bytes, endian)? as i64),
+        ValueType::I16 => Value::Signed(decode::<i16>(bytes, endian)? as i64),
+        ValueType::I32 => Value::Signed(decode::<i32>(bytes, endian)? as i64),
+        ValueType::I64 => Value::Signed(decode::<i64>(bytes, endian)?),
+        ValueType::F32 => Value::Float(decode::<f32>(bytes, endian)? as f64),
+        ValueType::F64 => Value::Float(decode::<f64>(bytes, endian)?),
+    })
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Values of selected addresses sampled over time.
+#[derive(Clone, Debug, Default)]
+pub struct SampleLog {
+    series: Vec<Series>,
+    samples: Vec<Sample>,
+    interval: Option<Duration>,
+    started: Option<Instant>,
+    last_sample: Option<Instant>,
+}
+
+impl SampleLog {
+    pub fn new() -> SampleLog {
+        SampleLog::default()
+    }
+
+    /// How often [`SampleLog::poll`] samples.
+    pub fn with_interval(mut self, interval: Duration) -> SampleLog {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Log the `value_type` at `addr` as the column `name`. Series can only be added before the
+    /// first sample, so every sample has a value for each.
+    pub fn track(&mut self, name: &str, addr: u64, value_type: ValueType, endian: Endian) -> Result<(), ReversedWordsError> {
+        if !self.samples.is_empty() {
+            return Err(ReversedWordsError::InvalidConfig { reason: format!("can't add series {name:?} after sampling") });
+        }
+        self.series.push(Series { name: name.to_string(), addr, value_type, endian });
+        Ok(())
+    }
+
+    pub fn series(&self) -> &[Series] {
+        &self.series
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.started = None;
+        self.last_sample = None;
+    }
+
+    /// Sample every series from `source`, timestamped with the wall time since the first sample.
+    pub fn sample<S: WatchSource + ?Sized>(&mut self, source: &S) -> std::io::Result<()> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(*self.started.get_or_insert(now));
+        self.sample_at(source, elapsed)?;
+        self.last_sample = Some(now);
+        Ok(())
+    }
+
+    /// Sample every series from `source` with a timestamp of the caller's, such as emulated time
+    /// from a frame counter.
+    pub fn sample_at<S: WatchSource + ?Sized>(&mut self, source: &S, elapsed: Duration) -> std::io::Result<()> {
+        let mut values = Vec::with_capacity(self.series.len());
+        for series in &self.series {
+            let mut bytes = [0u8; 8];
+            let bytes = &mut bytes[..series.value_type.size() as usize];
+            if source.read_watched(series.addr, bytes)? < bytes.len() {
+                return Err(ReversedWordsError::RangeOutOfRange {
+                    start: series.addr,
+                    end: series.addr.saturating_add(series.value_type.size()),
+                    len: source.len(),
+                }
+                .into());
+            }
+            values.push(decode_value(bytes, series.value_type, series.endian).expect("read the value's size"));
+        }
+        self.samples.push(Sample { elapsed, values });
+        Ok(())
+    }
+
+    /// Sample if the interval has passed since the last sample (or there is none yet), returning
+    /// whether it did. Without an interval this always samples.
+    pub fn poll<S: WatchSource + ?Sized>(&mut self, source: &S) -> std::io::Result<bool> {
+        let due = match (self.interval, self.last_sample) {
+            (Some(interval), Some(last)) => last.elapsed() >= interval,
+            _ => true,
+        };
+        if due {
+            self.sample(source)?;
+        }
+        Ok(due)
+    }
+
+    /// Write the samples as CSV: an `elapsed_us` column of microseconds, then one column per
+    /// series, with a header row of their names.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut header = String::from("elapsed_us");
+        for series in &self.series {
+            header.push(',');
+            header.push_str(&csv_field(&series.name));
+        }
+        writeln!(writer, "{header}")?;
+        for sample in &self.samples {
+            write!(writer, "{}", sample.elapsed.as_micros())?;
+            for value in &sample.values {
+                match value {
+                    Value::Unsigned(value) => write!(writer, ",{value}")?,
+                    Value::Signed(value) => write!(writer, ",{value}")?,
+                    Value::Float(value) => write!(writer, ",{value}")?,
+                }
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Write the samples as a Parquet file with one row group: a required `elapsed_us` `INT64`
+    /// column, then a required `INT64` (unsigned or signed) or `DOUBLE` column per series.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W: Write + Send>(&self, writer: W) -> std::io::Result<()> {
+        use std::sync::Arc;
+
+        use parquet::{
+            basic::{LogicalType, Repetition, Type as PhysicalType},
+            data_type::{DoubleType, Int64Type},
+            file::writer::SerializedFileWriter,
+            schema::types::Type,
+        };
+
+        let error = std::io::Error::other;
+        let column = |name: &str, physical, logical| {
+            Type::primitive_type_builder(name, physical)
+                .with_repetition(Repetition::REQUIRED)
+                .with_logical_type(logical)
+                .build()
+                .map(Arc::new)
+        };
+        let integer = |is_signed| Some(LogicalType::Integer { bit_width: 64, is_signed });
+        let mut fields = vec![column("elapsed_us", PhysicalType::INT64, integer(true)).map_err(error)?];
+        for series in &self.series {
+            let field = match series.value_type {
+                ValueType::F32 | ValueType::F64 => column(&series.name, PhysicalType::DOUBLE, None),
+                ValueType::I8 | ValueType::I16 | ValueType::I32 | ValueType::I64 => {
+                    column(&series.name, PhysicalType::INT64, integer(true))
+                }
+                _ => column(&series.name, PhysicalType::INT64, integer(false)),
+            };
+            fields.push(field.map_err(error)?);
+        }
+        let schema = Type::group_type_builder("samples").with_fields(fields).build().map_err(error)?;
+
+        let mut file = SerializedFileWriter::new(writer, Arc::new(schema), Default::default()).map_err(error)?;
+        let mut row_group = file.next_row_group().map_err(error)?;
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column().map_err(error)? {
+            if index == 0 {
+                let elapsed: Vec<i64> = self.samples.iter().map(|sample| sample.elapsed.as_micros() as i64).collect();
+                column.typed::<Int64Type>().write_batch(&elapsed, None, None).map_err(error)?;
+            } else if matches!(self.series[index - 1].value_type, ValueType::F32 | ValueType::F64) {
+                let values: Vec<f64> = self
+                    .samples
+                    .iter()
+                    .map(|sample| match sample.values[index - 1] {
+                        Value::Float(value) => value,
+                        _ => unreachable!("series decode to one kind"),
+                    })
+                    .collect();
+                column.typed::<DoubleType>().write_batch(&values, None, None).map_err(error)?;
+            } else {
+                // unsigned values keep their bits, the column's logical type says how to read them
+                let values: Vec<i64> = self
+                    .samples
+                    .iter()
+                    .map(|sample| match sample.values[index - 1] {
+                        Value::Unsigned(value) => value as i64,
+                        Value::Signed(value) => value,
+                        Value::Float(_) => unreachable!("series decode to one kind"),
+                    })
+                    .collect();
+                column.typed::<Int64Type>().write_batch(&values, None, None).map_err(error)?;
+            }
+            column.close().map_err(error)?;
+            index += 1;
+        }
+        row_group.close().map_err(error)?;
+        file.close().map_err(error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicU8, mpsc};
+
+    use crate::watch::*;
+
+    #[test]
+    fn poll_reports_changes_after_baseline() {
+        let mut data = vec![0u8; 8];
+        let mut watcher = Watcher::new();
+        let health = watcher.watch_value::<u16>(2);
+        let other = watcher.watch(4..8);
+        assert!(watcher.poll(&ReversedWords::new(&mut data)).unwrap().is_empty());
+
+        ReversedWords::new(&mut data).write_u16_at(2, 999, Endian::Big).unwrap();
+        let changes = watcher.poll(&ReversedWords::new(&mut data)).unwrap();
+        assert_eq!(1, changes.len());
+        assert_eq!(health, changes[0].id);
+        assert_eq!(Some(0), changes[0].old_value::<u16>(Endian::Big));
+        assert_eq!(Some(999), changes[0].new_value::<u16>(Endian::Big));
+        assert!(watcher.poll(&ReversedWords::new(&mut data)).unwrap().is_empty());
+
+        assert!(watcher.unwatch(other));
+        assert!(!watcher.unwatch(other));
+        watcher.watch(6..10);
+        assert!(watcher.poll(&ReversedWords::new(&mut data)).is_err());
+    }
+
+    #[test]
+    fn samples_to_csv() {
+        let mut data = vec![0u8; 8];
+        let mut log = SampleLog::new();
+        log.track("health", 2, ValueType::U16, Endian::Big).unwrap();
+        log.track("x, pos", 4, ValueType::F32, Endian::Big).unwrap();
+        for frame in 0..3u16 {
+            let mut words = ReversedWords::new(&mut data);
+            words.write_u16_at(2, 100 - frame, Endian::Big).unwrap();
+            words.write_f32_at(4, frame as f32 * 0.5, Endian::Big).unwrap();
+            log.sample_at(&words, Duration::from_micros(16_667) * frame as u32).unwrap();
+        }
+        assert_eq!(vec![Value::Unsigned(98), Value::Float(1.0)], log.samples()[2].values);
+        assert!(log.track("late", 0, ValueType::U8, Endian::Big).is_err());
+
+        let mut csv = Vec::new();
+        log.write_csv(&mut csv).unwrap();
+        assert_eq!("elapsed_us,health,\"x, pos\"\n0,100,0\n16667,99,0.5\n33334,98,1\n", String::from_utf8(csv).unwrap());
+
+        log.clear();
+        log.track("out of range", 7, ValueType::U16, Endian::Big).unwrap();
+        assert!(log.sample(&ReversedWords::new(&mut data)).is_err());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn samples_to_parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let mut data = vec![0u8; 8];
+        let mut log = SampleLog::new();
+        log.track("health", 2, ValueType::U16, Endian::Big).unwrap();
+        log.track("speed", 4, ValueType::F32, Endian::Big).unwrap();
+        for _ in 0..5 {
+            log.sample(&ReversedWords::new(&mut data)).unwrap();
+        }
+        let path = std::env::temp_dir().join(format!("reversed-samples-{}.parquet", std::process::id()));
+        log.write_parquet(std::fs::File::create(&path).unwrap()).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(5, metadata.num_rows());
+        assert_eq!(3, metadata.schema_descr().num_columns());
+        assert_eq!("speed", metadata.schema_descr().column(2).name());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn run_every_sends_changes_from_another_thread() {
+        let ram: Vec<AtomicU8> = (0..8).map(|_| AtomicU8::new(0)).collect();
+        let words = AtomicReversedWords::new(&ram);
+        let stop = AtomicBool::new(false);
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = Watcher::new();
+        watcher.watch_value::<u32>(4);
+        watcher.poll(&words).unwrap();
+        std::thread::scope(|scope| {
+            scope.spawn(|| watcher.run_every(&words, Duration::from_millis(1), &sender, &stop).unwrap());
+            words.write_value_at(4, 0xDEADBEEFu32, Endian::Big).unwrap();
+            let change = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            stop.store(true, Ordering::Relaxed);
+            assert_eq!(Some(0xDEADBEEF), change.new_value::<u32>(Endian::Big));
+        });
+    }
+}
@@ -0,0 +1,320 @@
+//! WebSocket push of watch changes for the bridge server.
+//!
+//! `GET /events?watch=0x1000:4,0x2000:2&interval_ms=16` with the usual `Upgrade: websocket`
+//! headers opens a connection that the server polls the listed `addr:len` ranges on, sending one
+//! text message per change:
+//!
+//! ```json
+//! {"type": "change", "id": 0, "addr": 4096, "old": "00000000", "new": "0000002a"}
+//! ```
+//!
+//! `id` is the range's position in the `watch` list, and the bytes are lowercase hex in logical
+//! order. The first poll only records a baseline. Pings are answered, and a close from the client
+//! (or the server being stopped) ends the connection with a close frame.
+
+use std::{
+    convert::TryInto,
+    io::{ErrorKind, Read, Write},
+    net::TcpStream,
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use crate::{
+    server::{json_string, parse_number, respond, to_hex, Request},
+    watch::Watcher,
+    AtomicReversedWords,
+};
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_RANGES: usize = 4096;
+const MAX_CONTROL_PAYLOAD: usize = 125;
+// clients only send control frames, so anything buffering past this is hung up on
+const MAX_INCOMING: usize = 64 << 10;
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(16);
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+    let mut digest = [0u8; 20];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| bits | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key.trim(), HANDSHAKE_GUID).as_bytes()))
+}
+
+fn write_frame(stream: &mut TcpStream, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+// a complete frame at the front of `buf`: (opcode, unmasked payload, frame length)
+fn parse_frame(buf: &[u8]) -> Option<(u8, Vec<u8>, usize)> {
+    let opcode = buf.first()? & 0x0F;
+    let masked = buf.get(1)? & 0x80 != 0;
+    let (len, mut offset) = match buf[1] & 0x7F {
+        126 => (u16::from_be_bytes([*buf.get(2)?, *buf.get(3)?]) as usize, 4),
+        127 => (u64::from_be_bytes(buf.get(2..10)?.try_into().ok()?) as usize, 10),
+        len => (len as usize, 2),
+    };
+    let mask = if masked {
+        let mask = buf.get(offset..offset + 4)?.to_vec();
+        offset += 4;
+        Some(mask)
+    } else {
+        None
+    };
+    let mut payload = buf.get(offset..offset.checked_add(len)?)?.to_vec();
+    if let Some(mask) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Some((opcode, payload, offset + len))
+}
+
+fn parse_ranges(list: &str) -> Result<Vec<Range<u64>>, String> {
+    let ranges: Vec<Range<u64>> = list
+        .split(',')
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (addr, len) = item.split_once(':').ok_or_else(|| format!("watch entry {:?} is not addr:len", item))?;
+            let addr = parse_number(addr).ok_or_else(|| format!("invalid address {:?}", addr))?;
+            let len = parse_number(len).ok_or_else(|| format!("invalid length {:?}", len))?;
+            let end = addr.checked_add(len).ok_or_else(|| format!("{:?} overflows", item))?;
+            Ok(addr..end)
+        })
+        .collect::<Result<_, String>>()?;
+    if ranges.is_empty() || ranges.len() > MAX_RANGES {
+        return Err(format!("watch needs between 1 and {} ranges", MAX_RANGES));
+    }
+    Ok(ranges)
+}
+
+/// Upgrade `stream` to a WebSocket and push changes until either side stops.
+pub(crate) fn serve_events(words: AtomicReversedWords, request: &Request, mut stream: TcpStream, stop: &AtomicBool) -> std::io::Result<()> {
+    let key = match request.header("sec-websocket-key") {
+        Some(key) if request.header("upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")) => key,
+        _ => return respond(&mut stream, "426 Upgrade Required", "{\"error\": \"expected a websocket upgrade\"}"),
+    };
+    let ranges = match parse_ranges(request.query.iter().find(|(key, _)| key == "watch").map_or("", |(_, value)| value)) {
+        Ok(ranges) if ranges.iter().all(|range| range.end <= words.len()) => ranges,
+        Ok(_) => return respond(&mut stream, "400 Bad Request", "{\"error\": \"watched range is outside of the buffer\"}"),
+        Err(message) => return respond(&mut stream, "400 Bad Request", &format!("{{\"error\": {}}}", json_string(&message))),
+    };
+    let interval = match request.number("interval_ms") {
+        Ok(interval) => interval.map_or(DEFAULT_INTERVAL, Duration::from_millis).max(Duration::from_millis(1)),
+        Err(message) => return respond(&mut stream, "400 Bad Request", &format!("{{\"error\": {}}}", json_string(&message))),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )?;
+
+    let mut watcher = Watcher::new();
+    let ids: Vec<_> = ranges.into_iter().map(|range| watcher.watch(range)).collect();
+    watcher.poll(&words)?;
+    stream.set_nonblocking(true)?;
+    let mut incoming = Vec::new();
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            stream.set_nonblocking(false)?;
+            return write_frame(&mut stream, OPCODE_CLOSE, &[]);
+        }
+        let mut buf = [0u8; 256];
+        match stream.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => incoming.extend_from_slice(&buf[..n]),
+            Err(error) if error.kind() == ErrorKind::WouldBlock && incoming.len() > MAX_INCOMING => {
+                stream.set_nonblocking(false)?;
+                return write_frame(&mut stream, OPCODE_CLOSE, &[]);
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+            Err(error) => return Err(error),
+        }
+        while let Some((opcode, payload, len)) = parse_frame(&incoming) {
+            incoming.drain(..len);
+            stream.set_nonblocking(false)?;
+            match opcode {
+                OPCODE_CLOSE => return write_frame(&mut stream, OPCODE_CLOSE, &payload[..payload.len().min(MAX_CONTROL_PAYLOAD)]),
+                OPCODE_PING => write_frame(&mut stream, OPCODE_PONG, &payload[..payload.len().min(MAX_CONTROL_PAYLOAD)])?,
+                _ => {}
+            }
+            stream.set_nonblocking(true)?;
+        }
+        let mut messages = Vec::new();
+        watcher.poll_with(&words, |change| {
+            let id = ids.iter().position(|id| *id == change.id).expect("watched here");
+            messages.push(format!(
+                "{{\"type\": \"change\", \"id\": {}, \"addr\": {}, \"old\": \"{}\", \"new\": \"{}\"}}",
+                id,
+                change.addr,
+                to_hex(&change.old),
+                to_hex(&change.new)
+            ));
+        })?;
+        if !messages.is_empty() {
+            stream.set_nonblocking(false)?;
+            for message in messages {
+                write_frame(&mut stream, OPCODE_TEXT, message.as_bytes())?;
+            }
+            stream.set_nonblocking(true)?;
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        sync::atomic::AtomicU8,
+    };
+
+    use crate::{server::BridgeServer, websocket::*, Endian};
+
+    fn read_frame(reader: &mut BufReader<TcpStream>) -> (u8, Vec<u8>) {
+        let mut head = [0u8; 2];
+        reader.read_exact(&mut head).unwrap();
+        assert_eq!(0, head[1] & 0x80, "server frames are unmasked");
+        let len = match head[1] {
+            126 => {
+                let mut len = [0u8; 2];
+                reader.read_exact(&mut len).unwrap();
+                u16::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut payload = vec![0u8; len];
+        reader.read_exact(&mut payload).unwrap();
+        (head[0] & 0x0F, payload)
+    }
+
+    #[test]
+    fn handshake_primitives() {
+        assert_eq!("a9993e364706816aba3e25717850c26c9cd0d89d", to_hex(&sha1(b"abc")));
+        assert_eq!("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=", accept_key("dGhlIHNhbXBsZSBub25jZQ=="));
+        assert_eq!(["", "Zg==", "Zm8=", "Zm9v"], [base64(b""), base64(b"f"), base64(b"fo"), base64(b"foo")]);
+        assert_eq!(Some((OPCODE_TEXT, b"Hi".to_vec(), 8)), parse_frame(&[0x81, 0x82, 1, 2, 3, 4, b'H' ^ 1, b'i' ^ 2]));
+        assert_eq!(None, parse_frame(&[0x81, 0x85, 1, 2]));
+        assert!(parse_ranges("0x10:4,32:2").is_ok());
+        assert!(parse_ranges("0x10").is_err());
+        assert!(parse_ranges("").is_err());
+    }
+
+    #[test]
+    fn pushes_changes_to_websocket_clients() {
+        let ram: Vec<AtomicU8> = (0..16).map(|_| AtomicU8::new(0)).collect();
+        let words = AtomicReversedWords::new(&ram);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stop = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            scope.spawn(|| BridgeServer::new(words).serve(&listener, &stop).unwrap());
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(
+                stream,
+                "GET /events?watch=0:2,8:4&interval_ms=1 HTTP/1.1\r\nHost: test\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                 Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+            )
+            .unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            while !head.ends_with("\r\n\r\n") {
+                reader.read_line(&mut head).unwrap();
+            }
+            assert!(head.starts_with("HTTP/1.1 101"), "{}", head);
+            assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+            std::thread::sleep(Duration::from_millis(20));
+            words.write_value_at(8, 42u32, Endian::Big).unwrap();
+            let (opcode, payload) = read_frame(&mut reader);
+            assert_eq!(OPCODE_TEXT, opcode);
+            assert_eq!(
+                "{\"type\": \"change\", \"id\": 1, \"addr\": 8, \"old\": \"00000000\", \"new\": \"0000002a\"}",
+                String::from_utf8(payload).unwrap()
+            );
+
+            stream.write_all(&[0x89, 0x81, 0, 0, 0, 0, 7]).unwrap();
+            assert_eq!((OPCODE_PONG, vec![7]), read_frame(&mut reader));
+            stream.write_all(&[0x88, 0x80, 1, 2, 3, 4]).unwrap();
+            assert_eq!(OPCODE_CLOSE, read_frame(&mut reader).0);
+
+            let (status, _) = crate::server::tests::get(addr, "/events?watch=0:2");
+            assert!(status.starts_with("HTTP/1.1 426"));
+            stop.store(true, Ordering::Relaxed);
+        });
+    }
+}
@@ -0,0 +1,266 @@
+//! Naming the word size by its integer type, and streaming whole values.
+
+use std::{
+    io::{Seek, SeekFrom},
+    ops::{Deref, DerefMut},
+};
+
+use binread::Endian;
+
+use crate::{transform_byte, Primitive, ReversedWords, ReversedWordsError};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// An unsigned integer type usable as the word of a view: `u8`, `u16`, `u32`, `u64` or `u128`.
+///
+/// `ReversedWords::with_word::<u16>(buf)` is `ReversedWords::new_with_word_size(buf, 2)`, and
+/// [`ReversedWords::read_word`] decodes a whole word as that integer straight from storage.
+pub trait Word: Primitive + sealed::Sealed {
+    const SIZE: u8;
+}
+
+macro_rules! impl_word {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl Word for $ty {
+                const SIZE: u8 = std::mem::size_of::<$ty>() as u8;
+            }
+        )*
+    };
+}
+
+impl_word!(u8, u16, u32, u64, u128);
+
+impl<'a> ReversedWords<'a> {
+    pub fn with_word<W: Word>(ram: &'a mut [u8]) -> ReversedWords<'a> {
+        ReversedWords::new_with_word_size(ram, W::SIZE)
+    }
+
+    // storage range of word `index`, which must be a whole `W`
+    fn word_range<W: Word>(&self, index: u64) -> Result<std::ops::Range<usize>, ReversedWordsError> {
+        if W::SIZE != self.word_size {
+            return Err(ReversedWordsError::InvalidWordSize);
+        }
+        let start = index.checked_mul(W::SIZE as u64).filter(|start| start.checked_add(W::SIZE as u64).is_some_and(|end| end <= self.len));
+        match start {
+            Some(start) => Ok(start as usize..start as usize + W::SIZE as usize),
+            None => Err(ReversedWordsError::AddressOutOfRange { addr: index.saturating_mul(W::SIZE as u64), len: self.len }),
+        }
+    }
+
+    /// The value of word `index`, with its logical bytes read as big endian. Doesn't use or move
+    /// the cursor. Fails with [`ReversedWordsError::InvalidWordSize`] if `W` isn't the view's word.
+    pub fn read_word<W: Word>(&self, index: u64) -> std::io::Result<W> {
+        let range = self.word_range::<W>(index)?;
+        let mut bytes = W::Bytes::default();
+        // reversing a big endian word's bytes leaves it stored little endian
+        for (byte, index) in bytes.as_mut().iter_mut().zip(range) {
+            *byte = self.load(index);
+        }
+        Ok(W::from_bytes(bytes, Endian::Little))
+    }
+
+    /// Set word `index` to `value`, the counterpart of [`ReversedWords::read_word`].
+    pub fn write_word<W: Word>(&mut self, index: u64, value: W) -> std::io::Result<()> {
+        let range = self.word_range::<W>(index)?;
+        for (index, byte) in range.zip(value.to_bytes(Endian::Little).as_ref()) {
+            self.store(index, *byte);
+        }
+        Ok(())
+    }
+}
+
+/// A word of a view decoded as a `W` that can be changed in place through `DerefMut`. The new
+/// value is swapped back into storage when the guard is dropped.
+pub struct WordGuard<'g, W: Word> {
+    storage: &'g mut [u8],
+    transform: (bool, bool),
+    index: u64,
+    value: W,
+}
+
+impl<'g, W: Word> WordGuard<'g, W> {
+    fn new(storage: &'g mut [u8], transform: (bool, bool), index: u64) -> WordGuard<'g, W> {
+        let mut bytes = W::Bytes::default();
+        for (byte, stored) in bytes.as_mut().iter_mut().zip(storage.iter()) {
+            *byte = transform_byte(*stored, transform);
+        }
+        WordGuard { storage, transform, index, value: W::from_bytes(bytes, Endian::Little) }
+    }
+
+    /// Which word of the view this is.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+}
+
+impl<W: Word> Deref for WordGuard<'_, W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.value
+    }
+}
+
+impl<W: Word> DerefMut for WordGuard<'_, W> {
+    fn deref_mut(&mut self) -> &mut W {
+        &mut self.value
+    }
+}
+
+impl<W: Word> Drop for WordGuard<'_, W> {
+    fn drop(&mut self) {
+        for (stored, byte) in self.storage.iter_mut().zip(self.value.to_bytes(Endian::Little).as_ref()) {
+            *stored = transform_byte(*byte, self.transform);
+        }
+    }
+}
+
+impl<'a> ReversedWords<'a> {
+    /// Word `index` as a `W` to modify in place, like [`ReversedWords::read_word`] followed by
+    /// [`ReversedWords::write_word`] when the guard is dropped.
+    pub fn word_mut<W: Word>(&mut self, index: u64) -> std::io::Result<WordGuard<'_, W>> {
+        let range = self.word_range::<W>(index)?;
+        let transform = self.byte_transform();
+        Ok(WordGuard::new(&mut self.cursor.get_mut()[range], transform, index))
+    }
+
+    /// A guard for every whole word of the view, in order.
+    pub fn words_mut<W: Word>(&mut self) -> std::io::Result<impl Iterator<Item = WordGuard<'_, W>>> {
+        if W::SIZE != self.word_size {
+            return Err(ReversedWordsError::InvalidWordSize.into());
+        }
+        let size = W::SIZE as usize;
+        let whole = (self.len / size as u64) as usize * size;
+        let transform = self.byte_transform();
+        let storage = &mut self.cursor.get_mut()[..whole];
+        Ok(storage.chunks_exact_mut(size).enumerate().map(move |(index, word)| WordGuard::new(word, transform, index as u64)))
+    }
+}
+
+/// Reads and writes whole values at the cursor of a view, see [`ReversedWords::word_stream`].
+///
+/// The value type is chosen per call, and its size is the unit for
+/// [`WordStream::word_position`] and [`WordStream::seek_word`].
+pub struct WordStream<'w, 'a> {
+    words: &'w mut ReversedWords<'a>,
+    endian: Endian,
+}
+
+impl<'a> ReversedWords<'a> {
+    /// Stream values in `endian` order from the cursor.
+    pub fn word_stream(&mut self, endian: Endian) -> WordStream<'_, 'a> {
+        WordStream { words: self, endian }
+    }
+}
+
+impl<'a> WordStream<'_, 'a> {
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    pub fn words(&mut self) -> &mut ReversedWords<'a> {
+        self.words
+    }
+
+    /// The value at the cursor, moving past it. `None` once less than a whole `T` is left, or if
+    /// the view refuses the read (for example a misaligned cursor under
+    /// [`MisalignmentPolicy::Reject`](crate::MisalignmentPolicy::Reject)).
+    pub fn next_word<T: Primitive>(&mut self) -> Option<T> {
+        if self.words.remaining() < std::mem::size_of::<T>() as u64 {
+            return None;
+        }
+        self.words.read_value_at(self.words.position(), self.endian).ok()
+    }
+
+    /// Write `value` at the cursor and move past it.
+    pub fn write_word<T: Primitive>(&mut self, value: T) -> std::io::Result<()> {
+        self.words.write_value_at(self.words.position(), value, self.endian)
+    }
+
+    /// The cursor position in `T`s, rounded down.
+    pub fn word_position<T: Primitive>(&self) -> u64 {
+        self.words.position() / std::mem::size_of::<T>() as u64
+    }
+
+    /// Move the cursor to the `index`th `T`.
+    pub fn seek_word<T: Primitive>(&mut self, index: u64) -> std::io::Result<u64> {
+        let addr = index.saturating_mul(std::mem::size_of::<T>() as u64);
+        self.words.seek(SeekFrom::Start(addr))?;
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn typed_words() {
+        let mut data: Vec<u8> = (0..8).collect();
+        let mut words = ReversedWords::with_word::<u16>(&mut data);
+        assert_eq!(2, words.word_size());
+        assert_eq!(0x0302, words.read_word::<u16>(1).unwrap());
+        assert_eq!(0x0302, words.read_u16_at(2, Endian::Big).unwrap());
+        words.write_word::<u16>(3, 0xBEEF).unwrap();
+        assert_eq!(0xBEEF, words.read_u16_at(6, Endian::Big).unwrap());
+        assert!(words.read_word::<u16>(4).is_err());
+        let error = words.read_word::<u16>(u64::MAX / 2).unwrap_err();
+        assert_eq!(
+            Some(&ReversedWordsError::AddressOutOfRange { addr: u64::MAX - 1, len: 8 }),
+            ReversedWordsError::from_io(&error)
+        );
+        let error = words.read_word::<u32>(0).unwrap_err();
+        assert_eq!(Some(&ReversedWordsError::InvalidWordSize), ReversedWordsError::from_io(&error));
+    }
+
+    #[test]
+    fn word_guards_write_back_on_drop() {
+        let mut data: Vec<u8> = (0..10).collect();
+        let mut words = ReversedWords::with_word::<u32>(&mut data).with_nibble_swap(true);
+        {
+            let mut word = words.word_mut::<u32>(1).unwrap();
+            assert_eq!(0x7060_5040, *word);
+            *word += 1;
+            assert_eq!(1, word.index());
+        }
+        assert_eq!(0x7060_5041, words.read_u32_at(4, Endian::Big).unwrap());
+        assert!(words.word_mut::<u32>(2).is_err());
+        assert!(words.word_mut::<u16>(0).is_err());
+
+        for mut word in words.words_mut::<u32>().unwrap() {
+            *word = word.rotate_left(8);
+        }
+        assert_eq!(0x2010_0030, words.read_u32_at(0, Endian::Big).unwrap());
+        assert_eq!(0x6050_4170, words.read_u32_at(4, Endian::Big).unwrap());
+        assert_eq!(2, words.words_mut::<u32>().unwrap().count());
+        // the partial word is left alone
+        assert_eq!([8, 9], data[8..]);
+        assert_eq!(0x07, data[4]);
+    }
+
+    #[test]
+    fn word_stream() {
+        let mut data = vec![0u8; 10];
+        let mut words = ReversedWords::new(&mut data);
+        let mut stream = words.word_stream(Endian::Big);
+        stream.write_word(0x0102_0304u32).unwrap();
+        stream.write_word(0xBEEFu16).unwrap();
+        assert_eq!(3, stream.word_position::<u16>());
+        stream.seek_word::<u32>(0).unwrap();
+        assert_eq!(Some(0x0102_0304u32), stream.next_word());
+        assert_eq!(Some(0xBEEFu16), stream.next_word());
+        assert_eq!(None, stream.next_word::<u32>());
+        stream.set_endian(Endian::Little);
+        stream.seek_word::<u16>(2).unwrap();
+        assert_eq!(Some(0xEFBEu16), stream.next_word());
+    }
+}
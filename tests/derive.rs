@@ -0,0 +1,64 @@
+#![cfg(feature = "derive")]
+
+use reversed_word_byte_rw::{Endian, ReversedWords, ReversedWordsError, SwappedLayout};
+
+#[derive(Debug, PartialEq, SwappedLayout)]
+struct Player {
+    #[swapped(offset = 0x4)]
+    health: u32,
+    #[swapped(offset = 0x9, endian = "little")]
+    score: u16,
+    #[swapped(offset = 0xb)]
+    lives: i8,
+}
+
+#[test]
+fn field_accessors_use_offsets() {
+    let mut data = vec![0u8; 16];
+    let mut ram = ReversedWords::new(&mut data);
+    Player::set_health(&mut ram, 0, 0x11223344).unwrap();
+    assert_eq!(0x11223344, Player::health(&mut ram, 0).unwrap());
+    assert_eq!(0x11223344, ram.read_u32_at(4, reversed_word_byte_rw::Endian::Big).unwrap());
+
+    Player::set_score(&mut ram, 0, 0xAABB).unwrap();
+    assert_eq!(0xAABB, ram.read_u16_at(9, reversed_word_byte_rw::Endian::Little).unwrap());
+}
+
+#[test]
+fn load_and_store_with_base_address() {
+    let player = Player { health: 100, score: 9001, lives: -1 };
+    let mut data = vec![0u8; 32];
+    let mut ram = ReversedWords::new(&mut data);
+    player.store(&mut ram, 0x10).unwrap();
+    assert_eq!(player, Player::load(&mut ram, 0x10).unwrap());
+    assert_eq!(0, Player::health(&mut ram, 0).unwrap());
+}
+
+#[derive(Debug, PartialEq, SwappedLayout)]
+#[swapped(endian = "big")]
+struct Fixed {
+    #[swapped(offset = 0)]
+    value: u16,
+}
+
+#[test]
+fn fields_follow_the_view_endianness() {
+    let mut data = vec![0u8; 8];
+    let mut ram = ReversedWords::new(&mut data).with_endian(Endian::Little);
+    Player::set_health(&mut ram, 0, 0x11223344).unwrap();
+    assert_eq!(0x11223344, ram.read_u32_at(4, Endian::Little).unwrap());
+    Fixed { value: 0xAABB }.store(&mut ram, 0).unwrap();
+    assert_eq!(0xAABB, ram.read_u16_at(0, Endian::Big).unwrap());
+}
+
+#[test]
+fn overflowing_field_addresses_are_an_error() {
+    let mut data = vec![0u8; 16];
+    let mut ram = ReversedWords::new(&mut data);
+    let error = Player::health(&mut ram, u64::MAX - 1).unwrap_err();
+    assert_eq!(
+        Some(&ReversedWordsError::AddressOutOfRange { addr: u64::MAX, len: 16 }),
+        ReversedWordsError::from_io(&error)
+    );
+    assert!(Player::set_health(&mut ram, u64::MAX, 1).is_err());
+}
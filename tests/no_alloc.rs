@@ -0,0 +1,75 @@
+//! The core read, write and seek paths never touch the heap, see "Allocation-free core" in the
+//! README.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+use reversed_word_byte_rw::{transfer, Endian, ReversedWords};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// how many allocations `f` makes on this thread
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn core_paths_do_not_allocate() {
+    let mut data = [0u8; 64];
+    let mut other = [0u8; 64];
+    let mut buf = [0u8; 24];
+    let count = allocations(|| {
+        for word_size in [1, 2, 4, 8, 3] {
+            let mut words = ReversedWords::new_with_word_size(&mut data, word_size).with_nibble_swap(word_size == 3).with_endian(Endian::Little);
+            words.seek(SeekFrom::Start(5)).unwrap();
+            words.write_all(&[1, 2, 3, 4, 5, 6, 7]).unwrap();
+            words.seek(SeekFrom::Current(-7)).unwrap();
+            words.read_exact(&mut buf[..7]).unwrap();
+            assert_eq!([1, 2, 3, 4, 5, 6, 7], buf[..7]);
+            words.write_at(30, &buf[..20]).unwrap();
+            words.read_at(29, &mut buf).unwrap();
+            words.set_byte(1, 0xAA).unwrap();
+            assert_eq!(0xAA, words.get_byte(1).unwrap());
+            words.write_u32_at(9, 0x1234_5678, Endian::Big).unwrap();
+            assert_eq!(0x1234_5678, words.read_u32_at(9, Endian::Big).unwrap());
+            words.write_value(40, 1.5f32).unwrap();
+            assert_eq!(1.5, words.read_value::<f32>(40).unwrap());
+            words.write_pod(44, [7u16; 3], Endian::Big).unwrap();
+            assert_eq!([7u16; 3], words.read_pod::<[u16; 3]>(44, Endian::Big).unwrap());
+            assert_eq!([7, 0], words.read_array::<2>(45).unwrap());
+            words.fill(50..54, 0xEE).unwrap();
+            words.copy_within(50..54, 20).unwrap();
+            let mut target = ReversedWords::new(&mut other);
+            transfer(&words, 0..48, &mut target, 8).unwrap();
+        }
+    });
+    assert_eq!(0, count);
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "cli")]
+
+use std::{fs, path::PathBuf, process::Command};
+
+fn revwords(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_revwords")).args(args).output().unwrap();
+    (output.status.success(), String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("revwords-{}-{}", std::process::id(), name));
+    fs::write(&path, data).unwrap();
+    path
+}
+
+#[test]
+fn convert_between_word_sizes() {
+    let input = temp_file("convert-in", &[0, 1, 2, 3, 4, 5, 6, 7]);
+    let output = input.with_extension("out");
+    let (ok, _, stderr) = revwords(&["convert", input.to_str().unwrap(), output.to_str().unwrap(), "--from", "4", "--to", "2"]);
+    assert!(ok, "{}", stderr);
+    assert_eq!(vec![2, 3, 0, 1, 6, 7, 4, 5], fs::read(&output).unwrap());
+
+    // a partial trailing word can't be converted, and nothing is written
+    let partial = temp_file("convert-partial", &[0, 1, 2, 3, 4, 5]);
+    let output = partial.with_extension("out");
+    let (ok, _, stderr) = revwords(&["convert", partial.to_str().unwrap(), output.to_str().unwrap()]);
+    assert!(!ok);
+    assert!(stderr.contains("not a whole number of 4 byte words"), "{}", stderr);
+    assert!(!output.exists());
+    for path in [input.clone(), input.with_extension("out"), partial] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[test]
+fn search_and_patch() {
+    let input = temp_file("patch", &[0u8; 8]);
+    let path = input.to_str().unwrap();
+    let (ok, _, stderr) = revwords(&["patch", path, "--at", "0x3", "--hex", "DE AD"]);
+    assert!(ok, "{}", stderr);
+    assert_eq!(vec![0xDE, 0, 0, 0, 0, 0, 0, 0xAD], fs::read(&input).unwrap());
+    let (ok, stdout, _) = revwords(&["search", path, "--u16", "0xDEAD"]);
+    assert!(ok);
+    assert_eq!("0x00000003\n", stdout);
+
+    let (ok, _, stderr) = revwords(&["patch", path, "--at", "7", "--hex", "01 02"]);
+    assert!(!ok);
+    assert!(stderr.contains("runs past the end"), "{}", stderr);
+    // non-ASCII input is an error, not a panic
+    let (ok, _, stderr) = revwords(&["search", path, "--hex", "DEé"]);
+    assert!(!ok);
+    assert!(stderr.starts_with("revwords: "), "{}", stderr);
+    let _ = fs::remove_file(input);
+}